@@ -0,0 +1,101 @@
+//! Criterion benchmarks for the audio primitives.
+//!
+//! Run with `cargo bench --bench audio`. Inputs are one second of a
+//! synthetic voice-band signal at 8kHz (the VAD gets ten seconds of speech
+//! and pauses), so throughput reads as samples per second of audio.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion, Throughput};
+use voice_echo::pipeline::audio::{self, BandpassFilter};
+use voice_echo::pipeline::frames::FRAME_BYTES;
+use voice_echo::pipeline::vad::VoiceActivityDetector;
+
+const SAMPLE_RATE: u32 = 8000;
+
+/// `secs` of a 200Hz + 1.1kHz mix with a little noise.
+fn voice_band(secs: u32) -> Vec<i16> {
+    let mut noise: u32 = 0x2545_f491;
+    (0..SAMPLE_RATE * secs)
+        .map(|n| {
+            noise ^= noise << 13;
+            noise ^= noise >> 17;
+            noise ^= noise << 5;
+            let t = n as f64 / SAMPLE_RATE as f64;
+            let tone = (2.0 * std::f64::consts::PI * 200.0 * t).sin() * 6000.0
+                + (2.0 * std::f64::consts::PI * 1100.0 * t).sin() * 3000.0;
+            (tone + (noise % 512) as f64 - 256.0) as i16
+        })
+        .collect()
+}
+
+fn mulaw(c: &mut Criterion) {
+    let pcm = voice_band(1);
+    let encoded = audio::encode_mulaw(&pcm);
+
+    let mut group = c.benchmark_group("mulaw");
+    group.throughput(Throughput::Elements(pcm.len() as u64));
+    group.bench_function("encode", |b| {
+        b.iter(|| audio::encode_mulaw(black_box(&pcm)))
+    });
+    group.bench_function("decode", |b| {
+        b.iter(|| audio::decode_mulaw(black_box(&encoded)))
+    });
+    group.finish();
+}
+
+fn dsp(c: &mut Criterion) {
+    let pcm = voice_band(1);
+
+    let mut group = c.benchmark_group("dsp");
+    group.throughput(Throughput::Elements(pcm.len() as u64));
+    group.bench_function("bandpass", |b| {
+        let mut filter = BandpassFilter::new(300.0, 3400.0, SAMPLE_RATE as f64);
+        b.iter(|| filter.filter(black_box(&pcm)))
+    });
+    group.bench_function("resample_linear 8k->16k", |b| {
+        b.iter(|| audio::resample_linear(black_box(&pcm), SAMPLE_RATE, 16_000))
+    });
+    group.bench_function("resample_linear 48k->8k", |b| {
+        let pcm_48k = audio::resample_linear(&pcm, SAMPLE_RATE, 48_000);
+        b.iter(|| audio::resample_linear(black_box(&pcm_48k), 48_000, SAMPLE_RATE))
+    });
+    group.bench_function("rms_energy", |b| {
+        b.iter(|| audio::rms_energy(black_box(&pcm)))
+    });
+    group.bench_function("pcm_to_wav", |b| {
+        b.iter(|| audio::pcm_to_wav(black_box(&pcm)))
+    });
+    group.finish();
+}
+
+fn vad(c: &mut Criterion) {
+    // Alternate a second of speech with a second of silence
+    let speech = audio::encode_mulaw(&voice_band(1));
+    let silence = audio::encode_mulaw(&vec![0; SAMPLE_RATE as usize]);
+    let stream: Vec<u8> = (0..5)
+        .flat_map(|_| speech.iter().chain(&silence).copied())
+        .collect();
+
+    let mut group = c.benchmark_group("vad");
+    group.throughput(Throughput::Elements(stream.len() as u64));
+    for (name, adaptive) in [("feed", false), ("feed adaptive", true)] {
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                let mut vad = VoiceActivityDetector::new(300, 500);
+                if adaptive {
+                    vad = vad.with_adaptive(3.0, 0.995);
+                }
+                let mut utterances = 0;
+                for chunk in stream.chunks(FRAME_BYTES) {
+                    if vad.feed(black_box(chunk)).is_some() {
+                        utterances += 1;
+                    }
+                }
+                utterances
+            })
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, mulaw, dsp, vad);
+criterion_main!(benches);
@@ -0,0 +1,131 @@
+//! Media frame encoding under concurrent calls.
+//!
+//! Encodes ten-second responses for 20 concurrent calls, draining each
+//! call's channel as the stream handler would, with the old per-frame
+//! `serde_json::json!` encoding and with `FrameEncoder`. Reports wall time
+//! and heap allocations per frame.
+//!
+//! Run with `cargo bench --bench send_audio`.
+
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{Duration, Instant};
+
+use axum::extract::ws::Message;
+use base64::Engine;
+use tokio::sync::mpsc;
+use voice_echo::pipeline::frames::{FrameEncoder, FRAME_BYTES};
+
+struct CountingAlloc;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for CountingAlloc {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+}
+
+#[global_allocator]
+static GLOBAL: CountingAlloc = CountingAlloc;
+
+const CALLS: usize = 20;
+const RESPONSES_PER_CALL: usize = 50;
+/// Ten seconds of mu-law.
+const RESPONSE_BYTES: usize = 80_000;
+
+#[derive(Clone, Copy)]
+enum Encoding {
+    Json,
+    FrameEncoder,
+}
+
+async fn send_response(
+    encoding: Encoding,
+    stream_sid: &str,
+    audio: &[u8],
+    tx: &mpsc::Sender<Message>,
+) {
+    match encoding {
+        Encoding::Json => {
+            for chunk in audio.chunks(FRAME_BYTES) {
+                let b64 = base64::engine::general_purpose::STANDARD.encode(chunk);
+                let msg = serde_json::json!({
+                    "event": "media",
+                    "streamSid": stream_sid,
+                    "media": { "payload": b64 }
+                });
+                tx.send(Message::Text(msg.to_string().into()))
+                    .await
+                    .unwrap();
+            }
+        }
+        Encoding::FrameEncoder => {
+            let frames = FrameEncoder::twilio(stream_sid);
+            for chunk in audio.chunks(FRAME_BYTES) {
+                tx.send(frames.frame(chunk)).await.unwrap();
+            }
+        }
+    }
+}
+
+async fn run(encoding: Encoding) -> (Duration, usize, usize) {
+    let audio = vec![0x7f_u8; RESPONSE_BYTES];
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let started = Instant::now();
+
+    let mut calls = Vec::new();
+    for call in 0..CALLS {
+        let audio = audio.clone();
+        calls.push(tokio::spawn(async move {
+            let (tx, mut rx) = mpsc::channel::<Message>(64);
+            let drain = tokio::spawn(async move {
+                let mut frames = 0;
+                while rx.recv().await.is_some() {
+                    frames += 1;
+                }
+                frames
+            });
+            let stream_sid = format!("MZ{call:032}");
+            for _ in 0..RESPONSES_PER_CALL {
+                send_response(encoding, &stream_sid, &audio, &tx).await;
+            }
+            drop(tx);
+            drain.await.unwrap()
+        }));
+    }
+    let mut frames = 0;
+    for call in calls {
+        frames += call.await.unwrap();
+    }
+
+    let elapsed = started.elapsed();
+    (
+        elapsed,
+        ALLOCATIONS.load(Ordering::Relaxed) - before,
+        frames,
+    )
+}
+
+#[tokio::main]
+async fn main() {
+    for (name, encoding) in [
+        ("json!", Encoding::Json),
+        ("FrameEncoder", Encoding::FrameEncoder),
+    ] {
+        // Warm up, then measure
+        run(encoding).await;
+        let (elapsed, allocations, frames) = run(encoding).await;
+        println!(
+            "{name:>12}: {CALLS} calls, {frames} frames in {elapsed:>10.2?} \
+             ({:.0} ns/frame, {:.1} allocations/frame)",
+            elapsed.as_nanos() as f64 / frames as f64,
+            allocations as f64 / frames as f64,
+        );
+    }
+}
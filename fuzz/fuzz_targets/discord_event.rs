@@ -0,0 +1,12 @@
+//! discord-voice sidecar WebSocket messages, batched audio included.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voice_echo::discord::stream;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        stream::fuzz_message(text);
+    }
+});
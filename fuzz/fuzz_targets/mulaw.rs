@@ -0,0 +1,15 @@
+//! Mu-law from Twilio and the Discord sidecar.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voice_echo::pipeline::audio;
+
+fuzz_target!(|data: &[u8]| {
+    let pcm = audio::decode_mulaw(data);
+    assert_eq!(pcm.len(), data.len());
+    // Every code word but negative zero (0x7f) re-encodes to itself
+    for (&byte, &sample) in data.iter().zip(&pcm) {
+        assert!(byte == 0x7f || audio::pcm_to_mulaw(sample) == byte);
+    }
+});
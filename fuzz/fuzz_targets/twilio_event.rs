@@ -0,0 +1,12 @@
+//! Twilio media stream WebSocket messages.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voice_echo::twilio::media;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(text) = std::str::from_utf8(data) {
+        media::fuzz_message(text);
+    }
+});
@@ -0,0 +1,11 @@
+//! Uploaded hold music and WAV bytes from STT/TTS round trips.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use voice_echo::pipeline::audio;
+
+fuzz_target!(|data: &[u8]| {
+    let _ = audio::wav_to_pcm(data);
+    let _ = audio::decode_wav_as_mulaw(data, 0.3);
+});
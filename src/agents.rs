@@ -0,0 +1,171 @@
+//! Named agents (`[[agents]]`).
+//!
+//! An agent bundles the per-persona knobs — spoken name, voice, greeting
+//! pool, prompt template and brain overrides — under one name. Calls pick an
+//! agent by the Twilio number that was dialled, the Discord guild, or by
+//! name from the API. Calls without an agent use the top-level settings.
+
+use std::sync::Arc;
+
+use rand::seq::SliceRandom;
+
+use crate::config::{AgentConfig, Config};
+use crate::rng::SharedRng;
+use crate::Brain;
+
+pub struct Agent {
+    pub name: String,
+    /// Name spoken in greetings (`{name}`).
+    pub display_name: String,
+    pub voice_id: Option<String>,
+    greetings: Vec<String>,
+    /// Locale of the built-in/configured greeting pool, when `greetings` is empty.
+    pub locale: Option<String>,
+    prompt_template: Option<String>,
+    /// Replaces the default system prompt in local brain mode.
+    pub system_prompt: Option<String>,
+    /// Replaces the default brain for this agent's calls.
+    pub brain: Option<Brain>,
+    phone_numbers: Vec<String>,
+    discord_guilds: Vec<String>,
+    /// Picks greetings; seeded by `[debug] seed`.
+    rng: SharedRng,
+}
+
+impl Agent {
+    /// Build an agent. `brain` is the agent's own brain, if it overrides one.
+    pub fn new(agent: &AgentConfig, config: &Config, brain: Option<Brain>) -> Self {
+        let system_prompt = agent.self_path.as_ref().and_then(|path| {
+            std::fs::read_to_string(path)
+                .map_err(
+                    |e| tracing::warn!(agent = %agent.name, path, "Failed to read self_path: {e}"),
+                )
+                .ok()
+        });
+        Self {
+            name: agent.name.clone(),
+            display_name: agent
+                .display_name
+                .clone()
+                .unwrap_or_else(|| config.llm.name.clone()),
+            voice_id: agent.voice_id.clone(),
+            greetings: agent.greetings.clone(),
+            locale: agent.locale.clone(),
+            prompt_template: agent.prompt_template.clone(),
+            system_prompt,
+            brain,
+            phone_numbers: agent.phone_numbers.clone(),
+            discord_guilds: agent.discord_guilds.clone(),
+            rng: SharedRng::new(config.debug.seed),
+        }
+    }
+
+    /// A greeting from the agent's pool, or `None` if it has none.
+    pub fn greeting(&self) -> Option<String> {
+        let template = self.rng.with(|rng| self.greetings.choose(rng))?;
+        Some(template.replace("{name}", &self.display_name))
+    }
+
+    /// Render the agent's prompt template around a transcript, or `None`
+    /// if the agent has no template.
+    ///
+    /// Placeholders: `{transcript}`, `{context}` (empty when absent), `{name}`.
+    pub fn render_prompt(&self, transcript: &str, context: Option<&str>) -> Option<String> {
+        let template = self.prompt_template.as_ref()?;
+        Some(
+            template
+                .replace("{name}", &self.display_name)
+                .replace("{context}", context.unwrap_or(""))
+                .replace("{transcript}", transcript),
+        )
+    }
+}
+
+/// All configured agents.
+#[derive(Default)]
+pub struct Agents {
+    agents: Vec<Arc<Agent>>,
+}
+
+impl Agents {
+    pub fn new(agents: Vec<Agent>) -> Self {
+        Self {
+            agents: agents.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Agent>> {
+        self.agents.iter().find(|a| a.name == name).cloned()
+    }
+
+    /// The agent answering calls to `number` (the dialled Twilio number).
+    pub fn for_number(&self, number: &str) -> Option<Arc<Agent>> {
+        self.agents
+            .iter()
+            .find(|a| a.phone_numbers.iter().any(|n| n == number))
+            .cloned()
+    }
+
+    /// The agent for a Discord guild.
+    pub fn for_guild(&self, guild_id: &str) -> Option<Arc<Agent>> {
+        self.agents
+            .iter()
+            .find(|a| a.discord_guilds.iter().any(|g| g == guild_id))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn agent(toml: &str) -> Agent {
+        let config: AgentConfig = toml::from_str(toml).unwrap();
+        Agent {
+            name: config.name,
+            display_name: config.display_name.unwrap_or_else(|| "Echo".into()),
+            voice_id: config.voice_id,
+            greetings: config.greetings,
+            locale: config.locale,
+            prompt_template: config.prompt_template,
+            system_prompt: None,
+            brain: None,
+            phone_numbers: config.phone_numbers,
+            discord_guilds: config.discord_guilds,
+            rng: SharedRng::default(),
+        }
+    }
+
+    #[test]
+    fn routes_by_number_and_guild() {
+        let agents = Agents::new(vec![
+            agent(
+                r#"name = "alerts"
+                     phone_numbers = ["+15550001"]"#,
+            ),
+            agent(
+                r#"name = "friend"
+                     discord_guilds = ["42"]"#,
+            ),
+        ]);
+        assert_eq!(agents.for_number("+15550001").unwrap().name, "alerts");
+        assert_eq!(agents.for_guild("42").unwrap().name, "friend");
+        assert!(agents.for_number("+15559999").is_none());
+        assert!(agents.get("friend").is_some());
+    }
+
+    #[test]
+    fn renders_template_and_greeting() {
+        let a = agent(
+            r#"name = "alerts"
+               display_name = "Sentinel"
+               greetings = ["{name} on duty"]
+               prompt_template = "[{context}] {name} heard: {transcript}""#,
+        );
+        assert_eq!(a.greeting().unwrap(), "Sentinel on duty");
+        assert_eq!(
+            a.render_prompt("disk full?", Some("alert")).unwrap(),
+            "[alert] Sentinel heard: disk full?"
+        );
+    }
+}
@@ -0,0 +1,251 @@
+use std::convert::Infallible;
+use std::sync::atomic::Ordering;
+
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, StatusCode};
+use axum::response::sse::{Event, KeepAlive, Sse};
+use axum::response::{Html, IntoResponse, Response};
+use axum::Json;
+use base64::Engine;
+use futures_util::stream::{self, Stream};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::events::{CallEvent, EndReason};
+use crate::registry::Transport;
+use crate::AppState;
+
+use super::inject::speak_into;
+use super::outbound::{check_auth, unauthorized};
+
+/// The dashboard: one self-contained page, no external assets.
+const PAGE: &str = include_str!("admin.html");
+
+#[derive(Debug, Serialize)]
+struct CallsResponse {
+    draining: bool,
+    calls: Vec<CallSummary>,
+}
+
+#[derive(Debug, Serialize)]
+struct CallSummary {
+    call_sid: String,
+    transport: Transport,
+    age_secs: u64,
+    tenant: Option<String>,
+    agent: Option<String>,
+    /// The caller's `[[contacts]]` name, if known.
+    contact: Option<String>,
+    language: Option<String>,
+    speaking: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct InjectRequest {
+    pub text: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct DrainRequest {
+    pub draining: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// `check_auth`, also taking the token as the password of Basic auth, with
+/// a Basic challenge on 401 so a browser asks for it and sends it on the
+/// page's own requests. Only the dashboard's routes take Basic auth.
+#[allow(clippy::result_large_err)]
+fn admin_auth(headers: &HeaderMap, state: &AppState) -> Result<(), Response> {
+    let token = &state.config.api.token;
+    let result = match basic_password(headers) {
+        Some(password) if !token.is_empty() => match password == *token {
+            true => Ok(()),
+            false => Err(unauthorized()),
+        },
+        _ => check_auth(headers, token),
+    };
+    result.map_err(|mut resp| {
+        if resp.status() == StatusCode::UNAUTHORIZED {
+            resp.headers_mut().insert(
+                header::WWW_AUTHENTICATE,
+                header::HeaderValue::from_static(r#"Basic realm="voice-echo admin""#),
+            );
+        }
+        resp
+    })
+}
+
+/// The password of `Authorization: Basic` credentials, with any username.
+/// That's how a browser presents the token to `/admin`.
+fn basic_password(headers: &HeaderMap) -> Option<String> {
+    let encoded = headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Basic ")?;
+    let decoded = base64::engine::general_purpose::STANDARD
+        .decode(encoded)
+        .ok()?;
+    let credentials = String::from_utf8(decoded).ok()?;
+    let (_, password) = credentials.split_once(':')?;
+    Some(password.to_string())
+}
+
+/// GET /admin — The operator dashboard.
+///
+/// Shows active calls, live transcripts, provider health and a drain switch,
+/// with hangup and inject per call. Everything it shows or does goes through
+/// the endpoints below and `/health/deep`.
+///
+/// Requires the `[api] token`, as a bearer token or as the password of HTTP
+/// Basic auth (any username); tenant tokens are refused.
+pub async fn handle_page(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    Html(PAGE).into_response()
+}
+
+/// GET /admin/calls — Active calls, oldest first, and whether we're draining.
+pub async fn handle_calls(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    let mut entries = state.call_registry.entries().await;
+    entries.sort_by_key(|(_, entry)| std::cmp::Reverse(entry.age()));
+    let calls = entries
+        .into_iter()
+        .map(|(call_sid, entry)| CallSummary {
+            transport: entry.transport,
+            age_secs: entry.age().as_secs(),
+            speaking: entry.is_speaking(),
+            tenant: entry.profile.tenant.as_ref().map(|t| t.name.clone()),
+            agent: entry.profile.agent.as_ref().map(|a| a.name.clone()),
+            contact: entry.profile.contact.as_ref().map(|c| c.name.clone()),
+            language: entry.profile.language,
+            call_sid,
+        })
+        .collect();
+    Json(CallsResponse {
+        draining: state.is_draining(),
+        calls,
+    })
+    .into_response()
+}
+
+/// POST /admin/calls/{call_sid}/hangup — End a call.
+///
+/// Closing the media stream ends a Twilio call (nothing follows its
+/// `<Connect>`) and tells the Discord sidecar to leave.
+pub async fn handle_hangup(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(call_sid): Path<String>,
+) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    if state.call_registry.get(&call_sid).await.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No active call with sid {call_sid}"),
+            }),
+        )
+            .into_response();
+    }
+    tracing::warn!(call_sid = %call_sid, "Hanging up from /admin");
+    state.end_call(&call_sid, EndReason::Operator).await;
+    Json(StatusResponse {
+        status: "ended".to_string(),
+    })
+    .into_response()
+}
+
+/// POST /admin/calls/{call_sid}/inject — Speak `text` into a call, as
+/// `/api/inject` does.
+pub async fn handle_inject(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(call_sid): Path<String>,
+    Json(req): Json<InjectRequest>,
+) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    let Some(entry) = state.call_registry.get(&call_sid).await else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No active call with sid {call_sid}"),
+            }),
+        )
+            .into_response();
+    };
+    tracing::info!(call_sid = %call_sid, "Injecting from /admin");
+    speak_into(&state, &call_sid, &entry, &req.text).await
+}
+
+/// POST /admin/drain — Start or stop draining.
+///
+/// While draining, new inbound calls hear the `[degraded]` message,
+/// `/api/call` answers 503 and `/health/deep` reports unhealthy; calls in
+/// progress carry on.
+pub async fn handle_drain(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DrainRequest>,
+) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    let was = state.draining.swap(req.draining, Ordering::Relaxed);
+    if was != req.draining {
+        if req.draining {
+            tracing::warn!("Draining: turning new calls away");
+        } else {
+            tracing::warn!("Drain cancelled: accepting calls");
+        }
+    }
+    Json(req).into_response()
+}
+
+/// GET /admin/events — The event bus as server-sent events, one JSON
+/// `CallEvent` per message. Events missed while the reader lagged are
+/// dropped.
+pub async fn handle_events(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    if let Err(resp) = admin_auth(&headers, &state) {
+        return resp;
+    }
+    Sse::new(event_stream(state.events.subscribe()))
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+fn event_stream(
+    rx: tokio::sync::broadcast::Receiver<CallEvent>,
+) -> impl Stream<Item = Result<Event, Infallible>> {
+    stream::unfold(rx, |mut rx| async move {
+        loop {
+            match rx.recv().await {
+                Ok(event) => {
+                    let data = serde_json::to_string(&event).unwrap_or_default();
+                    return Some((Ok(Event::default().data(data)), rx));
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    tracing::warn!(missed, "Admin event feed lagged");
+                }
+                Err(RecvError::Closed) => return None,
+            }
+        }
+    })
+}
@@ -0,0 +1,150 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use axum::body::Bytes;
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use base64::Engine;
+use serde::Serialize;
+
+use crate::pipeline::audio;
+use crate::pipeline::turn::{self, Stage, TurnTrace};
+use crate::registry::Transport;
+use crate::AppState;
+
+use super::outbound::check_auth;
+
+/// Numbers the pseudo-calls `debug-1`, `debug-2`, ... so runs don't share
+/// brain history.
+static NEXT_RUN: AtomicU64 = AtomicU64::new(1);
+
+#[derive(Debug, Serialize)]
+struct PipelineResponse {
+    call_sid: String,
+    transcript: Option<String>,
+    language: Option<String>,
+    confidence: Option<f32>,
+    response: Option<String>,
+    timings_ms: Timings,
+    /// The reply as a base64 8kHz WAV, if there was one.
+    audio_wav: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<StageError>,
+}
+
+#[derive(Debug, Default, Serialize)]
+struct Timings {
+    queue: u64,
+    stt: Option<u64>,
+    brain: Option<u64>,
+    tts: Option<u64>,
+    total: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct StageError {
+    stage: Option<String>,
+    message: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// POST /debug/pipeline — Run an uploaded WAV through the pipeline.
+///
+/// The whole upload is one utterance (no VAD). It's converted to 8kHz mu-law
+/// as a phone call would deliver it, then goes through the same STT → brain
+/// → TTS turn as a call, gate and budgets included. The response has the
+/// transcript, the brain's answer, per-stage timings and the reply audio; a
+/// failed stage is named in `error` with a 502.
+///
+/// Only served with `[debug] pipeline = true`. Requires the
+/// `Authorization: Bearer <token>` of `[api] token`; tenant tokens are refused.
+pub async fn handle_pipeline(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> impl IntoResponse {
+    if let Err(resp) = check_auth(&headers, &state.config.api.token) {
+        return resp;
+    }
+
+    let pcm = match audio::decode_wav_as_mulaw(&body, 1.0) {
+        Ok(mulaw) => audio::decode_mulaw(&mulaw),
+        Err(e) => {
+            return (
+                StatusCode::BAD_REQUEST,
+                Json(ErrorResponse {
+                    error: format!("Invalid WAV: {e}"),
+                }),
+            )
+                .into_response();
+        }
+    };
+
+    let call_sid = format!("debug-{}", NEXT_RUN.fetch_add(1, Ordering::Relaxed));
+    tracing::info!(call_sid = %call_sid, samples = pcm.len(), "Debug pipeline run");
+
+    let mut trace = TurnTrace::default();
+    let started = std::time::Instant::now();
+    let result =
+        turn::run_turn_traced(&pcm, &call_sid, Transport::Twilio, &state, &mut trace).await;
+    let total = started.elapsed();
+    // Debug runs aren't registered, so there's no agent
+    state.end_sessions(&call_sid, None).await;
+
+    let (status, audio_wav, error) = match result {
+        Ok(mulaw) => (StatusCode::OK, mulaw.map(|m| encode_wav(&m)), None),
+        Err(e) => {
+            tracing::warn!(call_sid = %call_sid, stage = ?trace.stage, "Debug pipeline run failed: {e}");
+            let error = StageError {
+                stage: trace.stage.map(|s| s.to_string()),
+                message: e.to_string(),
+            };
+            (StatusCode::BAD_GATEWAY, None, Some(error))
+        }
+    };
+
+    (
+        status,
+        Json(PipelineResponse {
+            call_sid,
+            timings_ms: timings(&trace, total),
+            transcript: trace.transcript,
+            language: trace.language,
+            confidence: trace.confidence,
+            response: trace.response,
+            audio_wav,
+            error,
+        }),
+    )
+        .into_response()
+}
+
+fn timings(trace: &TurnTrace, total: Duration) -> Timings {
+    let mut timings = Timings {
+        queue: trace.queued.as_millis() as u64,
+        total: total.as_millis() as u64,
+        ..Timings::default()
+    };
+    for &(stage, took) in &trace.timings {
+        let ms = Some(took.as_millis() as u64);
+        match stage {
+            Stage::Stt => timings.stt = ms,
+            Stage::Brain => timings.brain = ms,
+            Stage::Tts => timings.tts = ms,
+            Stage::Turn => {}
+        }
+    }
+    timings
+}
+
+/// Mu-law reply audio as a base64 WAV, playable as-is.
+fn encode_wav(mulaw: &[u8]) -> String {
+    let wav = audio::pcm_to_wav(&audio::decode_mulaw(mulaw));
+    base64::engine::general_purpose::STANDARD.encode(wav)
+}
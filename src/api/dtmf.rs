@@ -0,0 +1,108 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use crate::pipeline::actions;
+use crate::registry::Transport;
+use crate::twilio::outbound::is_dtmf;
+use crate::AppState;
+
+use super::outbound::authenticate;
+
+#[derive(Debug, Deserialize)]
+pub struct DtmfRequest {
+    /// The call_sid to press keys on.
+    pub call_sid: String,
+    /// Keys to press: `0-9 * #`, `w` for a half-second pause.
+    pub digits: String,
+}
+
+#[derive(Debug, Serialize)]
+struct DtmfResponse {
+    status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// POST /api/dtmf — Press keys on an active call.
+///
+/// Sends the digits through the Twilio API (`<Play digits>`), so an
+/// outbound call can get through a phone menu ("press 2 for billing").
+/// The media stream reconnects once they've played and the call carries
+/// on. The brain can do the same after a reply with `[DTMF:2]`.
+///
+/// Requires `Authorization: Bearer <token>` header. A tenant token only
+/// reaches that tenant's calls.
+pub async fn handle_dtmf(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DtmfRequest>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+
+    if !is_dtmf(&req.digits) {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Invalid DTMF digits '{}'", req.digits),
+            }),
+        )
+            .into_response();
+    }
+
+    // Other tenants' calls look like missing ones
+    let entry = state
+        .call_registry
+        .get(&req.call_sid)
+        .await
+        .filter(|e| e.profile.belongs_to(tenant.as_deref()));
+    let Some(entry) = entry else {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No active call with sid {}", req.call_sid),
+            }),
+        )
+            .into_response();
+    };
+
+    // Only phone calls have keys to press
+    if entry.transport != Transport::Twilio {
+        return (
+            StatusCode::BAD_REQUEST,
+            Json(ErrorResponse {
+                error: format!("Call {} is not a phone call", req.call_sid),
+            }),
+        )
+            .into_response();
+    }
+
+    tracing::info!(call_sid = %req.call_sid, digits = %req.digits, "Sending DTMF");
+
+    if let Err(e) = actions::press(&state, &req.call_sid, &req.digits).await {
+        tracing::error!(call_sid = %req.call_sid, "Failed to send DTMF: {e}");
+        return (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ErrorResponse {
+                error: format!("Failed to send DTMF: {e}"),
+            }),
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        Json(DtmfResponse {
+            status: "sent".to_string(),
+        }),
+    )
+        .into_response()
+}
@@ -1,13 +1,14 @@
 use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
-use axum::response::IntoResponse;
+use axum::response::{IntoResponse, Response};
 use axum::Json;
 use serde::{Deserialize, Serialize};
 
-use crate::registry::CallRegistry;
+use crate::pipeline::transliterate;
+use crate::registry::{CallEntry, CallRegistry};
 use crate::AppState;
 
-use super::outbound::check_auth;
+use super::outbound::authenticate;
 
 #[derive(Debug, Deserialize)]
 pub struct InjectRequest {
@@ -33,20 +34,26 @@ struct ErrorResponse {
 /// When D sends a Discord message during a call, bridge-echo sends
 /// the Claude response here instead of back to Discord.
 ///
-/// Requires `Authorization: Bearer <token>` header.
+/// Requires `Authorization: Bearer <token>` header. A tenant token only
+/// reaches that tenant's calls.
 pub async fn handle_inject(
     State(state): State<AppState>,
     headers: HeaderMap,
     Json(req): Json<InjectRequest>,
 ) -> impl IntoResponse {
-    if let Err(resp) = check_auth(&headers, &state.config.api.token) {
-        return resp;
-    }
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
 
     tracing::info!(call_sid = %req.call_sid, text_len = req.text.len(), "Inject requested");
 
-    // Look up the active call
-    let entry = state.call_registry.get(&req.call_sid).await;
+    // Look up the active call (other tenants' calls look like missing ones)
+    let entry = state
+        .call_registry
+        .get(&req.call_sid)
+        .await
+        .filter(|e| e.profile.belongs_to(tenant.as_deref()));
     let Some(entry) = entry else {
         tracing::warn!(call_sid = %req.call_sid, "No active call found for inject");
         return (
@@ -57,12 +64,28 @@ pub async fn handle_inject(
         )
             .into_response();
     };
+    speak_into(&state, &req.call_sid, &entry, &req.text).await
+}
 
-    // Run TTS
-    let tts_mulaw = match state.tts.synthesize(&req.text).await {
+/// Synthesize `text` in the call's voice and play it into the call.
+pub(crate) async fn speak_into(
+    state: &AppState,
+    call_sid: &str,
+    entry: &CallEntry,
+    text: &str,
+) -> Response {
+    // Run TTS in the call's voice
+    let tts_client = state.tts_for(&entry.profile);
+    let text = state.profanity.clean_response(text);
+    let text = transliterate::apply(&text, &state.config.tts);
+    let tts = match state.voice_for(&entry.profile) {
+        Some(voice_id) => tts_client.synthesize_with_voice(&text, voice_id).await,
+        None => tts_client.synthesize(&text).await,
+    };
+    let tts_mulaw = match tts {
         Ok(data) => data,
         Err(e) => {
-            tracing::error!(call_sid = %req.call_sid, "TTS failed for inject: {e}");
+            tracing::error!(call_sid = %call_sid, "TTS failed for inject: {e}");
             return (
                 StatusCode::INTERNAL_SERVER_ERROR,
                 Json(ErrorResponse {
@@ -77,8 +100,8 @@ pub async fn handle_inject(
     entry.set_speaking(true);
 
     // Send audio frames through the call's response channel
-    if let Err(e) = CallRegistry::send_audio(&entry, &tts_mulaw).await {
-        tracing::error!(call_sid = %req.call_sid, "Failed to inject audio: {e}");
+    if let Err(e) = CallRegistry::send_audio(entry, &tts_mulaw).await {
+        tracing::error!(call_sid = %call_sid, "Failed to inject audio: {e}");
         entry.set_speaking(false);
         return (
             StatusCode::INTERNAL_SERVER_ERROR,
@@ -90,7 +113,7 @@ pub async fn handle_inject(
     }
 
     tracing::info!(
-        call_sid = %req.call_sid,
+        call_sid = %call_sid,
         tts_bytes = tts_mulaw.len(),
         "Audio injected successfully"
     );
@@ -0,0 +1,149 @@
+use axum::extract::State;
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::{Deserialize, Serialize};
+
+use std::sync::Arc;
+
+use crate::pipeline::language;
+use crate::twilio::outbound::OutboundError;
+use crate::AppState;
+
+use super::outbound::authenticate;
+
+#[derive(Debug, Deserialize)]
+pub struct InterpretRequest {
+    /// The two parties to call. Each hears the other translated into its
+    /// own language.
+    pub legs: [LegRequest; 2],
+}
+
+#[derive(Debug, Deserialize)]
+pub struct LegRequest {
+    /// Phone number to call (E.164 format).
+    pub to: String,
+    /// Language spoken on this leg (code or locale, e.g. "es" or "es-MX").
+    pub language: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct InterpretResponse {
+    pub call_sids: [String; 2],
+    pub status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// POST /api/interpret — Call two parties and interpret between them.
+///
+/// Requires `Authorization: Bearer <token>` header. Each leg's speech is
+/// transcribed, translated by the brain and spoken into the other leg.
+///
+/// Request body:
+/// ```json
+/// {
+///   "legs": [
+///     { "to": "+34612345678", "language": "es" },
+///     { "to": "+14155550100", "language": "en" }
+///   ]
+/// }
+/// ```
+pub async fn handle_interpret(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<InterpretRequest>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+
+    if state.is_draining() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Draining, not placing new calls".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    let [a, b] = req.legs;
+    tracing::info!(
+        a = %a.to,
+        b = %b.to,
+        tenant = tenant.as_ref().map(|t| t.name.as_str()),
+        "Interpreted call requested"
+    );
+
+    let twilio = Arc::clone(tenant.as_ref().map_or(&state.twilio, |t| &t.twilio));
+    let a_sid = match twilio.call(&a.to).await {
+        Ok(call_sid) => call_sid,
+        Err(e) => return call_failed(e),
+    };
+    // A leg from the start: the first party may answer while the second
+    // is still being called
+    state
+        .interpreter
+        .hold(
+            a_sid.clone(),
+            language::of_locale(&a.language),
+            language::of_locale(&b.language),
+            tenant.map(|t| t.name.clone()),
+            Arc::clone(&twilio),
+        )
+        .await;
+    let b_sid = match twilio.call(&b.to).await {
+        Ok(call_sid) => call_sid,
+        Err(e) => {
+            // Don't leave the first party on the line alone
+            state.interpreter.remove(&a_sid).await;
+            if let Err(e) = twilio.hangup(&a_sid).await {
+                tracing::warn!(call_sid = %a_sid, "Failed to hang up first leg: {e}");
+            }
+            return call_failed(e);
+        }
+    };
+
+    if !state.interpreter.pair(&a_sid, b_sid.clone()).await {
+        // The first party hung up while the second was being called
+        if let Err(e) = twilio.hangup(&b_sid).await {
+            tracing::warn!(call_sid = %b_sid, "Failed to hang up second leg: {e}");
+        }
+        return (
+            StatusCode::CONFLICT,
+            Json(ErrorResponse {
+                error: format!("First leg {a_sid} ended before the second was placed"),
+            }),
+        )
+            .into_response();
+    }
+
+    (
+        StatusCode::OK,
+        Json(InterpretResponse {
+            call_sids: [a_sid, b_sid],
+            status: "initiated".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+fn call_failed(e: OutboundError) -> axum::response::Response {
+    tracing::error!("Failed to initiate interpreter leg: {e}");
+    let status = match e {
+        OutboundError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+        _ => StatusCode::INTERNAL_SERVER_ERROR,
+    };
+    (
+        status,
+        Json(ErrorResponse {
+            error: e.to_string(),
+        }),
+    )
+        .into_response()
+}
@@ -1,2 +1,8 @@
+pub mod admin;
+pub mod debug;
+pub mod dtmf;
 pub mod inject;
+pub mod interpret;
 pub mod outbound;
+pub mod reminders;
+pub mod scheduled;
@@ -2,11 +2,18 @@ use axum::extract::State;
 use axum::http::{HeaderMap, StatusCode};
 use axum::response::IntoResponse;
 use axum::Json;
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use crate::pipeline::language;
+use crate::tenants::Tenant;
+use crate::twilio::outbound::OutboundError;
 use crate::{AppState, CallMeta};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct CallRequest {
     /// Phone number to call (E.164 format, e.g., "+34612345678")
     pub to: String,
@@ -19,6 +26,28 @@ pub struct CallRequest {
     /// Short reason for calling, used in the outbound greeting.
     /// e.g., "I found something interesting in the logs"
     pub reason: Option<String>,
+    /// Named agent (`[[agents]]`) placing the call — its voice, greeting
+    /// and prompt template are used instead of the defaults. Tenants
+    /// default to their first agent.
+    pub agent: Option<String>,
+    /// Name of an `[outbound.templates]` entry that builds `context` (and
+    /// `reason`) from `vars`. Explicit `context`/`reason` take precedence.
+    pub template: Option<String>,
+    /// Values for the template's `{placeholders}`.
+    #[serde(default)]
+    pub vars: HashMap<String, serde_json::Value>,
+    /// Language for the whole call (code or locale, e.g. "es" or "es-MX"):
+    /// sets the STT hint, voice, greeting locale and reply language.
+    pub language: Option<String>,
+    /// Ring now, even during `[outbound] quiet_hours`.
+    #[serde(default)]
+    pub urgent: bool,
+    /// Added to the brain's system prompt for this call only, e.g. a
+    /// purpose-specific persona.
+    pub system_prompt: Option<String>,
+    /// Tools the brain may use on this call (bridge-echo only), replacing
+    /// `llm.allowed_tools`. `[]` keeps the call tool-free.
+    pub allowed_tools: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize)]
@@ -27,6 +56,14 @@ pub struct CallResponse {
     pub status: String,
 }
 
+/// A call held back by quiet hours, to be placed at `scheduled_for`.
+#[derive(Debug, Serialize)]
+pub struct DeferredResponse {
+    pub id: String,
+    pub status: String,
+    pub scheduled_for: DateTime<Utc>,
+}
+
 #[derive(Debug, Serialize)]
 struct ErrorResponse {
     error: String,
@@ -40,47 +77,83 @@ struct ErrorResponse {
 /// ```json
 /// {
 ///   "to": "+34612345678",
-///   "message": "Server CPU at 95%"
+///   "context": "Server CPU at 95%",
+///   "agent": "alerts"
+/// }
+/// ```
+///
+/// `"language": "es"` runs the whole call in Spanish.
+///
+/// During `[outbound] quiet_hours` the call is deferred until the window
+/// ends and the answer is 202 with its schedule id, unless `"urgent": true`.
+///
+/// Or, with a server-side template:
+/// ```json
+/// {
+///   "to": "+34612345678",
+///   "template": "alert",
+///   "vars": { "host": "web-1", "metric": "CPU", "value": "95%" }
 /// }
 /// ```
 pub async fn handle_call(
     State(state): State<AppState>,
     headers: HeaderMap,
-    Json(req): Json<CallRequest>,
+    Json(mut req): Json<CallRequest>,
 ) -> impl IntoResponse {
-    // Check bearer token
-    if let Err(resp) = check_auth(&headers, &state.config.api.token) {
-        return resp;
-    }
-
-    tracing::info!(to = %req.to, "Outbound call requested");
-
-    match state.twilio.call(&req.to).await {
-        Ok(call_sid) => {
-            // Store call metadata (context + reason) for this call
-            if req.context.is_some() || req.reason.is_some() {
-                state.call_metas.lock().await.insert(
-                    call_sid.clone(),
-                    CallMeta {
-                        context: req.context,
-                        reason: req.reason,
-                    },
-                );
-                tracing::info!(call_sid = %call_sid, "Stored call metadata");
-            }
-            (
-                StatusCode::OK,
-                Json(CallResponse {
-                    call_sid,
-                    status: "initiated".to_string(),
+    // Check bearer token — a tenant token places the call as that tenant
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+
+    if state.is_draining() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Draining, not placing new calls".to_string(),
+            }),
+        )
+            .into_response();
+    }
+
+    if let Err(error) = prepare(&mut req, tenant.as_deref(), &state) {
+        return (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response();
+    }
+
+    let tenant_name = tenant.as_ref().map(|t| t.name.clone());
+    if !req.urgent {
+        if let Some(at) = quiet_until(&state, &req.to, Utc::now()) {
+            tracing::info!(to = %req.to, tenant = ?tenant_name, %at, "Quiet hours, deferring call");
+            let id = state.scheduler.defer(at, tenant_name, req).await;
+            return (
+                StatusCode::ACCEPTED,
+                Json(DeferredResponse {
+                    id,
+                    status: "deferred".to_string(),
+                    scheduled_for: at,
                 }),
             )
-                .into_response()
+                .into_response();
         }
+    }
+
+    match place(&state, tenant, req).await {
+        Ok(call_sid) => (
+            StatusCode::OK,
+            Json(CallResponse {
+                call_sid,
+                status: "initiated".to_string(),
+            }),
+        )
+            .into_response(),
         Err(e) => {
             tracing::error!("Failed to initiate call: {e}");
+            let status = match e {
+                OutboundError::Unavailable(_) => StatusCode::SERVICE_UNAVAILABLE,
+                _ => StatusCode::INTERNAL_SERVER_ERROR,
+            };
             (
-                StatusCode::INTERNAL_SERVER_ERROR,
+                status,
                 Json(ErrorResponse {
                     error: e.to_string(),
                 }),
@@ -90,6 +163,167 @@ pub async fn handle_call(
     }
 }
 
+/// Apply the request's template and settle its agent and language, so it
+/// can be placed as is, now or later.
+pub fn prepare(
+    req: &mut CallRequest,
+    tenant: Option<&Tenant>,
+    state: &AppState,
+) -> Result<(), String> {
+    apply_template(req, state)?;
+
+    req.agent = req
+        .agent
+        .take()
+        .or_else(|| tenant?.default_agent().map(str::to_string));
+    if let Some(ref agent) = req.agent {
+        let allowed = tenant.map_or(true, |t| t.allows_agent(agent));
+        if !allowed || state.agents.get(agent).is_none() {
+            return Err(format!("Unknown agent '{agent}'"));
+        }
+    }
+
+    if let Some(ref requested) = req.language {
+        req.language = Some(
+            language::code(&language::of_locale(requested))
+                .ok_or_else(|| format!("Unknown language '{requested}'"))?,
+        );
+    }
+    Ok(())
+}
+
+/// When `[outbound] quiet_hours` at the destination end, if they're on now.
+fn quiet_until(state: &AppState, to: &str, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let quiet = state.config.outbound.quiet_hours.as_ref()?;
+    quiet.ends_after(now, destination_timezone(state, to))
+}
+
+/// Where `to` is: its contact's timezone, then the quiet hours' for the
+/// number, then `[identity] timezone`.
+pub fn destination_timezone(state: &AppState, to: &str) -> Option<chrono_tz::Tz> {
+    state
+        .contacts
+        .for_number(to)
+        .and_then(|contact| contact.timezone)
+        .or_else(|| {
+            let quiet = state.config.outbound.quiet_hours.as_ref()?;
+            quiet.timezone_for(to)
+        })
+        .or(state.config.identity.timezone)
+}
+
+/// Place a prepared call from the tenant's Twilio account and remember its
+/// context for the media stream. Returns the call SID.
+pub async fn place(
+    state: &AppState,
+    tenant: Option<Arc<Tenant>>,
+    req: CallRequest,
+) -> Result<String, OutboundError> {
+    let tenant_name = tenant.as_ref().map(|t| t.name.clone());
+    tracing::info!(to = %req.to, tenant = ?tenant_name, agent = ?req.agent, "Outbound call requested");
+
+    let twilio = tenant.as_ref().map_or(&state.twilio, |t| &t.twilio);
+    let call_sid = twilio.call(&req.to).await?;
+    // Store call metadata (context, reason, agent, tenant) for this call
+    if req.context.is_some()
+        || req.reason.is_some()
+        || req.agent.is_some()
+        || tenant_name.is_some()
+        || req.language.is_some()
+        || req.system_prompt.is_some()
+        || req.allowed_tools.is_some()
+    {
+        state.call_metas.write().await.insert(
+            call_sid.clone(),
+            CallMeta {
+                context: req.context,
+                reason: req.reason,
+                agent: req.agent,
+                tenant: tenant_name,
+                language: req.language,
+                system_prompt: req.system_prompt,
+                allowed_tools: req.allowed_tools,
+            },
+        );
+        tracing::info!(call_sid = %call_sid, "Stored call metadata");
+    }
+    Ok(call_sid)
+}
+
+/// Fill `context`, `reason` and `agent` from the request's template, where
+/// the request doesn't set them itself.
+fn apply_template(req: &mut CallRequest, state: &AppState) -> Result<(), String> {
+    let Some(ref name) = req.template else {
+        return Ok(());
+    };
+    let template = state
+        .config
+        .outbound
+        .templates
+        .get(name)
+        .ok_or_else(|| format!("Unknown template '{name}'"))?;
+
+    if req.context.is_none() {
+        req.context = Some(render(&template.context, &req.vars)?);
+    }
+    if req.reason.is_none() {
+        req.reason = template
+            .reason
+            .as_ref()
+            .map(|reason| render(reason, &req.vars))
+            .transpose()?;
+    }
+    if req.agent.is_none() {
+        req.agent = template.agent.clone();
+    }
+    Ok(())
+}
+
+/// Replace `{var}` placeholders. Fails listing any placeholder without a value.
+fn render(template: &str, vars: &HashMap<String, serde_json::Value>) -> Result<String, String> {
+    let mut out = String::with_capacity(template.len());
+    let mut missing = Vec::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        out.push_str(&rest[..open]);
+        let after = &rest[open + 1..];
+        let Some(close) = after.find('}') else {
+            rest = &rest[open..];
+            break;
+        };
+        let key = &after[..close];
+        match vars.get(key) {
+            Some(serde_json::Value::String(value)) => out.push_str(value),
+            Some(value) => out.push_str(&value.to_string()),
+            None => missing.push(key),
+        }
+        rest = &after[close + 1..];
+    }
+    out.push_str(rest);
+
+    if missing.is_empty() {
+        Ok(out)
+    } else {
+        Err(format!("Missing template vars: {}", missing.join(", ")))
+    }
+}
+
+/// Authenticate an `/api/*` request. Returns the tenant owning the bearer
+/// token, or `None` for the operator's `api.token`.
+#[allow(clippy::result_large_err)]
+pub fn authenticate(
+    headers: &HeaderMap,
+    state: &AppState,
+) -> Result<Option<Arc<Tenant>>, axum::response::Response> {
+    if let Some(tenant) = bearer_token(headers).and_then(|t| state.tenants.for_token(t)) {
+        return Ok(Some(tenant));
+    }
+    if state.config.api.token.is_empty() && !state.tenants.is_empty() {
+        return Err(unauthorized());
+    }
+    check_auth(headers, &state.config.api.token).map(|()| None)
+}
+
 #[allow(clippy::result_large_err)]
 pub fn check_auth(
     headers: &HeaderMap,
@@ -106,22 +340,68 @@ pub fn check_auth(
             .into_response());
     }
 
-    let provided = headers
+    match bearer_token(headers) {
+        Some(token) if token == expected_token => Ok(()),
+        _ => Err(unauthorized()),
+    }
+}
+
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
         .get("authorization")
         .and_then(|v| v.to_str().ok())
-        .and_then(|v| v.strip_prefix("Bearer "));
+        .and_then(|v| v.strip_prefix("Bearer "))
+}
 
-    match provided {
-        Some(token) if token == expected_token => Ok(()),
-        _ => {
-            tracing::warn!("Unauthorized API request");
-            Err((
-                StatusCode::UNAUTHORIZED,
-                Json(ErrorResponse {
-                    error: "Invalid or missing bearer token".to_string(),
-                }),
-            )
-                .into_response())
-        }
+pub(crate) fn unauthorized() -> axum::response::Response {
+    tracing::warn!("Unauthorized API request");
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ErrorResponse {
+            error: "Invalid or missing bearer token".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use base64::Engine;
+
+    fn vars(json: serde_json::Value) -> HashMap<String, serde_json::Value> {
+        serde_json::from_value(json).unwrap()
+    }
+
+    #[test]
+    fn renders_placeholders() {
+        let vars = vars(serde_json::json!({ "host": "web-1", "value": 95 }));
+        assert_eq!(
+            render("{host} is at {value}% CPU", &vars).unwrap(),
+            "web-1 is at 95% CPU"
+        );
+    }
+
+    #[test]
+    fn reports_missing_vars() {
+        let vars = vars(serde_json::json!({ "host": "web-1" }));
+        assert_eq!(
+            render("{host} {metric} {value}", &vars).unwrap_err(),
+            "Missing template vars: metric, value"
+        );
+    }
+
+    #[test]
+    fn accepts_the_token_as_bearer_only() {
+        let auth = |value: &str| {
+            let mut headers = HeaderMap::new();
+            headers.insert("authorization", value.parse().unwrap());
+            check_auth(&headers, "s3cret").is_ok()
+        };
+        assert!(auth("Bearer s3cret"));
+        assert!(!auth("Bearer wrong"));
+        // Basic auth is for the dashboard alone
+        let basic = base64::engine::general_purpose::STANDARD.encode("admin:s3cret");
+        assert!(!auth(&format!("Basic {basic}")));
     }
 }
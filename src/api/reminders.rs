@@ -0,0 +1,212 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+use crate::cron::Schedule;
+use crate::reminders::{self, Reminder};
+use crate::tenants::Tenant;
+use crate::AppState;
+
+use super::outbound::{authenticate, destination_timezone, prepare, CallRequest};
+
+#[derive(Debug, Deserialize)]
+pub struct ReminderRequest {
+    /// Cron schedule, e.g. "0 9 * * *".
+    pub schedule: String,
+    /// Timezone the schedule runs in; defaults to the destination's.
+    pub timezone: Option<chrono_tz::Tz>,
+    /// Where each run's outcome is POSTed, as well as `[[webhooks]]`.
+    pub webhook_url: Option<String>,
+    /// The call to place, as for `/api/call`.
+    #[serde(flatten)]
+    pub call: CallRequest,
+}
+
+#[derive(Debug, Serialize)]
+struct RemindersResponse {
+    reminders: Vec<Reminder>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// POST /api/reminders — Create a recurring call.
+///
+/// Requires `Authorization: Bearer <token>` header. A tenant token creates
+/// the reminder as that tenant.
+///
+/// Request body:
+/// ```json
+/// {
+///   "schedule": "0 9 * * *",
+///   "timezone": "Europe/Madrid",
+///   "to": "+34612345678",
+///   "template": "medication",
+///   "vars": { "pill": "blood pressure" },
+///   "webhook_url": "https://example.com/reminder-done"
+/// }
+/// ```
+pub async fn handle_create(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ReminderRequest>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+
+    let schedule = match req.schedule.parse::<Schedule>() {
+        Ok(schedule) => schedule,
+        Err(e) => return bad_request(format!("Invalid schedule: {e}")),
+    };
+    let reminder = Reminder {
+        id: String::new(),
+        schedule,
+        timezone: req
+            .timezone
+            .or_else(|| destination_timezone(&state, &req.call.to)),
+        tenant: tenant.as_ref().map(|t| t.name.clone()),
+        webhook_url: req.webhook_url,
+        next_at: None,
+        last_run: None,
+        request: req.call,
+    };
+
+    // Check the call would place now, so a bad template fails here and
+    // not on every run
+    let now = Utc::now();
+    if let Err(error) = prepare(&mut reminder.request_at(now), tenant.as_deref(), &state) {
+        return bad_request(error);
+    }
+    if reminder.next_after(now).is_none() {
+        return bad_request(format!("Schedule '{}' never fires", reminder.schedule));
+    }
+
+    let reminder = state.reminders.add(reminder).await;
+    tracing::info!(id = %reminder.id, schedule = %reminder.schedule, to = %reminder.request.to, "Reminder created");
+    (StatusCode::CREATED, Json(reminder)).into_response()
+}
+
+/// GET /api/reminders — All reminders, soonest first. A tenant token only
+/// sees that tenant's.
+pub async fn handle_list(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    let reminders = state
+        .reminders
+        .list()
+        .await
+        .into_iter()
+        .filter(|reminder| owns(tenant.as_deref(), reminder))
+        .collect();
+    Json(RemindersResponse { reminders }).into_response()
+}
+
+/// GET /api/reminders/{id} — One reminder, with its next and last runs.
+pub async fn handle_get(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    match state.reminders.get(&id).await {
+        Some(reminder) if owns(tenant.as_deref(), &reminder) => Json(reminder).into_response(),
+        _ => not_found(&id),
+    }
+}
+
+/// DELETE /api/reminders/{id} — Stop a reminder. A run in progress carries on.
+pub async fn handle_delete(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    let owned = state
+        .reminders
+        .get(&id)
+        .await
+        .is_some_and(|reminder| owns(tenant.as_deref(), &reminder));
+    if !owned || state.reminders.remove(&id).await.is_none() {
+        return not_found(&id);
+    }
+    tracing::info!(id = %id, "Reminder deleted");
+    Json(StatusResponse {
+        status: "deleted".to_string(),
+    })
+    .into_response()
+}
+
+/// POST /api/reminders/{id}/run — Run a reminder now, outside its schedule.
+/// The outcome goes to its webhook as usual.
+pub async fn handle_run(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    if state.is_draining() {
+        return (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ErrorResponse {
+                error: "Draining, not placing new calls".to_string(),
+            }),
+        )
+            .into_response();
+    }
+    let reminder = match state.reminders.get(&id).await {
+        Some(reminder) if owns(tenant.as_deref(), &reminder) => reminder,
+        _ => return not_found(&id),
+    };
+    tokio::spawn(reminders::run(state.clone(), reminder, Utc::now()));
+    (
+        StatusCode::ACCEPTED,
+        Json(StatusResponse {
+            status: "running".to_string(),
+        }),
+    )
+        .into_response()
+}
+
+/// The operator reaches every reminder, a tenant only its own.
+fn owns(tenant: Option<&Tenant>, reminder: &Reminder) -> bool {
+    tenant.map_or(true, |t| {
+        reminder.tenant.as_deref() == Some(t.name.as_str())
+    })
+}
+
+fn bad_request(error: String) -> axum::response::Response {
+    (StatusCode::BAD_REQUEST, Json(ErrorResponse { error })).into_response()
+}
+
+fn not_found(id: &str) -> axum::response::Response {
+    (
+        StatusCode::NOT_FOUND,
+        Json(ErrorResponse {
+            error: format!("No reminder with id {id}"),
+        }),
+    )
+        .into_response()
+}
@@ -0,0 +1,80 @@
+use axum::extract::{Path, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::Json;
+use serde::Serialize;
+
+use crate::scheduler::ScheduledCall;
+use crate::AppState;
+
+use super::outbound::authenticate;
+
+#[derive(Debug, Serialize)]
+struct ScheduledResponse {
+    calls: Vec<ScheduledCall>,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusResponse {
+    status: String,
+}
+
+#[derive(Debug, Serialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+/// GET /api/scheduled — Calls deferred by quiet hours, soonest first.
+///
+/// Requires `Authorization: Bearer <token>` header. A tenant token only
+/// sees that tenant's calls.
+pub async fn handle_list(State(state): State<AppState>, headers: HeaderMap) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    let tenant_name = tenant.as_ref().map(|t| t.name.as_str());
+    let calls = state
+        .scheduler
+        .pending()
+        .await
+        .into_iter()
+        .filter(|call| tenant_name.is_none() || call.tenant.as_deref() == tenant_name)
+        .collect();
+    Json(ScheduledResponse { calls }).into_response()
+}
+
+/// DELETE /api/scheduled/{id} — Cancel a deferred call.
+pub async fn handle_cancel(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> impl IntoResponse {
+    let tenant = match authenticate(&headers, &state) {
+        Ok(tenant) => tenant,
+        Err(resp) => return resp,
+    };
+    let owned = match tenant {
+        Some(ref tenant) => state
+            .scheduler
+            .pending()
+            .await
+            .iter()
+            .any(|call| call.id == id && call.tenant.as_deref() == Some(tenant.name.as_str())),
+        None => true,
+    };
+    if !owned || state.scheduler.cancel(&id).await.is_none() {
+        return (
+            StatusCode::NOT_FOUND,
+            Json(ErrorResponse {
+                error: format!("No scheduled call with id {id}"),
+            }),
+        )
+            .into_response();
+    }
+    tracing::info!(id = %id, "Scheduled call cancelled");
+    Json(StatusResponse {
+        status: "cancelled".to_string(),
+    })
+    .into_response()
+}
@@ -0,0 +1,317 @@
+//! Circuit breakers for external providers.
+//!
+//! Each provider client (Groq, Inworld, Twilio REST, bridge-echo) owns a
+//! breaker. When the error rate over a sliding window crosses the configured
+//! threshold, the breaker opens and requests fail immediately instead of
+//! stacking timeouts across every concurrent call. With
+//! `consecutive_failures` set, that many failures in a row open it too, so a
+//! provider that's plainly down is caught before the window fills. After a
+//! cool-down a few
+//! half-open probe requests are let through; a successful probe closes the
+//! breaker, a failed one reopens it.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::BreakerConfig;
+
+/// Current breaker state.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BreakerState {
+    /// Requests flow normally.
+    Closed,
+    /// Requests are rejected until the cool-down elapses.
+    Open,
+    /// A limited number of probe requests are allowed through.
+    HalfOpen,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("{name} circuit open, failing fast")]
+pub struct BreakerOpen {
+    pub name: String,
+}
+
+pub struct CircuitBreaker {
+    name: String,
+    config: BreakerConfig,
+    inner: Mutex<Inner>,
+}
+
+struct Inner {
+    state: BreakerState,
+    opened_at: Option<Instant>,
+    probes_in_flight: usize,
+    /// (finished_at, success) for requests completed while closed.
+    outcomes: VecDeque<(Instant, bool)>,
+    /// Failures in a row while closed.
+    streak: usize,
+}
+
+/// Permission to make one request. Report the outcome with [`finish`];
+/// dropping the permit unfinished (e.g. the request was cancelled by a
+/// timeout) counts as a failure.
+///
+/// [`finish`]: BreakerPermit::finish
+pub struct BreakerPermit<'a> {
+    breaker: &'a CircuitBreaker,
+    probe: bool,
+    done: bool,
+}
+
+impl BreakerPermit<'_> {
+    /// Record whether the request succeeded.
+    pub fn finish(mut self, success: bool) {
+        self.done = true;
+        self.breaker.record(self.probe, success, Instant::now());
+    }
+}
+
+impl Drop for BreakerPermit<'_> {
+    fn drop(&mut self) {
+        if !self.done {
+            self.breaker.record(self.probe, false, Instant::now());
+        }
+    }
+}
+
+impl CircuitBreaker {
+    pub fn new(name: impl Into<String>, config: &BreakerConfig) -> Self {
+        Self {
+            name: name.into(),
+            config: config.clone(),
+            inner: Mutex::new(Inner {
+                state: BreakerState::Closed,
+                opened_at: None,
+                probes_in_flight: 0,
+                outcomes: VecDeque::new(),
+                streak: 0,
+            }),
+        }
+    }
+
+    /// Provider name, as used in logs.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Ask to make a request. Fails fast while the breaker is open.
+    pub fn check(&self) -> Result<BreakerPermit<'_>, BreakerOpen> {
+        self.check_at(Instant::now())
+    }
+
+    /// Current state, accounting for an elapsed cool-down.
+    pub fn state(&self) -> BreakerState {
+        let inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::Open if self.cooled_down(&inner, Instant::now()) => {
+                BreakerState::HalfOpen
+            }
+            state => state,
+        }
+    }
+
+    fn cooled_down(&self, inner: &Inner, now: Instant) -> bool {
+        inner
+            .opened_at
+            .is_some_and(|at| now >= at + Duration::from_secs(self.config.open_secs))
+    }
+
+    fn check_at(&self, now: Instant) -> Result<BreakerPermit<'_>, BreakerOpen> {
+        if !self.config.enabled {
+            return Ok(self.permit(false));
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        if inner.state == BreakerState::Open && self.cooled_down(&inner, now) {
+            tracing::info!(provider = %self.name, "Circuit half-open, probing");
+            inner.state = BreakerState::HalfOpen;
+            inner.probes_in_flight = 0;
+        }
+
+        match inner.state {
+            BreakerState::Closed => Ok(self.permit(false)),
+            BreakerState::HalfOpen if inner.probes_in_flight < self.config.half_open_probes => {
+                inner.probes_in_flight += 1;
+                Ok(self.permit(true))
+            }
+            _ => Err(BreakerOpen {
+                name: self.name.clone(),
+            }),
+        }
+    }
+
+    fn permit(&self, probe: bool) -> BreakerPermit<'_> {
+        BreakerPermit {
+            breaker: self,
+            probe,
+            done: false,
+        }
+    }
+
+    fn record(&self, probe: bool, success: bool, now: Instant) {
+        if !self.config.enabled {
+            return;
+        }
+
+        let mut inner = self.inner.lock().unwrap();
+        match inner.state {
+            BreakerState::HalfOpen if probe => {
+                inner.probes_in_flight = inner.probes_in_flight.saturating_sub(1);
+                if success {
+                    tracing::info!(provider = %self.name, "Circuit closed");
+                    inner.state = BreakerState::Closed;
+                    inner.opened_at = None;
+                    inner.outcomes.clear();
+                    inner.streak = 0;
+                } else {
+                    tracing::warn!(provider = %self.name, "Probe failed, circuit reopened");
+                    inner.state = BreakerState::Open;
+                    inner.opened_at = Some(now);
+                }
+            }
+            BreakerState::Closed => {
+                inner.outcomes.push_back((now, success));
+                inner.streak = if success { 0 } else { inner.streak + 1 };
+                let window = Duration::from_secs(self.config.window_secs);
+                while inner
+                    .outcomes
+                    .front()
+                    .is_some_and(|(at, _)| now.duration_since(*at) > window)
+                {
+                    inner.outcomes.pop_front();
+                }
+
+                let total = inner.outcomes.len();
+                let failures = inner.outcomes.iter().filter(|(_, ok)| !ok).count();
+                let streak = self.config.consecutive_failures > 0
+                    && inner.streak >= self.config.consecutive_failures;
+                if streak
+                    || total >= self.config.min_requests
+                        && failures as f64 / total as f64 >= self.config.failure_rate
+                {
+                    tracing::warn!(
+                        provider = %self.name,
+                        failures,
+                        total,
+                        in_a_row = inner.streak,
+                        open_secs = self.config.open_secs,
+                        "Circuit opened"
+                    );
+                    inner.state = BreakerState::Open;
+                    inner.opened_at = Some(now);
+                    inner.outcomes.clear();
+                    inner.streak = 0;
+                }
+            }
+            // Late results from requests admitted before the breaker opened
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> BreakerConfig {
+        BreakerConfig {
+            enabled: true,
+            failure_rate: 0.5,
+            min_requests: 4,
+            window_secs: 30,
+            open_secs: 10,
+            half_open_probes: 1,
+            consecutive_failures: 0,
+        }
+    }
+
+    #[test]
+    fn opens_after_error_rate_exceeded() {
+        let breaker = CircuitBreaker::new("test", &config());
+        let now = Instant::now();
+        for ok in [true, false, true, false] {
+            breaker.check_at(now).unwrap().finish(ok);
+        }
+        assert_eq!(breaker.state(), BreakerState::Open);
+        assert!(breaker.check_at(now).is_err());
+    }
+
+    #[test]
+    fn stays_closed_below_min_requests() {
+        let breaker = CircuitBreaker::new("test", &config());
+        let now = Instant::now();
+        for _ in 0..3 {
+            breaker.record(false, false, now);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn opens_after_consecutive_failures() {
+        let breaker = CircuitBreaker::new(
+            "test",
+            &BreakerConfig {
+                min_requests: 100,
+                consecutive_failures: 3,
+                ..config()
+            },
+        );
+        let now = Instant::now();
+        // A success in between starts the count over
+        for ok in [false, false, true, false, false] {
+            breaker.record(false, ok, now);
+        }
+        assert_eq!(breaker.state(), BreakerState::Closed);
+        breaker.record(false, false, now);
+        assert_eq!(breaker.state(), BreakerState::Open);
+    }
+
+    #[test]
+    fn half_open_probe_closes_on_success() {
+        let breaker = CircuitBreaker::new("test", &config());
+        let now = Instant::now();
+        for _ in 0..4 {
+            breaker.record(false, false, now);
+        }
+        let later = now + Duration::from_secs(11);
+        let probe = breaker.check_at(later).unwrap();
+        // Only one probe at a time
+        assert!(breaker.check_at(later).is_err());
+        probe.finish(true);
+        assert_eq!(breaker.state(), BreakerState::Closed);
+    }
+
+    #[test]
+    fn dropped_probe_counts_as_failure() {
+        let breaker = CircuitBreaker::new("test", &config());
+        let now = Instant::now();
+        for _ in 0..4 {
+            breaker.record(false, false, now);
+        }
+        let later = now + Duration::from_secs(11);
+        drop(breaker.check_at(later).unwrap());
+        // Reopened with a fresh cool-down
+        assert_eq!(breaker.state(), BreakerState::Open);
+    }
+
+    #[test]
+    fn disabled_breaker_never_opens() {
+        let breaker = CircuitBreaker::new(
+            "test",
+            &BreakerConfig {
+                enabled: false,
+                ..config()
+            },
+        );
+        let now = Instant::now();
+        for _ in 0..10 {
+            breaker.record(false, false, now);
+        }
+        assert!(breaker.check_at(now).is_ok());
+    }
+}
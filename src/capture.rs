@@ -0,0 +1,490 @@
+//! Call capture (`[capture]`) and replay (`voice-echo --replay`).
+//!
+//! With `[capture] dir` set, every inbound WebSocket text message of a
+//! Twilio or Discord call is appended to `<dir>/<call_sid>.jsonl` as it
+//! arrives, with its offset from the start of the call. Account SIDs,
+//! tokens and other secret fields are redacted first. A stream resumed
+//! after pressing keys carries on in the same file.
+//!
+//! The first line names the transport and when the call started
+//! (`{"transport":"twilio","started_at_ms":N}`); each following line is
+//! `{"at_ms":N,"message":{...}}`. `replay` sends the messages back at the
+//! same offsets to a running server and collects what it answers, so a
+//! call that went wrong can be re-run against mock or real providers.
+//! `voice-echo --retranscribe` runs the caller's audio back through STT
+//! instead (see [`crate::retranscribe`]).
+
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::Value;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader, BufWriter};
+use tokio::time::Instant;
+use tokio_tungstenite::tungstenite::Message;
+
+use crate::pipeline::audio;
+use crate::registry::Transport;
+
+/// How long `replay` keeps listening after the last message if the server
+/// doesn't close the stream.
+const REPLAY_DRAIN: Duration = Duration::from_secs(10);
+
+/// Field names (matched case-insensitively) whose string values are
+/// redacted.
+const SECRET_FIELDS: &[&str] = &[
+    "accountsid",
+    "authtoken",
+    "auth_token",
+    "token",
+    "apikey",
+    "api_key",
+    "secret",
+    "password",
+];
+
+const REDACTED: &str = "[redacted]";
+
+/// Messages kept while the stream has no call SID yet, to name the file.
+/// The SID comes with the first message or two; a stream that sends more
+/// without one isn't captured.
+const MAX_PENDING: usize = 16;
+
+/// The inbound messages of one call, appended to its file as they arrive.
+pub struct Capture {
+    transport: Transport,
+    dir: PathBuf,
+    started: Instant,
+    /// When the stream started, in Unix milliseconds.
+    started_at_ms: u64,
+    /// Recorded before the call SID was known, with their offsets.
+    pending: Vec<(u64, Value)>,
+    /// Open once the call SID is known, until writing fails.
+    file: Option<Open>,
+    /// The call SID couldn't be had, or the file couldn't be written.
+    failed: bool,
+}
+
+struct Open {
+    call_sid: String,
+    path: PathBuf,
+    writer: BufWriter<tokio::fs::File>,
+    /// Where this stream starts in the call: 0 unless it was resumed.
+    offset_ms: u64,
+}
+
+impl Capture {
+    pub fn new(transport: Transport, dir: impl Into<PathBuf>) -> Self {
+        Self {
+            transport,
+            dir: dir.into(),
+            started: Instant::now(),
+            started_at_ms: chrono::Utc::now().timestamp_millis().max(0) as u64,
+            pending: Vec::new(),
+            file: None,
+            failed: false,
+        }
+    }
+
+    /// Record one inbound text message. Anything that isn't JSON is kept
+    /// as a string.
+    pub async fn record(&mut self, text: &str) {
+        if self.failed {
+            return;
+        }
+        let mut message =
+            serde_json::from_str(text).unwrap_or_else(|_| Value::String(text.to_string()));
+        redact(&mut message);
+        let at_ms = self.started.elapsed().as_millis() as u64;
+        if self.file.is_none() {
+            if self.pending.len() == MAX_PENDING {
+                tracing::warn!("No call SID on the stream, not capturing it");
+                self.pending = Vec::new();
+                self.failed = true;
+            } else {
+                self.pending.push((at_ms, message));
+            }
+            return;
+        }
+        self.write(at_ms, &message).await;
+    }
+
+    /// Start writing to `<dir>/<call_sid>.jsonl`, appending if the call
+    /// already has one (a resumed stream). Messages recorded so far go in
+    /// first.
+    pub async fn open(&mut self, call_sid: &str) {
+        if self.failed || self.file.is_some() || call_sid.is_empty() {
+            return;
+        }
+        let path = self.dir.join(format!("{}.jsonl", file_stem(call_sid)));
+        match open(&path, self.transport, self.started_at_ms).await {
+            Ok((writer, offset_ms)) => {
+                self.file = Some(Open {
+                    call_sid: call_sid.to_string(),
+                    path,
+                    writer,
+                    offset_ms,
+                });
+            }
+            Err(e) => {
+                tracing::error!(call_sid, path = %path.display(), "Failed to open call capture: {e}");
+                self.pending = Vec::new();
+                self.failed = true;
+                return;
+            }
+        }
+        for (at_ms, message) in std::mem::take(&mut self.pending) {
+            self.write(at_ms, &message).await;
+        }
+    }
+
+    async fn write(&mut self, at_ms: u64, message: &Value) {
+        let Some(ref mut file) = self.file else {
+            return;
+        };
+        let mut line = serde_json::json!({
+            "at_ms": file.offset_ms + at_ms,
+            "message": message,
+        })
+        .to_string();
+        line.push('\n');
+        if let Err(e) = file.writer.write_all(line.as_bytes()).await {
+            tracing::error!(call_sid = %file.call_sid, "Failed to write call capture: {e}");
+            self.file = None;
+            self.failed = true;
+        }
+    }
+
+    /// Flush what's left once the stream ends. Streams that never got a
+    /// call SID leave nothing.
+    pub async fn finish(mut self) {
+        let Some(ref mut file) = self.file else {
+            return;
+        };
+        match file.writer.flush().await {
+            Ok(()) => tracing::info!(
+                call_sid = %file.call_sid,
+                path = %file.path.display(),
+                "Saved call capture"
+            ),
+            Err(e) => {
+                tracing::error!(call_sid = %file.call_sid, "Failed to save call capture: {e}")
+            }
+        }
+    }
+}
+
+/// Open the capture at `path` for appending, writing the header if it's
+/// new. Returns the writer and how far into the call a stream started at
+/// `started_at_ms` is, from the header's start time.
+async fn open(
+    path: &Path,
+    transport: Transport,
+    started_at_ms: u64,
+) -> std::io::Result<(BufWriter<tokio::fs::File>, u64)> {
+    if let Some(dir) = path.parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
+    let mut offset_ms = 0;
+    match tokio::fs::File::open(path).await {
+        Ok(existing) => {
+            let mut header = String::new();
+            BufReader::new(existing).read_line(&mut header).await?;
+            let started_at = serde_json::from_str::<Value>(&header)
+                .ok()
+                .and_then(|h| h["started_at_ms"].as_u64());
+            offset_ms = started_at.map_or(0, |at| started_at_ms.saturating_sub(at));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e),
+    }
+    let file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    let mut writer = BufWriter::new(file);
+    if writer.get_ref().metadata().await?.len() == 0 {
+        let mut header = serde_json::json!({
+            "transport": transport_name(transport),
+            "started_at_ms": started_at_ms,
+        })
+        .to_string();
+        header.push('\n');
+        writer.write_all(header.as_bytes()).await?;
+    }
+    Ok((writer, offset_ms))
+}
+
+fn transport_name(transport: Transport) -> &'static str {
+    match transport {
+        Transport::Twilio => "twilio",
+        Transport::Discord => "discord",
+    }
+}
+
+/// `call_sid` made safe for a file name (`discord:123` → `discord_123`).
+fn file_stem(call_sid: &str) -> String {
+    call_sid
+        .chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Replace the string values of secret fields, at any depth.
+fn redact(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            for (key, field) in map.iter_mut() {
+                let key = key.to_ascii_lowercase();
+                if field.is_string() && SECRET_FIELDS.contains(&key.as_str()) {
+                    *field = Value::from(REDACTED);
+                } else {
+                    redact(field);
+                }
+            }
+        }
+        Value::Array(items) => items.iter_mut().for_each(redact),
+        _ => {}
+    }
+}
+
+/// The caller's audio in the capture at `path`, as one run of mu-law from
+/// the start of the stream: Twilio `media` payloads or Discord `audio`
+/// frames, with silence where nothing arrived for a while.
+pub async fn inbound_audio(
+    path: &Path,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    let mut mulaw = Vec::new();
+    // Skip the header
+    for line in contents.lines().filter(|l| !l.trim().is_empty()).skip(1) {
+        let entry: Value = serde_json::from_str(line)?;
+        let message = &entry["message"];
+        let payload = match message["event"].as_str() {
+            Some("media") => message["media"]["payload"].as_str(),
+            _ => message["audio"].as_str(),
+        };
+        let Some(payload) = payload else {
+            continue;
+        };
+        let at = entry["at_ms"].as_u64().unwrap_or(0) as usize * 8;
+        if at > mulaw.len() {
+            mulaw.resize(at, audio::pcm_to_mulaw(0));
+        }
+        mulaw.extend(base64::engine::general_purpose::STANDARD.decode(payload)?);
+    }
+    Ok(mulaw)
+}
+
+/// A message the server sent during a replay.
+#[derive(Debug)]
+pub struct Reply {
+    pub at_ms: u64,
+    pub message: Value,
+}
+
+/// Replay the capture at `path` against the server at `base_url`
+/// (`ws://host:port`), at the original pace. Returns what the server sent.
+pub async fn replay(
+    base_url: &str,
+    path: &Path,
+) -> Result<Vec<Reply>, Box<dyn std::error::Error + Send + Sync>> {
+    let contents = tokio::fs::read_to_string(path).await?;
+    let mut lines = contents.lines().filter(|l| !l.trim().is_empty());
+    let header: Value = serde_json::from_str(lines.next().ok_or("empty capture")?)?;
+    let route = match header["transport"].as_str() {
+        Some("twilio") => "/twilio/media",
+        Some("discord") => "/discord-stream",
+        other => return Err(format!("unknown capture transport {other:?}").into()),
+    };
+    let messages = lines
+        .map(|line| {
+            let entry: Value = serde_json::from_str(line)?;
+            let at = Duration::from_millis(entry["at_ms"].as_u64().unwrap_or(0));
+            let text = match &entry["message"] {
+                Value::String(text) => text.clone(),
+                message => message.to_string(),
+            };
+            Ok((at, text))
+        })
+        .collect::<Result<Vec<_>, serde_json::Error>>()?;
+
+    let (ws, _) = tokio_tungstenite::connect_async(format!("{base_url}{route}")).await?;
+    let (mut sink, mut stream) = ws.split();
+    let started = Instant::now();
+    let mut pending = messages.into_iter().peekable();
+    // Once everything is sent, wait this long for the server to finish
+    // (it closes the stream itself after a stop or leave)
+    let mut drain_until = None;
+    let mut replies = Vec::new();
+
+    loop {
+        let wake = match pending.peek() {
+            Some((at, _)) => started + *at,
+            None => *drain_until.get_or_insert_with(|| Instant::now() + REPLAY_DRAIN),
+        };
+        tokio::select! {
+            message = stream.next() => match message {
+                Some(Ok(Message::Text(text))) => replies.push(Reply {
+                    at_ms: started.elapsed().as_millis() as u64,
+                    message: serde_json::from_str(text.as_str())
+                        .unwrap_or_else(|_| Value::String(text.to_string())),
+                }),
+                Some(Ok(_)) => {}
+                Some(Err(_)) | None => break,
+            },
+            _ = tokio::time::sleep_until(wake) => match pending.next() {
+                Some((_, text)) => sink.send(Message::Text(text.into())).await?,
+                None => break,
+            },
+        }
+    }
+    let _ = sink.close().await;
+    Ok(replies)
+}
+
+/// One line per reply, with consecutive audio merged into its duration:
+/// `   1.24s  audio 480ms`.
+pub fn summarize(replies: &[Reply]) -> Vec<String> {
+    let line = |at_ms: u64, what: &str| format!("{:>7.2}s  {what}", at_ms as f64 / 1000.0);
+    let mut lines = Vec::new();
+    // Start and byte count of the audio run in progress
+    let mut audio: Option<(u64, usize)> = None;
+    for reply in replies {
+        let message = &reply.message;
+        let payload = message["media"]["payload"]
+            .as_str()
+            .or_else(|| message["audio"].as_str());
+        if let Some(payload) = payload {
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(payload)
+                .map_or(0, |b| b.len());
+            audio.get_or_insert((reply.at_ms, 0)).1 += bytes;
+            continue;
+        }
+        if let Some((at_ms, bytes)) = audio.take() {
+            lines.push(line(at_ms, &format!("audio {}ms", bytes / 8)));
+        }
+        let kind = message["event"]
+            .as_str()
+            .or_else(|| message["type"].as_str())
+            .unwrap_or("?");
+        let what = match message["mark"]["name"].as_str() {
+            Some(name) => format!("{kind} {name}"),
+            None => kind.to_string(),
+        };
+        lines.push(line(reply.at_ms, &what));
+    }
+    if let Some((at_ms, bytes)) = audio {
+        lines.push(line(at_ms, &format!("audio {}ms", bytes / 8)));
+    }
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn redacts_secret_fields() {
+        let mut start = serde_json::json!({
+            "event": "start",
+            "start": {
+                "accountSid": "AC123",
+                "callSid": "CA123",
+                "customParameters": { "agent": "echo", "api_key": "k", "keyword": "k" },
+            },
+            "media": { "payload": "/38A" },
+        });
+        redact(&mut start);
+        assert_eq!(start["start"]["accountSid"], REDACTED);
+        assert_eq!(start["start"]["customParameters"]["api_key"], REDACTED);
+        assert_eq!(start["start"]["callSid"], "CA123");
+        assert_eq!(start["start"]["customParameters"]["agent"], "echo");
+        assert_eq!(start["start"]["customParameters"]["keyword"], "k");
+        assert_eq!(start["media"]["payload"], "/38A");
+    }
+
+    #[tokio::test]
+    async fn resumed_stream_appends_to_the_call() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let start = r#"{"event":"start","start":{"callSid":"CA1","accountSid":"AC1"}}"#;
+        let stop = r#"{"event":"stop"}"#;
+
+        let mut first = Capture::new(Transport::Twilio, dir);
+        first.record(r#"{"event":"connected"}"#).await;
+        first.record(start).await;
+        first.open("CA1").await;
+        first.record(stop).await;
+        first.finish().await;
+
+        let mut resumed = Capture::new(Transport::Twilio, dir);
+        resumed.started_at_ms += 2000;
+        resumed.record(start).await;
+        resumed.open("CA1").await;
+        resumed.finish().await;
+
+        let contents = std::fs::read_to_string(dir.join("CA1.jsonl")).unwrap();
+        let lines: Vec<Value> = contents
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 5);
+        assert_eq!(lines[0]["transport"], "twilio");
+        assert_eq!(lines[2]["message"]["start"]["accountSid"], REDACTED);
+        assert_eq!(lines[3]["message"]["event"], "stop");
+        // The resumed stream's offsets carry on from the call's start
+        assert_eq!(lines[4]["message"]["event"], "start");
+        assert!(lines[4]["at_ms"].as_u64().unwrap() >= 2000);
+    }
+
+    #[tokio::test]
+    async fn stream_without_a_call_sid_is_not_kept() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path().join("captures");
+        let mut capture = Capture::new(Transport::Discord, &dir);
+        for _ in 0..=MAX_PENDING {
+            capture.record(r#"{"type":"audio","audio":"/38A"}"#).await;
+        }
+        assert!(capture.pending.is_empty());
+        capture.open("discord:1").await;
+        capture.finish().await;
+        assert!(!dir.exists());
+    }
+
+    #[test]
+    fn summary_merges_audio() {
+        let reply = |at_ms, message| Reply { at_ms, message };
+        let payload = base64::engine::general_purpose::STANDARD.encode([0xff; 160]);
+        let media = serde_json::json!({ "event": "media", "media": { "payload": payload } });
+        let replies = [
+            reply(100, media.clone()),
+            reply(120, media),
+            reply(
+                130,
+                serde_json::json!({ "event": "mark", "mark": { "name": "response_end" } }),
+            ),
+            reply(1500, serde_json::json!({ "event": "clear" })),
+        ];
+        assert_eq!(
+            summarize(&replies),
+            [
+                "   0.10s  audio 40ms",
+                "   0.13s  mark response_end",
+                "   1.50s  clear"
+            ]
+        );
+    }
+
+    #[test]
+    fn file_stem_is_path_safe() {
+        assert_eq!(file_stem("discord:123/../x"), "discord_123____x");
+        assert_eq!(file_stem("CA1-b_2"), "CA1-b_2");
+    }
+}
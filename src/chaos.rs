@@ -0,0 +1,143 @@
+//! Fault injection for resilience testing (`[chaos]`).
+//!
+//! With `[chaos] enabled = true`, each provider request attempt (Groq,
+//! Inworld, bridge-echo or the local brain, and their mocks) can be delayed,
+//! failed, or have its response trickle in, per `[chaos.stt]`,
+//! `[chaos.tts]` and `[chaos.brain]`. Faults land inside the retry loops and
+//! circuit breakers, so those react as they would to a real outage; the
+//! fallback brains are never faulted, so failover can be watched
+//! taking over. Seeded by `[debug] seed` for repeatable runs.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+use crate::config::FaultConfig;
+use crate::rng::SharedRng;
+
+/// An error the injector made up in place of a provider's.
+#[derive(Debug, thiserror::Error)]
+#[error("injected {0} fault")]
+pub struct InjectedFault(&'static str);
+
+/// Faults for one provider stage. The default injects nothing.
+#[derive(Default)]
+pub struct FaultInjector {
+    /// `None` when off, so unfaulted requests skip the lock.
+    faults: Option<(&'static str, FaultConfig, SharedRng)>,
+}
+
+impl FaultInjector {
+    /// Inject `config`'s faults into `stage` ("stt", "tts" or "brain"),
+    /// seeded with `seed` when set.
+    pub fn new(stage: &'static str, config: &FaultConfig, seed: Option<u64>) -> Self {
+        if !config.any() {
+            return Self::default();
+        }
+        // Each stage gets its own sequence from one seed
+        let seed = seed.map(|seed| {
+            stage
+                .bytes()
+                .fold(seed, |h, b| (h ^ b as u64).wrapping_mul(0x100_0000_01b3))
+        });
+        Self {
+            faults: Some((stage, config.clone(), SharedRng::new(seed))),
+        }
+    }
+
+    /// Before an attempt: wait the added latency, then fail at the error rate.
+    pub async fn before(&self) -> Result<(), InjectedFault> {
+        let Some((stage, ref config, ref rng)) = self.faults else {
+            return Ok(());
+        };
+        let (jitter, fail) = rng.with(|rng| {
+            (
+                rng.gen_range(0..=config.jitter_ms),
+                rng.gen_bool(config.error_rate.clamp(0.0, 1.0)),
+            )
+        });
+        let latency = Duration::from_millis(config.latency_ms + jitter);
+        if !latency.is_zero() {
+            tokio::time::sleep(latency).await;
+        }
+        if fail {
+            tracing::debug!(stage, "Injecting a fault");
+            return Err(InjectedFault(stage));
+        }
+        Ok(())
+    }
+
+    /// After a successful attempt: hold a `bytes` long response back as if
+    /// it arrived at the drip rate.
+    pub async fn drip(&self, bytes: usize) {
+        let Some((_, ref config, _)) = self.faults else {
+            return;
+        };
+        if config.drip_bytes_per_sec > 0 {
+            let secs = bytes as f64 / config.drip_bytes_per_sec as f64;
+            tokio::time::sleep(Duration::from_secs_f64(secs)).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Instant;
+
+    use super::*;
+
+    fn faults(toml: &str) -> FaultConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn default_injects_nothing() {
+        let chaos = FaultInjector::new("stt", &faults(""), None);
+        let started = Instant::now();
+        for _ in 0..100 {
+            chaos.before().await.unwrap();
+            chaos.drip(1_000_000).await;
+        }
+        assert!(started.elapsed() < Duration::from_millis(50));
+    }
+
+    #[tokio::test]
+    async fn error_rate_extremes() {
+        let always = FaultInjector::new("tts", &faults("error_rate = 1.0"), None);
+        let err = always.before().await.unwrap_err();
+        assert_eq!(err.to_string(), "injected tts fault");
+        let never = FaultInjector::new("tts", &faults("latency_ms = 1"), None);
+        for _ in 0..100 {
+            never.before().await.unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn seeded_faults_repeat() {
+        let pattern = |stage, seed| async move {
+            let chaos = FaultInjector::new(stage, &faults("error_rate = 0.5"), Some(seed));
+            let mut failed = Vec::new();
+            for _ in 0..64 {
+                failed.push(chaos.before().await.is_err());
+            }
+            failed
+        };
+        assert_eq!(pattern("brain", 3).await, pattern("brain", 3).await);
+        assert_ne!(pattern("brain", 3).await, pattern("stt", 3).await);
+    }
+
+    #[tokio::test]
+    async fn latency_and_drip_delay() {
+        let chaos = FaultInjector::new(
+            "brain",
+            &faults("latency_ms = 30\ndrip_bytes_per_sec = 1000"),
+            None,
+        );
+        let started = Instant::now();
+        chaos.before().await.unwrap();
+        assert!(started.elapsed() >= Duration::from_millis(30));
+        let started = Instant::now();
+        chaos.drip(50).await;
+        assert!(started.elapsed() >= Duration::from_millis(50));
+    }
+}
@@ -1,154 +1,2423 @@
+use regex::Regex;
 use serde::Deserialize;
+use std::collections::HashMap;
 use std::path::PathBuf;
+use std::time::Duration;
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct Config {
     pub server: ServerConfig,
     pub twilio: TwilioConfig,
+    #[serde(default)]
+    pub stt: SttConfig,
+    #[serde(default)]
     pub groq: GroqConfig,
+    #[serde(default)]
+    pub deepgram: DeepgramConfig,
+    #[serde(default)]
+    pub assemblyai: AssemblyAiConfig,
+    #[serde(default)]
+    pub azure: AzureSpeechConfig,
+    #[serde(default)]
     pub inworld: InworldConfig,
+    #[serde(default)]
+    pub elevenlabs: ElevenLabsConfig,
     #[serde(alias = "claude")]
     pub llm: LlmConfig,
+    #[serde(default)]
+    pub brain: BrainConfig,
+    #[serde(default)]
+    pub openai: OpenAiConfig,
+    #[serde(default)]
+    pub ollama: OllamaConfig,
+    #[serde(default)]
+    pub anthropic: AnthropicConfig,
+    #[serde(default)]
+    pub gemini: GeminiConfig,
+    #[serde(default)]
+    pub grok: GrokConfig,
+    #[serde(default)]
+    pub local_llm: Option<LocalLlmConfig>,
     pub vad: VadConfig,
     #[serde(default)]
-    pub api: ApiConfig,
+    pub api: ApiConfig,
+    #[serde(default)]
+    pub hold_music: Option<HoldMusicConfig>,
+    #[serde(default)]
+    pub hold_announcements: HoldAnnouncementsConfig,
+    #[serde(default)]
+    pub identity: IdentityConfig,
+    #[serde(default)]
+    pub greetings: GreetingsConfig,
+    #[serde(default)]
+    pub pipeline: PipelineConfig,
+    #[serde(default)]
+    pub timeouts: TimeoutsConfig,
+    #[serde(default)]
+    pub breaker: BreakerConfig,
+    #[serde(default)]
+    pub degraded: DegradedConfig,
+    #[serde(default)]
+    pub keepalive: KeepaliveConfig,
+    #[serde(default)]
+    pub audio: AudioConfig,
+    #[serde(default)]
+    pub http: HttpConfig,
+    #[serde(default)]
+    pub reaper: ReaperConfig,
+    #[serde(default)]
+    pub preflight: PreflightConfig,
+    #[serde(default)]
+    pub warmup: WarmupConfig,
+    #[serde(default)]
+    pub agents: Vec<AgentConfig>,
+    #[serde(default)]
+    pub tenants: Vec<TenantConfig>,
+    #[serde(default)]
+    pub contacts: Vec<ContactConfig>,
+    #[serde(default)]
+    pub outbound: OutboundConfig,
+    #[serde(default)]
+    pub reminders: RemindersConfig,
+    #[serde(default)]
+    pub memory: MemoryConfig,
+    #[serde(default)]
+    pub publish: Option<PublishConfig>,
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+    #[serde(default)]
+    pub language: LanguageConfig,
+    #[serde(default)]
+    pub tts: TtsConfig,
+    #[serde(default)]
+    pub diarization: Option<DiarizationConfig>,
+    #[serde(default)]
+    pub actions: Option<ActionsConfig>,
+    #[serde(default)]
+    pub concise: Option<ConciseConfig>,
+    #[serde(default)]
+    pub filter: Option<FilterConfig>,
+    #[serde(default)]
+    pub profanity: ProfanityConfig,
+    #[serde(default)]
+    pub privacy: PrivacyConfig,
+    #[serde(default)]
+    pub prompt: PromptConfig,
+    #[serde(default)]
+    pub mocks: MocksConfig,
+    #[serde(default)]
+    pub capture: CaptureConfig,
+    #[serde(default)]
+    pub debug: DebugConfig,
+    #[serde(default)]
+    pub chaos: ChaosConfig,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub external_url: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TwilioConfig {
+    pub account_sid: String,
+    pub auth_token: String,
+    pub phone_number: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct GroqConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_groq_model")]
+    pub model: String,
+    /// Shorthand for `[language]`: a code (`es`) sets `default`, `auto`
+    /// turns on `detect`.
+    #[serde(default)]
+    pub language: Option<String>,
+}
+
+impl Default for GroqConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_groq_model(),
+            language: None,
+        }
+    }
+}
+
+fn default_groq_model() -> String {
+    "whisper-large-v3-turbo".to_string()
+}
+
+/// `[stt]`: which vendor transcribes the calls.
+#[derive(Debug, Deserialize, Clone)]
+pub struct SttConfig {
+    #[serde(default)]
+    pub provider: SttVendor,
+    /// Vendor tried once `provider` has failed, retries and all.
+    #[serde(default)]
+    pub fallback: Option<SttVendor>,
+    /// Retries of connection failures, 429s and 5xx, per vendor.
+    #[serde(default = "default_stt_retries")]
+    pub retries: u32,
+    #[serde(default = "default_stt_retry_delay_ms")]
+    pub retry_delay_ms: u64,
+    /// Cap on the retry delay, which doubles per retry.
+    #[serde(default = "default_stt_retry_max_delay_ms")]
+    pub retry_max_delay_ms: u64,
+    /// Give up on one request after this long, so a stalled upload is
+    /// retried or falls back instead of using up `[timeouts] stt_ms`. 0
+    /// means no limit.
+    #[serde(default = "default_stt_request_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Domain terms (product names, people, hostnames) the vendor should
+    /// expect to hear.
+    #[serde(default)]
+    pub vocabulary: Vec<String>,
+    /// Prime the vendor with the call's last exchange, what the caller said
+    /// and what was answered, for names and follow-ups like "yes, that
+    /// one". Only Whisper (`groq`) takes it.
+    #[serde(default = "default_true")]
+    pub prime: bool,
+    /// Rewrite dictated values the way they're written before the brain
+    /// sees them: "four one five" as "415", "ana at gmail dot com" as
+    /// "ana@gmail.com". English only.
+    #[serde(default = "default_true")]
+    pub dictation: bool,
+    /// Stream Twilio calls' audio to the vendor as it arrives, instead of
+    /// uploading each utterance the VAD cuts. The vendor then decides where
+    /// utterances end. Needs a vendor with live sessions (`deepgram`);
+    /// others keep buffering.
+    #[serde(default)]
+    pub streaming: bool,
+    /// Transcripts the vendor is less sure of than this (0 to 1) aren't
+    /// answered; see `low_confidence`. Off when unset.
+    #[serde(default)]
+    pub min_confidence: Option<f32>,
+    #[serde(default)]
+    pub low_confidence: LowConfidence,
+    /// What's asked under `low_confidence = "confirm"`; `{transcript}` is
+    /// what was heard.
+    #[serde(default = "default_confirm_prompt")]
+    pub confirm_prompt: String,
+    /// Clean up utterances before they're uploaded (`[stt.preprocess]`).
+    #[serde(default)]
+    pub preprocess: Option<PreprocessConfig>,
+    /// Phone calls' model (`[stt.phone]`).
+    #[serde(default)]
+    pub phone: SttChannelConfig,
+    /// Discord sessions' model (`[stt.discord]`), e.g. a slower, more
+    /// accurate one where latency matters less.
+    #[serde(default)]
+    pub discord: SttChannelConfig,
+}
+
+/// `[stt.phone]` / `[stt.discord]`.
+#[derive(Debug, Deserialize, Clone, Default, PartialEq, Eq)]
+pub struct SttChannelConfig {
+    /// Model for `[stt] provider` on this channel, in place of its own
+    /// block's (`[groq] model`, `[deepgram] model`, `[assemblyai]
+    /// speech_model`). Azure Speech has none to pick. Unset keeps the
+    /// block's.
+    #[serde(default)]
+    pub model: Option<String>,
+}
+
+/// `[stt.preprocess]`: filtering and levelling of each utterance before
+/// STT, for quiet or noisy lines (car calls) Whisper hears nothing on.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PreprocessConfig {
+    /// High-pass cutoff removing rumble and hum; 0 turns it off.
+    #[serde(default = "default_preprocess_highpass_hz")]
+    pub highpass_hz: f64,
+    /// 20ms blocks quieter than this (dBFS RMS) are silenced. Off when
+    /// unset.
+    #[serde(default)]
+    pub gate_dbfs: Option<f64>,
+    /// Speech level to aim for, in dBFS RMS.
+    #[serde(default = "default_loudness_target_dbfs")]
+    pub target_dbfs: f64,
+    /// Most an utterance is turned up.
+    #[serde(default = "default_preprocess_max_gain_db")]
+    pub max_gain_db: f64,
+}
+
+fn default_preprocess_highpass_hz() -> f64 {
+    100.0
+}
+
+fn default_preprocess_max_gain_db() -> f64 {
+    24.0
+}
+
+/// What happens to a transcript below `[stt] min_confidence`.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LowConfidence {
+    /// Ask the caller whether that's what they said; their answer goes to
+    /// the brain with the doubtful transcript.
+    #[default]
+    Confirm,
+    /// Ignore it, as if nothing was said.
+    Drop,
+}
+
+impl Default for SttConfig {
+    fn default() -> Self {
+        Self {
+            provider: SttVendor::default(),
+            fallback: None,
+            retries: default_stt_retries(),
+            retry_delay_ms: default_stt_retry_delay_ms(),
+            retry_max_delay_ms: default_stt_retry_max_delay_ms(),
+            timeout_ms: default_stt_request_timeout_ms(),
+            vocabulary: Vec::new(),
+            prime: true,
+            dictation: true,
+            streaming: false,
+            min_confidence: None,
+            low_confidence: LowConfidence::default(),
+            confirm_prompt: default_confirm_prompt(),
+            preprocess: None,
+            phone: SttChannelConfig::default(),
+            discord: SttChannelConfig::default(),
+        }
+    }
+}
+
+fn default_confirm_prompt() -> String {
+    "Sorry, I didn't quite catch that. Did you say: {transcript}?".to_string()
+}
+
+fn default_stt_retries() -> u32 {
+    1
+}
+
+fn default_stt_request_timeout_ms() -> u64 {
+    5_000
+}
+
+fn default_stt_retry_delay_ms() -> u64 {
+    250
+}
+
+fn default_stt_retry_max_delay_ms() -> u64 {
+    1_000
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SttVendor {
+    /// Groq Whisper (`[groq]`).
+    #[default]
+    Groq,
+    /// Deepgram (`[deepgram]`).
+    Deepgram,
+    /// AssemblyAI (`[assemblyai]`).
+    AssemblyAi,
+    /// Azure Speech (`[azure]`).
+    Azure,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct DeepgramConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_deepgram_model")]
+    pub model: String,
+    #[serde(default = "default_deepgram_base_url")]
+    pub base_url: String,
+    /// Punctuate and format numbers, dates and the like.
+    #[serde(default = "default_true")]
+    pub smart_format: bool,
+    /// Silence that ends an utterance on live sessions (`[stt] streaming`).
+    #[serde(default = "default_deepgram_endpointing_ms")]
+    pub endpointing_ms: u64,
+}
+
+impl Default for DeepgramConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_deepgram_model(),
+            base_url: default_deepgram_base_url(),
+            smart_format: true,
+            endpointing_ms: default_deepgram_endpointing_ms(),
+        }
+    }
+}
+
+fn default_deepgram_endpointing_ms() -> u64 {
+    800
+}
+
+fn default_deepgram_model() -> String {
+    "nova-3".to_string()
+}
+
+fn default_deepgram_base_url() -> String {
+    "https://api.deepgram.com".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct AssemblyAiConfig {
+    #[serde(default)]
+    pub api_key: String,
+    /// `https://api.eu.assemblyai.com` keeps audio in the EU.
+    #[serde(default = "default_assemblyai_base_url")]
+    pub base_url: String,
+    /// `best`, `nano`, ...; AssemblyAI's default when unset.
+    #[serde(default)]
+    pub speech_model: Option<String>,
+    /// How often a queued transcript is checked on.
+    #[serde(default = "default_assemblyai_poll_ms")]
+    pub poll_ms: u64,
+}
+
+impl Default for AssemblyAiConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            base_url: default_assemblyai_base_url(),
+            speech_model: None,
+            poll_ms: default_assemblyai_poll_ms(),
+        }
+    }
+}
+
+fn default_assemblyai_base_url() -> String {
+    "https://api.assemblyai.com".to_string()
+}
+
+fn default_assemblyai_poll_ms() -> u64 {
+    200
+}
+
+/// `[azure]`: Azure Speech, for deployments whose audio must stay in one
+/// Azure region.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AzureSpeechConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_azure_region")]
+    pub region: String,
+    /// Replaces the region's endpoint (sovereign clouds, private links).
+    #[serde(default)]
+    pub endpoint: Option<String>,
+    /// Locale recognized when there's no language hint; Azure can't
+    /// detect it.
+    #[serde(default = "default_azure_language")]
+    pub language: String,
+    /// Neural voice with `[tts] provider = "azure"`.
+    #[serde(default = "default_azure_voice")]
+    pub voice: String,
+    /// Replaces the region's text-to-speech endpoint.
+    #[serde(default)]
+    pub tts_endpoint: Option<String>,
+}
+
+impl Default for AzureSpeechConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            region: default_azure_region(),
+            endpoint: None,
+            language: default_azure_language(),
+            voice: default_azure_voice(),
+            tts_endpoint: None,
+        }
+    }
+}
+
+fn default_azure_region() -> String {
+    "westeurope".to_string()
+}
+
+fn default_azure_language() -> String {
+    "en-US".to_string()
+}
+
+fn default_azure_voice() -> String {
+    "en-US-JennyNeural".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct InworldConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_voice_id")]
+    pub voice_id: String,
+    #[serde(default = "default_inworld_model")]
+    pub model: String,
+    /// Retries per failed chunk request before giving up on the chunk.
+    #[serde(default = "default_tts_chunk_retries")]
+    pub chunk_retries: u32,
+    /// Delay before the first retry; doubles on each further attempt.
+    #[serde(default = "default_tts_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for InworldConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            voice_id: default_voice_id(),
+            model: default_inworld_model(),
+            chunk_retries: default_tts_chunk_retries(),
+            retry_backoff_ms: default_tts_retry_backoff_ms(),
+        }
+    }
+}
+
+fn default_tts_chunk_retries() -> u32 {
+    2
+}
+
+fn default_tts_retry_backoff_ms() -> u64 {
+    200
+}
+
+fn default_voice_id() -> String {
+    "Olivia".to_string()
+}
+
+fn default_inworld_model() -> String {
+    "inworld-tts-1.5-max".to_string()
+}
+
+/// `[elevenlabs]`: ElevenLabs text-to-speech.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ElevenLabsConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_elevenlabs_voice_id")]
+    pub voice_id: String,
+    #[serde(default = "default_elevenlabs_model")]
+    pub model: String,
+    #[serde(default = "default_elevenlabs_base_url")]
+    pub base_url: String,
+    /// Retries per failed chunk request before giving up on the chunk.
+    #[serde(default = "default_tts_chunk_retries")]
+    pub chunk_retries: u32,
+    /// Delay before the first retry; doubles on each further attempt.
+    #[serde(default = "default_tts_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for ElevenLabsConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            voice_id: default_elevenlabs_voice_id(),
+            model: default_elevenlabs_model(),
+            base_url: default_elevenlabs_base_url(),
+            chunk_retries: default_tts_chunk_retries(),
+            retry_backoff_ms: default_tts_retry_backoff_ms(),
+        }
+    }
+}
+
+/// Rachel, one of ElevenLabs' stock voices.
+fn default_elevenlabs_voice_id() -> String {
+    "21m00Tcm4TlvDq8ikWAM".to_string()
+}
+
+fn default_elevenlabs_model() -> String {
+    "eleven_flash_v2_5".to_string()
+}
+
+fn default_elevenlabs_base_url() -> String {
+    "https://api.elevenlabs.io".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LlmConfig {
+    #[serde(default = "default_session_timeout")]
+    pub session_timeout_secs: u64,
+    #[serde(default)]
+    pub greeting: String,
+    #[serde(default = "default_name")]
+    pub name: String,
+    #[serde(default)]
+    pub self_path: Option<String>,
+    /// URL of bridge-echo multiplexer. When set, voice-echo forwards
+    /// transcripts to bridge-echo instead of spawning its own Claude Code process.
+    #[serde(default)]
+    pub bridge_url: Option<String>,
+    /// Per-request bridge-echo timeout. 0 disables it.
+    #[serde(default = "default_bridge_timeout_ms")]
+    pub bridge_timeout_ms: u64,
+    /// Retries for bridge requests that never reached bridge-echo.
+    #[serde(default = "default_bridge_retries")]
+    pub bridge_retries: u32,
+    #[serde(default = "default_bridge_retry_delay_ms")]
+    pub bridge_retry_delay_ms: u64,
+    /// Cap on the bridge retry delay, which doubles after each retry.
+    #[serde(default = "default_bridge_retry_max_delay_ms")]
+    pub bridge_retry_max_delay_ms: u64,
+    /// Send turns over a persistent WebSocket to bridge-echo's `/ws`.
+    #[serde(default)]
+    pub bridge_websocket: bool,
+    /// Stream responses from bridge-echo and speak them sentence by sentence.
+    #[serde(default)]
+    pub bridge_streaming: bool,
+    /// Shared secret identifying this instance to bridge-echo.
+    #[serde(default)]
+    pub bridge_token: Option<String>,
+    /// How `bridge_token` is presented: bearer header or HMAC signature.
+    #[serde(default)]
+    pub bridge_auth: BridgeAuthMode,
+    /// Per-call timeout for the local LLM provider. 0 disables it.
+    #[serde(default = "default_invoke_timeout_ms")]
+    pub invoke_timeout_ms: u64,
+    /// Retries for local provider calls that failed or timed out.
+    #[serde(default)]
+    pub invoke_retries: u32,
+    /// MCP server config bridge-echo passes to the Claude CLI on turns
+    /// granted tools.
+    #[serde(default)]
+    pub mcp_config: Option<String>,
+    /// Tools calls may use, unless `/api/call` grants others. Empty keeps
+    /// calls tool-free.
+    #[serde(default)]
+    pub allowed_tools: Vec<String>,
+    /// Max tokens for LLM responses. Short for voice (default: 1024).
+    #[serde(default = "default_max_response_tokens")]
+    pub max_response_tokens: u32,
+    /// Directory keeping the conversations of brains that hold them here
+    /// (all but bridge-echo) across restarts.
+    #[serde(default = "default_sessions_dir")]
+    pub sessions_dir: PathBuf,
+    /// Turns after which a brain holding the conversation here summarizes
+    /// it and carries on from the summary. 0 never does.
+    #[serde(default)]
+    pub summarize_after_turns: usize,
+    /// Brain to fall back to when the primary errors or times out.
+    #[serde(default)]
+    pub fallback: Option<FallbackConfig>,
+}
+
+/// A named persona (`[[agents]]`). Unset fields fall back to the top-level config.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AgentConfig {
+    /// Identifier used by the API and in logs, e.g. "alerts".
+    pub name: String,
+    /// Name spoken in greetings. Defaults to `llm.name`.
+    #[serde(default)]
+    pub display_name: Option<String>,
+    #[serde(default)]
+    pub voice_id: Option<String>,
+    /// Greeting pool; `{name}` is replaced with the display name.
+    #[serde(default)]
+    pub greetings: Vec<String>,
+    /// Locale of the `[greetings.pools]` entry used when `greetings` is empty.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Wraps the caller's words in local brain mode. Placeholders:
+    /// `{transcript}`, `{context}`, `{name}`.
+    #[serde(default)]
+    pub prompt_template: Option<String>,
+    /// System prompt file replacing `llm.self_path` (local brain mode).
+    #[serde(default)]
+    pub self_path: Option<String>,
+    /// Route this agent's turns to a different bridge-echo.
+    #[serde(default)]
+    pub bridge_url: Option<String>,
+    /// Twilio numbers (E.164) this agent answers.
+    #[serde(default)]
+    pub phone_numbers: Vec<String>,
+    /// Discord guild IDs this agent answers in.
+    #[serde(default)]
+    pub discord_guilds: Vec<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct LanguageConfig {
+    /// STT language hint (ISO 639-1) until a call's language is known.
+    #[serde(default = "default_language")]
+    pub default: String,
+    /// Detect the caller's language and lock it in for the rest of the call.
+    #[serde(default)]
+    pub detect: bool,
+    /// Languages a call may switch to; others keep `default`. Empty = any.
+    #[serde(default)]
+    pub supported: Vec<String>,
+    /// Detections in a row that must agree before the language is locked.
+    #[serde(default = "default_lock_after")]
+    pub lock_after: u32,
+    /// Utterances shorter than this don't count towards the lock.
+    #[serde(default = "default_lock_min_ms")]
+    pub lock_min_ms: u64,
+    /// For callers who mix languages: detect every turn, never lock, tell
+    /// the brain each turn's language and follow it with the voice.
+    #[serde(default)]
+    pub code_switching: bool,
+}
+
+impl Default for LanguageConfig {
+    fn default() -> Self {
+        Self {
+            default: default_language(),
+            detect: false,
+            supported: Vec::new(),
+            lock_after: default_lock_after(),
+            lock_min_ms: default_lock_min_ms(),
+            code_switching: false,
+        }
+    }
+}
+
+fn default_language() -> String {
+    "en".to_string()
+}
+
+fn default_lock_after() -> u32 {
+    2
+}
+
+fn default_lock_min_ms() -> u64 {
+    1000
+}
+
+/// `[diarization]`: tell apart several people on one line (speakerphone)
+/// and label each turn with its speaker.
+#[derive(Debug, Deserialize, Clone)]
+pub struct DiarizationConfig {
+    /// Voiceprint similarity (0–1) at which an utterance is the same speaker.
+    #[serde(default = "default_diarization_threshold")]
+    pub threshold: f32,
+    /// Most speakers told apart on a call; further voices go to the closest.
+    #[serde(default = "default_diarization_max_speakers")]
+    pub max_speakers: usize,
+    /// Utterances shorter than this are matched to a known speaker but never
+    /// start a new one or refine a voice.
+    #[serde(default = "default_diarization_min_utterance_ms")]
+    pub min_utterance_ms: u64,
+    /// Take speaker labels from the STT vendor (`deepgram`) instead of
+    /// voiceprints, and label each part of an utterance several people
+    /// spoke in. Labels hold for a whole call with `[stt] streaming`;
+    /// uploaded utterances are each diarized on their own.
+    #[serde(default)]
+    pub vendor: bool,
+}
+
+fn default_diarization_threshold() -> f32 {
+    0.8
+}
+
+fn default_diarization_max_speakers() -> usize {
+    4
+}
+
+fn default_diarization_min_utterance_ms() -> u64 {
+    1000
+}
+
+/// Actions the brain may take on Twilio calls besides speaking (`[actions]`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ActionsConfig {
+    /// Let the brain end the call.
+    #[serde(default = "default_true")]
+    pub hangup: bool,
+    /// Numbers (E.164) the brain may transfer calls to. Empty: no transfers.
+    #[serde(default)]
+    pub transfer_to: Vec<String>,
+    /// Let the brain text the other party.
+    #[serde(default)]
+    pub sms: bool,
+}
+
+/// Post-processing that makes brain responses fit for the phone
+/// (`[concise]`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ConciseConfig {
+    /// Strip markdown (headings, bullets, emphasis, links, tables).
+    #[serde(default = "default_true")]
+    pub markdown: bool,
+    /// Spoken in place of the first code block. Empty drops code silently.
+    #[serde(default = "default_code_notice")]
+    pub code_notice: String,
+    /// Speak at most this many sentences of a response. 0 = no limit.
+    #[serde(default)]
+    pub max_sentences: usize,
+}
+
+fn default_code_notice() -> String {
+    "I've got the code, I'll send it separately.".to_string()
+}
+
+/// Redaction and blocking of brain responses before TTS (`[filter]`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct FilterConfig {
+    /// Redact things that look like credentials (API keys, tokens).
+    #[serde(default = "default_true")]
+    pub secrets: bool,
+    /// Redact phone numbers.
+    #[serde(default)]
+    pub phone_numbers: bool,
+    /// Extra patterns to redact.
+    #[serde(default, deserialize_with = "deserialize_regexes")]
+    pub redact: Vec<Regex>,
+    /// Spoken in place of each redacted match.
+    #[serde(default = "default_filter_replacement")]
+    pub replacement: String,
+    /// Patterns that withhold the whole response.
+    #[serde(default, deserialize_with = "deserialize_regexes")]
+    pub block: Vec<Regex>,
+    /// Spoken instead of a blocked response.
+    #[serde(default = "default_filter_fallback")]
+    pub fallback: String,
+}
+
+fn default_filter_replacement() -> String {
+    "redacted".to_string()
+}
+
+fn default_filter_fallback() -> String {
+    "Sorry, that's not something I can read out over the phone.".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct TtsConfig {
+    #[serde(default)]
+    pub provider: TtsVendor,
+    /// `provider` voice per language code, used once a call's language is
+    /// known.
+    #[serde(default)]
+    pub voices: HashMap<String, String>,
+    /// Rewrites applied to text before synthesis (`[[tts.transliterate]]`).
+    #[serde(default)]
+    pub transliterate: Vec<TransliterationRule>,
+    /// How spelled-out text (`[SPELL]` tags, `spell` rules) is read.
+    #[serde(default)]
+    pub spell: SpellConfig,
+    /// Level every synthesized clip to the same loudness (`[tts.loudness]`).
+    #[serde(default)]
+    pub loudness: Option<LoudnessConfig>,
+    /// Keep synthesized phrases for reuse (`[tts.cache]`).
+    #[serde(default)]
+    pub cache: Option<TtsCacheConfig>,
+    /// Chunk retries for `openai` and `azure`, whose blocks belong to the
+    /// brain and STT too; `[inworld]` and `[elevenlabs]` have their own.
+    #[serde(default = "default_tts_chunk_retries")]
+    pub chunk_retries: u32,
+    #[serde(default = "default_tts_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+}
+
+impl Default for TtsConfig {
+    fn default() -> Self {
+        Self {
+            provider: TtsVendor::default(),
+            voices: HashMap::new(),
+            transliterate: Vec::new(),
+            spell: SpellConfig::default(),
+            loudness: None,
+            cache: None,
+            chunk_retries: default_tts_chunk_retries(),
+            retry_backoff_ms: default_tts_retry_backoff_ms(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TtsVendor {
+    /// Inworld (`[inworld]`).
+    #[default]
+    Inworld,
+    /// ElevenLabs (`[elevenlabs]`).
+    ElevenLabs,
+    /// OpenAI speech (`[openai] tts_model`).
+    OpenAi,
+    /// Azure Speech neural voices (`[azure] voice`).
+    Azure,
+}
+
+/// `[tts.loudness]`: RMS normalization of synthesized audio, so greetings,
+/// replies and error messages play at the same volume whatever the voice.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LoudnessConfig {
+    /// Speech level to aim for, in dBFS RMS.
+    #[serde(default = "default_loudness_target_dbfs")]
+    pub target_dbfs: f64,
+    /// Most a clip is turned up, so near-silence isn't pumped into noise.
+    #[serde(default = "default_loudness_max_gain_db")]
+    pub max_gain_db: f64,
+}
+
+fn default_loudness_target_dbfs() -> f64 {
+    -20.0
+}
+
+fn default_loudness_max_gain_db() -> f64 {
+    12.0
+}
+
+/// `[tts.cache]`: synthesized audio kept by text, voice and model, so the
+/// greeting, error messages and hold prompts are paid for once rather than
+/// on every call. Replies are never cached.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TtsCacheConfig {
+    /// In-memory size; least recently used clips go first.
+    #[serde(default = "default_tts_cache_memory_mb")]
+    pub memory_mb: u64,
+    /// Also keep clips here, across restarts. Memory only when unset.
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+    /// Size of `dir`; oldest clips go first.
+    #[serde(default = "default_tts_cache_disk_mb")]
+    pub disk_mb: u64,
+}
+
+fn default_tts_cache_memory_mb() -> u64 {
+    16
+}
+
+fn default_tts_cache_disk_mb() -> u64 {
+    256
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct SpellConfig {
+    /// Pause between characters with `ssml`, in ms. Groups ("X7K-42Q") get
+    /// twice this between them.
+    #[serde(default = "default_spell_pause_ms")]
+    pub pause_ms: u64,
+    /// Extra times to read a spelled item, for confirmation.
+    #[serde(default)]
+    pub repeat: u32,
+    /// Pause with SSML `<break>` tags. Only for TTS models that support
+    /// them; otherwise punctuation paces the reading.
+    #[serde(default)]
+    pub ssml: bool,
+}
+
+impl Default for SpellConfig {
+    fn default() -> Self {
+        Self {
+            pause_ms: default_spell_pause_ms(),
+            repeat: 0,
+            ssml: false,
+        }
+    }
+}
+
+fn default_spell_pause_ms() -> u64 {
+    300
+}
+
+/// Text matching `pattern` is rewritten into something pronounceable.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TransliterationRule {
+    #[serde(deserialize_with = "deserialize_regex")]
+    pub pattern: Regex,
+    #[serde(default)]
+    pub mode: TransliterationMode,
+    /// Replacement for `replace` mode; may use `$1`-style group references.
+    #[serde(default)]
+    pub replacement: String,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TransliterationMode {
+    /// Read the match character by character, as `[tts.spell]` says.
+    #[default]
+    Spell,
+    /// Substitute `replacement` (a phonetic spelling, say).
+    Replace,
+}
+
+fn deserialize_regex<'de, D>(deserializer: D) -> Result<Regex, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let pattern = String::deserialize(deserializer)?;
+    Regex::new(&pattern).map_err(serde::de::Error::custom)
+}
+
+fn deserialize_regexes<'de, D>(deserializer: D) -> Result<Vec<Regex>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Vec::<String>::deserialize(deserializer)?
+        .iter()
+        .map(|pattern| Regex::new(pattern).map_err(serde::de::Error::custom))
+        .collect()
+}
+
+/// `[prompt]`: how the caller's words are put to brains that take a prompt
+/// (all but bridge-echo), per channel.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct PromptConfig {
+    #[serde(default)]
+    pub phone: ChannelPromptConfig,
+    #[serde(default)]
+    pub discord: ChannelPromptConfig,
+}
+
+/// `[prompt.phone]` / `[prompt.discord]`. Unset fields keep the built-in
+/// text.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChannelPromptConfig {
+    /// Trust wrapper put before every prompt, even an agent's own template.
+    #[serde(default)]
+    pub trust: Option<String>,
+    /// Frames the caller's words. Placeholders: `{transcript}`, `{context}`,
+    /// `{caller}`, `{speaker}`.
+    #[serde(default)]
+    pub template: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct ProfanityConfig {
+    /// Mask profanity in caller transcripts before they are logged or sent
+    /// to the brain.
+    #[serde(default)]
+    pub inbound: bool,
+    /// Drop profanity from responses before TTS.
+    #[serde(default)]
+    pub outbound: bool,
+    /// Use the built-in English word list (in addition to `words`).
+    #[serde(default = "default_true")]
+    pub builtin: bool,
+    /// Extra words to filter, matched as whole words, case-insensitively.
+    #[serde(default)]
+    pub words: Vec<String>,
+    /// What a masked transcript word becomes.
+    #[serde(default = "default_profanity_mask")]
+    pub mask: String,
+}
+
+impl Default for ProfanityConfig {
+    fn default() -> Self {
+        Self {
+            inbound: false,
+            outbound: false,
+            builtin: true,
+            words: Vec::new(),
+            mask: default_profanity_mask(),
+        }
+    }
+}
+
+fn default_profanity_mask() -> String {
+    "***".to_string()
+}
+
+/// Masking of what's written to disk (`[privacy]`): the conversations kept
+/// under `[llm] sessions_dir` and the caller memories under `[memory] dir`.
+/// The brain still gets the words as said.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PrivacyConfig {
+    /// Mask phone numbers.
+    #[serde(default)]
+    pub phone_numbers: bool,
+    /// Mask runs of 13 to 19 digits, as card numbers are.
+    #[serde(default)]
+    pub card_numbers: bool,
+    /// Mask email addresses.
+    #[serde(default)]
+    pub emails: bool,
+    /// Mask the `[profanity]` words, whether or not they're filtered on
+    /// calls.
+    #[serde(default)]
+    pub profanity: bool,
+    /// What each masked match becomes. Profanity takes `[profanity] mask`.
+    #[serde(default = "default_privacy_replacement")]
+    pub replacement: String,
+}
+
+impl Default for PrivacyConfig {
+    fn default() -> Self {
+        Self {
+            phone_numbers: false,
+            card_numbers: false,
+            emails: false,
+            profanity: false,
+            replacement: default_privacy_replacement(),
+        }
+    }
+}
+
+fn default_privacy_replacement() -> String {
+    "[redacted]".to_string()
+}
+
+/// `[memory]`: what's remembered about callers from one call to the next.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MemoryConfig {
+    /// Remember callers, by number or Discord user.
+    #[serde(default)]
+    pub enabled: bool,
+    /// One file per caller.
+    #[serde(default = "default_memory_dir")]
+    pub dir: PathBuf,
+    /// Longest a caller's profile may grow, in characters.
+    #[serde(default = "default_memory_max_chars")]
+    pub max_chars: usize,
+}
+
+impl Default for MemoryConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dir: default_memory_dir(),
+            max_chars: default_memory_max_chars(),
+        }
+    }
+}
+
+fn default_sessions_dir() -> PathBuf {
+    config_dir().join("sessions")
+}
+
+fn default_memory_dir() -> PathBuf {
+    config_dir().join("memory")
+}
+
+fn default_memory_max_chars() -> usize {
+    1_000
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct OutboundConfig {
+    /// Context templates for `/api/call`, by name (`[outbound.templates.alert]`).
+    #[serde(default)]
+    pub templates: HashMap<String, OutboundTemplate>,
+    /// A daily window during which `/api/call` defers calls that aren't
+    /// `urgent` until it ends, instead of ringing someone at 3am.
+    #[serde(default)]
+    pub quiet_hours: Option<QuietHoursConfig>,
+    /// JSON file keeping deferred calls across restarts. Unset keeps them in
+    /// memory only.
+    #[serde(default)]
+    pub schedule_file: Option<PathBuf>,
+}
+
+/// `[outbound.quiet_hours]`. The window is local to the destination: its
+/// `[[contacts]]` timezone, else the longest matching `timezones` prefix,
+/// else `timezone`, else `[identity] timezone`, else the server's.
+#[derive(Debug, Deserialize, Clone)]
+pub struct QuietHoursConfig {
+    /// Start of the window, "HH:MM". A window ending earlier than it starts
+    /// spans midnight; equal times disable it.
+    pub start: chrono::NaiveTime,
+    /// End of the window, "HH:MM".
+    pub end: chrono::NaiveTime,
+    #[serde(default)]
+    pub timezone: Option<chrono_tz::Tz>,
+    /// Destination timezones by number prefix, e.g. `"+1212" = "America/New_York"`.
+    #[serde(default)]
+    pub timezones: HashMap<String, chrono_tz::Tz>,
+}
+
+impl QuietHoursConfig {
+    /// The configured timezone of a destination number.
+    pub fn timezone_for(&self, number: &str) -> Option<chrono_tz::Tz> {
+        self.timezones
+            .iter()
+            .filter(|(prefix, _)| number.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, tz)| *tz)
+            .or(self.timezone)
+    }
+
+    /// When the window `now` falls in ends, or `None` outside it. Times are
+    /// in `tz`, or the server's local time.
+    pub fn ends_after(
+        &self,
+        now: chrono::DateTime<chrono::Utc>,
+        tz: Option<chrono_tz::Tz>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        match tz {
+            Some(tz) => self.end_of_window(now.with_timezone(&tz)),
+            None => self.end_of_window(now.with_timezone(&chrono::Local)),
+        }
+    }
+
+    fn end_of_window<T: chrono::TimeZone>(
+        &self,
+        now: chrono::DateTime<T>,
+    ) -> Option<chrono::DateTime<chrono::Utc>> {
+        let time = now.time();
+        let quiet = if self.start <= self.end {
+            self.start <= time && time < self.end
+        } else {
+            time >= self.start || time < self.end
+        };
+        if !quiet {
+            return None;
+        }
+        let mut date = now.date_naive();
+        if time >= self.end {
+            date = date.succ_opt()?;
+        }
+        let end = date.and_time(self.end);
+        // A DST jump can skip the end time itself; the hour after exists
+        let zone = now.timezone();
+        zone.from_local_datetime(&end)
+            .earliest()
+            .or_else(|| {
+                zone.from_local_datetime(&(end + chrono::Duration::hours(1)))
+                    .earliest()
+            })
+            .map(|end| end.with_timezone(&chrono::Utc))
+    }
+}
+
+/// `[reminders]`: recurring calls managed through `/api/reminders`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct RemindersConfig {
+    /// JSON file keeping reminders across restarts. Unset keeps them in
+    /// memory only.
+    #[serde(default)]
+    pub file: Option<PathBuf>,
+    /// Extra attempts when a reminder call can't be placed or isn't answered.
+    #[serde(default = "default_reminder_retries")]
+    pub retries: u32,
+    /// Wait between attempts.
+    #[serde(default = "default_reminder_retry_delay_secs")]
+    pub retry_delay_secs: u64,
+    /// How long a placed call may go without its media stream starting
+    /// before it counts as unanswered and is hung up.
+    #[serde(default = "default_reminder_answer_timeout_secs")]
+    pub answer_timeout_secs: u64,
+    /// Where each run's outcome is POSTed. Shorthand for a `[[webhooks]]`
+    /// entry subscribed to `reminder.finished`, which it's folded into on
+    /// load.
+    #[serde(default)]
+    pub webhook_url: Option<String>,
+    /// Sent as `Authorization: Bearer <token>` with reminder webhooks.
+    #[serde(default)]
+    pub webhook_token: Option<String>,
+}
+
+impl Default for RemindersConfig {
+    fn default() -> Self {
+        Self {
+            file: None,
+            retries: default_reminder_retries(),
+            retry_delay_secs: default_reminder_retry_delay_secs(),
+            answer_timeout_secs: default_reminder_answer_timeout_secs(),
+            webhook_url: None,
+            webhook_token: None,
+        }
+    }
+}
+
+impl RemindersConfig {
+    /// A reminder webhook for `url`, carrying `webhook_token`.
+    pub fn webhook(&self, url: &str) -> WebhookConfig {
+        let mut hook = WebhookConfig::new(url, &["reminder.finished"]);
+        if let Some(ref token) = self.webhook_token {
+            hook.headers
+                .insert("Authorization".to_string(), format!("Bearer {token}"));
+        }
+        hook
+    }
+}
+
+fn default_reminder_retries() -> u32 {
+    2
+}
+
+fn default_reminder_retry_delay_secs() -> u64 {
+    300
+}
+
+fn default_reminder_answer_timeout_secs() -> u64 {
+    90
+}
+
+/// `[publish]`: call events mirrored to MQTT or Redis.
+#[derive(Debug, Deserialize, Clone)]
+pub struct PublishConfig {
+    /// `mqtt://[user[:password]@]host[:port]` or
+    /// `redis://[[user]:password@]host[:port][/db]`; `mqtts://` and
+    /// `rediss://` for TLS.
+    pub url: String,
+    /// MQTT topic prefix (events go to `<topic>/<name>`), or the Redis channel.
+    #[serde(default = "default_publish_topic")]
+    pub topic: String,
+    /// Event names to publish, as for `[[webhooks]]`, e.g.
+    /// `["call.started", "call.ended"]` (empty = all).
+    #[serde(default)]
+    pub events: Vec<String>,
+    #[serde(default = "default_publish_reconnect_secs")]
+    pub reconnect_secs: u64,
+}
+
+impl PublishConfig {
+    /// Whether event `name` is published.
+    pub fn wants(&self, name: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == name)
+    }
+}
+
+fn default_publish_topic() -> String {
+    "voice-echo".to_string()
+}
+
+fn default_publish_reconnect_secs() -> u64 {
+    5
+}
+
+/// A `[[webhooks]]` subscription to call events.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WebhookConfig {
+    pub url: String,
+    /// Event names to deliver, e.g. `["call.started", "pipeline.error"]`
+    /// (empty = all).
+    #[serde(default)]
+    pub events: Vec<String>,
+    /// Sent with every delivery, e.g. `Authorization = "Bearer ..."`.
+    #[serde(default)]
+    pub headers: HashMap<String, String>,
+    /// Extra attempts after a network error, 429 or 5xx.
+    #[serde(default = "default_webhook_retries")]
+    pub retries: u32,
+    /// Wait before the first retry; doubles after each.
+    #[serde(default = "default_webhook_retry_backoff_ms")]
+    pub retry_backoff_ms: u64,
+    #[serde(default = "default_webhook_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl WebhookConfig {
+    /// A hook to `url` for `events`, with the default retries and timeout.
+    pub fn new(url: &str, events: &[&str]) -> Self {
+        Self {
+            url: url.to_string(),
+            events: events.iter().map(|e| e.to_string()).collect(),
+            headers: HashMap::new(),
+            retries: default_webhook_retries(),
+            retry_backoff_ms: default_webhook_retry_backoff_ms(),
+            timeout_ms: default_webhook_timeout_ms(),
+        }
+    }
+
+    /// Whether the hook subscribes to event `name`.
+    pub fn wants(&self, name: &str) -> bool {
+        self.events.is_empty() || self.events.iter().any(|e| e == name)
+    }
+}
+
+fn default_webhook_retries() -> u32 {
+    3
+}
+
+fn default_webhook_retry_backoff_ms() -> u64 {
+    500
+}
+
+fn default_webhook_timeout_ms() -> u64 {
+    5000
+}
+
+/// A server-side outbound context. `{var}` placeholders are filled from the
+/// request's `vars`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OutboundTemplate {
+    pub context: String,
+    #[serde(default)]
+    pub reason: Option<String>,
+    /// Agent placing calls made with this template, unless the request names one.
+    #[serde(default)]
+    pub agent: Option<String>,
+}
+
+/// A known caller (`[[contacts]]`).
+#[derive(Debug, Deserialize, Clone)]
+pub struct ContactConfig {
+    /// Spoken in greetings as `{caller_name}`.
+    pub name: String,
+    /// Numbers (E.164) the contact calls from.
+    pub numbers: Vec<String>,
+    /// Frequent callers get the short greeting, without the intro.
+    #[serde(default)]
+    pub frequent: bool,
+    /// The contact's language (ISO 639-1). Sets the STT hint, the
+    /// `[tts.voices]` voice and the reply language from the first word.
+    #[serde(default)]
+    pub language: Option<String>,
+    /// Where the contact lives (IANA name), for `[outbound.quiet_hours]`.
+    #[serde(default)]
+    pub timezone: Option<chrono_tz::Tz>,
+}
+
+/// An isolated customer (`[[tenants]]`). Unset provider keys use the
+/// top-level ones.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TenantConfig {
+    pub name: String,
+    /// Bearer token for this tenant's `/api/*` requests.
+    pub api_token: String,
+    /// The tenant's own Twilio account; outbound calls are placed from it.
+    #[serde(default)]
+    pub twilio: Option<TwilioConfig>,
+    /// Inbound numbers (E.164) routed to this tenant, besides `twilio.phone_number`.
+    #[serde(default)]
+    pub phone_numbers: Vec<String>,
+    #[serde(default)]
+    pub groq_api_key: Option<String>,
+    #[serde(default)]
+    pub deepgram_api_key: Option<String>,
+    #[serde(default)]
+    pub assemblyai_api_key: Option<String>,
+    #[serde(default)]
+    pub azure_speech_key: Option<String>,
+    #[serde(default)]
+    pub inworld_api_key: Option<String>,
+    #[serde(default)]
+    pub elevenlabs_api_key: Option<String>,
+    /// Used for `[tts] provider = "openai"` only.
+    #[serde(default)]
+    pub openai_api_key: Option<String>,
+    /// Agents (`[[agents]]` names) this tenant may use; the first is its
+    /// default. Empty means no agent restriction and no default.
+    #[serde(default)]
+    pub agents: Vec<String>,
+    /// Root for the tenant's stored data (voicemails go in `<data_dir>/voicemail`).
+    #[serde(default)]
+    pub data_dir: Option<String>,
+}
+
+/// `[brain]`: which brain answers the calls.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct BrainConfig {
+    /// Unset: bridge-echo when `llm.bridge_url` is set, else the plugin's
+    /// provider.
+    #[serde(default)]
+    pub provider: Option<BrainProvider>,
+    /// Ordered fallback chain, e.g. `["bridge", "local"]`: each brain is
+    /// tried in turn until one answers. Replaces `provider` when set.
+    #[serde(default)]
+    pub providers: Vec<BrainProvider>,
+    /// Per-provider budgets (`[brain.timeout_ms]`), e.g. `bridge = 4000`.
+    /// Providers not listed get `timeouts.brain_ms`.
+    #[serde(default)]
+    pub timeout_ms: HashMap<BrainProvider, u64>,
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "lowercase")]
+pub enum BrainProvider {
+    /// bridge-echo at `llm.bridge_url`.
+    Bridge,
+    /// The LLM provider of the host process (plugin mode).
+    Local,
+    /// OpenAI Chat Completions (`[openai]`).
+    OpenAi,
+    /// A local Ollama server (`[ollama]`).
+    Ollama,
+    /// The Anthropic Messages API, without the CLI (`[anthropic]`).
+    Anthropic,
+    /// The Google Gemini API (`[gemini]`).
+    Gemini,
+    /// The xAI Grok API (`[grok]`).
+    Grok,
+    /// A model run in-process (`[local_llm]`, `local-llm` feature).
+    #[serde(rename = "local_llm")]
+    LocalLlm,
+}
+
+impl BrainConfig {
+    /// The configured provider, or the one `llm` implies.
+    pub fn provider(&self, llm: &LlmConfig) -> BrainProvider {
+        self.providers.first().copied().unwrap_or_else(|| {
+            self.provider.unwrap_or(match llm.bridge_url {
+                Some(_) => BrainProvider::Bridge,
+                None => BrainProvider::Local,
+            })
+        })
+    }
+
+    /// The brains to try, in order: `providers`, or just `provider`.
+    pub fn chain(&self, llm: &LlmConfig) -> Vec<BrainProvider> {
+        match self.providers.is_empty() {
+            true => vec![self.provider(llm)],
+            false => self.providers.clone(),
+        }
+    }
+
+    /// Budget for one attempt on `provider`; `default_ms` unless
+    /// `[brain.timeout_ms]` lists it.
+    pub fn budget_ms(&self, provider: Option<BrainProvider>, default_ms: u64) -> u64 {
+        provider
+            .and_then(|p| self.timeout_ms.get(&p).copied())
+            .unwrap_or(default_ms)
+    }
+}
+
+/// `[openai]`: the Chat Completions API, or any server compatible with it.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OpenAiConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_openai_model")]
+    pub model: String,
+    #[serde(default = "default_openai_base_url")]
+    pub base_url: String,
+    /// Per-request timeout. 0 disables it.
+    #[serde(default = "default_openai_timeout_ms")]
+    pub timeout_ms: u64,
+    /// Speech model with `[tts] provider = "openai"`.
+    #[serde(default = "default_openai_tts_model")]
+    pub tts_model: String,
+    /// Voice with `[tts] provider = "openai"`.
+    #[serde(default = "default_openai_voice")]
+    pub voice: String,
+}
+
+impl Default for OpenAiConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_openai_model(),
+            base_url: default_openai_base_url(),
+            timeout_ms: default_openai_timeout_ms(),
+            tts_model: default_openai_tts_model(),
+            voice: default_openai_voice(),
+        }
+    }
+}
+
+fn default_openai_model() -> String {
+    "gpt-4o-mini".to_string()
+}
+
+fn default_openai_base_url() -> String {
+    "https://api.openai.com/v1".to_string()
+}
+
+fn default_openai_timeout_ms() -> u64 {
+    20_000
+}
+
+fn default_openai_tts_model() -> String {
+    "tts-1".to_string()
+}
+
+fn default_openai_voice() -> String {
+    "alloy".to_string()
+}
+
+/// `[ollama]`: a local Ollama server.
+#[derive(Debug, Deserialize, Clone)]
+pub struct OllamaConfig {
+    #[serde(default = "default_ollama_base_url")]
+    pub base_url: String,
+    /// A model already pulled on the server, e.g. "llama3.2".
+    #[serde(default = "default_ollama_model")]
+    pub model: String,
+    /// How long Ollama keeps the model loaded after a turn (e.g. "30m").
+    /// Unset = the server's default.
+    #[serde(default)]
+    pub keep_alive: Option<String>,
+    /// Per-request timeout. 0 disables it. Generous, since local models are
+    /// slow to load, but under `[timeouts] brain_ms` so a stuck request is
+    /// dropped before the turn gives up on it.
+    #[serde(default = "default_ollama_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for OllamaConfig {
+    fn default() -> Self {
+        Self {
+            base_url: default_ollama_base_url(),
+            model: default_ollama_model(),
+            keep_alive: None,
+            timeout_ms: default_ollama_timeout_ms(),
+        }
+    }
+}
+
+fn default_ollama_base_url() -> String {
+    "http://localhost:11434".to_string()
+}
+
+fn default_ollama_model() -> String {
+    "llama3.2".to_string()
+}
+
+fn default_ollama_timeout_ms() -> u64 {
+    25_000
+}
+
+/// `[anthropic]`: the Anthropic Messages API.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AnthropicConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_anthropic_model")]
+    pub model: String,
+    #[serde(default = "default_anthropic_base_url")]
+    pub base_url: String,
+    /// Per-request timeout. 0 disables it.
+    #[serde(default = "default_anthropic_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for AnthropicConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_anthropic_model(),
+            base_url: default_anthropic_base_url(),
+            timeout_ms: default_anthropic_timeout_ms(),
+        }
+    }
+}
+
+fn default_anthropic_model() -> String {
+    "claude-haiku-4-5".to_string()
+}
+
+fn default_anthropic_base_url() -> String {
+    "https://api.anthropic.com".to_string()
+}
+
+fn default_anthropic_timeout_ms() -> u64 {
+    20_000
+}
+
+/// `[gemini]`: the Google Gemini API.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GeminiConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_gemini_model")]
+    pub model: String,
+    #[serde(default = "default_gemini_base_url")]
+    pub base_url: String,
+    /// Per-request timeout. 0 disables it.
+    #[serde(default = "default_gemini_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for GeminiConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_gemini_model(),
+            base_url: default_gemini_base_url(),
+            timeout_ms: default_gemini_timeout_ms(),
+        }
+    }
+}
+
+fn default_gemini_model() -> String {
+    "gemini-2.5-flash".to_string()
+}
+
+fn default_gemini_base_url() -> String {
+    "https://generativelanguage.googleapis.com".to_string()
+}
+
+fn default_gemini_timeout_ms() -> u64 {
+    20_000
+}
+
+/// `[grok]`: the xAI API. It speaks Chat Completions, so the OpenAI brain
+/// talks to it.
+#[derive(Debug, Deserialize, Clone)]
+pub struct GrokConfig {
+    #[serde(default)]
+    pub api_key: String,
+    #[serde(default = "default_grok_model")]
+    pub model: String,
+    #[serde(default = "default_grok_base_url")]
+    pub base_url: String,
+    /// Per-request timeout. 0 disables it.
+    #[serde(default = "default_grok_timeout_ms")]
+    pub timeout_ms: u64,
+}
+
+impl Default for GrokConfig {
+    fn default() -> Self {
+        Self {
+            api_key: String::new(),
+            model: default_grok_model(),
+            base_url: default_grok_base_url(),
+            timeout_ms: default_grok_timeout_ms(),
+        }
+    }
+}
+
+impl GrokConfig {
+    /// The same settings, for the OpenAI brain.
+    pub fn as_openai(&self) -> OpenAiConfig {
+        OpenAiConfig {
+            api_key: self.api_key.clone(),
+            model: self.model.clone(),
+            base_url: self.base_url.clone(),
+            timeout_ms: self.timeout_ms,
+            ..OpenAiConfig::default()
+        }
+    }
+}
+
+fn default_grok_model() -> String {
+    "grok-3-mini".to_string()
+}
+
+fn default_grok_base_url() -> String {
+    "https://api.x.ai/v1".to_string()
+}
+
+fn default_grok_timeout_ms() -> u64 {
+    20_000
+}
+
+/// `[local_llm]`: a model run in-process by the `local_llm` brain.
+#[derive(Debug, Deserialize, Clone)]
+pub struct LocalLlmConfig {
+    /// Quantized llama-family model (GGUF).
+    pub model_path: PathBuf,
+    /// The model's `tokenizer.json`.
+    pub tokenizer_path: PathBuf,
+    /// Tokens of prompt and answer the model is run with. Older turns are
+    /// left out of longer conversations.
+    #[serde(default = "default_context_length")]
+    pub context_length: usize,
+    #[serde(default)]
+    pub prompt_format: PromptFormat,
+    /// Sampling temperature; 0 always picks the likeliest token.
+    #[serde(default = "default_local_temperature")]
+    pub temperature: f64,
+}
+
+/// The chat markup the model was trained on.
+#[derive(Debug, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum PromptFormat {
+    /// `<|im_start|>` turns (Qwen, Phi, SmolLM, ...).
+    #[default]
+    ChatMl,
+    /// `<|start_header_id|>` turns (Llama 3).
+    Llama3,
+}
+
+fn default_context_length() -> usize {
+    4096
+}
+
+fn default_local_temperature() -> f64 {
+    0.7
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct FallbackConfig {
+    /// Fall back to this bridge-echo instance. When unset and the primary
+    /// is a bridge, the local plugin provider is used instead.
+    #[serde(default)]
+    pub bridge_url: Option<String>,
+    /// Spoken before the first fallback answer on a call. Empty disables it.
+    #[serde(default = "default_fallback_notice")]
+    pub notice: String,
+}
+
+fn default_fallback_notice() -> String {
+    "I'm running in limited mode right now.".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BridgeAuthMode {
+    #[default]
+    Bearer,
+    Hmac,
+}
+
+fn default_bridge_timeout_ms() -> u64 {
+    25_000
+}
+
+fn default_bridge_retries() -> u32 {
+    2
+}
+
+fn default_bridge_retry_delay_ms() -> u64 {
+    250
+}
+
+fn default_bridge_retry_max_delay_ms() -> u64 {
+    2_000
+}
+
+fn default_invoke_timeout_ms() -> u64 {
+    25_000
+}
+
+fn default_max_response_tokens() -> u32 {
+    1024
+}
+
+fn default_session_timeout() -> u64 {
+    300
+}
+
+fn default_name() -> String {
+    "Echo".to_string()
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct VadConfig {
+    #[serde(default = "default_silence_threshold")]
+    pub silence_threshold_ms: u64,
+    #[serde(default = "default_energy_threshold")]
+    pub energy_threshold: u16,
+    #[serde(default)]
+    pub adaptive_threshold: bool,
+    #[serde(default = "default_noise_floor_multiplier")]
+    pub noise_floor_multiplier: f64,
+    #[serde(default = "default_noise_floor_decay")]
+    pub noise_floor_decay: f64,
+    /// Force-send an utterance this long. Unset or 0, the cap is one
+    /// minute; there's always a cap, so a line that never goes quiet can't
+    /// grow the buffer without limit.
+    #[serde(default)]
+    pub max_utterance_secs: Option<u64>,
+    /// Audio kept from just before speech is detected, so the first
+    /// syllable reaches STT.
+    #[serde(default = "default_pre_roll_ms")]
+    pub pre_roll_ms: u64,
+}
+
+fn default_pre_roll_ms() -> u64 {
+    300
+}
+
+fn default_silence_threshold() -> u64 {
+    1500
+}
+
+fn default_energy_threshold() -> u16 {
+    50
+}
+
+fn default_noise_floor_multiplier() -> f64 {
+    3.0
+}
+
+fn default_noise_floor_decay() -> f64 {
+    0.995
+}
+
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ApiConfig {
+    /// Bearer token required for /api/* endpoints. If empty, all requests are rejected.
+    #[serde(default)]
+    pub token: String,
+}
+
+#[derive(Debug, Deserialize, Clone)]
+pub struct PipelineConfig {
+    /// Max STT/brain/TTS turns processed at once across all calls.
+    /// Extra turns queue behind hold music. 0 disables the limit.
+    #[serde(default = "default_max_concurrent_turns")]
+    pub max_concurrent_turns: usize,
+    /// Utterances shorter than this are admitted ahead of longer ones
+    /// when the pipeline is saturated.
+    #[serde(default = "default_short_turn_ms")]
+    pub short_turn_ms: u64,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            max_concurrent_turns: default_max_concurrent_turns(),
+            short_turn_ms: default_short_turn_ms(),
+        }
+    }
+}
+
+fn default_max_concurrent_turns() -> usize {
+    8
+}
+
+fn default_short_turn_ms() -> u64 {
+    3000
+}
+
+/// Per-stage time budgets in milliseconds. 0 disables a budget.
+#[derive(Debug, Deserialize, Clone)]
+pub struct TimeoutsConfig {
+    #[serde(default = "default_stt_timeout_ms")]
+    pub stt_ms: u64,
+    #[serde(default = "default_brain_timeout_ms")]
+    pub brain_ms: u64,
+    #[serde(default = "default_tts_timeout_ms")]
+    pub tts_ms: u64,
+    /// Budget for the whole turn once it has a pipeline slot.
+    #[serde(default = "default_turn_timeout_ms")]
+    pub turn_ms: u64,
+}
+
+impl Default for TimeoutsConfig {
+    fn default() -> Self {
+        Self {
+            stt_ms: default_stt_timeout_ms(),
+            brain_ms: default_brain_timeout_ms(),
+            tts_ms: default_tts_timeout_ms(),
+            turn_ms: default_turn_timeout_ms(),
+        }
+    }
+}
+
+fn default_stt_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_brain_timeout_ms() -> u64 {
+    30_000
+}
+
+fn default_tts_timeout_ms() -> u64 {
+    10_000
+}
+
+fn default_turn_timeout_ms() -> u64 {
+    45_000
+}
+
+/// Circuit breaker settings shared by all external provider clients.
+#[derive(Debug, Deserialize, Clone)]
+pub struct BreakerConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Fraction of failed requests (0.0–1.0) in the window that opens the breaker.
+    #[serde(default = "default_breaker_failure_rate")]
+    pub failure_rate: f64,
+    /// Minimum requests in the window before the error rate is considered.
+    #[serde(default = "default_breaker_min_requests")]
+    pub min_requests: usize,
+    /// Sliding window over which the error rate is measured.
+    #[serde(default = "default_breaker_window_secs")]
+    pub window_secs: u64,
+    /// How long the breaker stays open before probing.
+    #[serde(default = "default_breaker_open_secs")]
+    pub open_secs: u64,
+    /// Concurrent probe requests allowed while half-open.
+    #[serde(default = "default_breaker_half_open_probes")]
+    pub half_open_probes: usize,
+    /// Failures in a row that open the breaker regardless of the error
+    /// rate. 0 disables it.
+    #[serde(default)]
+    pub consecutive_failures: usize,
+}
+
+impl Default for BreakerConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            failure_rate: default_breaker_failure_rate(),
+            min_requests: default_breaker_min_requests(),
+            window_secs: default_breaker_window_secs(),
+            open_secs: default_breaker_open_secs(),
+            half_open_probes: default_breaker_half_open_probes(),
+            consecutive_failures: 0,
+        }
+    }
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn default_breaker_failure_rate() -> f64 {
+    0.5
+}
+
+fn default_breaker_min_requests() -> usize {
+    5
+}
+
+fn default_breaker_window_secs() -> u64 {
+    60
+}
+
+fn default_breaker_open_secs() -> u64 {
+    30
+}
+
+fn default_breaker_half_open_probes() -> usize {
+    1
+}
+
+/// WebSocket keepalive for Twilio and Discord media streams.
+#[derive(Debug, Deserialize, Clone)]
+pub struct KeepaliveConfig {
+    /// Ping the peer this often. 0 disables pings.
+    #[serde(default = "default_ping_interval_secs")]
+    pub ping_interval_secs: u64,
+    /// Close the stream and end the call after this long without any frame
+    /// (audio, control or pong). 0 disables the watchdog.
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: u64,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            ping_interval_secs: default_ping_interval_secs(),
+            idle_timeout_secs: default_idle_timeout_secs(),
+        }
+    }
+}
+
+fn default_ping_interval_secs() -> u64 {
+    15
+}
+
+fn default_idle_timeout_secs() -> u64 {
+    60
+}
+
+/// Framing of outbound audio on the Twilio and Discord media streams.
+#[derive(Debug, Deserialize, Clone)]
+pub struct AudioConfig {
+    /// Audio per outbound frame, which is also the hold music pacing.
+    /// Larger frames mean fewer messages but a coarser stop when the
+    /// caller barges in. Clamped to 10-200ms.
+    #[serde(default = "default_frame_ms")]
+    pub frame_ms: u64,
+    /// Outbound messages queued per stream before pipeline tasks wait on
+    /// the socket.
+    #[serde(default = "default_channel_capacity")]
+    pub channel_capacity: usize,
+}
+
+impl Default for AudioConfig {
+    fn default() -> Self {
+        Self {
+            frame_ms: default_frame_ms(),
+            channel_capacity: default_channel_capacity(),
+        }
+    }
+}
+
+impl AudioConfig {
+    /// Mu-law bytes per frame (8 per millisecond at 8kHz).
+    pub fn frame_bytes(&self) -> usize {
+        self.frame_ms.clamp(10, 200) as usize * 8
+    }
+
+    /// Time each frame plays for.
+    pub fn frame_duration(&self) -> Duration {
+        Duration::from_millis(self.frame_ms.clamp(10, 200))
+    }
+
+    /// Capacity of a stream's outbound channel (at least 1).
+    pub fn channel_capacity(&self) -> usize {
+        self.channel_capacity.max(1)
+    }
+}
+
+fn default_frame_ms() -> u64 {
+    20
+}
+
+fn default_channel_capacity() -> usize {
+    64
+}
+
+/// Canned STT / TTS / brain for tests and CI. Only honoured when built
+/// with `--features mocks`.
+#[derive(Debug, Deserialize, Clone)]
+pub struct MocksConfig {
+    /// Replace Groq with canned transcripts.
+    #[serde(default)]
+    pub stt: bool,
+    /// Replace Inworld with generated audio.
     #[serde(default)]
-    pub hold_music: Option<HoldMusicConfig>,
+    pub tts: bool,
+    /// Replace the brain with scripted responses.
     #[serde(default)]
-    pub identity: IdentityConfig,
+    pub brain: bool,
+    /// Place outbound calls nowhere instead of through Twilio.
     #[serde(default)]
-    pub greetings: GreetingsConfig,
+    pub twilio: bool,
+    /// Transcripts returned in turn, one per utterance.
+    #[serde(default = "default_mock_transcripts")]
+    pub transcripts: Vec<String>,
+    /// Brain responses returned in turn. Empty echoes the caller.
+    #[serde(default)]
+    pub responses: Vec<String>,
+    /// What mock TTS audio sounds like.
+    #[serde(default)]
+    pub audio: MockAudio,
+    /// Mock TTS audio length per character of text.
+    #[serde(default = "default_mock_ms_per_char")]
+    pub ms_per_char: u64,
+    /// Delay before every mock STT, TTS and brain result.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Time each mock outbound call takes to place.
+    #[serde(default)]
+    pub dial_ms: u64,
+    /// Confidence reported with every mock transcript.
+    #[serde(default)]
+    pub confidence: Option<f32>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct ServerConfig {
-    pub host: String,
-    pub port: u16,
-    pub external_url: String,
+impl Default for MocksConfig {
+    fn default() -> Self {
+        Self {
+            stt: false,
+            tts: false,
+            brain: false,
+            twilio: false,
+            transcripts: default_mock_transcripts(),
+            responses: Vec::new(),
+            audio: MockAudio::default(),
+            ms_per_char: default_mock_ms_per_char(),
+            latency_ms: 0,
+            dial_ms: 0,
+            confidence: None,
+        }
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct TwilioConfig {
-    pub account_sid: String,
-    pub auth_token: String,
-    pub phone_number: String,
+impl MocksConfig {
+    /// Whether any provider is mocked.
+    pub fn any(&self) -> bool {
+        self.stt || self.tts || self.brain || self.twilio
+    }
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct GroqConfig {
-    pub api_key: String,
-    #[serde(default = "default_groq_model")]
-    pub model: String,
+/// Mock TTS audio: a 440Hz tone (passes VAD) or silence.
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum MockAudio {
+    #[default]
+    Sine,
+    Silence,
 }
 
-fn default_groq_model() -> String {
-    "whisper-large-v3-turbo".to_string()
+fn default_mock_transcripts() -> Vec<String> {
+    vec!["Hello".to_string()]
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct InworldConfig {
-    pub api_key: String,
-    #[serde(default = "default_voice_id")]
-    pub voice_id: String,
-    #[serde(default = "default_inworld_model")]
-    pub model: String,
+fn default_mock_ms_per_char() -> u64 {
+    60
 }
 
-fn default_voice_id() -> String {
-    "Olivia".to_string()
+/// Recording calls for `voice-echo --replay`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct CaptureConfig {
+    /// Append each call's inbound stream here as `<call_sid>.jsonl`,
+    /// secrets redacted. Unset disables capture.
+    #[serde(default)]
+    pub dir: Option<String>,
 }
 
-fn default_inworld_model() -> String {
-    "inworld-tts-1.5-max".to_string()
+/// Troubleshooting endpoints, all off by default.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct DebugConfig {
+    /// Serve `POST /debug/pipeline`: run an uploaded WAV through STT, the
+    /// brain and TTS and report each stage. Needs `[api] token`.
+    #[serde(default)]
+    pub pipeline: bool,
+    /// Seed every random choice (greetings) so runs repeat exactly. Unset
+    /// seeds from the OS.
+    #[serde(default)]
+    pub seed: Option<u64>,
 }
 
-#[derive(Debug, Deserialize, Clone)]
-pub struct LlmConfig {
-    #[serde(default = "default_session_timeout")]
-    pub session_timeout_secs: u64,
+/// Fault injection for resilience testing. Nothing is injected unless
+/// `enabled`.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct ChaosConfig {
     #[serde(default)]
-    pub greeting: String,
-    #[serde(default = "default_name")]
-    pub name: String,
+    pub enabled: bool,
     #[serde(default)]
-    pub self_path: Option<String>,
-    /// URL of bridge-echo multiplexer. When set, voice-echo forwards
-    /// transcripts to bridge-echo instead of spawning its own Claude Code process.
+    pub stt: FaultConfig,
     #[serde(default)]
-    pub bridge_url: Option<String>,
-    /// Max tokens for LLM responses. Short for voice (default: 1024).
-    #[serde(default = "default_max_response_tokens")]
-    pub max_response_tokens: u32,
+    pub tts: FaultConfig,
+    /// Faults the primary brain (and agents' own), never the fallbacks.
+    #[serde(default)]
+    pub brain: FaultConfig,
 }
 
-fn default_max_response_tokens() -> u32 {
-    1024
+/// Faults injected into each request attempt to one provider.
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct FaultConfig {
+    /// Added to every attempt.
+    #[serde(default)]
+    pub latency_ms: u64,
+    /// Up to this much more, at random.
+    #[serde(default)]
+    pub jitter_ms: u64,
+    /// Fraction of attempts (0.0–1.0) that fail after the latency.
+    #[serde(default)]
+    pub error_rate: f64,
+    /// Deliver successful responses at this many bytes per second. 0 = off.
+    #[serde(default)]
+    pub drip_bytes_per_sec: u64,
 }
 
-fn default_session_timeout() -> u64 {
-    300
+impl FaultConfig {
+    /// Whether this injects anything.
+    pub fn any(&self) -> bool {
+        self.latency_ms > 0
+            || self.jitter_ms > 0
+            || self.error_rate > 0.0
+            || self.drip_bytes_per_sec > 0
+    }
 }
 
-fn default_name() -> String {
-    "Echo".to_string()
+/// The HTTP client shared by the Groq, Inworld, Twilio and bridge-echo
+/// clients, so they all draw on one connection pool.
+#[derive(Debug, Deserialize, Clone)]
+pub struct HttpConfig {
+    /// Give up on connecting to a host after this long.
+    #[serde(default = "default_connect_timeout_ms")]
+    pub connect_timeout_ms: u64,
+    /// Close pooled connections idle for this long.
+    #[serde(default = "default_pool_idle_timeout_secs")]
+    pub pool_idle_timeout_secs: u64,
+    /// Idle connections kept open per host.
+    #[serde(default = "default_pool_max_idle_per_host")]
+    pub pool_max_idle_per_host: usize,
+    /// Use HTTP/2 where the server offers it, multiplexing concurrent
+    /// calls' requests over one connection. `false` forces HTTP/1.1.
+    #[serde(default = "default_true")]
+    pub http2: bool,
+}
+
+impl Default for HttpConfig {
+    fn default() -> Self {
+        Self {
+            connect_timeout_ms: default_connect_timeout_ms(),
+            pool_idle_timeout_secs: default_pool_idle_timeout_secs(),
+            pool_max_idle_per_host: default_pool_max_idle_per_host(),
+            http2: true,
+        }
+    }
+}
+
+fn default_connect_timeout_ms() -> u64 {
+    5000
 }
 
+fn default_pool_idle_timeout_secs() -> u64 {
+    90
+}
+
+fn default_pool_max_idle_per_host() -> usize {
+    16
+}
+
+/// Startup checks of credentials, brain reachability and URLs.
 #[derive(Debug, Deserialize, Clone)]
-pub struct VadConfig {
-    #[serde(default = "default_silence_threshold")]
-    pub silence_threshold_ms: u64,
-    #[serde(default = "default_energy_threshold")]
-    pub energy_threshold: u16,
+pub struct PreflightConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Refuse to start when a check fails, instead of logging and continuing.
     #[serde(default)]
-    pub adaptive_threshold: bool,
-    #[serde(default = "default_noise_floor_multiplier")]
-    pub noise_floor_multiplier: f64,
-    #[serde(default = "default_noise_floor_decay")]
-    pub noise_floor_decay: f64,
+    pub strict: bool,
+    /// Per-probe timeout.
+    #[serde(default = "default_preflight_timeout_secs")]
+    pub timeout_secs: u64,
+}
+
+impl Default for PreflightConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            strict: false,
+            timeout_secs: default_preflight_timeout_secs(),
+        }
+    }
+}
+
+fn default_preflight_timeout_secs() -> u64 {
+    5
+}
+
+/// `[warmup]`: a throwaway prompt through each brain at startup, so the
+/// first caller doesn't pay for cold connections and processes.
+#[derive(Debug, Deserialize, Clone)]
+pub struct WarmupConfig {
     #[serde(default)]
-    pub max_utterance_secs: Option<u64>,
+    pub enabled: bool,
+    #[serde(default = "default_warmup_prompt")]
+    pub prompt: String,
+    /// Give up on a brain's warmup after this long.
+    #[serde(default = "default_warmup_timeout_secs")]
+    pub timeout_secs: u64,
 }
 
-fn default_silence_threshold() -> u64 {
-    1500
+impl Default for WarmupConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            prompt: default_warmup_prompt(),
+            timeout_secs: default_warmup_timeout_secs(),
+        }
+    }
 }
 
-fn default_energy_threshold() -> u16 {
-    50
+fn default_warmup_prompt() -> String {
+    "Reply with just: ok".to_string()
 }
 
-fn default_noise_floor_multiplier() -> f64 {
-    3.0
+fn default_warmup_timeout_secs() -> u64 {
+    60
 }
 
-fn default_noise_floor_decay() -> f64 {
-    0.995
+/// Background cleanup of calls whose stream handler died or that ran too long.
+#[derive(Debug, Deserialize, Clone)]
+pub struct ReaperConfig {
+    /// How often to scan the call registry. 0 disables the reaper.
+    #[serde(default = "default_reaper_interval_secs")]
+    pub interval_secs: u64,
+    /// End calls older than this. 0 (the default) means no limit.
+    #[serde(default)]
+    pub max_call_secs: u64,
 }
 
-#[derive(Debug, Deserialize, Clone, Default)]
-pub struct ApiConfig {
-    /// Bearer token required for /api/* endpoints. If empty, all requests are rejected.
+impl Default for ReaperConfig {
+    fn default() -> Self {
+        Self {
+            interval_secs: default_reaper_interval_secs(),
+            max_call_secs: 0,
+        }
+    }
+}
+
+fn default_reaper_interval_secs() -> u64 {
+    30
+}
+
+/// Canned-response mode for phone calls when STT or the brain is down.
+#[derive(Debug, Deserialize, Clone)]
+pub struct DegradedConfig {
+    #[serde(default = "default_true")]
+    pub enabled: bool,
+    /// Spoken once when a call enters degraded mode.
+    #[serde(default = "default_degraded_message")]
+    pub message: String,
+    /// Where to save voicemails captured in degraded mode. Unset disables capture.
     #[serde(default)]
-    pub token: String,
+    pub voicemail_dir: Option<String>,
+    #[serde(default = "default_max_voicemail_secs")]
+    pub max_voicemail_secs: u64,
+    /// Answer new calls with `reject_message` and hang up while STT, TTS
+    /// or the brain is known to be down.
+    #[serde(default = "default_true")]
+    pub reject_calls: bool,
+    #[serde(default = "default_reject_message")]
+    pub reject_message: String,
+}
+
+impl Default for DegradedConfig {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            message: default_degraded_message(),
+            voicemail_dir: None,
+            max_voicemail_secs: default_max_voicemail_secs(),
+            reject_calls: true,
+            reject_message: default_reject_message(),
+        }
+    }
+}
+
+fn default_degraded_message() -> String {
+    "I'm having trouble right now, but I've noted that you called and will follow up. \
+     Feel free to leave a message."
+        .to_string()
+}
+
+fn default_reject_message() -> String {
+    "Sorry, I can't take calls right now. Please try again later.".to_string()
+}
+
+fn default_max_voicemail_secs() -> u64 {
+    120
 }
 
 #[derive(Debug, Deserialize, Clone)]
 pub struct HoldMusicConfig {
-    pub file: String,
+    /// A WAV file.
+    #[serde(default)]
+    pub file: Option<String>,
+    /// More WAV files. Each hold plays the next one (after `file`), and
+    /// long holds move on to the one after.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Pick tracks at random instead of in order.
+    #[serde(default)]
+    pub shuffle: bool,
+    /// An HTTP or Icecast stream (MP3, Ogg Vorbis or WAV) to play live
+    /// instead of files.
+    #[serde(default)]
+    pub url: Option<String>,
     #[serde(default = "default_hold_music_volume")]
     pub volume: f32,
 }
 
+impl HoldMusicConfig {
+    /// `file`, then `files`.
+    pub fn paths(&self) -> impl Iterator<Item = &str> {
+        self.file.iter().chain(&self.files).map(String::as_str)
+    }
+}
+
 fn default_hold_music_volume() -> f32 {
     0.3
 }
 
+/// Spoken reminders mixed into the hold while a Twilio caller waits on a turn.
+#[derive(Debug, Deserialize, Clone)]
+pub struct HoldAnnouncementsConfig {
+    /// Seconds of hold before each announcement. 0 disables them.
+    #[serde(default)]
+    pub every_secs: u64,
+    /// Spoken in turn, in the call's voice.
+    #[serde(default = "default_hold_announcements")]
+    pub messages: Vec<String>,
+    /// Hold music volume under an announcement (0.0 to 1.0).
+    #[serde(default = "default_hold_duck")]
+    pub duck: f32,
+}
+
+impl Default for HoldAnnouncementsConfig {
+    fn default() -> Self {
+        Self {
+            every_secs: 0,
+            messages: default_hold_announcements(),
+            duck: default_hold_duck(),
+        }
+    }
+}
+
+fn default_hold_announcements() -> Vec<String> {
+    vec!["Still with you, just a moment.".into()]
+}
+
+fn default_hold_duck() -> f32 {
+    0.3
+}
+
 #[derive(Debug, Deserialize, Clone)]
 pub struct IdentityConfig {
     #[serde(default = "default_identity_name")]
     pub name: String,
     #[serde(default = "default_caller_name")]
     pub caller_name: String,
+    /// IANA timezone for time-of-day logic (e.g. "Europe/Madrid").
+    /// Unset uses the server's local time.
+    #[serde(default)]
+    pub timezone: Option<chrono_tz::Tz>,
 }
 
 impl Default for IdentityConfig {
@@ -156,6 +2425,18 @@ impl Default for IdentityConfig {
         Self {
             name: default_identity_name(),
             caller_name: default_caller_name(),
+            timezone: None,
+        }
+    }
+}
+
+impl IdentityConfig {
+    /// The current hour (0-23) in `timezone`.
+    pub fn current_hour(&self) -> u32 {
+        use chrono::Timelike;
+        match self.timezone {
+            Some(tz) => chrono::Utc::now().with_timezone(&tz).hour(),
+            None => chrono::Local::now().hour(),
         }
     }
 }
@@ -176,6 +2457,45 @@ pub struct GreetingsConfig {
     pub outbound_template: String,
     #[serde(default = "default_outbound_fallback")]
     pub outbound_fallback: String,
+    /// Locale whose pool greets calls (e.g. "es"). Unset uses the built-in
+    /// English greetings.
+    #[serde(default)]
+    pub locale: Option<String>,
+    /// Greeting pools keyed by locale (`[greetings.pools.es]`).
+    #[serde(default)]
+    pub pools: HashMap<String, GreetingPoolConfig>,
+    /// Whether an English pool (`pools.en`) adds to the built-in greetings
+    /// or replaces them.
+    #[serde(default)]
+    pub builtin: BuiltinGreetings,
+    /// Greetings for callers found in `[[contacts]]`.
+    #[serde(default = "default_known_caller_greetings")]
+    pub known_caller: Vec<GreetingTemplate>,
+    /// Greetings for contacts marked `frequent`; no intro.
+    #[serde(default = "default_frequent_caller_greetings")]
+    pub frequent_caller: Vec<GreetingTemplate>,
+}
+
+fn default_known_caller_greetings() -> Vec<GreetingTemplate> {
+    vec![
+        GreetingTemplate::Text("Hey {caller_name}, it's {name}".to_string()),
+        GreetingTemplate::Text("Hi {caller_name}, {name} here".to_string()),
+    ]
+}
+
+fn default_frequent_caller_greetings() -> Vec<GreetingTemplate> {
+    vec![
+        GreetingTemplate::Text("Hey {caller_name}".to_string()),
+        GreetingTemplate::Text("Hi {caller_name}, what's up?".to_string()),
+    ]
+}
+
+#[derive(Debug, Deserialize, Clone, Copy, Default, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum BuiltinGreetings {
+    #[default]
+    Merge,
+    Replace,
 }
 
 impl Default for GreetingsConfig {
@@ -184,8 +2504,62 @@ impl Default for GreetingsConfig {
             inbound: default_inbound_greetings(),
             outbound_template: default_outbound_template(),
             outbound_fallback: default_outbound_fallback(),
+            locale: None,
+            pools: HashMap::new(),
+            builtin: BuiltinGreetings::default(),
+            known_caller: default_known_caller_greetings(),
+            frequent_caller: default_frequent_caller_greetings(),
+        }
+    }
+}
+
+/// Greeting templates for one locale, by time of day. `anytime` entries are
+/// candidates at every hour. Placeholders: `{name}` (entity name),
+/// `{caller_name}` (`identity.caller_name`), `{time}` ("morning", ...).
+#[derive(Debug, Deserialize, Clone, Default)]
+pub struct GreetingPoolConfig {
+    #[serde(default)]
+    pub anytime: Vec<GreetingTemplate>,
+    #[serde(default)]
+    pub morning: Vec<GreetingTemplate>,
+    #[serde(default)]
+    pub afternoon: Vec<GreetingTemplate>,
+    #[serde(default)]
+    pub evening: Vec<GreetingTemplate>,
+    #[serde(default)]
+    pub night: Vec<GreetingTemplate>,
+}
+
+/// A greeting template: plain text, or `{ text = "...", weight = 3 }` to
+/// make it more (or, with 0, never) likely. Plain text has weight 1.
+#[derive(Debug, Deserialize, Clone)]
+#[serde(untagged)]
+pub enum GreetingTemplate {
+    Text(String),
+    Weighted {
+        text: String,
+        #[serde(default = "default_greeting_weight")]
+        weight: u32,
+    },
+}
+
+impl GreetingTemplate {
+    pub fn text(&self) -> &str {
+        match self {
+            Self::Text(text) | Self::Weighted { text, .. } => text,
         }
     }
+
+    pub fn weight(&self) -> u32 {
+        match self {
+            Self::Text(_) => 1,
+            Self::Weighted { weight, .. } => *weight,
+        }
+    }
+}
+
+fn default_greeting_weight() -> u32 {
+    1
 }
 
 fn default_inbound_greetings() -> Vec<String> {
@@ -239,16 +2613,46 @@ impl Config {
         if let Ok(v) = std::env::var("GROQ_API_KEY") {
             config.groq.api_key = v;
         }
+        if let Ok(v) = std::env::var("DEEPGRAM_API_KEY") {
+            config.deepgram.api_key = v;
+        }
+        if let Ok(v) = std::env::var("ASSEMBLYAI_API_KEY") {
+            config.assemblyai.api_key = v;
+        }
+        if let Ok(v) = std::env::var("AZURE_SPEECH_KEY") {
+            config.azure.api_key = v;
+        }
         if let Ok(v) = std::env::var("INWORLD_API_KEY") {
             config.inworld.api_key = v;
         }
+        if let Ok(v) = std::env::var("ELEVENLABS_API_KEY") {
+            config.elevenlabs.api_key = v;
+        }
         if let Ok(v) = std::env::var("ECHO_API_TOKEN") {
             config.api.token = v;
         }
+        if let Ok(v) = std::env::var("BRIDGE_TOKEN") {
+            config.llm.bridge_token = Some(v);
+        }
+        if let Ok(v) = std::env::var("OPENAI_API_KEY") {
+            config.openai.api_key = v;
+        }
+        if let Ok(v) = std::env::var("ANTHROPIC_API_KEY") {
+            config.anthropic.api_key = v;
+        }
+        if let Ok(v) = std::env::var("GEMINI_API_KEY") {
+            config.gemini.api_key = v;
+        }
+        if let Ok(v) = std::env::var("XAI_API_KEY") {
+            config.grok.api_key = v;
+        }
         if let Ok(v) = std::env::var("SERVER_EXTERNAL_URL") {
             config.server.external_url = v;
         }
 
+        config.apply_stt_language();
+        config.fold_reminder_webhook();
+
         // Backward compat: if [greetings] was not set but [llm].greeting was
         // customized, use it as the sole inbound greeting template.
         if config.greetings.inbound == default_inbound_greetings()
@@ -259,6 +2663,23 @@ impl Config {
 
         Ok(config)
     }
+
+    /// Fold `[reminders] webhook_url` into `[[webhooks]]`.
+    fn fold_reminder_webhook(&mut self) {
+        if let Some(url) = self.reminders.webhook_url.take() {
+            let hook = self.reminders.webhook(&url);
+            self.webhooks.push(hook);
+        }
+    }
+
+    /// Fold `[groq] language` into `[language]`.
+    fn apply_stt_language(&mut self) {
+        match self.groq.language.as_deref().map(str::to_lowercase) {
+            Some(ref auto) if auto == "auto" => self.language.detect = true,
+            Some(code) => self.language.default = code,
+            None => {}
+        }
+    }
 }
 
 fn config_dir() -> PathBuf {
@@ -279,3 +2700,128 @@ fn config_path() -> PathBuf {
 
     config_dir().join("config.toml")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn window(start: &str, end: &str) -> QuietHoursConfig {
+        toml::from_str(&format!(
+            "start = {start:?}\nend = {end:?}\n[timezones]\n\"+1\" = \"America/New_York\"\n\"+1808\" = \"Pacific/Honolulu\""
+        ))
+        .unwrap()
+    }
+
+    fn utc(h: u32, m: u32) -> chrono::DateTime<chrono::Utc> {
+        chrono::Utc.with_ymd_and_hms(2025, 1, 15, h, m, 0).unwrap()
+    }
+
+    #[test]
+    fn groq_language_is_language_shorthand() {
+        let mut config: Config = toml::from_str(
+            "[server]\nhost = \"127.0.0.1\"\nport = 0\nexternal_url = \"https://echo.test\"\n\
+             [twilio]\naccount_sid = \"AC\"\nauth_token = \"t\"\nphone_number = \"+1\"\n\
+             [inworld]\napi_key = \"k\"\n[llm]\n[vad]\n[groq]\nlanguage = \"ES\"",
+        )
+        .unwrap();
+        config.apply_stt_language();
+        assert_eq!(config.language.default, "es");
+        assert!(!config.language.detect);
+
+        config.groq.language = Some("auto".into());
+        config.apply_stt_language();
+        assert!(config.language.detect);
+        assert_eq!(config.language.default, "es");
+    }
+
+    #[test]
+    fn reminder_webhook_is_a_webhooks_entry() {
+        let mut config: Config = toml::from_str(
+            "[server]\nhost = \"127.0.0.1\"\nport = 0\nexternal_url = \"https://echo.test\"\n\
+             [twilio]\naccount_sid = \"AC\"\nauth_token = \"t\"\nphone_number = \"+1\"\n\
+             [inworld]\napi_key = \"k\"\n[llm]\n[vad]\n\
+             [reminders]\nwebhook_url = \"https://hooks.test/done\"\nwebhook_token = \"s3cret\"",
+        )
+        .unwrap();
+        config.fold_reminder_webhook();
+        assert!(config.reminders.webhook_url.is_none());
+        let [ref hook] = config.webhooks[..] else {
+            panic!("expected one hook, got {:?}", config.webhooks);
+        };
+        assert_eq!(hook.url, "https://hooks.test/done");
+        assert!(hook.wants("reminder.finished") && !hook.wants("call.started"));
+        assert_eq!(hook.headers["Authorization"], "Bearer s3cret");
+    }
+
+    #[test]
+    fn ollama_gives_up_before_the_brain_budget() {
+        let budget = TimeoutsConfig::default().brain_ms;
+        assert!(OllamaConfig::default().timeout_ms < budget);
+    }
+
+    #[test]
+    fn quiet_hours_span_midnight() {
+        let quiet = window("22:00", "08:00");
+        let tz = Some(chrono_tz::UTC);
+        assert_eq!(quiet.ends_after(utc(3, 0), tz), Some(utc(8, 0)));
+        assert_eq!(
+            quiet.ends_after(utc(23, 30), tz),
+            Some(utc(8, 0) + chrono::Duration::days(1))
+        );
+        assert_eq!(quiet.ends_after(utc(8, 0), tz), None);
+        assert_eq!(quiet.ends_after(utc(12, 0), tz), None);
+        assert_eq!(window("08:00", "08:00").ends_after(utc(8, 0), tz), None);
+    }
+
+    #[test]
+    fn quiet_hours_follow_the_destination() {
+        let quiet = window("13:00", "14:00");
+        assert_eq!(
+            quiet.timezone_for("+12125550100"),
+            Some(chrono_tz::America::New_York)
+        );
+        assert_eq!(
+            quiet.timezone_for("+18085550100"),
+            Some(chrono_tz::Pacific::Honolulu)
+        );
+        assert_eq!(quiet.timezone_for("+34600000000"), None);
+
+        // 13:30 in New York is 18:30 UTC
+        let ny = Some(chrono_tz::America::New_York);
+        assert_eq!(quiet.ends_after(utc(18, 30), ny), Some(utc(19, 0)));
+        assert_eq!(quiet.ends_after(utc(13, 30), ny), None);
+    }
+
+    #[test]
+    fn quiet_hours_ending_in_a_dst_gap_end_an_hour_later() {
+        // Clocks in Madrid jump from 02:00 to 03:00 on 2025-03-30
+        let quiet = window("00:00", "02:30");
+        let madrid = Some(chrono_tz::Europe::Madrid);
+        let now = chrono::Utc
+            .with_ymd_and_hms(2025, 3, 29, 23, 30, 0)
+            .unwrap();
+        let end = chrono::Utc.with_ymd_and_hms(2025, 3, 30, 1, 30, 0).unwrap();
+        assert_eq!(quiet.ends_after(now, madrid), Some(end));
+    }
+
+    #[test]
+    fn brain_chain_falls_back_to_provider() {
+        let llm: LlmConfig = toml::from_str("bridge_url = \"http://localhost:8445\"").unwrap();
+        let brain = BrainConfig::default();
+        assert_eq!(brain.chain(&llm), vec![BrainProvider::Bridge]);
+
+        let brain: BrainConfig = toml::from_str(
+            "provider = \"openai\"\nproviders = [\"bridge\", \"local\"]\n[timeout_ms]\nbridge = 4000",
+        )
+        .unwrap();
+        assert_eq!(brain.provider(&llm), BrainProvider::Bridge);
+        assert_eq!(
+            brain.chain(&llm),
+            vec![BrainProvider::Bridge, BrainProvider::Local]
+        );
+        assert_eq!(brain.budget_ms(Some(BrainProvider::Bridge), 30000), 4000);
+        assert_eq!(brain.budget_ms(Some(BrainProvider::Local), 30000), 30000);
+        assert_eq!(brain.budget_ms(None, 30000), 30000);
+    }
+}
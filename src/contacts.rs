@@ -0,0 +1,69 @@
+//! Known callers (`[[contacts]]`).
+//!
+//! Inbound calls are matched against the contacts by the caller's number so
+//! Echo can greet people it knows by name.
+
+use std::sync::Arc;
+
+use crate::config::ContactConfig;
+use crate::pipeline::language;
+
+#[derive(Debug)]
+pub struct Contact {
+    pub name: String,
+    numbers: Vec<String>,
+    pub frequent: bool,
+    pub language: Option<String>,
+    pub timezone: Option<chrono_tz::Tz>,
+}
+
+impl Contact {
+    pub fn new(contact: &ContactConfig) -> Self {
+        Self {
+            name: contact.name.clone(),
+            numbers: contact.numbers.clone(),
+            frequent: contact.frequent,
+            language: contact.language.as_deref().map(language::of_locale),
+            timezone: contact.timezone,
+        }
+    }
+}
+
+/// All configured contacts.
+#[derive(Default)]
+pub struct Contacts {
+    contacts: Vec<Arc<Contact>>,
+}
+
+impl Contacts {
+    pub fn new(contacts: &[ContactConfig]) -> Self {
+        Self {
+            contacts: contacts.iter().map(|c| Arc::new(Contact::new(c))).collect(),
+        }
+    }
+
+    /// The contact calling from `number`.
+    pub fn for_number(&self, number: &str) -> Option<Arc<Contact>> {
+        self.contacts
+            .iter()
+            .find(|c| c.numbers.iter().any(|n| n == number))
+            .cloned()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn matches_any_number() {
+        let config: Vec<ContactConfig> = vec![toml::from_str(
+            r#"name = "Maria"
+               numbers = ["+34600000001", "+34600000002"]"#,
+        )
+        .unwrap()];
+        let contacts = Contacts::new(&config);
+        assert_eq!(contacts.for_number("+34600000002").unwrap().name, "Maria");
+        assert!(contacts.for_number("+34600000003").is_none());
+    }
+}
@@ -0,0 +1,289 @@
+//! Cron schedules for reminder calls.
+//!
+//! The classic five fields, `minute hour day-of-month month day-of-week`.
+//! Each is `*`, a value, a range `a-b`, a step (`*/n`, `a-b/n`, `a/n`) or a
+//! comma list of those. Months and weekdays also take names (`jan`, `mon`);
+//! weekdays run 0-6 from Sunday, and 7 is Sunday too. As in cron, when both
+//! day fields are restricted a day matching either one fires. `@hourly`,
+//! `@daily`, `@weekly`, `@monthly` and `@yearly` are shorthands.
+
+use std::fmt;
+use std::str::FromStr;
+
+use chrono::{DateTime, Datelike, Duration, NaiveDate, TimeZone, Timelike};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+/// How far ahead to look for a match before giving up (e.g. "0 0 30 2 *").
+const MAX_DAYS: u32 = 366 * 8;
+
+const MONTHS: [&str; 12] = [
+    "jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec",
+];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+#[derive(Debug, thiserror::Error)]
+pub enum CronError {
+    #[error("expected 5 fields (minute hour day month weekday), got {0}")]
+    Fields(usize),
+    #[error("invalid {field} field '{value}'")]
+    Invalid { field: &'static str, value: String },
+}
+
+/// A parsed schedule. Each field is a bitmask of the values it matches.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    source: String,
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl FromStr for Schedule {
+    type Err = CronError;
+
+    fn from_str(source: &str) -> Result<Self, CronError> {
+        let expanded = match source.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            other => other,
+        };
+        let fields: Vec<&str> = expanded.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(CronError::Fields(fields.len()));
+        };
+        // Sunday is both 0 and 7
+        let weekdays = parse_field(weekday, "weekday", 0, 7, &WEEKDAYS)?;
+        Ok(Self {
+            source: source.trim().to_string(),
+            minutes: parse_field(minute, "minute", 0, 59, &[])?,
+            hours: parse_field(hour, "hour", 0, 23, &[])? as u32,
+            days: parse_field(day, "day", 1, 31, &[])? as u32,
+            months: parse_field(month, "month", 1, 12, &MONTHS)? as u16,
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.source)
+    }
+}
+
+impl Serialize for Schedule {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.source)
+    }
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer)?
+            .parse()
+            .map_err(serde::de::Error::custom)
+    }
+}
+
+impl Schedule {
+    /// The first matching minute strictly after `after`, in its timezone.
+    /// Local times skipped by a DST change don't fire; repeated ones fire
+    /// once.
+    pub fn next_after<T: TimeZone>(&self, after: &DateTime<T>) -> Option<DateTime<T>> {
+        let tz = after.timezone();
+        let local = after.naive_local();
+        let start = local.date();
+        for offset in 0..MAX_DAYS {
+            let date = start + Duration::days(offset.into());
+            if !self.matches_date(date) {
+                continue;
+            }
+            for hour in (0..24).filter(|h| self.hours & (1 << h) != 0) {
+                for minute in (0..60).filter(|m| self.minutes & (1 << m) != 0) {
+                    let candidate = date.and_hms_opt(hour, minute, 0)?;
+                    if candidate <= local.with_second(0)?.with_nanosecond(0)? {
+                        continue;
+                    }
+                    if let Some(at) = tz.from_local_datetime(&candidate).earliest() {
+                        if at > *after {
+                            return Some(at);
+                        }
+                    }
+                }
+            }
+        }
+        None
+    }
+
+    fn matches_date(&self, date: NaiveDate) -> bool {
+        if self.months & (1 << date.month()) == 0 {
+            return false;
+        }
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+}
+
+/// One field as a bitmask, bit `n` set when value `n` matches.
+fn parse_field(
+    field: &str,
+    name: &'static str,
+    min: u32,
+    max: u32,
+    names: &[&str],
+) -> Result<u64, CronError> {
+    let invalid = || CronError::Invalid {
+        field: name,
+        value: field.to_string(),
+    };
+    let value = |s: &str| -> Result<u32, CronError> {
+        let lower = s.to_ascii_lowercase();
+        let n = match names.iter().position(|n| *n == lower) {
+            Some(i) => i as u32 + min,
+            None => s.parse().map_err(|_| invalid())?,
+        };
+        if (min..=max).contains(&n) {
+            Ok(n)
+        } else {
+            Err(invalid())
+        }
+    };
+
+    let mut mask = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err(invalid());
+        }
+        let (low, high) = match range.split_once('-') {
+            _ if range == "*" => (min, max),
+            Some((low, high)) => (value(low)?, value(high)?),
+            // "a/n" runs from a to the end
+            None if step > 1 => (value(range)?, max),
+            None => {
+                let n = value(range)?;
+                (n, n)
+            }
+        };
+        if low > high {
+            return Err(invalid());
+        }
+        for n in (low..=high).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+
+    fn at(s: &str) -> DateTime<Utc> {
+        s.parse().unwrap()
+    }
+
+    fn next(schedule: &str, after: &str) -> DateTime<Utc> {
+        schedule
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(&at(after))
+            .unwrap()
+    }
+
+    #[test]
+    fn finds_the_next_match() {
+        // 2025-01-15 is a Wednesday
+        assert_eq!(
+            next("0 9 * * *", "2025-01-15T08:30:00Z"),
+            at("2025-01-15T09:00:00Z")
+        );
+        assert_eq!(
+            next("0 9 * * *", "2025-01-15T09:00:00Z"),
+            at("2025-01-16T09:00:00Z")
+        );
+        assert_eq!(
+            next("*/15 * * * *", "2025-01-15T09:07:30Z"),
+            at("2025-01-15T09:15:00Z")
+        );
+        assert_eq!(
+            next("30 8 * * mon-fri", "2025-01-17T09:00:00Z"),
+            at("2025-01-20T08:30:00Z")
+        );
+        assert_eq!(
+            next("0 12 1 jan,jul *", "2025-01-15T00:00:00Z"),
+            at("2025-07-01T12:00:00Z")
+        );
+        assert_eq!(
+            next("@weekly", "2025-01-15T00:00:00Z"),
+            at("2025-01-19T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn either_day_field_fires_when_both_are_set() {
+        // The 20th, or any Sunday
+        assert_eq!(
+            next("0 0 20 * 7", "2025-01-15T00:00:00Z"),
+            at("2025-01-19T00:00:00Z")
+        );
+        assert_eq!(
+            next("0 0 20 * 0", "2025-01-19T00:00:00Z"),
+            at("2025-01-20T00:00:00Z")
+        );
+    }
+
+    #[test]
+    fn follows_the_timezone() {
+        let madrid = chrono_tz::Europe::Madrid;
+        let schedule: Schedule = "0 9 * * *".parse().unwrap();
+        let after = at("2025-01-15T12:00:00Z").with_timezone(&madrid);
+        assert_eq!(
+            schedule.next_after(&after).unwrap(),
+            at("2025-01-16T08:00:00Z")
+        );
+        // 02:30 doesn't exist in Madrid on 2025-03-30
+        let schedule: Schedule = "30 2 * * *".parse().unwrap();
+        let after = at("2025-03-29T12:00:00Z").with_timezone(&madrid);
+        assert_eq!(
+            schedule.next_after(&after).unwrap(),
+            at("2025-03-31T00:30:00Z")
+        );
+    }
+
+    #[test]
+    fn rejects_bad_schedules() {
+        for bad in [
+            "0 9 * *",
+            "60 * * * *",
+            "* 24 * * *",
+            "* * 0 * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "* * * foo *",
+        ] {
+            assert!(bad.parse::<Schedule>().is_err(), "{bad}");
+        }
+        assert!("0 0 30 2 *"
+            .parse::<Schedule>()
+            .unwrap()
+            .next_after(&Utc::now())
+            .is_none());
+    }
+}
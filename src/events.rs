@@ -0,0 +1,150 @@
+//! In-process call events: lifecycle, and what was said on each turn.
+//!
+//! A broadcast bus carried in `AppState`. Publishing never blocks; events
+//! are dropped when nobody is subscribed, and slow subscribers lag rather
+//! than holding up the call.
+
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+
+use crate::pipeline::turn::Stage;
+use crate::registry::Transport;
+use crate::reminders::RunOutcome;
+
+/// Why a call ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum EndReason {
+    /// Twilio `stop` or Discord `leave`.
+    Hangup,
+    /// The media socket closed or errored.
+    Disconnected,
+    /// No frames for longer than the keepalive idle timeout.
+    Stalled,
+    /// Reaped: the call's stream handler is gone.
+    Orphaned,
+    /// Reaped: the call ran past the configured maximum duration.
+    MaxDuration,
+    /// Hung up from the admin dashboard.
+    Operator,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum CallEvent {
+    /// A media stream started: the call was answered.
+    CallStarted {
+        call_sid: String,
+        transport: Transport,
+    },
+    CallEnded {
+        call_sid: String,
+        reason: EndReason,
+    },
+    /// The caller started speaking: the VAD heard speech, or a live STT
+    /// session its first words.
+    UtteranceStarted {
+        call_sid: String,
+    },
+    /// The utterance under way, as a live STT session (`[stt] streaming`)
+    /// has heard it so far. Later ones revise it.
+    PartialTranscript {
+        call_sid: String,
+        text: String,
+    },
+    /// What the caller said, after the profanity filter.
+    Transcript {
+        call_sid: String,
+        text: String,
+        /// Who said it (from 1), with `[diarization]` on.
+        #[serde(skip_serializing_if = "Option::is_none")]
+        speaker: Option<u32>,
+    },
+    /// What the brain answered, before TTS.
+    Response {
+        call_sid: String,
+        text: String,
+    },
+    /// A turn failed; the caller hears the error message.
+    PipelineError {
+        call_sid: String,
+        /// The stage that failed, if the turn got that far.
+        stage: Option<Stage>,
+        error: String,
+    },
+    /// A reminder run finished: its call answered and ended, went
+    /// unanswered, or couldn't be placed.
+    ReminderFinished(RunOutcome),
+}
+
+impl CallEvent {
+    /// Every `name()`, for validating `[[webhooks]]` and `[publish]`
+    /// `events`.
+    pub const NAMES: [&'static str; 8] = [
+        "call.started",
+        "call.ended",
+        "utterance.started",
+        "utterance.partial",
+        "utterance.transcribed",
+        "brain.responded",
+        "pipeline.error",
+        "reminder.finished",
+    ];
+
+    /// The event's name in `[[webhooks]]` and `[publish]` subscriptions.
+    pub fn name(&self) -> &'static str {
+        match self {
+            CallEvent::CallStarted { .. } => "call.started",
+            CallEvent::CallEnded { .. } => "call.ended",
+            CallEvent::UtteranceStarted { .. } => "utterance.started",
+            CallEvent::PartialTranscript { .. } => "utterance.partial",
+            CallEvent::Transcript { .. } => "utterance.transcribed",
+            CallEvent::Response { .. } => "brain.responded",
+            CallEvent::PipelineError { .. } => "pipeline.error",
+            CallEvent::ReminderFinished(_) => "reminder.finished",
+        }
+    }
+
+    pub fn call_sid(&self) -> &str {
+        match self {
+            CallEvent::CallStarted { call_sid, .. }
+            | CallEvent::CallEnded { call_sid, .. }
+            | CallEvent::UtteranceStarted { call_sid }
+            | CallEvent::PartialTranscript { call_sid, .. }
+            | CallEvent::Transcript { call_sid, .. }
+            | CallEvent::Response { call_sid, .. }
+            | CallEvent::PipelineError { call_sid, .. } => call_sid,
+            // A run that couldn't place a call has none
+            CallEvent::ReminderFinished(outcome) => outcome.call_sid.as_deref().unwrap_or(""),
+        }
+    }
+}
+
+/// Shared event bus. Cheap to clone.
+#[derive(Clone)]
+pub struct EventBus {
+    tx: broadcast::Sender<CallEvent>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (tx, _) = broadcast::channel(256);
+        Self { tx }
+    }
+
+    pub fn publish(&self, event: CallEvent) {
+        tracing::debug!(?event, "Event");
+        // Err only means there are no subscribers
+        let _ = self.tx.send(event);
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<CallEvent> {
+        self.tx.subscribe()
+    }
+}
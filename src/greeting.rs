@@ -1,6 +1,13 @@
-use chrono::{Local, Timelike};
+use std::collections::HashMap;
+
 use rand::seq::SliceRandom;
 
+use crate::config::{
+    BuiltinGreetings, GreetingPoolConfig, GreetingTemplate, GreetingsConfig, IdentityConfig,
+};
+use crate::contacts::Contact;
+use crate::rng::SharedRng;
+
 const ANYTIME: &[&str] = &[
     "Hey, it's {name}",
     "Hi there, {name} here",
@@ -22,42 +29,175 @@ const NIGHT: &[&str] = &[
     "{name} here, burning the midnight oil?",
 ];
 
+/// Part of the day a greeting is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DayPart {
+    Morning,
+    Afternoon,
+    Evening,
+    Night,
+}
+
+impl DayPart {
+    fn of(hour: u32) -> Self {
+        match hour {
+            5..=11 => Self::Morning,
+            12..=16 => Self::Afternoon,
+            17..=20 => Self::Evening,
+            _ => Self::Night,
+        }
+    }
+
+    /// The `{time}` placeholder value.
+    fn word(self) -> &'static str {
+        match self {
+            Self::Morning => "morning",
+            Self::Afternoon => "afternoon",
+            Self::Evening => "evening",
+            Self::Night => "night",
+        }
+    }
+}
+
 fn time_pool(hour: u32) -> &'static [&'static str] {
-    match hour {
-        5..=11 => MORNING,
-        12..=16 => AFTERNOON,
-        17..=20 => EVENING,
-        _ => NIGHT,
+    match DayPart::of(hour) {
+        DayPart::Morning => MORNING,
+        DayPart::Afternoon => AFTERNOON,
+        DayPart::Evening => EVENING,
+        DayPart::Night => NIGHT,
     }
 }
 
-/// Select a greeting based on the current time of day.
+/// Greeting pools loaded from `[greetings.pools]`, keyed by locale.
+///
+/// Templates are picked by weight. The English pool (`en`, also used when no
+/// locale is set) is merged with the built-in English greetings unless
+/// `builtin = "replace"`. A locale with no templates for the current time of
+/// day (and no `anytime` ones) falls back to the built-ins.
 ///
-/// Combines anytime greetings with time-specific ones and picks randomly.
-/// The `{name}` placeholder is replaced with the provided name.
-pub fn select_greeting(name: &str) -> String {
-    let hour = Local::now().hour();
-    select_greeting_for_hour(name, hour)
+/// Picks are random unless seeded with `with_seed` (`[debug] seed`).
+pub struct Greetings {
+    locale: Option<String>,
+    pools: HashMap<String, GreetingPoolConfig>,
+    builtin: BuiltinGreetings,
+    known_caller: Vec<GreetingTemplate>,
+    frequent_caller: Vec<GreetingTemplate>,
+    identity: IdentityConfig,
+    rng: SharedRng,
 }
 
-fn select_greeting_for_hour(name: &str, hour: u32) -> String {
-    let time_specific = time_pool(hour);
-    let mut pool: Vec<&str> = Vec::with_capacity(ANYTIME.len() + time_specific.len());
-    pool.extend_from_slice(ANYTIME);
-    pool.extend_from_slice(time_specific);
+impl Greetings {
+    pub fn new(config: &GreetingsConfig, identity: &IdentityConfig) -> Self {
+        Self {
+            locale: config.locale.clone(),
+            pools: config.pools.clone(),
+            builtin: config.builtin,
+            known_caller: config.known_caller.clone(),
+            frequent_caller: config.frequent_caller.clone(),
+            identity: identity.clone(),
+            rng: SharedRng::default(),
+        }
+    }
+
+    /// Pick from a generator seeded with `seed`, if set, so the same calls
+    /// get the same greetings.
+    pub fn with_seed(mut self, seed: Option<u64>) -> Self {
+        if seed.is_some() {
+            self.rng = SharedRng::new(seed);
+        }
+        self
+    }
+
+    /// Select a greeting for the current time of day (in
+    /// `identity.timezone`) in `locale`, or the configured default locale
+    /// when `None`.
+    pub fn select(&self, name: &str, locale: Option<&str>) -> String {
+        self.select_for_hour(name, locale, self.identity.current_hour())
+    }
+
+    fn select_for_hour(&self, name: &str, locale: Option<&str>, hour: u32) -> String {
+        let part = DayPart::of(hour);
+        let locale = locale.or(self.locale.as_deref()).unwrap_or("en");
+
+        let mut candidates: Vec<(&str, u32)> = Vec::new();
+        if let Some(pool) = self.pool(locale) {
+            let time_specific = match part {
+                DayPart::Morning => &pool.morning,
+                DayPart::Afternoon => &pool.afternoon,
+                DayPart::Evening => &pool.evening,
+                DayPart::Night => &pool.night,
+            };
+            candidates.extend(
+                pool.anytime
+                    .iter()
+                    .chain(time_specific)
+                    .map(|t| (t.text(), t.weight())),
+            );
+        }
+        let english = language(locale) == "en";
+        if candidates.is_empty() || (english && self.builtin == BuiltinGreetings::Merge) {
+            candidates.extend(ANYTIME.iter().chain(time_pool(hour)).map(|t| (*t, 1)));
+        }
+
+        let template = self
+            .rng
+            .with(|rng| candidates.choose_weighted(rng, |(_, weight)| *weight).ok())
+            .map_or(ANYTIME[0], |(text, _)| text);
+        self.render(template, name, &self.identity.caller_name, part)
+    }
+
+    /// Greet a known contact by name. Frequent callers get the short
+    /// greeting without the intro.
+    pub fn select_for_contact(&self, name: &str, contact: &Contact) -> String {
+        self.select_for_contact_at_hour(name, contact, self.identity.current_hour())
+    }
+
+    fn select_for_contact_at_hour(&self, name: &str, contact: &Contact, hour: u32) -> String {
+        let pool = if contact.frequent && !self.frequent_caller.is_empty() {
+            &self.frequent_caller
+        } else {
+            &self.known_caller
+        };
+        match self
+            .rng
+            .with(|rng| pool.choose_weighted(rng, |t| t.weight()).ok())
+        {
+            Some(template) => self.render(template.text(), name, &contact.name, DayPart::of(hour)),
+            None => format!("Hey {}, it's {name}", contact.name),
+        }
+    }
+
+    fn render(&self, template: &str, name: &str, caller_name: &str, part: DayPart) -> String {
+        template
+            .replace("{name}", name)
+            .replace("{caller_name}", caller_name)
+            .replace("{time}", part.word())
+    }
+
+    /// The pool for a locale, falling back from a region ("es-MX") to its
+    /// language ("es").
+    fn pool(&self, locale: &str) -> Option<&GreetingPoolConfig> {
+        self.pools
+            .get(locale)
+            .or_else(|| self.pools.get(language(locale)))
+    }
+}
 
-    let mut rng = rand::thread_rng();
-    let template = pool.choose(&mut rng).unwrap_or(&ANYTIME[0]);
-    template.replace("{name}", name)
+fn language(locale: &str) -> &str {
+    locale.split(['-', '_']).next().unwrap_or(locale)
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    fn builtin() -> Greetings {
+        Greetings::new(&GreetingsConfig::default(), &IdentityConfig::default())
+    }
+
     #[test]
     fn greeting_contains_name() {
-        let greeting = select_greeting_for_hour("TestBot", 10);
+        let greeting = builtin().select_for_hour("TestBot", None, 10);
         assert!(
             greeting.contains("TestBot"),
             "greeting should contain entity name: {greeting}"
@@ -67,7 +207,7 @@ mod tests {
     #[test]
     fn greeting_no_placeholder_leftover() {
         for hour in 0..24 {
-            let greeting = select_greeting_for_hour("Echo", hour);
+            let greeting = builtin().select_for_hour("Echo", None, hour);
             assert!(
                 !greeting.contains("{name}"),
                 "placeholder not replaced at hour {hour}: {greeting}"
@@ -78,7 +218,7 @@ mod tests {
     #[test]
     fn greeting_never_empty() {
         for hour in 0..24 {
-            let greeting = select_greeting_for_hour("X", hour);
+            let greeting = builtin().select_for_hour("X", None, hour);
             assert!(!greeting.is_empty(), "empty greeting at hour {hour}");
         }
     }
@@ -113,6 +253,133 @@ mod tests {
             .any(|g| g.contains("late") || g.contains("midnight")));
     }
 
+    fn ana() -> IdentityConfig {
+        IdentityConfig {
+            caller_name: "Ana".into(),
+            ..IdentityConfig::default()
+        }
+    }
+
+    fn spanish() -> Greetings {
+        let config: GreetingsConfig = toml::from_str(
+            r#"
+            locale = "es"
+            [pools.es]
+            morning = ["Buenos días, soy {name}"]
+            "#,
+        )
+        .unwrap();
+        Greetings::new(&config, &ana())
+    }
+
+    #[test]
+    fn locale_pool_used_for_its_day_part() {
+        let greetings = spanish();
+        assert_eq!(
+            greetings.select_for_hour("Eco", None, 9),
+            "Buenos días, soy Eco"
+        );
+        assert_eq!(
+            greetings.select_for_hour("Eco", Some("es-MX"), 9),
+            "Buenos días, soy Eco"
+        );
+    }
+
+    #[test]
+    fn locale_pool_falls_back_to_builtin() {
+        let greetings = spanish();
+        // No Spanish evening templates
+        let evening = greetings.select_for_hour("Eco", None, 19);
+        assert!(ANYTIME
+            .iter()
+            .chain(EVENING)
+            .any(|t| t.replace("{name}", "Eco") == evening));
+        // Unknown locale
+        let other = greetings.select_for_hour("Eco", Some("fr"), 9);
+        assert!(ANYTIME
+            .iter()
+            .chain(MORNING)
+            .any(|t| t.replace("{name}", "Eco") == other));
+    }
+
+    #[test]
+    fn english_pool_weights_and_placeholders() {
+        let config: GreetingsConfig = toml::from_str(
+            r#"
+            builtin = "replace"
+            [pools.en]
+            anytime = [
+                { text = "Good {time}, {caller_name}, {name} here", weight = 5 },
+                { text = "never", weight = 0 },
+            ]
+            "#,
+        )
+        .unwrap();
+        let greetings = Greetings::new(&config, &ana());
+        for _ in 0..20 {
+            assert_eq!(
+                greetings.select_for_hour("Echo", None, 9),
+                "Good morning, Ana, Echo here"
+            );
+        }
+    }
+
+    #[test]
+    fn english_pool_merges_with_builtin_by_default() {
+        let config: GreetingsConfig = toml::from_str(
+            r#"
+            [pools.en]
+            anytime = ["Custom {name}"]
+            "#,
+        )
+        .unwrap();
+        let greetings = Greetings::new(&config, &ana());
+        let seen: std::collections::HashSet<String> = (0..200)
+            .map(|_| greetings.select_for_hour("Echo", None, 9))
+            .collect();
+        assert!(seen.contains("Custom Echo"));
+        assert!(seen.len() > 1);
+    }
+
+    #[test]
+    fn seeded_picks_repeat() {
+        let config: GreetingsConfig = toml::from_str(
+            r#"
+            [pools.en]
+            anytime = ["One {name}", "Two {name}", "Three {name}"]
+            "#,
+        )
+        .unwrap();
+        let picks = |seed| {
+            let greetings = Greetings::new(&config, &ana()).with_seed(Some(seed));
+            (0..20)
+                .map(|hour| greetings.select_for_hour("Echo", None, hour % 24))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(picks(7), picks(7));
+        assert_ne!(picks(7), picks(8));
+    }
+
+    #[test]
+    fn contacts_greeted_by_name() {
+        let greetings = Greetings::new(&GreetingsConfig::default(), &ana());
+        let config: crate::config::ContactConfig = toml::from_str(
+            r#"name = "Maria"
+               numbers = ["+34600000001"]"#,
+        )
+        .unwrap();
+        let mut contact = Contact::new(&config);
+        let known = greetings.select_for_contact_at_hour("Echo", &contact, 9);
+        assert!(known.contains("Maria") && known.contains("Echo"), "{known}");
+
+        contact.frequent = true;
+        let frequent = greetings.select_for_contact_at_hour("Echo", &contact, 9);
+        assert!(
+            frequent.contains("Maria") && !frequent.contains("Echo"),
+            "{frequent}"
+        );
+    }
+
     #[test]
     fn time_pool_boundaries() {
         // 4 AM = night, 5 AM = morning, 11 AM = morning, 12 PM = afternoon
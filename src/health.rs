@@ -0,0 +1,145 @@
+//! Dependency health derived from the provider circuit breakers.
+//!
+//! `GET /health` stays a cheap liveness probe; `GET /health/deep` reports
+//! each external dependency and answers 503 when a call couldn't succeed
+//! or the server is draining.
+//! The same check lets `/twilio/voice` turn callers away politely instead
+//! of connecting a stream that will only fail.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+
+use axum::extract::State;
+use axum::http::StatusCode;
+use axum::Json;
+use serde::Serialize;
+
+use crate::agents::Agent;
+use crate::breaker::{BreakerState, CircuitBreaker};
+use crate::tenants::Tenant;
+use crate::warmup::WarmupStatus;
+use crate::{AppState, Brain};
+
+#[derive(Debug, Serialize)]
+pub struct DependencyStatus {
+    pub name: Cow<'static, str>,
+    /// Breaker state, or `None` for dependencies without a breaker.
+    pub circuit: Option<BreakerState>,
+    pub up: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DeepHealth {
+    pub healthy: bool,
+    /// Turning new calls away (`/admin`); reported unhealthy so load
+    /// balancers move traffic elsewhere.
+    pub draining: bool,
+    pub dependencies: Vec<DependencyStatus>,
+    /// The startup brain warmup (`[warmup]`). Doesn't affect `healthy`.
+    pub warmup: WarmupStatus,
+}
+
+fn status(name: impl Into<Cow<'static, str>>, breaker: &CircuitBreaker) -> DependencyStatus {
+    let circuit = breaker.state();
+    DependencyStatus {
+        name: name.into(),
+        circuit: Some(circuit),
+        up: circuit != BreakerState::Open,
+    }
+}
+
+fn brain_status(name: impl Into<Cow<'static, str>>, brain: &Brain) -> DependencyStatus {
+    let name = name.into();
+    match brain {
+        Brain::Bridge(bridge) => status(name, bridge.breaker()),
+        Brain::Chat(chat) => status(name, chat.breaker()),
+        Brain::Local(_) => DependencyStatus {
+            name,
+            circuit: None,
+            up: true,
+        },
+        #[cfg(feature = "mocks")]
+        Brain::Mock(_) => DependencyStatus {
+            name,
+            circuit: None,
+            up: true,
+        },
+    }
+}
+
+pub fn dependencies(state: &AppState) -> Vec<DependencyStatus> {
+    let mut deps = vec![
+        status("stt", state.stt.breaker()),
+        status("tts", state.tts.breaker()),
+        brain_status("brain", &state.brain),
+    ];
+    if let Some(fallback) = state.stt.fallback() {
+        deps.push(status("fallback_stt", fallback.breaker()));
+    }
+    if !Arc::ptr_eq(&state.stt, &state.discord_stt) {
+        deps.push(status("discord_stt", state.discord_stt.breaker()));
+    }
+    if let Some(ref fallback) = state.fallback {
+        // "fallback_brain", "fallback_brain_2", ... in the order they're tried
+        for (i, brain) in fallback.brains().iter().enumerate() {
+            let name = match i {
+                0 => Cow::Borrowed("fallback_brain"),
+                _ => Cow::Owned(format!("fallback_brain_{}", i + 1)),
+            };
+            deps.push(brain_status(name, brain));
+        }
+    }
+    deps.push(status("twilio", state.twilio.breaker()));
+    deps
+}
+
+/// The first dependency a call needs that is known to be down: STT, TTS,
+/// or the brain (STT and the brain only when their fallbacks are down too).
+/// A call for `tenant` uses its providers, and one handled by `agent` its
+/// brain, if they have their own.
+pub fn known_dead(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    agent: Option<&Agent>,
+) -> Option<&'static str> {
+    let (stt, tts) = match tenant {
+        Some(tenant) => (&tenant.stt, &tenant.tts),
+        None => (&state.stt, &state.tts),
+    };
+    if stt.down() {
+        return Some("stt");
+    }
+    if tts.breaker().state() == BreakerState::Open {
+        return Some("tts");
+    }
+    if brain_down(state, agent) {
+        return Some("brain");
+    }
+    None
+}
+
+/// True when the brain answering `agent`'s calls (its own, or the shared
+/// one) and any fallback are both known to be down.
+pub fn brain_down(state: &AppState, agent: Option<&Agent>) -> bool {
+    let brain = agent.and_then(|a| a.brain.as_ref()).unwrap_or(&state.brain);
+    let fallback_up = state.fallback.as_ref().is_some_and(|fb| fb.reachable());
+    !brain.reachable() && !fallback_up
+}
+
+/// Handle GET /health/deep.
+pub async fn handle_deep_health(State(state): State<AppState>) -> (StatusCode, Json<DeepHealth>) {
+    let draining = state.is_draining();
+    let healthy = known_dead(&state, None, None).is_none() && !draining;
+    let code = if healthy {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    let body = DeepHealth {
+        healthy,
+        draining,
+        dependencies: dependencies(&state),
+        warmup: state.warmup.status(),
+    };
+    (code, Json(body))
+}
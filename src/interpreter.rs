@@ -0,0 +1,206 @@
+//! Interpreter mode: two phone legs bridged through a translator.
+//!
+//! Each utterance on one leg is transcribed in that leg's language,
+//! translated by the brain into the other leg's language and spoken into
+//! the other leg. Neither leg talks to Echo itself.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::sync::RwLock;
+
+use crate::pipeline::turn;
+use crate::registry::CallRegistry;
+use crate::twilio::outbound::TwilioClient;
+use crate::AppState;
+
+/// One side of an interpreted call.
+#[derive(Clone)]
+pub struct Leg {
+    /// Language spoken on this leg (ISO 639-1).
+    pub language: String,
+    /// call_sid of the other leg, once it's been placed.
+    pub peer: Option<String>,
+    /// Language spoken on the other leg.
+    pub peer_language: String,
+    /// Tenant that placed the call, if any.
+    pub tenant: Option<String>,
+    /// Client that placed the legs, used to hang up the other one.
+    pub twilio: Arc<TwilioClient>,
+}
+
+/// Active interpreter pairs, keyed by either leg's call_sid.
+#[derive(Clone, Default)]
+pub struct Interpreter {
+    legs: Arc<RwLock<HashMap<String, Leg>>>,
+}
+
+impl Interpreter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Treat a placed call as a leg before its peer is placed, so it's
+    /// never greeted as an ordinary call if it's answered first.
+    pub async fn hold(
+        &self,
+        call_sid: String,
+        language: String,
+        peer_language: String,
+        tenant: Option<String>,
+        twilio: Arc<TwilioClient>,
+    ) {
+        let leg = Leg {
+            language,
+            peer: None,
+            peer_language,
+            tenant,
+            twilio,
+        };
+        self.legs.write().await.insert(call_sid, leg);
+    }
+
+    /// Bridge a held leg with its placed peer. Returns false if the held
+    /// leg has ended in the meantime.
+    pub async fn pair(&self, call_sid: &str, peer: String) -> bool {
+        let mut legs = self.legs.write().await;
+        let Some(held) = legs.get_mut(call_sid) else {
+            return false;
+        };
+        held.peer = Some(peer.clone());
+        let other = Leg {
+            language: held.peer_language.clone(),
+            peer: Some(call_sid.to_string()),
+            peer_language: held.language.clone(),
+            tenant: held.tenant.clone(),
+            twilio: Arc::clone(&held.twilio),
+        };
+        legs.insert(peer, other);
+        true
+    }
+
+    /// The interpreter leg for `call_sid`, if the call is one.
+    pub async fn leg(&self, call_sid: &str) -> Option<Leg> {
+        self.legs.read().await.get(call_sid).cloned()
+    }
+
+    /// Unpair a leg and its peer. Returns the leg if the call was one.
+    pub async fn remove(&self, call_sid: &str) -> Option<Leg> {
+        let mut legs = self.legs.write().await;
+        let leg = legs.remove(call_sid)?;
+        if let Some(ref peer) = leg.peer {
+            legs.remove(peer);
+        }
+        Some(leg)
+    }
+}
+
+/// Translate an utterance from `call_sid` and speak it into the peer leg.
+///
+/// Dropped with a log line if the peer hasn't been placed or hasn't
+/// connected yet.
+pub async fn relay(
+    pcm_data: &[i16],
+    call_sid: &str,
+    leg: &Leg,
+    state: &AppState,
+) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+    let Some(ref peer_sid) = leg.peer else {
+        tracing::warn!(call_sid, "Interpreter peer not placed yet, dropping");
+        return Ok(());
+    };
+    let Some(translation) =
+        turn::run_translation(pcm_data, call_sid, &leg.language, &leg.peer_language, state).await?
+    else {
+        return Ok(());
+    };
+    let Some(peer) = state.call_registry.get(peer_sid).await else {
+        tracing::warn!(call_sid, peer = %peer_sid, "Interpreter peer not connected, dropping");
+        return Ok(());
+    };
+
+    // Spoken in the peer's voice and language
+    let mulaw = turn::synthesize(state, peer_sid, &translation).await?;
+    peer.set_speaking(true);
+    if let Err(e) = CallRegistry::send_audio(&peer, &mulaw).await {
+        peer.set_speaking(false);
+        return Err(e);
+    }
+    tracing::info!(call_sid, peer = %peer_sid, "Relayed translation");
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::breaker::CircuitBreaker;
+    use crate::config::{BreakerConfig, TwilioConfig};
+
+    fn twilio() -> Arc<TwilioClient> {
+        let config = TwilioConfig {
+            account_sid: "AC".into(),
+            auth_token: "token".into(),
+            phone_number: "+15550000000".into(),
+        };
+        let breaker = CircuitBreaker::new("twilio", &BreakerConfig::default());
+        Arc::new(TwilioClient::new(
+            reqwest::Client::new(),
+            &config,
+            "https://example.com",
+            breaker,
+        ))
+    }
+
+    /// `CA1` (Spanish) held and paired with `CA2` (English).
+    async fn paired() -> Interpreter {
+        let interpreter = Interpreter::new();
+        interpreter
+            .hold("CA1".into(), "es".into(), "en".into(), None, twilio())
+            .await;
+        assert!(interpreter.pair("CA1", "CA2".into()).await);
+        interpreter
+    }
+
+    #[tokio::test]
+    async fn legs_point_at_each_other() {
+        let interpreter = paired().await;
+
+        let a = interpreter.leg("CA1").await.unwrap();
+        assert_eq!(
+            (a.peer.as_deref(), a.language.as_str()),
+            (Some("CA2"), "es")
+        );
+        assert_eq!(a.peer_language, "en");
+        let b = interpreter.leg("CA2").await.unwrap();
+        assert_eq!(
+            (b.peer.as_deref(), b.language.as_str()),
+            (Some("CA1"), "en")
+        );
+        assert_eq!(b.peer_language, "es");
+    }
+
+    #[tokio::test]
+    async fn held_leg_is_a_leg_before_its_peer_is_placed() {
+        let interpreter = Interpreter::new();
+        interpreter
+            .hold("CA1".into(), "es".into(), "en".into(), None, twilio())
+            .await;
+        let held = interpreter.leg("CA1").await.unwrap();
+        assert_eq!((held.peer, held.language.as_str()), (None, "es"));
+
+        // Hung up before the peer was placed: nothing left to pair
+        assert!(interpreter.remove("CA1").await.is_some());
+        assert!(!interpreter.pair("CA1", "CA2".into()).await);
+        assert!(interpreter.leg("CA2").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn removing_one_leg_unpairs_both() {
+        let interpreter = paired().await;
+
+        let removed = interpreter.remove("CA2").await.unwrap();
+        assert_eq!(removed.peer.as_deref(), Some("CA1"));
+        assert!(interpreter.leg("CA1").await.is_none());
+        assert!(interpreter.remove("CA1").await.is_none());
+    }
+}
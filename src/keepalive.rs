@@ -0,0 +1,119 @@
+//! Keepalive pings and stall detection for media stream sockets.
+//!
+//! Both the Twilio and Discord handlers ping their socket periodically and
+//! track when the last frame (of any kind, including pongs) arrived. A
+//! socket that goes quiet for longer than the idle timeout is treated as
+//! half-dead: the handler closes it and tears the call down, instead of
+//! leaking the registry entry and brain session.
+
+use std::time::Duration;
+
+use tokio::time::Instant;
+
+use crate::config::KeepaliveConfig;
+
+/// What the stream handler should do when [`Keepalive::tick`] fires.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeepaliveAction {
+    /// Send a WebSocket ping.
+    Ping,
+    /// No frames for longer than the idle timeout — close the socket.
+    Stalled,
+}
+
+pub struct Keepalive {
+    ping_every: Option<Duration>,
+    idle_timeout: Option<Duration>,
+    last_frame: Instant,
+    last_ping: Instant,
+}
+
+impl Keepalive {
+    pub fn new(config: &KeepaliveConfig) -> Self {
+        let now = Instant::now();
+        Self {
+            ping_every: (config.ping_interval_secs > 0)
+                .then(|| Duration::from_secs(config.ping_interval_secs)),
+            idle_timeout: (config.idle_timeout_secs > 0)
+                .then(|| Duration::from_secs(config.idle_timeout_secs)),
+            last_frame: now,
+            last_ping: now,
+        }
+    }
+
+    /// Record that a frame arrived from the peer.
+    pub fn frame_received(&mut self) {
+        self.last_frame = Instant::now();
+    }
+
+    /// How long the socket has been silent.
+    pub fn idle(&self) -> Duration {
+        self.last_frame.elapsed()
+    }
+
+    /// Wait until the next ping or stall check is due.
+    /// Never resolves if both are disabled.
+    pub async fn tick(&mut self) -> KeepaliveAction {
+        match self.next_action() {
+            Some((at, action)) => {
+                tokio::time::sleep_until(at).await;
+                if action == KeepaliveAction::Ping {
+                    self.last_ping = Instant::now();
+                }
+                action
+            }
+            None => std::future::pending().await,
+        }
+    }
+
+    fn next_action(&self) -> Option<(Instant, KeepaliveAction)> {
+        let ping = self
+            .ping_every
+            .map(|every| (self.last_ping + every, KeepaliveAction::Ping));
+        let stall = self
+            .idle_timeout
+            .map(|timeout| (self.last_frame + timeout, KeepaliveAction::Stalled));
+        match (ping, stall) {
+            (Some(p), Some(s)) => Some(if s.0 <= p.0 { s } else { p }),
+            (p, s) => p.or(s),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(ping: u64, idle: u64) -> KeepaliveConfig {
+        KeepaliveConfig {
+            ping_interval_secs: ping,
+            idle_timeout_secs: idle,
+        }
+    }
+
+    #[test]
+    fn pings_before_stall() {
+        let ka = Keepalive::new(&config(15, 60));
+        let (at, action) = ka.next_action().unwrap();
+        assert_eq!(action, KeepaliveAction::Ping);
+        assert_eq!(at, ka.last_ping + Duration::from_secs(15));
+    }
+
+    #[test]
+    fn stalls_when_frames_stop() {
+        let mut ka = Keepalive::new(&config(15, 60));
+        // Pings keep going out but nothing comes back
+        ka.last_ping = ka.last_frame + Duration::from_secs(55);
+        assert_eq!(ka.next_action().unwrap().1, KeepaliveAction::Stalled);
+    }
+
+    #[test]
+    fn disabled_never_fires() {
+        assert!(Keepalive::new(&config(0, 0)).next_action().is_none());
+        let watchdog_only = Keepalive::new(&config(0, 30));
+        assert_eq!(
+            watchdog_only.next_action().unwrap().1,
+            KeepaliveAction::Stalled
+        );
+    }
+}
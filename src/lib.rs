@@ -5,37 +5,100 @@
 //! and text-to-speech (Inworld). It can be used as a standalone binary or as a
 //! library dependency in echo-system.
 
+pub mod agents;
 pub mod api;
+pub mod breaker;
+pub mod capture;
+pub mod chaos;
 pub mod config;
+pub mod contacts;
+pub mod cron;
 pub mod discord;
+pub mod events;
 pub mod greeting;
+pub mod health;
+pub mod interpreter;
+pub mod keepalive;
+pub mod memory;
+#[cfg(feature = "mocks")]
+pub mod mocks;
+pub mod persist;
 pub mod pipeline;
+pub mod preflight;
+pub mod publish;
+pub mod reaper;
 pub mod registry;
+pub mod reminders;
+pub mod retranscribe;
+pub mod rng;
+pub mod scheduler;
+pub mod tenants;
 pub mod twilio;
+pub mod warmup;
+pub mod webhooks;
 
 use std::any::Any;
 use std::collections::HashMap;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::Duration;
 
-use axum::routing::{get, post};
+use agents::{Agent, Agents};
+use axum::routing::{delete, get, post};
 use axum::Router;
 use echo_system_types::llm::LmProvider;
 use echo_system_types::plugin::{Plugin, PluginContext, PluginResult, PluginRole};
 use echo_system_types::{HealthStatus, PluginMeta, SetupPrompt};
-use tokio::sync::Mutex;
+use tokio::sync::RwLock;
 use tower_http::trace::TraceLayer;
 
-use config::Config;
-use pipeline::audio;
+use breaker::CircuitBreaker;
+use chaos::FaultInjector;
+use config::{
+    AssemblyAiConfig, BrainProvider, Config, DeepgramConfig, FaultConfig, HttpConfig, SttVendor,
+    TenantConfig, TtsVendor, TwilioConfig,
+};
+use contacts::Contacts;
+use events::{CallEvent, EndReason, EventBus};
+use greeting::Greetings;
+use interpreter::Interpreter;
+use memory::Memory;
+use pipeline::anthropic::AnthropicChat;
+use pipeline::assemblyai::AssemblyAiStt;
+use pipeline::azure::AzureStt;
+use pipeline::azure_tts::AzureTts;
 use pipeline::bridge::BridgeClient;
+use pipeline::bridge_auth::BridgeAuth;
+use pipeline::chat::{ChatClient, ChatProvider};
 use pipeline::conversation::ConversationManager;
-use pipeline::stt::SttClient;
-use pipeline::tts::TtsClient;
-use registry::CallRegistry;
+use pipeline::deepgram::DeepgramStt;
+use pipeline::elevenlabs::ElevenLabsTts;
+use pipeline::failover::Fallback;
+use pipeline::gemini::GeminiChat;
+use pipeline::history::SessionsDir;
+use pipeline::hold_music::{HoldAnnouncements, HoldMusic};
+use pipeline::limiter::TurnLimiter;
+#[cfg(feature = "local-llm")]
+use pipeline::local_llm::LocalLlm;
+use pipeline::middleware::{BrainMiddleware, Middleware};
+use pipeline::ollama::OllamaChat;
+use pipeline::openai::OpenAiChat;
+use pipeline::openai_tts::OpenAiTts;
+use pipeline::privacy::PrivacyMask;
+use pipeline::profanity::ProfanityFilter;
+use pipeline::queue::BrainQueue;
+use pipeline::stt::{GroqStt, SttClient, SttProvider};
+use pipeline::tts::{InworldTts, TtsClient, TtsProvider};
+use pipeline::tts_cache::TtsCache;
+use registry::{CallProfile, CallRegistry, Transport};
+use reminders::Reminders;
+use scheduler::Scheduler;
+use tenants::{Tenant, Tenants};
 use twilio::outbound::TwilioClient;
+use warmup::Warmup;
 
 /// How LLM communication is routed for a call.
 #[derive(Clone)]
@@ -44,12 +107,103 @@ pub enum Brain {
     Local(Arc<ConversationManager>),
     /// Forwarded to bridge-echo multiplexer.
     Bridge(Arc<BridgeClient>),
+    /// A chat API or a model run in-process, history kept here
+    /// (`[brain] provider`).
+    Chat(Arc<ChatClient>),
+    /// Scripted responses (`[mocks] brain`).
+    #[cfg(feature = "mocks")]
+    Mock(Arc<mocks::MockBrain>),
+}
+
+impl Brain {
+    /// The `[brain]` provider this is; `None` for the mock.
+    pub fn provider(&self) -> Option<BrainProvider> {
+        match self {
+            Brain::Local(_) => Some(BrainProvider::Local),
+            Brain::Bridge(_) => Some(BrainProvider::Bridge),
+            Brain::Chat(chat) => Some(chat.provider()),
+            #[cfg(feature = "mocks")]
+            Brain::Mock(_) => None,
+        }
+    }
+
+    /// False when the brain is known to be down (its circuit is open).
+    pub fn reachable(&self) -> bool {
+        match self {
+            Brain::Bridge(bridge) => bridge.breaker().state() != breaker::BreakerState::Open,
+            Brain::Chat(chat) => chat.breaker().state() != breaker::BreakerState::Open,
+            Brain::Local(_) => true,
+            #[cfg(feature = "mocks")]
+            Brain::Mock(_) => true,
+        }
+    }
+
+    /// Summarize the call's conversation if it's due
+    /// (`[llm] summarize_after_turns`). Bridge-echo keeps its own.
+    pub async fn summarize(&self, call_sid: &str) {
+        match self {
+            Brain::Local(conversation) => conversation.summarize(call_sid).await,
+            Brain::Chat(chat) => chat.summarize(call_sid).await,
+            Brain::Bridge(_) => {}
+            #[cfg(feature = "mocks")]
+            Brain::Mock(_) => {}
+        }
+    }
+
+    /// Ask a one-off question outside any call's conversation, under a
+    /// throwaway session `key` that's dropped afterwards.
+    pub async fn ask_once(
+        &self,
+        key: &str,
+        prompt: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let response = match self {
+            Brain::Bridge(bridge) => bridge
+                .send(key, prompt, &Default::default())
+                .await
+                .map_err(Into::into),
+            Brain::Local(conversation) => conversation
+                .send_with_system(key, prompt, None)
+                .await
+                .map_err(Into::into),
+            Brain::Chat(chat) => chat
+                .send_with_system(key, prompt, None)
+                .await
+                .map_err(Into::into),
+            #[cfg(feature = "mocks")]
+            Brain::Mock(mock) => mock.send(prompt).await.map_err(Into::into),
+        };
+        self.end_session(key).await;
+        response
+    }
+
+    /// Drop any per-call history (call ended). Bridge-echo keeps its own.
+    pub async fn end_session(&self, call_sid: &str) {
+        match self {
+            Brain::Local(conversation) => conversation.end_session(call_sid).await,
+            Brain::Chat(chat) => chat.end_session(call_sid).await,
+            Brain::Bridge(_) => {}
+            #[cfg(feature = "mocks")]
+            Brain::Mock(_) => {}
+        }
+    }
 }
 
 /// Metadata for an outbound call — context and reason injected into the first prompt.
 pub struct CallMeta {
     pub context: Option<String>,
     pub reason: Option<String>,
+    /// Named agent placing the call (`[[agents]]`).
+    pub agent: Option<String>,
+    /// Tenant that placed the call (`[[tenants]]`).
+    pub tenant: Option<String>,
+    /// Language for the whole call (ISO 639-1), overriding agent, contact
+    /// and detection.
+    pub language: Option<String>,
+    /// Added to the brain's system prompt for the whole call.
+    pub system_prompt: Option<String>,
+    /// Tools the brain may use on the call, replacing `llm.allowed_tools`.
+    pub allowed_tools: Option<Vec<String>>,
 }
 
 /// Shared application state accessible from all handlers.
@@ -57,15 +211,157 @@ pub struct CallMeta {
 pub struct AppState {
     pub config: Config,
     pub stt: Arc<SttClient>,
+    /// STT for Discord sessions: `stt` unless `[stt.discord]` picks another
+    /// model than `[stt.phone]`.
+    pub discord_stt: Arc<SttClient>,
     pub tts: Arc<TtsClient>,
     pub brain: Brain,
+    /// Tried in order when the primary brain errors or times out (the rest
+    /// of `[brain] providers`, then `[llm.fallback]`).
+    pub fallback: Option<Arc<Fallback>>,
+    /// The default system prompt (`llm.self_path`), empty if there is none.
+    pub system_prompt: Arc<str>,
     pub twilio: Arc<TwilioClient>,
     pub call_registry: CallRegistry,
-    /// Pre-converted mu-law hold music data, if configured.
-    pub hold_music: Option<Arc<Vec<u8>>>,
+    /// Hold music from `[hold_music]`, if any of it could be loaded.
+    pub hold_music: Option<Arc<HoldMusic>>,
+    /// Spoken reminders on hold, from `[hold_announcements]`.
+    pub hold_announcements: Option<Arc<HoldAnnouncements>>,
     /// Metadata for outbound calls, keyed by call_sid.
     /// Consumed on first utterance so the LLM knows why it called.
-    pub call_metas: Arc<Mutex<HashMap<String, CallMeta>>>,
+    pub call_metas: Arc<RwLock<HashMap<String, CallMeta>>>,
+    /// Global gate bounding concurrent STT/brain/TTS turns across all calls.
+    pub turn_limiter: TurnLimiter,
+    /// One brain request at a time per call.
+    pub brain_queue: BrainQueue,
+    /// Hooks around the brain (`VoiceEcho::with_middleware`).
+    pub middleware: Middleware,
+    /// Call lifecycle events.
+    pub events: EventBus,
+    /// Named agents from `[[agents]]`.
+    pub agents: Arc<Agents>,
+    /// Tenants from `[[tenants]]`.
+    pub tenants: Arc<Tenants>,
+    /// Greeting pools from `[greetings]`.
+    pub greetings: Arc<Greetings>,
+    /// Known callers from `[[contacts]]`.
+    pub contacts: Arc<Contacts>,
+    /// Call pairs bridged in interpreter mode.
+    pub interpreter: Interpreter,
+    /// Profanity filter from `[profanity]`.
+    pub profanity: Arc<ProfanityFilter>,
+    /// Pooled HTTP client from `[http]`, shared by every outbound client.
+    pub http: reqwest::Client,
+    /// Set from `/admin`: turn new Twilio calls away, let live ones finish.
+    pub draining: Arc<AtomicBool>,
+    /// Outbound calls deferred by `[outbound] quiet_hours`.
+    pub scheduler: Arc<Scheduler>,
+    /// Recurring calls from `/api/reminders`.
+    pub reminders: Arc<Reminders>,
+    /// Callers remembered between calls (`[memory]`).
+    pub memory: Arc<Memory>,
+    /// How the startup brain warmup went (`[warmup]`).
+    pub warmup: Warmup,
+}
+
+impl AppState {
+    /// True once `/admin` has started draining the server.
+    pub fn is_draining(&self) -> bool {
+        self.draining.load(Ordering::Relaxed)
+    }
+
+    /// STT client for a call on `transport`: its tenant's, or the shared
+    /// one.
+    pub fn stt_for<'a>(&'a self, profile: &'a CallProfile, transport: Transport) -> &'a SttClient {
+        match (transport, &profile.tenant) {
+            (Transport::Twilio, Some(tenant)) => &tenant.stt,
+            (Transport::Twilio, None) => &self.stt,
+            (Transport::Discord, Some(tenant)) => &tenant.discord_stt,
+            (Transport::Discord, None) => &self.discord_stt,
+        }
+    }
+
+    /// TTS voice for a call, if it differs from the default: a mid-call
+    /// switch, then the voice mapped to the call's language, then the agent's.
+    pub fn voice_for<'a>(&'a self, profile: &'a CallProfile) -> Option<&'a str> {
+        let language_voice = || {
+            let language = profile.language.as_ref()?;
+            self.config.tts.voices.get(language).map(String::as_str)
+        };
+        profile
+            .voice_override
+            .as_deref()
+            .or_else(language_voice)
+            .or_else(|| profile.voice_id())
+    }
+
+    /// TTS client for a call: its tenant's, or the shared one.
+    pub fn tts_for<'a>(&'a self, profile: &'a CallProfile) -> &'a TtsClient {
+        profile.tenant.as_ref().map_or(&self.tts, |t| &t.tts)
+    }
+
+    /// Ask `brain` a one-off question outside any call (see
+    /// [`Brain::ask_once`]), telling its bridge-echo the throwaway session
+    /// is over afterwards.
+    pub async fn ask_once(
+        &self,
+        brain: &Brain,
+        key: &str,
+        prompt: &str,
+    ) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+        let response = brain.ask_once(key, prompt).await;
+        if let Brain::Bridge(bridge) = brain {
+            bridge.notify_call_ended(key).await;
+        }
+        response
+    }
+
+    /// Drop the call's history from every brain that may hold it: the
+    /// agent's own, if it has one, the shared brain and the fallbacks. An
+    /// agent's bridge-echo is told the call is over.
+    pub async fn end_sessions(&self, call_sid: &str, agent: Option<&Agent>) {
+        if let Some(brain) = agent.and_then(|a| a.brain.as_ref()) {
+            brain.end_session(call_sid).await;
+            if let Brain::Bridge(bridge) = brain {
+                bridge.notify_call_ended(call_sid).await;
+            }
+        }
+        self.brain.end_session(call_sid).await;
+        if let Some(ref fallback) = self.fallback {
+            fallback.end_session(call_sid).await;
+        }
+    }
+
+    /// Tear down a call: release its registry entry, end brain sessions,
+    /// tell bridge-echo the call is over and publish `CallEnded`. The
+    /// caller's `[memory]` is updated in the background.
+    ///
+    /// Only the first call for a given call_sid does anything, so the stream
+    /// handler and the reaper can both call this safely. An interpreter leg
+    /// takes its peer down with it.
+    pub async fn end_call(&self, call_sid: &str, reason: EndReason) {
+        if let Some(leg) = self.interpreter.remove(call_sid).await {
+            if let Some(ref peer) = leg.peer {
+                if let Err(e) = leg.twilio.hangup(peer).await {
+                    tracing::warn!(call_sid, peer = %peer, "Failed to hang up interpreter peer: {e}");
+                }
+            }
+        }
+        let Some(profile) = self.call_registry.deregister(call_sid).await else {
+            return;
+        };
+        let (state, sid) = (self.clone(), call_sid.to_string());
+        tokio::spawn(async move { memory::remember(&state, &sid).await });
+        self.end_sessions(call_sid, profile.agent.as_deref()).await;
+        if let Some(ref url) = self.config.llm.bridge_url {
+            let auth = BridgeAuth::from_config(&self.config.llm);
+            pipeline::notify::notify_call_ended(&self.http, url, &auth, call_sid).await;
+        }
+        self.events.publish(CallEvent::CallEnded {
+            call_sid: call_sid.to_string(),
+            reason,
+        });
+    }
 }
 
 /// The voice-echo plugin. Manages the voice pipeline lifecycle.
@@ -74,6 +370,7 @@ pub struct VoiceEcho {
     provider: Option<Arc<dyn LmProvider>>,
     state: Option<AppState>,
     shutdown_tx: Option<tokio::sync::oneshot::Sender<()>>,
+    middleware: Middleware,
 }
 
 impl VoiceEcho {
@@ -84,32 +381,43 @@ impl VoiceEcho {
             provider: None,
             state: None,
             shutdown_tx: None,
+            middleware: Middleware::default(),
         }
     }
 
-    /// Start the voice server. Builds state, binds the listener, and serves.
-    /// This blocks until the server is shut down via `stop()`.
+    /// Run `middleware` around the brain on every conversation turn. Call
+    /// before `start`; hooks run in the order they were added.
+    pub fn with_middleware(mut self, middleware: impl BrainMiddleware + 'static) -> Self {
+        self.middleware.push(Arc::new(middleware));
+        self
+    }
+
+    /// Start the voice server. Binds `[server] host:port`, builds state,
+    /// and serves. This blocks until the server is shut down via `stop()`.
     pub async fn start(&mut self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        let addr: SocketAddr = format!("{}:{}", self.config.server.host, self.config.server.port)
+            .parse()
+            .map_err(|e| format!("Invalid server address: {e}"))?;
+
+        tracing::info!(%addr, "Listening");
+
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        self.serve(listener).await
+    }
+
+    /// Like `start`, on an already-bound listener (e.g. port 0 in tests).
+    pub async fn serve(
+        &mut self,
+        listener: tokio::net::TcpListener,
+    ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         let config = &self.config;
+        let http = http_client(&config.http)
+            .map_err(|e| format!("Failed to build the [http] client: {e}"))?;
 
         // Load hold music if configured
         let hold_music = config.hold_music.as_ref().and_then(|hm| {
-            let path = std::path::Path::new(&hm.file);
-            match audio::load_wav_as_mulaw(path, hm.volume) {
-                Ok(data) => {
-                    tracing::info!(
-                        path = %hm.file,
-                        volume = hm.volume,
-                        mulaw_bytes = data.len(),
-                        "Loaded hold music"
-                    );
-                    Some(Arc::new(data))
-                }
-                Err(e) => {
-                    tracing::warn!(path = %hm.file, "Failed to load hold music: {e}");
-                    None
-                }
-            }
+            HoldMusic::from_config(hm, &http, config.audio.frame_bytes(), config.debug.seed)
+                .map(Arc::new)
         });
 
         // Build system prompt from SELF.md if configured
@@ -120,65 +428,248 @@ impl VoiceEcho {
             .and_then(|path| std::fs::read_to_string(path).ok())
             .unwrap_or_default();
 
-        // Determine brain mode
-        let brain = if let Some(ref bridge_url) = config.llm.bridge_url {
-            Brain::Bridge(Arc::new(BridgeClient::new(
-                bridge_url,
-                config.identity.caller_name.clone(),
-            )))
-        } else if let Some(ref provider) = self.provider {
-            Brain::Local(Arc::new(ConversationManager::new(
-                Arc::clone(provider),
-                system_prompt,
-                config.llm.session_timeout_secs,
-                config.llm.max_response_tokens,
-            )))
+        if config.mocks.any() && cfg!(not(feature = "mocks")) {
+            tracing::warn!("[mocks] is set but this build lacks the mocks feature; ignoring");
+        }
+        if config.chaos.enabled {
+            tracing::warn!(
+                "[chaos] enabled: provider requests will be delayed and failed on purpose"
+            );
+        }
+
+        // Determine brain mode: the first of `[brain] providers` answers,
+        // the rest are fallbacks in order
+        let chain = config.brain.chain(&config.llm);
+        let brain = if let Some(brain) = mock_brain(config) {
+            tracing::warn!("Using the mock brain");
+            brain
         } else {
-            return Err("No LLM provider available. Set bridge_url or run as a plugin.".into());
+            let chaos = fault_injector(config, "brain", &config.chaos.brain);
+            build_brain(
+                config,
+                &http,
+                chain[0],
+                self.provider.as_ref(),
+                &system_prompt,
+                chaos,
+            )?
         };
 
+        let mut fallbacks = Vec::new();
+        for &provider in &chain[1..] {
+            // [chaos.brain] never faults a fallback, so failover can be watched
+            fallbacks.push(build_brain(
+                config,
+                &http,
+                provider,
+                self.provider.as_ref(),
+                &system_prompt,
+                FaultInjector::default(),
+            )?);
+        }
+        if let Some(ref fb) = config.llm.fallback {
+            if let Some(ref bridge_url) = fb.bridge_url {
+                fallbacks.push(Brain::Bridge(Arc::new(bridge_client(
+                    &http,
+                    bridge_url,
+                    CircuitBreaker::new("fallback-bridge", &config.breaker),
+                    config,
+                ))));
+            } else if let (Brain::Bridge(_), Some(provider), true) =
+                (&brain, &self.provider, fallbacks.is_empty())
+            {
+                fallbacks.push(Brain::Local(Arc::new(
+                    ConversationManager::new(
+                        Arc::clone(provider),
+                        system_prompt.clone(),
+                        config.llm.session_timeout_secs,
+                        config.llm.max_response_tokens,
+                    )
+                    .with_timeout(config.llm.invoke_timeout_ms)
+                    .with_retries(config.llm.invoke_retries)
+                    .with_sessions_dir(Some(sessions_dir(config, "local")))
+                    .with_summary_after(config.llm.summarize_after_turns),
+                )));
+            } else if fallbacks.is_empty() {
+                tracing::warn!("[llm.fallback] set but no distinct fallback brain available");
+            }
+        }
+        let fallback = (!fallbacks.is_empty()).then(|| {
+            tracing::info!(count = fallbacks.len(), "Fallback brains configured");
+            let notice = config
+                .llm
+                .fallback
+                .as_ref()
+                .map(|fb| fb.notice.clone())
+                .unwrap_or_default();
+            Arc::new(Fallback::new(fallbacks, notice))
+        });
+
+        let agents = Agents::new(
+            config
+                .agents
+                .iter()
+                .map(|agent| {
+                    let brain = agent.bridge_url.as_ref().map(|url| {
+                        let breaker =
+                            CircuitBreaker::new(format!("bridge:{}", agent.name), &config.breaker);
+                        let chaos = fault_injector(config, "brain", &config.chaos.brain);
+                        Brain::Bridge(Arc::new(
+                            bridge_client(&http, url, breaker, config).with_chaos(chaos),
+                        ))
+                    });
+                    Agent::new(agent, config, brain)
+                })
+                .collect(),
+        );
+
+        let stt = Arc::new(stt_client(&http, None, Transport::Twilio, config));
+        let discord_stt = match config.stt.discord == config.stt.phone {
+            true => Arc::clone(&stt),
+            false => Arc::new(stt_client(&http, None, Transport::Discord, config)),
+        };
+        let tts_cache = config
+            .tts
+            .cache
+            .as_ref()
+            .map(|c| Arc::new(TtsCache::new(c)));
+        let tts = Arc::new(tts_client(&http, None, tts_cache.clone(), config));
+        let twilio = Arc::new(twilio_client(&http, &config.twilio, "twilio", config));
+
+        let tenants = Tenants::new(
+            config
+                .tenants
+                .iter()
+                .map(|tenant| {
+                    let name = &tenant.name;
+                    let own_stt_key = std::iter::once(config.stt.provider)
+                        .chain(config.stt.fallback)
+                        .any(|vendor| tenant_stt_key(tenant, vendor).is_some());
+                    let (stt, discord_stt) = match own_stt_key {
+                        true => {
+                            let stt = Arc::new(stt_client(
+                                &http,
+                                Some(tenant),
+                                Transport::Twilio,
+                                config,
+                            ));
+                            let discord_stt = match config.stt.discord == config.stt.phone {
+                                true => Arc::clone(&stt),
+                                false => Arc::new(stt_client(
+                                    &http,
+                                    Some(tenant),
+                                    Transport::Discord,
+                                    config,
+                                )),
+                            };
+                            (stt, discord_stt)
+                        }
+                        false => (Arc::clone(&stt), Arc::clone(&discord_stt)),
+                    };
+                    let tts = match tenant_tts_key(tenant, config.tts.provider) {
+                        Some(_) => {
+                            Arc::new(tts_client(&http, Some(tenant), tts_cache.clone(), config))
+                        }
+                        None => Arc::clone(&tts),
+                    };
+                    let twilio = match tenant.twilio {
+                        Some(ref account) => Arc::new(twilio_client(
+                            &http,
+                            account,
+                            &format!("twilio:{name}"),
+                            config,
+                        )),
+                        None => Arc::clone(&twilio),
+                    };
+                    tracing::info!(tenant = %name, "Tenant configured");
+                    Tenant::new(tenant, stt, tts, twilio).with_discord_stt(discord_stt)
+                })
+                .collect(),
+        );
+
         // Build shared state
         let state = AppState {
-            stt: Arc::new(SttClient::new(
-                config.groq.api_key.clone(),
-                config.groq.model.clone(),
-            )),
-            tts: Arc::new(TtsClient::new(
-                config.inworld.api_key.clone(),
-                config.inworld.voice_id.clone(),
-                config.inworld.model.clone(),
-            )),
+            stt,
+            discord_stt,
+            tts,
             brain,
-            twilio: Arc::new(TwilioClient::new(
-                &config.twilio,
-                &config.server.external_url,
-            )),
-            call_registry: CallRegistry::new(),
+            fallback,
+            system_prompt: system_prompt.into(),
+            twilio,
+            call_registry: CallRegistry::new().with_frame_bytes(config.audio.frame_bytes()),
             config: config.clone(),
             hold_music,
-            call_metas: Arc::new(Mutex::new(HashMap::new())),
+            hold_announcements: HoldAnnouncements::from_config(&config.hold_announcements)
+                .map(Arc::new),
+            call_metas: Arc::new(RwLock::new(HashMap::new())),
+            turn_limiter: TurnLimiter::new(config.pipeline.max_concurrent_turns),
+            brain_queue: BrainQueue::default(),
+            middleware: self.middleware.clone(),
+            events: EventBus::new(),
+            agents: Arc::new(agents),
+            tenants: Arc::new(tenants),
+            greetings: Arc::new(
+                Greetings::new(&config.greetings, &config.identity).with_seed(config.debug.seed),
+            ),
+            contacts: Arc::new(Contacts::new(&config.contacts)),
+            interpreter: Interpreter::new(),
+            profanity: Arc::new(ProfanityFilter::new(&config.profanity)),
+            http,
+            draining: Arc::new(AtomicBool::new(false)),
+            scheduler: Arc::new(Scheduler::load(config.outbound.schedule_file.clone())),
+            reminders: Arc::new(Reminders::load(config.reminders.file.clone())),
+            memory: Arc::new(
+                Memory::new(&config.memory)
+                    .with_mask(PrivacyMask::new(&config.privacy, &config.profanity)),
+            ),
+            warmup: Warmup::new(&config.warmup),
         };
 
-        self.state = Some(state.clone());
-
-        let app = self.build_router(state);
+        if config.preflight.enabled {
+            let checks = preflight::run(&state).await;
+            if let Err(e) = preflight::report(&checks) {
+                if config.preflight.strict {
+                    return Err(e.into());
+                }
+                tracing::warn!("Starting anyway (preflight.strict = false)");
+            }
+        }
 
-        let addr: SocketAddr = format!("{}:{}", config.server.host, config.server.port)
-            .parse()
-            .map_err(|e| format!("Invalid server address: {e}"))?;
+        self.state = Some(state.clone());
 
-        tracing::info!(%addr, "Listening");
-
-        let listener = tokio::net::TcpListener::bind(addr).await?;
+        let app = self.build_router(state.clone());
 
         let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
         self.shutdown_tx = Some(shutdown_tx);
 
-        axum::serve(listener, app)
+        let mut tasks = vec![
+            tokio::spawn(reaper::run(state.clone())),
+            tokio::spawn(warmup::run(state.clone())),
+        ];
+        if let Some(ref publish) = config.publish {
+            tasks.push(tokio::spawn(publish::run(
+                state.events.clone(),
+                publish.clone(),
+            )));
+        }
+        for hook in &config.webhooks {
+            tasks.push(tokio::spawn(webhooks::run(
+                state.http.clone(),
+                state.events.clone(),
+                hook.clone(),
+            )));
+        }
+        tasks.push(tokio::spawn(scheduler::run(state)));
+
+        let result = axum::serve(listener, app)
             .with_graceful_shutdown(async {
                 let _ = shutdown_rx.await;
             })
-            .await?;
+            .await;
+        for task in tasks {
+            task.abort();
+        }
+        result?;
 
         Ok(())
     }
@@ -270,7 +761,14 @@ impl VoiceEcho {
     }
 
     fn build_router(&self, state: AppState) -> Router {
-        Router::new()
+        let router = Router::new();
+        let router = if state.config.debug.pipeline {
+            tracing::warn!("Debug endpoint /debug/pipeline enabled");
+            router.route("/debug/pipeline", post(api::debug::handle_pipeline))
+        } else {
+            router
+        };
+        router
             .route("/twilio/voice", post(twilio::webhook::handle_voice))
             .route(
                 "/twilio/voice/outbound",
@@ -278,17 +776,490 @@ impl VoiceEcho {
             )
             .route("/twilio/media", get(twilio::media::handle_media_upgrade))
             .route("/api/call", post(api::outbound::handle_call))
+            .route("/api/dtmf", post(api::dtmf::handle_dtmf))
             .route("/api/inject", post(api::inject::handle_inject))
+            .route("/api/interpret", post(api::interpret::handle_interpret))
+            .route("/api/scheduled", get(api::scheduled::handle_list))
+            .route("/api/scheduled/{id}", delete(api::scheduled::handle_cancel))
+            .route(
+                "/api/reminders",
+                get(api::reminders::handle_list).post(api::reminders::handle_create),
+            )
+            .route(
+                "/api/reminders/{id}",
+                get(api::reminders::handle_get).delete(api::reminders::handle_delete),
+            )
+            .route("/api/reminders/{id}/run", post(api::reminders::handle_run))
             .route(
                 "/discord-stream",
                 get(discord::stream::handle_discord_upgrade),
             )
+            .route("/admin", get(api::admin::handle_page))
+            .route("/admin/calls", get(api::admin::handle_calls))
+            .route(
+                "/admin/calls/{call_sid}/hangup",
+                post(api::admin::handle_hangup),
+            )
+            .route(
+                "/admin/calls/{call_sid}/inject",
+                post(api::admin::handle_inject),
+            )
+            .route("/admin/drain", post(api::admin::handle_drain))
+            .route("/admin/events", get(api::admin::handle_events))
             .route("/health", get(health_handler))
+            .route("/health/deep", get(health::handle_deep_health))
             .layer(TraceLayer::new_for_http())
             .with_state(state)
     }
 }
 
+/// Build the pooled HTTP client every outbound client shares.
+pub(crate) fn http_client(config: &HttpConfig) -> Result<reqwest::Client, reqwest::Error> {
+    let builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(config.connect_timeout_ms))
+        .pool_idle_timeout(Duration::from_secs(config.pool_idle_timeout_secs))
+        .pool_max_idle_per_host(config.pool_max_idle_per_host)
+        .tcp_keepalive(Duration::from_secs(30))
+        .tcp_nodelay(true);
+    let builder = if config.http2 {
+        // Keep idle HTTP/2 connections warm between a call's turns
+        builder
+            .http2_adaptive_window(true)
+            .http2_keep_alive_interval(Duration::from_secs(30))
+            .http2_keep_alive_while_idle(true)
+    } else {
+        builder.http1_only()
+    };
+    builder.build()
+}
+
+/// Build a bridge-echo client with the `[llm]` transport settings.
+fn bridge_client(
+    http: &reqwest::Client,
+    bridge_url: &str,
+    breaker: CircuitBreaker,
+    config: &Config,
+) -> BridgeClient {
+    let llm = &config.llm;
+    let client = BridgeClient::new(
+        http.clone(),
+        bridge_url,
+        config.identity.caller_name.clone(),
+        breaker,
+    )
+    .with_timeout(llm.bridge_timeout_ms)
+    .with_retries(llm.bridge_retries, llm.bridge_retry_delay_ms)
+    .with_max_retry_delay(llm.bridge_retry_max_delay_ms)
+    .with_mcp_config(llm.mcp_config.clone())
+    .with_auth(BridgeAuth::from_config(llm));
+    let client = if llm.bridge_streaming {
+        client.with_streaming()
+    } else {
+        client
+    };
+    if llm.bridge_websocket {
+        client.with_websocket()
+    } else {
+        client
+    }
+}
+
+/// Build the brain for one `[brain]` provider.
+fn build_brain(
+    config: &Config,
+    http: &reqwest::Client,
+    provider: BrainProvider,
+    local: Option<&Arc<dyn LmProvider>>,
+    system_prompt: &str,
+    chaos: FaultInjector,
+) -> Result<Brain, Box<dyn std::error::Error + Send + Sync>> {
+    Ok(match (provider, &config.llm.bridge_url, local) {
+        (BrainProvider::Bridge, Some(bridge_url), _) => Brain::Bridge(Arc::new(
+            bridge_client(
+                http,
+                bridge_url,
+                CircuitBreaker::new("bridge", &config.breaker),
+                config,
+            )
+            .with_chaos(chaos),
+        )),
+        (BrainProvider::Bridge, None, _) => {
+            return Err("[brain] provider \"bridge\" needs llm.bridge_url".into());
+        }
+        (BrainProvider::Local, _, Some(local)) => Brain::Local(Arc::new(
+            ConversationManager::new(
+                Arc::clone(local),
+                system_prompt.to_string(),
+                config.llm.session_timeout_secs,
+                config.llm.max_response_tokens,
+            )
+            .with_timeout(config.llm.invoke_timeout_ms)
+            .with_retries(config.llm.invoke_retries)
+            .with_chaos(chaos)
+            .with_sessions_dir(Some(sessions_dir(config, "local")))
+            .with_summary_after(config.llm.summarize_after_turns),
+        )),
+        (BrainProvider::Local, _, None) => {
+            return Err("No LLM provider available. Set bridge_url or run as a plugin.".into());
+        }
+        (BrainProvider::OpenAi, _, _) => {
+            if config.openai.api_key.is_empty() {
+                return Err("[brain] provider \"openai\" needs openai.api_key".into());
+            }
+            tracing::info!(model = %config.openai.model, "Using the OpenAI brain");
+            chat_brain(
+                config,
+                OpenAiChat::new(http.clone(), &config.openai),
+                "openai",
+                system_prompt,
+                chaos,
+            )
+        }
+        (BrainProvider::Ollama, _, _) => {
+            tracing::info!(
+                model = %config.ollama.model,
+                url = %config.ollama.base_url,
+                "Using the Ollama brain"
+            );
+            chat_brain(
+                config,
+                OllamaChat::new(http.clone(), &config.ollama),
+                "ollama",
+                system_prompt,
+                chaos,
+            )
+        }
+        (BrainProvider::Anthropic, _, _) => {
+            if config.anthropic.api_key.is_empty() {
+                return Err("[brain] provider \"anthropic\" needs anthropic.api_key".into());
+            }
+            tracing::info!(model = %config.anthropic.model, "Using the Anthropic API brain");
+            chat_brain(
+                config,
+                AnthropicChat::new(http.clone(), &config.anthropic),
+                "anthropic",
+                system_prompt,
+                chaos,
+            )
+        }
+        (BrainProvider::Gemini, _, _) => {
+            if config.gemini.api_key.is_empty() {
+                return Err("[brain] provider \"gemini\" needs gemini.api_key".into());
+            }
+            tracing::info!(model = %config.gemini.model, "Using the Gemini brain");
+            chat_brain(
+                config,
+                GeminiChat::new(http.clone(), &config.gemini),
+                "gemini",
+                system_prompt,
+                chaos,
+            )
+        }
+        (BrainProvider::Grok, _, _) => {
+            if config.grok.api_key.is_empty() {
+                return Err("[brain] provider \"grok\" needs grok.api_key".into());
+            }
+            tracing::info!(model = %config.grok.model, "Using the Grok brain");
+            chat_brain(
+                config,
+                OpenAiChat::grok(http.clone(), &config.grok),
+                "grok",
+                system_prompt,
+                chaos,
+            )
+        }
+        #[cfg(feature = "local-llm")]
+        (BrainProvider::LocalLlm, _, _) => {
+            let Some(ref local_llm) = config.local_llm else {
+                return Err("[brain] provider \"local_llm\" needs [local_llm]".into());
+            };
+            chat_brain(
+                config,
+                LocalLlm::load(local_llm, config.debug.seed)?,
+                "local_llm",
+                system_prompt,
+                chaos,
+            )
+        }
+        #[cfg(not(feature = "local-llm"))]
+        (BrainProvider::LocalLlm, _, _) => {
+            return Err(
+                "[brain] provider \"local_llm\" needs a build with --features local-llm".into(),
+            );
+        }
+    })
+}
+
+/// The brain for a chat vendor, `name` in its breaker and sessions dir.
+fn chat_brain(
+    config: &Config,
+    provider: impl ChatProvider + 'static,
+    name: &str,
+    system_prompt: &str,
+    chaos: FaultInjector,
+) -> Brain {
+    Brain::Chat(Arc::new(
+        ChatClient::new(
+            Box::new(provider),
+            system_prompt.to_string(),
+            config.llm.session_timeout_secs,
+            config.llm.max_response_tokens,
+            CircuitBreaker::new(name, &config.breaker),
+        )
+        .with_chaos(chaos)
+        .with_sessions_dir(Some(sessions_dir(config, name)))
+        .with_summary_after(config.llm.summarize_after_turns),
+    ))
+}
+
+/// Where a brain keeps its conversations (`[llm] sessions_dir/<brain>/`),
+/// masked per `[privacy]`.
+fn sessions_dir(config: &Config, brain: &str) -> SessionsDir {
+    SessionsDir {
+        dir: config.llm.sessions_dir.join(brain),
+        mask: PrivacyMask::new(&config.privacy, &config.profanity),
+    }
+}
+
+/// Build the `[stt]` client for calls on `transport`: the vendor, with the
+/// channel's model, then any fallback vendor. `tenant` is set for a tenant
+/// with its own keys.
+fn stt_client(
+    http: &reqwest::Client,
+    tenant: Option<&TenantConfig>,
+    transport: Transport,
+    config: &Config,
+) -> SttClient {
+    let channel = match transport {
+        Transport::Twilio => &config.stt.phone,
+        Transport::Discord => &config.stt.discord,
+    };
+    let vendor = config.stt.provider;
+    let client = stt_vendor_client(http, vendor, channel.model.as_deref(), tenant, config);
+    match config.stt.fallback {
+        Some(vendor) => client.with_fallback(stt_vendor_client(http, vendor, None, tenant, config)),
+        None => client,
+    }
+}
+
+/// A tenant's own key for an STT vendor.
+fn tenant_stt_key(tenant: &TenantConfig, vendor: SttVendor) -> Option<&str> {
+    match vendor {
+        SttVendor::Groq => tenant.groq_api_key.as_deref(),
+        SttVendor::Deepgram => tenant.deepgram_api_key.as_deref(),
+        SttVendor::AssemblyAi => tenant.assemblyai_api_key.as_deref(),
+        SttVendor::Azure => tenant.azure_speech_key.as_deref(),
+    }
+}
+
+/// One STT vendor's client, with `[stt]` retries, mocked under
+/// `[mocks] stt` in a mocks build. `model` overrides the vendor block's.
+pub(crate) fn stt_vendor_client(
+    http: &reqwest::Client,
+    vendor: SttVendor,
+    model: Option<&str>,
+    tenant: Option<&TenantConfig>,
+    config: &Config,
+) -> SttClient {
+    let api_key = tenant.and_then(|t| tenant_stt_key(t, vendor));
+    let vendor_diarization = config.diarization.as_ref().is_some_and(|d| d.vendor);
+    let provider: Box<dyn SttProvider> = match vendor {
+        SttVendor::Groq => Box::new(
+            GroqStt::new(
+                http.clone(),
+                api_key.unwrap_or(&config.groq.api_key).to_string(),
+                model.unwrap_or(&config.groq.model).to_string(),
+            )
+            .with_vocabulary(config.stt.vocabulary.clone()),
+        ),
+        SttVendor::Deepgram => Box::new(
+            DeepgramStt::new(
+                http.clone(),
+                api_key.unwrap_or(&config.deepgram.api_key).to_string(),
+                &DeepgramConfig {
+                    model: model.unwrap_or(&config.deepgram.model).to_string(),
+                    ..config.deepgram.clone()
+                },
+            )
+            .with_vocabulary(config.stt.vocabulary.clone())
+            .with_diarization(vendor_diarization),
+        ),
+        SttVendor::AssemblyAi => Box::new(
+            AssemblyAiStt::new(
+                http.clone(),
+                api_key.unwrap_or(&config.assemblyai.api_key).to_string(),
+                &AssemblyAiConfig {
+                    speech_model: model
+                        .map(String::from)
+                        .or_else(|| config.assemblyai.speech_model.clone()),
+                    ..config.assemblyai.clone()
+                },
+            )
+            .with_vocabulary(config.stt.vocabulary.clone())
+            .with_diarization(vendor_diarization),
+        ),
+        SttVendor::Azure => Box::new(AzureStt::new(
+            http.clone(),
+            api_key.unwrap_or(&config.azure.api_key).to_string(),
+            &config.azure,
+        )),
+    };
+    let mut breaker_name = match tenant {
+        Some(tenant) => format!("{}:{}", provider.name(), tenant.name),
+        None => provider.name().to_string(),
+    };
+    // A channel's own model gets its own breaker
+    if let Some(model) = model {
+        breaker_name = format!("{breaker_name}:{model}");
+    }
+    let client = SttClient::new(provider, CircuitBreaker::new(breaker_name, &config.breaker))
+        .with_retries(config.stt.retries, config.stt.retry_delay_ms)
+        .with_max_retry_delay(config.stt.retry_max_delay_ms)
+        .with_timeout(config.stt.timeout_ms)
+        .with_chaos(fault_injector(config, "stt", &config.chaos.stt));
+    #[cfg(feature = "mocks")]
+    let client = if config.mocks.stt {
+        client.with_mock(mocks::MockStt::new(&config.mocks))
+    } else {
+        client
+    };
+    client
+}
+
+fn twilio_client(
+    http: &reqwest::Client,
+    account: &TwilioConfig,
+    breaker_name: &str,
+    config: &Config,
+) -> TwilioClient {
+    let client = TwilioClient::new(
+        http.clone(),
+        account,
+        &config.server.external_url,
+        CircuitBreaker::new(breaker_name, &config.breaker),
+    );
+    #[cfg(feature = "mocks")]
+    let client = if config.mocks.twilio {
+        client.with_mock(mocks::MockTwilio::new(&config.mocks))
+    } else {
+        client
+    };
+    client
+}
+
+/// The scripted brain, under `[mocks] brain` in a mocks build.
+#[cfg(feature = "mocks")]
+fn mock_brain(config: &Config) -> Option<Brain> {
+    config.mocks.brain.then(|| {
+        let chaos = fault_injector(config, "brain", &config.chaos.brain);
+        Brain::Mock(Arc::new(
+            mocks::MockBrain::new(&config.mocks).with_chaos(chaos),
+        ))
+    })
+}
+
+/// `faults` for `stage` while `[chaos]` is enabled; otherwise none.
+fn fault_injector(config: &Config, stage: &'static str, faults: &FaultConfig) -> FaultInjector {
+    if !config.chaos.enabled {
+        return FaultInjector::default();
+    }
+    FaultInjector::new(stage, faults, config.debug.seed)
+}
+
+#[cfg(not(feature = "mocks"))]
+fn mock_brain(_config: &Config) -> Option<Brain> {
+    None
+}
+
+/// A tenant's own key for a TTS vendor.
+fn tenant_tts_key(tenant: &TenantConfig, vendor: TtsVendor) -> Option<&str> {
+    match vendor {
+        TtsVendor::Inworld => tenant.inworld_api_key.as_deref(),
+        TtsVendor::ElevenLabs => tenant.elevenlabs_api_key.as_deref(),
+        TtsVendor::OpenAi => tenant.openai_api_key.as_deref(),
+        TtsVendor::Azure => tenant.azure_speech_key.as_deref(),
+    }
+}
+
+/// Build the `[tts] provider` client with its block's voice and retry
+/// settings and the shared `[tts.cache]`, mocked under `[mocks] tts` in a
+/// mocks build. `tenant` is set for a tenant with its own key.
+fn tts_client(
+    http: &reqwest::Client,
+    tenant: Option<&TenantConfig>,
+    cache: Option<Arc<TtsCache>>,
+    config: &Config,
+) -> TtsClient {
+    let vendor = config.tts.provider;
+    let api_key = tenant.and_then(|t| tenant_tts_key(t, vendor));
+    let (inworld, elevenlabs) = (&config.inworld, &config.elevenlabs);
+    let (openai, azure) = (&config.openai, &config.azure);
+    let provider: Box<dyn TtsProvider> = match vendor {
+        TtsVendor::Inworld => Box::new(InworldTts::new(
+            http.clone(),
+            api_key.unwrap_or(&inworld.api_key).to_string(),
+            inworld.model.clone(),
+        )),
+        TtsVendor::ElevenLabs => Box::new(ElevenLabsTts::new(
+            http.clone(),
+            api_key.unwrap_or(&elevenlabs.api_key).to_string(),
+            elevenlabs,
+        )),
+        TtsVendor::OpenAi => Box::new(OpenAiTts::new(
+            http.clone(),
+            api_key.unwrap_or(&openai.api_key).to_string(),
+            openai,
+        )),
+        TtsVendor::Azure => Box::new(AzureTts::new(
+            http.clone(),
+            api_key.unwrap_or(&azure.api_key).to_string(),
+            azure,
+        )),
+    };
+    let (voice_id, retries, backoff_ms) = match vendor {
+        TtsVendor::Inworld => (
+            &inworld.voice_id,
+            inworld.chunk_retries,
+            inworld.retry_backoff_ms,
+        ),
+        TtsVendor::ElevenLabs => (
+            &elevenlabs.voice_id,
+            elevenlabs.chunk_retries,
+            elevenlabs.retry_backoff_ms,
+        ),
+        TtsVendor::OpenAi => (
+            &openai.voice,
+            config.tts.chunk_retries,
+            config.tts.retry_backoff_ms,
+        ),
+        TtsVendor::Azure => (
+            &azure.voice,
+            config.tts.chunk_retries,
+            config.tts.retry_backoff_ms,
+        ),
+    };
+    let breaker_name = match tenant {
+        Some(tenant) => format!("{}:{}", provider.name(), tenant.name),
+        None => provider.name().to_string(),
+    };
+    let client = TtsClient::new(
+        provider,
+        voice_id.clone(),
+        CircuitBreaker::new(breaker_name, &config.breaker),
+    )
+    .with_retries(retries, backoff_ms)
+    .with_loudness(config.tts.loudness.clone())
+    .with_cache(cache)
+    .with_chaos(fault_injector(config, "tts", &config.chaos.tts));
+    #[cfg(feature = "mocks")]
+    let client = if config.mocks.tts {
+        client.with_mock(mocks::MockTts::new(&config.mocks))
+    } else {
+        client
+    };
+    client
+}
+
 /// Factory function — creates a fully initialized voice-echo plugin.
 pub async fn create(
     config: &serde_json::Value,
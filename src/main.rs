@@ -1,6 +1,8 @@
 mod setup;
 
+use voice_echo::capture;
 use voice_echo::config::Config;
+use voice_echo::retranscribe;
 use voice_echo::VoiceEcho;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -11,6 +13,22 @@ fn main() {
     match args.get(1).map(|s| s.as_str()) {
         Some("--setup") => setup::run(),
         Some("--version") => println!("voice-echo {VERSION}"),
+        Some("--replay") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("Usage: voice-echo --replay <capture.jsonl>");
+                std::process::exit(1);
+            };
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+            rt.block_on(replay(path));
+        }
+        Some("--retranscribe") => {
+            let Some(path) = args.get(2) else {
+                eprintln!("Usage: voice-echo --retranscribe <capture.jsonl> [model]");
+                std::process::exit(1);
+            };
+            let rt = tokio::runtime::Runtime::new().expect("Failed to create tokio runtime");
+            rt.block_on(retranscribe(path, args.get(3).map(String::as_str)));
+        }
         Some("--help") | Some("-h") => print_usage(),
         Some(other) => {
             eprintln!("Unknown option: {other}");
@@ -31,29 +49,38 @@ fn print_usage() {
     println!("Usage: voice-echo [OPTIONS]");
     println!();
     println!("Options:");
-    println!("  --setup     Run interactive configuration wizard");
-    println!("  --version   Print version");
-    println!("  --help, -h  Print this help message");
+    println!("  --setup           Run interactive configuration wizard");
+    println!("  --replay <file>   Re-run a captured call against the configured providers");
+    println!("  --retranscribe <file> [model]");
+    println!("                    Transcribe a captured call again, with another STT model");
+    println!("  --version         Print version");
+    println!("  --help, -h        Print this help message");
     println!();
     println!("Without options, starts the voice server.");
 }
 
-async fn server() {
-    // Initialize tracing
+fn init_tracing() {
     tracing_subscriber::fmt()
         .with_env_filter(
             tracing_subscriber::EnvFilter::try_from_default_env()
                 .unwrap_or_else(|_| "voice_echo=info,tower_http=info".into()),
         )
         .init();
+}
 
-    let config = match Config::load() {
+fn load_config() -> Config {
+    match Config::load() {
         Ok(c) => c,
         Err(e) => {
             eprintln!("Failed to load config: {e}");
             std::process::exit(1);
         }
-    };
+    }
+}
+
+async fn server() {
+    init_tracing();
+    let config = load_config();
 
     tracing::info!(
         host = %config.server.host,
@@ -68,3 +95,65 @@ async fn server() {
         std::process::exit(1);
     }
 }
+
+/// Serve on a free local port and feed it a capture from `[capture] dir`,
+/// printing what the server sends back. Mock or real providers come from
+/// the config as usual.
+async fn replay(path: &str) {
+    init_tracing();
+    let mut config = load_config();
+    // Don't overwrite the capture being replayed
+    config.capture.dir = None;
+
+    let listener = match tokio::net::TcpListener::bind("127.0.0.1:0").await {
+        Ok(l) => l,
+        Err(e) => {
+            eprintln!("Failed to bind: {e}");
+            std::process::exit(1);
+        }
+    };
+    let addr = listener
+        .local_addr()
+        .expect("bound listener has an address");
+    tokio::spawn(async move {
+        let mut voice = VoiceEcho::new(config);
+        if let Err(e) = voice.serve(listener).await {
+            tracing::error!("Server error: {e}");
+        }
+    });
+
+    match capture::replay(&format!("ws://{addr}"), std::path::Path::new(path)).await {
+        Ok(replies) => {
+            for line in capture::summarize(&replies) {
+                println!("{line}");
+            }
+        }
+        Err(e) => {
+            eprintln!("Replay failed: {e}");
+            std::process::exit(1);
+        }
+    }
+}
+
+/// Run a capture's audio back through STT, printing what was heard and
+/// writing it next to the capture.
+async fn retranscribe(path: &str, model: Option<&str>) {
+    init_tracing();
+    let config = load_config();
+    match retranscribe::run(&config, std::path::Path::new(path), model).await {
+        Ok((out, transcript)) => {
+            for utterance in &transcript.utterances {
+                let at = utterance.at_ms as f64 / 1000.0;
+                match utterance.error {
+                    Some(ref e) => println!("{at:>7.2}s  (failed: {e})"),
+                    None => println!("{at:>7.2}s  {}", utterance.text),
+                }
+            }
+            println!("Wrote {}", out.display());
+        }
+        Err(e) => {
+            eprintln!("Re-transcription failed: {e}");
+            std::process::exit(1);
+        }
+    }
+}
@@ -0,0 +1,355 @@
+//! What's remembered about callers from one call to the next (`[memory]`).
+//!
+//! Opt-in. While a call runs its turns are noted; when it ends, the brain is
+//! asked to fold them into a short profile of the caller, kept as one JSON
+//! file per caller under `[memory] dir` (`~/.voice-echo/memory/` by
+//! default). The next call from the same number, or the same Discord user,
+//! starts with that profile in the first prompt's context. Callers without
+//! a number aren't remembered. Profiles are masked per `[privacy]` before
+//! they're written, like kept conversations.
+//!
+//! A tenant's callers are remembered apart from everyone else's, under its
+//! `<data_dir>/memory/`, or `[memory] dir/<tenant>/` without one. A call
+//! handled by an agent with its own brain has that brain write the profile.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::Mutex;
+
+use crate::config::MemoryConfig;
+use crate::persist::save_json;
+use crate::pipeline::privacy::PrivacyMask;
+use crate::registry::CallProfile;
+use crate::tenants::Tenant;
+use crate::{AppState, Brain};
+
+/// What's kept about one caller.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CallerMemory {
+    pub summary: String,
+    /// Calls the summary covers.
+    pub calls: u32,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// A live call's turns, for the caller's memory.
+struct Notes {
+    /// The caller's memory file.
+    path: PathBuf,
+    /// The agent's own brain, if it has one, to write the profile.
+    brain: Option<Brain>,
+    /// (what the caller said, what was answered)
+    turns: Vec<(String, String)>,
+}
+
+pub struct Memory {
+    config: MemoryConfig,
+    calls: Mutex<HashMap<String, Notes>>,
+    mask: PrivacyMask,
+}
+
+impl Memory {
+    pub fn new(config: &MemoryConfig) -> Self {
+        Self {
+            config: config.clone(),
+            calls: Mutex::new(HashMap::new()),
+            mask: PrivacyMask::default(),
+        }
+    }
+
+    /// Mask profiles per `[privacy]` on their way to disk.
+    pub fn with_mask(mut self, mask: PrivacyMask) -> Self {
+        self.mask = mask;
+        self
+    }
+
+    /// On a call's first turn, what's remembered about the caller, ready to
+    /// add to the prompt's context. Starts noting the call's turns.
+    pub async fn recall(&self, call_sid: &str, profile: &CallProfile) -> Option<String> {
+        if !self.config.enabled {
+            return None;
+        }
+        let path = self.path(profile.tenant.as_deref(), &caller(profile)?);
+        {
+            let mut calls = self.calls.lock().await;
+            if calls.contains_key(call_sid) {
+                return None;
+            }
+            calls.insert(
+                call_sid.to_string(),
+                Notes {
+                    path: path.clone(),
+                    brain: profile.agent.as_ref().and_then(|a| a.brain.clone()),
+                    turns: Vec::new(),
+                },
+            );
+        }
+        let memory = load(&path).await?;
+        tracing::info!(call_sid, calls = memory.calls, "Recalled caller memory");
+        Some(format!(
+            "You have spoken with this caller before. What you remember about them:\n{}",
+            memory.summary
+        ))
+    }
+
+    /// Note a turn of a call being remembered.
+    pub async fn note(&self, call_sid: &str, said: &str, answered: &str) {
+        if let Some(notes) = self.calls.lock().await.get_mut(call_sid) {
+            notes.turns.push((said.to_string(), answered.to_string()));
+        }
+    }
+
+    async fn save(&self, path: &Path, memory: &CallerMemory) {
+        let memory = CallerMemory {
+            summary: self.mask.apply(&memory.summary).into_owned(),
+            ..memory.clone()
+        };
+        save_json(path, &memory, "caller memory").await;
+    }
+
+    /// The caller's memory file, among `tenant`'s callers if the call is
+    /// one of its.
+    fn path(&self, tenant: Option<&Tenant>, caller: &str) -> PathBuf {
+        let dir = match tenant {
+            Some(tenant) => tenant
+                .memory_dir()
+                .unwrap_or_else(|| self.config.dir.join(file_stem(&tenant.name))),
+            None => self.config.dir.clone(),
+        };
+        dir.join(format!("{}.json", file_stem(caller)))
+    }
+}
+
+/// The memory in the file at `path`, if there is one.
+async fn load(path: &Path) -> Option<CallerMemory> {
+    match tokio::fs::read(path).await {
+        Ok(bytes) => match serde_json::from_slice(&bytes) {
+            Ok(memory) => Some(memory),
+            Err(e) => {
+                tracing::error!(path = %path.display(), "Unreadable caller memory: {e}");
+                None
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => {
+            tracing::error!(path = %path.display(), "Failed to read caller memory: {e}");
+            None
+        }
+    }
+}
+
+/// `name` made safe to use as a file name.
+fn file_stem(name: &str) -> String {
+    name.chars()
+        .map(|c| match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '+' | '-' | '_' => c,
+            _ => '_',
+        })
+        .collect()
+}
+
+/// Who's on the other end, as memory knows them.
+fn caller(profile: &CallProfile) -> Option<String> {
+    match (&profile.number, &profile.discord_user) {
+        (Some(number), _) => Some(number.clone()),
+        (None, Some(user)) => Some(format!("discord-{user}")),
+        (None, None) => None,
+    }
+}
+
+/// Update the caller's memory with the call that just ended. Does nothing
+/// for calls not being remembered, or where nothing was said.
+pub async fn remember(state: &AppState, call_sid: &str) {
+    let memory = &state.memory;
+    let Some(notes) = memory.calls.lock().await.remove(call_sid) else {
+        return;
+    };
+    if notes.turns.is_empty() {
+        return;
+    }
+    let previous = load(&notes.path).await;
+    let prompt = prompt(
+        previous.as_ref().map(|m| m.summary.as_str()),
+        &notes.turns,
+        memory.config.max_chars,
+    );
+
+    // A throwaway session, so the summary isn't part of any conversation,
+    // on the brain that handled the call
+    let key = format!("memory:{call_sid}");
+    let brain = notes.brain.as_ref().unwrap_or(&state.brain);
+    let summary = match state.ask_once(brain, &key, &prompt).await {
+        Ok(summary) => summary
+            .trim()
+            .chars()
+            .take(memory.config.max_chars)
+            .collect(),
+        Err(e) => {
+            tracing::warn!(call_sid, "Caller memory not updated: {e}");
+            return;
+        }
+    };
+
+    let updated = CallerMemory {
+        summary,
+        calls: previous.map_or(0, |m| m.calls) + 1,
+        updated_at: Utc::now(),
+    };
+    memory.save(&notes.path, &updated).await;
+    tracing::info!(call_sid, calls = updated.calls, "Updated caller memory");
+}
+
+/// Ask for the caller's profile, folding the call into what was known.
+fn prompt(previous: Option<&str>, turns: &[(String, String)], max_chars: usize) -> String {
+    let mut prompt = String::from(
+        "Write a short profile of the caller in this phone conversation, for \
+         your own reference on their next call: who they are, what they \
+         wanted, anything they asked you to remember and anything left open. \
+         Leave out passwords, card numbers and other secrets.",
+    );
+    prompt.push_str(&format!(
+        " Keep it under {max_chars} characters and reply with only the profile.\n"
+    ));
+    if let Some(previous) = previous {
+        prompt.push_str(&format!(
+            "\nWhat you knew from earlier calls (keep what still matters):\n{previous}\n"
+        ));
+    }
+    prompt.push_str("\nThis call:\n");
+    for (said, answered) in turns {
+        prompt.push_str(&format!("Caller: {said}\nYou: {answered}\n"));
+    }
+    prompt
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::tenants::fixture::tenant;
+
+    fn memory(dir: PathBuf) -> Memory {
+        Memory::new(&MemoryConfig {
+            enabled: true,
+            dir,
+            max_chars: 200,
+        })
+    }
+
+    fn profile(number: &str) -> CallProfile {
+        CallProfile {
+            number: Some(number.to_string()),
+            ..Default::default()
+        }
+    }
+
+    fn remembered(summary: &str) -> CallerMemory {
+        CallerMemory {
+            summary: summary.into(),
+            calls: 1,
+            updated_at: Utc::now(),
+        }
+    }
+
+    #[tokio::test]
+    async fn recalls_on_the_first_turn_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = memory(dir.path().to_path_buf());
+        let caller = "+15551234567";
+        let path = memory.path(None, caller);
+        memory
+            .save(&path, &remembered("Ana, asking about her order."))
+            .await;
+        assert_eq!(path, dir.path().join("+15551234567.json"));
+        assert!(path.exists());
+
+        let recalled = memory.recall("CA1", &profile(caller)).await.unwrap();
+        assert!(recalled.ends_with("\nAna, asking about her order."));
+        assert_eq!(memory.recall("CA1", &profile(caller)).await, None);
+
+        memory
+            .note("CA1", "Has it shipped?", "Yes, yesterday.")
+            .await;
+        memory.note("CA2", "Not remembered", "-").await;
+        let calls = memory.calls.lock().await;
+        assert_eq!(calls["CA1"].turns.len(), 1);
+        assert!(!calls.contains_key("CA2"));
+        drop(calls);
+
+        // No number, nothing to remember them by
+        assert_eq!(memory.recall("CA3", &CallProfile::default()).await, None);
+    }
+
+    #[tokio::test]
+    async fn tenants_keep_their_callers_apart() {
+        let dir = tempfile::tempdir().unwrap();
+        let memory = memory(dir.path().to_path_buf());
+        let acme = Arc::new(tenant("name = \"acme\"\napi_token = \"a\""));
+        let globex_data = dir.path().join("globex");
+        let globex = Arc::new(tenant(&format!(
+            "name = \"globex\"\napi_token = \"g\"\ndata_dir = {:?}",
+            globex_data.display().to_string()
+        )));
+        let caller = "+15551234567";
+        let call = |tenant: &Arc<Tenant>| CallProfile {
+            tenant: Some(Arc::clone(tenant)),
+            ..profile(caller)
+        };
+
+        let path = memory.path(Some(&acme), caller);
+        assert_eq!(path, dir.path().join("acme/+15551234567.json"));
+        memory
+            .save(&path, &remembered("Ana, an acme customer."))
+            .await;
+        assert_eq!(
+            memory.path(Some(&globex), caller),
+            globex_data.join("memory/+15551234567.json")
+        );
+
+        let recalled = memory.recall("CA1", &call(&acme)).await.unwrap();
+        assert!(recalled.ends_with("\nAna, an acme customer."));
+        // Same number, other tenant or none: nothing recalled
+        assert_eq!(memory.recall("CA2", &call(&globex)).await, None);
+        assert_eq!(memory.recall("CA3", &profile(caller)).await, None);
+    }
+
+    #[tokio::test]
+    async fn masks_what_it_saves() {
+        let dir = tempfile::tempdir().unwrap();
+        let privacy = crate::config::PrivacyConfig {
+            card_numbers: true,
+            ..Default::default()
+        };
+        let memory = memory(dir.path().to_path_buf())
+            .with_mask(PrivacyMask::new(&privacy, &Default::default()));
+        let path = memory.path(None, "+15551234567");
+        memory
+            .save(&path, &remembered("Ana, paid with 4111 1111 1111 1111."))
+            .await;
+        let saved = load(&path).await.unwrap();
+        assert!(!saved.summary.contains("4111"), "{}", saved.summary);
+        assert!(saved.summary.starts_with("Ana, paid with "));
+    }
+
+    #[test]
+    fn names_files_safely() {
+        let memory = memory(PathBuf::from("/m"));
+        assert_eq!(
+            memory.path(None, "discord-../../etc"),
+            PathBuf::from("/m/discord-______etc.json")
+        );
+    }
+
+    #[test]
+    fn prompt_carries_the_previous_profile() {
+        let turns = [("Hi, it's Ana".to_string(), "Hello Ana!".to_string())];
+        let prompt = prompt(Some("Ana, asking about an order."), &turns, 500);
+        assert!(prompt.contains("under 500 characters"));
+        assert!(prompt.contains("earlier calls (keep what still matters):\nAna, asking"));
+        assert!(prompt.ends_with("This call:\nCaller: Hi, it's Ana\nYou: Hello Ana!\n"));
+    }
+}
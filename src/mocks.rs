@@ -0,0 +1,188 @@
+//! Canned providers for tests and CI (`--features mocks`).
+//!
+//! `[mocks]` swaps Groq, Inworld, the brain and Twilio's REST API for
+//! stand-ins that need no credentials or network: STT cycles through fixed
+//! transcripts, TTS returns a tone or silence sized to the text, the brain
+//! replays scripted responses (or echoes the caller), and outbound calls
+//! ring nobody. STT, TTS and the brain can add latency so hold music,
+//! barge-in and timeouts can be exercised.
+
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::Duration;
+
+use crate::chaos::{FaultInjector, InjectedFault};
+use crate::config::{MockAudio, MocksConfig};
+use crate::pipeline::audio;
+use crate::pipeline::stt::Transcription;
+
+const SAMPLE_RATE: f64 = 8000.0;
+const TONE_HZ: f64 = 440.0;
+const TONE_AMPLITUDE: f64 = 8000.0;
+
+/// Speech-to-text returning `[mocks] transcripts` in turn.
+pub struct MockStt {
+    transcripts: Vec<String>,
+    latency: Duration,
+    confidence: Option<f32>,
+    next: AtomicUsize,
+}
+
+impl MockStt {
+    pub fn new(config: &MocksConfig) -> Self {
+        Self {
+            transcripts: config.transcripts.clone(),
+            latency: Duration::from_millis(config.latency_ms),
+            confidence: config.confidence,
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    /// The next transcript. When asked to detect, reports English.
+    pub async fn transcribe(&self, language: Option<&str>) -> Transcription {
+        tokio::time::sleep(self.latency).await;
+        let text = match self.transcripts.len() {
+            0 => String::new(),
+            n => self.transcripts[self.next.fetch_add(1, Ordering::Relaxed) % n].clone(),
+        };
+        Transcription {
+            text,
+            language: language.is_none().then(|| "en".to_string()),
+            confidence: self.confidence,
+            speakers: Vec::new(),
+        }
+    }
+}
+
+/// Text-to-speech returning `ms_per_char` of tone or silence per character.
+pub struct MockTts {
+    audio: MockAudio,
+    ms_per_char: u64,
+    latency: Duration,
+}
+
+impl MockTts {
+    pub fn new(config: &MocksConfig) -> Self {
+        Self {
+            audio: config.audio,
+            ms_per_char: config.ms_per_char,
+            latency: Duration::from_millis(config.latency_ms),
+        }
+    }
+
+    /// Mu-law 8kHz audio for `text`.
+    pub async fn synthesize(&self, text: &str) -> Vec<u8> {
+        tokio::time::sleep(self.latency).await;
+        let samples = text.chars().count() * self.ms_per_char as usize * 8;
+        match self.audio {
+            MockAudio::Silence => vec![audio::pcm_to_mulaw(0); samples],
+            MockAudio::Sine => (0..samples)
+                .map(|i| {
+                    let t = i as f64 / SAMPLE_RATE;
+                    let sample = (2.0 * std::f64::consts::PI * TONE_HZ * t).sin() * TONE_AMPLITUDE;
+                    audio::pcm_to_mulaw(sample as i16)
+                })
+                .collect(),
+        }
+    }
+}
+
+/// Brain replaying `[mocks] responses` in turn, or echoing the caller when
+/// there are none.
+pub struct MockBrain {
+    responses: Vec<String>,
+    latency: Duration,
+    next: AtomicUsize,
+    chaos: FaultInjector,
+}
+
+impl MockBrain {
+    pub fn new(config: &MocksConfig) -> Self {
+        Self {
+            responses: config.responses.clone(),
+            latency: Duration::from_millis(config.latency_ms),
+            next: AtomicUsize::new(0),
+            chaos: FaultInjector::default(),
+        }
+    }
+
+    /// Inject `[chaos.brain]` faults, the only way this brain fails.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    pub async fn send(&self, transcript: &str) -> Result<String, InjectedFault> {
+        self.chaos.before().await?;
+        tokio::time::sleep(self.latency).await;
+        let response = match self.responses.len() {
+            0 => format!("You said: {transcript}"),
+            n => self.responses[self.next.fetch_add(1, Ordering::Relaxed) % n].clone(),
+        };
+        self.chaos.drip(response.len()).await;
+        Ok(response)
+    }
+}
+
+/// Twilio's REST API, placing calls nowhere. Each call gets the next
+/// `CAmock<n>` sid, `dial_ms` after it's asked for; a test answers it by
+/// starting a media stream with that sid.
+pub struct MockTwilio {
+    calls: AtomicUsize,
+    dial: Duration,
+}
+
+impl MockTwilio {
+    pub fn new(config: &MocksConfig) -> Self {
+        Self {
+            calls: AtomicUsize::new(0),
+            dial: Duration::from_millis(config.dial_ms),
+        }
+    }
+
+    pub async fn call(&self, to: &str) -> String {
+        tokio::time::sleep(self.dial).await;
+        let call_sid = format!("CAmock{}", self.calls.fetch_add(1, Ordering::Relaxed) + 1);
+        tracing::info!(to, call_sid = %call_sid, "Mock outbound call");
+        call_sid
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> MocksConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[tokio::test]
+    async fn stt_and_brain_cycle_their_scripts() {
+        let scripts = config("transcripts = [\"one\", \"two\"]\nresponses = [\"a\"]");
+        let stt = MockStt::new(&scripts);
+        assert_eq!(stt.transcribe(Some("en")).await.text, "one");
+        assert_eq!(stt.transcribe(Some("en")).await.text, "two");
+        let third = stt.transcribe(None).await;
+        assert_eq!(third.text, "one");
+        assert_eq!(third.language.as_deref(), Some("en"));
+
+        let brain = MockBrain::new(&scripts);
+        assert_eq!(brain.send("hi").await.unwrap(), "a");
+        assert_eq!(brain.send("hi").await.unwrap(), "a");
+        let echo = MockBrain::new(&config(""));
+        assert_eq!(echo.send("hi").await.unwrap(), "You said: hi");
+    }
+
+    #[tokio::test]
+    async fn tts_audio_scales_with_text() {
+        let tts = MockTts::new(&config("ms_per_char = 10\naudio = \"silence\""));
+        let audio = tts.synthesize("hello").await;
+        assert_eq!(audio.len(), 5 * 10 * 8);
+        assert!(audio.iter().all(|&b| b == audio::pcm_to_mulaw(0)));
+
+        let tone = MockTts::new(&config("ms_per_char = 10"))
+            .synthesize("hi")
+            .await;
+        let pcm: Vec<i16> = tone.iter().map(|&b| audio::mulaw_to_pcm(b)).collect();
+        assert!(audio::rms_energy(&pcm) > 1000.0);
+    }
+}
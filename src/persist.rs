@@ -0,0 +1,50 @@
+//! JSON files kept across restarts: scheduled calls, reminders, brain
+//! sessions and caller memories.
+
+use std::path::Path;
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// The JSON list saved at `path`; empty if there's no path, no file yet, or
+/// the file can't be read.
+pub(crate) fn load_list<T: DeserializeOwned>(path: Option<&Path>, what: &str) -> Vec<T> {
+    let Some(path) = path else {
+        return Vec::new();
+    };
+    match std::fs::read_to_string(path) {
+        Ok(contents) => match serde_json::from_str::<Vec<T>>(&contents) {
+            Ok(items) => {
+                tracing::info!(count = items.len(), path = %path.display(), "Loaded {what}");
+                items
+            }
+            Err(e) => {
+                tracing::error!(path = %path.display(), "Unreadable {what} file: {e}");
+                Vec::new()
+            }
+        },
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => {
+            tracing::error!(path = %path.display(), "Failed to read {what} file: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// Save `value` as JSON at `path`, creating its directory. Failures are
+/// logged.
+pub(crate) async fn save_json<T: Serialize + ?Sized>(path: &Path, value: &T, what: &str) {
+    let result = async {
+        if let Some(dir) = path.parent() {
+            tokio::fs::create_dir_all(dir).await?;
+        }
+        // Write then rename, so a crash never leaves half a file
+        let tmp = path.with_extension("tmp");
+        tokio::fs::write(&tmp, serde_json::to_vec_pretty(value)?).await?;
+        tokio::fs::rename(&tmp, path).await
+    }
+    .await;
+    if let Err(e) = result {
+        tracing::error!(path = %path.display(), "Failed to save {what}: {e}");
+    }
+}
@@ -0,0 +1,205 @@
+//! Actions the brain can take on a call (`[actions]`).
+//!
+//! Besides talking, the brain can put a JSON object with an `action` in its
+//! reply: `{"action": "hangup"}`, `{"action": "transfer", "to": "+34..."}`
+//! or `{"action": "sms", "body": "..."}`. Objects are cut out before TTS.
+//! Texts go out straight away; hangups and transfers wait until the reply
+//! has finished playing. The caller's words are untrusted input to the
+//! brain, so texts only ever go to the number on the other end of the call
+//! and transfers only to numbers listed in `transfer_to`.
+
+use serde::Deserialize;
+
+use crate::config::ActionsConfig;
+use crate::registry::Transport;
+use crate::twilio::outbound::OutboundError;
+use crate::AppState;
+
+/// Something the brain asked to do.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(tag = "action", rename_all = "lowercase")]
+pub enum Action {
+    Hangup,
+    Transfer {
+        to: String,
+    },
+    Sms {
+        body: String,
+    },
+    /// Keys pressed with a `[DTMF:...]` directive, not a JSON object.
+    #[serde(skip)]
+    Dtmf {
+        digits: String,
+    },
+}
+
+impl Action {
+    /// True for actions that have to wait for the reply to finish playing:
+    /// ones that take the caller away, and key presses, which restart the
+    /// media stream.
+    pub fn ends_call(&self) -> bool {
+        matches!(
+            self,
+            Action::Hangup | Action::Transfer { .. } | Action::Dtmf { .. }
+        )
+    }
+}
+
+/// Tells the brain which actions it may take, or `None` if none are on.
+pub fn instruction(config: &ActionsConfig) -> Option<String> {
+    let mut actions = Vec::new();
+    if config.hangup {
+        actions.push(r#"{"action": "hangup"} ends the call after your reply"#.to_string());
+    }
+    if !config.transfer_to.is_empty() {
+        actions.push(format!(
+            r#"{{"action": "transfer", "to": "<number>"}} transfers the caller to one of {}"#,
+            config.transfer_to.join(", ")
+        ));
+    }
+    if config.sms {
+        actions.push(r#"{"action": "sms", "body": "<text>"} texts the caller"#.to_string());
+    }
+    if actions.is_empty() {
+        return None;
+    }
+    Some(format!(
+        "You can act on this call by putting a JSON object in your reply: {}. \
+         The object is not spoken; only use it when the caller clearly wants it.",
+        actions.join("; ")
+    ))
+}
+
+/// Cut the action objects out of `text`. Returns what's left to speak and
+/// the actions, in order. Objects that aren't valid actions stay in the
+/// text.
+pub fn extract(text: &str) -> (String, Vec<Action>) {
+    let mut speech = String::new();
+    let mut actions = Vec::new();
+    let mut rest = text;
+
+    while let Some(open) = rest.find('{') {
+        let mut objects = serde_json::Deserializer::from_str(&rest[open..]).into_iter::<Action>();
+        match objects.next() {
+            Some(Ok(action)) => {
+                speech.push_str(&rest[..open]);
+                actions.push(action);
+                rest = &rest[open + objects.byte_offset()..];
+            }
+            _ => {
+                speech.push_str(&rest[..=open]);
+                rest = &rest[open + 1..];
+            }
+        }
+    }
+    speech.push_str(rest);
+    (speech.trim().to_string(), actions)
+}
+
+/// Carry out `actions` on the call, in order. Refusals and failures are
+/// logged.
+pub async fn perform(state: &AppState, call_sid: &str, actions: Vec<Action>) {
+    for action in actions {
+        match perform_one(state, call_sid, &action).await {
+            Ok(()) => tracing::info!(call_sid, ?action, "Performed brain action"),
+            Err(e) => tracing::warn!(call_sid, ?action, "Brain action not performed: {e}"),
+        }
+    }
+}
+
+/// Press `digits` on a Twilio call through the REST API. The call's media
+/// stream ends while they play and comes back with a `resume` parameter.
+pub async fn press(state: &AppState, call_sid: &str, digits: &str) -> Result<(), ActionError> {
+    let transport = state.call_registry.get(call_sid).await.map(|e| e.transport);
+    if transport != Some(Transport::Twilio) {
+        return Err(ActionError::NotAllowed("not a phone call".into()));
+    }
+    state.call_registry.expect_resume(call_sid).await;
+    if let Err(e) = state.twilio.send_digits(call_sid, digits).await {
+        state.call_registry.cancel_resume(call_sid).await;
+        return Err(e.into());
+    }
+    Ok(())
+}
+
+async fn perform_one(state: &AppState, call_sid: &str, action: &Action) -> Result<(), ActionError> {
+    // Directives, not `[actions]`
+    if let Action::Dtmf { digits } = action {
+        return press(state, call_sid, digits).await;
+    }
+    let Some(ref config) = state.config.actions else {
+        return Err(ActionError::NotAllowed("[actions] is off".into()));
+    };
+    match action {
+        Action::Hangup if config.hangup => state.twilio.hangup(call_sid).await?,
+        Action::Transfer { to } if config.transfer_to.contains(to) => {
+            state.twilio.transfer(call_sid, to).await?
+        }
+        Action::Sms { body } if config.sms => {
+            let number = state
+                .call_registry
+                .profile(call_sid)
+                .await
+                .number
+                .ok_or(ActionError::NoNumber)?;
+            state.twilio.send_sms(&number, body).await?
+        }
+        Action::Transfer { to } => {
+            return Err(ActionError::NotAllowed(format!(
+                "{to} is not in transfer_to"
+            )))
+        }
+        _ => return Err(ActionError::NotAllowed("disabled in [actions]".into())),
+    }
+    Ok(())
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ActionError {
+    #[error("not allowed: {0}")]
+    NotAllowed(String),
+    #[error("the other party's number is unknown")]
+    NoNumber,
+    #[error(transparent)]
+    Twilio(#[from] OutboundError),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cuts_actions_out_of_the_reply() {
+        let (speech, actions) = extract(
+            r#"Texting you the code now. {"action": "sms", "body": "Code: {42}"} Goodbye! {"action":"hangup"}"#,
+        );
+        assert_eq!(speech, "Texting you the code now.  Goodbye!");
+        assert_eq!(
+            actions,
+            vec![
+                Action::Sms {
+                    body: "Code: {42}".into()
+                },
+                Action::Hangup
+            ]
+        );
+    }
+
+    #[test]
+    fn leaves_other_braces_alone() {
+        let text = r#"Use {curly} braces or {"action": "fly"}."#;
+        assert_eq!(extract(text), (text.to_string(), vec![]));
+    }
+
+    #[test]
+    fn instruction_lists_enabled_actions() {
+        let config: ActionsConfig = toml::from_str("transfer_to = [\"+34600000000\"]").unwrap();
+        let text = instruction(&config).unwrap();
+        assert!(text.contains(r#"{"action": "hangup"}"#));
+        assert!(text.contains("one of +34600000000"));
+        assert!(!text.contains("sms"));
+
+        let config: ActionsConfig = toml::from_str("hangup = false").unwrap();
+        assert_eq!(instruction(&config), None);
+    }
+}
@@ -0,0 +1,234 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{AnthropicConfig, BrainProvider};
+use crate::pipeline::chat::{ChatError, ChatFuture, ChatProvider};
+use crate::pipeline::history::ChatMessage;
+
+/// `anthropic-version` header the request and response shapes follow.
+const API_VERSION: &str = "2023-06-01";
+
+/// The Anthropic Messages API, called directly
+/// (`[brain] provider = "anthropic"`).
+///
+/// Saves the CLI's per-turn process start: requests go over the shared
+/// pooled client.
+pub struct AnthropicChat {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest<'a> {
+    model: &'a str,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system: Option<&'a str>,
+    messages: &'a [ChatMessage],
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl AnthropicChat {
+    pub fn new(client: reqwest::Client, config: &AnthropicConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            timeout: (config.timeout_ms > 0).then(|| Duration::from_millis(config.timeout_ms)),
+        }
+    }
+
+    /// Credential check: list models.
+    async fn models(&self) -> Result<(), ChatError> {
+        let mut req = self
+            .client
+            .get(format!("{}/v1/models", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(ChatError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<String, ChatError> {
+        let body = MessagesRequest {
+            model: &self.model,
+            max_tokens,
+            system: (!system_prompt.is_empty()).then_some(system_prompt),
+            messages,
+        };
+
+        let mut req = self
+            .client
+            .post(format!("{}/v1/messages", self.base_url))
+            .header("x-api-key", &self.api_key)
+            .header("anthropic-version", API_VERSION)
+            .json(&body);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            let detail =
+                serde_json::from_str::<ErrorResponse>(&body).map_or(body, |e| e.error.message);
+            return Err(ChatError::Api(format!("{status}: {detail}")));
+        }
+
+        let result: MessagesResponse = resp
+            .json()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        let text: Vec<String> = result
+            .content
+            .into_iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text { text } => Some(text),
+                ContentBlock::Other => None,
+            })
+            .collect();
+        if text.is_empty() {
+            return Err(ChatError::Empty("Anthropic API"));
+        }
+        Ok(text.join(""))
+    }
+}
+
+impl ChatProvider for AnthropicChat {
+    fn brain(&self) -> BrainProvider {
+        BrainProvider::Anthropic
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String> {
+        Box::pin(self.request(system_prompt, messages, max_tokens))
+    }
+
+    fn probe(&self) -> ChatFuture<'_, ()> {
+        Box::pin(self.models())
+    }
+
+    fn hint(&self) -> &'static str {
+        "check anthropic.api_key"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::pipeline::chat::fixture::{converse, Fixture};
+
+    fn reply(text: &str) -> (StatusCode, serde_json::Value) {
+        let reply = json!({
+            "role": "assistant",
+            "content": [{ "type": "text", "text": text }],
+            "stop_reason": "end_turn"
+        });
+        (StatusCode::OK, reply)
+    }
+
+    #[tokio::test]
+    async fn maps_the_conversation() {
+        let overloaded = json!({
+            "type": "error",
+            "error": { "type": "overloaded_error", "message": "Overloaded" }
+        });
+        let api = Fixture::serve(vec![
+            reply("reply 1"),
+            (StatusCode::from_u16(529).unwrap(), overloaded),
+            reply("reply 3"),
+        ])
+        .await;
+        let config: AnthropicConfig = toml::from_str(&format!(
+            "api_key = \"sk-ant-test\"\nmodel = \"claude-test\"\nbase_url = {:?}",
+            api.url
+        ))
+        .unwrap();
+
+        let err = converse(Box::new(AnthropicChat::new(
+            reqwest::Client::new(),
+            &config,
+        )))
+        .await;
+        assert_eq!(
+            err.to_string(),
+            "API error: 529 <unknown status code>: Overloaded"
+        );
+
+        let requests = api.requests();
+        assert_eq!(requests[0].path, "/v1/messages");
+        assert_eq!(requests[0].headers["x-api-key"], "sk-ant-test");
+        assert_eq!(requests[0].headers["anthropic-version"], API_VERSION);
+        assert_eq!(requests[0].body["model"], "claude-test");
+        assert_eq!(requests[0].body["max_tokens"], 256);
+        assert_eq!(requests[0].body["system"], "You are Echo.");
+        assert_eq!(requests[2].body["system"], "You are Sentinel.");
+        assert_eq!(
+            requests[2].body["messages"],
+            json!([
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": "reply 1" },
+                { "role": "user", "content": "again" },
+            ])
+        );
+    }
+}
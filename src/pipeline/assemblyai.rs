@@ -0,0 +1,316 @@
+use std::time::Duration;
+
+use serde::de::DeserializeOwned;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::config::AssemblyAiConfig;
+use crate::pipeline::language;
+use crate::pipeline::stt::{SpeakerRun, SttError, SttFuture, SttProvider, Transcription};
+
+/// AssemblyAI transcription (`[stt] provider = "assemblyai"`). Each
+/// utterance is uploaded, queued as a transcript and polled until done.
+pub struct AssemblyAiStt {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    speech_model: Option<String>,
+    poll: Duration,
+    vocabulary: Vec<String>,
+    diarize: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct Upload {
+    upload_url: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct Transcript {
+    id: String,
+    /// `queued`, `processing`, `completed` or `error`.
+    status: String,
+    #[serde(default)]
+    text: Option<String>,
+    #[serde(default)]
+    confidence: Option<f32>,
+    /// With `language_detection`, e.g. `en_us` or `es`.
+    #[serde(default)]
+    language_code: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    /// With `speaker_labels`.
+    #[serde(default)]
+    utterances: Option<Vec<Utterance>>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Utterance {
+    /// "A", "B", ...
+    speaker: String,
+    text: String,
+}
+
+/// Speaker "A" is 1, "B" 2 and so on.
+fn speaker_number(label: &str) -> Option<u32> {
+    match label.as_bytes() {
+        [letter @ b'A'..=b'Z'] => Some((letter - b'A') as u32 + 1),
+        _ => None,
+    }
+}
+
+impl AssemblyAiStt {
+    pub fn new(client: reqwest::Client, api_key: String, config: &AssemblyAiConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key,
+            speech_model: config.speech_model.clone(),
+            poll: Duration::from_millis(config.poll_ms),
+            vocabulary: Vec::new(),
+            diarize: false,
+        }
+    }
+
+    /// Boost `terms` (`word_boost`).
+    pub fn with_vocabulary(mut self, terms: Vec<String>) -> Self {
+        self.vocabulary = terms;
+        self
+    }
+
+    /// Label speakers (`[diarization] vendor`), per utterance.
+    pub fn with_diarization(mut self, diarize: bool) -> Self {
+        self.diarize = diarize;
+        self
+    }
+
+    /// Send an authenticated request and parse the JSON answer.
+    async fn send<T: DeserializeOwned>(
+        &self,
+        request: reqwest::RequestBuilder,
+    ) -> Result<T, SttError> {
+        let resp = request
+            .header("Authorization", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(SttError::Api(format!("{status}: {body}")));
+        }
+
+        resp.json()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))
+    }
+
+    async fn request(
+        &self,
+        wav_data: Vec<u8>,
+        hint: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        let upload: Upload = self
+            .send(
+                self.client
+                    .post(format!("{}/v2/upload", self.base_url))
+                    .body(wav_data),
+            )
+            .await?;
+
+        let mut job = json!({
+            "audio_url": upload.upload_url,
+            "speaker_labels": self.diarize,
+        });
+        match hint {
+            Some(hint) => job["language_code"] = json!(hint),
+            None => job["language_detection"] = json!(true),
+        }
+        if let Some(ref model) = self.speech_model {
+            job["speech_model"] = json!(model);
+        }
+        if !self.vocabulary.is_empty() {
+            job["word_boost"] = json!(self.vocabulary);
+        }
+        let mut transcript: Transcript = self
+            .send(
+                self.client
+                    .post(format!("{}/v2/transcript", self.base_url))
+                    .json(&job),
+            )
+            .await?;
+
+        // Bounded by `[stt] timeout_ms` and the stage budget
+        loop {
+            match transcript.status.as_str() {
+                "completed" => break,
+                "error" => {
+                    return Err(SttError::Api(transcript.error.unwrap_or(transcript.status)));
+                }
+                _ => tokio::time::sleep(self.poll).await,
+            }
+            let url = format!("{}/v2/transcript/{}", self.base_url, transcript.id);
+            transcript = self.send(self.client.get(url)).await?;
+        }
+
+        let language = match hint {
+            Some(_) => None,
+            None => transcript
+                .language_code
+                .as_deref()
+                .and_then(|l| language::code(&language::of_locale(l))),
+        };
+        let speakers = transcript
+            .utterances
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|u| {
+                Some(SpeakerRun {
+                    speaker: speaker_number(&u.speaker)?,
+                    text: u.text,
+                })
+            })
+            .collect();
+        // No speech comes back without text
+        Ok(Transcription {
+            text: transcript.text.unwrap_or_default(),
+            language,
+            confidence: transcript.confidence,
+            speakers,
+        })
+    }
+}
+
+impl SttProvider for AssemblyAiStt {
+    fn name(&self) -> &'static str {
+        "assemblyai"
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        wav_data: Vec<u8>,
+        language: Option<&'a str>,
+        _context: Option<&'a str>,
+    ) -> SttFuture<'a, Transcription> {
+        Box::pin(self.request(wav_data, language))
+    }
+
+    /// List one transcript.
+    fn probe(&self) -> SttFuture<'_, ()> {
+        Box::pin(async {
+            let url = format!("{}/v2/transcript?limit=1", self.base_url);
+            self.send::<serde_json::Value>(self.client.get(url))
+                .await
+                .map(|_| ())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    use axum::extract::{Path, State};
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::Value;
+
+    /// Jobs by id, and how many times each has been polled.
+    type Jobs = Arc<Mutex<HashMap<String, (Value, u32)>>>;
+
+    /// An AssemblyAI API taking uploads and finishing each transcript on
+    /// its second poll, with the upload's length as the text.
+    async fn server() -> String {
+        let jobs = Jobs::default();
+        let app = Router::new()
+            .route(
+                "/v2/upload",
+                post(|headers: HeaderMap, body: axum::body::Bytes| async move {
+                    assert_eq!(headers["authorization"], "aai-test");
+                    Json(json!({ "upload_url": format!("https://cdn/{}", body.len()) }))
+                }),
+            )
+            .route(
+                "/v2/transcript",
+                post(
+                    |State(jobs): State<Jobs>, Json(job): Json<Value>| async move {
+                        let id = format!("t{}", jobs.lock().unwrap().len());
+                        jobs.lock().unwrap().insert(id.clone(), (job, 0));
+                        Json(json!({ "id": id, "status": "queued" }))
+                    },
+                ),
+            )
+            .route(
+                "/v2/transcript/{id}",
+                get(
+                    |State(jobs): State<Jobs>, Path(id): Path<String>| async move {
+                        let mut jobs = jobs.lock().unwrap();
+                        let (job, polls) = jobs.get_mut(&id).unwrap();
+                        *polls += 1;
+                        if *polls < 2 {
+                            return Json(json!({ "id": id, "status": "processing" }));
+                        }
+                        let bytes = job["audio_url"]
+                            .as_str()
+                            .unwrap()
+                            .trim_start_matches("https://cdn/");
+                        let mut done = json!({
+                            "id": id,
+                            "status": "completed",
+                            "text": format!("{bytes} bytes"),
+                            "confidence": 0.9,
+                            "utterances": [
+                                { "speaker": "A", "text": "Which one?" },
+                                { "speaker": "B", "text": "The blue one." },
+                            ],
+                        });
+                        if job["language_detection"] == true {
+                            done["language_code"] = json!("es");
+                        }
+                        assert_eq!(job["word_boost"], json!(["Kubernetes"]));
+                        Json(done)
+                    },
+                ),
+            )
+            .with_state(jobs);
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn polls_until_the_transcript_is_done() {
+        let url = server().await;
+        let config: AssemblyAiConfig =
+            toml::from_str(&format!("base_url = {url:?}\npoll_ms = 1")).unwrap();
+        let assemblyai = AssemblyAiStt::new(reqwest::Client::new(), "aai-test".into(), &config)
+            .with_vocabulary(vec!["Kubernetes".into()]);
+
+        let hinted = assemblyai
+            .transcribe(vec![0; 44], Some("en"), None)
+            .await
+            .unwrap();
+        assert_eq!(hinted.text, "44 bytes");
+        assert_eq!(hinted.language, None);
+        assert_eq!(hinted.confidence, Some(0.9));
+        assert_eq!(hinted.speakers[1].speaker, 2);
+
+        let detected = assemblyai
+            .transcribe(vec![0; 10], None, None)
+            .await
+            .unwrap();
+        assert_eq!(detected.language.as_deref(), Some("es"));
+    }
+
+    #[test]
+    fn numbers_speakers_from_letters() {
+        assert_eq!(speaker_number("A"), Some(1));
+        assert_eq!(speaker_number("C"), Some(3));
+        assert_eq!(speaker_number("speaker 1"), None);
+    }
+}
@@ -1,9 +1,14 @@
 use std::io::Cursor;
 use std::path::Path;
 
+use crate::config::PreprocessConfig;
+
 const MULAW_SAMPLE_RATE: u32 = 8000;
 const MULAW_BIAS: i16 = 0x84;
 const MULAW_CLIP: i16 = 32635;
+/// Sample rates a hold music WAV may have.
+const MIN_WAV_SAMPLE_RATE: u32 = 4000;
+const MAX_WAV_SAMPLE_RATE: u32 = 384_000;
 
 /// Decode a single mu-law byte to 16-bit PCM sample.
 pub fn mulaw_to_pcm(mulaw: u8) -> i16 {
@@ -70,24 +75,37 @@ pub fn encode_mulaw(pcm_data: &[i16]) -> Vec<u8> {
     pcm_data.iter().map(|&s| pcm_to_mulaw(s)).collect()
 }
 
-/// Encode PCM samples as a WAV file in memory (8kHz, 16-bit, mono).
-pub fn pcm_to_wav(pcm_data: &[i16]) -> Result<Vec<u8>, hound::Error> {
-    let mut buffer = Cursor::new(Vec::new());
-
-    let spec = hound::WavSpec {
-        channels: 1,
-        sample_rate: MULAW_SAMPLE_RATE,
-        bits_per_sample: 16,
-        sample_format: hound::SampleFormat::Int,
-    };
+/// Size of the canonical PCM WAV header.
+const WAV_HEADER_BYTES: usize = 44;
 
-    let mut writer = hound::WavWriter::new(&mut buffer, spec)?;
-    for &sample in pcm_data {
-        writer.write_sample(sample)?;
+/// Encode PCM samples as a WAV file in memory (8kHz, 16-bit, mono).
+///
+/// Allocates exactly once, sized from the sample count. The STT upload
+/// takes ownership of the bytes, so there is no buffer to keep for reuse.
+pub fn pcm_to_wav(pcm_data: &[i16]) -> Vec<u8> {
+    const CHANNELS: u16 = 1;
+    const BITS_PER_SAMPLE: u16 = 16;
+    const BLOCK_ALIGN: u16 = CHANNELS * BITS_PER_SAMPLE / 8;
+
+    let data_len = u32::try_from(pcm_data.len() * BLOCK_ALIGN as usize).unwrap_or(u32::MAX);
+    let mut buffer = Vec::with_capacity(WAV_HEADER_BYTES + data_len as usize);
+
+    buffer.extend_from_slice(b"RIFF");
+    buffer.extend_from_slice(&(36u32.saturating_add(data_len)).to_le_bytes());
+    buffer.extend_from_slice(b"WAVEfmt ");
+    buffer.extend_from_slice(&16u32.to_le_bytes()); // fmt chunk size
+    buffer.extend_from_slice(&1u16.to_le_bytes()); // PCM
+    buffer.extend_from_slice(&CHANNELS.to_le_bytes());
+    buffer.extend_from_slice(&MULAW_SAMPLE_RATE.to_le_bytes());
+    buffer.extend_from_slice(&(MULAW_SAMPLE_RATE * BLOCK_ALIGN as u32).to_le_bytes());
+    buffer.extend_from_slice(&BLOCK_ALIGN.to_le_bytes());
+    buffer.extend_from_slice(&BITS_PER_SAMPLE.to_le_bytes());
+    buffer.extend_from_slice(b"data");
+    buffer.extend_from_slice(&data_len.to_le_bytes());
+    for sample in pcm_data {
+        buffer.extend_from_slice(&sample.to_le_bytes());
     }
-    writer.finalize()?;
-
-    Ok(buffer.into_inner())
+    buffer
 }
 
 /// Decode WAV file bytes to PCM samples. Expects 16-bit mono.
@@ -108,6 +126,103 @@ pub fn rms_energy(pcm_data: &[i16]) -> f64 {
     (sum / pcm_data.len() as f64).sqrt()
 }
 
+/// Blocks quieter than this don't count toward the loudness of speech.
+const LOUDNESS_GATE_DBFS: f64 = -50.0;
+/// Gains this small aren't worth re-quantizing the audio for.
+const LOUDNESS_MIN_GAIN_DB: f64 = 0.5;
+
+/// Level mu-law audio so its speech sits at `target_dbfs` RMS.
+///
+/// Loudness is measured over 20ms blocks above a silence gate, so pauses
+/// don't drag the level down. Gain is capped at `max_gain_db` (noise and
+/// near-silence aren't pumped up) and kept low enough that the peak doesn't
+/// clip. Returns the gain applied, in dB.
+pub fn normalize_mulaw(mulaw: &mut [u8], target_dbfs: f64, max_gain_db: f64) -> f64 {
+    let pcm = decode_mulaw(mulaw);
+    let gain_db = loudness_gain_db(&pcm, target_dbfs, max_gain_db);
+    if gain_db == 0.0 {
+        return 0.0;
+    }
+
+    let full_scale = MULAW_CLIP as f64;
+    let gain = 10f64.powf(gain_db / 20.0);
+    for (byte, sample) in mulaw.iter_mut().zip(pcm) {
+        let scaled = (sample as f64 * gain).clamp(-full_scale, full_scale);
+        *byte = pcm_to_mulaw(scaled as i16);
+    }
+    gain_db
+}
+
+/// [`normalize_mulaw`] for PCM.
+pub fn normalize_pcm(pcm: &mut [i16], target_dbfs: f64, max_gain_db: f64) -> f64 {
+    let gain_db = loudness_gain_db(pcm, target_dbfs, max_gain_db);
+    if gain_db == 0.0 {
+        return 0.0;
+    }
+
+    let gain = 10f64.powf(gain_db / 20.0);
+    for sample in pcm {
+        *sample = (*sample as f64 * gain).clamp(-32768.0, 32767.0) as i16;
+    }
+    gain_db
+}
+
+/// The gain bringing `pcm`'s speech to `target_dbfs`, capped as described
+/// for [`normalize_mulaw`]. Zero when it isn't worth applying.
+fn loudness_gain_db(pcm: &[i16], target_dbfs: f64, max_gain_db: f64) -> f64 {
+    let full_scale = MULAW_CLIP as f64;
+    let to_dbfs = |rms: f64| 20.0 * (rms / full_scale).log10();
+
+    let (sum, count) = pcm
+        .chunks(160)
+        .map(|block| (rms_energy(block), block))
+        .filter(|(rms, _)| *rms > 0.0 && to_dbfs(*rms) > LOUDNESS_GATE_DBFS)
+        .fold((0.0, 0usize), |(sum, count), (rms, block)| {
+            (sum + rms * rms * block.len() as f64, count + block.len())
+        });
+    if count == 0 {
+        return 0.0;
+    }
+    let level = to_dbfs((sum / count as f64).sqrt());
+    let peak = pcm.iter().map(|s| s.unsigned_abs()).max().unwrap_or(0) as f64;
+    let headroom = to_dbfs(peak).min(0.0).abs();
+    let gain_db = (target_dbfs - level).min(max_gain_db).min(headroom);
+    match gain_db.abs() < LOUDNESS_MIN_GAIN_DB {
+        true => 0.0,
+        false => gain_db,
+    }
+}
+
+/// Silence the 20ms blocks of `pcm` quieter than `threshold_dbfs`: line
+/// hiss and road noise between words.
+pub fn noise_gate(pcm: &mut [i16], threshold_dbfs: f64) {
+    let threshold = MULAW_CLIP as f64 * 10f64.powf(threshold_dbfs / 20.0);
+    for block in pcm.chunks_mut(160) {
+        if rms_energy(block) < threshold {
+            block.fill(0);
+        }
+    }
+}
+
+/// Clean up an 8kHz utterance for STT (`[stt.preprocess]`): high-pass it,
+/// gate the noise between words, then level the speech.
+pub fn preprocess(pcm: &[i16], config: &PreprocessConfig) -> Vec<i16> {
+    let mut pcm = match config.highpass_hz > 0.0 {
+        true => {
+            let mut highpass = BiquadFilter::highpass(config.highpass_hz, MULAW_SAMPLE_RATE as f64);
+            pcm.iter()
+                .map(|&s| highpass.process(s as f64).clamp(-32768.0, 32767.0) as i16)
+                .collect()
+        }
+        false => pcm.to_vec(),
+    };
+    if let Some(threshold_dbfs) = config.gate_dbfs {
+        noise_gate(&mut pcm, threshold_dbfs);
+    }
+    normalize_pcm(&mut pcm, config.target_dbfs, config.max_gain_db);
+    pcm
+}
+
 /// Second-order IIR (biquad) filter using Audio EQ Cookbook formulas.
 struct BiquadFilter {
     b0: f64,
@@ -174,6 +289,13 @@ impl BiquadFilter {
         }
     }
 
+    /// Settle on a constant input `x`, as though it had always been there.
+    fn settle(&mut self, x: f64) {
+        let gain = (self.b0 + self.b1 + self.b2) / (1.0 + self.a1 + self.a2);
+        (self.x1, self.x2) = (x, x);
+        (self.y1, self.y2) = (x * gain, x * gain);
+    }
+
     /// Process a single sample.
     fn process(&mut self, x: f64) -> f64 {
         let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2
@@ -193,9 +315,13 @@ impl BiquadFilter {
 ///
 /// Strips low-frequency noise (engine rumble, road noise) and high-frequency
 /// artifacts while preserving the 300–3400Hz telephony speech band.
+///
+/// The filters settle on the first sample, so audio that opens at some
+/// level other than zero isn't heard as a click.
 pub struct BandpassFilter {
     highpass: BiquadFilter,
     lowpass: BiquadFilter,
+    settled: bool,
 }
 
 impl BandpassFilter {
@@ -204,19 +330,40 @@ impl BandpassFilter {
         Self {
             highpass: BiquadFilter::highpass(low_hz, sample_rate),
             lowpass: BiquadFilter::lowpass(high_hz, sample_rate),
+            settled: false,
         }
     }
 
     /// Filter PCM samples, returning only energy in the target band.
     /// Used for VAD energy calculation — does not modify the original buffer.
     pub fn filter(&mut self, samples: &[i16]) -> Vec<i16> {
-        samples
-            .iter()
-            .map(|&s| {
-                let filtered = self.lowpass.process(self.highpass.process(s as f64));
-                filtered.clamp(-32768.0, 32767.0) as i16
-            })
-            .collect()
+        samples.iter().map(|&s| self.process(s)).collect()
+    }
+
+    /// RMS energy of the filtered samples, computed sample by sample
+    /// without materializing them. Same result as `rms_energy(&filter(..))`.
+    pub fn filtered_energy(&mut self, samples: impl IntoIterator<Item = i16>) -> f64 {
+        let (mut sum, mut count) = (0.0, 0usize);
+        for sample in samples {
+            let filtered = self.process(sample) as f64;
+            sum += filtered * filtered;
+            count += 1;
+        }
+        if count == 0 {
+            return 0.0;
+        }
+        (sum / count as f64).sqrt()
+    }
+
+    fn process(&mut self, sample: i16) -> i16 {
+        if !self.settled {
+            // The lowpass only sees what the highpass lets through: nothing,
+            // for a constant
+            self.highpass.settle(sample as f64);
+            self.settled = true;
+        }
+        let filtered = self.lowpass.process(self.highpass.process(sample as f64));
+        filtered.clamp(-32768.0, 32767.0) as i16
     }
 }
 
@@ -236,6 +383,9 @@ pub fn resample_linear(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16
     if from_rate == to_rate {
         return samples.to_vec();
     }
+    if from_rate == 0 || to_rate == 0 {
+        return Vec::new();
+    }
 
     let ratio = from_rate as f64 / to_rate as f64;
     let out_len = (samples.len() as f64 / ratio) as usize;
@@ -260,16 +410,49 @@ pub fn resample_linear(samples: &[i16], from_rate: u32, to_rate: u32) -> Vec<i16
     output
 }
 
+/// Top of the telephone speech band. Anything above would alias at 8kHz.
+const TELEPHONY_CUTOFF_HZ: f64 = 3400.0;
+
+/// 16-bit PCM at `sample_rate` as mu-law 8kHz, for TTS vendors that don't
+/// produce it: low-passed to the telephone band (twice, for a steeper
+/// roll-off) so nothing aliases, then resampled.
+pub fn pcm_to_telephony(pcm: &[i16], sample_rate: u32) -> Vec<u8> {
+    if sample_rate <= MULAW_SAMPLE_RATE {
+        return encode_mulaw(&resample_linear(pcm, sample_rate, MULAW_SAMPLE_RATE));
+    }
+    let mut first = BiquadFilter::lowpass(TELEPHONY_CUTOFF_HZ, sample_rate as f64);
+    let mut second = BiquadFilter::lowpass(TELEPHONY_CUTOFF_HZ, sample_rate as f64);
+    let filtered: Vec<i16> = pcm
+        .iter()
+        .map(|&s| {
+            let y = second.process(first.process(s as f64));
+            y.clamp(-32768.0, 32767.0) as i16
+        })
+        .collect();
+    encode_mulaw(&resample_linear(&filtered, sample_rate, MULAW_SAMPLE_RATE))
+}
+
 /// Load a WAV file and convert it to mu-law 8kHz, ready for Twilio streaming.
 ///
 /// Handles stereo→mono downmix, arbitrary sample rate resampling, volume
 /// adjustment, and mu-law encoding.
 pub fn load_wav_as_mulaw(path: &Path, volume: f32) -> Result<Vec<u8>, HoldMusicError> {
-    let reader = hound::WavReader::open(path)?;
+    decode_wav_as_mulaw(&std::fs::read(path)?, volume)
+}
+
+/// `load_wav_as_mulaw` on WAV bytes already in memory.
+pub fn decode_wav_as_mulaw(wav_data: &[u8], volume: f32) -> Result<Vec<u8>, HoldMusicError> {
+    let reader = hound::WavReader::new(Cursor::new(wav_data))?;
     let spec = reader.spec();
 
     let channels = spec.channels as usize;
     let sample_rate = spec.sample_rate;
+    // Resampling from a near-zero rate would make the output unboundedly long
+    if !(MIN_WAV_SAMPLE_RATE..=MAX_WAV_SAMPLE_RATE).contains(&sample_rate) {
+        return Err(HoldMusicError::Unsupported(format!(
+            "{sample_rate}Hz sample rate not supported"
+        )));
+    }
 
     // Read samples as i16 (handle both 16-bit and 8-bit)
     let all_samples: Vec<i16> = match spec.sample_format {
@@ -350,14 +533,171 @@ mod tests {
         }
     }
 
+    #[test]
+    fn constant_level_has_no_speech_band_energy() {
+        let mut bandpass = BandpassFilter::new(300.0, 3000.0, 8000.0);
+        let level = mulaw_to_pcm(0);
+        for _ in 0..10 {
+            assert!(bandpass.filtered_energy([level; 160]) < 1.0);
+        }
+    }
+
+    #[test]
+    fn filtered_energy_matches_filter_then_rms() {
+        let samples: Vec<i16> = (0..800).map(|i| ((i * 37) % 4000 - 2000) as i16).collect();
+        let mut a = BandpassFilter::new(300.0, 3000.0, 8000.0);
+        let mut b = BandpassFilter::new(300.0, 3000.0, 8000.0);
+        for chunk in samples.chunks(160) {
+            assert_eq!(
+                a.filtered_energy(chunk.iter().copied()),
+                rms_energy(&b.filter(chunk))
+            );
+        }
+    }
+
+    #[test]
+    fn normalizes_quiet_and_loud_speech_alike() {
+        let tone = |amplitude: f64| -> Vec<u8> {
+            let mut pcm: Vec<i16> = (0..8000)
+                .map(|i| ((i as f64 * 0.3).sin() * amplitude) as i16)
+                .collect();
+            // A pause shouldn't count toward the level
+            pcm.extend([0; 4000]);
+            encode_mulaw(&pcm)
+        };
+        let level = |mulaw: &[u8]| rms_energy(&decode_mulaw(&mulaw[..8000]));
+
+        let mut quiet = tone(1000.0);
+        let mut loud = tone(12000.0);
+        assert!(normalize_mulaw(&mut quiet, -20.0, 24.0) > 0.0);
+        assert!(normalize_mulaw(&mut loud, -20.0, 24.0) < 0.0);
+        let target = MULAW_CLIP as f64 * 0.1;
+        for mulaw in [&quiet, &loud] {
+            assert!(
+                (level(mulaw) / target - 1.0).abs() < 0.1,
+                "{}",
+                level(mulaw)
+            );
+        }
+        assert!(decode_mulaw(&quiet[8000..]).iter().all(|&s| s == 0));
+    }
+
+    #[test]
+    fn normalization_caps_gain_and_leaves_silence() {
+        let mut faint = encode_mulaw(&[200, -200].repeat(4000));
+        assert_eq!(normalize_mulaw(&mut faint, -20.0, 6.0), 6.0);
+        let mut silence = vec![pcm_to_mulaw(0); 8000];
+        assert_eq!(normalize_mulaw(&mut silence, -20.0, 6.0), 0.0);
+        // A square wave at full scale can't go up without clipping
+        let mut peaky = encode_mulaw(&[30000, -30000].repeat(4000));
+        assert!(normalize_mulaw(&mut peaky, 0.0, 12.0) < 1.0);
+    }
+
+    #[test]
+    fn preprocessing_lifts_quiet_speech_over_a_silent_gate() {
+        let config: PreprocessConfig = toml::from_str("gate_dbfs = -55").unwrap();
+        // A faint voice, with hiss and a hum in the pauses
+        let mut pcm: Vec<i16> = (0..8000)
+            .map(|i| ((i as f64 * 0.8).sin() * 800.0) as i16)
+            .collect();
+        pcm.extend((0..4000).map(|i| [3, -3][i % 2] + ((i as f64 * 0.04).sin() * 10.0) as i16));
+
+        let cleaned = preprocess(&pcm, &config);
+        assert_eq!(cleaned.len(), pcm.len());
+        assert!(rms_energy(&cleaned[800..8000]) > 4.0 * rms_energy(&pcm[800..8000]));
+        assert!(cleaned[8160..].iter().all(|&s| s == 0));
+    }
+
     #[test]
     fn wav_roundtrip() {
         let samples: Vec<i16> = (0..100).map(|i| (i * 100) as i16).collect();
-        let wav = pcm_to_wav(&samples).unwrap();
+        let wav = pcm_to_wav(&samples);
+        assert_eq!(wav.len(), WAV_HEADER_BYTES + 200);
+        assert_eq!(&wav[..4], b"RIFF");
+        assert_eq!(wav[4..8], 236u32.to_le_bytes());
+        assert_eq!(&wav[8..16], b"WAVEfmt ");
+        // PCM, mono, 8kHz, 16000 bytes/s, 2-byte frames, 16 bits
+        assert_eq!(
+            wav[16..36],
+            [16, 0, 0, 0, 1, 0, 1, 0, 0x40, 0x1f, 0, 0, 0x80, 0x3e, 0, 0, 2, 0, 16, 0]
+        );
+        assert_eq!(&wav[36..40], b"data");
+        assert_eq!(wav[40..44], 200u32.to_le_bytes());
         let decoded = wav_to_pcm(&wav).unwrap();
         assert_eq!(samples, decoded);
     }
 
+    #[test]
+    fn hold_music_rejects_degenerate_sample_rates() {
+        let wav = |sample_rate| {
+            let spec = hound::WavSpec {
+                channels: 1,
+                sample_rate,
+                bits_per_sample: 16,
+                sample_format: hound::SampleFormat::Int,
+            };
+            let mut buffer = Cursor::new(Vec::new());
+            let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+            for _ in 0..16 {
+                writer.write_sample(1000i16).unwrap();
+            }
+            writer.finalize().unwrap();
+            buffer.into_inner()
+        };
+        assert_eq!(decode_wav_as_mulaw(&wav(16000), 1.0).unwrap().len(), 8);
+        assert!(matches!(
+            decode_wav_as_mulaw(&wav(1), 1.0),
+            Err(HoldMusicError::Unsupported(_))
+        ));
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn resampling_keeps_length_and_range(
+            samples in proptest::collection::vec(proptest::num::i16::ANY, 0..500),
+            from_rate in 100u32..96_000,
+            to_rate in 100u32..96_000,
+        ) {
+            let out = resample_linear(&samples, from_rate, to_rate);
+            let expected = samples.len() as f64 * to_rate as f64 / from_rate as f64;
+            proptest::prop_assert!((out.len() as f64 - expected).abs() <= 1.0);
+            // Interpolation never leaves the input's range
+            if let (Some(min), Some(max)) = (samples.iter().min(), samples.iter().max()) {
+                proptest::prop_assert!(out.iter().all(|s| (min..=max).contains(&s)));
+            }
+            if from_rate == to_rate {
+                proptest::prop_assert_eq!(out, samples);
+            }
+        }
+    }
+
+    #[test]
+    fn resampling_from_or_to_zero_is_empty() {
+        assert!(resample_linear(&[1, 2, 3], 0, 8000).is_empty());
+        assert!(resample_linear(&[1, 2, 3], 8000, 0).is_empty());
+    }
+
+    #[test]
+    fn telephony_conversion_keeps_speech_and_drops_what_would_alias() {
+        let tone = |hz: f64| -> Vec<i16> {
+            (0..24_000)
+                .map(|i| {
+                    let t = i as f64 / 24_000.0;
+                    ((2.0 * std::f64::consts::PI * hz * t).sin() * 8000.0) as i16
+                })
+                .collect()
+        };
+        let energy = |mulaw: &[u8]| rms_energy(&decode_mulaw(&mulaw[800..]));
+
+        let speech = pcm_to_telephony(&tone(1000.0), 24_000);
+        assert_eq!(speech.len(), 8000);
+        let level = energy(&speech);
+        assert!((4500.0..6500.0).contains(&level), "{level}");
+        // 6kHz would fold back to 2kHz
+        let above = energy(&pcm_to_telephony(&tone(6000.0), 24_000));
+        assert!(above < level / 5.0, "{above} vs {level}");
+    }
+
     #[test]
     fn rms_energy_silence() {
         let silence = vec![0i16; 100];
@@ -0,0 +1,217 @@
+use serde::Deserialize;
+
+use crate::config::AzureSpeechConfig;
+use crate::pipeline::stt::{SttError, SttFuture, SttProvider, Transcription};
+use crate::pipeline::{audio, language};
+
+/// Azure Speech short-audio recognition (`[stt] provider = "azure"`), in
+/// the region of the deployment's choosing. The REST API can't detect the
+/// language: without a hint, `[azure] language` is used.
+pub struct AzureStt {
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    language: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Recognition {
+    recognition_status: String,
+    #[serde(default, rename = "NBest")]
+    n_best: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct Candidate {
+    confidence: f32,
+    display: String,
+}
+
+/// The locale Azure expects for an ISO 639-1 code: `default` if it's of
+/// that language, else the language's most widely used one.
+fn locale(code: &str, default: &str) -> String {
+    if language::of_locale(default) == code {
+        return default.to_string();
+    }
+    let locale = match code {
+        "en" => "en-US",
+        "ar" => "ar-SA",
+        "ca" => "ca-ES",
+        "hi" => "hi-IN",
+        "ja" => "ja-JP",
+        "ko" => "ko-KR",
+        "pt" => "pt-BR",
+        "sv" => "sv-SE",
+        "uk" => "uk-UA",
+        "zh" => "zh-CN",
+        _ => return format!("{code}-{}", code.to_ascii_uppercase()),
+    };
+    locale.to_string()
+}
+
+impl AzureStt {
+    pub fn new(client: reqwest::Client, api_key: String, config: &AzureSpeechConfig) -> Self {
+        let endpoint = match config.endpoint {
+            Some(ref endpoint) => endpoint.trim_end_matches('/').to_string(),
+            None => format!("https://{}.stt.speech.microsoft.com", config.region),
+        };
+        Self {
+            client,
+            url: format!("{endpoint}/speech/recognition/conversation/cognitiveservices/v1"),
+            api_key,
+            language: config.language.clone(),
+        }
+    }
+
+    async fn request(
+        &self,
+        wav_data: Vec<u8>,
+        hint: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        let language = match hint {
+            Some(hint) => locale(hint, &self.language),
+            None => self.language.clone(),
+        };
+        // Raw, so `[profanity]` decides what's masked
+        let query = [
+            ("language", language.as_str()),
+            ("format", "detailed"),
+            ("profanity", "raw"),
+        ];
+
+        let resp = self
+            .client
+            .post(&self.url)
+            .header("Ocp-Apim-Subscription-Key", &self.api_key)
+            .header(
+                reqwest::header::CONTENT_TYPE,
+                "audio/wav; codecs=audio/pcm; samplerate=8000",
+            )
+            .query(&query)
+            .body(wav_data)
+            .send()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(SttError::Api(format!("{status}: {body}")));
+        }
+
+        let result: Recognition = resp
+            .json()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+        let best = match result.recognition_status.as_str() {
+            "Success" => result.n_best.into_iter().next(),
+            // No speech comes back as an empty transcript, as with Whisper
+            "NoMatch" | "InitialSilenceTimeout" | "BabbleTimeout" => None,
+            status => return Err(SttError::Api(status.to_string())),
+        };
+        Ok(Transcription {
+            text: best
+                .as_ref()
+                .map_or_else(String::new, |b| b.display.clone()),
+            language: None,
+            confidence: best.map(|b| b.confidence),
+            speakers: Vec::new(),
+        })
+    }
+}
+
+impl SttProvider for AzureStt {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        wav_data: Vec<u8>,
+        language: Option<&'a str>,
+        _context: Option<&'a str>,
+    ) -> SttFuture<'a, Transcription> {
+        Box::pin(self.request(wav_data, language))
+    }
+
+    /// Recognize a tenth of a second of silence; the key and region are
+    /// all that can go wrong.
+    fn probe(&self) -> SttFuture<'_, ()> {
+        Box::pin(async {
+            let silence = audio::pcm_to_wav(&[0; 800]);
+            self.request(silence, None).await.map(|_| ())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use axum::extract::Query;
+    use axum::http::HeaderMap;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    /// An Azure Speech endpoint hearing the body length in the requested
+    /// locale, and nothing in a tenth of a second.
+    async fn server() -> String {
+        let app = Router::new().route(
+            "/speech/recognition/conversation/cognitiveservices/v1",
+            post(
+                |Query(query): Query<HashMap<String, String>>,
+                 headers: HeaderMap,
+                 body: axum::body::Bytes| async move {
+                    assert_eq!(headers["ocp-apim-subscription-key"], "az-test");
+                    assert_eq!(query["format"], "detailed");
+                    if body.len() == 44 + 1600 {
+                        return Json(json!({ "RecognitionStatus": "InitialSilenceTimeout" }));
+                    }
+                    Json(json!({
+                        "RecognitionStatus": "Success",
+                        "DisplayText": "-",
+                        "NBest": [{
+                            "Confidence": 0.93,
+                            "Display": format!("{} bytes in {}", body.len(), query["language"]),
+                        }],
+                    })) as Json<Value>
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn transcribes_in_the_hinted_locale() {
+        let url = server().await;
+        let config: AzureSpeechConfig =
+            toml::from_str(&format!("endpoint = {url:?}\nlanguage = \"en-GB\"")).unwrap();
+        let azure = AzureStt::new(reqwest::Client::new(), "az-test".into(), &config);
+
+        let heard = azure
+            .transcribe(vec![0; 44], Some("es"), None)
+            .await
+            .unwrap();
+        assert_eq!(heard.text, "44 bytes in es-ES");
+        assert_eq!(heard.confidence, Some(0.93));
+        let heard = azure.transcribe(vec![0; 10], None, None).await.unwrap();
+        assert_eq!(heard.text, "10 bytes in en-GB");
+
+        azure.probe().await.unwrap();
+    }
+
+    #[test]
+    fn prefers_the_configured_locale() {
+        assert_eq!(locale("en", "en-GB"), "en-GB");
+        assert_eq!(locale("en", "es-MX"), "en-US");
+        assert_eq!(locale("de", "en-US"), "de-DE");
+        assert_eq!(locale("zh", "en-US"), "zh-CN");
+    }
+}
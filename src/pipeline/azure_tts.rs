@@ -0,0 +1,184 @@
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::config::AzureSpeechConfig;
+use crate::pipeline::tts::{TtsError, TtsFuture, TtsProvider};
+
+/// Azure Speech neural voices (`[tts] provider = "azure"`), in the `[azure]`
+/// region. Azure renders telephone audio itself: asked for
+/// `raw-8khz-8bit-mono-mulaw`, it needs no conversion.
+pub struct AzureTts {
+    client: reqwest::Client,
+    endpoint: String,
+    api_key: String,
+}
+
+/// Characters per request, well inside Azure's limit on SSML size.
+const MAX_CHARS: usize = 3000;
+
+/// `<break>` tags from `[tts.spell] ssml`, kept as markup when the rest is
+/// escaped.
+static BREAKS: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"<break\b[^<>]*/>").unwrap());
+
+/// `text` as XML character data.
+fn escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+/// The SSML document speaking `text` in `voice`, whose name starts with its
+/// locale (`en-US-JennyNeural`).
+fn ssml(text: &str, voice: &str) -> String {
+    let locale = match voice.splitn(3, '-').collect::<Vec<_>>()[..] {
+        [language, region, _] => format!("{language}-{region}"),
+        _ => "en-US".to_string(),
+    };
+    let mut body = String::new();
+    let mut rest = 0;
+    for tag in BREAKS.find_iter(text) {
+        body.push_str(&escape(&text[rest..tag.start()]));
+        body.push_str(tag.as_str());
+        rest = tag.end();
+    }
+    body.push_str(&escape(&text[rest..]));
+    format!(
+        "<speak version='1.0' xml:lang='{}'><voice name='{}'>{body}</voice></speak>",
+        escape(&locale),
+        escape(voice)
+    )
+}
+
+impl AzureTts {
+    pub fn new(client: reqwest::Client, api_key: String, config: &AzureSpeechConfig) -> Self {
+        let endpoint = match config.tts_endpoint {
+            Some(ref endpoint) => endpoint.trim_end_matches('/').to_string(),
+            None => format!("https://{}.tts.speech.microsoft.com", config.region),
+        };
+        Self {
+            client,
+            endpoint,
+            api_key,
+        }
+    }
+
+    async fn request(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        let resp = self
+            .client
+            .post(format!("{}/cognitiveservices/v1", self.endpoint))
+            .header("Ocp-Apim-Subscription-Key", &self.api_key)
+            .header(reqwest::header::CONTENT_TYPE, "application/ssml+xml")
+            .header("X-Microsoft-OutputFormat", "raw-8khz-8bit-mono-mulaw")
+            .header(reqwest::header::USER_AGENT, "voice-echo")
+            .body(ssml(text, voice_id))
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(TtsError::Api(format!("{status}: {body}")));
+        }
+
+        let audio = resp
+            .bytes()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        Ok(audio.to_vec())
+    }
+
+    /// Credential check: list voices.
+    async fn voices(&self) -> Result<(), TtsError> {
+        let resp = self
+            .client
+            .get(format!("{}/cognitiveservices/voices/list", self.endpoint))
+            .header("Ocp-Apim-Subscription-Key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(TtsError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl TtsProvider for AzureTts {
+    fn name(&self) -> &'static str {
+        "azure"
+    }
+
+    /// Neural voices have no model to pick; the voice is the model.
+    fn model(&self) -> &str {
+        "neural"
+    }
+
+    fn max_chars(&self) -> usize {
+        MAX_CHARS
+    }
+
+    fn synthesize<'a>(&'a self, text: &'a str, voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+        Box::pin(self.request(text, voice_id))
+    }
+
+    fn probe(&self) -> TtsFuture<'_, ()> {
+        Box::pin(self.voices())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::Router;
+
+    /// An Azure TTS endpoint answering with one mu-law byte per byte of
+    /// SSML.
+    async fn server() -> String {
+        let app = Router::new()
+            .route(
+                "/cognitiveservices/v1",
+                post(|headers: HeaderMap, body: String| async move {
+                    assert_eq!(headers["ocp-apim-subscription-key"], "az-test");
+                    assert_eq!(
+                        headers["x-microsoft-outputformat"],
+                        "raw-8khz-8bit-mono-mulaw"
+                    );
+                    assert!(body.contains("<voice name='es-ES-ElviraNeural'>Hola</voice>"));
+                    vec![0xff; body.len()]
+                }),
+            )
+            .route("/cognitiveservices/voices/list", get(|| async { "[]" }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn speaks_ssml_in_the_given_voice() {
+        let url = server().await;
+        let config: AzureSpeechConfig = toml::from_str(&format!("tts_endpoint = {url:?}")).unwrap();
+        let tts = AzureTts::new(reqwest::Client::new(), "az-test".into(), &config);
+
+        let audio = tts.synthesize("Hola", "es-ES-ElviraNeural").await.unwrap();
+        assert_eq!(audio.len(), ssml("Hola", "es-ES-ElviraNeural").len());
+        tts.probe().await.unwrap();
+    }
+
+    #[test]
+    fn escapes_text_but_keeps_breaks() {
+        assert_eq!(
+            ssml(r#"Q&A <b> A <break time="300ms"/> 1"#, "en-GB-SoniaNeural"),
+            "<speak version='1.0' xml:lang='en-GB'><voice name='en-GB-SoniaNeural'>\
+             Q&amp;A &lt;b&gt; A <break time=\"300ms\"/> 1</voice></speak>"
+        );
+        assert!(ssml("Hi", "custom").contains("xml:lang='en-US'"));
+    }
+}
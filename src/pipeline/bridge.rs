@@ -1,71 +1,629 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
 use serde_json::json;
+use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::InvalidHeaderValue;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::breaker::CircuitBreaker;
+use crate::chaos::FaultInjector;
+use crate::pipeline::bridge_auth::BridgeAuth;
+use crate::pipeline::notify;
+
+/// How long the WebSocket handshake may take before the turn goes over
+/// HTTP instead.
+const WS_CONNECT_TIMEOUT: Duration = Duration::from_secs(5);
 
 /// HTTP client for bridge-echo. Sends transcribed speech to the multiplexer
 /// and receives Claude's response. All session management and trust context
 /// wrapping is handled by bridge-echo.
+///
+/// Requests go over pooled keep-alive HTTP connections, or optionally over a
+/// single persistent WebSocket to `/ws` (see [`BridgeClient::with_websocket`]).
 pub struct BridgeClient {
     url: String,
     caller_name: String,
     client: reqwest::Client,
+    breaker: CircuitBreaker,
+    timeout: Option<Duration>,
+    retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    auth: BridgeAuth,
+    ws: Option<WsLink>,
+    streaming: bool,
+    mcp_config: Option<String>,
+    chaos: FaultInjector,
+}
+
+/// What bridge-echo is told about a turn besides the words.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct Metadata<'a> {
+    /// Why an outbound call was placed, so Claude knows why it called.
+    /// Consumed on first utterance.
+    pub context: Option<&'a str>,
+    /// The call's agent, so bridge-echo can pick the persona.
+    pub agent: Option<&'a str>,
+    /// The call's addition to its system prompt.
+    pub system_prompt: Option<&'a str>,
+    /// Tools Claude may use on this turn; none when empty.
+    pub tools: &'a [String],
 }
 
 impl BridgeClient {
-    pub fn new(bridge_url: &str, caller_name: String) -> Self {
+    pub fn new(
+        client: reqwest::Client,
+        bridge_url: &str,
+        caller_name: String,
+        breaker: CircuitBreaker,
+    ) -> Self {
         Self {
             url: format!("{}/chat", bridge_url.trim_end_matches('/')),
             caller_name,
-            client: reqwest::Client::new(),
+            client,
+            breaker,
+            timeout: None,
+            retries: 0,
+            retry_delay: Duration::ZERO,
+            max_retry_delay: Duration::ZERO,
+            auth: BridgeAuth::None,
+            ws: None,
+            streaming: false,
+            mcp_config: None,
+            chaos: FaultInjector::default(),
         }
     }
 
-    /// Send a voice transcript to bridge-echo and get the response.
+    /// MCP server config for bridge-echo to hand the Claude CLI
+    /// (`--mcp-config`) on turns granted tools.
+    pub fn with_mcp_config(mut self, mcp_config: Option<String>) -> Self {
+        self.mcp_config = mcp_config;
+        self
+    }
+
+    /// Authenticate requests to bridge-echo, including the WebSocket
+    /// handshake.
+    pub fn with_auth(mut self, auth: BridgeAuth) -> Self {
+        self.auth = auth;
+        self
+    }
+
+    /// Give up on a request after `timeout_ms`. 0 means no timeout.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+        self
+    }
+
+    /// Retry requests that never reached bridge-echo (connection failures,
+    /// 502/503/504) up to `retries` times, `delay_ms` apart.
     ///
-    /// The `context` parameter is used for outbound calls — it tells Claude
-    /// why it initiated the call. Consumed on first utterance.
+    /// Timeouts are not retried: bridge-echo may still be working on the
+    /// message, and resending it would duplicate the turn.
+    pub fn with_retries(mut self, retries: u32, delay_ms: u64) -> Self {
+        self.retries = retries;
+        self.retry_delay = Duration::from_millis(delay_ms);
+        self
+    }
+
+    /// Double the delay after each failed retry, up to `max_delay_ms`. At or
+    /// below the retry delay, retries stay evenly spaced.
+    pub fn with_max_retry_delay(mut self, max_delay_ms: u64) -> Self {
+        self.max_retry_delay = Duration::from_millis(max_delay_ms);
+        self
+    }
+
+    /// How long to wait before retry number `attempt` (from 1).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        delay.min(self.max_retry_delay.max(self.retry_delay))
+    }
+
+    /// Inject `[chaos.brain]` faults into every attempt.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Send turns over a persistent WebSocket to `<bridge_url>/ws` instead of
+    /// one HTTP request per turn. Falls back to HTTP while the socket can't
+    /// be established.
+    pub fn with_websocket(mut self) -> Self {
+        let base = self.url.trim_end_matches("/chat");
+        let ws_url = match base.split_once("://") {
+            Some(("https", rest)) => format!("wss://{rest}/ws"),
+            Some((_, rest)) => format!("ws://{rest}/ws"),
+            None => format!("ws://{base}/ws"),
+        };
+        self.ws = Some(WsLink::new(ws_url));
+        self
+    }
+
+    /// Ask bridge-echo to stream responses as server-sent events, so
+    /// [`BridgeClient::send_streaming`] can pass text on as it's written.
+    /// Streamed turns always go over HTTP.
+    pub fn with_streaming(mut self) -> Self {
+        self.streaming = true;
+        self
+    }
+
+    /// Whether responses are streamed.
+    pub fn streams(&self) -> bool {
+        self.streaming
+    }
+
+    /// Circuit breaker guarding bridge-echo.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// Check that bridge-echo accepts connections. Any HTTP response counts;
+    /// bridge-echo has no dedicated health route. Bypasses the breaker.
+    pub async fn probe(&self) -> Result<(), BridgeError> {
+        let base = self.url.trim_end_matches("/chat");
+        let mut req = self.client.get(base);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        req.send()
+            .await
+            .map(|_| ())
+            .map_err(|e| BridgeError::Unreachable(e.to_string()))
+    }
+
+    /// Tell bridge-echo the call is over, so it drops the session.
+    pub async fn notify_call_ended(&self, call_sid: &str) {
+        let base = self.url.trim_end_matches("/chat");
+        notify::notify_call_ended(&self.client, base, &self.auth, call_sid).await;
+    }
+
+    /// Send a voice transcript to bridge-echo and get the response.
     pub async fn send(
         &self,
         call_sid: &str,
         transcript: &str,
-        context: Option<&str>,
+        metadata: &Metadata<'_>,
     ) -> Result<String, BridgeError> {
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| BridgeError::Unavailable(e.to_string()))?;
+        let body = self.body(call_sid, transcript, metadata);
+        let result = self.request(call_sid, &body).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// `send`, passing each piece of the response to `deltas` as bridge-echo
+    /// writes it. Returns the whole response.
+    ///
+    /// bridge-echo streams `data: {"delta": ".."}` events and ends with
+    /// `data: {"response": ".."}`; one that answers with plain JSON instead
+    /// is passed on in a single piece.
+    pub async fn send_streaming(
+        &self,
+        call_sid: &str,
+        transcript: &str,
+        metadata: &Metadata<'_>,
+        deltas: mpsc::UnboundedSender<String>,
+    ) -> Result<String, BridgeError> {
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| BridgeError::Unavailable(e.to_string()))?;
+        let mut body = self.body(call_sid, transcript, metadata);
+        body["stream"] = json!(true);
+
+        // Unreachable means nothing was streamed yet, so retrying is safe
+        let mut attempt = 0;
+        let result = loop {
+            match self.attempt_streaming(&body, &deltas).await {
+                Err(e @ BridgeError::Unreachable(_)) if attempt < self.retries => {
+                    attempt += 1;
+                    tracing::warn!(call_sid, attempt, "Retrying bridge request: {e}");
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                }
+                result => break result,
+            }
+        };
+        permit.finish(result.is_ok());
+        result
+    }
+
+    fn body(&self, call_sid: &str, transcript: &str, turn: &Metadata<'_>) -> serde_json::Value {
         let mut metadata = json!({
             "call_sid": call_sid,
         });
-        if let Some(ctx) = context {
+        if let Some(ctx) = turn.context {
             metadata["context"] = json!(ctx);
         }
+        if let Some(agent) = turn.agent {
+            metadata["agent"] = json!(agent);
+        }
+        if let Some(system_prompt) = turn.system_prompt {
+            metadata["system_prompt"] = json!(system_prompt);
+        }
+        if !turn.tools.is_empty() {
+            let mut tools = json!({ "allowed": turn.tools });
+            if let Some(ref mcp_config) = self.mcp_config {
+                tools["mcp_config"] = json!(mcp_config);
+            }
+            metadata["tools"] = tools;
+        }
 
-        let body = json!({
+        json!({
             "channel": "voice",
             "sender": self.caller_name,
             "message": transcript,
             "metadata": metadata,
-        });
-
-        let resp = self
-            .client
-            .post(&self.url)
-            .json(&body)
-            .send()
-            .await
-            .map_err(|e| BridgeError::Request(e.to_string()))?;
+        })
+    }
 
-        if !resp.status().is_success() {
-            let status = resp.status();
-            let body = resp.text().await.unwrap_or_default();
-            return Err(BridgeError::Response(format!("HTTP {status}: {body}")));
+    async fn request(
+        &self,
+        call_sid: &str,
+        body: &serde_json::Value,
+    ) -> Result<String, BridgeError> {
+        let mut attempt = 0;
+        loop {
+            match self.attempt(body).await {
+                Err(e @ BridgeError::Unreachable(_)) if attempt < self.retries => {
+                    attempt += 1;
+                    tracing::warn!(call_sid, attempt, "Retrying bridge request: {e}");
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                }
+                result => return result,
+            }
         }
+    }
 
-        let parsed: serde_json::Value = resp
-            .json()
+    async fn attempt(&self, body: &serde_json::Value) -> Result<String, BridgeError> {
+        // Injected faults look like a bridge that's down, so they're retried
+        self.chaos
+            .before()
             .await
-            .map_err(|e| BridgeError::Parse(e.to_string()))?;
+            .map_err(|e| BridgeError::Unreachable(e.to_string()))?;
+        let parsed = match &self.ws {
+            Some(ws) => match ws.sender(&self.auth).await {
+                Ok(tx) => self.within_timeout(WsLink::send(tx, body.clone())).await?,
+                Err(e) => {
+                    tracing::debug!("Bridge WebSocket unavailable, using HTTP: {e}");
+                    self.post(body).await?
+                }
+            },
+            None => self.post(body).await?,
+        };
 
-        parsed
+        let response = parsed
             .get("response")
             .and_then(|v| v.as_str())
             .map(String::from)
-            .ok_or_else(|| BridgeError::Parse("Missing 'response' field".into()))
+            .ok_or_else(|| BridgeError::Parse("Missing 'response' field".into()))?;
+        self.chaos.drip(response.len()).await;
+        Ok(response)
+    }
+
+    async fn within_timeout<F>(&self, fut: F) -> Result<serde_json::Value, BridgeError>
+    where
+        F: std::future::Future<Output = Result<serde_json::Value, BridgeError>>,
+    {
+        match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, fut)
+                .await
+                .map_err(|_| BridgeError::Request("timed out".into()))?,
+            None => fut.await,
+        }
+    }
+
+    async fn attempt_streaming(
+        &self,
+        body: &serde_json::Value,
+        deltas: &mpsc::UnboundedSender<String>,
+    ) -> Result<String, BridgeError> {
+        self.chaos
+            .before()
+            .await
+            .map_err(|e| BridgeError::Unreachable(e.to_string()))?;
+        let mut resp = self.send_http(body, "text/event-stream").await?;
+
+        let is_sse = resp
+            .headers()
+            .get(reqwest::header::CONTENT_TYPE)
+            .and_then(|v| v.to_str().ok())
+            .is_some_and(|v| v.starts_with("text/event-stream"));
+        if !is_sse {
+            let parsed: serde_json::Value = resp
+                .json()
+                .await
+                .map_err(|e| BridgeError::Parse(e.to_string()))?;
+            let response = parsed
+                .get("response")
+                .and_then(|v| v.as_str())
+                .map(String::from)
+                .ok_or_else(|| BridgeError::Parse("Missing 'response' field".into()))?;
+            self.chaos.drip(response.len()).await;
+            let _ = deltas.send(response.clone());
+            return Ok(response);
+        }
+
+        let mut buffer = Vec::new();
+        let mut streamed = String::new();
+        while let Some(chunk) = resp
+            .chunk()
+            .await
+            .map_err(|e| BridgeError::Request(e.to_string()))?
+        {
+            buffer.extend_from_slice(&chunk);
+            while let Some(event) = next_event(&mut buffer) {
+                match event? {
+                    StreamEvent::Delta(delta) => {
+                        self.chaos.drip(delta.len()).await;
+                        streamed.push_str(&delta);
+                        let _ = deltas.send(delta);
+                    }
+                    StreamEvent::Done(response) => return Ok(response.unwrap_or(streamed)),
+                }
+            }
+        }
+        Err(BridgeError::Parse(
+            "stream ended before the response did".into(),
+        ))
+    }
+
+    async fn post(&self, body: &serde_json::Value) -> Result<serde_json::Value, BridgeError> {
+        self.send_http(body, "application/json")
+            .await?
+            .json()
+            .await
+            .map_err(|e| BridgeError::Parse(e.to_string()))
+    }
+
+    /// POST `body` to `/chat`, failing on a non-2xx status.
+    async fn send_http(
+        &self,
+        body: &serde_json::Value,
+        accept: &str,
+    ) -> Result<reqwest::Response, BridgeError> {
+        // Serialize up front so an HMAC signature covers the exact bytes sent
+        let bytes = serde_json::to_vec(body).map_err(|e| BridgeError::Request(e.to_string()))?;
+        let mut req = self
+            .auth
+            .apply(self.client.post(&self.url), &bytes)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(reqwest::header::ACCEPT, accept)
+            .body(bytes);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req.send().await.map_err(|e| {
+            if e.is_connect() {
+                BridgeError::Unreachable(e.to_string())
+            } else {
+                BridgeError::Request(e.to_string())
+            }
+        })?;
+
+        let status = resp.status();
+        if !status.is_success() {
+            let body = resp.text().await.unwrap_or_default();
+            let msg = format!("HTTP {status}: {body}");
+            return Err(match status.as_u16() {
+                502..=504 => BridgeError::Unreachable(msg),
+                _ => BridgeError::Response(msg),
+            });
+        }
+        Ok(resp)
+    }
+}
+
+/// One event of a streamed bridge-echo response.
+#[derive(Debug, PartialEq)]
+enum StreamEvent {
+    Delta(String),
+    /// The stream is over, with the whole response if bridge-echo sent it.
+    Done(Option<String>),
+}
+
+/// Take the next complete server-sent event off the front of `buffer`.
+/// Events without data (comments, keep-alives) are skipped.
+fn next_event(buffer: &mut Vec<u8>) -> Option<Result<StreamEvent, BridgeError>> {
+    loop {
+        let (end, separator) = [&b"\r\n\r\n"[..], b"\n\n"]
+            .into_iter()
+            .filter_map(|sep| {
+                let at = buffer.windows(sep.len()).position(|w| w == sep)?;
+                Some((at, sep.len()))
+            })
+            .min()?;
+        // Events end on ASCII, so no character is split
+        let event: Vec<u8> = buffer.drain(..end + separator).collect();
+        let event = String::from_utf8_lossy(&event[..end]);
+
+        let data: Vec<&str> = event
+            .lines()
+            .filter_map(|line| line.strip_prefix("data:"))
+            .map(|data| data.strip_prefix(' ').unwrap_or(data))
+            .collect();
+        if data.is_empty() {
+            continue;
+        }
+        let data = data.join("\n");
+        if data == "[DONE]" {
+            return Some(Ok(StreamEvent::Done(None)));
+        }
+        let parsed: serde_json::Value = match serde_json::from_str(&data) {
+            Ok(v) => v,
+            Err(e) => return Some(Err(BridgeError::Parse(e.to_string()))),
+        };
+        if let Some(err) = parsed.get("error").and_then(|v| v.as_str()) {
+            return Some(Err(BridgeError::Response(err.to_string())));
+        }
+        if let Some(response) = parsed.get("response").and_then(|v| v.as_str()) {
+            return Some(Ok(StreamEvent::Done(Some(response.to_string()))));
+        }
+        if let Some(delta) = parsed.get("delta").and_then(|v| v.as_str()) {
+            return Some(Ok(StreamEvent::Delta(delta.to_string())));
+        }
+    }
+}
+
+/// Persistent WebSocket to bridge-echo.
+///
+/// Frames are the `/chat` request body plus a numeric `id`; bridge-echo
+/// answers with `{"id": .., "response": ..}` (or `"error"`) for the same id,
+/// so several calls can share the socket. A background task owns the socket
+/// and is respawned on the next request after it drops.
+struct WsLink {
+    url: String,
+    connect_timeout: Duration,
+    tx: Mutex<Option<mpsc::Sender<WsRequest>>>,
+}
+
+struct WsRequest {
+    body: serde_json::Value,
+    reply: oneshot::Sender<Result<serde_json::Value, BridgeError>>,
+}
+
+impl WsLink {
+    fn new(url: String) -> Self {
+        Self {
+            url,
+            connect_timeout: WS_CONNECT_TIMEOUT,
+            tx: Mutex::new(None),
+        }
+    }
+
+    /// Handle to the socket task, if it's running.
+    async fn live(&self) -> Option<mpsc::Sender<WsRequest>> {
+        self.tx
+            .lock()
+            .await
+            .as_ref()
+            .filter(|live| !live.is_closed())
+            .cloned()
+    }
+
+    /// Handle to the live socket task, connecting if needed.
+    async fn sender(&self, auth: &BridgeAuth) -> Result<mpsc::Sender<WsRequest>, BridgeError> {
+        if let Some(live) = self.live().await {
+            return Ok(live);
+        }
+
+        let mut request = self
+            .url
+            .as_str()
+            .into_client_request()
+            .map_err(|e| BridgeError::Unreachable(e.to_string()))?;
+        // Handshake has no body; HMAC signs just the timestamp
+        for (name, value) in auth.headers(b"") {
+            let value = value
+                .parse()
+                .map_err(|e: InvalidHeaderValue| BridgeError::Request(e.to_string()))?;
+            request.headers_mut().insert(name, value);
+        }
+        // Not holding the lock, so a bridge that's slow to answer doesn't
+        // hold up every other call's turn
+        let (socket, _) = tokio::time::timeout(
+            self.connect_timeout,
+            tokio_tungstenite::connect_async(request),
+        )
+        .await
+        .map_err(|_| BridgeError::Unreachable("bridge WebSocket connect timed out".into()))?
+        .map_err(|e| BridgeError::Unreachable(e.to_string()))?;
+
+        let mut tx = self.tx.lock().await;
+        // Another turn connected meanwhile: share its socket, drop this one
+        if let Some(ref live) = *tx {
+            if !live.is_closed() {
+                return Ok(live.clone());
+            }
+        }
+        tracing::info!(url = %self.url, "Bridge WebSocket connected");
+        let (req_tx, req_rx) = mpsc::channel(32);
+        tokio::spawn(run_socket(socket, req_rx));
+        *tx = Some(req_tx.clone());
+        Ok(req_tx)
+    }
+
+    async fn send(
+        tx: mpsc::Sender<WsRequest>,
+        body: serde_json::Value,
+    ) -> Result<serde_json::Value, BridgeError> {
+        let (reply, rx) = oneshot::channel();
+        tx.send(WsRequest { body, reply })
+            .await
+            .map_err(|_| BridgeError::Unreachable("bridge WebSocket closed".into()))?;
+        rx.await
+            .map_err(|_| BridgeError::Request("bridge WebSocket dropped the request".into()))?
+    }
+}
+
+async fn run_socket(
+    socket: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+    mut requests: mpsc::Receiver<WsRequest>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let mut pending: HashMap<u64, oneshot::Sender<Result<serde_json::Value, BridgeError>>> =
+        HashMap::new();
+    let mut next_id: u64 = 0;
+
+    loop {
+        tokio::select! {
+            req = requests.recv() => {
+                let Some(mut req) = req else { break };
+                next_id += 1;
+                req.body["id"] = json!(next_id);
+                if let Err(e) = sink.send(WsMessage::Text(req.body.to_string().into())).await {
+                    // Never reached bridge-echo, safe to retry
+                    let _ = req.reply.send(Err(BridgeError::Unreachable(e.to_string())));
+                    break;
+                }
+                pending.insert(next_id, req.reply);
+            }
+            frame = stream.next() => {
+                let text = match frame {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("Bridge WebSocket error: {e}");
+                        break;
+                    }
+                    Some(Ok(_)) => continue,
+                };
+                let parsed: serde_json::Value = match serde_json::from_str(&text) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        tracing::warn!("Unparseable bridge WebSocket frame: {e}");
+                        continue;
+                    }
+                };
+                let Some(reply) = parsed
+                    .get("id")
+                    .and_then(|v| v.as_u64())
+                    .and_then(|id| pending.remove(&id))
+                else {
+                    continue;
+                };
+                let result = match parsed.get("error").and_then(|v| v.as_str()) {
+                    Some(err) => Err(BridgeError::Response(err.to_string())),
+                    None => Ok(parsed),
+                };
+                let _ = reply.send(result);
+            }
+        }
+    }
+
+    tracing::info!("Bridge WebSocket disconnected");
+    for (_, reply) in pending {
+        let _ = reply.send(Err(BridgeError::Request(
+            "bridge WebSocket closed mid-request".into(),
+        )));
     }
 }
 
@@ -77,4 +635,206 @@ pub enum BridgeError {
     Response(String),
     #[error("Failed to parse bridge response: {0}")]
     Parse(String),
+    #[error("Bridge unavailable: {0}")]
+    Unavailable(String),
+    #[error("Bridge unreachable: {0}")]
+    Unreachable(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::BreakerConfig;
+
+    use axum::http::header::CONTENT_TYPE;
+    use axum::routing::post;
+    use axum::{Json, Router};
+    use serde_json::Value;
+
+    fn client(url: &str) -> BridgeClient {
+        BridgeClient::new(
+            reqwest::Client::new(),
+            url,
+            "User".into(),
+            CircuitBreaker::new("test", &BreakerConfig::default()),
+        )
+    }
+
+    #[test]
+    fn websocket_url_follows_bridge_scheme() {
+        let ws = client("http://localhost:8445/")
+            .with_websocket()
+            .ws
+            .unwrap();
+        assert_eq!(ws.url, "ws://localhost:8445/ws");
+        let wss = client("https://bridge.example.com")
+            .with_websocket()
+            .ws
+            .unwrap();
+        assert_eq!(wss.url, "wss://bridge.example.com/ws");
+    }
+
+    #[tokio::test]
+    async fn websocket_connect_times_out_without_holding_the_link() {
+        // Accepts connections but never answers the handshake
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://{}/ws", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let mut held = Vec::new();
+            while let Ok((stream, _)) = listener.accept().await {
+                held.push(stream);
+            }
+        });
+
+        let mut link = WsLink::new(url);
+        link.connect_timeout = Duration::from_millis(200);
+        let link = std::sync::Arc::new(link);
+        let connecting = tokio::spawn({
+            let link = std::sync::Arc::clone(&link);
+            async move { link.sender(&BridgeAuth::None).await }
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert!(link.tx.try_lock().is_ok(), "lock held while connecting");
+        let result = tokio::time::timeout(Duration::from_secs(2), connecting)
+            .await
+            .expect("connect never gave up")
+            .unwrap();
+        assert!(matches!(result, Err(BridgeError::Unreachable(_))));
+    }
+
+    #[tokio::test]
+    async fn websocket_handshake_is_authenticated_whatever_the_builder_order() {
+        use tokio_tungstenite::tungstenite::handshake::server::{Request, Response};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let (seen_tx, seen_rx) = oneshot::channel();
+        tokio::spawn(async move {
+            let (stream, _) = listener.accept().await.unwrap();
+            // The handshake callback's signature is tungstenite's, not ours
+            #[allow(clippy::result_large_err)]
+            let callback = |req: &Request, resp: Response| {
+                let auth = req
+                    .headers()
+                    .get("Authorization")
+                    .map(|v| v.to_str().unwrap().to_string());
+                let _ = seen_tx.send(auth);
+                Ok(resp)
+            };
+            let _socket = tokio_tungstenite::accept_hdr_async(stream, callback)
+                .await
+                .unwrap();
+            std::future::pending::<()>().await;
+        });
+
+        let client = client(&format!("http://{addr}/chat"))
+            .with_websocket()
+            .with_auth(BridgeAuth::Bearer("s3cret".into()));
+        client
+            .ws
+            .as_ref()
+            .unwrap()
+            .sender(&client.auth)
+            .await
+            .unwrap();
+        assert_eq!(seen_rx.await.unwrap().as_deref(), Some("Bearer s3cret"));
+    }
+
+    #[test]
+    fn body_carries_only_the_metadata_given() {
+        let bridge = client("http://localhost:8445");
+        let turn = Metadata {
+            agent: Some("alerts"),
+            system_prompt: Some("Be brief."),
+            ..Default::default()
+        };
+        assert_eq!(
+            bridge.body("CA1", "hi", &turn)["metadata"],
+            json!({ "call_sid": "CA1", "agent": "alerts", "system_prompt": "Be brief." })
+        );
+        let turn = Metadata {
+            context: Some("Disk full"),
+            ..Default::default()
+        };
+        assert_eq!(
+            bridge.body("CA1", "hi", &turn)["metadata"],
+            json!({ "call_sid": "CA1", "context": "Disk full" })
+        );
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let delays = |client: BridgeClient| -> Vec<u128> {
+            (1..=5).map(|n| client.backoff(n).as_millis()).collect()
+        };
+        let backoff = client("http://bridge")
+            .with_retries(5, 250)
+            .with_max_retry_delay(1500);
+        assert_eq!(delays(backoff), [250, 500, 1000, 1500, 1500]);
+        // No cap above the delay: evenly spaced
+        let fixed = client("http://bridge").with_retries(5, 250);
+        assert_eq!(delays(fixed), [250; 5]);
+    }
+
+    #[test]
+    fn grants_tools_with_the_mcp_config() {
+        let bridge = client("http://localhost:8445").with_mcp_config(Some("/etc/mcp.json".into()));
+        let tools = vec!["mcp__calendar".to_string()];
+        let turn = Metadata {
+            tools: &tools,
+            ..Default::default()
+        };
+        assert_eq!(
+            bridge.body("CA1", "hi", &turn)["metadata"]["tools"],
+            json!({ "allowed": ["mcp__calendar"], "mcp_config": "/etc/mcp.json" })
+        );
+        // Tool-free turns don't mention tools at all
+        let body = bridge.body("CA1", "hi", &Metadata::default());
+        assert!(body["metadata"].get("tools").is_none());
+    }
+
+    #[tokio::test]
+    async fn streams_response_pieces_as_they_arrive() {
+        let app = Router::new().route(
+            "/chat",
+            post(|Json(body): Json<Value>| async move {
+                assert_eq!(body["stream"], true);
+                let events = ": keep-alive\n\n\
+                    data: {\"delta\": \"Hello \"}\n\n\
+                    data: {\"delta\": \"there.\"}\r\n\r\n\
+                    data: {\"response\": \"Hello there.\"}\n\n";
+                ([(CONTENT_TYPE, "text/event-stream")], events)
+            }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let (tx, mut rx) = mpsc::unbounded_channel();
+        let response = client(&url)
+            .with_streaming()
+            .send_streaming("CA1", "hi", &Metadata::default(), tx)
+            .await
+            .unwrap();
+        assert_eq!(response, "Hello there.");
+        assert_eq!(rx.recv().await.as_deref(), Some("Hello "));
+        assert_eq!(rx.recv().await.as_deref(), Some("there."));
+        assert_eq!(rx.recv().await, None);
+    }
+
+    #[test]
+    fn events_wait_for_their_blank_line() {
+        let mut buffer = "data: {\"delta\": \"caf\u{e9}\"}\n".as_bytes().to_vec();
+        assert!(next_event(&mut buffer).is_none());
+        buffer.extend_from_slice(b"\ndata: [DONE]\n\n");
+        assert_eq!(
+            next_event(&mut buffer).unwrap().unwrap(),
+            StreamEvent::Delta("caf\u{e9}".into())
+        );
+        assert_eq!(
+            next_event(&mut buffer).unwrap().unwrap(),
+            StreamEvent::Done(None)
+        );
+        assert!(buffer.is_empty());
+    }
 }
@@ -0,0 +1,375 @@
+//! Brains that call a chat API (or run a model) directly, rather than
+//! through bridge-echo or the host process.
+//!
+//! Every such vendor is stateless, so [`ChatClient`] keeps each call's
+//! conversation and resends it on every turn, and owns the circuit breaker,
+//! injected faults and summarization. A [`ChatProvider`] only maps one
+//! completion request onto its own API.
+
+use std::future::Future;
+use std::pin::Pin;
+
+use crate::breaker::CircuitBreaker;
+use crate::chaos::FaultInjector;
+use crate::config::BrainProvider;
+use crate::pipeline::history::{ChatMessage, History, SessionsDir};
+
+/// What a [`ChatProvider`] call returns.
+pub type ChatFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, ChatError>> + Send + 'a>>;
+
+/// A chat vendor (`[brain] provider`). [`ChatClient`] wraps one with the
+/// conversation history, the circuit breaker, injected faults and
+/// summarization, so a vendor only speaks its own API.
+pub trait ChatProvider: Send + Sync {
+    /// The `[brain] provider` this is.
+    fn brain(&self) -> BrainProvider;
+
+    /// The model answering, for logs.
+    fn model(&self) -> &str;
+
+    /// Answer the last of `messages`, the conversation so far, under
+    /// `system_prompt` (none if empty), in at most `max_tokens` tokens.
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String>;
+
+    /// Cheap credential check.
+    fn probe(&self) -> ChatFuture<'_, ()>;
+
+    /// What to check when [`probe`](Self::probe) fails.
+    fn hint(&self) -> &'static str;
+}
+
+/// Brain for a [`ChatProvider`], holding each call's conversation.
+pub struct ChatClient {
+    provider: Box<dyn ChatProvider>,
+    system_prompt: String,
+    max_response_tokens: u32,
+    history: History,
+    breaker: CircuitBreaker,
+    chaos: FaultInjector,
+}
+
+impl ChatClient {
+    pub fn new(
+        provider: Box<dyn ChatProvider>,
+        system_prompt: String,
+        session_timeout_secs: u64,
+        max_response_tokens: u32,
+        breaker: CircuitBreaker,
+    ) -> Self {
+        Self {
+            provider,
+            system_prompt,
+            max_response_tokens,
+            history: History::new(session_timeout_secs),
+            breaker,
+            chaos: FaultInjector::default(),
+        }
+    }
+
+    /// Inject `[chaos.brain]` faults into every request.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Keep conversations in `dir` across restarts (`[llm] sessions_dir`).
+    pub fn with_sessions_dir(mut self, dir: Option<SessionsDir>) -> Self {
+        self.history = self.history.with_dir(dir);
+        self
+    }
+
+    /// Summarize conversations after `turns` turns
+    /// (`[llm] summarize_after_turns`).
+    pub fn with_summary_after(mut self, turns: usize) -> Self {
+        self.history = self.history.with_summary_after(turns);
+        self
+    }
+
+    /// Circuit breaker guarding the vendor.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// The `[brain] provider` this is.
+    pub fn provider(&self) -> BrainProvider {
+        self.provider.brain()
+    }
+
+    /// What to check when [`probe`](Self::probe) fails.
+    pub fn hint(&self) -> &'static str {
+        self.provider.hint()
+    }
+
+    /// Cheap credential check. Bypasses the breaker.
+    pub async fn probe(&self) -> Result<(), ChatError> {
+        self.provider.probe().await
+    }
+
+    /// Send a prompt on the call's conversation and get the response text.
+    /// `system_prompt` replaces the default one (e.g. a named agent's own).
+    ///
+    /// Fails fast with `ChatError::Unavailable` while the breaker is open.
+    pub async fn send_with_system(
+        &self,
+        call_sid: &str,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<String, ChatError> {
+        let messages = self.history.with_prompt(call_sid, prompt).await;
+        let system_prompt = system_prompt.unwrap_or(&self.system_prompt);
+
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| ChatError::Unavailable(e.to_string()))?;
+        let brain = self.provider.brain();
+        tracing::info!(call_sid, ?brain, model = %self.provider.model(), "Invoking brain");
+        let result = self.attempt(system_prompt, &messages).await;
+        permit.finish(result.is_ok());
+
+        let text = result?;
+        self.history.record(call_sid, prompt, &text).await;
+        tracing::info!(
+            call_sid,
+            ?brain,
+            response_len = text.len(),
+            "Brain responded"
+        );
+        Ok(text)
+    }
+
+    /// Drop a call's conversation (call ended).
+    pub async fn end_session(&self, call_sid: &str) {
+        self.history.end(call_sid).await;
+    }
+
+    /// Summarize the call's conversation if it's due
+    /// (`[llm] summarize_after_turns`).
+    pub async fn summarize(&self, call_sid: &str) {
+        let Some((messages, covered)) = self.history.to_summarize(call_sid).await else {
+            return;
+        };
+        let summary = self.attempt(&self.system_prompt, &messages).await;
+        match summary {
+            Ok(ref summary) => tracing::info!(
+                call_sid,
+                covered,
+                len = summary.len(),
+                "Summarized conversation"
+            ),
+            Err(ref e) => tracing::warn!(call_sid, "Conversation not summarized: {e}"),
+        }
+        self.history
+            .summarized(call_sid, covered, summary.ok())
+            .await;
+    }
+
+    /// One completion, with any injected faults.
+    async fn attempt(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+    ) -> Result<String, ChatError> {
+        self.chaos
+            .before()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        let text = self
+            .provider
+            .complete(system_prompt, messages, self.max_response_tokens)
+            .await?;
+        self.chaos.drip(text.len()).await;
+        Ok(text)
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum ChatError {
+    #[error("HTTP request failed: {0}")]
+    Request(String),
+    #[error("API error: {0}")]
+    Api(String),
+    /// The vendor answered with nothing to say.
+    #[error("{0} returned no text")]
+    Empty(&'static str),
+    #[error("brain unavailable: {0}")]
+    Unavailable(String),
+    #[error("inference failed: {0}")]
+    Inference(String),
+    #[error("prompt is {0} tokens, the context leaves room for {1}")]
+    TooLong(usize, usize),
+}
+
+/// A vendor API for [`ChatProvider`] tests, and the conversation every
+/// vendor is put through.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use std::sync::{Arc, Mutex, MutexGuard};
+
+    use axum::body::Bytes;
+    use axum::http::{HeaderMap, StatusCode, Uri};
+    use axum::response::IntoResponse;
+    use axum::{Json, Router};
+    use serde_json::Value;
+
+    use super::*;
+
+    /// One request the vendor made.
+    pub struct Request {
+        pub path: String,
+        pub headers: HeaderMap,
+        /// The JSON body; `Null` if there's none.
+        pub body: Value,
+    }
+
+    /// Answers each request, on any path, with the next of its replies,
+    /// recording what it was sent.
+    pub struct Fixture {
+        pub url: String,
+        requests: Arc<Mutex<Vec<Request>>>,
+    }
+
+    impl Fixture {
+        pub async fn serve(replies: Vec<(StatusCode, Value)>) -> Self {
+            let requests = Arc::new(Mutex::new(Vec::new()));
+            let seen = Arc::clone(&requests);
+            let replies = Arc::new(Mutex::new(replies.into_iter()));
+            let app = Router::new().fallback(
+                move |uri: Uri, headers: HeaderMap, body: Bytes| async move {
+                    seen.lock().unwrap().push(Request {
+                        path: uri.path().to_string(),
+                        headers,
+                        body: serde_json::from_slice(&body).unwrap_or(Value::Null),
+                    });
+                    let (status, reply) = replies.lock().unwrap().next().expect("no reply left");
+                    (status, Json(reply)).into_response()
+                },
+            );
+            let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+            let url = format!("http://{}", listener.local_addr().unwrap());
+            tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+            Self { url, requests }
+        }
+
+        pub fn requests(&self) -> MutexGuard<'_, Vec<Request>> {
+            self.requests.lock().unwrap()
+        }
+    }
+
+    /// Put `provider` through a call: "hi" answered with "reply 1", "lost"
+    /// failing, and "again" under the system prompt "You are Sentinel."
+    /// answered with "reply 3", with at most 256 tokens each. Returns the
+    /// failed turn's error; the fixture has the requests.
+    pub async fn converse(provider: Box<dyn ChatProvider>) -> ChatError {
+        let client = ChatClient::new(
+            provider,
+            "You are Echo.".to_string(),
+            300,
+            256,
+            CircuitBreaker::new("test", &Default::default()),
+        );
+        assert_eq!(
+            client.send_with_system("CA1", "hi", None).await.unwrap(),
+            "reply 1"
+        );
+        let err = client
+            .send_with_system("CA1", "lost", None)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            client
+                .send_with_system("CA1", "again", Some("You are Sentinel."))
+                .await
+                .unwrap(),
+            "reply 3"
+        );
+        err
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use crate::config::BreakerConfig;
+
+    /// A vendor echoing the conversation it was sent, counting requests.
+    /// Fails while `failing` is set.
+    #[derive(Default)]
+    struct Echoing {
+        requests: Arc<AtomicUsize>,
+        failing: Arc<Mutex<bool>>,
+    }
+
+    impl ChatProvider for Echoing {
+        fn brain(&self) -> BrainProvider {
+            BrainProvider::OpenAi
+        }
+
+        fn model(&self) -> &str {
+            "m"
+        }
+
+        fn complete<'a>(
+            &'a self,
+            system_prompt: &'a str,
+            messages: &'a [ChatMessage],
+            _max_tokens: u32,
+        ) -> ChatFuture<'a, String> {
+            self.requests.fetch_add(1, Ordering::SeqCst);
+            let failing = *self.failing.lock().unwrap();
+            Box::pin(async move {
+                if failing {
+                    return Err(ChatError::Api("503".into()));
+                }
+                let said: Vec<&str> = messages.iter().map(|m| m.content.as_str()).collect();
+                Ok(format!("{system_prompt}|{}", said.join(",")))
+            })
+        }
+
+        fn probe(&self) -> ChatFuture<'_, ()> {
+            Box::pin(async { Ok(()) })
+        }
+
+        fn hint(&self) -> &'static str {
+            ""
+        }
+    }
+
+    #[tokio::test]
+    async fn open_breaker_fails_fast() {
+        let echoing = Echoing::default();
+        let (requests, failing) = (Arc::clone(&echoing.requests), Arc::clone(&echoing.failing));
+        let breaker: BreakerConfig = toml::from_str("consecutive_failures = 1").unwrap();
+        let client = ChatClient::new(
+            Box::new(echoing),
+            "sys".to_string(),
+            300,
+            256,
+            CircuitBreaker::new("test", &breaker),
+        );
+
+        assert_eq!(
+            client.send_with_system("CA1", "hi", None).await.unwrap(),
+            "sys|hi"
+        );
+        *failing.lock().unwrap() = true;
+        client
+            .send_with_system("CA1", "lost", None)
+            .await
+            .unwrap_err();
+        let err = client
+            .send_with_system("CA1", "lost", None)
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ChatError::Unavailable(_)), "{err}");
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,162 @@
+//! Making brain responses fit for the phone (`[concise]`).
+//!
+//! Brains write for screens: markdown lists, code blocks and answers far
+//! longer than anyone wants to hear. Between the brain and TTS, each
+//! sentence has its markdown stripped, code blocks are replaced by a short
+//! spoken note, and with `max_sentences` set, the rest of a long answer is
+//! left unsaid. Directives and action objects pass through untouched.
+
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::config::ConciseConfig;
+
+/// `[text](url)`, read as just the text.
+static LINK: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\[([^\[\]]*)\]\([^)\s]*\)").unwrap());
+
+/// Cleans one response's sentences, in order.
+pub struct Concise<'a> {
+    config: &'a ConciseConfig,
+    /// Inside a ``` fence.
+    in_code: bool,
+    /// The code note was already spoken.
+    noted: bool,
+    spoken: usize,
+}
+
+impl<'a> Concise<'a> {
+    pub fn new(config: &'a ConciseConfig) -> Self {
+        Self {
+            config,
+            in_code: false,
+            noted: false,
+            spoken: 0,
+        }
+    }
+
+    /// The sentence as it should be spoken, or `None` if nothing of it
+    /// should be (all code or markup, or past `max_sentences`).
+    pub fn clean(&mut self, sentence: &str) -> Option<String> {
+        let mut lines = Vec::new();
+        for line in sentence.lines() {
+            let trimmed = line.trim();
+            if trimmed.starts_with("```") {
+                if !self.in_code && !self.noted && !self.config.code_notice.is_empty() {
+                    lines.push(self.config.code_notice.clone());
+                    self.noted = true;
+                }
+                self.in_code = !self.in_code;
+                continue;
+            }
+            if self.in_code {
+                continue;
+            }
+            let line = match self.config.markdown {
+                true => strip_markdown(trimmed),
+                false => trimmed.to_string(),
+            };
+            if !line.is_empty() {
+                lines.push(line);
+            }
+        }
+        if lines.is_empty() {
+            return None;
+        }
+        if self.config.max_sentences > 0 && self.spoken >= self.config.max_sentences {
+            return None;
+        }
+        self.spoken += 1;
+        Some(lines.join("\n"))
+    }
+}
+
+/// One line of markdown as plain text.
+fn strip_markdown(line: &str) -> String {
+    // Rules (---, ***) and table separators (|---|:--|) say nothing
+    if line.len() >= 3
+        && line
+            .chars()
+            .all(|c| matches!(c, '-' | '*' | '_' | '|' | ':' | ' '))
+    {
+        return String::new();
+    }
+
+    let mut line = line.trim_start_matches('>').trim_start();
+    let hashes = line.len() - line.trim_start_matches('#').len();
+    if hashes > 0 && line[hashes..].starts_with(' ') {
+        line = line[hashes..].trim_start();
+    }
+    for bullet in ["- ", "* ", "+ ", "• "] {
+        if let Some(rest) = line.strip_prefix(bullet) {
+            line = rest.trim_start();
+            break;
+        }
+    }
+
+    let line = LINK.replace_all(line, "$1");
+    let line = line.replace("**", "").replace("__", "").replace("~~", "");
+    let line: String = line.chars().filter(|&c| c != '`' && c != '*').collect();
+    // Table rows: cells read as a list
+    if line.starts_with('|') && line.ends_with('|') && line.len() > 1 {
+        return line[1..line.len() - 1]
+            .split('|')
+            .map(str::trim)
+            .collect::<Vec<_>>()
+            .join(", ");
+    }
+    line
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> ConciseConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn strips_markdown() {
+        assert_eq!(
+            strip_markdown("## **Step 1:** run `ls` — see [the docs](https://x.io/a)"),
+            "Step 1: run ls — see the docs"
+        );
+        assert_eq!(strip_markdown("- *first* item"), "first item");
+        assert_eq!(strip_markdown("> quoted"), "quoted");
+        assert_eq!(strip_markdown("---"), "");
+        assert_eq!(strip_markdown("| Plan | Price |"), "Plan, Price");
+        assert_eq!(strip_markdown("|---|:---:|"), "");
+        // Directives and snake_case are left alone
+        assert_eq!(
+            strip_markdown("[VOICE:abc] Your id is user_name."),
+            "[VOICE:abc] Your id is user_name."
+        );
+    }
+
+    #[test]
+    fn replaces_code_blocks_with_one_note() {
+        let config = config("");
+        let mut concise = Concise::new(&config);
+        assert_eq!(
+            concise.clean("Here you go:\n```python").as_deref(),
+            Some("Here you go:\nI've got the code, I'll send it separately.")
+        );
+        assert_eq!(concise.clean("print(1)\nprint(2)"), None);
+        assert_eq!(
+            concise.clean("```\nThat's all."),
+            Some("That's all.".into())
+        );
+        assert_eq!(concise.clean("```js\nx()\n```"), None);
+    }
+
+    #[test]
+    fn stops_after_max_sentences() {
+        let config = config("max_sentences = 2");
+        let mut concise = Concise::new(&config);
+        assert!(concise.clean("One.").is_some());
+        assert_eq!(concise.clean("---"), None);
+        assert!(concise.clean("Two.").is_some());
+        assert_eq!(concise.clean("Three."), None);
+    }
+}
@@ -1,9 +1,10 @@
-use std::collections::HashMap;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
+use std::time::Duration;
 
 use echo_system_types::llm::{LmProvider, Message, MessageContent, Role};
-use tokio::sync::Mutex;
+
+use crate::chaos::FaultInjector;
+use crate::pipeline::history::{ChatMessage, ChatRole, History, SessionsDir};
 
 /// LLM conversation manager. Maintains per-call message history and invokes
 /// the provider with the full history on each turn.
@@ -12,15 +13,12 @@ use tokio::sync::Mutex;
 /// but backed by any `Arc<dyn LmProvider>` instead of the Claude CLI subprocess.
 pub struct ConversationManager {
     provider: Arc<dyn LmProvider>,
-    sessions: Arc<Mutex<HashMap<String, Session>>>,
-    session_timeout: Duration,
+    history: History,
     system_prompt: String,
     max_response_tokens: u32,
-}
-
-struct Session {
-    messages: Vec<Message>,
-    last_used: Instant,
+    timeout: Option<Duration>,
+    retries: u32,
+    chaos: FaultInjector,
 }
 
 impl ConversationManager {
@@ -32,79 +30,163 @@ impl ConversationManager {
     ) -> Self {
         Self {
             provider,
-            sessions: Arc::new(Mutex::new(HashMap::new())),
-            session_timeout: Duration::from_secs(session_timeout_secs),
+            history: History::new(session_timeout_secs),
             system_prompt,
             max_response_tokens,
+            timeout: None,
+            retries: 0,
+            chaos: FaultInjector::default(),
         }
     }
 
+    /// Give up on a provider call after `timeout_ms`. 0 disables the
+    /// timeout. The call is dropped, which cancels whatever the provider
+    /// was doing.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+        self
+    }
+
+    /// Try a failed or timed-out provider call up to `retries` more times.
+    /// The turn isn't in the history until answered, so a retry asks the
+    /// same question again.
+    pub fn with_retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Inject `[chaos.brain]` faults into every provider call.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Keep conversations in `dir` across restarts (`[llm] sessions_dir`).
+    pub fn with_sessions_dir(mut self, dir: Option<SessionsDir>) -> Self {
+        self.history = self.history.with_dir(dir);
+        self
+    }
+
+    /// Summarize conversations after `turns` turns
+    /// (`[llm] summarize_after_turns`).
+    pub fn with_summary_after(mut self, turns: usize) -> Self {
+        self.history = self.history.with_summary_after(turns);
+        self
+    }
+
     /// Send a prompt and get the response text.
     ///
     /// Maintains per-call message history so multi-turn voice conversations
     /// carry context across utterances within a single call.
     pub async fn send(&self, call_sid: &str, prompt: &str) -> Result<String, ConversationError> {
-        let mut sessions = self.sessions.lock().await;
+        self.send_with_system(call_sid, prompt, None).await
+    }
 
-        // Clean up expired sessions
-        sessions.retain(|_, s| s.last_used.elapsed() < self.session_timeout);
+    /// Like [`send`](Self::send), but with a system prompt replacing the
+    /// default one (e.g. a named agent's own `self_path`).
+    pub async fn send_with_system(
+        &self,
+        call_sid: &str,
+        prompt: &str,
+        system_prompt: Option<&str>,
+    ) -> Result<String, ConversationError> {
+        let messages = to_provider(self.history.with_prompt(call_sid, prompt).await);
 
-        let session = sessions
-            .entry(call_sid.to_string())
-            .or_insert_with(|| Session {
-                messages: Vec::new(),
-                last_used: Instant::now(),
-            });
+        tracing::info!(call_sid, provider = self.provider.name(), "Invoking LLM");
+
+        let system_prompt = system_prompt.unwrap_or(&self.system_prompt);
+        let mut attempt = 0;
+        let text = loop {
+            match self.attempt(system_prompt, &messages).await {
+                Err(e) if attempt < self.retries => {
+                    attempt += 1;
+                    tracing::warn!(call_sid, attempt, "Retrying LLM call: {e}");
+                }
+                result => break result?,
+            }
+        };
+
+        self.history.record(call_sid, prompt, &text).await;
+        tracing::info!(call_sid, response_len = text.len(), "LLM responded");
 
-        // Append user message
-        session.messages.push(Message {
-            role: Role::User,
-            content: MessageContent::Text(prompt.to_string()),
-        });
-        session.last_used = Instant::now();
+        Ok(text)
+    }
 
-        // Clone what we need before releasing the lock
-        let messages = session.messages.clone();
-        drop(sessions);
+    /// Remove a session (call ended).
+    pub async fn end_session(&self, call_sid: &str) {
+        self.history.end(call_sid).await;
+    }
 
-        tracing::info!(call_sid, provider = self.provider.name(), "Invoking LLM");
+    /// Summarize the call's conversation if it's due
+    /// (`[llm] summarize_after_turns`).
+    pub async fn summarize(&self, call_sid: &str) {
+        let Some((messages, covered)) = self.history.to_summarize(call_sid).await else {
+            return;
+        };
+        let summary = self
+            .attempt(&self.system_prompt, &to_provider(messages))
+            .await;
+        match summary {
+            Ok(ref summary) => tracing::info!(
+                call_sid,
+                covered,
+                len = summary.len(),
+                "Summarized conversation"
+            ),
+            Err(ref e) => tracing::warn!(call_sid, "Conversation not summarized: {e}"),
+        }
+        self.history
+            .summarized(call_sid, covered, summary.ok())
+            .await;
+    }
 
-        let response = self
-            .provider
-            .invoke(
-                &self.system_prompt,
-                &messages,
-                self.max_response_tokens,
-                None, // no tools for voice
-            )
+    async fn attempt(
+        &self,
+        system_prompt: &str,
+        messages: &[Message],
+    ) -> Result<String, ConversationError> {
+        self.chaos
+            .before()
             .await
             .map_err(|e| ConversationError::Provider(e.to_string()))?;
-
-        let text = response.text();
-
-        // Append assistant response to history
-        let mut sessions = self.sessions.lock().await;
-        if let Some(session) = sessions.get_mut(call_sid) {
-            session.messages.push(Message {
-                role: Role::Assistant,
-                content: MessageContent::Text(text.clone()),
-            });
-            session.last_used = Instant::now();
+        let invoke = self.provider.invoke(
+            system_prompt,
+            messages,
+            self.max_response_tokens,
+            None, // no tools for voice
+        );
+        let response = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, invoke)
+                .await
+                .map_err(|_| ConversationError::Timeout(timeout.as_millis() as u64))?,
+            None => invoke.await,
         }
+        .map_err(|e| ConversationError::Provider(e.to_string()))?;
 
-        tracing::info!(call_sid, response_len = text.len(), "LLM responded");
-
+        let text = response.text();
+        self.chaos.drip(text.len()).await;
         Ok(text)
     }
+}
 
-    /// Remove a session (call ended).
-    pub async fn end_session(&self, call_sid: &str) {
-        self.sessions.lock().await.remove(call_sid);
-    }
+/// Messages in the provider's shape.
+fn to_provider(messages: Vec<ChatMessage>) -> Vec<Message> {
+    messages
+        .into_iter()
+        .map(|m| Message {
+            role: match m.role {
+                ChatRole::User => Role::User,
+                ChatRole::Assistant => Role::Assistant,
+            },
+            content: MessageContent::Text(m.content),
+        })
+        .collect()
 }
 
 #[derive(Debug, thiserror::Error)]
 pub enum ConversationError {
     #[error("LLM provider error: {0}")]
     Provider(String),
+    #[error("LLM provider timed out after {0}ms")]
+    Timeout(u64),
 }
@@ -0,0 +1,625 @@
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+use tokio_tungstenite::tungstenite::http::header::InvalidHeaderValue;
+use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+use crate::config::DeepgramConfig;
+use crate::pipeline::language;
+use crate::pipeline::stt::{
+    LiveStt, LiveTranscript, SpeakerRun, SttError, SttFuture, SttProvider, Transcription,
+};
+
+/// Deepgram closes a live session after 10s without audio, which happens
+/// whenever nothing is forwarded while the reply plays.
+const KEEPALIVE: Duration = Duration::from_secs(5);
+
+/// Deepgram transcription (`[stt] provider = "deepgram"`): pre-recorded
+/// utterances, or a live session per call with `[stt] streaming`.
+pub struct DeepgramStt {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    smart_format: bool,
+    endpointing_ms: u64,
+    vocabulary: Vec<String>,
+    diarize: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenResponse {
+    results: ListenResults,
+}
+
+#[derive(Debug, Deserialize)]
+struct ListenResults {
+    #[serde(default)]
+    channels: Vec<Channel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Channel {
+    #[serde(default)]
+    alternatives: Vec<Alternative>,
+    /// Present with `detect_language=true`.
+    #[serde(default)]
+    detected_language: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Alternative {
+    transcript: String,
+    #[serde(default)]
+    confidence: Option<f32>,
+    #[serde(default)]
+    words: Vec<Word>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Word {
+    word: String,
+    /// With `smart_format`.
+    #[serde(default)]
+    punctuated_word: Option<String>,
+    /// From 0, with `diarize=true`.
+    #[serde(default)]
+    speaker: Option<u32>,
+}
+
+/// Add diarized words to `runs`, continuing the last run while its
+/// speaker goes on talking.
+fn extend_runs(runs: &mut Vec<SpeakerRun>, words: &[Word]) {
+    for word in words {
+        let Some(speaker) = word.speaker.map(|s| s + 1) else {
+            continue;
+        };
+        let text = word.punctuated_word.as_ref().unwrap_or(&word.word);
+        match runs.last_mut() {
+            Some(run) if run.speaker == speaker => {
+                run.text.push(' ');
+                run.text.push_str(text);
+            }
+            _ => runs.push(SpeakerRun {
+                speaker,
+                text: text.clone(),
+            }),
+        }
+    }
+}
+
+/// A message on a live session.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type")]
+enum LiveMessage {
+    Results {
+        channel: Channel,
+        /// Seconds of audio the result covers.
+        #[serde(default)]
+        duration: f64,
+        /// Won't be revised.
+        #[serde(default)]
+        is_final: bool,
+        /// Endpointing heard the speaker stop.
+        #[serde(default)]
+        speech_final: bool,
+    },
+    /// No words for `utterance_end_ms`, where endpointing missed the end
+    /// (background noise).
+    UtteranceEnd,
+    #[serde(other)]
+    Other,
+}
+
+/// Live results, stitched into utterances.
+#[derive(Debug, Default)]
+struct Utterance {
+    text: String,
+    seconds: f64,
+    /// The lowest of its parts'.
+    confidence: Option<f32>,
+    speakers: Vec<SpeakerRun>,
+}
+
+impl Utterance {
+    /// Take in a result's best alternative, returning what to pass on.
+    fn result(
+        &mut self,
+        alternative: &Alternative,
+        duration: f64,
+        is_final: bool,
+        speech_final: bool,
+    ) -> Option<LiveTranscript> {
+        let transcript = alternative.transcript.trim();
+        if !is_final {
+            return (!transcript.is_empty())
+                .then(|| LiveTranscript::Interim(self.with(transcript)));
+        }
+        if !transcript.is_empty() {
+            self.text = self.with(transcript);
+            self.seconds += duration;
+            self.confidence = match (self.confidence, alternative.confidence) {
+                (Some(a), Some(b)) => Some(a.min(b)),
+                (a, b) => a.or(b),
+            };
+            extend_runs(&mut self.speakers, &alternative.words);
+        }
+        if speech_final {
+            self.end()
+        } else {
+            None
+        }
+    }
+
+    /// The utterance so far as final, if anything was said.
+    fn end(&mut self) -> Option<LiveTranscript> {
+        let Utterance {
+            text,
+            seconds,
+            confidence,
+            speakers,
+        } = std::mem::take(self);
+        (!text.is_empty()).then_some(LiveTranscript::Final {
+            text,
+            duration_ms: (seconds * 1000.0) as u64,
+            confidence,
+            speakers,
+        })
+    }
+
+    fn with(&self, transcript: &str) -> String {
+        match self.text.is_empty() {
+            true => transcript.to_string(),
+            false => format!("{} {transcript}", self.text),
+        }
+    }
+}
+
+impl DeepgramStt {
+    pub fn new(client: reqwest::Client, api_key: String, config: &DeepgramConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: config.model.clone(),
+            smart_format: config.smart_format,
+            endpointing_ms: config.endpointing_ms,
+            vocabulary: Vec::new(),
+            diarize: false,
+        }
+    }
+
+    /// Tell speakers apart (`[diarization] vendor`). Labels hold for a live
+    /// session; uploaded utterances are each diarized on their own.
+    pub fn with_diarization(mut self, diarize: bool) -> Self {
+        self.diarize = diarize;
+        self
+    }
+
+    /// Boost `terms`: key terms on Nova-3, keywords on older models.
+    pub fn with_vocabulary(mut self, terms: Vec<String>) -> Self {
+        self.vocabulary = terms;
+        self
+    }
+
+    /// The query parameters boosting the vocabulary.
+    fn boosts(&self) -> impl Iterator<Item = (&'static str, String)> + '_ {
+        let param = match self.model.starts_with("nova-3") {
+            true => "keyterm",
+            false => "keywords",
+        };
+        self.vocabulary
+            .iter()
+            .map(move |term| (param, term.clone()))
+    }
+
+    /// Open a live session for Twilio's 8kHz mu-law.
+    async fn listen(&self, language: &str) -> Result<LiveStt, SttError> {
+        let mut query = vec![
+            ("model", self.model.clone()),
+            ("language", language.to_string()),
+            ("encoding", "mulaw".to_string()),
+            ("sample_rate", "8000".to_string()),
+            ("channels", "1".to_string()),
+            ("smart_format", self.smart_format.to_string()),
+            ("interim_results", "true".to_string()),
+            ("endpointing", self.endpointing_ms.to_string()),
+            // The shortest Deepgram allows
+            ("utterance_end_ms", "1000".to_string()),
+            ("diarize", self.diarize.to_string()),
+        ];
+        query.extend(self.boosts());
+        let query =
+            serde_urlencoded::to_string(query).map_err(|e| SttError::Request(e.to_string()))?;
+        // https → wss, http → ws
+        let url = format!(
+            "{}/v1/listen?{query}",
+            self.base_url.replacen("http", "ws", 1)
+        );
+        let mut request = url
+            .into_client_request()
+            .map_err(|e| SttError::Request(e.to_string()))?;
+        let auth = format!("Token {}", self.api_key)
+            .parse()
+            .map_err(|e: InvalidHeaderValue| SttError::Request(e.to_string()))?;
+        request.headers_mut().insert("Authorization", auth);
+
+        let (socket, _) = tokio_tungstenite::connect_async(request)
+            .await
+            .map_err(|e| match e {
+                tokio_tungstenite::tungstenite::Error::Http(resp) => {
+                    SttError::Api(resp.status().to_string())
+                }
+                e => SttError::Request(e.to_string()),
+            })?;
+        tracing::debug!(language, "Deepgram live session opened");
+
+        let (audio_tx, audio_rx) = mpsc::unbounded_channel();
+        let (transcripts_tx, transcripts_rx) = mpsc::unbounded_channel();
+        tokio::spawn(run_live(socket, audio_rx, transcripts_tx));
+        Ok(LiveStt::new(audio_tx, transcripts_rx))
+    }
+
+    async fn request(
+        &self,
+        wav_data: Vec<u8>,
+        hint: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        let mut query = vec![
+            ("model", self.model.clone()),
+            ("smart_format", self.smart_format.to_string()),
+            ("diarize", self.diarize.to_string()),
+        ];
+        match hint {
+            Some(hint) => query.push(("language", hint.to_string())),
+            None => query.push(("detect_language", "true".to_string())),
+        }
+        query.extend(self.boosts());
+
+        let resp = self
+            .client
+            .post(format!("{}/v1/listen", self.base_url))
+            .header("Authorization", format!("Token {}", self.api_key))
+            .header(reqwest::header::CONTENT_TYPE, "audio/wav")
+            .query(&query)
+            .body(wav_data)
+            .send()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(SttError::Api(format!("{status}: {body}")));
+        }
+
+        let result: ListenResponse = resp
+            .json()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+        let channel = result.results.channels.into_iter().next();
+        let language = channel
+            .as_ref()
+            .and_then(|c| c.detected_language.as_deref())
+            .and_then(|l| language::code(&language::of_locale(l)));
+        // No speech comes back as an empty transcript, as with Whisper
+        let best = channel
+            .and_then(|c| c.alternatives.into_iter().next())
+            .unwrap_or_default();
+        let mut speakers = Vec::new();
+        extend_runs(&mut speakers, &best.words);
+        Ok(Transcription {
+            text: best.transcript,
+            language,
+            confidence: best.confidence,
+            speakers,
+        })
+    }
+}
+
+impl SttProvider for DeepgramStt {
+    fn name(&self) -> &'static str {
+        "deepgram"
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        wav_data: Vec<u8>,
+        language: Option<&'a str>,
+        // Deepgram takes no prompt; the vocabulary is its priming
+        _context: Option<&'a str>,
+    ) -> SttFuture<'a, Transcription> {
+        Box::pin(self.request(wav_data, language))
+    }
+
+    /// List the key's projects.
+    fn probe(&self) -> SttFuture<'_, ()> {
+        Box::pin(async {
+            let resp = self
+                .client
+                .get(format!("{}/v1/projects", self.base_url))
+                .header("Authorization", format!("Token {}", self.api_key))
+                .send()
+                .await
+                .map_err(|e| SttError::Request(e.to_string()))?;
+            if !resp.status().is_success() {
+                return Err(SttError::Api(resp.status().to_string()));
+            }
+            Ok(())
+        })
+    }
+
+    fn live<'a>(&'a self, language: &'a str) -> Option<SttFuture<'a, LiveStt>> {
+        Some(Box::pin(self.listen(language)))
+    }
+}
+
+/// Forward audio to a live session and its transcripts back, until either
+/// side is gone.
+async fn run_live(
+    socket: tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+    mut audio: mpsc::UnboundedReceiver<Vec<u8>>,
+    transcripts: mpsc::UnboundedSender<LiveTranscript>,
+) {
+    let (mut sink, mut stream) = socket.split();
+    let mut keepalive = tokio::time::interval(KEEPALIVE);
+    let mut utterance = Utterance::default();
+
+    loop {
+        tokio::select! {
+            mulaw = audio.recv() => {
+                let Some(mulaw) = mulaw else {
+                    // Session dropped with the call
+                    let close = r#"{"type":"CloseStream"}"#;
+                    let _ = sink.send(WsMessage::Text(close.into())).await;
+                    break;
+                };
+                if let Err(e) = sink.send(WsMessage::Binary(mulaw.into())).await {
+                    tracing::warn!("Deepgram live session send failed: {e}");
+                    break;
+                }
+            }
+            _ = keepalive.tick() => {
+                let ping = r#"{"type":"KeepAlive"}"#;
+                if sink.send(WsMessage::Text(ping.into())).await.is_err() {
+                    break;
+                }
+            }
+            frame = stream.next() => {
+                let text = match frame {
+                    Some(Ok(WsMessage::Text(text))) => text,
+                    Some(Ok(WsMessage::Close(_))) | None => break,
+                    Some(Err(e)) => {
+                        tracing::warn!("Deepgram live session error: {e}");
+                        break;
+                    }
+                    Some(Ok(_)) => continue,
+                };
+                let heard = match serde_json::from_str(&text) {
+                    Ok(LiveMessage::Results { channel, duration, is_final, speech_final }) => {
+                        let best = channel.alternatives.into_iter().next().unwrap_or_default();
+                        utterance.result(&best, duration, is_final, speech_final)
+                    }
+                    Ok(LiveMessage::UtteranceEnd) => utterance.end(),
+                    Ok(LiveMessage::Other) => None,
+                    Err(e) => {
+                        tracing::warn!("Unparseable Deepgram live message: {e}");
+                        None
+                    }
+                };
+                if let Some(heard) = heard {
+                    if transcripts.send(heard).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    }
+    tracing::debug!("Deepgram live session closed");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use axum::extract::ws::{Message, WebSocketUpgrade};
+    use axum::extract::Query;
+    use axum::http::HeaderMap;
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    /// A Deepgram API echoing the query back, with the body length as the
+    /// transcript. Live sessions hear "hello there" in two parts for any
+    /// mu-law frame.
+    async fn server() -> String {
+        let live = get(
+            |Query(query): Query<HashMap<String, String>>, ws: WebSocketUpgrade| async move {
+                assert_eq!(query["encoding"], "mulaw");
+                assert_eq!(query["language"], "en");
+                ws.on_upgrade(|mut socket| async move {
+                    while let Some(Ok(message)) = socket.recv().await {
+                        if !matches!(message, Message::Binary(_)) {
+                            continue;
+                        }
+                        let results = [
+                            json!({"transcript": "hello", "is_final": false}),
+                            json!({"transcript": "hello", "is_final": true, "duration": 0.5,
+                                   "confidence": 0.5}),
+                            json!({"transcript": "there", "is_final": true, "duration": 0.75,
+                                   "speech_final": true, "confidence": 0.75}),
+                        ];
+                        for result in results {
+                            let mut message = result.clone();
+                            message["type"] = json!("Results");
+                            message["channel"] = json!({"alternatives": [{
+                                "transcript": result["transcript"],
+                                "confidence": result["confidence"],
+                            }]});
+                            let text = message.to_string();
+                            socket.send(Message::Text(text.into())).await.unwrap();
+                        }
+                    }
+                })
+            },
+        );
+        let app = Router::new().route("/v1/listen", live).route(
+            "/v1/listen",
+            post(
+                |Query(query): Query<HashMap<String, String>>,
+                 headers: HeaderMap,
+                 body: axum::body::Bytes| async move {
+                    assert_eq!(headers["authorization"], "Token dg-test");
+                    assert_eq!(headers["content-type"], "audio/wav");
+                    let mut channel = json!({
+                        "alternatives": [{
+                            "transcript": format!("{} bytes", body.len()),
+                            "confidence": 0.98
+                        }]
+                    });
+                    if query.get("detect_language").is_some_and(|v| v == "true") {
+                        channel["detected_language"] = json!("es");
+                    }
+                    Json(json!({
+                        "metadata": { "query": query },
+                        "results": { "channels": [channel] }
+                    })) as Json<Value>
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn transcribes_and_detects_language() {
+        let url = server().await;
+        let config: DeepgramConfig = toml::from_str(&format!("base_url = {url:?}")).unwrap();
+        let deepgram = DeepgramStt::new(reqwest::Client::new(), "dg-test".into(), &config);
+
+        let hinted = deepgram
+            .transcribe(vec![0; 44], Some("en"), None)
+            .await
+            .unwrap();
+        assert_eq!(hinted.text, "44 bytes");
+        assert_eq!(hinted.language, None);
+        assert_eq!(hinted.confidence, Some(0.98));
+
+        let detected = deepgram.transcribe(vec![0; 10], None, None).await.unwrap();
+        assert_eq!(detected.language.as_deref(), Some("es"));
+    }
+
+    #[tokio::test]
+    async fn live_sessions_stitch_utterances() {
+        let url = server().await;
+        let config: DeepgramConfig = toml::from_str(&format!("base_url = {url:?}")).unwrap();
+        let deepgram = DeepgramStt::new(reqwest::Client::new(), "dg-test".into(), &config);
+
+        let mut live = deepgram.live("en").unwrap().await.unwrap();
+        assert!(live.send(vec![0xff; 160]));
+        assert_eq!(
+            live.recv().await,
+            Some(LiveTranscript::Interim("hello".into()))
+        );
+        assert_eq!(
+            live.recv().await,
+            Some(LiveTranscript::Final {
+                text: "hello there".into(),
+                duration_ms: 1250,
+                confidence: Some(0.5),
+                speakers: Vec::new(),
+            })
+        );
+    }
+
+    #[test]
+    fn boosts_key_terms_on_nova_3() {
+        let terms = vec!["Kubernetes".to_string(), "Grafana".to_string()];
+        let deepgram = |model: &str| {
+            let config: DeepgramConfig = toml::from_str(&format!("model = {model:?}")).unwrap();
+            DeepgramStt::new(reqwest::Client::new(), "dg-test".into(), &config)
+                .with_vocabulary(terms.clone())
+        };
+        let boosts: Vec<_> = deepgram("nova-3").boosts().collect();
+        assert_eq!(
+            boosts,
+            [
+                ("keyterm", "Kubernetes".to_string()),
+                ("keyterm", "Grafana".to_string())
+            ]
+        );
+        assert_eq!(
+            deepgram("nova-2-phonecall").boosts().next().unwrap().0,
+            "keywords"
+        );
+    }
+
+    #[test]
+    fn utterance_end_flushes_what_endpointing_missed() {
+        let said = |transcript: &str| Alternative {
+            transcript: transcript.into(),
+            ..Default::default()
+        };
+        let mut utterance = Utterance::default();
+        assert_eq!(utterance.result(&said(""), 1.0, true, true), None);
+        assert_eq!(utterance.result(&said("order"), 0.5, true, false), None);
+        assert_eq!(
+            utterance.result(&said("forty"), 0.2, false, false),
+            Some(LiveTranscript::Interim("order forty".into()))
+        );
+        assert_eq!(
+            utterance.end(),
+            Some(LiveTranscript::Final {
+                text: "order".into(),
+                duration_ms: 500,
+                confidence: None,
+                speakers: Vec::new(),
+            })
+        );
+        assert_eq!(utterance.end(), None);
+    }
+
+    #[test]
+    fn diarized_words_run_on_across_results() {
+        let said = |words: Value| -> Alternative {
+            serde_json::from_value(json!({"transcript": "-", "words": words})).unwrap()
+        };
+        let mut utterance = Utterance::default();
+        let first = said(json!([
+            {"word": "which", "punctuated_word": "Which", "speaker": 0},
+            {"word": "one", "punctuated_word": "one?", "speaker": 0},
+            {"word": "the", "punctuated_word": "The", "speaker": 1},
+        ]));
+        let second = said(json!([
+            {"word": "blue", "punctuated_word": "blue", "speaker": 1},
+            {"word": "one", "punctuated_word": "one.", "speaker": 1},
+        ]));
+        utterance.result(&first, 1.0, true, false);
+        let Some(LiveTranscript::Final { speakers, .. }) =
+            utterance.result(&second, 1.0, true, true)
+        else {
+            panic!("no final transcript");
+        };
+        assert_eq!(
+            speakers,
+            [
+                SpeakerRun {
+                    speaker: 1,
+                    text: "Which one?".into()
+                },
+                SpeakerRun {
+                    speaker: 2,
+                    text: "The blue one.".into()
+                },
+            ]
+        );
+    }
+}
@@ -0,0 +1,118 @@
+//! Degraded mode for phone calls.
+//!
+//! When STT or every brain is unreachable (breaker open), there's no point
+//! running turns only to speak the generic error after each utterance. The
+//! call instead hears a canned message once and everything the caller says
+//! afterwards is captured as a voicemail, saved when the call ends. Once
+//! the providers are back (their breakers let requests through again), the
+//! caller's next utterance leaves degraded mode and gets a normal turn.
+
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use crate::health;
+use crate::pipeline::audio;
+use crate::AppState;
+
+/// Samples per second of VAD utterance PCM.
+const SAMPLE_RATE: usize = 8000;
+
+/// True when a turn can't succeed: STT is down (with any fallback vendor),
+/// or the primary brain and any fallback are.
+pub fn providers_down(state: &AppState) -> bool {
+    state.stt.down() || health::brain_down(state, None)
+}
+
+/// Degraded-mode state for one call.
+pub struct DegradedCall {
+    active: AtomicBool,
+    voicemail: Mutex<Vec<i16>>,
+    max_samples: usize,
+}
+
+impl DegradedCall {
+    pub fn new(max_voicemail_secs: u64) -> Self {
+        Self {
+            active: AtomicBool::new(false),
+            voicemail: Mutex::new(Vec::new()),
+            max_samples: max_voicemail_secs as usize * SAMPLE_RATE,
+        }
+    }
+
+    /// Whether the call has switched to degraded mode.
+    pub fn is_active(&self) -> bool {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Switch the call to degraded mode. Returns true only the first time,
+    /// so the canned message is spoken once.
+    pub fn activate(&self) -> bool {
+        !self.active.swap(true, Ordering::Relaxed)
+    }
+
+    /// Leave degraded mode. Returns true if the call was in it.
+    pub fn deactivate(&self) -> bool {
+        self.active.swap(false, Ordering::Relaxed)
+    }
+
+    /// Append an utterance to the voicemail, up to the configured length.
+    pub fn record(&self, pcm: &[i16]) {
+        let mut voicemail = self.voicemail.lock().unwrap();
+        let room = self.max_samples.saturating_sub(voicemail.len());
+        voicemail.extend_from_slice(&pcm[..pcm.len().min(room)]);
+    }
+
+    /// Write the voicemail as `<dir>/<call_sid>.wav`. Returns `None` if
+    /// nothing was recorded.
+    pub async fn save(
+        &self,
+        dir: &Path,
+        call_sid: &str,
+    ) -> Result<Option<PathBuf>, Box<dyn std::error::Error + Send + Sync>> {
+        let wav = {
+            let voicemail = self.voicemail.lock().unwrap();
+            if voicemail.is_empty() {
+                return Ok(None);
+            }
+            audio::pcm_to_wav(&voicemail)
+        };
+        tokio::fs::create_dir_all(dir).await?;
+        let path = dir.join(format!("{call_sid}.wav"));
+        tokio::fs::write(&path, wav).await?;
+        Ok(Some(path))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn activates_once() {
+        let call = DegradedCall::new(10);
+        assert!(!call.is_active());
+        assert!(call.activate());
+        assert!(!call.activate());
+        assert!(call.is_active());
+    }
+
+    #[test]
+    fn leaves_and_reenters() {
+        let call = DegradedCall::new(10);
+        assert!(!call.deactivate());
+        call.activate();
+        assert!(call.deactivate());
+        assert!(!call.is_active());
+        // Going down again speaks the message again
+        assert!(call.activate());
+    }
+
+    #[test]
+    fn voicemail_is_capped() {
+        let call = DegradedCall::new(1);
+        call.record(&[1; 6000]);
+        call.record(&[2; 6000]);
+        assert_eq!(call.voicemail.lock().unwrap().len(), SAMPLE_RATE);
+    }
+}
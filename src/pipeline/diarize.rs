@@ -0,0 +1,289 @@
+//! Speaker diarization for speakerphone calls (`[diarization]`).
+//!
+//! Each utterance gets a voiceprint: its long-term average spectrum in
+//! mel-spaced bands, with the overall level taken out so someone leaning
+//! into the phone still matches themselves. Voiceprints are clustered per
+//! call as they arrive: an utterance joins the closest speaker if it's
+//! similar enough, otherwise it starts a new one. Crude next to a neural
+//! embedding, but cheap enough for every turn and good enough to tell a
+//! few people around one phone apart. Labels only start once a second
+//! voice is heard; a call with one person on it stays unlabelled.
+
+use std::f32::consts::PI;
+use std::sync::{Arc, LazyLock};
+
+use rustfft::num_complex::Complex;
+use rustfft::{Fft, FftPlanner};
+
+use crate::pipeline::stt::SpeakerRun;
+
+const SAMPLE_RATE: f32 = 8000.0;
+/// Samples per analysis frame (32ms).
+const FRAME: usize = 256;
+const BANDS: usize = 16;
+const LOW_HZ: f32 = 100.0;
+const HIGH_HZ: f32 = 3800.0;
+/// Frames this far (dB) below the utterance's loudest are pauses, not speech.
+const VOICED_RANGE_DB: f32 = 30.0;
+
+static FFT: LazyLock<Arc<dyn Fft<f32>>> =
+    LazyLock::new(|| FftPlanner::new().plan_fft_forward(FRAME));
+
+/// Added to every prompt with a speaker label (from the second voice on),
+/// so the brain knows what the labels mean.
+pub const INSTRUCTION: &str = "Several people may be speaking on this call. Each \
+     utterance is labelled with its speaker (Speaker 1, Speaker 2, ...); keep track of \
+     who asked what and address people by speaker when it helps.";
+
+/// The spectral shape of one utterance: unit length, level-independent.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Voiceprint([f32; BANDS]);
+
+impl Voiceprint {
+    /// Voiceprint of 8kHz PCM, or `None` if there's no speech to measure.
+    pub fn of(pcm: &[i16]) -> Option<Self> {
+        let window: Vec<f32> = (0..FRAME)
+            .map(|n| 0.5 - 0.5 * (2.0 * PI * n as f32 / FRAME as f32).cos())
+            .collect();
+        let edges = band_edges();
+
+        let mut frames = Vec::new();
+        let mut spectrum = vec![Complex::default(); FRAME];
+        for chunk in pcm.chunks_exact(FRAME) {
+            for ((bin, &s), w) in spectrum.iter_mut().zip(chunk).zip(&window) {
+                *bin = Complex::new(s as f32 * w, 0.0);
+            }
+            FFT.process(&mut spectrum);
+            let mut bands = [0.0f32; BANDS];
+            for (band, power) in bands.iter_mut().enumerate() {
+                *power = spectrum[edges[band]..edges[band + 1]]
+                    .iter()
+                    .map(|c| c.norm_sqr())
+                    .sum();
+            }
+            frames.push(bands);
+        }
+
+        let energy = |bands: &[f32; BANDS]| bands.iter().sum::<f32>();
+        let loudest = frames.iter().map(energy).fold(0.0, f32::max);
+        if loudest <= 0.0 {
+            return None;
+        }
+        let gate = loudest * 10f32.powf(-VOICED_RANGE_DB / 10.0);
+        let voiced: Vec<_> = frames.iter().filter(|f| energy(f) >= gate).collect();
+
+        let mut shape = [0.0f32; BANDS];
+        for bands in &voiced {
+            for (level, power) in shape.iter_mut().zip(bands.iter()) {
+                *level += (power + 1.0).ln() / voiced.len() as f32;
+            }
+        }
+        // Taking out the mean level leaves the shape
+        let mean = shape.iter().sum::<f32>() / BANDS as f32;
+        for level in &mut shape {
+            *level -= mean;
+        }
+        Self::normalized(shape)
+    }
+
+    /// Cosine similarity: 1 for the same spectral shape, lower the more the
+    /// voices differ.
+    pub fn similarity(&self, other: &Voiceprint) -> f32 {
+        self.0.iter().zip(&other.0).map(|(a, b)| a * b).sum()
+    }
+
+    fn normalized(mut v: [f32; BANDS]) -> Option<Self> {
+        let norm = v.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm <= f32::EPSILON {
+            return None;
+        }
+        for x in &mut v {
+            *x /= norm;
+        }
+        Some(Self(v))
+    }
+}
+
+/// FFT bin bounds of each band, mel-spaced between `LOW_HZ` and `HIGH_HZ`.
+fn band_edges() -> [usize; BANDS + 1] {
+    let mel = |hz: f32| 2595.0 * (1.0 + hz / 700.0).log10();
+    let hz = |mel: f32| 700.0 * (10f32.powf(mel / 2595.0) - 1.0);
+    let (low, high) = (mel(LOW_HZ), mel(HIGH_HZ));
+    let bin_hz = SAMPLE_RATE / FRAME as f32;
+    let mut edges = [0; BANDS + 1];
+    for (i, edge) in edges.iter_mut().enumerate() {
+        let mel = low + (high - low) * i as f32 / BANDS as f32;
+        *edge = (hz(mel) / bin_hz).round() as usize;
+    }
+    edges
+}
+
+/// The speakers heard so far on one call.
+#[derive(Debug, Clone, Default)]
+pub struct Speakers {
+    voices: Vec<Speaker>,
+}
+
+#[derive(Debug, Clone)]
+struct Speaker {
+    /// Sum of the speaker's voiceprints; its direction is their voice.
+    sum: [f32; BANDS],
+}
+
+impl Speaker {
+    fn voiceprint(&self) -> Option<Voiceprint> {
+        Voiceprint::normalized(self.sum)
+    }
+}
+
+impl Speakers {
+    /// The speaker (numbered from 1) `voice` belongs to: the most similar
+    /// one if it reaches `threshold`. With `learn`, the match's voice is
+    /// refined and a voice unlike any so far becomes a new speaker, up to
+    /// `max`; past that, or without `learn`, the closest speaker is used.
+    /// `None` only before anyone has been heard.
+    pub fn identify(
+        &mut self,
+        voice: &Voiceprint,
+        threshold: f32,
+        max: usize,
+        learn: bool,
+    ) -> Option<u32> {
+        let closest = self
+            .voices
+            .iter()
+            .enumerate()
+            .filter_map(|(i, s)| Some((i, s.voiceprint()?.similarity(voice))))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+        let index = match closest {
+            Some((i, similarity)) if similarity >= threshold => i,
+            _ if learn && self.voices.len() < max => {
+                self.voices.push(Speaker { sum: [0.0; BANDS] });
+                self.voices.len() - 1
+            }
+            Some((i, _)) => i,
+            None => return None,
+        };
+        if learn {
+            for (sum, x) in self.voices[index].sum.iter_mut().zip(&voice.0) {
+                *sum += x;
+            }
+        }
+        Some(index as u32 + 1)
+    }
+
+    /// How many speakers have been heard.
+    pub fn len(&self) -> usize {
+        self.voices.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.voices.is_empty()
+    }
+}
+
+/// `transcript` as the brain sees it, labelled with its speaker.
+pub fn label(speaker: u32, transcript: &str) -> String {
+    format!("Speaker {speaker}: {transcript}")
+}
+
+/// An utterance the STT vendor heard several people in, each part labelled
+/// with its speaker. `None` when one person said it all.
+pub fn label_runs(runs: &[SpeakerRun]) -> Option<String> {
+    let first = runs.first()?;
+    if runs.iter().all(|run| run.speaker == first.speaker) {
+        return None;
+    }
+    let parts: Vec<String> = runs
+        .iter()
+        .map(|run| label(run.speaker, &run.text))
+        .collect();
+    Some(parts.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A second of a vowel-like voice: harmonics of `f0` shaped by two
+    /// formant resonances, then half a second of silence.
+    fn voice(f0: f32, formants: (f32, f32), amplitude: f32) -> Vec<i16> {
+        let resonance = |f: f32, center: f32| 1.0 / (1.0 + ((f - center) / 150.0).powi(2));
+        let harmonics: Vec<(f32, f32)> = (1..)
+            .map(|h| h as f32 * f0)
+            .take_while(|&f| f < 3900.0)
+            .map(|f| (f, resonance(f, formants.0) + resonance(f, formants.1)))
+            .collect();
+        let peak: f32 = harmonics.iter().map(|h| h.1).sum();
+        let mut pcm: Vec<i16> = (0..8000)
+            .map(|n| {
+                let t = n as f32 / SAMPLE_RATE;
+                let s: f32 = harmonics
+                    .iter()
+                    .map(|(f, gain)| gain * (2.0 * PI * f * t).sin())
+                    .sum();
+                (amplitude * s / peak) as i16
+            })
+            .collect();
+        pcm.extend(std::iter::repeat(0).take(4000));
+        pcm
+    }
+
+    #[test]
+    fn same_voice_matches_at_any_level() {
+        let quiet = Voiceprint::of(&voice(120.0, (600.0, 1200.0), 2000.0)).unwrap();
+        let loud = Voiceprint::of(&voice(124.0, (600.0, 1200.0), 12000.0)).unwrap();
+        let other = Voiceprint::of(&voice(220.0, (400.0, 2600.0), 6000.0)).unwrap();
+        assert!(quiet.similarity(&loud) > 0.9, "{}", quiet.similarity(&loud));
+        assert!(
+            quiet.similarity(&other) < 0.7,
+            "{}",
+            quiet.similarity(&other)
+        );
+        assert_eq!(Voiceprint::of(&[0; 4000]), None);
+    }
+
+    #[test]
+    fn labels_speakers_in_order_of_appearance() {
+        let alice = Voiceprint::of(&voice(120.0, (600.0, 1200.0), 6000.0)).unwrap();
+        let bob = Voiceprint::of(&voice(220.0, (400.0, 2600.0), 6000.0)).unwrap();
+        let alice_again = Voiceprint::of(&voice(118.0, (600.0, 1200.0), 3000.0)).unwrap();
+
+        let mut speakers = Speakers::default();
+        assert_eq!(speakers.identify(&alice, 0.85, 4, false), None);
+        assert_eq!(speakers.identify(&alice, 0.85, 4, true), Some(1));
+        assert_eq!(speakers.identify(&bob, 0.85, 4, true), Some(2));
+        assert_eq!(speakers.identify(&alice_again, 0.85, 4, true), Some(1));
+        assert_eq!(speakers.len(), 2);
+    }
+
+    #[test]
+    fn labels_each_speaker_within_an_utterance() {
+        let run = |speaker, text: &str| SpeakerRun {
+            speaker,
+            text: text.into(),
+        };
+        assert_eq!(label_runs(&[]), None);
+        assert_eq!(label_runs(&[run(1, "Hi."), run(1, "It's me.")]), None);
+        assert_eq!(
+            label_runs(&[run(1, "Which one?"), run(2, "The blue one.")]).as_deref(),
+            Some("Speaker 1: Which one?\nSpeaker 2: The blue one.")
+        );
+    }
+
+    #[test]
+    fn short_utterances_and_full_calls_reuse_the_closest_speaker() {
+        let alice = Voiceprint::of(&voice(120.0, (600.0, 1200.0), 6000.0)).unwrap();
+        let bob = Voiceprint::of(&voice(220.0, (400.0, 2600.0), 6000.0)).unwrap();
+
+        let mut speakers = Speakers::default();
+        speakers.identify(&alice, 0.85, 1, true);
+        assert_eq!(speakers.identify(&bob, 0.85, 1, true), Some(1));
+        assert_eq!(speakers.len(), 1);
+
+        let mut speakers = Speakers::default();
+        speakers.identify(&alice, 0.85, 4, true);
+        assert_eq!(speakers.identify(&bob, 0.85, 4, false), Some(1));
+        assert_eq!(speakers.len(), 1);
+    }
+}
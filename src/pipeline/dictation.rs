@@ -0,0 +1,154 @@
+//! Dictated values in caller transcripts (`[stt] dictation`).
+//!
+//! STT writes down what it hears: a phone number read digit by digit comes
+//! back as "four one five, five five five", an email address as "ana dot
+//! lopez at gmail dot com". Before the brain sees a transcript, runs of
+//! spoken digits become digits and spoken addresses are written as
+//! addresses. English only.
+
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use regex::{Captures, Regex};
+
+const DIGIT: &str = r"(?:zero|oh|one|two|three|four|five|six|seven|eight|nine|\d+)";
+
+/// Two or more digits, spoken or not, with "double" and "triple" and
+/// commas or dashes between them, after an optional "plus".
+static DIGIT_RUN: LazyLock<Regex> = LazyLock::new(|| {
+    let item = format!(r"(?:(?:double|triple)\s+)?{DIGIT}");
+    Regex::new(&format!(
+        r"(?i)\b(?:(plus)\s+)?{item}(?:(?:\s*[,-]\s*|\s+){item})+\b"
+    ))
+    .unwrap()
+});
+
+static ITEM: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(&format!(r"(?i)(?:(double|triple)\s+)?({DIGIT})")).unwrap());
+
+/// "<local> at <domain> dot <tld>", with "dot", "underscore" and "dash"
+/// spoken or written. The domain has to end in a common TLD, so "meet me
+/// at the station" is left alone.
+static EMAIL: LazyLock<Regex> = LazyLock::new(|| {
+    let word = r"[a-z0-9][a-z0-9._-]*";
+    let sep = r"(?:\s+(?:dot|underscore|dash|hyphen)\s+|[._-])";
+    let tld = "com|net|org|edu|gov|io|co|uk|us|ca|au|de|fr|es|it|nl|ie|info|biz|me|ai|app|dev";
+    Regex::new(&format!(
+        r"(?i)\b{word}(?:{sep}{word})*\s+at\s+{word}(?:{sep}{word})*?(?:\s+dot\s+|\.)(?:{tld})\b"
+    ))
+    .unwrap()
+});
+
+/// Fewer digits than this are more likely counted than dictated ("one or
+/// two", "five six people").
+const MIN_DIGITS: usize = 3;
+
+/// Rewrite spoken digit runs and email addresses in `text`.
+pub fn apply(text: &str) -> Cow<'_, str> {
+    let mut text = DIGIT_RUN.replace_all(text, digits);
+    let rewritten = EMAIL.replace_all(&text, email);
+    if let Cow::Owned(rewritten) = rewritten {
+        text = Cow::Owned(rewritten);
+    }
+    text
+}
+
+/// A digit run as digits, if enough of it was spoken to be dictation.
+fn digits(caps: &Captures) -> String {
+    let run = &caps[0];
+    let mut out = String::new();
+    let mut spoken = false;
+    for item in ITEM.captures_iter(run) {
+        let word = item[2].to_ascii_lowercase();
+        let digit = match word.as_str() {
+            "zero" | "oh" => "0",
+            "one" => "1",
+            "two" => "2",
+            "three" => "3",
+            "four" => "4",
+            "five" => "5",
+            "six" => "6",
+            "seven" => "7",
+            "eight" => "8",
+            "nine" => "9",
+            written => {
+                out.push_str(written);
+                continue;
+            }
+        };
+        spoken = true;
+        let times = match item.get(1).map(|m| m.as_str().to_ascii_lowercase()) {
+            Some(ref m) if m == "double" => 2,
+            Some(ref m) if m == "triple" => 3,
+            _ => 1,
+        };
+        out.push_str(&digit.repeat(times));
+    }
+    if !spoken || out.len() < MIN_DIGITS {
+        return run.to_string();
+    }
+    match caps.get(1) {
+        Some(_) => format!("+{out}"),
+        None => out,
+    }
+}
+
+/// An email address as written: lowercase, no spaces.
+fn email(caps: &Captures) -> String {
+    caps[0]
+        .split_whitespace()
+        .map(|word| match word.to_ascii_lowercase().as_str() {
+            "at" => "@".to_string(),
+            "dot" => ".".to_string(),
+            "underscore" => "_".to_string(),
+            "dash" | "hyphen" => "-".to_string(),
+            word => word.to_string(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn writes_dictated_numbers_as_digits() {
+        assert_eq!(
+            apply("My number is four one five, five five five, one two one two."),
+            "My number is 4155551212."
+        );
+        assert_eq!(
+            apply("It's plus four four seven double seven oh nine"),
+            "It's +4477709"
+        );
+        assert_eq!(apply("Code nine-one-one"), "Code 911");
+        assert_eq!(apply("Extension 2 oh 4"), "Extension 204");
+    }
+
+    #[test]
+    fn leaves_counting_and_written_numbers_alone() {
+        for text in [
+            "I'd like one or two",
+            "Oh, one more thing",
+            "Call 415 555 1212",
+            "Someone told me",
+            "Table for five at nine",
+        ] {
+            assert_eq!(apply(text), text);
+        }
+    }
+
+    #[test]
+    fn writes_spoken_emails_as_addresses() {
+        assert_eq!(
+            apply("Send it to Ana dot Lopez at Gmail dot com please"),
+            "Send it to ana.lopez@gmail.com please"
+        );
+        assert_eq!(
+            apply("it's j underscore smith at example.co dot uk."),
+            "it's j_smith@example.co.uk."
+        );
+        assert_eq!(apply("Meet me at the station"), "Meet me at the station");
+        assert_eq!(apply("ana at gmail"), "ana at gmail");
+    }
+}
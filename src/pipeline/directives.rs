@@ -0,0 +1,135 @@
+//! Control tokens in brain responses.
+//!
+//! The brain can embed `[VOICE:<voice_id>]` to switch the TTS voice from that
+//! point on — to quote someone, or to change language with a voice that
+//! speaks it. The switch sticks for the rest of the call;
+//! `[VOICE:default]` returns to the call's own voice. `[DTMF:<digits>]`
+//! presses keys once the reply has played, to get through phone menus on
+//! outbound calls. Directives are never spoken.
+
+use crate::twilio::outbound::is_dtmf;
+
+const VOICE_OPEN: &str = "[VOICE:";
+const DTMF_OPEN: &str = "[DTMF:";
+
+/// A piece of a response, in order.
+#[derive(Debug, PartialEq, Eq)]
+pub enum Segment<'a> {
+    /// Text to speak in the current voice.
+    Text(&'a str),
+    /// Switch voice. `None` means back to the call's own voice.
+    Voice(Option<&'a str>),
+    /// Press keys.
+    Dtmf(&'a str),
+}
+
+/// Split a response into text and directives. Malformed directives (no
+/// closing bracket, empty or odd voice id, non-keypad digits) are left in
+/// the text.
+pub fn parse(response: &str) -> Vec<Segment<'_>> {
+    let mut segments = Vec::new();
+    // Start of pending text, and where to search for the next directive
+    let mut text_start = 0;
+    let mut cursor = 0;
+
+    while let Some((found, tag)) = next_directive(&response[cursor..]) {
+        let open = cursor + found;
+        let value_start = open + tag.len();
+        cursor = value_start;
+        let Some(len) = response[value_start..].find(']') else {
+            break;
+        };
+        let value = response[value_start..value_start + len].trim();
+        let segment = match tag {
+            VOICE_OPEN if is_voice_id(value) => Segment::Voice(match value {
+                "default" => None,
+                id => Some(id),
+            }),
+            DTMF_OPEN if is_dtmf(value) => Segment::Dtmf(value),
+            _ => continue,
+        };
+
+        push_text(&mut segments, &response[text_start..open]);
+        segments.push(segment);
+        cursor = value_start + len + 1;
+        text_start = cursor;
+    }
+    push_text(&mut segments, &response[text_start..]);
+    segments
+}
+
+/// Offset and opening tag of the first directive in `text`.
+fn next_directive(text: &str) -> Option<(usize, &'static str)> {
+    [VOICE_OPEN, DTMF_OPEN]
+        .into_iter()
+        .filter_map(|tag| text.find(tag).map(|at| (at, tag)))
+        .min_by_key(|(at, _)| *at)
+}
+
+fn push_text<'a>(segments: &mut Vec<Segment<'a>>, text: &'a str) {
+    let text = text.trim();
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+}
+
+fn is_voice_id(id: &str) -> bool {
+    !id.is_empty()
+        && id
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn splits_on_voice_directives() {
+        assert_eq!(
+            parse("He said [VOICE:Dennis] I'm out of coffee [VOICE:default] anyway."),
+            vec![
+                Segment::Text("He said"),
+                Segment::Voice(Some("Dennis")),
+                Segment::Text("I'm out of coffee"),
+                Segment::Voice(None),
+                Segment::Text("anyway."),
+            ]
+        );
+        assert_eq!(
+            parse("[VOICE:Hana]Hola"),
+            vec![Segment::Voice(Some("Hana")), Segment::Text("Hola")]
+        );
+    }
+
+    #[test]
+    fn splits_on_dtmf_directives() {
+        assert_eq!(
+            parse("Navigating [DTMF:2] menu [VOICE:Ok][DTMF:1w#]"),
+            vec![
+                Segment::Text("Navigating"),
+                Segment::Dtmf("2"),
+                Segment::Text("menu"),
+                Segment::Voice(Some("Ok")),
+                Segment::Dtmf("1w#"),
+            ]
+        );
+        assert_eq!(parse("[DTMF:2x]"), vec![Segment::Text("[DTMF:2x]")]);
+    }
+
+    #[test]
+    fn leaves_malformed_directives_in_text() {
+        assert_eq!(
+            parse("a [VOICE:bad id] b [VOICE:Ok] c"),
+            vec![
+                Segment::Text("a [VOICE:bad id] b"),
+                Segment::Voice(Some("Ok")),
+                Segment::Text("c"),
+            ]
+        );
+        assert_eq!(
+            parse("trailing [VOICE:"),
+            vec![Segment::Text("trailing [VOICE:")]
+        );
+    }
+}
@@ -0,0 +1,155 @@
+use crate::config::ElevenLabsConfig;
+use crate::pipeline::tts::{TtsError, TtsFuture, TtsProvider};
+
+/// ElevenLabs text-to-speech (`[tts] provider = "elevenlabs"`). Asked for
+/// `ulaw_8000`, it returns audio Twilio plays as is.
+pub struct ElevenLabsTts {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+/// ElevenLabs' per-request character limit on its older models; newer ones
+/// take more.
+const MAX_CHARS: usize = 5000;
+
+impl ElevenLabsTts {
+    pub fn new(client: reqwest::Client, api_key: String, config: &ElevenLabsConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: config.model.clone(),
+        }
+    }
+
+    async fn request(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        let body = serde_json::json!({
+            "text": text,
+            "model_id": &self.model,
+        });
+
+        let resp = self
+            .client
+            .post(format!("{}/v1/text-to-speech/{voice_id}", self.base_url))
+            .header("xi-api-key", &self.api_key)
+            .query(&[("output_format", "ulaw_8000")])
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(TtsError::Api(format!("{status}: {body}")));
+        }
+
+        let audio = resp
+            .bytes()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        Ok(audio.to_vec())
+    }
+
+    /// Credential check: list voices.
+    async fn voices(&self) -> Result<(), TtsError> {
+        let resp = self
+            .client
+            .get(format!("{}/v1/voices", self.base_url))
+            .header("xi-api-key", &self.api_key)
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(TtsError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl TtsProvider for ElevenLabsTts {
+    fn name(&self) -> &'static str {
+        "elevenlabs"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn max_chars(&self) -> usize {
+        MAX_CHARS
+    }
+
+    fn synthesize<'a>(&'a self, text: &'a str, voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+        Box::pin(self.request(text, voice_id))
+    }
+
+    fn probe(&self) -> TtsFuture<'_, ()> {
+        Box::pin(self.voices())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+
+    use axum::extract::{Path, Query};
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::{json, Value};
+
+    /// An ElevenLabs API answering with one mu-law byte per character of
+    /// text, valued the voice's length, for the `el-test` key only.
+    async fn server() -> String {
+        let app = Router::new()
+            .route(
+                "/v1/text-to-speech/{voice}",
+                post(
+                    |Path(voice): Path<String>,
+                     Query(query): Query<HashMap<String, String>>,
+                     headers: HeaderMap,
+                     Json(body): Json<Value>| async move {
+                        assert_eq!(headers["xi-api-key"], "el-test");
+                        assert_eq!(query["output_format"], "ulaw_8000");
+                        assert_eq!(body["model_id"], "eleven_flash_v2_5");
+                        let len = body["text"].as_str().unwrap().len();
+                        vec![voice.len() as u8; len]
+                    },
+                ),
+            )
+            .route(
+                "/v1/voices",
+                get(|headers: HeaderMap| async move {
+                    match headers["xi-api-key"] == "el-test" {
+                        true => Ok(Json(json!({ "voices": [] }))),
+                        false => Err(StatusCode::UNAUTHORIZED),
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    fn config(url: &str) -> ElevenLabsConfig {
+        toml::from_str(&format!("base_url = {url:?}")).unwrap()
+    }
+
+    #[tokio::test]
+    async fn synthesizes_mulaw_in_the_given_voice() {
+        let url = server().await;
+        let tts = ElevenLabsTts::new(reqwest::Client::new(), "el-test".into(), &config(&url));
+
+        let audio = tts.synthesize("Hello", "voice-a").await.unwrap();
+        assert_eq!(audio, vec![7; 5]);
+        tts.probe().await.unwrap();
+
+        let wrong = ElevenLabsTts::new(reqwest::Client::new(), "nope".into(), &config(&url));
+        assert!(matches!(wrong.probe().await, Err(TtsError::Api(_))));
+    }
+}
@@ -0,0 +1,92 @@
+//! Fallback brains.
+//!
+//! When the primary brain errors or blows its budget, the turn is retried
+//! against each fallback in order (`[brain] providers`, e.g. bridge-echo →
+//! local provider, or `[llm.fallback]`) until one answers. The first
+//! fallback answer on a call can be prefixed with a spoken notice so the
+//! caller knows they're talking to a reduced setup.
+
+use std::collections::HashSet;
+use std::sync::Mutex;
+
+use crate::Brain;
+
+pub struct Fallback {
+    brains: Vec<Brain>,
+    notice: String,
+    /// Calls that have already heard the notice.
+    noticed: Mutex<HashSet<String>>,
+}
+
+impl Fallback {
+    pub fn new(brains: Vec<Brain>, notice: String) -> Self {
+        Self {
+            brains,
+            notice,
+            noticed: Mutex::new(HashSet::new()),
+        }
+    }
+
+    /// The fallback brains, in the order they're tried.
+    pub fn brains(&self) -> &[Brain] {
+        &self.brains
+    }
+
+    /// False when every fallback brain is known to be down.
+    pub fn reachable(&self) -> bool {
+        self.brains.iter().any(Brain::reachable)
+    }
+
+    /// The limited-mode notice, returned only the first time it's asked for
+    /// on a call. `None` if already given or no notice is configured.
+    pub fn take_notice(&self, call_sid: &str) -> Option<&str> {
+        if self.notice.is_empty() {
+            return None;
+        }
+        let first = self.noticed.lock().unwrap().insert(call_sid.to_string());
+        first.then_some(self.notice.as_str())
+    }
+
+    /// Forget per-call state (call ended).
+    pub async fn end_session(&self, call_sid: &str) {
+        self.noticed.lock().unwrap().remove(call_sid);
+        for brain in &self.brains {
+            brain.end_session(call_sid).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+    use crate::breaker::CircuitBreaker;
+    use crate::config::BreakerConfig;
+    use crate::pipeline::bridge::BridgeClient;
+
+    fn fallback(notice: &str) -> Fallback {
+        let bridge = BridgeClient::new(
+            reqwest::Client::new(),
+            "http://localhost:1",
+            "User".into(),
+            CircuitBreaker::new("test", &BreakerConfig::default()),
+        );
+        Fallback::new(vec![Brain::Bridge(Arc::new(bridge))], notice.into())
+    }
+
+    #[tokio::test]
+    async fn notice_is_given_once_per_call() {
+        let fb = fallback("Limited mode.");
+        assert_eq!(fb.take_notice("CA1"), Some("Limited mode."));
+        assert_eq!(fb.take_notice("CA1"), None);
+        assert_eq!(fb.take_notice("CA2"), Some("Limited mode."));
+        fb.end_session("CA1").await;
+        assert_eq!(fb.take_notice("CA1"), Some("Limited mode."));
+    }
+
+    #[test]
+    fn empty_notice_is_never_given() {
+        assert_eq!(fallback("").take_notice("CA1"), None);
+    }
+}
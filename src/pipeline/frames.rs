@@ -0,0 +1,263 @@
+//! Outbound media frames for the Twilio and Discord streams.
+//!
+//! Every 20ms of audio (`[audio] frame_ms`) is one JSON text frame, so a
+//! ten-second response is 500 frames. The JSON around the payload never changes within a stream;
+//! it is rendered once and each frame is built into a single pre-sized
+//! buffer with the base64 payload encoded straight into it.
+//!
+//! A discord-voice sidecar can ask at Join for several frames per message
+//! (`batch_frames`). A batched `audio` message carries the frames' bytes
+//! back to back plus `lengths`, one entry per frame, in either direction.
+
+use std::fmt::Write;
+
+use axum::extract::ws::Message;
+use base64::Engine;
+
+/// Default bytes of mu-law per frame: 20ms at 8kHz.
+pub const FRAME_BYTES: usize = 160;
+
+/// Most frames per batched message: one second of default frames.
+pub const MAX_BATCH_FRAMES: usize = 50;
+
+/// Builds media frames for one stream.
+pub struct FrameEncoder {
+    prefix: String,
+    suffix: &'static str,
+    frame_bytes: usize,
+    /// Frames per message; above 1 only for discord-voice.
+    batch: usize,
+}
+
+impl FrameEncoder {
+    /// Twilio `media` events for `stream_sid`.
+    pub fn twilio(stream_sid: &str) -> Self {
+        let sid = serde_json::Value::from(stream_sid);
+        Self {
+            prefix: format!(r#"{{"event":"media","streamSid":{sid},"media":{{"payload":""#),
+            suffix: r#""}}"#,
+            frame_bytes: FRAME_BYTES,
+            batch: 1,
+        }
+    }
+
+    /// discord-voice `audio` messages.
+    pub fn discord() -> Self {
+        Self::discord_batched(1)
+    }
+
+    /// discord-voice `audio` messages carrying up to `frames` frames each,
+    /// as negotiated at Join.
+    pub fn discord_batched(frames: usize) -> Self {
+        Self {
+            prefix: r#"{"type":"audio","audio":""#.to_string(),
+            suffix: r#""}"#,
+            frame_bytes: FRAME_BYTES,
+            batch: frames.clamp(1, MAX_BATCH_FRAMES),
+        }
+    }
+
+    /// Split audio into `bytes`-sized frames instead of `FRAME_BYTES`.
+    pub fn with_frame_bytes(mut self, bytes: usize) -> Self {
+        self.frame_bytes = bytes.max(1);
+        self
+    }
+
+    /// Messages carrying all of `mulaw`: one per frame, or one per batch.
+    pub fn messages<'a>(&'a self, mulaw: &'a [u8]) -> impl Iterator<Item = Message> + 'a {
+        mulaw
+            .chunks(self.frame_bytes * self.batch)
+            .map(move |chunk| match self.batch {
+                1 => self.frame(chunk),
+                _ => self.batch_frame(chunk),
+            })
+    }
+
+    /// One frame carrying `chunk` (at most the frame size for real-time
+    /// pacing, but any length encodes).
+    pub fn frame(&self, chunk: &[u8]) -> Message {
+        let b64_len = chunk.len().div_ceil(3) * 4;
+        let mut json = String::with_capacity(self.prefix.len() + b64_len + self.suffix.len());
+        json.push_str(&self.prefix);
+        base64::engine::general_purpose::STANDARD.encode_string(chunk, &mut json);
+        json.push_str(self.suffix);
+        Message::Text(json.into())
+    }
+
+    /// One batched message carrying `chunk` split into frames.
+    fn batch_frame(&self, chunk: &[u8]) -> Message {
+        let b64_len = chunk.len().div_ceil(3) * 4;
+        let frames = chunk.len().div_ceil(self.frame_bytes);
+        let mut json = String::with_capacity(self.prefix.len() + b64_len + 16 + frames * 4);
+        json.push_str(&self.prefix);
+        base64::engine::general_purpose::STANDARD.encode_string(chunk, &mut json);
+        json.push_str(r#"","lengths":["#);
+        for (i, frame) in chunk.chunks(self.frame_bytes).enumerate() {
+            if i > 0 {
+                json.push(',');
+            }
+            let _ = write!(json, "{}", frame.len());
+        }
+        json.push_str("]}");
+        Message::Text(json.into())
+    }
+
+    /// One frame carrying a payload already base64-encoded by
+    /// `encode_payloads`.
+    pub fn frame_encoded(&self, payload: &str) -> Message {
+        let mut json = String::with_capacity(self.prefix.len() + payload.len() + self.suffix.len());
+        json.push_str(&self.prefix);
+        json.push_str(payload);
+        json.push_str(self.suffix);
+        Message::Text(json.into())
+    }
+}
+
+/// Base64 payloads for `mulaw` split into `frame_bytes` frames, for audio
+/// played over and over (hold music) so it is encoded once rather than
+/// every tick.
+pub fn encode_payloads(mulaw: &[u8], frame_bytes: usize) -> Vec<String> {
+    mulaw
+        .chunks(frame_bytes.max(1))
+        .map(|chunk| base64::engine::general_purpose::STANDARD.encode(chunk))
+        .collect()
+}
+
+/// Split the decoded audio of a batched message back into frames. `None`
+/// if `lengths` doesn't account for exactly every byte. The lengths come
+/// from the sidecar, so their sum is checked rather than trusted.
+pub fn split_batch<'a>(audio: &'a [u8], lengths: &[usize]) -> Option<Vec<&'a [u8]>> {
+    let total = lengths
+        .iter()
+        .try_fold(0usize, |total, &len| total.checked_add(len));
+    if total != Some(audio.len()) {
+        return None;
+    }
+    let mut rest = audio;
+    let frames = lengths
+        .iter()
+        .map(|&len| {
+            let (frame, tail) = rest.split_at(len);
+            rest = tail;
+            frame
+        })
+        .collect();
+    Some(frames)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(message: Message) -> serde_json::Value {
+        match message {
+            Message::Text(text) => serde_json::from_str(text.as_str()).unwrap(),
+            other => panic!("expected a text frame, got {other:?}"),
+        }
+    }
+
+    #[test]
+    fn twilio_frame_matches_media_event() {
+        let frame = FrameEncoder::twilio("MZ\"1").frame(&[0xff, 0x7f, 0x00]);
+        assert_eq!(
+            text(frame),
+            serde_json::json!({
+                "event": "media",
+                "streamSid": "MZ\"1",
+                "media": { "payload": "/38A" }
+            })
+        );
+    }
+
+    #[test]
+    fn pre_encoded_frames_match_encoded_ones() {
+        let audio: Vec<u8> = (0..=255).collect();
+        let frames = FrameEncoder::twilio("MZ1");
+        let payloads = encode_payloads(&audio, FRAME_BYTES);
+        assert_eq!(payloads.len(), 2);
+        for (chunk, payload) in audio.chunks(FRAME_BYTES).zip(&payloads) {
+            assert_eq!(frames.frame_encoded(payload), frames.frame(chunk));
+        }
+    }
+
+    #[test]
+    fn batched_messages_round_trip() {
+        let audio: Vec<u8> = (0..FRAME_BYTES * 5 + 40).map(|i| i as u8).collect();
+        let messages: Vec<_> = FrameEncoder::discord_batched(2).messages(&audio).collect();
+        assert_eq!(messages.len(), 3);
+
+        let mut frames = Vec::new();
+        for message in messages {
+            let json = text(message);
+            let bytes = base64::engine::general_purpose::STANDARD
+                .decode(json["audio"].as_str().unwrap())
+                .unwrap();
+            let lengths: Vec<usize> = serde_json::from_value(json["lengths"].clone()).unwrap();
+            frames.extend(
+                split_batch(&bytes, &lengths)
+                    .unwrap()
+                    .into_iter()
+                    .map(<[u8]>::to_vec),
+            );
+        }
+        let expected: Vec<_> = audio.chunks(FRAME_BYTES).map(<[u8]>::to_vec).collect();
+        assert_eq!(frames, expected);
+    }
+
+    #[test]
+    fn frames_follow_configured_size() {
+        let audio = [0u8; 700];
+        let frames = FrameEncoder::twilio("MZ1").with_frame_bytes(320);
+        let sizes: Vec<_> = frames
+            .messages(&audio)
+            .map(|m| {
+                let json = text(m);
+                let payload = json["media"]["payload"].as_str().unwrap().to_string();
+                base64::engine::general_purpose::STANDARD
+                    .decode(payload)
+                    .unwrap()
+                    .len()
+            })
+            .collect();
+        assert_eq!(sizes, vec![320, 320, 60]);
+    }
+
+    #[test]
+    fn unbatched_discord_messages_are_single_frames() {
+        let audio = [0u8; FRAME_BYTES * 2];
+        let frames = FrameEncoder::discord();
+        let messages: Vec<_> = frames.messages(&audio).collect();
+        assert_eq!(messages, vec![frames.frame(&audio[..FRAME_BYTES]); 2]);
+    }
+
+    #[test]
+    fn split_batch_rejects_mismatched_lengths() {
+        assert_eq!(
+            split_batch(&[1, 2, 3], &[1, 2]),
+            Some(vec![&[1][..], &[2, 3]])
+        );
+        assert_eq!(split_batch(&[1, 2, 3], &[1, 1]), None);
+        assert_eq!(split_batch(&[1, 2], &[1, 2]), None);
+    }
+
+    #[test]
+    fn split_batch_rejects_overflowing_lengths() {
+        // Wraps to 2 when summed unchecked
+        assert_eq!(split_batch(&[1, 2], &[usize::MAX, 3]), None);
+        assert_eq!(split_batch(&[], &[usize::MAX, 1]), None);
+    }
+
+    #[test]
+    fn discord_frame_matches_audio_message() {
+        let frame = FrameEncoder::discord().frame(&[1; FRAME_BYTES]);
+        let json = text(frame);
+        assert_eq!(json["type"], "audio");
+        let payload = json["audio"].as_str().unwrap();
+        assert_eq!(
+            base64::engine::general_purpose::STANDARD
+                .decode(payload)
+                .unwrap(),
+            vec![1; FRAME_BYTES]
+        );
+    }
+}
@@ -0,0 +1,269 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BrainProvider, GeminiConfig};
+use crate::pipeline::chat::{ChatError, ChatFuture, ChatProvider};
+use crate::pipeline::history::{ChatMessage, ChatRole};
+
+/// The Google Gemini API (`[brain] provider = "gemini"`).
+pub struct GeminiChat {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    system_instruction: Option<Content<'a>>,
+    contents: Vec<Content<'a>>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Debug, Serialize)]
+struct Content<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role: Option<&'a str>,
+    parts: [Part<'a>; 1],
+}
+
+#[derive(Debug, Serialize)]
+struct Part<'a> {
+    text: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    max_output_tokens: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Candidate {
+    #[serde(default)]
+    content: Option<CandidateContent>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<CandidatePart>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CandidatePart {
+    #[serde(default)]
+    text: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: ErrorDetail,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorDetail {
+    message: String,
+}
+
+impl GeminiChat {
+    pub fn new(client: reqwest::Client, config: &GeminiConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            timeout: (config.timeout_ms > 0).then(|| Duration::from_millis(config.timeout_ms)),
+        }
+    }
+
+    /// Credential check: look up the model.
+    async fn model_info(&self) -> Result<(), ChatError> {
+        let mut req = self
+            .client
+            .get(format!("{}/v1beta/models/{}", self.base_url, self.model))
+            .header("x-goog-api-key", &self.api_key);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(ChatError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<String, ChatError> {
+        let body = GenerateRequest {
+            system_instruction: (!system_prompt.is_empty()).then_some(Content {
+                role: None,
+                parts: [Part {
+                    text: system_prompt,
+                }],
+            }),
+            contents: messages
+                .iter()
+                .map(|m| Content {
+                    // Gemini calls the assistant "model"
+                    role: Some(match m.role {
+                        ChatRole::User => "user",
+                        ChatRole::Assistant => "model",
+                    }),
+                    parts: [Part { text: &m.content }],
+                })
+                .collect(),
+            generation_config: GenerationConfig {
+                max_output_tokens: max_tokens,
+            },
+        };
+
+        let mut req = self
+            .client
+            .post(format!(
+                "{}/v1beta/models/{}:generateContent",
+                self.base_url, self.model
+            ))
+            .header("x-goog-api-key", &self.api_key)
+            .json(&body);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            let detail =
+                serde_json::from_str::<ErrorResponse>(&body).map_or(body, |e| e.error.message);
+            return Err(ChatError::Api(format!("{status}: {detail}")));
+        }
+
+        let result: GenerateResponse = resp
+            .json()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        let text: String = result
+            .candidates
+            .into_iter()
+            .next()
+            .and_then(|candidate| candidate.content)
+            .map(|content| content.parts.into_iter().filter_map(|p| p.text).collect())
+            .unwrap_or_default();
+        if text.is_empty() {
+            return Err(ChatError::Empty("Gemini"));
+        }
+        Ok(text)
+    }
+}
+
+impl ChatProvider for GeminiChat {
+    fn brain(&self) -> BrainProvider {
+        BrainProvider::Gemini
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String> {
+        Box::pin(self.request(system_prompt, messages, max_tokens))
+    }
+
+    fn probe(&self) -> ChatFuture<'_, ()> {
+        Box::pin(self.model_info())
+    }
+
+    fn hint(&self) -> &'static str {
+        "check gemini.api_key and gemini.model"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::pipeline::chat::fixture::{converse, Fixture};
+
+    fn reply(text: &str) -> (StatusCode, serde_json::Value) {
+        let reply = json!({
+            "candidates": [{
+                "content": { "role": "model", "parts": [{ "text": text }] },
+                "finishReason": "STOP"
+            }]
+        });
+        (StatusCode::OK, reply)
+    }
+
+    #[tokio::test]
+    async fn maps_the_conversation() {
+        let quota = json!({
+            "error": { "code": 429, "message": "Quota exceeded", "status": "RESOURCE_EXHAUSTED" }
+        });
+        let api = Fixture::serve(vec![
+            reply("reply 1"),
+            (StatusCode::TOO_MANY_REQUESTS, quota),
+            reply("reply 3"),
+        ])
+        .await;
+        let config: GeminiConfig = toml::from_str(&format!(
+            "api_key = \"AIza-test\"\nmodel = \"gemini-test\"\nbase_url = {:?}",
+            api.url
+        ))
+        .unwrap();
+
+        let err = converse(Box::new(GeminiChat::new(reqwest::Client::new(), &config))).await;
+        assert_eq!(
+            err.to_string(),
+            "API error: 429 Too Many Requests: Quota exceeded"
+        );
+
+        let requests = api.requests();
+        assert_eq!(
+            requests[0].path,
+            "/v1beta/models/gemini-test:generateContent"
+        );
+        assert_eq!(requests[0].headers["x-goog-api-key"], "AIza-test");
+        assert_eq!(
+            requests[0].body["systemInstruction"],
+            json!({ "parts": [{ "text": "You are Echo." }] })
+        );
+        assert_eq!(requests[0].body["generationConfig"]["maxOutputTokens"], 256);
+        assert_eq!(
+            requests[2].body["contents"],
+            json!([
+                { "role": "user", "parts": [{ "text": "hi" }] },
+                { "role": "model", "parts": [{ "text": "reply 1" }] },
+                { "role": "user", "parts": [{ "text": "again" }] },
+            ])
+        );
+    }
+}
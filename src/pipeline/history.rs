@@ -0,0 +1,491 @@
+//! Per-call message history for brains that call an LLM API directly.
+//!
+//! The API is stateless, so every turn resends the call's conversation so
+//! far. A turn is only recorded once the brain has answered it; a failed
+//! request leaves the history as it was.
+//!
+//! Each call's conversation is also written to its own JSON file under
+//! `[llm] sessions_dir` whenever it changes, and reloaded on start, so a
+//! restart mid-call doesn't make the brain forget what was said. `[privacy]`
+//! masks what's written.
+//!
+//! With `[llm] summarize_after_turns` set, a conversation that reaches that
+//! many turns is summarized by the brain, and the summary stands in for
+//! those turns from then on, so long calls don't resend an ever-growing
+//! history.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::RwLock;
+
+use crate::persist::save_json;
+use crate::pipeline::privacy::PrivacyMask;
+
+/// Asked after the conversation to be summarized.
+const SUMMARIZE: &str = "Summarize this call so far in a few sentences, for your own reference \
+     later in the call: who the caller is, what they want, and anything agreed or still open. \
+     Reply with the summary only.";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChatRole {
+    User,
+    Assistant,
+}
+
+impl ChatRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ChatRole::User => "user",
+            ChatRole::Assistant => "assistant",
+        }
+    }
+}
+
+/// One message of a conversation, in the shape chat APIs take.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChatMessage {
+    pub role: ChatRole,
+    pub content: String,
+}
+
+impl ChatMessage {
+    pub fn user(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::User,
+            content: content.into(),
+        }
+    }
+
+    pub fn assistant(content: impl Into<String>) -> Self {
+        Self {
+            role: ChatRole::Assistant,
+            content: content.into(),
+        }
+    }
+}
+
+/// Where conversations are kept across restarts, one file per call, and
+/// what's masked in them on the way there.
+pub struct SessionsDir {
+    pub dir: PathBuf,
+    pub mask: PrivacyMask,
+}
+
+impl SessionsDir {
+    pub fn unmasked(dir: PathBuf) -> Self {
+        Self {
+            dir,
+            mask: PrivacyMask::default(),
+        }
+    }
+
+    /// The file keeping `call_sid`'s conversation.
+    fn path(&self, call_sid: &str) -> PathBuf {
+        let name: String = call_sid
+            .chars()
+            .map(|c| match c {
+                'a'..='z' | 'A'..='Z' | '0'..='9' | '-' | '_' => c,
+                _ => '_',
+            })
+            .collect();
+        self.dir.join(format!("{name}.json"))
+    }
+}
+
+/// Conversations keyed by call_sid. Idle ones expire after the session
+/// timeout, so calls that never ended cleanly don't pile up.
+pub struct History {
+    sessions: RwLock<HashMap<String, Arc<Mutex<Session>>>>,
+    timeout: Duration,
+    dir: Option<SessionsDir>,
+    /// Turns after which a conversation is summarized; 0 never does.
+    summarize_after: usize,
+    /// Held while saving, so snapshots reach the file in order.
+    saving: tokio::sync::Mutex<()>,
+}
+
+struct Session {
+    /// Summary of the turns before `messages`.
+    summary: Option<String>,
+    messages: Vec<ChatMessage>,
+    last_used: Instant,
+    /// A summary is being written.
+    summarizing: bool,
+}
+
+impl Session {
+    fn new(summary: Option<String>, messages: Vec<ChatMessage>, last_used: Instant) -> Self {
+        Self {
+            summary,
+            messages,
+            last_used,
+            summarizing: false,
+        }
+    }
+
+    /// The conversation as sent: the summary, if any, then the turns since.
+    fn conversation(&self) -> Vec<ChatMessage> {
+        let mut conversation = Vec::with_capacity(self.messages.len() + 2);
+        if let Some(ref summary) = self.summary {
+            conversation.push(ChatMessage::user(format!(
+                "[Summary of the call so far: {summary}]"
+            )));
+            conversation.push(ChatMessage::assistant("Understood."));
+        }
+        conversation.extend(self.messages.iter().cloned());
+        conversation
+    }
+}
+
+/// A conversation as saved in the sessions file.
+#[derive(Debug, Serialize, Deserialize)]
+struct SavedSession {
+    call_sid: String,
+    last_used: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    summary: Option<String>,
+    messages: Vec<ChatMessage>,
+}
+
+impl History {
+    pub fn new(session_timeout_secs: u64) -> Self {
+        Self {
+            sessions: RwLock::new(HashMap::new()),
+            timeout: Duration::from_secs(session_timeout_secs),
+            dir: None,
+            summarize_after: 0,
+            saving: tokio::sync::Mutex::new(()),
+        }
+    }
+
+    /// Keep conversations in `dir` across restarts, starting from the ones
+    /// saved there that haven't expired yet. `None` keeps them in memory.
+    pub fn with_dir(mut self, dir: Option<SessionsDir>) -> Self {
+        let Some(dir) = dir else {
+            return self;
+        };
+        let now = Utc::now();
+        let sessions: HashMap<_, _> = load(&dir.dir)
+            .into_iter()
+            .filter_map(|(path, saved)| {
+                let idle = (now - saved.last_used).to_std().unwrap_or_default();
+                let last_used = Instant::now()
+                    .checked_sub(idle)
+                    .filter(|_| idle < self.timeout);
+                let Some(last_used) = last_used else {
+                    let _ = std::fs::remove_file(path);
+                    return None;
+                };
+                let session = Session::new(saved.summary, saved.messages, last_used);
+                Some((saved.call_sid, Arc::new(Mutex::new(session))))
+            })
+            .collect();
+        if !sessions.is_empty() {
+            tracing::info!(count = sessions.len(), dir = %dir.dir.display(), "Loaded brain sessions");
+        }
+        self.sessions = RwLock::new(sessions);
+        self.dir = Some(dir);
+        self
+    }
+
+    /// Summarize conversations once they reach `turns` turns. 0 never does.
+    pub fn with_summary_after(mut self, turns: usize) -> Self {
+        self.summarize_after = turns;
+        self
+    }
+
+    /// The call's conversation so far, followed by `prompt`.
+    pub async fn with_prompt(&self, call_sid: &str, prompt: &str) -> Vec<ChatMessage> {
+        let session = self.session(call_sid).await;
+        let mut session = session.lock().unwrap();
+        session.last_used = Instant::now();
+        let mut messages = session.conversation();
+        messages.push(ChatMessage::user(prompt));
+        messages
+    }
+
+    /// If the call's conversation is due a summary: the request asking the
+    /// brain for one, and how many messages it covers. Hand the answer to
+    /// [`summarized`](Self::summarized).
+    pub async fn to_summarize(&self, call_sid: &str) -> Option<(Vec<ChatMessage>, usize)> {
+        if self.summarize_after == 0 {
+            return None;
+        }
+        let session = Arc::clone(self.sessions.read().await.get(call_sid)?);
+        let mut session = session.lock().unwrap();
+        if session.summarizing || session.messages.len() < self.summarize_after * 2 {
+            return None;
+        }
+        session.summarizing = true;
+        let mut messages = session.conversation();
+        messages.push(ChatMessage::user(SUMMARIZE));
+        Some((messages, session.messages.len()))
+    }
+
+    /// Replace the first `covered` messages with `summary`. `None` (the
+    /// brain failed) leaves the conversation as it was, to try again after
+    /// the next turn.
+    pub async fn summarized(&self, call_sid: &str, covered: usize, summary: Option<String>) {
+        let Some(session) = self.sessions.read().await.get(call_sid).cloned() else {
+            return;
+        };
+        {
+            let mut session = session.lock().unwrap();
+            session.summarizing = false;
+            let Some(summary) = summary else {
+                return;
+            };
+            let covered = covered.min(session.messages.len());
+            session.messages.drain(..covered);
+            session.summary = Some(summary);
+        }
+        self.save(call_sid).await;
+    }
+
+    /// Record an answered turn.
+    pub async fn record(&self, call_sid: &str, prompt: &str, response: &str) {
+        {
+            let session = self.session(call_sid).await;
+            let mut session = session.lock().unwrap();
+            session.messages.push(ChatMessage::user(prompt));
+            session.messages.push(ChatMessage::assistant(response));
+            session.last_used = Instant::now();
+        }
+        self.save(call_sid).await;
+    }
+
+    /// Forget a call's conversation (call ended).
+    pub async fn end(&self, call_sid: &str) {
+        let ended = self.sessions.write().await.remove(call_sid).is_some();
+        if ended {
+            self.save(call_sid).await;
+        }
+    }
+
+    /// Write the call's conversation to its file, if conversations are
+    /// kept, or remove the file once the conversation is gone.
+    async fn save(&self, call_sid: &str) {
+        let Some(ref dir) = self.dir else {
+            return;
+        };
+        let _saving = self.saving.lock().await;
+        let path = dir.path(call_sid);
+        let session = self.sessions.read().await.get(call_sid).cloned();
+        let Some(session) = session else {
+            remove(&path).await;
+            return;
+        };
+        let saved = {
+            let session = session.lock().unwrap();
+            let mask = |text: &str| dir.mask.apply(text).into_owned();
+            SavedSession {
+                call_sid: call_sid.to_string(),
+                last_used: Utc::now()
+                    - chrono::Duration::from_std(session.last_used.elapsed()).unwrap_or_default(),
+                summary: session.summary.as_deref().map(mask),
+                messages: session
+                    .messages
+                    .iter()
+                    .map(|message| ChatMessage {
+                        role: message.role,
+                        content: mask(&message.content),
+                    })
+                    .collect(),
+            }
+        };
+        save_json(&path, &saved, "brain session").await;
+    }
+
+    /// The call's session, created on its first turn.
+    async fn session(&self, call_sid: &str) -> Arc<Mutex<Session>> {
+        if let Some(session) = self.sessions.read().await.get(call_sid) {
+            return Arc::clone(session);
+        }
+
+        let mut sessions = self.sessions.write().await;
+
+        // Only new calls pay for the sweep; a session locked mid-turn is in
+        // use, so it stays
+        let mut expired = Vec::new();
+        sessions.retain(|call_sid, s| {
+            let keep = s
+                .try_lock()
+                .map_or(true, |s| s.last_used.elapsed() < self.timeout);
+            if !keep {
+                expired.push(call_sid.clone());
+            }
+            keep
+        });
+
+        let session = Arc::clone(sessions.entry(call_sid.to_string()).or_insert_with(|| {
+            Arc::new(Mutex::new(Session::new(None, Vec::new(), Instant::now())))
+        }));
+        drop(sessions);
+        if let Some(ref dir) = self.dir {
+            for call_sid in expired {
+                remove(&dir.path(&call_sid)).await;
+            }
+        }
+        session
+    }
+}
+
+/// The conversations saved in `dir`, with the file each came from.
+/// Unreadable files are logged and skipped.
+fn load(dir: &Path) -> Vec<(PathBuf, SavedSession)> {
+    let entries = match std::fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Vec::new(),
+        Err(e) => {
+            tracing::error!(dir = %dir.display(), "Failed to read brain sessions: {e}");
+            return Vec::new();
+        }
+    };
+    entries
+        .filter_map(Result::ok)
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|e| e == "json"))
+        .filter_map(|path| {
+            let parsed = std::fs::read(&path)
+                .map_err(|e| e.to_string())
+                .and_then(|bytes| serde_json::from_slice(&bytes).map_err(|e| e.to_string()));
+            match parsed {
+                Ok(saved) => Some((path, saved)),
+                Err(e) => {
+                    tracing::error!(path = %path.display(), "Unreadable brain session: {e}");
+                    None
+                }
+            }
+        })
+        .collect()
+}
+
+/// Remove a call's saved conversation, if there is one.
+async fn remove(path: &Path) {
+    match tokio::fs::remove_file(path).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => tracing::error!(path = %path.display(), "Failed to remove brain session: {e}"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn records_only_answered_turns() {
+        let history = History::new(300);
+        assert_eq!(
+            history.with_prompt("CA1", "hi").await,
+            vec![ChatMessage::user("hi")]
+        );
+        // Never answered
+        history.with_prompt("CA1", "lost").await;
+        history.record("CA1", "hi", "hello").await;
+        assert_eq!(
+            history.with_prompt("CA1", "bye").await,
+            vec![
+                ChatMessage::user("hi"),
+                ChatMessage::assistant("hello"),
+                ChatMessage::user("bye"),
+            ]
+        );
+        assert_eq!(history.with_prompt("CA2", "hey").await.len(), 1);
+
+        history.end("CA1").await;
+        assert_eq!(history.with_prompt("CA1", "again").await.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn summary_stands_in_for_earlier_turns() {
+        let history = History::new(300).with_summary_after(2);
+        history.record("CA1", "hi", "hello").await;
+        assert_eq!(history.to_summarize("CA1").await, None);
+        history.record("CA1", "I'm Ana", "Hi Ana").await;
+
+        let (request, covered) = history.to_summarize("CA1").await.unwrap();
+        assert_eq!(covered, 4);
+        assert_eq!(request.len(), 5);
+        assert_eq!(request[4], ChatMessage::user(SUMMARIZE));
+        // Already under way
+        assert_eq!(history.to_summarize("CA1").await, None);
+
+        // A turn answered meanwhile is kept
+        history.record("CA1", "what time is it?", "Noon").await;
+        history
+            .summarized("CA1", covered, Some("Caller is Ana.".into()))
+            .await;
+        assert_eq!(
+            history.with_prompt("CA1", "thanks").await,
+            vec![
+                ChatMessage::user("[Summary of the call so far: Caller is Ana.]"),
+                ChatMessage::assistant("Understood."),
+                ChatMessage::user("what time is it?"),
+                ChatMessage::assistant("Noon"),
+                ChatMessage::user("thanks"),
+            ]
+        );
+        assert_eq!(history.to_summarize("CA1").await, None);
+    }
+
+    #[tokio::test]
+    async fn conversations_survive_a_restart() {
+        let tmp = tempfile::tempdir().unwrap();
+        let dir = tmp.path();
+        let sessions = || Some(SessionsDir::unmasked(dir.to_path_buf()));
+
+        let history = History::new(300).with_dir(sessions());
+        history.record("CA1", "hi", "hello").await;
+        history.record("CA2", "hey", "hi there").await;
+        assert!(dir.join("CA2.json").exists());
+        history.end("CA2").await;
+        // One file per call, gone once the call ends
+        assert!(dir.join("CA1.json").exists());
+        assert!(!dir.join("CA2.json").exists());
+        drop(history);
+
+        let history = History::new(300).with_dir(sessions());
+        assert_eq!(
+            history.with_prompt("CA1", "still there?").await,
+            vec![
+                ChatMessage::user("hi"),
+                ChatMessage::assistant("hello"),
+                ChatMessage::user("still there?"),
+            ]
+        );
+        assert_eq!(history.with_prompt("CA2", "hey").await.len(), 1);
+
+        // Expired conversations aren't brought back
+        let history = History::new(0).with_dir(sessions());
+        assert_eq!(history.with_prompt("CA1", "hi").await.len(), 1);
+        assert!(!dir.join("CA1.json").exists());
+    }
+
+    #[tokio::test]
+    async fn saves_conversations_masked() {
+        let dir = tempfile::tempdir().unwrap();
+        let privacy = toml::from_str("phone_numbers = true").unwrap();
+        let sessions = SessionsDir {
+            dir: dir.path().to_path_buf(),
+            mask: PrivacyMask::new(&privacy, &Default::default()),
+        };
+
+        let history = History::new(300).with_dir(Some(sessions));
+        history.record("CA1", "It's 4155551212", "Got it").await;
+        // The brain still has the number
+        assert_eq!(
+            history.with_prompt("CA1", "thanks").await[0],
+            ChatMessage::user("It's 4155551212")
+        );
+        let saved = std::fs::read_to_string(dir.path().join("CA1.json")).unwrap();
+        assert!(saved.contains("It's [redacted]"));
+        assert!(!saved.contains("4155551212"));
+    }
+}
@@ -0,0 +1,804 @@
+//! Hold music sources (`[hold_music]`).
+//!
+//! WAV files are converted to frame payloads once at startup and played as a
+//! playlist: each hold starts the next track, in order or shuffled, and a
+//! long hold moves on to the one after instead of looping one track. A `url`
+//! is decoded and resampled to 8kHz mu-law as it arrives. The stream is only
+//! connected while someone is on hold (plus a short linger between turns),
+//! and every call on hold hears the same feed.
+//!
+//! `[hold_announcements]` speak a short message every so often during a long
+//! turn, with the music ducked underneath.
+
+use std::collections::HashMap;
+use std::future::Future;
+use std::io::{self, Read};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use base64::Engine;
+use bytes::{Buf, Bytes};
+use rand::Rng;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error as SymphoniaError;
+use symphonia::core::formats::FormatOptions;
+use symphonia::core::io::{MediaSourceStream, ReadOnlySource};
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
+use tokio::sync::{broadcast, mpsc, Notify};
+
+use crate::config::{HoldAnnouncementsConfig, HoldMusicConfig};
+use crate::pipeline::{audio, frames};
+use crate::rng::SharedRng;
+use crate::AppState;
+
+/// Stream audio decoded ahead of real time, at most. Keeps a static file
+/// served over HTTP from being decoded all at once.
+const MAX_LEAD: Duration = Duration::from_secs(2);
+/// Frames buffered per listener; comfortably more than `MAX_LEAD`.
+const STREAM_BUFFER_FRAMES: usize = 512;
+/// Keep the stream connected this long after the last listener, so the
+/// next turn's hold doesn't reconnect.
+const IDLE_LINGER: Duration = Duration::from_secs(30);
+const RECONNECT_DELAY: Duration = Duration::from_secs(2);
+
+#[derive(Debug, thiserror::Error)]
+pub enum HoldStreamError {
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    #[error("undecodable stream: {0}")]
+    Decode(#[from] SymphoniaError),
+    #[error("stream has no audio track")]
+    NoTrack,
+    #[error("decoder stopped: {0}")]
+    Task(#[from] tokio::task::JoinError),
+}
+
+/// Loaded `[hold_music]`, shared by all calls.
+pub enum HoldMusic {
+    Playlist(Box<Playlist>),
+    Stream(LiveStream),
+}
+
+impl HoldMusic {
+    /// Load `config`, or `None` when nothing playable is configured. Files
+    /// that fail to load are skipped with a warning.
+    pub fn from_config(
+        config: &HoldMusicConfig,
+        http: &reqwest::Client,
+        frame_bytes: usize,
+        seed: Option<u64>,
+    ) -> Option<Self> {
+        if let Some(ref url) = config.url {
+            if config.paths().next().is_some() {
+                tracing::warn!("hold_music.url is set; ignoring hold_music files");
+            }
+            tracing::info!(url = %url, volume = config.volume, "Streaming hold music");
+            return Some(Self::Stream(LiveStream::start(
+                http.clone(),
+                url.clone(),
+                config.volume,
+                frame_bytes,
+            )));
+        }
+
+        let mut tracks: Vec<Vec<Arc<str>>> = Vec::new();
+        let mut skipped = Vec::new();
+        for path in config.paths() {
+            match audio::load_wav_as_mulaw(std::path::Path::new(path), config.volume) {
+                Ok(data) => {
+                    tracing::info!(
+                        path,
+                        volume = config.volume,
+                        mulaw_bytes = data.len(),
+                        "Loaded hold music"
+                    );
+                    let payloads = frames::encode_payloads(&data, frame_bytes);
+                    tracks.push(payloads.into_iter().map(Arc::from).collect());
+                }
+                Err(e) => {
+                    tracing::warn!(path, "Failed to load hold music: {e}");
+                    skipped.push(path.to_string());
+                }
+            }
+        }
+        tracks.retain(|track| !track.is_empty());
+        if tracks.is_empty() {
+            return None;
+        }
+        Some(Self::Playlist(Box::new(Playlist {
+            tracks,
+            skipped,
+            shuffle: config.shuffle,
+            next: AtomicUsize::new(0),
+            rng: SharedRng::new(seed),
+        })))
+    }
+
+    /// Start a hold: the frames one caller hears.
+    pub fn listen(self: &Arc<Self>) -> Listener {
+        match **self {
+            HoldMusic::Playlist(ref playlist) => Listener::Track {
+                music: Arc::clone(self),
+                track: playlist.pick(),
+                frame: 0,
+            },
+            HoldMusic::Stream(ref stream) => Listener::Stream(stream.subscribe()),
+        }
+    }
+
+    /// Files that couldn't be loaded.
+    pub fn skipped(&self) -> &[String] {
+        match self {
+            HoldMusic::Playlist(playlist) => &playlist.skipped,
+            HoldMusic::Stream(_) => &[],
+        }
+    }
+
+    /// Check that a stream answers. Files were checked when loaded.
+    pub async fn probe(&self) -> Result<(), HoldStreamError> {
+        match self {
+            HoldMusic::Playlist(_) => Ok(()),
+            HoldMusic::Stream(stream) => {
+                // Only the headers; dropping the response disconnects
+                stream
+                    .http
+                    .get(&stream.url)
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                Ok(())
+            }
+        }
+    }
+}
+
+/// WAV tracks as base64 frame payloads.
+pub struct Playlist {
+    tracks: Vec<Vec<Arc<str>>>,
+    skipped: Vec<String>,
+    shuffle: bool,
+    /// Rotation position, or the last track picked when shuffling.
+    next: AtomicUsize,
+    rng: SharedRng,
+}
+
+impl Playlist {
+    /// The track to play next. Shuffling never picks the same track twice
+    /// in a row.
+    fn pick(&self) -> usize {
+        let count = self.tracks.len();
+        if !self.shuffle || count < 2 {
+            return self.next.fetch_add(1, Ordering::Relaxed) % count;
+        }
+        let skip = self.rng.with(|rng| rng.gen_range(1..count));
+        let last = self.next.load(Ordering::Relaxed);
+        let track = (last + skip) % count;
+        self.next.store(track, Ordering::Relaxed);
+        track
+    }
+}
+
+/// One caller's position in the hold music.
+pub enum Listener {
+    Track {
+        music: Arc<HoldMusic>,
+        track: usize,
+        frame: usize,
+    },
+    Stream(broadcast::Receiver<Arc<str>>),
+}
+
+impl Listener {
+    /// The next frame's base64 payload, or `None` to stay quiet this tick
+    /// (the stream hasn't caught up, or has gone away).
+    pub fn next_frame(&mut self) -> Option<Arc<str>> {
+        match self {
+            Listener::Track {
+                music,
+                track,
+                frame,
+            } => {
+                let HoldMusic::Playlist(ref playlist) = **music else {
+                    return None;
+                };
+                if *frame == playlist.tracks[*track].len() {
+                    *track = playlist.pick();
+                    *frame = 0;
+                }
+                let payload = Arc::clone(&playlist.tracks[*track][*frame]);
+                *frame += 1;
+                Some(payload)
+            }
+            Listener::Stream(rx) => loop {
+                match rx.try_recv() {
+                    Ok(payload) => return Some(payload),
+                    // Fell behind: carry on from the oldest frame still held
+                    Err(broadcast::error::TryRecvError::Lagged(_)) => continue,
+                    Err(_) => return None,
+                }
+            },
+        }
+    }
+}
+
+/// Announcement audio by voice and message.
+type AnnouncementCache = HashMap<(Option<String>, String), Arc<Vec<u8>>>;
+
+/// Spoken reminders during long turns (`[hold_announcements]`).
+pub struct HoldAnnouncements {
+    every: Duration,
+    messages: Vec<String>,
+    duck: f32,
+    next: AtomicUsize,
+    /// Synthesized audio, so each message is paid for once per voice.
+    cache: Mutex<AnnouncementCache>,
+}
+
+impl HoldAnnouncements {
+    /// `None` when announcements are off.
+    pub fn from_config(config: &HoldAnnouncementsConfig) -> Option<Self> {
+        if config.every_secs == 0 || config.messages.is_empty() {
+            return None;
+        }
+        Some(Self {
+            every: Duration::from_secs(config.every_secs),
+            messages: config.messages.clone(),
+            duck: config.duck.clamp(0.0, 1.0),
+            next: AtomicUsize::new(0),
+            cache: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// Every `every` until dropped, synthesize the next message in the
+    /// call's voice and hand it to the hold. Returns once the hold is gone.
+    async fn run(&self, state: &AppState, call_sid: &str, tx: &mpsc::Sender<Arc<Vec<u8>>>) {
+        loop {
+            tokio::time::sleep(self.every).await;
+            let message =
+                &self.messages[self.next.fetch_add(1, Ordering::Relaxed) % self.messages.len()];
+            let profile = state.call_registry.profile(call_sid).await;
+            let voice = state.voice_for(&profile).map(str::to_string);
+            let key = (voice, message.clone());
+            let cached = self
+                .cache
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .get(&key)
+                .cloned();
+            let audio = match cached {
+                Some(audio) => audio,
+                None => {
+                    let tts = state.tts_for(&profile);
+                    let result = tts.synthesize_phrase(message, key.0.as_deref()).await;
+                    match result {
+                        Ok(audio) => {
+                            let audio = Arc::new(audio);
+                            self.cache
+                                .lock()
+                                .unwrap_or_else(PoisonError::into_inner)
+                                .insert(key, Arc::clone(&audio));
+                            audio
+                        }
+                        Err(e) => {
+                            tracing::warn!(call_sid, "Hold announcement TTS failed: {e}");
+                            continue;
+                        }
+                    }
+                }
+            };
+            tracing::debug!(call_sid, message = %message, "Hold announcement");
+            if tx.send(audio).await.is_err() {
+                return;
+            }
+        }
+    }
+}
+
+/// Run `work` (a turn) while `[hold_announcements]`, if on, feeds `tx`.
+pub async fn announce_while<T>(
+    state: &AppState,
+    call_sid: &str,
+    tx: mpsc::Sender<Arc<Vec<u8>>>,
+    work: impl Future<Output = T>,
+) -> T {
+    let Some(ref announcements) = state.hold_announcements else {
+        return work.await;
+    };
+    tokio::pin!(work);
+    tokio::select! {
+        out = &mut work => out,
+        () = announcements.run(state, call_sid, &tx) => work.await,
+    }
+}
+
+/// What one caller hears on hold: the music, with any announcement spoken
+/// over it while the music is turned down.
+pub struct HoldMix {
+    music: Option<Listener>,
+    announcements: mpsc::Receiver<Arc<Vec<u8>>>,
+    duck: f32,
+    frame_bytes: usize,
+    /// The announcement playing, and how much of it has been sent.
+    speaking: Option<(Arc<Vec<u8>>, usize)>,
+}
+
+impl HoldMix {
+    pub fn new(
+        state: &AppState,
+        announcements: mpsc::Receiver<Arc<Vec<u8>>>,
+        frame_bytes: usize,
+    ) -> Self {
+        Self {
+            music: state.hold_music.as_ref().map(HoldMusic::listen),
+            announcements,
+            duck: state.hold_announcements.as_ref().map_or(1.0, |a| a.duck),
+            frame_bytes,
+            speaking: None,
+        }
+    }
+
+    /// The next frame's base64 payload, or `None` to stay quiet this tick.
+    pub fn next_frame(&mut self) -> Option<Arc<str>> {
+        if self.speaking.is_none() {
+            self.speaking = self.announcements.try_recv().ok().map(|audio| (audio, 0));
+        }
+        let music = self.music.as_mut().and_then(Listener::next_frame);
+        let Some((ref speech, ref mut sent)) = self.speaking else {
+            return music;
+        };
+        let chunk = &speech[*sent..speech.len().min(*sent + self.frame_bytes)];
+        *sent += chunk.len();
+        let music = music
+            .and_then(|payload| {
+                base64::engine::general_purpose::STANDARD
+                    .decode(payload.as_bytes())
+                    .ok()
+            })
+            .unwrap_or_default();
+        let mixed = duck_under(&music, chunk, self.duck);
+        if *sent >= speech.len() {
+            self.speaking = None;
+        }
+        Some(Arc::from(
+            base64::engine::general_purpose::STANDARD.encode(mixed),
+        ))
+    }
+}
+
+/// Mu-law `speech` over `music` scaled by `duck`, as long as the longer.
+fn duck_under(music: &[u8], speech: &[u8], duck: f32) -> Vec<u8> {
+    let pcm = |audio: &[u8], i: usize| audio.get(i).map_or(0.0, |&b| audio::mulaw_to_pcm(b) as f32);
+    (0..music.len().max(speech.len()))
+        .map(|i| {
+            let mixed = pcm(music, i) * duck + pcm(speech, i);
+            audio::pcm_to_mulaw(mixed.clamp(-32768.0, 32767.0) as i16)
+        })
+        .collect()
+}
+
+/// A URL decoded to frame payloads by a background task, broadcast to
+/// every listener.
+pub struct LiveStream {
+    http: reqwest::Client,
+    url: String,
+    frames: broadcast::Sender<Arc<str>>,
+    /// Woken when a listener arrives, so the task can connect.
+    wanted: Arc<Notify>,
+}
+
+impl LiveStream {
+    fn start(http: reqwest::Client, url: String, volume: f32, frame_bytes: usize) -> Self {
+        let (frames, _) = broadcast::channel(STREAM_BUFFER_FRAMES);
+        let wanted = Arc::new(Notify::new());
+        tokio::spawn(run_stream(
+            http.clone(),
+            url.clone(),
+            volume,
+            frame_bytes,
+            frames.clone(),
+            Arc::clone(&wanted),
+        ));
+        Self {
+            http,
+            url,
+            frames,
+            wanted,
+        }
+    }
+
+    fn subscribe(&self) -> broadcast::Receiver<Arc<str>> {
+        let rx = self.frames.subscribe();
+        self.wanted.notify_one();
+        rx
+    }
+}
+
+/// Connect whenever someone is listening, reconnecting after errors and
+/// when the stream ends (a static file loops this way).
+async fn run_stream(
+    http: reqwest::Client,
+    url: String,
+    volume: f32,
+    frame_bytes: usize,
+    frames: broadcast::Sender<Arc<str>>,
+    wanted: Arc<Notify>,
+) {
+    loop {
+        if frames.receiver_count() == 0 {
+            wanted.notified().await;
+            continue;
+        }
+        match stream_once(&http, &url, volume, frame_bytes, &frames).await {
+            Ok(()) => tracing::debug!(url = %url, "Hold music stream ended"),
+            Err(e) => tracing::warn!(url = %url, "Hold music stream failed: {e}"),
+        }
+        if frames.receiver_count() > 0 {
+            tokio::time::sleep(RECONNECT_DELAY).await;
+        }
+    }
+}
+
+/// One connection: feed the body to a decoder on a blocking thread until
+/// either side stops.
+async fn stream_once(
+    http: &reqwest::Client,
+    url: &str,
+    volume: f32,
+    frame_bytes: usize,
+    frames: &broadcast::Sender<Arc<str>>,
+) -> Result<(), HoldStreamError> {
+    let mut response = http.get(url).send().await?.error_for_status()?;
+    let hint = response
+        .headers()
+        .get(reqwest::header::CONTENT_TYPE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(extension_for);
+    tracing::debug!(url, ?hint, "Hold music stream connected");
+
+    let (chunks_tx, chunks_rx) = mpsc::channel(16);
+    let frames = frames.clone();
+    let decoder =
+        tokio::task::spawn_blocking(move || decode(chunks_rx, hint, volume, frame_bytes, &frames));
+    while let Some(chunk) = response.chunk().await? {
+        if chunks_tx.send(chunk).await.is_err() {
+            break; // decoder finished
+        }
+    }
+    drop(chunks_tx);
+    decoder.await?
+}
+
+/// Container hint from a stream's `Content-Type`.
+fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim();
+    match mime {
+        "audio/mpeg" | "audio/mp3" => Some("mp3"),
+        "audio/ogg" | "application/ogg" | "audio/vorbis" => Some("ogg"),
+        "audio/wav" | "audio/x-wav" | "audio/wave" => Some("wav"),
+        _ => None,
+    }
+}
+
+/// Decode until the body ends or nobody has listened for `IDLE_LINGER`,
+/// broadcasting mu-law frames no further than `MAX_LEAD` ahead of real time.
+fn decode(
+    chunks: mpsc::Receiver<Bytes>,
+    hint: Option<&'static str>,
+    volume: f32,
+    frame_bytes: usize,
+    frames: &broadcast::Sender<Arc<str>>,
+) -> Result<(), HoldStreamError> {
+    let source = ReadOnlySource::new(ChunkReader {
+        chunks,
+        current: Bytes::new(),
+    });
+    let stream = MediaSourceStream::new(Box::new(source), Default::default());
+    let mut format_hint = Hint::new();
+    if let Some(extension) = hint {
+        format_hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe().format(
+        &format_hint,
+        stream,
+        &FormatOptions::default(),
+        &MetadataOptions::default(),
+    )?;
+    let mut format = probed.format;
+    let track = format.default_track().ok_or(HoldStreamError::NoTrack)?;
+    let track_id = track.id;
+    let mut decoder =
+        symphonia::default::get_codecs().make(&track.codec_params, &DecoderOptions::default())?;
+
+    let frame_duration = Duration::from_secs_f64(frame_bytes as f64 / 8000.0);
+    let started = Instant::now();
+    let mut sent = Duration::ZERO;
+    let mut idle_since = None;
+    let mut resampler: Option<(u32, Resampler)> = None;
+    let mut pcm = Vec::new();
+    let mut pending = Vec::with_capacity(frame_bytes * 2);
+
+    loop {
+        if frames.receiver_count() > 0 {
+            idle_since = None;
+        } else if idle_since.get_or_insert_with(Instant::now).elapsed() >= IDLE_LINGER {
+            return Ok(());
+        }
+
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            Err(SymphoniaError::IoError(e)) if e.kind() == io::ErrorKind::UnexpectedEof => {
+                return Ok(());
+            }
+            Err(e) => return Err(e.into()),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        let decoded = match decoder.decode(&packet) {
+            Ok(decoded) => decoded,
+            // A corrupt packet; live streams recover on the next one
+            Err(SymphoniaError::DecodeError(e)) => {
+                tracing::debug!("Skipping undecodable hold music packet: {e}");
+                continue;
+            }
+            Err(e) => return Err(e.into()),
+        };
+
+        let spec = *decoded.spec();
+        let channels = spec.channels.count().max(1);
+        let mut samples = SampleBuffer::<i16>::new(decoded.capacity() as u64, spec);
+        samples.copy_interleaved_ref(decoded);
+        let mono: Vec<i16> = samples
+            .samples()
+            .chunks(channels)
+            .map(|frame| (frame.iter().map(|&s| s as i32).sum::<i32>() / channels as i32) as i16)
+            .collect();
+
+        let resampler = match resampler {
+            Some((rate, ref mut resampler)) if rate == spec.rate => resampler,
+            _ => {
+                &mut resampler
+                    .insert((spec.rate, Resampler::new(spec.rate, 8000)))
+                    .1
+            }
+        };
+        pcm.clear();
+        resampler.process(&mono, &mut pcm);
+        pending.extend(
+            pcm.iter().map(|&s| {
+                audio::pcm_to_mulaw(((s as f32) * volume).clamp(-32768.0, 32767.0) as i16)
+            }),
+        );
+
+        let mut start = 0;
+        while pending.len() - start >= frame_bytes {
+            let payload = base64::engine::general_purpose::STANDARD
+                .encode(&pending[start..start + frame_bytes]);
+            // Err only means nobody is listening right now
+            let _ = frames.send(Arc::from(payload));
+            start += frame_bytes;
+            sent += frame_duration;
+        }
+        pending.drain(..start);
+
+        if let Some(ahead) = sent.checked_sub(started.elapsed() + MAX_LEAD) {
+            std::thread::sleep(ahead);
+        }
+    }
+}
+
+/// Blocking `Read` over the body chunks, for the decoder thread.
+struct ChunkReader {
+    chunks: mpsc::Receiver<Bytes>,
+    current: Bytes,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        while self.current.is_empty() {
+            match self.chunks.blocking_recv() {
+                Some(chunk) => self.current = chunk,
+                None => return Ok(0),
+            }
+        }
+        let n = buf.len().min(self.current.len());
+        buf[..n].copy_from_slice(&self.current[..n]);
+        self.current.advance(n);
+        Ok(n)
+    }
+}
+
+/// Linear resampling that carries its position across packets, so rates
+/// that don't divide evenly don't drift.
+struct Resampler {
+    step: f64,
+    /// Next output position, in input samples; -1 is the previous packet's
+    /// last sample.
+    pos: f64,
+    prev: i16,
+}
+
+impl Resampler {
+    fn new(from_rate: u32, to_rate: u32) -> Self {
+        Self {
+            step: from_rate as f64 / to_rate as f64,
+            pos: 0.0,
+            prev: 0,
+        }
+    }
+
+    fn process(&mut self, input: &[i16], out: &mut Vec<i16>) {
+        let Some(&last) = input.last() else {
+            return;
+        };
+        let sample = |i: isize| if i < 0 { self.prev } else { input[i as usize] };
+        let end = (input.len() - 1) as f64;
+        while self.pos < end {
+            let i = self.pos.floor();
+            let frac = self.pos - i;
+            let (a, b) = (sample(i as isize) as f64, sample(i as isize + 1) as f64);
+            out.push((a + (b - a) * frac) as i16);
+            self.pos += self.step;
+        }
+        self.pos -= input.len() as f64;
+        self.prev = last;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use axum::routing::get;
+    use axum::Router;
+
+    use super::*;
+
+    fn playlist(tracks: usize, shuffle: bool) -> Arc<HoldMusic> {
+        Arc::new(HoldMusic::Playlist(Box::new(Playlist {
+            tracks: (0..tracks)
+                .map(|t| vec![Arc::from(format!("{t}a")), Arc::from(format!("{t}b"))])
+                .collect(),
+            skipped: Vec::new(),
+            shuffle,
+            next: AtomicUsize::new(0),
+            rng: SharedRng::new(Some(7)),
+        })))
+    }
+
+    fn frames(listener: &mut Listener, count: usize) -> Vec<String> {
+        (0..count)
+            .map(|_| listener.next_frame().unwrap().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn playlist_rotates_between_and_within_holds() {
+        let music = playlist(3, false);
+        assert_eq!(
+            frames(&mut music.listen(), 5),
+            ["0a", "0b", "1a", "1b", "2a"]
+        );
+        // Every hold, and every track change within one, takes the next track
+        assert_eq!(frames(&mut music.listen(), 3), ["0a", "0b", "1a"]);
+        assert_eq!(frames(&mut music.listen(), 1), ["2a"]);
+    }
+
+    #[test]
+    fn shuffle_never_repeats_a_track() {
+        let music = playlist(3, true);
+        let mut listener = music.listen();
+        let tracks: Vec<String> = frames(&mut listener, 200).into_iter().step_by(2).collect();
+        assert!(tracks.windows(2).all(|pair| pair[0] != pair[1]));
+        for t in 0..3 {
+            assert!(tracks.contains(&format!("{t}a")), "track {t} never played");
+        }
+    }
+
+    #[test]
+    fn resampler_keeps_time_across_packets() {
+        let mut resampler = Resampler::new(44100, 8000);
+        let mut out = Vec::new();
+        for _ in 0..100 {
+            resampler.process(&[1000; 1152], &mut out);
+        }
+        let expected = 115_200 * 8000 / 44100;
+        assert!(out.len().abs_diff(expected) <= 1, "{}", out.len());
+        assert!(out[1..].iter().all(|&s| s == 1000));
+    }
+
+    #[test]
+    fn music_is_ducked_under_speech() {
+        let music = vec![audio::pcm_to_mulaw(8000); 4];
+        let silence = vec![audio::pcm_to_mulaw(0); 2];
+        // Music outlasting the speech stays ducked to the end of the frame
+        let mixed = duck_under(&music, &silence, 0.5);
+        assert_eq!(mixed.len(), 4);
+        for b in mixed {
+            assert!((3800..=4200).contains(&audio::mulaw_to_pcm(b)));
+        }
+        // No music: the speech as it was
+        let speech = vec![audio::pcm_to_mulaw(-3000); 3];
+        assert_eq!(duck_under(&[], &speech, 0.5), speech);
+    }
+
+    #[test]
+    fn content_types_hint_the_container() {
+        assert_eq!(extension_for("audio/mpeg"), Some("mp3"));
+        assert_eq!(
+            extension_for("application/ogg; charset=binary"),
+            Some("ogg")
+        );
+        assert_eq!(extension_for("audio/x-wav"), Some("wav"));
+        assert_eq!(extension_for("text/html"), None);
+    }
+
+    /// One second of 16kHz stereo tone as a WAV.
+    fn wav() -> Vec<u8> {
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 16000,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut buffer = Cursor::new(Vec::new());
+        let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+        for i in 0..16000 {
+            let t = i as f64 / 16000.0;
+            let sample = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 8000.0) as i16;
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        buffer.into_inner()
+    }
+
+    #[tokio::test]
+    async fn stream_is_transcoded_for_listeners() {
+        let body = wav();
+        let app = Router::new().route(
+            "/hold.wav",
+            get(|| async move { ([("content-type", "audio/wav")], body) }),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hold.wav", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let music = Arc::new(HoldMusic::Stream(LiveStream::start(
+            reqwest::Client::new(),
+            url,
+            1.0,
+            160,
+        )));
+        music.probe().await.unwrap();
+        let mut listener = music.listen();
+
+        // A second of audio is 50 frames, all decoded well within MAX_LEAD
+        let mut received = Vec::new();
+        tokio::time::timeout(Duration::from_secs(5), async {
+            while received.len() < 50 {
+                match listener.next_frame() {
+                    Some(payload) => received.push(payload),
+                    None => tokio::time::sleep(Duration::from_millis(10)).await,
+                }
+            }
+        })
+        .await
+        .expect("stream frames");
+        for payload in &received {
+            let mulaw = base64::engine::general_purpose::STANDARD
+                .decode(payload.as_bytes())
+                .unwrap();
+            assert_eq!(mulaw.len(), 160);
+        }
+        let loudness = audio::rms_energy(&audio::decode_mulaw(
+            &base64::engine::general_purpose::STANDARD
+                .decode(received[25].as_bytes())
+                .unwrap(),
+        ));
+        assert!(loudness > 1000.0, "{loudness}");
+    }
+}
@@ -0,0 +1,119 @@
+//! Caller language handling.
+//!
+//! Whisper's verbose transcription reports the detected language by English
+//! name ("spanish"); the rest of the pipeline uses ISO 639-1 codes ("es")
+//! for the STT hint, the voice mapping and the brain instruction.
+
+/// ISO 639-1 code and English name of the languages we name explicitly.
+const LANGUAGES: &[(&str, &str)] = &[
+    ("ar", "Arabic"),
+    ("ca", "Catalan"),
+    ("de", "German"),
+    ("en", "English"),
+    ("es", "Spanish"),
+    ("fr", "French"),
+    ("hi", "Hindi"),
+    ("it", "Italian"),
+    ("ja", "Japanese"),
+    ("ko", "Korean"),
+    ("nl", "Dutch"),
+    ("pl", "Polish"),
+    ("pt", "Portuguese"),
+    ("ru", "Russian"),
+    ("sv", "Swedish"),
+    ("tr", "Turkish"),
+    ("uk", "Ukrainian"),
+    ("zh", "Chinese"),
+];
+
+/// Normalize a language as reported by Whisper (a name or a code) to its
+/// ISO 639-1 code.
+pub fn code(language: &str) -> Option<String> {
+    let language = language.trim().to_ascii_lowercase();
+    if language.len() == 2 {
+        return Some(language);
+    }
+    LANGUAGES
+        .iter()
+        .find(|(_, name)| name.eq_ignore_ascii_case(&language))
+        .map(|(code, _)| code.to_string())
+}
+
+/// The language code of a locale ("es-MX" → "es").
+pub fn of_locale(locale: &str) -> String {
+    locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or(locale)
+        .to_ascii_lowercase()
+}
+
+/// English name of a language code, for prompts. Unknown codes are returned
+/// as given.
+pub fn name(code: &str) -> &str {
+    LANGUAGES
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map_or(code, |(_, name)| name)
+}
+
+/// Tell the brain which language to answer in.
+pub fn instruction(code: &str) -> String {
+    let name = name(code);
+    format!("The caller is speaking {name}. Respond in {name}.")
+}
+
+/// Tell the brain which language a code-switching caller mostly used this
+/// turn.
+pub fn code_switching_instruction(code: &str) -> String {
+    let name = name(code);
+    format!(
+        "The caller mixes languages; this turn was mostly {name}. \
+         Respond in {name}, keeping any words they used from other languages."
+    )
+}
+
+/// Tell the brain to act as an interpreter from `from` into `to`.
+pub fn translation(from: &str, to: &str) -> String {
+    format!(
+        "You are interpreting a phone call. Translate what the caller said from {} into {}. \
+         Reply with the translation only.",
+        name(from),
+        name(to)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn normalizes_whisper_languages() {
+        assert_eq!(code("spanish").as_deref(), Some("es"));
+        assert_eq!(code("English").as_deref(), Some("en"));
+        assert_eq!(code("es").as_deref(), Some("es"));
+        assert_eq!(code("klingon"), None);
+        assert_eq!(of_locale("es-MX"), "es");
+        assert_eq!(of_locale("pt_BR"), "pt");
+    }
+
+    #[test]
+    fn instruction_names_the_language() {
+        assert_eq!(
+            instruction("es"),
+            "The caller is speaking Spanish. Respond in Spanish."
+        );
+        assert_eq!(name("xx"), "xx");
+    }
+
+    #[test]
+    fn code_switching_instruction_names_the_turn_language() {
+        assert!(code_switching_instruction("es").contains("mostly Spanish"));
+    }
+
+    #[test]
+    fn translation_names_both_languages() {
+        let instruction = translation("es", "en");
+        assert!(instruction.contains("from Spanish into English"));
+    }
+}
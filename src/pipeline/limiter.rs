@@ -0,0 +1,234 @@
+//! Global admission control for pipeline turns.
+//!
+//! Bounds how many STT → brain → TTS turns run at once across all calls.
+//! Turns beyond the limit queue (the caller keeps hearing hold music) and
+//! are admitted in order of:
+//!
+//! 1. fewest turns already in flight for the same call, so one verbose
+//!    caller can't hold every slot while others wait,
+//! 2. short utterances before long ones, since quick replies ("yes", "no")
+//!    are cheap and the caller notices the delay most,
+//! 3. arrival order.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::oneshot;
+
+/// Shared limiter handle. Cheap to clone.
+#[derive(Clone)]
+pub struct TurnLimiter {
+    inner: Arc<Mutex<LimiterState>>,
+}
+
+struct LimiterState {
+    available: usize,
+    in_flight: HashMap<String, usize>,
+    waiters: Vec<Waiter>,
+    next_seq: u64,
+}
+
+struct Waiter {
+    call_sid: String,
+    short: bool,
+    seq: u64,
+    tx: oneshot::Sender<TurnPermit>,
+}
+
+/// A granted pipeline slot. The slot is released when the permit is dropped.
+pub struct TurnPermit {
+    call_sid: String,
+    limiter: Option<Arc<Mutex<LimiterState>>>,
+}
+
+impl TurnLimiter {
+    /// Create a limiter allowing `max_concurrent` turns at once.
+    /// A value of 0 disables the limit.
+    pub fn new(max_concurrent: usize) -> Self {
+        let available = if max_concurrent == 0 {
+            usize::MAX
+        } else {
+            max_concurrent
+        };
+        Self {
+            inner: Arc::new(Mutex::new(LimiterState {
+                available,
+                in_flight: HashMap::new(),
+                waiters: Vec::new(),
+                next_seq: 0,
+            })),
+        }
+    }
+
+    /// Wait for a pipeline slot for `call_sid`.
+    ///
+    /// `short` marks the utterance as a short turn, which is admitted ahead
+    /// of long turns when the limiter is saturated.
+    pub async fn acquire(&self, call_sid: &str, short: bool) -> TurnPermit {
+        let (tx, rx) = oneshot::channel();
+        {
+            let mut state = self.inner.lock().unwrap();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiters.push(Waiter {
+                call_sid: call_sid.to_string(),
+                short,
+                seq,
+                tx,
+            });
+            dispatch(&self.inner, &mut state);
+        }
+        // Waiters are only removed by `dispatch`, which always sends a permit.
+        rx.await.expect("turn limiter dropped a waiter")
+    }
+
+    /// Number of turns currently waiting for a slot.
+    pub fn queued(&self) -> usize {
+        self.inner.lock().unwrap().waiters.len()
+    }
+}
+
+impl Drop for TurnPermit {
+    fn drop(&mut self) {
+        let Some(limiter) = self.limiter.take() else {
+            return;
+        };
+        let mut state = limiter.lock().unwrap();
+        release(&mut state, &self.call_sid);
+        dispatch(&limiter, &mut state);
+    }
+}
+
+fn release(state: &mut LimiterState, call_sid: &str) {
+    state.available = state.available.saturating_add(1);
+    if let Some(count) = state.in_flight.get_mut(call_sid) {
+        *count -= 1;
+        if *count == 0 {
+            state.in_flight.remove(call_sid);
+        }
+    }
+}
+
+/// Hand free slots to the highest-priority waiters.
+fn dispatch(limiter: &Arc<Mutex<LimiterState>>, state: &mut LimiterState) {
+    while state.available > 0 && !state.waiters.is_empty() {
+        let idx = state
+            .waiters
+            .iter()
+            .enumerate()
+            .min_by_key(|(_, w)| {
+                let in_flight = state.in_flight.get(&w.call_sid).copied().unwrap_or(0);
+                (in_flight, !w.short, w.seq)
+            })
+            .map(|(i, _)| i)
+            .unwrap_or(0);
+        let waiter = state.waiters.swap_remove(idx);
+
+        state.available -= 1;
+        *state.in_flight.entry(waiter.call_sid.clone()).or_insert(0) += 1;
+
+        let permit = TurnPermit {
+            call_sid: waiter.call_sid,
+            limiter: Some(Arc::clone(limiter)),
+        };
+        if let Err(mut permit) = waiter.tx.send(permit) {
+            // The waiting turn was cancelled. Disarm the permit so its Drop
+            // doesn't re-lock the state we're holding, and return the slot.
+            permit.limiter = None;
+            release(state, &permit.call_sid);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    async fn try_acquire(
+        limiter: &TurnLimiter,
+        call_sid: &str,
+        short: bool,
+    ) -> tokio::task::JoinHandle<TurnPermit> {
+        let limiter = limiter.clone();
+        let call_sid = call_sid.to_string();
+        let handle = tokio::spawn(async move { limiter.acquire(&call_sid, short).await });
+        // Let the task enqueue itself
+        tokio::time::sleep(Duration::from_millis(10)).await;
+        handle
+    }
+
+    #[tokio::test]
+    async fn admits_up_to_limit() {
+        let limiter = TurnLimiter::new(2);
+        let _a = limiter.acquire("a", false).await;
+        let _b = limiter.acquire("b", false).await;
+        let c = try_acquire(&limiter, "c", false).await;
+        assert!(!c.is_finished());
+        assert_eq!(limiter.queued(), 1);
+        drop(_a);
+        tokio::time::timeout(Duration::from_secs(1), c)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn short_turns_jump_the_queue() {
+        let limiter = TurnLimiter::new(1);
+        let held = limiter.acquire("a", false).await;
+        let long = try_acquire(&limiter, "b", false).await;
+        let short = try_acquire(&limiter, "c", true).await;
+        drop(held);
+        let permit = tokio::time::timeout(Duration::from_secs(1), short)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!long.is_finished());
+        drop(permit);
+        tokio::time::timeout(Duration::from_secs(1), long)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn busy_call_yields_to_other_calls() {
+        let limiter = TurnLimiter::new(2);
+        let _verbose = limiter.acquire("verbose", true).await;
+        let held = limiter.acquire("quiet", false).await;
+        // The verbose caller queues a short turn first, then another call
+        // queues a long one. The other call still goes first.
+        let verbose_again = try_acquire(&limiter, "verbose", true).await;
+        let other = try_acquire(&limiter, "other", false).await;
+        drop(held);
+        tokio::time::timeout(Duration::from_secs(1), other)
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(!verbose_again.is_finished());
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_returns_slot() {
+        let limiter = TurnLimiter::new(1);
+        let held = limiter.acquire("a", false).await;
+        let cancelled = try_acquire(&limiter, "b", false).await;
+        cancelled.abort();
+        let _ = cancelled.await;
+        drop(held);
+        tokio::time::timeout(Duration::from_secs(1), limiter.acquire("c", false))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn zero_means_unlimited() {
+        let limiter = TurnLimiter::new(0);
+        let mut permits = Vec::new();
+        for i in 0..100 {
+            permits.push(limiter.acquire(&i.to_string(), false).await);
+        }
+        assert_eq!(limiter.queued(), 0);
+    }
+}
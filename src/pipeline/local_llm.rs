@@ -0,0 +1,359 @@
+//! Brain running a model in-process (`[brain] provider = "local_llm"`).
+//!
+//! A quantized llama-family model (GGUF) is loaded once at startup and run
+//! on the CPU with candle: no subprocess, no network. Answers are slower
+//! and simpler than a hosted model's, but a call still gets one with the
+//! machine offline. Built with `--features local-llm`.
+
+use std::fs::File;
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+
+use candle_core::quantized::gguf_file;
+use candle_core::{Device, Tensor};
+use candle_transformers::generation::LogitsProcessor;
+use candle_transformers::models::quantized_llama::ModelWeights;
+use rand::Rng;
+use tokenizers::Tokenizer;
+
+use crate::config::{BrainProvider, LocalLlmConfig, PromptFormat};
+use crate::pipeline::chat::{ChatError, ChatFuture, ChatProvider};
+use crate::pipeline::history::ChatMessage;
+use crate::rng::SharedRng;
+
+pub struct LocalLlm {
+    engine: Arc<Engine>,
+    /// The model file's name, for logs.
+    model: String,
+}
+
+/// The loaded model. One generation at a time: it's CPU-bound, and the
+/// model's KV cache belongs to the generation using it.
+struct Engine {
+    model: Mutex<ModelWeights>,
+    tokenizer: Tokenizer,
+    device: Device,
+    format: PromptFormat,
+    context_length: usize,
+    temperature: f64,
+    /// Tokens ending the answer.
+    stop: Vec<u32>,
+    /// Seeds each generation's sampler; pinned by `[debug] seed`.
+    rng: SharedRng,
+}
+
+impl LocalLlm {
+    /// Load the model and tokenizer. Takes a while for large models.
+    /// Sampling is seeded from `seed` (`[debug] seed`), if set.
+    pub fn load(config: &LocalLlmConfig, seed: Option<u64>) -> Result<Self, LoadError> {
+        let device = Device::Cpu;
+        let mut file = File::open(&config.model_path)
+            .map_err(|e| LoadError(config.model_path.clone(), e.to_string()))?;
+        let content = gguf_file::Content::read(&mut file)
+            .map_err(|e| LoadError(config.model_path.clone(), e.to_string()))?;
+        let model = ModelWeights::from_gguf(content, &mut file, &device)
+            .map_err(|e| LoadError(config.model_path.clone(), e.to_string()))?;
+        let tokenizer = Tokenizer::from_file(&config.tokenizer_path)
+            .map_err(|e| LoadError(config.tokenizer_path.clone(), e.to_string()))?;
+
+        let stop: Vec<u32> = config
+            .prompt_format
+            .stop_tokens()
+            .iter()
+            .filter_map(|token| tokenizer.token_to_id(token))
+            .collect();
+        if stop.is_empty() {
+            return Err(LoadError(
+                config.tokenizer_path.clone(),
+                format!(
+                    "no {:?} stop token in the tokenizer; check local_llm.prompt_format",
+                    config.prompt_format
+                ),
+            ));
+        }
+        tracing::info!(model = %config.model_path.display(), "Loaded local model");
+
+        Ok(Self {
+            engine: Arc::new(Engine {
+                model: Mutex::new(model),
+                tokenizer,
+                device,
+                format: config.prompt_format,
+                context_length: config.context_length,
+                temperature: config.temperature,
+                stop,
+                rng: SharedRng::new(seed),
+            }),
+            model: config
+                .model_path
+                .file_name()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .into_owned(),
+        })
+    }
+
+    async fn generate(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<String, ChatError> {
+        let engine = Arc::clone(&self.engine);
+        let system_prompt = system_prompt.to_string();
+        let messages = messages.to_vec();
+        cancellable(move |cancelled| {
+            engine.generate(&system_prompt, messages, max_tokens as usize, cancelled)
+        })
+        .await
+    }
+}
+
+/// Run `generate` on the blocking pool with a flag that's set once the
+/// returned future is dropped, e.g. when the turn's budget runs out, so the
+/// generation stops instead of holding the model until `max_tokens`.
+async fn cancellable<T: Send + 'static>(
+    generate: impl FnOnce(&AtomicBool) -> Result<T, ChatError> + Send + 'static,
+) -> Result<T, ChatError> {
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let _cancel = CancelOnDrop(Arc::clone(&cancelled));
+    tokio::task::spawn_blocking(move || generate(&cancelled))
+        .await
+        .map_err(|e| ChatError::Inference(e.to_string()))?
+}
+
+/// Sets its flag when dropped.
+struct CancelOnDrop(Arc<AtomicBool>);
+
+impl Drop for CancelOnDrop {
+    fn drop(&mut self) {
+        self.0.store(true, Ordering::Relaxed);
+    }
+}
+
+/// The answer to `prompt`, holding `model` throughout: `step` feeds tokens
+/// in at a position and samples the next one, until a `stop` token,
+/// `max_tokens`, or `cancelled` is set.
+fn answer_tokens<M>(
+    model: &Mutex<M>,
+    prompt: &[u32],
+    stop: &[u32],
+    max_tokens: usize,
+    cancelled: &AtomicBool,
+    mut step: impl FnMut(&mut M, &[u32], usize) -> Result<u32, ChatError>,
+) -> Result<Vec<u32>, ChatError> {
+    let mut model = model.lock().unwrap_or_else(PoisonError::into_inner);
+    let mut next = step(&mut model, prompt, 0)?;
+    let mut answer = Vec::new();
+    while !stop.contains(&next) && answer.len() < max_tokens {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(ChatError::Inference("generation cancelled".into()));
+        }
+        answer.push(next);
+        let at = prompt.len() + answer.len() - 1;
+        next = step(&mut model, &[next], at)?;
+    }
+    Ok(answer)
+}
+
+impl ChatProvider for LocalLlm {
+    fn brain(&self) -> BrainProvider {
+        BrainProvider::LocalLlm
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String> {
+        Box::pin(self.generate(system_prompt, messages, max_tokens))
+    }
+
+    /// Loaded at startup, or there'd be no brain to check.
+    fn probe(&self) -> ChatFuture<'_, ()> {
+        Box::pin(async { Ok(()) })
+    }
+
+    fn hint(&self) -> &'static str {
+        "check local_llm.model_path"
+    }
+}
+
+impl Engine {
+    fn generate(
+        &self,
+        system_prompt: &str,
+        mut messages: Vec<ChatMessage>,
+        max_tokens: usize,
+        cancelled: &AtomicBool,
+    ) -> Result<String, ChatError> {
+        // Oldest turns go first when the conversation outgrows the context
+        let budget = self.context_length.saturating_sub(max_tokens);
+        let tokens = loop {
+            let prompt = self.format.render(system_prompt, &messages);
+            let tokens = self
+                .tokenizer
+                .encode(prompt, false)
+                .map_err(|e| ChatError::Inference(e.to_string()))?
+                .get_ids()
+                .to_vec();
+            if tokens.len() <= budget {
+                break tokens;
+            }
+            if messages.len() <= 1 {
+                return Err(ChatError::TooLong(tokens.len(), budget));
+            }
+            messages.drain(..2.min(messages.len() - 1));
+        };
+
+        let seed = self.rng.with(|rng| rng.gen());
+        let mut sampler = LogitsProcessor::new(seed, Some(self.temperature), None);
+        let answer = answer_tokens(
+            &self.model,
+            &tokens,
+            &self.stop,
+            max_tokens,
+            cancelled,
+            |model, tokens, at| self.step(model, &mut sampler, tokens, at),
+        )?;
+
+        let text = self
+            .tokenizer
+            .decode(&answer, true)
+            .map_err(|e| ChatError::Inference(e.to_string()))?;
+        let text = text.trim();
+        if text.is_empty() {
+            return Err(ChatError::Empty("local model"));
+        }
+        Ok(text.to_string())
+    }
+
+    /// Feed `tokens` in at position `at` and sample the next one.
+    fn step(
+        &self,
+        model: &mut ModelWeights,
+        sampler: &mut LogitsProcessor,
+        tokens: &[u32],
+        at: usize,
+    ) -> Result<u32, ChatError> {
+        let inference = |e: candle_core::Error| ChatError::Inference(e.to_string());
+        let input = Tensor::new(tokens, &self.device)
+            .and_then(|t| t.unsqueeze(0))
+            .map_err(inference)?;
+        let logits = model
+            .forward(&input, at)
+            .and_then(|l| l.squeeze(0))
+            .map_err(inference)?;
+        sampler.sample(&logits).map_err(inference)
+    }
+}
+
+impl PromptFormat {
+    /// The model's own chat markup around the conversation, ending where
+    /// the assistant's answer starts.
+    fn render(self, system_prompt: &str, messages: &[ChatMessage]) -> String {
+        let mut prompt = String::new();
+        match self {
+            PromptFormat::ChatMl => {
+                let mut turn = |role: &str, content: &str| {
+                    prompt.push_str(&format!("<|im_start|>{role}\n{content}<|im_end|>\n"));
+                };
+                if !system_prompt.is_empty() {
+                    turn("system", system_prompt);
+                }
+                for message in messages {
+                    turn(message.role.as_str(), &message.content);
+                }
+                prompt.push_str("<|im_start|>assistant\n");
+            }
+            PromptFormat::Llama3 => {
+                prompt.push_str("<|begin_of_text|>");
+                let mut turn = |role: &str, content: &str| {
+                    prompt.push_str(&format!(
+                        "<|start_header_id|>{role}<|end_header_id|>\n\n{content}<|eot_id|>"
+                    ));
+                };
+                if !system_prompt.is_empty() {
+                    turn("system", system_prompt);
+                }
+                for message in messages {
+                    turn(message.role.as_str(), &message.content);
+                }
+                prompt.push_str("<|start_header_id|>assistant<|end_header_id|>\n\n");
+            }
+        }
+        prompt
+    }
+
+    fn stop_tokens(self) -> &'static [&'static str] {
+        match self {
+            PromptFormat::ChatMl => &["<|im_end|>", "<|endoftext|>"],
+            PromptFormat::Llama3 => &["<|eot_id|>", "<|end_of_text|>"],
+        }
+    }
+}
+
+/// The model or tokenizer couldn't be loaded.
+#[derive(Debug, thiserror::Error)]
+#[error("can't load {0}: {1}")]
+pub struct LoadError(PathBuf, String);
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[tokio::test]
+    async fn cancelled_generation_releases_the_model() {
+        let model = Arc::new(Mutex::new(()));
+        let held = Arc::clone(&model);
+        // Never reaches a stop token on its own
+        let generation = cancellable(move |cancelled| {
+            answer_tokens(&held, &[0], &[1], usize::MAX, cancelled, |_, _, _| {
+                std::thread::sleep(Duration::from_millis(1));
+                Ok(0)
+            })
+        });
+        // The turn's budget runs out mid-answer
+        let timed_out = tokio::time::timeout(Duration::from_millis(50), generation).await;
+        assert!(timed_out.is_err());
+
+        let released = async {
+            while model.try_lock().is_err() {
+                tokio::time::sleep(Duration::from_millis(10)).await;
+            }
+        };
+        tokio::time::timeout(Duration::from_secs(1), released)
+            .await
+            .expect("model still held after the turn gave up");
+    }
+
+    #[test]
+    fn renders_the_chat_markup() {
+        let messages = [
+            ChatMessage::user("hi"),
+            ChatMessage::assistant("Hello!"),
+            ChatMessage::user("bye"),
+        ];
+        assert_eq!(
+            PromptFormat::ChatMl.render("You are Echo.", &messages),
+            "<|im_start|>system\nYou are Echo.<|im_end|>\n\
+             <|im_start|>user\nhi<|im_end|>\n\
+             <|im_start|>assistant\nHello!<|im_end|>\n\
+             <|im_start|>user\nbye<|im_end|>\n\
+             <|im_start|>assistant\n"
+        );
+        assert_eq!(
+            PromptFormat::Llama3.render("", &messages[..1]),
+            "<|begin_of_text|><|start_header_id|>user<|end_header_id|>\n\nhi<|eot_id|>\
+             <|start_header_id|>assistant<|end_header_id|>\n\n"
+        );
+    }
+}
@@ -0,0 +1,121 @@
+//! Hooks around the brain, for voice-echo used as a library.
+//!
+//! Middleware registered with [`VoiceEcho::with_middleware`] sees every
+//! conversation turn: `before` can rewrite what the brain is asked (inject
+//! retrieved context, scrub PII), `after` what the caller hears back. Hooks
+//! run in the order they were registered. While any middleware is
+//! registered, streaming bridge-echo answers are collected whole first, so
+//! `after` sees the full response before any of it is spoken.
+//!
+//! [`VoiceEcho::with_middleware`]: crate::VoiceEcho::with_middleware
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+
+/// What a hook returns: the work it does, run before the turn goes on.
+pub type Hook<'a> = Pin<Box<dyn Future<Output = ()> + Send + 'a>>;
+
+/// What the brain is about to be asked.
+#[derive(Debug, Clone)]
+pub struct BrainRequest {
+    pub call_sid: String,
+    /// The caller's words.
+    pub transcript: String,
+    /// Call context shown to the brain alongside them, if any.
+    pub context: Option<String>,
+}
+
+/// A hook around the brain. Both methods default to doing nothing.
+pub trait BrainMiddleware: Send + Sync {
+    /// Inspect or change the request before the brain sees it.
+    fn before<'a>(&'a self, _request: &'a mut BrainRequest) -> Hook<'a> {
+        Box::pin(async {})
+    }
+
+    /// Inspect or change the brain's response before it's spoken.
+    fn after<'a>(&'a self, _call_sid: &'a str, _response: &'a mut String) -> Hook<'a> {
+        Box::pin(async {})
+    }
+}
+
+/// The registered middleware, in order. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct Middleware {
+    hooks: Vec<Arc<dyn BrainMiddleware>>,
+}
+
+impl Middleware {
+    pub fn push(&mut self, hook: Arc<dyn BrainMiddleware>) {
+        self.hooks.push(hook);
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hooks.is_empty()
+    }
+
+    pub async fn before(&self, request: &mut BrainRequest) {
+        for hook in &self.hooks {
+            hook.before(request).await;
+        }
+    }
+
+    pub async fn after(&self, call_sid: &str, response: &mut String) {
+        for hook in &self.hooks {
+            hook.after(call_sid, response).await;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Rag;
+
+    impl BrainMiddleware for Rag {
+        fn before<'a>(&'a self, request: &'a mut BrainRequest) -> Hook<'a> {
+            Box::pin(async move {
+                request.context = Some(format!("Order #42 shipped. {}", request.transcript));
+            })
+        }
+    }
+
+    struct Scrub(&'static str);
+
+    impl BrainMiddleware for Scrub {
+        fn before<'a>(&'a self, request: &'a mut BrainRequest) -> Hook<'a> {
+            Box::pin(async move {
+                request.transcript = request.transcript.replace(self.0, "[redacted]");
+            })
+        }
+
+        fn after<'a>(&'a self, _call_sid: &'a str, response: &'a mut String) -> Hook<'a> {
+            Box::pin(async move { *response = response.replace(self.0, "[redacted]") })
+        }
+    }
+
+    #[tokio::test]
+    async fn runs_hooks_in_order() {
+        let mut middleware = Middleware::default();
+        middleware.push(Arc::new(Scrub("4111")));
+        middleware.push(Arc::new(Rag));
+
+        let mut request = BrainRequest {
+            call_sid: "CA1".into(),
+            transcript: "my card is 4111".into(),
+            context: None,
+        };
+        middleware.before(&mut request).await;
+        assert_eq!(request.transcript, "my card is [redacted]");
+        // Rag ran second, on the scrubbed words
+        assert_eq!(
+            request.context.as_deref(),
+            Some("Order #42 shipped. my card is [redacted]")
+        );
+
+        let mut response = "Card 4111 noted.".to_string();
+        middleware.after("CA1", &mut response).await;
+        assert_eq!(response, "Card [redacted] noted.");
+    }
+}
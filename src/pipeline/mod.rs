@@ -1,7 +1,43 @@
+pub mod actions;
+pub mod anthropic;
+pub mod assemblyai;
 pub mod audio;
+pub mod azure;
+pub mod azure_tts;
 pub mod bridge;
+pub mod bridge_auth;
+pub mod chat;
+pub mod concise;
 pub mod conversation;
+pub mod deepgram;
+pub mod degraded;
+pub mod diarize;
+pub mod dictation;
+pub mod directives;
+pub mod elevenlabs;
+pub mod failover;
+pub mod filter;
+pub mod frames;
+pub mod gemini;
+pub mod history;
+pub mod hold_music;
+pub mod language;
+pub mod limiter;
+#[cfg(feature = "local-llm")]
+pub mod local_llm;
+pub mod middleware;
 pub mod notify;
+pub mod ollama;
+pub mod openai;
+pub mod openai_tts;
+pub mod privacy;
+pub mod profanity;
+pub mod prompt;
+pub mod queue;
+pub mod sentences;
 pub mod stt;
+pub mod transliterate;
 pub mod tts;
+pub mod tts_cache;
+pub mod turn;
 pub mod vad;
@@ -3,26 +3,45 @@
 //! Used by both Twilio and Discord stream handlers to notify bridge-echo
 //! of session lifecycle events for cross-channel routing.
 
+use std::time::Duration;
+
+use crate::pipeline::bridge_auth::BridgeAuth;
+
+/// Notifications are best-effort; don't hold up call setup or teardown.
+const NOTIFY_TIMEOUT: Duration = Duration::from_secs(5);
+
+async fn post(
+    client: &reqwest::Client,
+    url: &str,
+    auth: &BridgeAuth,
+    body: &serde_json::Value,
+) -> reqwest::Result<reqwest::Response> {
+    let bytes = body.to_string().into_bytes();
+    auth.apply(client.post(url), &bytes)
+        .timeout(NOTIFY_TIMEOUT)
+        .header(reqwest::header::CONTENT_TYPE, "application/json")
+        .body(bytes)
+        .send()
+        .await
+}
+
 /// Notify bridge-echo that a voice session started so it can pre-register
 /// for cross-channel routing before any voice utterance flows through.
 pub async fn notify_session_started(
+    client: &reqwest::Client,
     bridge_url: &str,
+    auth: &BridgeAuth,
     call_sid: &str,
     sender: &str,
     transport: &str,
 ) {
     let url = format!("{}/session-started", bridge_url.trim_end_matches('/'));
-    let client = reqwest::Client::new();
-    match client
-        .post(&url)
-        .json(&serde_json::json!({
-            "call_sid": call_sid,
-            "sender": sender,
-            "transport": transport,
-        }))
-        .send()
-        .await
-    {
+    let body = serde_json::json!({
+        "call_sid": call_sid,
+        "sender": sender,
+        "transport": transport,
+    });
+    match post(client, &url, auth, &body).await {
         Ok(resp) if resp.status().is_success() => {
             tracing::debug!(call_sid, "Notified bridge-echo of session start");
         }
@@ -44,15 +63,15 @@ pub async fn notify_session_started(
 
 /// Notify bridge-echo that a voice session ended so it stops routing
 /// cross-channel responses to voice.
-pub async fn notify_call_ended(bridge_url: &str, call_sid: &str) {
+pub async fn notify_call_ended(
+    client: &reqwest::Client,
+    bridge_url: &str,
+    auth: &BridgeAuth,
+    call_sid: &str,
+) {
     let url = format!("{}/call-ended", bridge_url.trim_end_matches('/'));
-    let client = reqwest::Client::new();
-    match client
-        .post(&url)
-        .json(&serde_json::json!({ "call_sid": call_sid }))
-        .send()
-        .await
-    {
+    let body = serde_json::json!({ "call_sid": call_sid });
+    match post(client, &url, auth, &body).await {
         Ok(resp) if resp.status().is_success() => {
             tracing::debug!(call_sid, "Notified bridge-echo of session end");
         }
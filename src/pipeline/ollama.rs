@@ -0,0 +1,251 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BrainProvider, OllamaConfig};
+use crate::pipeline::chat::{ChatError, ChatFuture, ChatProvider};
+use crate::pipeline::history::ChatMessage;
+
+/// A local Ollama server (`[brain] provider = "ollama"`), for setups where
+/// nothing may leave the premises. Talks to `/api/chat` without streaming.
+pub struct OllamaChat {
+    client: reqwest::Client,
+    base_url: String,
+    model: String,
+    keep_alive: Option<String>,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<RequestMessage<'a>>,
+    stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<&'a str>,
+    options: ChatOptions,
+}
+
+#[derive(Debug, Serialize)]
+struct RequestMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatOptions {
+    /// Ollama's name for max tokens.
+    num_predict: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct ErrorResponse {
+    error: String,
+}
+
+impl OllamaChat {
+    pub fn new(client: reqwest::Client, config: &OllamaConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            model: config.model.clone(),
+            keep_alive: config.keep_alive.clone(),
+            timeout: (config.timeout_ms > 0).then(|| Duration::from_millis(config.timeout_ms)),
+        }
+    }
+
+    /// Reachability check: the server answers and has the model pulled.
+    async fn tags(&self) -> Result<(), ChatError> {
+        #[derive(Deserialize)]
+        struct Tags {
+            models: Vec<Tag>,
+        }
+        #[derive(Deserialize)]
+        struct Tag {
+            name: String,
+        }
+
+        let mut req = self.client.get(format!("{}/api/tags", self.base_url));
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(ChatError::Api(resp.status().to_string()));
+        }
+        let tags: Tags = resp
+            .json()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        // "llama3.2" is stored as "llama3.2:latest"
+        let pulled = tags.models.iter().any(|tag| {
+            tag.name == self.model || tag.name.strip_suffix(":latest") == Some(self.model.as_str())
+        });
+        if !pulled {
+            return Err(ChatError::Api(format!(
+                "model '{}' is not pulled",
+                self.model
+            )));
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<String, ChatError> {
+        let system = (!system_prompt.is_empty()).then_some(RequestMessage {
+            role: "system",
+            content: system_prompt,
+        });
+        let body = ChatRequest {
+            model: &self.model,
+            messages: system
+                .into_iter()
+                .chain(messages.iter().map(|m| RequestMessage {
+                    role: m.role.as_str(),
+                    content: &m.content,
+                }))
+                .collect(),
+            stream: false,
+            keep_alive: self.keep_alive.as_deref(),
+            options: ChatOptions {
+                num_predict: max_tokens,
+            },
+        };
+
+        let mut req = self
+            .client
+            .post(format!("{}/api/chat", self.base_url))
+            .json(&body);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            // Ollama explains itself in {"error": "..."}
+            let detail = serde_json::from_str::<ErrorResponse>(&body).map_or(body, |e| e.error);
+            return Err(ChatError::Api(format!("{status}: {detail}")));
+        }
+
+        let result: ChatResponse = resp
+            .json()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        Ok(result.message.content)
+    }
+}
+
+impl ChatProvider for OllamaChat {
+    fn brain(&self) -> BrainProvider {
+        BrainProvider::Ollama
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String> {
+        Box::pin(self.request(system_prompt, messages, max_tokens))
+    }
+
+    fn probe(&self) -> ChatFuture<'_, ()> {
+        Box::pin(self.tags())
+    }
+
+    fn hint(&self) -> &'static str {
+        "is Ollama running at ollama.base_url, with ollama.model pulled?"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::pipeline::chat::fixture::{converse, Fixture};
+
+    fn reply(text: &str) -> (StatusCode, serde_json::Value) {
+        let message = json!({ "role": "assistant", "content": text });
+        (StatusCode::OK, json!({ "message": message, "done": true }))
+    }
+
+    fn ollama(base_url: &str, model: &str) -> OllamaChat {
+        let config: OllamaConfig =
+            toml::from_str(&format!("model = {model:?}\nbase_url = \"{base_url}/\"")).unwrap();
+        OllamaChat::new(reqwest::Client::new(), &config)
+    }
+
+    #[tokio::test]
+    async fn maps_the_conversation() {
+        let api = Fixture::serve(vec![
+            reply("reply 1"),
+            (
+                StatusCode::SERVICE_UNAVAILABLE,
+                json!({ "error": "model is loading" }),
+            ),
+            reply("reply 3"),
+        ])
+        .await;
+
+        let err = converse(Box::new(ollama(&api.url, "llama3.2"))).await;
+        assert_eq!(
+            err.to_string(),
+            "API error: 503 Service Unavailable: model is loading"
+        );
+
+        let requests = api.requests();
+        assert_eq!(requests[0].path, "/api/chat");
+        assert_eq!(requests[0].body["model"], "llama3.2");
+        assert_eq!(requests[0].body["stream"], false);
+        assert_eq!(requests[0].body["options"]["num_predict"], 256);
+        assert_eq!(
+            requests[2].body["messages"],
+            json!([
+                { "role": "system", "content": "You are Sentinel." },
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": "reply 1" },
+                { "role": "user", "content": "again" },
+            ])
+        );
+    }
+
+    #[tokio::test]
+    async fn probe_requires_the_model_to_be_pulled() {
+        let tags = json!({ "models": [{ "name": "llama3.2:latest" }] });
+        let api =
+            Fixture::serve(vec![(StatusCode::OK, tags.clone()), (StatusCode::OK, tags)]).await;
+        assert!(ollama(&api.url, "llama3.2").probe().await.is_ok());
+        let err = ollama(&api.url, "qwen2.5").probe().await.unwrap_err();
+        assert_eq!(err.to_string(), "API error: model 'qwen2.5' is not pulled");
+        assert_eq!(api.requests()[0].path, "/api/tags");
+    }
+}
@@ -0,0 +1,224 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::{BrainProvider, GrokConfig, OpenAiConfig};
+use crate::pipeline::chat::{ChatError, ChatFuture, ChatProvider};
+use crate::pipeline::history::ChatMessage;
+
+/// OpenAI Chat Completions (`[brain] provider = "openai"`). Works with any
+/// server speaking the same API via `[openai] base_url`.
+pub struct OpenAiChat {
+    /// OpenAI itself, or Grok.
+    brain: BrainProvider,
+    client: reqwest::Client,
+    url: String,
+    api_key: String,
+    model: String,
+    timeout: Option<Duration>,
+}
+
+#[derive(Debug, Serialize)]
+struct CompletionRequest<'a> {
+    model: &'a str,
+    messages: Vec<RequestMessage<'a>>,
+    max_tokens: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct RequestMessage<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Deserialize)]
+struct CompletionResponse {
+    choices: Vec<Choice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Choice {
+    message: ResponseMessage,
+}
+
+#[derive(Debug, Deserialize)]
+struct ResponseMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
+impl OpenAiChat {
+    pub fn new(client: reqwest::Client, config: &OpenAiConfig) -> Self {
+        Self {
+            brain: BrainProvider::OpenAi,
+            client,
+            url: format!("{}/chat/completions", config.base_url.trim_end_matches('/')),
+            api_key: config.api_key.clone(),
+            model: config.model.clone(),
+            timeout: (config.timeout_ms > 0).then(|| Duration::from_millis(config.timeout_ms)),
+        }
+    }
+
+    /// xAI's Grok (`[brain] provider = "grok"`), which speaks the same API.
+    pub fn grok(client: reqwest::Client, config: &GrokConfig) -> Self {
+        Self {
+            brain: BrainProvider::Grok,
+            ..Self::new(client, &config.as_openai())
+        }
+    }
+
+    /// Credential check: list models.
+    async fn models(&self) -> Result<(), ChatError> {
+        let url = self.url.trim_end_matches("/chat/completions");
+        let mut req = self
+            .client
+            .get(format!("{url}/models"))
+            .bearer_auth(&self.api_key);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(ChatError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn request(
+        &self,
+        system_prompt: &str,
+        messages: &[ChatMessage],
+        max_tokens: u32,
+    ) -> Result<String, ChatError> {
+        let system = (!system_prompt.is_empty()).then_some(RequestMessage {
+            role: "system",
+            content: system_prompt,
+        });
+        let body = CompletionRequest {
+            model: &self.model,
+            messages: system
+                .into_iter()
+                .chain(messages.iter().map(|m| RequestMessage {
+                    role: m.role.as_str(),
+                    content: &m.content,
+                }))
+                .collect(),
+            max_tokens,
+        };
+
+        let mut req = self
+            .client
+            .post(&self.url)
+            .bearer_auth(&self.api_key)
+            .json(&body);
+        if let Some(timeout) = self.timeout {
+            req = req.timeout(timeout);
+        }
+        let resp = req
+            .send()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(ChatError::Api(format!("{status}: {body}")));
+        }
+
+        let result: CompletionResponse = resp
+            .json()
+            .await
+            .map_err(|e| ChatError::Request(e.to_string()))?;
+        result
+            .choices
+            .into_iter()
+            .next()
+            .and_then(|choice| choice.message.content)
+            .ok_or(ChatError::Empty("OpenAI"))
+    }
+}
+
+impl ChatProvider for OpenAiChat {
+    fn brain(&self) -> BrainProvider {
+        self.brain
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn complete<'a>(
+        &'a self,
+        system_prompt: &'a str,
+        messages: &'a [ChatMessage],
+        max_tokens: u32,
+    ) -> ChatFuture<'a, String> {
+        Box::pin(self.request(system_prompt, messages, max_tokens))
+    }
+
+    fn probe(&self) -> ChatFuture<'_, ()> {
+        Box::pin(self.models())
+    }
+
+    fn hint(&self) -> &'static str {
+        match self.brain {
+            BrainProvider::Grok => "check grok.api_key",
+            _ => "check openai.api_key and openai.base_url",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::http::StatusCode;
+    use serde_json::json;
+
+    use crate::pipeline::chat::fixture::{converse, Fixture};
+
+    fn reply(text: &str) -> (StatusCode, serde_json::Value) {
+        let message = json!({ "role": "assistant", "content": text });
+        (
+            StatusCode::OK,
+            json!({ "choices": [{ "message": message }] }),
+        )
+    }
+
+    #[tokio::test]
+    async fn maps_the_conversation() {
+        let api = Fixture::serve(vec![
+            reply("reply 1"),
+            (StatusCode::TOO_MANY_REQUESTS, json!({})),
+            reply("reply 3"),
+        ])
+        .await;
+        let config: OpenAiConfig = toml::from_str(&format!(
+            "api_key = \"sk-test\"\nmodel = \"gpt-test\"\nbase_url = \"{}/v1/\"",
+            api.url
+        ))
+        .unwrap();
+
+        let err = converse(Box::new(OpenAiChat::new(reqwest::Client::new(), &config))).await;
+        assert_eq!(err.to_string(), "API error: 429 Too Many Requests: {}");
+
+        let requests = api.requests();
+        assert_eq!(requests[0].path, "/v1/chat/completions");
+        assert_eq!(requests[0].headers["authorization"], "Bearer sk-test");
+        assert_eq!(requests[0].body["model"], "gpt-test");
+        assert_eq!(requests[0].body["max_tokens"], 256);
+        // The failed turn isn't remembered
+        assert_eq!(
+            requests[2].body["messages"],
+            json!([
+                { "role": "system", "content": "You are Sentinel." },
+                { "role": "user", "content": "hi" },
+                { "role": "assistant", "content": "reply 1" },
+                { "role": "user", "content": "again" },
+            ])
+        );
+    }
+}
@@ -0,0 +1,157 @@
+use crate::config::OpenAiConfig;
+use crate::pipeline::audio;
+use crate::pipeline::tts::{TtsError, TtsFuture, TtsProvider};
+
+/// OpenAI speech (`[tts] provider = "openai"`). It has no mu-law: replies
+/// come as raw 24kHz PCM and are brought down to 8kHz here.
+pub struct OpenAiTts {
+    client: reqwest::Client,
+    base_url: String,
+    api_key: String,
+    model: String,
+}
+
+/// Sample rate of the `pcm` response format.
+const PCM_SAMPLE_RATE: u32 = 24_000;
+
+/// OpenAI's per-request character limit.
+const MAX_CHARS: usize = 4096;
+
+impl OpenAiTts {
+    pub fn new(client: reqwest::Client, api_key: String, config: &OpenAiConfig) -> Self {
+        Self {
+            client,
+            base_url: config.base_url.trim_end_matches('/').to_string(),
+            api_key,
+            model: config.tts_model.clone(),
+        }
+    }
+
+    async fn request(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        let body = serde_json::json!({
+            "model": &self.model,
+            "input": text,
+            "voice": voice_id,
+            "response_format": "pcm",
+        });
+
+        let resp = self
+            .client
+            .post(format!("{}/audio/speech", self.base_url))
+            .bearer_auth(&self.api_key)
+            .json(&body)
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(TtsError::Api(format!("{status}: {body}")));
+        }
+
+        let bytes = resp
+            .bytes()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        // 16-bit little-endian mono
+        let pcm: Vec<i16> = bytes
+            .chunks_exact(2)
+            .map(|b| i16::from_le_bytes([b[0], b[1]]))
+            .collect();
+        Ok(audio::pcm_to_telephony(&pcm, PCM_SAMPLE_RATE))
+    }
+
+    /// Credential check: look up the model.
+    async fn model_info(&self) -> Result<(), TtsError> {
+        let resp = self
+            .client
+            .get(format!("{}/models/{}", self.base_url, self.model))
+            .bearer_auth(&self.api_key)
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(TtsError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+}
+
+impl TtsProvider for OpenAiTts {
+    fn name(&self) -> &'static str {
+        "openai"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn max_chars(&self) -> usize {
+        MAX_CHARS
+    }
+
+    fn synthesize<'a>(&'a self, text: &'a str, voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+        Box::pin(self.request(text, voice_id))
+    }
+
+    fn probe(&self) -> TtsFuture<'_, ()> {
+        Box::pin(self.model_info())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use axum::extract::Path;
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::{get, post};
+    use axum::{Json, Router};
+    use serde_json::Value;
+
+    /// An OpenAI API speaking a tenth of a second of 24kHz PCM per request.
+    async fn server() -> String {
+        let app = Router::new()
+            .route(
+                "/v1/audio/speech",
+                post(|headers: HeaderMap, Json(body): Json<Value>| async move {
+                    assert_eq!(headers["authorization"], "Bearer sk-test");
+                    assert_eq!(body["model"], "tts-1");
+                    assert_eq!(body["voice"], "nova");
+                    assert_eq!(body["response_format"], "pcm");
+                    let pcm: Vec<u8> = (0..2400i16)
+                        .flat_map(|i| ((i % 100) * 100).to_le_bytes())
+                        .collect();
+                    pcm
+                }),
+            )
+            .route(
+                "/v1/models/{model}",
+                get(|Path(model): Path<String>| async move {
+                    match model.as_str() {
+                        "tts-1" => Ok(()),
+                        _ => Err(StatusCode::NOT_FOUND),
+                    }
+                }),
+            );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/v1", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        url
+    }
+
+    #[tokio::test]
+    async fn converts_speech_to_8khz_mulaw() {
+        let url = server().await;
+        let config = OpenAiConfig {
+            base_url: url,
+            ..OpenAiConfig::default()
+        };
+        let tts = OpenAiTts::new(reqwest::Client::new(), "sk-test".into(), &config);
+
+        let audio = tts.synthesize("Hello", "nova").await.unwrap();
+        assert_eq!(audio.len(), 800);
+        tts.probe().await.unwrap();
+    }
+}
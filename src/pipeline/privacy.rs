@@ -0,0 +1,103 @@
+//! Masking of transcripts on their way to disk (`[privacy]`).
+//!
+//! Conversations kept under `[llm] sessions_dir` hold everything the caller
+//! dictated: phone numbers, card numbers, email addresses. Each category
+//! can be masked in what's written, while the brain keeps hearing the
+//! words as said for the rest of the call. Masked text is what comes back
+//! after a restart.
+
+use std::borrow::Cow;
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+use crate::config::{PrivacyConfig, ProfanityConfig};
+use crate::pipeline::filter::PHONE_NUMBERS;
+use crate::pipeline::profanity;
+
+/// 13 to 19 digits, grouped by spaces or dashes or not at all.
+static CARD_NUMBERS: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\b\d(?:[ -]?\d){12,18}\b").unwrap());
+
+static EMAILS: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"[A-Za-z0-9._%+-]+@[A-Za-z0-9-]+(?:\.[A-Za-z0-9-]+)*\.[A-Za-z]{2,}").unwrap()
+});
+
+/// The `[privacy]` masks, ready to apply. The default masks nothing.
+#[derive(Default)]
+pub struct PrivacyMask {
+    /// Card numbers before phone numbers, which would match part of one.
+    patterns: Vec<&'static Regex>,
+    replacement: String,
+    profanity: Option<Regex>,
+    profanity_mask: String,
+}
+
+impl PrivacyMask {
+    pub fn new(config: &PrivacyConfig, profanity: &ProfanityConfig) -> Self {
+        let patterns = [
+            config.card_numbers.then_some(&*CARD_NUMBERS),
+            config.phone_numbers.then_some(&*PHONE_NUMBERS),
+            config.emails.then_some(&*EMAILS),
+        ];
+        Self {
+            patterns: patterns.into_iter().flatten().collect(),
+            replacement: config.replacement.clone(),
+            profanity: profanity::words(profanity).filter(|_| config.profanity),
+            profanity_mask: profanity.mask.clone(),
+        }
+    }
+
+    /// `text` as it may be stored.
+    pub fn apply<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut text = Cow::Borrowed(text);
+        for pattern in &self.patterns {
+            if let Cow::Owned(masked) =
+                pattern.replace_all(&text, regex::NoExpand(&self.replacement))
+            {
+                text = Cow::Owned(masked);
+            }
+        }
+        if let Some(ref profanity) = self.profanity {
+            if let Cow::Owned(masked) =
+                profanity.replace_all(&text, regex::NoExpand(&self.profanity_mask))
+            {
+                text = Cow::Owned(masked);
+            }
+        }
+        text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn mask(toml: &str) -> PrivacyMask {
+        PrivacyMask::new(&toml::from_str(toml).unwrap(), &ProfanityConfig::default())
+    }
+
+    #[test]
+    fn masks_each_category_when_asked() {
+        let said = "Card 4111 1111 1111 1111, call me on 4155551212 or mail ana@gmail.com, shit";
+        assert_eq!(
+            mask("card_numbers = true\nphone_numbers = true\nemails = true\nprofanity = true")
+                .apply(said),
+            "Card [redacted], call me on [redacted] or mail [redacted], ***"
+        );
+        assert_eq!(
+            mask("emails = true").apply(said),
+            "Card 4111 1111 1111 1111, call me on 4155551212 or mail [redacted], shit"
+        );
+    }
+
+    #[test]
+    fn masks_nothing_by_default() {
+        let said = "+44 20 7946 0958, ana@gmail.com";
+        assert!(matches!(mask("").apply(said), Cow::Borrowed(_)));
+        assert!(matches!(
+            PrivacyMask::default().apply(said),
+            Cow::Borrowed(_)
+        ));
+    }
+}
@@ -0,0 +1,133 @@
+//! Optional profanity filtering (`[profanity]`).
+//!
+//! Inbound, caller transcripts are masked before they are logged or reach
+//! the brain (and whatever history it keeps). Outbound, profanity is dropped
+//! from text before TTS. Each direction is toggled separately; words match
+//! whole, case-insensitively.
+
+use std::borrow::Cow;
+
+use regex::Regex;
+
+use crate::config::ProfanityConfig;
+
+/// Built-in English word list.
+const BUILTIN_WORDS: &[&str] = &[
+    "arsehole",
+    "asshole",
+    "bastard",
+    "bitch",
+    "bullshit",
+    "cock",
+    "cunt",
+    "dick",
+    "dickhead",
+    "fuck",
+    "fucked",
+    "fucker",
+    "fucking",
+    "motherfucker",
+    "piss",
+    "pissed",
+    "prick",
+    "shit",
+    "shitty",
+    "twat",
+    "wanker",
+];
+
+pub struct ProfanityFilter {
+    /// `None` when neither direction is enabled or there are no words.
+    pattern: Option<Regex>,
+    inbound: bool,
+    outbound: bool,
+    mask: String,
+}
+
+impl ProfanityFilter {
+    pub fn new(config: &ProfanityConfig) -> Self {
+        Self {
+            pattern: words(config).filter(|_| config.inbound || config.outbound),
+            inbound: config.inbound,
+            outbound: config.outbound,
+            mask: config.mask.clone(),
+        }
+    }
+
+    /// Mask profanity in a caller transcript, if inbound filtering is on.
+    pub fn mask_transcript<'a>(&self, transcript: &'a str) -> Cow<'a, str> {
+        match self.pattern {
+            Some(ref pattern) if self.inbound => {
+                pattern.replace_all(transcript, regex::NoExpand(&self.mask))
+            }
+            _ => Cow::Borrowed(transcript),
+        }
+    }
+
+    /// Drop profanity from text about to be spoken, if outbound filtering
+    /// is on.
+    pub fn clean_response<'a>(&self, response: &'a str) -> Cow<'a, str> {
+        let Some(ref pattern) = self.pattern else {
+            return Cow::Borrowed(response);
+        };
+        if !self.outbound || !pattern.is_match(response) {
+            return Cow::Borrowed(response);
+        }
+        let dropped = pattern.replace_all(response, "");
+        // Tidy the gaps the dropped words leave
+        Cow::Owned(dropped.split_whitespace().collect::<Vec<_>>().join(" "))
+    }
+}
+
+/// The configured words as one whole-word, case-insensitive pattern;
+/// `None` when there are none.
+pub fn words(config: &ProfanityConfig) -> Option<Regex> {
+    let builtin = BUILTIN_WORDS.iter().filter(|_| config.builtin).copied();
+    let words: Vec<String> = builtin
+        .chain(config.words.iter().map(String::as_str))
+        .map(|w| regex::escape(w.trim()))
+        .filter(|w| !w.is_empty())
+        .collect();
+    (!words.is_empty()).then(|| {
+        Regex::new(&format!(r"(?i)\b(?:{})\b", words.join("|")))
+            .expect("escaped words form a valid pattern")
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn filter(toml: &str) -> ProfanityFilter {
+        ProfanityFilter::new(&toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn masks_transcripts_when_inbound() {
+        let filter = filter("inbound = true\nwords = [\"heck\"]");
+        assert_eq!(
+            filter.mask_transcript("What the Heck, this is SHIT"),
+            "What the ***, this is ***"
+        );
+        assert_eq!(filter.mask_transcript("Dickens shipped"), "Dickens shipped");
+        assert_eq!(filter.clean_response("oh shit"), "oh shit");
+    }
+
+    #[test]
+    fn drops_words_from_responses_when_outbound() {
+        let filter = filter("outbound = true\nbuiltin = false\nwords = [\"darn\"]");
+        assert_eq!(
+            filter.clean_response("Well darn it, sorry."),
+            "Well it, sorry."
+        );
+        assert_eq!(filter.clean_response("Well shit"), "Well shit");
+        assert_eq!(filter.mask_transcript("darn"), "darn");
+    }
+
+    #[test]
+    fn disabled_by_default() {
+        let filter = filter("");
+        assert!(matches!(filter.mask_transcript("shit"), Cow::Borrowed(_)));
+        assert!(matches!(filter.clean_response("shit"), Cow::Borrowed(_)));
+    }
+}
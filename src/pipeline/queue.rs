@@ -0,0 +1,147 @@
+//! Per-call serialization of brain requests.
+//!
+//! A caller who speaks again while the last turn is still with the brain
+//! would otherwise have two requests racing on the same conversation:
+//! each sent without the other's turn, answers recorded out of order, and
+//! bridge-echo's session resumed twice at once. Requests for one call wait
+//! their turn, in arrival order; different calls never wait on each other.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use tokio::sync::OwnedMutexGuard;
+
+type Calls = Arc<Mutex<HashMap<String, Slot>>>;
+
+/// Shared queue handle. Cheap to clone.
+#[derive(Clone, Default)]
+pub struct BrainQueue {
+    calls: Calls,
+}
+
+/// One call's lock, and how many requests hold it or are waiting for it.
+#[derive(Default)]
+struct Slot {
+    lock: Arc<tokio::sync::Mutex<()>>,
+    requests: usize,
+}
+
+/// The call's turn with the brain. The next request goes ahead when this
+/// is dropped.
+pub struct BrainTurn {
+    // Released before the request is counted out
+    _guard: OwnedMutexGuard<()>,
+    _request: Request,
+}
+
+/// A request holding or waiting for its call's turn. The call is forgotten
+/// when the last one goes, whether it had its turn or was cancelled while
+/// waiting.
+struct Request {
+    call_sid: String,
+    calls: Calls,
+}
+
+impl BrainQueue {
+    /// Wait until the call's earlier requests are done.
+    pub async fn turn(&self, call_sid: &str) -> BrainTurn {
+        let lock = {
+            let mut calls = self.calls.lock().unwrap();
+            let slot = calls.entry(call_sid.to_string()).or_default();
+            slot.requests += 1;
+            Arc::clone(&slot.lock)
+        };
+        let request = Request {
+            call_sid: call_sid.to_string(),
+            calls: Arc::clone(&self.calls),
+        };
+        BrainTurn {
+            _guard: lock.lock_owned().await,
+            _request: request,
+        }
+    }
+
+    /// Calls with a request in flight or waiting.
+    pub fn len(&self) -> usize {
+        self.calls.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+impl Drop for Request {
+    fn drop(&mut self) {
+        let mut calls = self.calls.lock().unwrap();
+        if let Some(slot) = calls.get_mut(&self.call_sid) {
+            slot.requests -= 1;
+            // Nobody else holding or waiting: forget the call
+            if slot.requests == 0 {
+                calls.remove(&self.call_sid);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn serializes_requests_per_call() {
+        let queue = BrainQueue::default();
+        let first = queue.turn("CA1").await;
+
+        // Another call goes straight ahead
+        let other = queue.turn("CA2").await;
+        drop(other);
+
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.turn("CA1").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(first);
+        let second = tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(queue.len(), 1);
+        drop(second);
+        assert!(queue.is_empty());
+    }
+
+    #[tokio::test]
+    async fn cancelled_waiter_is_forgotten() {
+        let queue = BrainQueue::default();
+        let first = queue.turn("CA1").await;
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.turn("CA1").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+
+        // The holder finishes first, then the waiter gives up
+        drop(first);
+        waiting.abort();
+        let _ = waiting.await;
+        assert!(queue.is_empty());
+
+        // And the other way round
+        let first = queue.turn("CA1").await;
+        let waiting = tokio::spawn({
+            let queue = queue.clone();
+            async move { queue.turn("CA1").await }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        waiting.abort();
+        let _ = waiting.await;
+        assert_eq!(queue.len(), 1);
+        drop(first);
+        assert!(queue.is_empty());
+    }
+}
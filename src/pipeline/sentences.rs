@@ -0,0 +1,150 @@
+//! Splitting a streamed brain response into sentences for TTS.
+//!
+//! Text arrives in arbitrary pieces; each sentence is handed to TTS as soon
+//! as it's complete, so the caller hears the start of the answer while the
+//! brain is still writing the rest. A sentence ends at `.`, `!` or `?`
+//! (and any closing quote) followed by whitespace, or at a line break.
+//! Directives, action objects and `[SPELL]` spans are never cut in half,
+//! and very short pieces ("Dr.", "No.") wait for the next sentence rather
+//! than be spoken as a fragment.
+
+use crate::pipeline::transliterate::{SPELL_CLOSE, SPELL_OPEN};
+
+/// Pieces shorter than this (in bytes) are joined to the next sentence.
+const MIN_SENTENCE: usize = 12;
+
+/// Collects streamed text and yields whole sentences.
+#[derive(Debug, Default)]
+pub struct Sentences {
+    pending: String,
+}
+
+impl Sentences {
+    /// Add the next piece of the response; returns the sentences it
+    /// completed, in order.
+    pub fn push(&mut self, text: &str) -> Vec<String> {
+        self.pending.push_str(text);
+        let mut sentences = Vec::new();
+        while let Some(end) = sentence_end(&self.pending) {
+            let sentence: String = self.pending.drain(..end).collect();
+            let sentence = sentence.trim();
+            if !sentence.is_empty() {
+                sentences.push(sentence.to_string());
+            }
+        }
+        sentences
+    }
+
+    /// Whatever is left once the response is complete.
+    pub fn finish(&mut self) -> Option<String> {
+        let rest = std::mem::take(&mut self.pending);
+        let rest = rest.trim();
+        (!rest.is_empty()).then(|| rest.to_string())
+    }
+}
+
+/// Byte offset just past the first complete sentence in `text`, if any.
+fn sentence_end(text: &str) -> Option<usize> {
+    let start = text.len() - text.trim_start().len();
+    let mut brackets = 0usize;
+    let mut spelling = false;
+    let mut chars = text.char_indices().peekable();
+
+    while let Some((at, c)) = chars.next() {
+        match c {
+            '[' => {
+                if text[at..].starts_with(SPELL_OPEN) {
+                    spelling = true;
+                } else if text[at..].starts_with(SPELL_CLOSE) {
+                    spelling = false;
+                }
+                brackets += 1;
+            }
+            '{' => brackets += 1,
+            ']' | '}' => brackets = brackets.saturating_sub(1),
+            _ => {}
+        }
+        if brackets > 0 || spelling || at + c.len_utf8() - start < MIN_SENTENCE {
+            continue;
+        }
+        if c == '\n' {
+            return Some(at + 1);
+        }
+        if matches!(c, '.' | '!' | '?') {
+            let mut end = at + 1;
+            while let Some(&(at, c)) = chars.peek() {
+                if !matches!(c, '"' | '\'' | ')' | '\u{201d}' | '\u{2019}') {
+                    break;
+                }
+                end = at + c.len_utf8();
+                chars.next();
+            }
+            // "3.5" and "e.g.," aren't ends; the sentence is only over once
+            // the next word has started
+            if chars.peek().is_some_and(|&(_, c)| c.is_whitespace()) {
+                return Some(end);
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn split(pieces: &[&str]) -> Vec<String> {
+        let mut sentences = Sentences::default();
+        let mut out: Vec<String> = pieces.iter().flat_map(|p| sentences.push(p)).collect();
+        out.extend(sentences.finish());
+        out
+    }
+
+    #[test]
+    fn yields_sentences_as_they_complete() {
+        let mut sentences = Sentences::default();
+        assert!(sentences.push("Sure, I can help with").is_empty());
+        assert_eq!(
+            sentences.push(" that. Your order ships"),
+            vec!["Sure, I can help with that."]
+        );
+        assert!(sentences.push(" on Tuesday!").is_empty());
+        assert_eq!(
+            sentences.push(" Anything else?\nBye"),
+            vec!["Your order ships on Tuesday!", "Anything else?"]
+        );
+        assert_eq!(sentences.finish().as_deref(), Some("Bye"));
+        assert_eq!(sentences.finish(), None);
+    }
+
+    #[test]
+    fn keeps_numbers_abbreviations_and_quotes_together() {
+        assert_eq!(
+            split(&[
+                "It costs 3.50 today. Dr. Smith said \"",
+                "fine.\" Then he left."
+            ]),
+            vec![
+                "It costs 3.50 today.",
+                "Dr. Smith said \"fine.\"",
+                "Then he left."
+            ]
+        );
+    }
+
+    #[test]
+    fn never_splits_directives_or_spelled_spans() {
+        assert_eq!(
+            split(&[
+                "Your code is [SPELL]A. B. C.[/SPE",
+                "LL] okay? [VOICE:x. y] Hi there. Bye! {\"action\": \"sms\", ",
+                "\"body\": \"Done. Thanks!\"} "
+            ]),
+            vec![
+                "Your code is [SPELL]A. B. C.[/SPELL] okay?",
+                "[VOICE:x. y] Hi there.",
+                "Bye! {\"action\": \"sms\", \"body\": \"Done. Thanks!\"}"
+            ]
+        );
+    }
+}
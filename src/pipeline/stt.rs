@@ -1,38 +1,431 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::time::Duration;
+
 use reqwest::multipart;
 use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::breaker::{BreakerState, CircuitBreaker};
+use crate::chaos::FaultInjector;
+use crate::pipeline::language;
+
+/// What an [`SttProvider`] call returns.
+pub type SttFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, SttError>> + Send + 'a>>;
+
+/// A speech-to-text vendor (`[stt] provider`). [`SttClient`] wraps one with
+/// the circuit breaker, injected faults and mocks, so a vendor only speaks
+/// its own API.
+pub trait SttProvider: Send + Sync {
+    /// Vendor name, as used for breakers and logs.
+    fn name(&self) -> &'static str;
+
+    /// Transcribe WAV audio with `language` (ISO 639-1) as the hint, or
+    /// detect the language when `None`. `context` is what was said just
+    /// before, for vendors that can be primed with it.
+    fn transcribe<'a>(
+        &'a self,
+        wav_data: Vec<u8>,
+        language: Option<&'a str>,
+        context: Option<&'a str>,
+    ) -> SttFuture<'a, Transcription>;
+
+    /// Cheap credential check.
+    fn probe(&self) -> SttFuture<'_, ()>;
+
+    /// Open a realtime session taking 8kHz mu-law and transcribing in
+    /// `language` (ISO 639-1), or `None` if the vendor only transcribes
+    /// whole utterances.
+    fn live<'a>(&'a self, _language: &'a str) -> Option<SttFuture<'a, LiveStt>> {
+        None
+    }
+}
+
+/// What a live session hears.
+#[derive(Debug, Clone, PartialEq)]
+pub enum LiveTranscript {
+    /// The utterance under way so far. Later ones revise it.
+    Interim(String),
+    /// A finished utterance, how long it was spoken for, the vendor's
+    /// confidence in its least certain part and who said what, if the
+    /// vendor diarizes.
+    Final {
+        text: String,
+        duration_ms: u64,
+        confidence: Option<f32>,
+        speakers: Vec<SpeakerRun>,
+    },
+}
+
+/// Words one speaker said in a row, as the vendor told speakers apart
+/// (`[diarization] vendor`).
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpeakerRun {
+    /// From 1.
+    pub speaker: u32,
+    pub text: String,
+}
+
+/// A realtime transcription session (`[stt] streaming`), fed a call's audio
+/// frame by frame. The vendor decides where utterances end. Closed when
+/// dropped.
+pub struct LiveStt {
+    audio: mpsc::UnboundedSender<Vec<u8>>,
+    transcripts: mpsc::UnboundedReceiver<LiveTranscript>,
+}
+
+impl LiveStt {
+    /// A session whose vendor task reads `audio` and writes `transcripts`.
+    pub fn new(
+        audio: mpsc::UnboundedSender<Vec<u8>>,
+        transcripts: mpsc::UnboundedReceiver<LiveTranscript>,
+    ) -> Self {
+        Self { audio, transcripts }
+    }
 
-/// Groq Whisper speech-to-text client.
+    /// Forward a frame of mu-law. False once the session has ended.
+    pub fn send(&self, mulaw: Vec<u8>) -> bool {
+        self.audio.send(mulaw).is_ok()
+    }
+
+    /// The next transcript, or `None` once the session has ended.
+    pub async fn recv(&mut self) -> Option<LiveTranscript> {
+        self.transcripts.recv().await
+    }
+}
+
+/// Speech-to-text client for the configured vendor.
 pub struct SttClient {
+    provider: Box<dyn SttProvider>,
+    breaker: CircuitBreaker,
+    chaos: FaultInjector,
+    retries: u32,
+    retry_delay: Duration,
+    max_retry_delay: Duration,
+    timeout: Option<Duration>,
+    /// `[stt] fallback`, tried once this vendor has given up.
+    fallback: Option<Box<SttClient>>,
+    #[cfg(feature = "mocks")]
+    mock: Option<crate::mocks::MockStt>,
+}
+
+/// A transcript and, when the language wasn't given, the detected one.
+#[derive(Debug)]
+pub struct Transcription {
+    pub text: String,
+    /// ISO 639-1 code of the detected language.
+    pub language: Option<String>,
+    /// How sure the vendor is of the words, from 0 to 1, if it says.
+    pub confidence: Option<f32>,
+    /// Who said what, if the vendor diarizes. Empty otherwise.
+    pub speakers: Vec<SpeakerRun>,
+}
+
+impl SttClient {
+    pub fn new(provider: Box<dyn SttProvider>, breaker: CircuitBreaker) -> Self {
+        Self {
+            provider,
+            breaker,
+            chaos: FaultInjector::default(),
+            retries: 0,
+            retry_delay: Duration::ZERO,
+            max_retry_delay: Duration::ZERO,
+            timeout: None,
+            fallback: None,
+            #[cfg(feature = "mocks")]
+            mock: None,
+        }
+    }
+
+    /// Retry failed requests that might succeed next time (connection
+    /// failures, 429 and 5xx) up to `retries` times, `delay_ms` apart.
+    pub fn with_retries(mut self, retries: u32, delay_ms: u64) -> Self {
+        self.retries = retries;
+        self.retry_delay = Duration::from_millis(delay_ms);
+        self
+    }
+
+    /// Double the delay after each failed retry, up to `max_delay_ms`. At or
+    /// below the retry delay, retries stay evenly spaced.
+    pub fn with_max_retry_delay(mut self, max_delay_ms: u64) -> Self {
+        self.max_retry_delay = Duration::from_millis(max_delay_ms);
+        self
+    }
+
+    /// Give up on a request after `timeout_ms`, so a stalled upload can be
+    /// retried or handed to the fallback. 0 means no timeout.
+    pub fn with_timeout(mut self, timeout_ms: u64) -> Self {
+        self.timeout = (timeout_ms > 0).then(|| Duration::from_millis(timeout_ms));
+        self
+    }
+
+    /// Transcribe with `fallback` when this vendor fails, retries and all,
+    /// or its breaker is open.
+    pub fn with_fallback(mut self, fallback: SttClient) -> Self {
+        self.fallback = Some(Box::new(fallback));
+        self
+    }
+
+    /// How long to wait before retry number `attempt` (from 1).
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .retry_delay
+            .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)));
+        delay.min(self.max_retry_delay.max(self.retry_delay))
+    }
+
+    /// Inject `[chaos.stt]` faults into every request.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Answer from `mock` instead of the vendor (`[mocks] stt`).
+    #[cfg(feature = "mocks")]
+    pub fn with_mock(mut self, mock: crate::mocks::MockStt) -> Self {
+        self.mock = Some(mock);
+        self
+    }
+
+    /// Circuit breaker guarding the vendor's API.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// The vendor transcribing.
+    pub fn vendor(&self) -> &'static str {
+        self.provider.name()
+    }
+
+    /// The `[stt] fallback` vendor's client, if there is one.
+    pub fn fallback(&self) -> Option<&SttClient> {
+        self.fallback.as_deref()
+    }
+
+    /// True when this vendor's breaker is open, and any fallback's too.
+    pub fn down(&self) -> bool {
+        self.breaker.state() == BreakerState::Open
+            && self.fallback.as_ref().map_or(true, |f| f.down())
+    }
+
+    /// Transcribe WAV audio bytes to English text.
+    ///
+    /// Fails fast with `SttError::Unavailable` while the breaker is open.
+    pub async fn transcribe(&self, wav_data: Vec<u8>) -> Result<String, SttError> {
+        Ok(self.transcribe_in(wav_data, Some("en")).await?.text)
+    }
+
+    /// Transcribe with `language` (ISO 639-1) as the hint, or detect the
+    /// language when `None`. Falls back to the `[stt] fallback` vendor once
+    /// this one has given up.
+    pub async fn transcribe_in(
+        &self,
+        wav_data: Vec<u8>,
+        language: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        self.transcribe_after(wav_data, language, None).await
+    }
+
+    /// [`transcribe_in`](Self::transcribe_in), primed with `context`: the
+    /// conversation so far, so names and fragments like "yes, that one"
+    /// are heard right.
+    pub async fn transcribe_after(
+        &self,
+        wav_data: Vec<u8>,
+        language: Option<&str>,
+        context: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        let Some(ref fallback) = self.fallback else {
+            return self.retrying(&wav_data, language, context).await;
+        };
+        match self.retrying(&wav_data, language, context).await {
+            Err(e) => {
+                tracing::warn!(
+                    vendor = self.vendor(),
+                    fallback = fallback.vendor(),
+                    "STT failed, trying the fallback: {e}"
+                );
+                fallback.retrying(&wav_data, language, context).await
+            }
+            result => result,
+        }
+    }
+
+    /// This vendor's attempts, within its breaker.
+    async fn retrying(
+        &self,
+        wav_data: &[u8],
+        language: Option<&str>,
+        context: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| SttError::Unavailable(e.to_string()))?;
+        let mut attempt = 0;
+        let result = loop {
+            match self.attempt(wav_data.to_vec(), language, context).await {
+                Err(e) if e.is_retryable() && attempt < self.retries => {
+                    attempt += 1;
+                    tracing::warn!(vendor = self.vendor(), attempt, "Retrying STT request: {e}");
+                    tokio::time::sleep(self.backoff(attempt)).await;
+                }
+                result => break result,
+            }
+        };
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// One request, to the vendor or the mock, with any injected faults.
+    async fn attempt(
+        &self,
+        wav_data: Vec<u8>,
+        language: Option<&str>,
+        context: Option<&str>,
+    ) -> Result<Transcription, SttError> {
+        self.chaos
+            .before()
+            .await
+            .map_err(|e| SttError::Request(e.to_string()))?;
+        #[cfg(feature = "mocks")]
+        if let Some(ref mock) = self.mock {
+            let transcription = mock.transcribe(language).await;
+            self.chaos.drip(transcription.text.len()).await;
+            return Ok(transcription);
+        }
+        let request = self.provider.transcribe(wav_data, language, context);
+        let transcription = match self.timeout {
+            Some(timeout) => tokio::time::timeout(timeout, request).await.map_err(|_| {
+                SttError::Request(format!("timed out after {}ms", timeout.as_millis()))
+            })??,
+            None => request.await?,
+        };
+        self.chaos.drip(transcription.text.len()).await;
+        Ok(transcription)
+    }
+
+    /// Open a live session, or `None` if the vendor has none (or STT is
+    /// mocked). Fails fast while the breaker is open; a session that can't
+    /// be opened counts against it.
+    pub async fn live(&self, language: &str) -> Result<Option<LiveStt>, SttError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(None);
+        }
+        let Some(opening) = self.provider.live(language) else {
+            return Ok(None);
+        };
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| SttError::Unavailable(e.to_string()))?;
+        let result = opening.await;
+        permit.finish(result.is_ok());
+        result.map(Some)
+    }
+
+    /// Cheap credential check. Bypasses the breaker.
+    pub async fn probe(&self) -> Result<(), SttError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        self.provider.probe().await
+    }
+}
+
+/// Groq Whisper (`[stt] provider = "groq"`).
+pub struct GroqStt {
     client: reqwest::Client,
     api_key: String,
     model: String,
+    vocabulary: Vec<String>,
 }
 
 #[derive(Debug, Deserialize)]
 struct TranscriptionResponse {
     text: String,
+    #[serde(default)]
+    language: Option<String>,
+    #[serde(default)]
+    segments: Vec<Segment>,
 }
 
-impl SttClient {
-    pub fn new(api_key: String, model: String) -> Self {
+#[derive(Debug, Deserialize)]
+struct Segment {
+    avg_logprob: f64,
+}
+
+/// How much of the conversation goes into Whisper's prompt. Whisper only
+/// reads the last 224 tokens of it, and the vocabulary comes first.
+const MAX_CONTEXT_CHARS: usize = 400;
+
+/// Whisper's confidence in a transcript: the mean per-token probability
+/// over its segments.
+fn whisper_confidence(segments: &[Segment]) -> Option<f32> {
+    if segments.is_empty() {
+        return None;
+    }
+    let mean = segments.iter().map(|s| s.avg_logprob).sum::<f64>() / segments.len() as f64;
+    Some(mean.exp().clamp(0.0, 1.0) as f32)
+}
+
+impl GroqStt {
+    pub fn new(client: reqwest::Client, api_key: String, model: String) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
             api_key,
             model,
+            vocabulary: Vec::new(),
         }
     }
 
-    /// Transcribe WAV audio bytes to text using Groq Whisper.
-    pub async fn transcribe(&self, wav_data: Vec<u8>) -> Result<String, SttError> {
+    /// Steer Whisper towards `terms` by listing them in its prompt.
+    pub fn with_vocabulary(mut self, terms: Vec<String>) -> Self {
+        self.vocabulary = terms;
+        self
+    }
+
+    /// Whisper's prompt: the vocabulary, then the end of the conversation
+    /// so far.
+    fn prompt(&self, context: Option<&str>) -> Option<String> {
+        let context = context.map(|context| {
+            let skip = context.chars().count().saturating_sub(MAX_CONTEXT_CHARS);
+            context.chars().skip(skip).collect::<String>()
+        });
+        let vocabulary = (!self.vocabulary.is_empty()).then(|| self.vocabulary.join(", "));
+        match (vocabulary, context) {
+            (Some(vocabulary), Some(context)) => Some(format!("{vocabulary}. {context}")),
+            (vocabulary, context) => vocabulary.or(context),
+        }
+    }
+
+    async fn request(
+        &self,
+        wav_data: Vec<u8>,
+        hint: Option<&str>,
+        context: Option<&str>,
+    ) -> Result<Transcription, SttError> {
         let file_part = multipart::Part::bytes(wav_data)
             .file_name("audio.wav")
             .mime_str("audio/wav")
             .map_err(|e| SttError::Request(e.to_string()))?;
 
+        // Verbose output reports the detected language and segment
+        // log-probabilities
         let form = multipart::Form::new()
             .text("model", self.model.clone())
-            .text("language", "en")
+            .text("response_format", "verbose_json")
             .part("file", file_part);
+        let form = match hint {
+            Some(hint) => form.text("language", hint.to_string()),
+            None => form,
+        };
+        let form = match self.prompt(context) {
+            Some(prompt) => form.text("prompt", prompt),
+            None => form,
+        };
 
         let resp = self
             .client
@@ -54,7 +447,49 @@ impl SttClient {
             .await
             .map_err(|e| SttError::Request(e.to_string()))?;
 
-        Ok(result.text)
+        // Whisper reports the language it was told, too
+        let language = match hint {
+            Some(_) => None,
+            None => result.language.as_deref().and_then(language::code),
+        };
+        Ok(Transcription {
+            text: result.text,
+            language,
+            confidence: whisper_confidence(&result.segments),
+            speakers: Vec::new(),
+        })
+    }
+}
+
+impl SttProvider for GroqStt {
+    fn name(&self) -> &'static str {
+        "groq"
+    }
+
+    fn transcribe<'a>(
+        &'a self,
+        wav_data: Vec<u8>,
+        language: Option<&'a str>,
+        context: Option<&'a str>,
+    ) -> SttFuture<'a, Transcription> {
+        Box::pin(self.request(wav_data, language, context))
+    }
+
+    /// List models.
+    fn probe(&self) -> SttFuture<'_, ()> {
+        Box::pin(async {
+            let resp = self
+                .client
+                .get("https://api.groq.com/openai/v1/models")
+                .bearer_auth(&self.api_key)
+                .send()
+                .await
+                .map_err(|e| SttError::Request(e.to_string()))?;
+            if !resp.status().is_success() {
+                return Err(SttError::Api(resp.status().to_string()));
+            }
+            Ok(())
+        })
     }
 }
 
@@ -64,4 +499,174 @@ pub enum SttError {
     Request(String),
     #[error("API error: {0}")]
     Api(String),
+    #[error("STT unavailable: {0}")]
+    Unavailable(String),
+}
+
+impl SttError {
+    /// Whether the same request might succeed if sent again. API errors
+    /// lead with the HTTP status.
+    fn is_retryable(&self) -> bool {
+        match self {
+            SttError::Request(_) => true,
+            SttError::Api(e) => e.starts_with('5') || e.starts_with("429"),
+            SttError::Unavailable(_) => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicU32, Ordering};
+    use std::sync::Arc;
+
+    use crate::config::BreakerConfig;
+
+    /// Fails with each of `errors` in turn, then transcribes as `name`.
+    struct Flaky {
+        name: &'static str,
+        errors: Vec<fn() -> SttError>,
+        calls: Arc<AtomicU32>,
+    }
+
+    impl SttProvider for Flaky {
+        fn name(&self) -> &'static str {
+            self.name
+        }
+
+        fn transcribe<'a>(
+            &'a self,
+            _wav_data: Vec<u8>,
+            _language: Option<&'a str>,
+            _context: Option<&'a str>,
+        ) -> SttFuture<'a, Transcription> {
+            Box::pin(async move {
+                let call = self.calls.fetch_add(1, Ordering::Relaxed) as usize;
+                if let Some(error) = self.errors.get(call) {
+                    return Err(error());
+                }
+                Ok(Transcription {
+                    text: self.name.to_string(),
+                    language: None,
+                    confidence: None,
+                    speakers: Vec::new(),
+                })
+            })
+        }
+
+        fn probe(&self) -> SttFuture<'_, ()> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn client(name: &'static str, errors: Vec<fn() -> SttError>) -> (SttClient, Arc<AtomicU32>) {
+        let calls = Arc::new(AtomicU32::new(0));
+        let provider = Flaky {
+            name,
+            errors,
+            calls: Arc::clone(&calls),
+        };
+        let breaker = CircuitBreaker::new(name, &BreakerConfig::default());
+        (SttClient::new(Box::new(provider), breaker), calls)
+    }
+
+    #[tokio::test]
+    async fn retries_transient_failures() {
+        let timeout = || SttError::Request("timed out".into());
+        let busy = || SttError::Api("503 Service Unavailable: busy".into());
+        let (stt, calls) = client("groq", vec![timeout, busy]);
+        let stt = stt.with_retries(2, 1);
+        assert_eq!(stt.transcribe(vec![0; 44]).await.unwrap(), "groq");
+        assert_eq!(calls.load(Ordering::Relaxed), 3);
+
+        // A bad key stays bad
+        let unauthorized = || SttError::Api("401 Unauthorized: bad key".into());
+        let (stt, calls) = client("groq", vec![unauthorized]);
+        let stt = stt.with_retries(2, 1);
+        assert!(stt.transcribe(vec![0; 44]).await.is_err());
+        assert_eq!(calls.load(Ordering::Relaxed), 1);
+    }
+
+    #[tokio::test]
+    async fn falls_back_once_retries_run_out() {
+        let down = || SttError::Request("connection refused".into());
+        let (stt, calls) = client("groq", vec![down, down, down]);
+        let (deepgram, _) = client("deepgram", Vec::new());
+        let stt = stt.with_retries(1, 1).with_fallback(deepgram);
+        assert_eq!(stt.transcribe(vec![0; 44]).await.unwrap(), "deepgram");
+        assert_eq!(calls.load(Ordering::Relaxed), 2);
+        assert!(!stt.down());
+    }
+
+    #[tokio::test]
+    async fn stalled_requests_time_out_into_a_retry() {
+        struct Stalled;
+
+        impl SttProvider for Stalled {
+            fn name(&self) -> &'static str {
+                "groq"
+            }
+
+            fn transcribe<'a>(
+                &'a self,
+                _wav_data: Vec<u8>,
+                _language: Option<&'a str>,
+                _context: Option<&'a str>,
+            ) -> SttFuture<'a, Transcription> {
+                Box::pin(std::future::pending())
+            }
+
+            fn probe(&self) -> SttFuture<'_, ()> {
+                Box::pin(async { Ok(()) })
+            }
+        }
+
+        let breaker = CircuitBreaker::new("groq", &BreakerConfig::default());
+        let (deepgram, _) = client("deepgram", Vec::new());
+        let stt = SttClient::new(Box::new(Stalled), breaker)
+            .with_timeout(20)
+            .with_retries(1, 1)
+            .with_fallback(deepgram);
+        assert_eq!(stt.transcribe(vec![0; 44]).await.unwrap(), "deepgram");
+    }
+
+    #[test]
+    fn whisper_confidence_is_the_mean_token_probability() {
+        let segments: Vec<Segment> =
+            serde_json::from_str(r#"[{"avg_logprob": -0.1}, {"avg_logprob": -0.5}]"#).unwrap();
+        let confidence = whisper_confidence(&segments).unwrap();
+        assert!((confidence - (-0.3f32).exp()).abs() < 1e-6);
+        assert_eq!(whisper_confidence(&[]), None);
+    }
+
+    #[test]
+    fn prompts_whisper_with_vocabulary_then_context() {
+        let groq = GroqStt::new(reqwest::Client::new(), "gsk".into(), "whisper".into());
+        assert_eq!(groq.prompt(None), None);
+        assert_eq!(
+            groq.prompt(Some("Which cluster? The Grafana one."))
+                .as_deref(),
+            Some("Which cluster? The Grafana one.")
+        );
+
+        let groq = groq.with_vocabulary(vec!["Kubernetes".into(), "Grafana".into()]);
+        assert_eq!(groq.prompt(None).as_deref(), Some("Kubernetes, Grafana"));
+        let long = format!("{} the end.", "blah ".repeat(200));
+        let prompt = groq.prompt(Some(&long)).unwrap();
+        assert!(prompt.starts_with("Kubernetes, Grafana. "));
+        assert!(prompt.ends_with(" the end."));
+        assert_eq!(
+            prompt.chars().count(),
+            "Kubernetes, Grafana. ".len() + MAX_CONTEXT_CHARS
+        );
+    }
+
+    #[test]
+    fn retry_delay_doubles_up_to_the_cap() {
+        let (stt, _) = client("groq", Vec::new());
+        let stt = stt.with_retries(5, 250).with_max_retry_delay(1500);
+        let delays: Vec<_> = (1..=5).map(|n| stt.backoff(n).as_millis()).collect();
+        assert_eq!(delays, [250, 500, 1000, 1500, 1500]);
+    }
 }
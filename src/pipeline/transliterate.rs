@@ -0,0 +1,214 @@
+//! Pre-TTS rewrites for text that TTS reads badly.
+//!
+//! Confirmation codes, identifiers and foreign names come out of the brain
+//! as written; read as-is, "X7K-42Q" is a mumble. The brain can wrap
+//! critical data in `[SPELL]...[/SPELL]` to have it read character by
+//! character (`[tts.spell]`), and `[[tts.transliterate]]` rules match
+//! patterns and either spell the match out or substitute a pronounceable
+//! form. Tags are handled first, then rules in order. Rules only see the
+//! text as written: what's already spelled out, with its `<break>` pauses,
+//! is left alone.
+
+use std::borrow::Cow;
+
+use crate::config::{SpellConfig, TransliterationMode, TransliterationRule, TtsConfig};
+
+pub const SPELL_OPEN: &str = "[SPELL]";
+pub const SPELL_CLOSE: &str = "[/SPELL]";
+
+/// Apply `[SPELL]` tags and every transliteration rule to `text`.
+pub fn apply<'a>(text: &'a str, config: &TtsConfig) -> Cow<'a, str> {
+    if config.transliterate.is_empty() && !text.contains(SPELL_OPEN) {
+        return Cow::Borrowed(text);
+    }
+    let mut parts = spell_tags(text, &config.spell);
+    for rule in &config.transliterate {
+        parts = parts
+            .into_iter()
+            .flat_map(|part| match part {
+                Part::Text(text) => match rule.mode {
+                    TransliterationMode::Spell => spell_matches(&text, rule, &config.spell),
+                    TransliterationMode::Replace => vec![Part::Text(
+                        rule.pattern
+                            .replace_all(&text, rule.replacement.as_str())
+                            .into_owned(),
+                    )],
+                },
+                spelled => vec![spelled],
+            })
+            .collect();
+    }
+    let out: String = parts.iter().map(Part::as_str).collect();
+    match out == text {
+        true => Cow::Borrowed(text),
+        false => Cow::Owned(out),
+    }
+}
+
+/// A stretch of the text on its way to TTS.
+enum Part {
+    /// As written, for the rules to rewrite.
+    Text(String),
+    /// Already spelled out, left alone.
+    Spelled(String),
+}
+
+impl Part {
+    fn as_str(&self) -> &str {
+        match self {
+            Part::Text(text) | Part::Spelled(text) => text,
+        }
+    }
+}
+
+/// `text` with the matches of a spell rule spelled out.
+fn spell_matches(text: &str, rule: &TransliterationRule, config: &SpellConfig) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut last = 0;
+    for found in rule.pattern.find_iter(text) {
+        parts.push(Part::Text(text[last..found.start()].to_string()));
+        parts.push(Part::Spelled(spell(found.as_str(), config)));
+        last = found.end();
+    }
+    parts.push(Part::Text(text[last..].to_string()));
+    parts
+}
+
+/// Spell out `[SPELL]...[/SPELL]` spans. An unclosed tag is dropped so it
+/// isn't read aloud.
+fn spell_tags(text: &str, config: &SpellConfig) -> Vec<Part> {
+    let mut parts = Vec::new();
+    let mut rest = text;
+    while let Some(open) = rest.find(SPELL_OPEN) {
+        parts.push(Part::Text(rest[..open].to_string()));
+        let inner = &rest[open + SPELL_OPEN.len()..];
+        match inner.find(SPELL_CLOSE) {
+            Some(close) => {
+                parts.push(Part::Spelled(spell(&inner[..close], config)));
+                rest = &inner[close + SPELL_CLOSE.len()..];
+            }
+            None => rest = inner,
+        }
+    }
+    parts.push(Part::Text(rest.to_string()));
+    parts
+}
+
+/// Letters and digits one at a time, so TTS reads them individually.
+///
+/// Separators ("-", " ") split the code into groups with a longer pause
+/// between them, and the whole code is read `repeat` more times.
+pub fn spell(code: &str, config: &SpellConfig) -> String {
+    let pause = |ms: u64| format!(" <break time=\"{ms}ms\"/> ");
+    let (char_gap, group_gap, repeat_gap) = if config.ssml {
+        (
+            pause(config.pause_ms),
+            pause(config.pause_ms * 2),
+            pause(config.pause_ms * 4),
+        )
+    } else {
+        (", ".to_string(), ". ".to_string(), ". ".to_string())
+    };
+
+    let once = code
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|group| !group.is_empty())
+        .map(|group| {
+            group
+                .chars()
+                .map(|c| c.to_uppercase().to_string())
+                .collect::<Vec<_>>()
+                .join(&char_gap)
+        })
+        .collect::<Vec<_>>()
+        .join(&group_gap);
+    vec![once; config.repeat as usize + 1].join(&repeat_gap)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config(toml: &str) -> TtsConfig {
+        toml::from_str(toml).unwrap()
+    }
+
+    #[test]
+    fn spells_matching_codes() {
+        let config = config(
+            r#"[[transliterate]]
+               pattern = "\\b[A-Z0-9]{3}-[A-Z0-9]{3}\\b""#,
+        );
+        assert_eq!(
+            apply("Your code is X7K-42Q.", &config),
+            "Your code is X, 7, K. 4, 2, Q."
+        );
+        assert!(matches!(apply("No code here.", &config), Cow::Borrowed(_)));
+    }
+
+    #[test]
+    fn replaces_with_group_references() {
+        let config = config(
+            r#"[[transliterate]]
+               pattern = "\\bSiobhan\\b"
+               mode = "replace"
+               replacement = "Shivawn"
+
+               [[transliterate]]
+               pattern = "v(\\d+)\\.(\\d+)"
+               mode = "replace"
+               replacement = "version $1 point $2""#,
+        );
+        assert_eq!(
+            apply("Siobhan shipped v2.4", &config),
+            "Shivawn shipped version 2 point 4"
+        );
+    }
+
+    #[test]
+    fn invalid_pattern_fails_to_load() {
+        assert!(toml::from_str::<TtsConfig>("[[transliterate]]\npattern = \"(\"").is_err());
+    }
+
+    #[test]
+    fn spell_tags_are_spelled_and_never_spoken() {
+        let config = config("[spell]\nrepeat = 1");
+        assert_eq!(
+            apply("Booking [SPELL]ab1[/SPELL], thanks", &config),
+            "Booking A, B, 1. A, B, 1, thanks"
+        );
+        assert_eq!(apply("Oops [SPELL]abc", &config), "Oops abc");
+    }
+
+    #[test]
+    fn rules_leave_spelled_text_alone() {
+        let config = config(
+            r#"[spell]
+               ssml = true
+               pause_ms = 200
+
+               [[transliterate]]
+               pattern = "\\d+ms"
+               mode = "replace"
+               replacement = "a moment"
+
+               [[transliterate]]
+               pattern = "\\bA\\b"
+               mode = "replace"
+               replacement = "eh""#,
+        );
+        assert_eq!(
+            apply("Wait 300ms for [SPELL]A1[/SPELL]", &config),
+            r#"Wait a moment for A <break time="200ms"/> 1"#
+        );
+    }
+
+    #[test]
+    fn ssml_pauses_between_characters_and_groups() {
+        let config = config("[spell]\nssml = true\npause_ms = 200");
+        assert_eq!(
+            spell("A1-B", &config.spell),
+            r#"A <break time="200ms"/> 1 <break time="400ms"/> B"#
+        );
+    }
+}
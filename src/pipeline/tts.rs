@@ -1,60 +1,323 @@
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
 use base64::Engine;
 use serde::Deserialize;
 
-/// Inworld text-to-speech client.
+use crate::breaker::CircuitBreaker;
+use crate::chaos::FaultInjector;
+use crate::config::LoudnessConfig;
+use crate::pipeline::audio;
+use crate::pipeline::tts_cache::TtsCache;
+
+/// What a [`TtsProvider`] call returns.
+pub type TtsFuture<'a, T> = Pin<Box<dyn Future<Output = Result<T, TtsError>> + Send + 'a>>;
+
+/// A text-to-speech vendor (`[tts] provider`). [`TtsClient`] wraps one with
+/// chunking, retries, the circuit breaker, injected faults and mocks, so a
+/// vendor only speaks its own API.
+pub trait TtsProvider: Send + Sync {
+    /// Vendor name, as used for breakers and logs.
+    fn name(&self) -> &'static str;
+
+    /// The model speaking, which `[tts.cache]` keys clips by.
+    fn model(&self) -> &str;
+
+    /// Most characters one request may carry. Longer replies are split at
+    /// sentence boundaries.
+    fn max_chars(&self) -> usize;
+
+    /// Synthesize `text` in `voice_id` as raw mu-law 8kHz audio, ready for
+    /// Twilio. Vendors that can't produce it convert it themselves.
+    fn synthesize<'a>(&'a self, text: &'a str, voice_id: &'a str) -> TtsFuture<'a, Vec<u8>>;
+
+    /// Cheap credential check.
+    fn probe(&self) -> TtsFuture<'_, ()>;
+}
+
+/// Text-to-speech client for the `[tts] provider` vendor.
 ///
 /// Returns raw mu-law 8kHz audio — ready for Twilio with no conversion needed.
 pub struct TtsClient {
-    client: reqwest::Client,
-    api_key: String,
+    provider: Box<dyn TtsProvider>,
     voice_id: String,
-    model: String,
+    breaker: CircuitBreaker,
+    chunk_retries: u32,
+    retry_backoff: Duration,
+    chaos: FaultInjector,
+    loudness: Option<LoudnessConfig>,
+    cache: Option<Arc<TtsCache>>,
+    #[cfg(feature = "mocks")]
+    mock: Option<crate::mocks::MockTts>,
 }
 
-/// Inworld's per-request character limit.
-const MAX_CHARS: usize = 2000;
-
-/// Inworld TTS response shape.
-#[derive(Deserialize)]
-struct TtsResponse {
-    #[serde(rename = "audioContent")]
-    audio_content: String,
-}
+/// Spoken after a partial reply when a later chunk can't be synthesized.
+const TRUNCATED_APOLOGY: &str = "Sorry, I lost the rest of that.";
 
 impl TtsClient {
-    pub fn new(api_key: String, voice_id: String, model: String) -> Self {
+    /// A client for `provider`, speaking in `voice_id` unless told otherwise.
+    pub fn new(provider: Box<dyn TtsProvider>, voice_id: String, breaker: CircuitBreaker) -> Self {
         Self {
-            client: reqwest::Client::new(),
-            api_key,
+            provider,
             voice_id,
-            model,
+            breaker,
+            chunk_retries: 0,
+            retry_backoff: Duration::ZERO,
+            chaos: FaultInjector::default(),
+            loudness: None,
+            cache: None,
+            #[cfg(feature = "mocks")]
+            mock: None,
         }
     }
 
+    /// Level each synthesized reply to `loudness` (`[tts.loudness]`).
+    pub fn with_loudness(mut self, loudness: Option<LoudnessConfig>) -> Self {
+        self.loudness = loudness;
+        self
+    }
+
+    /// Reuse clips of fixed phrases from `cache` (`[tts.cache]`).
+    pub fn with_cache(mut self, cache: Option<Arc<TtsCache>>) -> Self {
+        self.cache = cache;
+        self
+    }
+
+    /// Inject `[chaos.tts]` faults into every chunk request.
+    pub fn with_chaos(mut self, chaos: FaultInjector) -> Self {
+        self.chaos = chaos;
+        self
+    }
+
+    /// Generate audio with `mock` instead of the vendor (`[mocks] tts`).
+    #[cfg(feature = "mocks")]
+    pub fn with_mock(mut self, mock: crate::mocks::MockTts) -> Self {
+        self.mock = Some(mock);
+        self
+    }
+
+    /// Retry failed chunk requests up to `retries` times, waiting `backoff_ms`
+    /// before the first retry and doubling each time.
+    pub fn with_retries(mut self, retries: u32, backoff_ms: u64) -> Self {
+        self.chunk_retries = retries;
+        self.retry_backoff = Duration::from_millis(backoff_ms);
+        self
+    }
+
+    /// Circuit breaker guarding the vendor's API.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
+    /// The vendor's name.
+    pub fn vendor(&self) -> &'static str {
+        self.provider.name()
+    }
+
     /// Convert text to raw mu-law 8kHz audio bytes using the default voice.
     pub async fn synthesize(&self, text: &str) -> Result<Vec<u8>, TtsError> {
         self.synthesize_with_voice(text, &self.voice_id).await
     }
 
     /// Convert text to raw mu-law 8kHz audio bytes using an explicit voice ID.
+    ///
+    /// Each chunk is retried with backoff. If a chunk still fails after some
+    /// audio has been synthesized, the partial audio is returned followed by
+    /// a short spoken apology rather than dropping the whole reply.
     pub async fn synthesize_with_voice(
         &self,
         text: &str,
         voice_id: &str,
     ) -> Result<Vec<u8>, TtsError> {
-        let chunks = split_text(text, MAX_CHARS);
+        let (audio, _) = self.synthesize_chunks(text, voice_id).await?;
+        Ok(self.level(audio))
+    }
+
+    /// Like [`synthesize_with_voice`](Self::synthesize_with_voice), for a
+    /// fixed phrase (a greeting, apology or announcement) rather than
+    /// anything said on the call: reused from the cache if it's there, and
+    /// kept in it if it isn't. `None` is the default voice.
+    pub async fn synthesize_phrase(
+        &self,
+        text: &str,
+        voice_id: Option<&str>,
+    ) -> Result<Vec<u8>, TtsError> {
+        let voice_id = voice_id.unwrap_or(&self.voice_id);
+        let Some(ref cache) = self.cache else {
+            return self.synthesize_with_voice(text, voice_id).await;
+        };
+        let key = TtsCache::key(text, voice_id, self.provider.model());
+        if let Some(audio) = cache.get(&key).await {
+            tracing::debug!(chars = text.len(), "TTS cache hit");
+            return Ok(self.level(audio));
+        }
+        let (audio, complete) = self.synthesize_chunks(text, voice_id).await?;
+        // Never keep a phrase cut short by a failed chunk
+        if complete {
+            cache.insert(&key, &audio).await;
+        }
+        Ok(self.level(audio))
+    }
+
+    /// `audio` at the `[tts.loudness]` level, if one is set.
+    fn level(&self, mut audio: Vec<u8>) -> Vec<u8> {
+        if let Some(ref loudness) = self.loudness {
+            let gain_db =
+                audio::normalize_mulaw(&mut audio, loudness.target_dbfs, loudness.max_gain_db);
+            tracing::debug!(gain_db, "Normalized TTS loudness");
+        }
+        audio
+    }
+
+    /// Synthesize `text` chunk by chunk. Returns the audio and whether all
+    /// of it was synthesized.
+    async fn synthesize_chunks(
+        &self,
+        text: &str,
+        voice_id: &str,
+    ) -> Result<(Vec<u8>, bool), TtsError> {
+        let chunks = split_text(text, self.provider.max_chars());
         let mut all_audio = Vec::new();
 
-        for chunk in &chunks {
-            let audio = self.synthesize_chunk(chunk, voice_id).await?;
-            all_audio.extend_from_slice(&audio);
+        for (i, chunk) in chunks.iter().enumerate() {
+            match self.synthesize_chunk_with_retry(chunk, voice_id).await {
+                Ok(audio) => all_audio.extend_from_slice(&audio),
+                Err(e) if all_audio.is_empty() => return Err(e),
+                Err(e) => {
+                    tracing::warn!(
+                        chunk = i,
+                        chunks = chunks.len(),
+                        "TTS chunk failed, returning partial audio: {e}"
+                    );
+                    match self.apology(voice_id).await {
+                        Ok(apology) => all_audio.extend_from_slice(&apology),
+                        Err(e) => tracing::warn!("TTS apology failed: {e}"),
+                    }
+                    return Ok((all_audio, false));
+                }
+            }
         }
+        Ok((all_audio, true))
+    }
 
-        Ok(all_audio)
+    /// [`TRUNCATED_APOLOGY`], from the cache if it's been spoken before.
+    async fn apology(&self, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        let Some(ref cache) = self.cache else {
+            return self.synthesize_chunk(TRUNCATED_APOLOGY, voice_id).await;
+        };
+        let key = TtsCache::key(TRUNCATED_APOLOGY, voice_id, self.provider.model());
+        if let Some(audio) = cache.get(&key).await {
+            return Ok(audio);
+        }
+        let audio = self.synthesize_chunk(TRUNCATED_APOLOGY, voice_id).await?;
+        cache.insert(&key, &audio).await;
+        Ok(audio)
+    }
+
+    async fn synthesize_chunk_with_retry(
+        &self,
+        text: &str,
+        voice_id: &str,
+    ) -> Result<Vec<u8>, TtsError> {
+        let mut attempt = 0;
+        loop {
+            match self.synthesize_chunk(text, voice_id).await {
+                Ok(audio) => return Ok(audio),
+                // Breaker open — retrying would only fail fast again
+                Err(e @ TtsError::Unavailable(_)) => return Err(e),
+                Err(e) if attempt >= self.chunk_retries => return Err(e),
+                Err(e) => {
+                    let delay = self.retry_backoff * 2u32.pow(attempt);
+                    tracing::debug!(attempt, ?delay, "Retrying TTS chunk: {e}");
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
     }
 
-    /// Synthesize a single chunk (must be <= MAX_CHARS).
+    /// Synthesize a single chunk (must be <= the vendor's `max_chars`).
     async fn synthesize_chunk(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| TtsError::Unavailable(e.to_string()))?;
+        let result = self.attempt(text, voice_id).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// One chunk request, to the vendor or the mock, with any injected faults.
+    async fn attempt(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
+        self.chaos
+            .before()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        #[cfg(feature = "mocks")]
+        let audio = match self.mock {
+            Some(ref mock) => mock.synthesize(text).await,
+            None => self.provider.synthesize(text, voice_id).await?,
+        };
+        #[cfg(not(feature = "mocks"))]
+        let audio = self.provider.synthesize(text, voice_id).await?;
+        self.chaos.drip(audio.len()).await;
+        Ok(audio)
+    }
+
+    /// Cheap credential check. Bypasses the breaker.
+    pub async fn probe(&self) -> Result<(), TtsError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        self.provider.probe().await
+    }
+}
+
+/// Inworld (`[tts] provider = "inworld"`), which speaks mu-law natively.
+pub struct InworldTts {
+    client: reqwest::Client,
+    api_key: String,
+    model: String,
+}
+
+/// Inworld's per-request character limit.
+const MAX_CHARS: usize = 2000;
+
+/// Inworld TTS response shape.
+#[derive(Deserialize)]
+struct TtsResponse {
+    #[serde(rename = "audioContent")]
+    audio_content: String,
+}
+
+impl InworldTts {
+    pub fn new(client: reqwest::Client, api_key: String, model: String) -> Self {
+        Self {
+            client,
+            api_key,
+            model,
+        }
+    }
+
+    /// Credential check: list voices.
+    async fn voices(&self) -> Result<(), TtsError> {
+        let resp = self
+            .client
+            .get("https://api.inworld.ai/tts/v1/voices")
+            .header("Authorization", format!("Basic {}", &self.api_key))
+            .send()
+            .await
+            .map_err(|e| TtsError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(TtsError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn request(&self, text: &str, voice_id: &str) -> Result<Vec<u8>, TtsError> {
         let body = serde_json::json!({
             "text": text,
             "voiceId": voice_id,
@@ -91,6 +354,28 @@ impl TtsClient {
     }
 }
 
+impl TtsProvider for InworldTts {
+    fn name(&self) -> &'static str {
+        "inworld"
+    }
+
+    fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn max_chars(&self) -> usize {
+        MAX_CHARS
+    }
+
+    fn synthesize<'a>(&'a self, text: &'a str, voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+        Box::pin(self.request(text, voice_id))
+    }
+
+    fn probe(&self) -> TtsFuture<'_, ()> {
+        Box::pin(self.voices())
+    }
+}
+
 /// Split text at sentence boundaries to stay under the character limit.
 ///
 /// Splits on `. `, `! `, `? ` boundaries. If a single sentence exceeds the
@@ -109,8 +394,18 @@ fn split_text(text: &str, max_chars: usize) -> Vec<&str> {
             break;
         }
 
+        // The limit, backed off to a char boundary. A limit too small for
+        // even the first char takes that char whole rather than stalling.
+        let mut limit = max_chars;
+        while !remaining.is_char_boundary(limit) {
+            limit -= 1;
+        }
+        if limit == 0 {
+            limit = remaining.chars().next().map_or(0, char::len_utf8);
+        }
+
         // Find the last sentence boundary within the limit
-        let search_slice = &remaining[..max_chars];
+        let search_slice = &remaining[..limit];
         let split_pos = search_slice
             .rmatch_indices(". ")
             .chain(search_slice.rmatch_indices("! "))
@@ -118,7 +413,7 @@ fn split_text(text: &str, max_chars: usize) -> Vec<&str> {
             .map(|(i, s)| i + s.len())
             .max();
 
-        let pos = split_pos.unwrap_or(max_chars);
+        let pos = split_pos.unwrap_or(limit);
         chunks.push(&remaining[..pos]);
         remaining = remaining[pos..].trim_start();
     }
@@ -132,11 +427,184 @@ pub enum TtsError {
     Request(String),
     #[error("API error: {0}")]
     Api(String),
+    #[error("TTS unavailable: {0}")]
+    Unavailable(String),
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use crate::config::BreakerConfig;
+
+    /// A vendor answering with one byte per character, counting requests.
+    struct Counting(Arc<AtomicUsize>);
+
+    impl TtsProvider for Counting {
+        fn name(&self) -> &'static str {
+            "counting"
+        }
+
+        fn model(&self) -> &str {
+            "m"
+        }
+
+        fn max_chars(&self) -> usize {
+            MAX_CHARS
+        }
+
+        fn synthesize<'a>(&'a self, text: &'a str, _voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+            self.0.fetch_add(1, Ordering::SeqCst);
+            Box::pin(async move { Ok(vec![0xff; text.len()]) })
+        }
+
+        fn probe(&self) -> TtsFuture<'_, ()> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    fn counting(requests: &Arc<AtomicUsize>) -> TtsClient {
+        TtsClient::new(
+            Box::new(Counting(Arc::clone(requests))),
+            "v".into(),
+            CircuitBreaker::new("test", &BreakerConfig::default()),
+        )
+    }
+
+    #[tokio::test]
+    async fn reuses_cached_phrases() {
+        let requests = Arc::new(AtomicUsize::new(0));
+        let cache = TtsCache::new(&toml::from_str("").unwrap());
+        let tts = counting(&requests).with_cache(Some(Arc::new(cache)));
+
+        for _ in 0..3 {
+            let audio = tts.synthesize_phrase("Hello", None).await.unwrap();
+            assert_eq!(audio.len(), 5);
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+        tts.synthesize_phrase("Hello", Some("w")).await.unwrap();
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+        // What's said on a call is never kept, however short
+        for _ in 0..2 {
+            tts.synthesize("Hello").await.unwrap();
+        }
+        assert_eq!(requests.load(Ordering::SeqCst), 4);
+    }
+
+    /// A vendor with a 20-char limit, answering with the chunk's first
+    /// byte once per character. Chunks in `failures` fail that many times
+    /// first. Records every request.
+    #[derive(Default)]
+    struct Scripted {
+        failures: std::sync::Mutex<std::collections::HashMap<&'static str, u32>>,
+        requests: Arc<std::sync::Mutex<Vec<String>>>,
+    }
+
+    impl TtsProvider for Scripted {
+        fn name(&self) -> &'static str {
+            "scripted"
+        }
+
+        fn model(&self) -> &str {
+            "m"
+        }
+
+        fn max_chars(&self) -> usize {
+            20
+        }
+
+        fn synthesize<'a>(&'a self, text: &'a str, _voice_id: &'a str) -> TtsFuture<'a, Vec<u8>> {
+            self.requests.lock().unwrap().push(text.to_string());
+            let fail = match self.failures.lock().unwrap().get_mut(text) {
+                Some(left) if *left > 0 => {
+                    *left -= 1;
+                    true
+                }
+                _ => false,
+            };
+            Box::pin(async move {
+                if fail {
+                    return Err(TtsError::Api("500".into()));
+                }
+                Ok(vec![text.as_bytes()[0]; text.len()])
+            })
+        }
+
+        fn probe(&self) -> TtsFuture<'_, ()> {
+            Box::pin(async { Ok(()) })
+        }
+    }
+
+    /// Three chunks: "First one here. ", "Second one here. ", "Third one here."
+    const REPLY: &str = "First one here. Second one here. Third one here.";
+
+    fn scripted(
+        failures: &[(&'static str, u32)],
+    ) -> (TtsClient, Arc<std::sync::Mutex<Vec<String>>>) {
+        let provider = Scripted::default();
+        provider
+            .failures
+            .lock()
+            .unwrap()
+            .extend(failures.iter().copied());
+        let requests = Arc::clone(&provider.requests);
+        let tts = TtsClient::new(
+            Box::new(provider),
+            "v".into(),
+            CircuitBreaker::new("test", &BreakerConfig::default()),
+        )
+        .with_retries(2, 0);
+        (tts, requests)
+    }
+
+    #[tokio::test]
+    async fn retries_a_failed_chunk() {
+        let (tts, requests) = scripted(&[("Second one here. ", 2)]);
+        let audio = tts.synthesize(REPLY).await.unwrap();
+        let expected: Vec<u8> = [(b'F', 16), (b'S', 17), (b'T', 15)]
+            .into_iter()
+            .flat_map(|(byte, len)| vec![byte; len])
+            .collect();
+        assert_eq!(audio, expected);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "First one here. ",
+                "Second one here. ",
+                "Second one here. ",
+                "Second one here. ",
+                "Third one here."
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_the_audio_before_a_chunk_that_keeps_failing() {
+        let (tts, requests) = scripted(&[("Second one here. ", u32::MAX)]);
+        let audio = tts.synthesize(REPLY).await.unwrap();
+        // What was said, then the apology; nothing after the failed chunk
+        let mut expected = vec![b'F'; 16];
+        expected.extend(vec![b'S'; TRUNCATED_APOLOGY.len()]);
+        assert_eq!(audio, expected);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            [
+                "First one here. ",
+                "Second one here. ",
+                "Second one here. ",
+                "Second one here. ",
+                TRUNCATED_APOLOGY
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn first_chunk_failing_fails_the_reply() {
+        let (tts, requests) = scripted(&[("First one here. ", u32::MAX)]);
+        assert!(matches!(tts.synthesize(REPLY).await, Err(TtsError::Api(_))));
+        assert_eq!(requests.lock().unwrap().len(), 3);
+    }
 
     #[test]
     fn short_text_not_split() {
@@ -160,4 +628,35 @@ mod tests {
         assert_eq!(chunks[0].len(), 2000);
         assert_eq!(chunks[1].len(), 1000);
     }
+
+    #[test]
+    fn hard_split_respects_char_boundaries() {
+        let text = "é".repeat(10);
+        let chunks = split_text(&text, 5);
+        assert_eq!(chunks, vec!["éé"; 5]);
+        assert_eq!(split_text("😀😀", 1), vec!["😀", "😀"]);
+    }
+
+    proptest::proptest! {
+        #[test]
+        fn chunks_fit_and_cover_the_text(
+            text in "([a-zé😀 ]{0,12}[.!?]? ){0,40}",
+            max_chars in 4usize..120,
+        ) {
+            let chunks = split_text(&text, max_chars);
+            let mut rest = text.as_str();
+            for (i, chunk) in chunks.iter().enumerate() {
+                // Slicing would have panicked off a char boundary; the
+                // limit only gives way when the text fits in one chunk
+                proptest::prop_assert!(chunk.len() <= max_chars || chunks.len() == 1);
+                // In order, with only whitespace dropped between chunks
+                if i > 0 {
+                    rest = rest.trim_start();
+                }
+                proptest::prop_assert!(rest.starts_with(chunk), "{chunk:?} not next in {rest:?}");
+                rest = &rest[chunk.len()..];
+            }
+            proptest::prop_assert!(rest.trim().is_empty());
+        }
+    }
 }
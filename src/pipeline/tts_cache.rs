@@ -0,0 +1,265 @@
+//! Synthesized audio kept for reuse (`[tts.cache]`).
+//!
+//! Every call opens with the same greeting, a failing stage apologizes with
+//! the same message and hold prompts repeat all day. Only those fixed
+//! phrases are kept, never what the caller or the brain said. Clips are
+//! kept by text, voice and model: in memory, least recently used out first,
+//! and in `dir` if set, where they outlive restarts.
+
+use std::collections::{HashMap, VecDeque};
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use sha2::{Digest, Sha256};
+
+use crate::config::TtsCacheConfig;
+
+const MB: u64 = 1024 * 1024;
+
+/// Mu-law clips by key, shared by every [`TtsClient`](super::tts::TtsClient).
+pub struct TtsCache {
+    memory: Mutex<Lru>,
+    dir: Option<PathBuf>,
+    max_disk_bytes: u64,
+    /// Bytes in `dir`, once counted. Held while writing, so two writers
+    /// don't both evict.
+    disk_bytes: tokio::sync::Mutex<Option<u64>>,
+}
+
+/// In-memory clips, least recently used at the front of `order`.
+struct Lru {
+    clips: HashMap<String, Arc<Vec<u8>>>,
+    order: VecDeque<String>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl Lru {
+    fn new(max_bytes: usize) -> Self {
+        Self {
+            clips: HashMap::new(),
+            order: VecDeque::new(),
+            bytes: 0,
+            max_bytes,
+        }
+    }
+
+    fn get(&mut self, key: &str) -> Option<Arc<Vec<u8>>> {
+        let clip = Arc::clone(self.clips.get(key)?);
+        self.touch(key);
+        Some(clip)
+    }
+
+    /// Mark `key` most recently used. Linear, but a cache of phrases holds
+    /// hundreds of clips at most.
+    fn touch(&mut self, key: &str) {
+        if let Some(i) = self.order.iter().position(|k| k == key) {
+            if let Some(key) = self.order.remove(i) {
+                self.order.push_back(key);
+            }
+        }
+    }
+
+    fn insert(&mut self, key: String, clip: Arc<Vec<u8>>) {
+        if clip.len() > self.max_bytes {
+            return;
+        }
+        self.bytes += clip.len();
+        match self.clips.insert(key.clone(), clip) {
+            Some(old) => {
+                self.bytes -= old.len();
+                self.touch(&key);
+            }
+            None => self.order.push_back(key),
+        }
+        while self.bytes > self.max_bytes {
+            let Some(oldest) = self.order.pop_front() else {
+                break;
+            };
+            if let Some(clip) = self.clips.remove(&oldest) {
+                self.bytes -= clip.len();
+            }
+        }
+    }
+}
+
+impl TtsCache {
+    pub fn new(config: &TtsCacheConfig) -> Self {
+        Self {
+            memory: Mutex::new(Lru::new((config.memory_mb * MB) as usize)),
+            dir: config.dir.clone(),
+            max_disk_bytes: config.disk_mb * MB,
+            disk_bytes: tokio::sync::Mutex::new(None),
+        }
+    }
+
+    /// The key of `text` spoken in `voice_id` by `model`.
+    pub fn key(text: &str, voice_id: &str, model: &str) -> String {
+        let mut hash = Sha256::new();
+        for part in [model, voice_id, text] {
+            hash.update(part.as_bytes());
+            hash.update([0]);
+        }
+        hex::encode(hash.finalize())
+    }
+
+    /// The clip kept under `key`, from memory or else from disk.
+    pub async fn get(&self, key: &str) -> Option<Vec<u8>> {
+        if let Some(clip) = self.lru().get(key) {
+            return Some(clip.to_vec());
+        }
+        let dir = self.dir.as_ref()?;
+        let clip = tokio::fs::read(clip_path(dir, key)).await.ok()?;
+        self.lru().insert(key.to_string(), Arc::new(clip.clone()));
+        Some(clip)
+    }
+
+    /// Keep `clip` under `key`. A failed disk write only loses the copy
+    /// on disk.
+    pub async fn insert(&self, key: &str, clip: &[u8]) {
+        self.lru().insert(key.to_string(), Arc::new(clip.to_vec()));
+        let Some(ref dir) = self.dir else {
+            return;
+        };
+        if let Err(e) = self.store(dir, key, clip).await {
+            tracing::warn!(dir = %dir.display(), "TTS cache write failed: {e}");
+        }
+    }
+
+    fn lru(&self) -> MutexGuard<'_, Lru> {
+        self.memory.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Write `clip` to `dir`, then trim the oldest clips past the limit.
+    async fn store(&self, dir: &Path, key: &str, clip: &[u8]) -> std::io::Result<()> {
+        let mut disk_bytes = self.disk_bytes.lock().await;
+        tokio::fs::create_dir_all(dir).await?;
+        let path = clip_path(dir, key);
+        // Renamed into place, so a crash never leaves half a clip to play
+        let partial = path.with_extension("partial");
+        tokio::fs::write(&partial, clip).await?;
+        tokio::fs::rename(&partial, &path).await?;
+
+        // Counted once, then kept up to date; a rewritten clip is counted
+        // twice until the next trim recounts
+        let used = match *disk_bytes {
+            Some(used) => used + clip.len() as u64,
+            None => clips(dir).await?.iter().map(|(_, len)| len).sum(),
+        };
+        *disk_bytes = Some(match used > self.max_disk_bytes {
+            true => trim(dir, self.max_disk_bytes).await?,
+            false => used,
+        });
+        Ok(())
+    }
+}
+
+fn clip_path(dir: &Path, key: &str) -> PathBuf {
+    dir.join(format!("{key}.ulaw"))
+}
+
+/// Clips in `dir` with their sizes, oldest first.
+async fn clips(dir: &Path) -> std::io::Result<Vec<(PathBuf, u64)>> {
+    let mut entries = tokio::fs::read_dir(dir).await?;
+    let mut clips = Vec::new();
+    while let Some(entry) = entries.next_entry().await? {
+        let path = entry.path();
+        if path.extension() != Some(OsStr::new("ulaw")) {
+            continue;
+        }
+        let meta = entry.metadata().await?;
+        clips.push((meta.modified()?, path, meta.len()));
+    }
+    clips.sort();
+    Ok(clips
+        .into_iter()
+        .map(|(_, path, len)| (path, len))
+        .collect())
+}
+
+/// Remove the oldest clips in `dir` until it holds `max_bytes` at most.
+/// Returns the bytes left.
+async fn trim(dir: &Path, max_bytes: u64) -> std::io::Result<u64> {
+    let clips = clips(dir).await?;
+    let mut used: u64 = clips.iter().map(|(_, len)| len).sum();
+    for (path, len) in clips {
+        if used <= max_bytes {
+            break;
+        }
+        tokio::fs::remove_file(&path).await?;
+        used -= len;
+    }
+    Ok(used)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cache(toml: &str) -> TtsCache {
+        TtsCache::new(&toml::from_str(toml).unwrap())
+    }
+
+    #[test]
+    fn drops_the_least_recently_used_clip() {
+        let mut lru = Lru::new(10);
+        lru.insert("a".into(), Arc::new(vec![0; 4]));
+        lru.insert("b".into(), Arc::new(vec![0; 4]));
+        assert!(lru.get("a").is_some());
+        lru.insert("c".into(), Arc::new(vec![0; 4]));
+        assert!(lru.get("b").is_none());
+        assert!(lru.get("a").is_some() && lru.get("c").is_some());
+        assert_eq!(lru.bytes, 8);
+
+        // Too big to keep at all
+        lru.insert("d".into(), Arc::new(vec![0; 11]));
+        assert!(lru.get("d").is_none());
+        assert_eq!(lru.bytes, 8);
+    }
+
+    #[test]
+    fn keys_by_text_voice_and_model() {
+        let key = TtsCache::key("Hello", "Olivia", "inworld-tts-1.5-max");
+        assert_eq!(key, TtsCache::key("Hello", "Olivia", "inworld-tts-1.5-max"));
+        assert_ne!(key, TtsCache::key("Hello", "Diego", "inworld-tts-1.5-max"));
+        assert_ne!(
+            key,
+            TtsCache::key("Hello", "Olivia", "inworld-tts-1.5-mini")
+        );
+        assert_ne!(
+            key,
+            TtsCache::key("Hello.", "Olivia", "inworld-tts-1.5-max")
+        );
+    }
+
+    #[tokio::test]
+    async fn keeps_clips_on_disk_across_restarts() {
+        let dir = tempfile::tempdir().unwrap();
+        let toml = format!("dir = {:?}", dir.path().display().to_string());
+        let key = TtsCache::key("Hello", "Olivia", "m");
+
+        cache(&toml).insert(&key, &[1, 2, 3]).await;
+        assert_eq!(cache(&toml).get(&key).await, Some(vec![1, 2, 3]));
+        assert_eq!(cache("").get(&key).await, None);
+    }
+
+    #[tokio::test]
+    async fn trims_the_oldest_clips_past_the_disk_limit() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = cache(&format!(
+            "dir = {:?}\ndisk_mb = 1\nmemory_mb = 0",
+            dir.path().display().to_string()
+        ));
+        let clip = vec![0; (MB as usize) * 2 / 5];
+        for text in ["one", "two", "three"] {
+            cache.insert(&TtsCache::key(text, "v", "m"), &clip).await;
+            // Apart in mtime
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+        assert_eq!(cache.get(&TtsCache::key("one", "v", "m")).await, None);
+        for text in ["two", "three"] {
+            assert!(cache.get(&TtsCache::key(text, "v", "m")).await.is_some());
+        }
+    }
+}
@@ -0,0 +1,1111 @@
+//! Shared STT → brain → TTS turn.
+//!
+//! Used by both the Twilio and Discord stream handlers. Each handler owns its
+//! transport concerns (hold music, framing, speaking flag); this module owns
+//! the provider round trip and the global concurrency gate around it.
+
+use std::borrow::Cow;
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+use tokio::sync::mpsc;
+
+use crate::agents::Agent;
+use crate::config::{LowConfidence, PromptConfig, SttConfig};
+use crate::events::CallEvent;
+use crate::pipeline::actions::{self, Action};
+use crate::pipeline::audio;
+use crate::pipeline::bridge;
+use crate::pipeline::concise::Concise;
+use crate::pipeline::diarize::{self, Voiceprint};
+use crate::pipeline::dictation;
+use crate::pipeline::directives::{self, Segment};
+use crate::pipeline::failover::Fallback;
+use crate::pipeline::filter;
+use crate::pipeline::language;
+use crate::pipeline::middleware::BrainRequest;
+use crate::pipeline::prompt;
+use crate::pipeline::sentences::Sentences;
+use crate::pipeline::stt::{SpeakerRun, Transcription};
+use crate::pipeline::transliterate;
+use crate::registry::{CallProfile, Transport};
+use crate::{AppState, Brain};
+
+/// Samples per second of the PCM handed over by the VAD.
+const PCM_SAMPLE_RATE: u64 = 8000;
+
+/// A pipeline stage with its own time budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Stage {
+    Stt,
+    Brain,
+    Tts,
+    /// The whole turn, from admission to TTS audio.
+    Turn,
+}
+
+impl fmt::Display for Stage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Stage::Stt => "stt",
+            Stage::Brain => "brain",
+            Stage::Tts => "tts",
+            Stage::Turn => "turn",
+        })
+    }
+}
+
+/// How far a turn got and how long each stage took, for
+/// `POST /debug/pipeline`.
+#[derive(Debug, Default)]
+pub struct TurnTrace {
+    /// Time spent waiting for a pipeline slot.
+    pub queued: Duration,
+    /// The last stage started; the one that failed if the turn did.
+    pub stage: Option<Stage>,
+    /// Whisper's transcript, before the hallucination and profanity filters.
+    pub transcript: Option<String>,
+    /// Language Whisper detected, when it was asked to.
+    pub language: Option<String>,
+    /// How sure STT was of the transcript, if it said.
+    pub confidence: Option<f32>,
+    /// Who spoke, with `[diarization]` on.
+    pub speaker: Option<u32>,
+    pub response: Option<String>,
+    /// Each stage that finished, in order.
+    pub timings: Vec<(Stage, Duration)>,
+    started: Option<Instant>,
+}
+
+impl TurnTrace {
+    fn begin(&mut self, stage: Stage) {
+        self.stage = Some(stage);
+        self.started = Some(Instant::now());
+    }
+
+    fn end(&mut self) {
+        if let (Some(stage), Some(started)) = (self.stage, self.started.take()) {
+            self.timings.push((stage, started.elapsed()));
+        }
+    }
+
+    /// Record a stage timed elsewhere (the brain and TTS overlap).
+    fn record(&mut self, stage: Stage, took: Duration) {
+        self.started = None;
+        self.timings.push((stage, took));
+    }
+}
+
+/// What the caller said, as a turn starts from it.
+#[derive(Debug, Clone, Copy)]
+pub enum Heard<'a> {
+    /// PCM of an utterance cut by the VAD, still to transcribe.
+    Audio(&'a [i16]),
+    /// An utterance a live STT session (`[stt] streaming`) already
+    /// transcribed, how long it was spoken for, the vendor's confidence and
+    /// who said what, if it diarizes.
+    Transcript {
+        text: &'a str,
+        duration_ms: u64,
+        confidence: Option<f32>,
+        speakers: &'a [SpeakerRun],
+    },
+}
+
+impl Heard<'_> {
+    fn duration_ms(&self) -> u64 {
+        match *self {
+            Heard::Audio(pcm) => pcm.len() as u64 * 1000 / PCM_SAMPLE_RATE,
+            Heard::Transcript { duration_ms, .. } => duration_ms,
+        }
+    }
+}
+
+#[derive(Debug, thiserror::Error)]
+pub enum TurnError {
+    #[error("{stage} stage timed out after {budget_ms}ms")]
+    Timeout { stage: Stage, budget_ms: u64 },
+}
+
+/// Run `fut` within the stage's budget. A budget of 0 means no limit.
+///
+/// On expiry the stage future is dropped (cancelling its in-flight request)
+/// and a `TurnError::Timeout` naming the stage is returned.
+async fn within_budget<T, E, F>(
+    stage: Stage,
+    budget_ms: u64,
+    call_sid: &str,
+    fut: F,
+) -> Result<T, Box<dyn std::error::Error + Send + Sync>>
+where
+    F: Future<Output = Result<T, E>>,
+    E: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    if budget_ms == 0 {
+        return fut.await.map_err(Into::into);
+    }
+    match tokio::time::timeout(Duration::from_millis(budget_ms), fut).await {
+        Ok(result) => result.map_err(Into::into),
+        Err(_) => {
+            tracing::warn!(call_sid, %stage, budget_ms, "Stage blew its time budget");
+            Err(TurnError::Timeout { stage, budget_ms }.into())
+        }
+    }
+}
+
+/// Synthesize speech in the call's voice within the configured TTS budget,
+/// after the profanity filter and the `[SPELL]` and `[[tts.transliterate]]`
+/// rewrites.
+///
+/// Used for greetings and fallback messages as well as turn responses, so a
+/// hung TTS request can't hold the speaking flag forever.
+pub async fn synthesize(
+    state: &AppState,
+    call_sid: &str,
+    text: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    speak_text(state, call_sid, text, false).await
+}
+
+/// [`synthesize`] for a fixed phrase — a greeting, apology or degraded-mode
+/// message — reused from `[tts.cache]`. Never for what the caller or the
+/// brain said.
+pub async fn synthesize_phrase(
+    state: &AppState,
+    call_sid: &str,
+    text: &str,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    speak_text(state, call_sid, text, true).await
+}
+
+async fn speak_text(
+    state: &AppState,
+    call_sid: &str,
+    text: &str,
+    phrase: bool,
+) -> Result<Vec<u8>, Box<dyn std::error::Error + Send + Sync>> {
+    let profile = state.call_registry.profile(call_sid).await;
+    let tts_client = state.tts_for(&profile);
+    let text = state.profanity.clean_response(text);
+    let text = transliterate::apply(&text, &state.config.tts);
+    let tts = async {
+        match (phrase, state.voice_for(&profile)) {
+            (true, voice_id) => tts_client.synthesize_phrase(&text, voice_id).await,
+            (false, Some(voice_id)) => tts_client.synthesize_with_voice(&text, voice_id).await,
+            (false, None) => tts_client.synthesize(&text).await,
+        }
+    };
+    within_budget(Stage::Tts, state.config.timeouts.tts_ms, call_sid, tts).await
+}
+
+/// Run STT → brain → TTS for one utterance, passing the TTS audio (mu-law)
+/// to `audio` a sentence at a time as it's synthesized. STT is skipped for
+/// utterances heard already transcribed. Returns the
+/// `[actions]` the brain asked for, for the caller to carry out.
+///
+/// Waits for a global pipeline slot first, so under load the turn queues
+/// instead of stampeding the providers. Once admitted, each stage and the
+/// turn as a whole run within the `[timeouts]` budgets. Does NOT send
+/// audio — the caller handles sequencing with hold music. A failure is
+/// published as `PipelineError`; audio already passed on stays valid.
+pub async fn run_turn(
+    heard: Heard<'_>,
+    call_sid: &str,
+    transport: Transport,
+    state: &AppState,
+    audio: mpsc::UnboundedSender<Vec<u8>>,
+) -> TurnResult {
+    let mut trace = TurnTrace::default();
+    let result = speak_turn(heard, call_sid, transport, state, &mut trace, audio).await;
+    if let Err(ref e) = result {
+        state.events.publish(CallEvent::PipelineError {
+            call_sid: call_sid.to_string(),
+            stage: trace.stage,
+            error: e.to_string(),
+        });
+    }
+    result
+}
+
+/// A turn whose audio is handed over a sentence at a time.
+pub struct SpokenTurn<'a> {
+    turn: Pin<Box<dyn Future<Output = TurnResult> + Send + 'a>>,
+    audio: mpsc::UnboundedReceiver<Vec<u8>>,
+    result: Option<TurnResult>,
+}
+
+type TurnResult = Result<Vec<Action>, Box<dyn std::error::Error + Send + Sync>>;
+
+impl SpokenTurn<'_> {
+    /// The next piece of audio to play, or `None` once the turn is over.
+    pub async fn next(&mut self) -> Option<Vec<u8>> {
+        if self.result.is_none() {
+            tokio::select! {
+                Some(mulaw) = self.audio.recv() => return Some(mulaw),
+                result = &mut self.turn => self.result = Some(result),
+            }
+        }
+        // Whatever was synthesized before the turn ended
+        self.audio.recv().await
+    }
+
+    /// How the turn went, with any actions to take. Call once `next` has
+    /// returned `None`.
+    pub fn finish(self) -> TurnResult {
+        self.result.unwrap_or(Ok(Vec::new()))
+    }
+}
+
+/// `run_turn`, with its audio read from the returned [`SpokenTurn`]. The
+/// turn runs while `next` is awaited.
+pub fn speak<'a>(
+    heard: Heard<'a>,
+    call_sid: &'a str,
+    transport: Transport,
+    state: &'a AppState,
+) -> SpokenTurn<'a> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    SpokenTurn {
+        turn: Box::pin(run_turn(heard, call_sid, transport, state, tx)),
+        audio: rx,
+        result: None,
+    }
+}
+
+/// `run_turn`, recording its progress in `trace` and returning all the
+/// audio at once (if any). Filled in as far as the turn got, whether it
+/// fails, times out or returns no audio.
+pub async fn run_turn_traced(
+    pcm_data: &[i16],
+    call_sid: &str,
+    transport: Transport,
+    state: &AppState,
+    trace: &mut TurnTrace,
+) -> Result<Option<Vec<u8>>, Box<dyn std::error::Error + Send + Sync>> {
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let heard = Heard::Audio(pcm_data);
+    speak_turn(heard, call_sid, transport, state, trace, tx).await?;
+    let mut mulaw = Vec::new();
+    while let Ok(chunk) = rx.try_recv() {
+        mulaw.extend(chunk);
+    }
+    Ok((!mulaw.is_empty()).then_some(mulaw))
+}
+
+async fn speak_turn(
+    heard: Heard<'_>,
+    call_sid: &str,
+    transport: Transport,
+    state: &AppState,
+    trace: &mut TurnTrace,
+    audio: mpsc::UnboundedSender<Vec<u8>>,
+) -> TurnResult {
+    let utterance_ms = heard.duration_ms();
+    let short = utterance_ms < state.config.pipeline.short_turn_ms;
+
+    let queued_at = Instant::now();
+    let _permit = state.turn_limiter.acquire(call_sid, short).await;
+    let waited = queued_at.elapsed();
+    trace.queued = waited;
+    if waited >= Duration::from_millis(100) {
+        tracing::info!(
+            call_sid,
+            waited_ms = waited.as_millis() as u64,
+            utterance_ms,
+            short,
+            "Turn waited for a pipeline slot"
+        );
+    }
+
+    let budget_ms = state.config.timeouts.turn_ms;
+    within_budget(
+        Stage::Turn,
+        budget_ms,
+        call_sid,
+        run_stages(heard, call_sid, transport, state, trace, audio),
+    )
+    .await
+}
+
+async fn run_stages(
+    heard: Heard<'_>,
+    call_sid: &str,
+    transport: Transport,
+    state: &AppState,
+    trace: &mut TurnTrace,
+    audio: mpsc::UnboundedSender<Vec<u8>>,
+) -> TurnResult {
+    let timeouts = &state.config.timeouts;
+    let utterance_ms = heard.duration_ms();
+    let mut profile = state.call_registry.profile(call_sid).await;
+
+    // With detection on, utterances go without a hint until enough
+    // agreeing detections lock the language in. Code-switching callers are
+    // never locked.
+    let languages = &state.config.language;
+    let hint = match profile.language {
+        _ if languages.code_switching => None,
+        Some(ref known) => Some(known.as_str()),
+        None if languages.detect => None,
+        None => Some(languages.default.as_str()),
+    };
+    let (transcription, pcm_data) = match heard {
+        Heard::Audio(pcm_data) => {
+            // 1. PCM → WAV
+            let wav_data = stt_wav(&state.config.stt, pcm_data);
+            tracing::debug!(wav_bytes = wav_data.len(), "Encoded WAV");
+
+            // 2. WAV → Text, primed with the last exchange
+            let context = match state.config.stt.prime {
+                true => state.call_registry.last_exchange(call_sid).await,
+                false => None,
+            };
+            trace.begin(Stage::Stt);
+            let transcription = within_budget(
+                Stage::Stt,
+                timeouts.stt_ms,
+                call_sid,
+                state.stt_for(&profile, transport).transcribe_after(
+                    wav_data,
+                    hint,
+                    context.as_deref(),
+                ),
+            )
+            .await?;
+            trace.end();
+            (transcription, Some(pcm_data))
+        }
+        // Live sessions transcribe in the language they were opened with
+        Heard::Transcript {
+            text,
+            confidence,
+            speakers,
+            ..
+        } => (
+            Transcription {
+                text: text.to_string(),
+                language: None,
+                confidence,
+                speakers: speakers.to_vec(),
+            },
+            None,
+        ),
+    };
+    let detecting = hint.is_none() && pcm_data.is_some();
+    let trimmed = transcription.text.trim();
+    trace.transcript = Some(trimmed.to_string());
+    trace.language = transcription.language.clone();
+    trace.confidence = transcription.confidence;
+    if trimmed.is_empty() {
+        tracing::debug!("Empty transcript, skipping");
+        return Ok(Vec::new());
+    }
+    if is_whisper_hallucination(trimmed) {
+        tracing::debug!(transcript = %trimmed, "Filtered whisper hallucination");
+        return Ok(Vec::new());
+    }
+    // The vendor heard several people in this one utterance: say who said
+    // what
+    let vendor_labels = state.config.diarization.as_ref().is_some_and(|d| d.vendor);
+    let labelled = match vendor_labels {
+        true => diarize::label_runs(&transcription.speakers),
+        false => None,
+    };
+    let trimmed = labelled.as_deref().unwrap_or(trimmed);
+    let dictated = dictate(state, trimmed);
+    let filtered = state.profanity.mask_transcript(&dictated);
+    let trimmed = filtered.as_ref();
+
+    // Too unsure of the words to answer them
+    let stt = &state.config.stt;
+    if let (Some(min), Some(confidence)) = (stt.min_confidence, transcription.confidence) {
+        if confidence < min {
+            tracing::info!(call_sid, confidence, transcript = %trimmed, "Low-confidence transcript");
+            if stt.low_confidence == LowConfidence::Confirm {
+                state
+                    .call_registry
+                    .ask_to_confirm(call_sid, trimmed.to_string())
+                    .await;
+                let question = stt.confirm_prompt.replace("{transcript}", trimmed);
+                trace.begin(Stage::Tts);
+                let (mulaw, _) = speak_response(state, call_sid, &question).await?;
+                trace.end();
+                let _ = audio.send(mulaw);
+            }
+            return Ok(Vec::new());
+        }
+    }
+
+    // Several people on one phone: label the turn with its speaker
+    let speaker = match (&state.config.diarization, pcm_data) {
+        _ if labelled.is_some() => None,
+        (Some(_), _) if vendor_labels && !transcription.speakers.is_empty() => {
+            Some(transcription.speakers[0].speaker)
+        }
+        (Some(diarization), Some(pcm_data)) => match Voiceprint::of(pcm_data) {
+            Some(voice) => {
+                let learn = utterance_ms >= diarization.min_utterance_ms;
+                state
+                    .call_registry
+                    .identify_speaker(call_sid, &voice, diarization, learn)
+                    .await
+            }
+            None => None,
+        },
+        _ => None,
+    };
+    trace.speaker = speaker;
+    tracing::info!(call_sid, ?transport, speaker, transcript = %trimmed, "Transcribed");
+    state.events.publish(CallEvent::Transcript {
+        call_sid: call_sid.to_string(),
+        text: trimmed.to_string(),
+        speaker,
+    });
+
+    if detecting {
+        let detected = transcription
+            .language
+            .filter(|l| languages.supported.is_empty() || languages.supported.contains(l))
+            .unwrap_or_else(|| languages.default.clone());
+        if languages.code_switching {
+            // The voice follows each turn's language
+            state
+                .call_registry
+                .set_language(call_sid, detected.clone())
+                .await;
+        } else if utterance_ms >= languages.lock_min_ms {
+            state
+                .call_registry
+                .observe_language(call_sid, &detected, languages.lock_after)
+                .await;
+        }
+        // Answer this turn in the detected language even before the lock
+        profile.language = Some(detected);
+    }
+
+    // 3. Text → brain response
+    // Only outbound calls have metadata; don't take the write lock for
+    // every utterance of every other call
+    let call_meta = if state.call_metas.read().await.contains_key(call_sid) {
+        state.call_metas.write().await.remove(call_sid)
+    } else {
+        None
+    };
+    let call_context = call_meta.as_ref().and_then(|m| m.context.as_deref());
+    if call_context.is_some() {
+        tracing::info!(call_sid, "Injecting call context into first prompt");
+    }
+    let call_context = match profile.language {
+        Some(ref code) if languages.code_switching => Some(join_context(
+            call_context,
+            &language::code_switching_instruction(code),
+        )),
+        Some(ref code) => Some(join_context(call_context, &language::instruction(code))),
+        None => call_context.map(str::to_string),
+    };
+    let call_context = match speaker.is_some() || labelled.is_some() {
+        true => Some(join_context(call_context.as_deref(), diarize::INSTRUCTION)),
+        false => call_context,
+    };
+    // Actions go through the Twilio API, so only phone calls get them
+    let acting = match state.config.actions {
+        Some(ref config) if transport == Transport::Twilio => actions::instruction(config),
+        _ => None,
+    };
+    let call_context = match acting {
+        Some(ref instruction) => Some(join_context(call_context.as_deref(), instruction)),
+        None => call_context,
+    };
+    // The answer to "did you say ...?"
+    let call_context = match state.call_registry.take_unconfirmed(call_sid).await {
+        Some(doubtful) => Some(join_context(
+            call_context.as_deref(),
+            &format!(
+                "The caller's last words were hard to make out; you asked whether \
+                 they said \"{doubtful}\". This is their reply."
+            ),
+        )),
+        None => call_context,
+    };
+    // A caller we've talked to before: what we remember, on the first turn
+    let call_context = match state.memory.recall(call_sid, &profile).await {
+        Some(remembered) => Some(join_context(call_context.as_deref(), &remembered)),
+        None => call_context,
+    };
+    let mut request = BrainRequest {
+        call_sid: call_sid.to_string(),
+        transcript: trimmed.to_string(),
+        context: call_context,
+    };
+    state.middleware.before(&mut request).await;
+
+    let question = Question {
+        transport,
+        transcript: &request.transcript,
+        speaker,
+        context: request.context.as_deref(),
+        caller: caller(&profile),
+        agent: profile.agent.as_deref(),
+        system_prompt: profile.system_prompt.as_deref(),
+        tools: profile
+            .allowed_tools
+            .as_deref()
+            .unwrap_or(&state.config.llm.allowed_tools),
+    };
+
+    // 4. Response → TTS audio (raw mu-law bytes from Inworld), a sentence
+    // at a time, while the rest of the response is still being written
+    trace.begin(Stage::Brain);
+    let (deltas_tx, mut deltas_rx) = mpsc::unbounded_channel();
+    let answer = async {
+        let _turn = state.brain_queue.turn(call_sid).await;
+        let started = Instant::now();
+        let response = ask_streaming(state, call_sid, &question, deltas_tx).await;
+        (response, started.elapsed())
+    };
+    let speak = async {
+        let mut sentences = Sentences::default();
+        let mut concise = state.config.concise.as_ref().map(Concise::new);
+        let mut took = Duration::ZERO;
+        let mut requested = Vec::new();
+        let mut open = true;
+        while open {
+            let ready = match deltas_rx.recv().await {
+                Some(delta) => sentences.push(&delta),
+                None => {
+                    open = false;
+                    sentences.finish().into_iter().collect()
+                }
+            };
+            for sentence in ready {
+                let sentence = match acting {
+                    Some(_) => {
+                        let (speech, found) = actions::extract(&sentence);
+                        requested.extend(found);
+                        speech
+                    }
+                    None => sentence,
+                };
+                let sentence = match concise {
+                    Some(ref mut concise) => match concise.clean(&sentence) {
+                        Some(sentence) => sentence,
+                        None => continue,
+                    },
+                    None => sentence,
+                };
+                let sentence = match state.config.filter {
+                    Some(ref config) => filter::redact(config, &sentence).into_owned(),
+                    None => sentence,
+                };
+                if sentence.is_empty() {
+                    continue;
+                }
+                let started = Instant::now();
+                let (mulaw, press) = speak_response(state, call_sid, &sentence).await?;
+                requested.extend(press);
+                took += started.elapsed();
+                tracing::debug!(tts_bytes = mulaw.len(), "TTS audio generated");
+                // A caller who hung up mid-answer stops listening
+                let _ = audio.send(mulaw);
+            }
+        }
+        Ok::<_, Box<dyn std::error::Error + Send + Sync>>((took, requested))
+    };
+    let ((response, brain_took), spoken) = tokio::join!(answer, speak);
+
+    let response = response?;
+    trace.record(Stage::Brain, brain_took);
+    state
+        .memory
+        .note(call_sid, &request.transcript, &response)
+        .await;
+    if state.config.stt.prime {
+        state
+            .call_registry
+            .set_last_exchange(call_sid, trimmed, &response)
+            .await;
+    }
+    tracing::info!(call_sid, response_len = response.len(), "Brain response");
+    state.events.publish(CallEvent::Response {
+        call_sid: call_sid.to_string(),
+        text: response.clone(),
+    });
+    trace.response = Some(response);
+
+    trace.begin(Stage::Tts);
+    let (tts_took, requested) = spoken?;
+    trace.record(Stage::Tts, tts_took);
+    if !requested.is_empty() {
+        tracing::info!(call_sid, actions = ?requested, "Brain asked for actions");
+    }
+
+    Ok(requested)
+}
+
+/// Transcribe one utterance spoken in `from` and have the brain translate
+/// it into `to`, for interpreter mode.
+///
+/// Shares the pipeline gate and budgets with `run_turn`. Returns `None` for
+/// empty or hallucinated transcripts.
+pub async fn run_translation(
+    pcm_data: &[i16],
+    call_sid: &str,
+    from: &str,
+    to: &str,
+    state: &AppState,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let utterance_ms = pcm_data.len() as u64 * 1000 / PCM_SAMPLE_RATE;
+    let short = utterance_ms < state.config.pipeline.short_turn_ms;
+    let _permit = state.turn_limiter.acquire(call_sid, short).await;
+
+    within_budget(
+        Stage::Turn,
+        state.config.timeouts.turn_ms,
+        call_sid,
+        translation_stages(pcm_data, call_sid, from, to, state),
+    )
+    .await
+}
+
+async fn translation_stages(
+    pcm_data: &[i16],
+    call_sid: &str,
+    from: &str,
+    to: &str,
+    state: &AppState,
+) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+    let wav_data = stt_wav(&state.config.stt, pcm_data);
+    let profile = state.call_registry.profile(call_sid).await;
+    let transcription = within_budget(
+        Stage::Stt,
+        state.config.timeouts.stt_ms,
+        call_sid,
+        state
+            .stt_for(&profile, Transport::Twilio)
+            .transcribe_in(wav_data, Some(from)),
+    )
+    .await?;
+    let trimmed = transcription.text.trim();
+    if trimmed.is_empty() || is_whisper_hallucination(trimmed) {
+        return Ok(None);
+    }
+    let dictated = dictate(state, trimmed);
+    let filtered = state.profanity.mask_transcript(&dictated);
+    let trimmed = filtered.as_ref();
+    tracing::info!(call_sid, from, to, transcript = %trimmed, "Transcribed for translation");
+
+    let instruction = language::translation(from, to);
+    let question = Question {
+        transport: Transport::Twilio,
+        transcript: trimmed,
+        speaker: None,
+        context: Some(&instruction),
+        caller: caller(&profile),
+        agent: None,
+        system_prompt: None,
+        tools: &[],
+    };
+    let _turn = state.brain_queue.turn(call_sid).await;
+    let translation = ask_with_fallback(state, call_sid, &question).await?;
+    Ok(Some(translation))
+}
+
+/// What the brain is asked on one turn.
+struct Question<'a> {
+    transport: Transport,
+    transcript: &'a str,
+    /// Who said it, with `[diarization]` on.
+    speaker: Option<u32>,
+    context: Option<&'a str>,
+    /// The contact's name or the number on the other end, if known.
+    caller: Option<&'a str>,
+    agent: Option<&'a Agent>,
+    /// Added to the system prompt for this call (`/api/call`).
+    system_prompt: Option<&'a str>,
+    /// Tools the brain may use (bridge-echo only).
+    tools: &'a [String],
+}
+
+impl Question<'_> {
+    /// The agent's own brain, or the shared one.
+    fn brain<'s>(&'s self, state: &'s AppState) -> &'s Brain {
+        self.agent
+            .and_then(|a| a.brain.as_ref())
+            .unwrap_or(&state.brain)
+    }
+
+    /// The system prompt replacing the brain's default, if any: the agent's
+    /// own, with the call's addition appended.
+    fn system_prompt(&self, state: &AppState) -> Option<String> {
+        let base = self.agent.and_then(|a| a.system_prompt.as_deref());
+        match self.system_prompt {
+            Some(extra) => Some(match base.unwrap_or(&*state.system_prompt) {
+                "" => extra.to_string(),
+                base => format!("{base}\n\n{extra}"),
+            }),
+            None => base.map(str::to_string),
+        }
+    }
+
+    /// What bridge-echo is told besides the words.
+    fn metadata(&self) -> bridge::Metadata<'_> {
+        bridge::Metadata {
+            context: self.context,
+            agent: self.agent.map(|a| a.name.as_str()),
+            system_prompt: self.system_prompt,
+            tools: self.tools,
+        }
+    }
+
+    /// The trust-wrapped prompt, for brains that take one.
+    fn prompt(&self, config: &PromptConfig) -> String {
+        prompt::build(
+            config,
+            &prompt::Parts {
+                transport: self.transport,
+                transcript: self.transcript,
+                speaker: self.speaker,
+                context: self.context,
+                caller: self.caller,
+                agent: self.agent,
+            },
+        )
+    }
+
+    /// The transcript, labelled with its speaker if known.
+    fn labelled(&self) -> Cow<'_, str> {
+        match self.speaker {
+            Some(speaker) => Cow::Owned(diarize::label(speaker, self.transcript)),
+            None => Cow::Borrowed(self.transcript),
+        }
+    }
+}
+
+/// An utterance as uploaded to STT, through `[stt.preprocess]` if set.
+pub(crate) fn stt_wav(config: &SttConfig, pcm_data: &[i16]) -> Vec<u8> {
+    match config.preprocess {
+        Some(ref preprocess) => audio::pcm_to_wav(&audio::preprocess(pcm_data, preprocess)),
+        None => audio::pcm_to_wav(pcm_data),
+    }
+}
+
+/// Dictated digits and addresses as written, with `[stt] dictation` on.
+fn dictate<'t>(state: &AppState, transcript: &'t str) -> Cow<'t, str> {
+    match state.config.stt.dictation {
+        true => dictation::apply(transcript),
+        false => Cow::Borrowed(transcript),
+    }
+}
+
+/// Budget for one attempt on `brain`: its `[brain.timeout_ms]` entry, or
+/// `timeouts.brain_ms`.
+fn brain_budget(state: &AppState, brain: &Brain) -> u64 {
+    state
+        .config
+        .brain
+        .budget_ms(brain.provider(), state.config.timeouts.brain_ms)
+}
+
+/// Ask the call's brain (the agent's own, or the shared one), falling back
+/// to the rest of `[brain] providers` and `[llm.fallback]` if it fails.
+async fn ask_with_fallback(
+    state: &AppState,
+    call_sid: &str,
+    question: &Question<'_>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let brain = question.brain(state);
+    let primary = within_budget(
+        Stage::Brain,
+        brain_budget(state, brain),
+        call_sid,
+        ask_brain(state, brain, call_sid, question),
+    );
+    match (primary.await, &state.fallback) {
+        (Ok(response), _) => Ok(response),
+        (Err(e), Some(fallback)) => {
+            tracing::warn!(call_sid, "Primary brain failed, using fallback: {e}");
+            ask_fallback(state, call_sid, fallback, question).await
+        }
+        (Err(e), None) => Err(e),
+    }
+}
+
+/// `ask_with_fallback`, passing the response on to `deltas` as it's
+/// written: in pieces from a streaming bridge, otherwise whole once it's in.
+///
+/// A stream that fails before anything came through still falls back; once
+/// part of the answer has been spoken, the failure is the turn's.
+async fn ask_streaming(
+    state: &AppState,
+    call_sid: &str,
+    question: &Question<'_>,
+    deltas: mpsc::UnboundedSender<String>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let brain = question.brain(state);
+    // Middleware and `[filter] block` see the whole response before any of
+    // it is spoken
+    let blocking = state
+        .config
+        .filter
+        .as_ref()
+        .is_some_and(|config| !config.block.is_empty());
+    let bridge = match brain {
+        Brain::Bridge(bridge) if bridge.streams() && state.middleware.is_empty() && !blocking => {
+            bridge
+        }
+        _ => {
+            let mut response = ask_with_fallback(state, call_sid, question).await?;
+            state.middleware.after(call_sid, &mut response).await;
+            let response = filtered(state, call_sid, response);
+            let _ = deltas.send(response.clone());
+            return Ok(response);
+        }
+    };
+
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    let transcript = question.labelled();
+    let metadata = question.metadata();
+    let stream = within_budget(
+        Stage::Brain,
+        brain_budget(state, brain),
+        call_sid,
+        bridge.send_streaming(call_sid, &transcript, &metadata, tx),
+    );
+    let forward = async {
+        let mut streamed = false;
+        while let Some(delta) = rx.recv().await {
+            streamed = true;
+            let _ = deltas.send(delta);
+        }
+        streamed
+    };
+    let (result, streamed) = tokio::join!(stream, forward);
+
+    match (result, &state.fallback) {
+        (Ok(response), _) => Ok(filtered(state, call_sid, response)),
+        (Err(e), Some(fallback)) if !streamed => {
+            tracing::warn!(call_sid, "Primary brain failed, using fallback: {e}");
+            let response = ask_fallback(state, call_sid, fallback, question).await?;
+            let _ = deltas.send(response.clone());
+            Ok(filtered(state, call_sid, response))
+        }
+        (Err(e), _) => Err(e),
+    }
+}
+
+/// The response as the caller hears it: `[filter] fallback` if it's
+/// blocked, otherwise with redactions applied. This is what memory,
+/// priming and events get, so nothing withheld from the line leaks there.
+fn filtered(state: &AppState, call_sid: &str, response: String) -> String {
+    match state.config.filter {
+        Some(ref config) if filter::blocks(config, &response) => {
+            tracing::warn!(call_sid, "Response blocked by [filter]");
+            config.fallback.clone()
+        }
+        Some(ref config) => filter::redact(config, &response).into_owned(),
+        None => response,
+    }
+}
+
+/// Ask each fallback brain in turn, each within its own budget, until one
+/// answers. Prefixes the notice to the first fallback answer on a call;
+/// if all fail, the last error is the turn's.
+async fn ask_fallback(
+    state: &AppState,
+    call_sid: &str,
+    fallback: &Fallback,
+    question: &Question<'_>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let mut brains = fallback.brains().iter().peekable();
+    let response = loop {
+        let Some(brain) = brains.next() else {
+            return Err("no fallback brain configured".into());
+        };
+        let result = within_budget(
+            Stage::Brain,
+            brain_budget(state, brain),
+            call_sid,
+            ask_brain(state, brain, call_sid, question),
+        )
+        .await;
+        match result {
+            Ok(response) => break response,
+            Err(e) if brains.peek().is_some() => {
+                tracing::warn!(call_sid, "Fallback brain failed, trying the next: {e}");
+            }
+            Err(e) => return Err(e),
+        }
+    };
+    Ok(match fallback.take_notice(call_sid) {
+        Some(notice) => format!("{notice} {response}"),
+        None => response,
+    })
+}
+
+/// Who's on the other end, for `{caller}`: the contact's name, else the
+/// number.
+fn caller(profile: &CallProfile) -> Option<&str> {
+    profile
+        .contact
+        .as_ref()
+        .map(|c| c.name.as_str())
+        .or(profile.number.as_deref())
+}
+
+/// Append a note to the call context.
+fn join_context(context: Option<&str>, note: &str) -> String {
+    match context {
+        Some(context) => format!("{context} {note}"),
+        None => note.to_string(),
+    }
+}
+
+/// Synthesize a brain response, applying any `[VOICE:...]` switches in it.
+/// Keys it asks to press with `[DTMF:...]` are returned as an action, to
+/// press once the reply has played.
+///
+/// Each text segment is spoken in the voice current at that point; the
+/// mu-law pieces are concatenated.
+async fn speak_response(
+    state: &AppState,
+    call_sid: &str,
+    response: &str,
+) -> Result<(Vec<u8>, Option<Action>), Box<dyn std::error::Error + Send + Sync>> {
+    let mut mulaw = Vec::new();
+    let mut digits = String::new();
+    for segment in directives::parse(response) {
+        match segment {
+            Segment::Text(text) => mulaw.extend(synthesize(state, call_sid, text).await?),
+            Segment::Voice(voice_id) => {
+                state
+                    .call_registry
+                    .set_voice(call_sid, voice_id.map(str::to_string))
+                    .await
+            }
+            Segment::Dtmf(keys) => digits.push_str(keys),
+        }
+    }
+    let press = (!digits.is_empty()).then_some(Action::Dtmf { digits });
+    Ok((mulaw, press))
+}
+
+async fn ask_brain(
+    state: &AppState,
+    brain: &Brain,
+    call_sid: &str,
+    question: &Question<'_>,
+) -> Result<String, Box<dyn std::error::Error + Send + Sync>> {
+    let response = match brain {
+        // Bridge-echo handles trust context and session management
+        Brain::Bridge(bridge) => {
+            bridge
+                .send(call_sid, &question.labelled(), &question.metadata())
+                .await?
+        }
+        Brain::Local(conversation) => {
+            // Local mode — build trust-wrapped prompt and send directly
+            let prompt = question.prompt(&state.config.prompt);
+            let system_prompt = question.system_prompt(state);
+            conversation
+                .send_with_system(call_sid, &prompt, system_prompt.as_deref())
+                .await?
+        }
+        Brain::Chat(chat) => {
+            let prompt = question.prompt(&state.config.prompt);
+            let system_prompt = question.system_prompt(state);
+            chat.send_with_system(call_sid, &prompt, system_prompt.as_deref())
+                .await?
+        }
+        #[cfg(feature = "mocks")]
+        Brain::Mock(mock) => mock.send(question.transcript).await?,
+    };
+
+    // Off the turn's path: the caller shouldn't wait on the summary
+    if state.config.llm.summarize_after_turns > 0 {
+        let (brain, call_sid) = (brain.clone(), call_sid.to_string());
+        tokio::spawn(async move { brain.summarize(&call_sid).await });
+    }
+    Ok(response)
+}
+
+/// Known Whisper hallucinations — phrases it generates from silence/noise.
+const WHISPER_HALLUCINATIONS: &[&str] = &[
+    "thank you",
+    "thank you.",
+    "thanks for watching",
+    "thanks for watching.",
+    "thank you for watching",
+    "thank you for watching.",
+    "subscribe",
+    "like and subscribe",
+    "bye",
+    "bye.",
+    "bye bye",
+    "bye bye.",
+    "you",
+    "you.",
+    "the end",
+    "the end.",
+    "so",
+    "...",
+    "eh",
+    "hmm",
+    "uh",
+    "oh",
+    "amen",
+    "amen.",
+];
+
+fn is_whisper_hallucination(transcript: &str) -> bool {
+    let lower = transcript.to_lowercase();
+    WHISPER_HALLUCINATIONS.iter().any(|h| lower == *h)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn budget_expiry_names_the_stage() {
+        let slow = async {
+            tokio::time::sleep(Duration::from_secs(5)).await;
+            Ok::<_, TurnError>(())
+        };
+        let err = within_budget(Stage::Brain, 10, "CA1", slow)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), "brain stage timed out after 10ms");
+    }
+
+    #[tokio::test]
+    async fn zero_budget_means_no_limit() {
+        let fast = async { Ok::<_, TurnError>(42) };
+        assert_eq!(within_budget(Stage::Stt, 0, "CA1", fast).await.unwrap(), 42);
+    }
+
+    #[test]
+    fn detects_known_hallucinations() {
+        assert!(is_whisper_hallucination("thank you"));
+        assert!(is_whisper_hallucination("Thank You"));
+        assert!(is_whisper_hallucination("THANKS FOR WATCHING."));
+        assert!(is_whisper_hallucination("..."));
+        assert!(is_whisper_hallucination("Bye bye."));
+    }
+
+    #[test]
+    fn passes_real_speech() {
+        assert!(!is_whisper_hallucination("Hello, how are you?"));
+        assert!(!is_whisper_hallucination("I need help with my order"));
+        assert!(!is_whisper_hallucination("Thank you for your help today"));
+        assert!(!is_whisper_hallucination("bye for now"));
+    }
+
+    #[test]
+    fn empty_string_is_not_hallucination() {
+        assert!(!is_whisper_hallucination(""));
+    }
+}
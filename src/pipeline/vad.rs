@@ -1,31 +1,57 @@
+use crate::config::VadConfig;
 use crate::pipeline::audio::{self, BandpassFilter};
+use std::collections::VecDeque;
 use std::time::{Duration, Instant};
 
 const SAMPLE_RATE: f64 = 8000.0;
 const SPEECH_LOW_HZ: f64 = 300.0;
 const SPEECH_HIGH_HZ: f64 = 3000.0;
 
+/// Samples per millisecond at 8kHz.
+const SAMPLES_PER_MS: usize = 8;
+/// Hard cap on an utterance when no max utterance is set: one minute,
+/// just under 1MB of PCM.
+const DEFAULT_MAX_UTTERANCE_SECS: u64 = 60;
+/// Audio kept from before speech is detected.
+const DEFAULT_PRE_ROLL_MS: u64 = 300;
+
 /// Energy-based Voice Activity Detection with noise resilience.
 ///
 /// Buffers incoming mu-law audio chunks. Applies a bandpass filter to isolate
 /// speech frequencies before energy calculation, optionally adapts the energy
 /// threshold to the ambient noise floor, and enforces a maximum utterance
-/// duration as a safety net.
+/// length as a safety net.
+///
+/// Memory is bounded either way: before speech only a short pre-roll is
+/// kept (so the first syllable isn't clipped), and an utterance is
+/// force-sent once it holds `max_utterance` worth of samples, however fast
+/// or bursty the audio arrives.
+///
+/// The silence gap is counted in audio as well as on the clock. A live
+/// stream brings audio in real time, so the two agree; a recording fed
+/// faster than that (`--retranscribe`, `tests/vad_golden.rs`) still splits
+/// where the caller paused, and a stream that stalls mid-call still ends
+/// the utterance.
 pub struct VoiceActivityDetector {
-    /// Accumulated PCM samples for the current utterance (unfiltered)
-    pcm_buffer: Vec<i16>,
+    /// Ring of PCM samples (unfiltered): the pre-roll, then the current
+    /// utterance once speech is detected
+    pcm_buffer: VecDeque<i16>,
     /// Whether we've detected speech in the current utterance
     has_speech: bool,
     /// When the last speech was detected
     last_speech_at: Option<Instant>,
-    /// When the current utterance started (for max duration timeout)
-    utterance_start: Option<Instant>,
+    /// Samples of silence since speech was last detected
+    silence_samples: usize,
     /// Base energy threshold (used as initial/fallback value)
     energy_threshold: f64,
     /// How long silence must last before we consider speech done
     silence_threshold: Duration,
-    /// Maximum utterance duration before force-sending to STT
-    max_utterance_duration: Option<Duration>,
+    /// The same, in samples
+    silence_threshold_samples: usize,
+    /// Samples kept from before speech starts
+    pre_roll_samples: usize,
+    /// Utterance length, in samples, that forces a send to STT
+    max_utterance_samples: usize,
     /// Bandpass filter isolating speech frequencies for VAD energy calculation
     bandpass: BandpassFilter,
     /// Whether adaptive threshold is enabled
@@ -41,13 +67,15 @@ pub struct VoiceActivityDetector {
 impl VoiceActivityDetector {
     pub fn new(energy_threshold: u16, silence_threshold_ms: u64) -> Self {
         Self {
-            pcm_buffer: Vec::with_capacity(8000 * 30),
+            pcm_buffer: VecDeque::with_capacity(8000 * 30),
             has_speech: false,
             last_speech_at: None,
-            utterance_start: None,
+            silence_samples: 0,
             energy_threshold: energy_threshold as f64,
             silence_threshold: Duration::from_millis(silence_threshold_ms),
-            max_utterance_duration: None,
+            silence_threshold_samples: silence_threshold_ms as usize * SAMPLES_PER_MS,
+            pre_roll_samples: DEFAULT_PRE_ROLL_MS as usize * SAMPLES_PER_MS,
+            max_utterance_samples: DEFAULT_MAX_UTTERANCE_SECS as usize * 1000 * SAMPLES_PER_MS,
             bandpass: BandpassFilter::new(SPEECH_LOW_HZ, SPEECH_HIGH_HZ, SAMPLE_RATE),
             adaptive: false,
             noise_floor: 0.0,
@@ -56,6 +84,19 @@ impl VoiceActivityDetector {
         }
     }
 
+    /// A detector set up as `[vad]` says.
+    pub fn from_config(config: &VadConfig) -> Self {
+        let mut vad = Self::new(config.energy_threshold, config.silence_threshold_ms)
+            .with_pre_roll(config.pre_roll_ms);
+        if config.adaptive_threshold {
+            vad = vad.with_adaptive(config.noise_floor_multiplier, config.noise_floor_decay);
+        }
+        if let Some(max_secs) = config.max_utterance_secs {
+            vad = vad.with_max_utterance(max_secs);
+        }
+        vad
+    }
+
     /// Enable adaptive threshold mode.
     pub fn with_adaptive(mut self, multiplier: f64, decay: f64) -> Self {
         self.adaptive = true;
@@ -64,9 +105,20 @@ impl VoiceActivityDetector {
         self
     }
 
-    /// Set maximum utterance duration (safety net).
+    /// Set maximum utterance duration (safety net), replacing the default
+    /// one-minute cap. 0 keeps the default: the buffer is always bounded.
     pub fn with_max_utterance(mut self, secs: u64) -> Self {
-        self.max_utterance_duration = Some(Duration::from_secs(secs));
+        let secs = match secs {
+            0 => DEFAULT_MAX_UTTERANCE_SECS,
+            secs => secs,
+        };
+        self.max_utterance_samples = secs as usize * 1000 * SAMPLES_PER_MS;
+        self
+    }
+
+    /// Keep `ms` of audio from before speech starts.
+    pub fn with_pre_roll(mut self, ms: u64) -> Self {
+        self.pre_roll_samples = ms as usize * SAMPLES_PER_MS;
         self
     }
 
@@ -98,20 +150,20 @@ impl VoiceActivityDetector {
     /// complete utterance is detected (speech followed by silence gap,
     /// or max utterance duration exceeded).
     pub fn feed(&mut self, mulaw_chunk: &[u8]) -> Option<Vec<i16>> {
-        let pcm = audio::decode_mulaw(mulaw_chunk);
+        // Decode straight into the utterance buffer (unfiltered, for STT)
+        let start = self.pcm_buffer.len();
+        self.pcm_buffer
+            .extend(mulaw_chunk.iter().map(|&b| audio::mulaw_to_pcm(b)));
 
-        // Filter to speech band before energy calculation
-        let filtered = self.bandpass.filter(&pcm);
-        let energy = audio::rms_energy(&filtered);
-
-        // Buffer the original (unfiltered) audio for STT
-        self.pcm_buffer.extend_from_slice(&pcm);
+        // Speech-band energy of the new samples, filtered on the fly
+        let energy = self
+            .bandpass
+            .filtered_energy(self.pcm_buffer.range(start..).copied());
 
         let threshold = self.speech_threshold();
 
         if energy > threshold {
             if !self.has_speech {
-                self.utterance_start = Some(Instant::now());
                 tracing::debug!(
                     energy = format!("{energy:.1}"),
                     threshold = format!("{threshold:.1}"),
@@ -121,47 +173,58 @@ impl VoiceActivityDetector {
             }
             self.has_speech = true;
             self.last_speech_at = Some(Instant::now());
-        } else if !self.has_speech {
+            self.silence_samples = 0;
+        } else if self.has_speech {
+            self.silence_samples += mulaw_chunk.len();
+        } else {
             // Only update noise floor when we're confident it's not speech
             self.update_noise_floor(energy);
         }
 
         if self.has_speech {
-            // Check max utterance duration (safety net)
-            if let (Some(max_dur), Some(start)) =
-                (self.max_utterance_duration, self.utterance_start)
-            {
-                if start.elapsed() >= max_dur {
-                    tracing::warn!(
-                        samples = self.pcm_buffer.len(),
-                        "Max utterance duration reached, force-sending"
-                    );
-                    return Some(self.take_utterance());
-                }
+            // Check max utterance length (safety net)
+            if self.pcm_buffer.len() >= self.max_utterance_samples {
+                tracing::warn!(
+                    samples = self.pcm_buffer.len(),
+                    "Max utterance duration reached, force-sending"
+                );
+                return Some(self.take_utterance());
             }
 
             // Check silence gap
-            if let Some(last) = self.last_speech_at {
-                if last.elapsed() >= self.silence_threshold {
-                    return Some(self.take_utterance());
-                }
+            let heard = self.silence_samples >= self.silence_threshold_samples;
+            let elapsed = self
+                .last_speech_at
+                .is_some_and(|last| last.elapsed() >= self.silence_threshold);
+            if heard || elapsed {
+                return Some(self.take_utterance());
             }
-        }
-
-        // Prevent unbounded growth if no speech is ever detected
-        if !self.has_speech && self.pcm_buffer.len() > 8000 * 5 {
-            self.pcm_buffer.clear();
+        } else {
+            // No speech yet: keep just the pre-roll
+            let excess = self.pcm_buffer.len().saturating_sub(self.pre_roll_samples);
+            self.pcm_buffer.drain(..excess);
         }
 
         None
     }
 
+    /// Whether an utterance is under way.
+    pub fn in_speech(&self) -> bool {
+        self.has_speech
+    }
+
+    /// The utterance under way, if any, as though its silence gap had
+    /// come (the audio ended mid-utterance).
+    pub fn flush(&mut self) -> Option<Vec<i16>> {
+        self.has_speech.then(|| self.take_utterance())
+    }
+
     /// Extract the buffered utterance and reset state.
     fn take_utterance(&mut self) -> Vec<i16> {
-        let utterance = std::mem::take(&mut self.pcm_buffer);
+        let utterance = Vec::from(std::mem::take(&mut self.pcm_buffer));
         self.has_speech = false;
         self.last_speech_at = None;
-        self.utterance_start = None;
+        self.silence_samples = 0;
         utterance
     }
 
@@ -170,7 +233,7 @@ impl VoiceActivityDetector {
         self.pcm_buffer.clear();
         self.has_speech = false;
         self.last_speech_at = None;
-        self.utterance_start = None;
+        self.silence_samples = 0;
         // Don't reset noise_floor or bandpass — they should persist across turns
     }
 }
@@ -180,6 +243,13 @@ mod tests {
     use super::*;
     use crate::pipeline::audio::pcm_to_mulaw;
 
+    /// One 20ms frame well above the energy threshold.
+    fn loud() -> Vec<u8> {
+        (0..160)
+            .map(|i| pcm_to_mulaw(((i % 50) * 500) as i16))
+            .collect()
+    }
+
     #[test]
     fn silence_does_not_trigger() {
         let mut vad = VoiceActivityDetector::new(50, 500);
@@ -193,26 +263,97 @@ mod tests {
     fn loud_signal_detected() {
         let mut vad = VoiceActivityDetector::new(50, 100);
 
-        let loud_pcm: Vec<i16> = (0..160).map(|i| ((i % 50) * 500) as i16).collect();
-        let loud_mulaw: Vec<u8> = loud_pcm.iter().map(|&s| pcm_to_mulaw(s)).collect();
-
-        let result = vad.feed(&loud_mulaw);
+        let result = vad.feed(&loud());
         // Won't trigger yet — silence threshold not elapsed
         assert!(result.is_none());
         assert!(vad.has_speech);
     }
 
+    #[test]
+    fn silence_gap_is_counted_in_audio() {
+        let mut vad = VoiceActivityDetector::new(50, 100);
+        let loud_mulaw = loud();
+        let silence = vec![0xffu8; 160];
+
+        // The filter rings into the first quiet frame
+        vad.feed(&loud_mulaw);
+        assert!(vad.feed(&silence).is_none());
+        // Faster than real time, 100ms of silence is still five frames
+        for _ in 0..4 {
+            assert!(vad.feed(&silence).is_none());
+        }
+        assert!(vad.feed(&silence).is_some());
+    }
+
+    #[test]
+    fn stalled_stream_still_ends_the_utterance() {
+        let mut vad = VoiceActivityDetector::new(50, 30);
+        let loud_mulaw = loud();
+
+        vad.feed(&loud_mulaw);
+        assert!(vad.feed(&[0xff; 160]).is_none());
+        // Then nothing for a while: 40ms on the clock, but 8 more samples
+        std::thread::sleep(Duration::from_millis(40));
+        assert!(vad.feed(&[0xff; 8]).is_some());
+    }
+
     #[test]
     fn max_utterance_forces_emit() {
-        // 0s max utterance — speech detection and timeout fire in the same feed
-        let mut vad = VoiceActivityDetector::new(50, 5000).with_max_utterance(0);
+        // 1s max utterance — hit long before the 5s silence gap
+        let mut vad = VoiceActivityDetector::new(50, 5000)
+            .with_pre_roll(0)
+            .with_max_utterance(1);
 
-        let loud_pcm: Vec<i16> = (0..160).map(|i| ((i % 50) * 500) as i16).collect();
-        let loud_mulaw: Vec<u8> = loud_pcm.iter().map(|&s| pcm_to_mulaw(s)).collect();
+        let loud_mulaw = loud();
 
-        // With 0s timeout, first feed detects speech AND exceeds max duration immediately
+        // Continuous speech is sent at the cap without waiting for a pause
+        for _ in 0..49 {
+            assert!(vad.feed(&loud_mulaw).is_none());
+        }
         let result = vad.feed(&loud_mulaw);
-        assert!(result.is_some());
+        assert_eq!(result.map(|u| u.len()), Some(8000));
+        assert!(vad.pcm_buffer.is_empty());
+    }
+
+    #[test]
+    fn zero_max_utterance_keeps_the_default_cap() {
+        // Silence never ends the utterance; only the cap can
+        let mut vad = VoiceActivityDetector::new(50, 600_000)
+            .with_pre_roll(0)
+            .with_max_utterance(0);
+        let loud_mulaw = loud();
+        let cap = DEFAULT_MAX_UTTERANCE_SECS as usize * 1000 * SAMPLES_PER_MS;
+        let mut sent = None;
+        for _ in 0..cap / loud_mulaw.len() + 1 {
+            assert!(vad.pcm_buffer.len() <= cap);
+            if let Some(utterance) = vad.feed(&loud_mulaw) {
+                sent = Some(utterance.len());
+            }
+        }
+        assert_eq!(sent, Some(cap));
+    }
+
+    #[test]
+    fn silence_keeps_only_the_pre_roll() {
+        let mut vad = VoiceActivityDetector::new(50, 500).with_pre_roll(100);
+        let silence = vec![0xffu8; 160];
+        for _ in 0..1000 {
+            vad.feed(&silence);
+        }
+        assert_eq!(vad.pcm_buffer.len(), 800);
+    }
+
+    #[test]
+    fn utterance_starts_with_pre_roll() {
+        let mut vad = VoiceActivityDetector::new(50, 60_000).with_pre_roll(40);
+        let silence = vec![0xffu8; 160];
+        for _ in 0..10 {
+            vad.feed(&silence);
+        }
+        let loud_mulaw = loud();
+        assert!(vad.feed(&loud_mulaw).is_none());
+        assert!(vad.in_speech());
+        assert_eq!(vad.pcm_buffer.len(), 320 + 160);
     }
 
     #[test]
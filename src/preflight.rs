@@ -0,0 +1,226 @@
+//! Startup preflight checks.
+//!
+//! Run once before the server binds, so misconfiguration shows up in the
+//! startup log with a clear fix instead of on the first call. Each provider
+//! gets a cheap authenticated probe; the brain is checked for reachability.
+//! With `[preflight] strict = true` any failure aborts startup; otherwise
+//! failures are logged and the server starts anyway (the circuit breakers
+//! and degraded mode take it from there).
+
+use std::future::Future;
+use std::time::Duration;
+
+use crate::config::{SttVendor, TtsVendor};
+use crate::{AppState, Brain};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Outcome {
+    Ok,
+    Warn(String),
+    Fail(String),
+}
+
+#[derive(Debug)]
+pub struct Check {
+    pub name: &'static str,
+    pub outcome: Outcome,
+}
+
+/// Run all checks. Never fails itself; inspect the outcomes.
+pub async fn run(state: &AppState) -> Vec<Check> {
+    let timeout = Duration::from_secs(state.config.preflight.timeout_secs);
+    let config = &state.config;
+
+    let mut checks = vec![Check {
+        name: "external_url",
+        outcome: check_external_url(&config.server.external_url),
+    }];
+
+    if config.hold_music.is_some() {
+        let outcome = match state.hold_music {
+            None => Outcome::Warn(
+                "no hold music file could be loaded as WAV; calls will wait in silence".into(),
+            ),
+            Some(ref music) if !music.skipped().is_empty() => Outcome::Warn(format!(
+                "{} could not be loaded as WAV; skipped",
+                music.skipped().join(", ")
+            )),
+            // Hold music is optional: an unreachable stream only warns
+            Some(ref music) => match probe(timeout, music.probe(), "check hold_music.url").await {
+                Outcome::Fail(msg) => Outcome::Warn(msg),
+                outcome => outcome,
+            },
+        };
+        checks.push(Check {
+            name: "hold_music",
+            outcome,
+        });
+    }
+
+    let (stt, tts, twilio, brain) = tokio::join!(
+        probe(
+            timeout,
+            state.stt.probe(),
+            stt_hint(state.config.stt.provider)
+        ),
+        probe(
+            timeout,
+            state.tts.probe(),
+            tts_hint(state.config.tts.provider)
+        ),
+        probe(
+            timeout,
+            state.twilio.probe(),
+            "check twilio.account_sid and twilio.auth_token"
+        ),
+        probe_brain(timeout, &state.brain),
+    );
+    checks.push(Check {
+        name: state.stt.vendor(),
+        outcome: stt,
+    });
+    // Calls still have the primary vendor: a broken fallback only warns
+    if let (Some(vendor), Some(fallback)) = (state.config.stt.fallback, state.stt.fallback()) {
+        let outcome = match probe(timeout, fallback.probe(), stt_hint(vendor)).await {
+            Outcome::Fail(msg) => Outcome::Warn(msg),
+            outcome => outcome,
+        };
+        checks.push(Check {
+            name: "fallback_stt",
+            outcome,
+        });
+    }
+    checks.push(Check {
+        name: state.tts.vendor(),
+        outcome: tts,
+    });
+    checks.push(Check {
+        name: "twilio",
+        outcome: twilio,
+    });
+    checks.push(Check {
+        name: "brain",
+        outcome: brain,
+    });
+
+    checks
+}
+
+fn stt_hint(vendor: SttVendor) -> &'static str {
+    match vendor {
+        SttVendor::Groq => "check groq.api_key",
+        SttVendor::Deepgram => "check deepgram.api_key",
+        SttVendor::AssemblyAi => "check assemblyai.api_key",
+        SttVendor::Azure => "check azure.api_key and azure.region",
+    }
+}
+
+fn tts_hint(vendor: TtsVendor) -> &'static str {
+    match vendor {
+        TtsVendor::Inworld => "check inworld.api_key",
+        TtsVendor::ElevenLabs => "check elevenlabs.api_key",
+        TtsVendor::OpenAi => "check openai.api_key",
+        TtsVendor::Azure => "check azure.api_key and azure.region",
+    }
+}
+
+/// Log each check. Returns an error listing the failures if any failed.
+pub fn report(checks: &[Check]) -> Result<(), String> {
+    let mut failures = Vec::new();
+    for check in checks {
+        match &check.outcome {
+            Outcome::Ok => tracing::info!(check = check.name, "Preflight ok"),
+            Outcome::Warn(msg) => tracing::warn!(check = check.name, "Preflight: {msg}"),
+            Outcome::Fail(msg) => {
+                tracing::error!(check = check.name, "Preflight failed: {msg}");
+                failures.push(format!("{}: {msg}", check.name));
+            }
+        }
+    }
+    if failures.is_empty() {
+        Ok(())
+    } else {
+        Err(format!("Preflight failed — {}", failures.join("; ")))
+    }
+}
+
+async fn probe<E: std::fmt::Display>(
+    timeout: Duration,
+    fut: impl Future<Output = Result<(), E>>,
+    hint: &str,
+) -> Outcome {
+    match tokio::time::timeout(timeout, fut).await {
+        Ok(Ok(())) => Outcome::Ok,
+        Ok(Err(e)) => Outcome::Fail(format!("{e} ({hint})")),
+        Err(_) => Outcome::Fail(format!("no response within {}s", timeout.as_secs())),
+    }
+}
+
+async fn probe_brain(timeout: Duration, brain: &Brain) -> Outcome {
+    match brain {
+        Brain::Bridge(bridge) => {
+            probe(
+                timeout,
+                bridge.probe(),
+                "is bridge-echo running at llm.bridge_url?",
+            )
+            .await
+        }
+        Brain::Chat(chat) => probe(timeout, chat.probe(), chat.hint()).await,
+        // The host process owns the provider; nothing to probe from here
+        Brain::Local(_) => Outcome::Ok,
+        #[cfg(feature = "mocks")]
+        Brain::Mock(_) => Outcome::Ok,
+    }
+}
+
+/// Twilio media streams must be `wss://`, derived from `external_url`.
+fn check_external_url(url: &str) -> Outcome {
+    let Some((scheme, rest)) = url.split_once("://") else {
+        return Outcome::Fail(format!(
+            "'{url}' has no scheme; use https://your-host so media streams use wss://"
+        ));
+    };
+    if rest.trim_end_matches('/').is_empty() {
+        return Outcome::Fail(format!("'{url}' has no host"));
+    }
+    match scheme {
+        "https" if url.ends_with('/') => Outcome::Warn(format!(
+            "'{url}' ends with '/'; webhook URLs will contain '//'"
+        )),
+        "https" => Outcome::Ok,
+        "http" => Outcome::Warn(format!(
+            "'{url}' yields a ws:// media stream URL; Twilio requires wss:// (https://)"
+        )),
+        other => Outcome::Fail(format!(
+            "unsupported scheme '{other}' in '{url}'; use https://"
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn external_url_needs_https() {
+        assert_eq!(check_external_url("https://echo.example.com"), Outcome::Ok);
+        assert!(matches!(
+            check_external_url("http://echo.example.com"),
+            Outcome::Warn(_)
+        ));
+        assert!(matches!(
+            check_external_url("https://echo.example.com/"),
+            Outcome::Warn(_)
+        ));
+        assert!(matches!(
+            check_external_url("echo.example.com"),
+            Outcome::Fail(_)
+        ));
+        assert!(matches!(
+            check_external_url("ftp://echo.example.com"),
+            Outcome::Fail(_)
+        ));
+        assert!(matches!(check_external_url("https://"), Outcome::Fail(_)));
+    }
+}
@@ -0,0 +1,42 @@
+//! Stale call reaper.
+//!
+//! Stream handlers end their own calls on every normal exit, but a panic in
+//! a handler task leaves its registry entry behind, and that zombie entry
+//! then swallows inject requests. This task periodically ends calls whose
+//! handler is gone or that exceeded the maximum call duration.
+
+use std::time::Duration;
+
+use crate::events::EndReason;
+use crate::AppState;
+
+/// Run the reaper until the task is aborted.
+pub async fn run(state: AppState) {
+    let config = &state.config.reaper;
+    if config.interval_secs == 0 {
+        return;
+    }
+    let max_age = (config.max_call_secs > 0).then(|| Duration::from_secs(config.max_call_secs));
+
+    let mut interval = tokio::time::interval(Duration::from_secs(config.interval_secs));
+    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        interval.tick().await;
+        for (call_sid, entry) in state.call_registry.entries().await {
+            let reason = if entry.is_orphaned() {
+                EndReason::Orphaned
+            } else if max_age.is_some_and(|max| entry.age() > max) {
+                EndReason::MaxDuration
+            } else {
+                continue;
+            };
+            tracing::warn!(
+                call_sid = %call_sid,
+                ?reason,
+                age_secs = entry.age().as_secs(),
+                "Reaping stale call"
+            );
+            state.end_call(&call_sid, reason).await;
+        }
+    }
+}
@@ -1,13 +1,25 @@
 use std::collections::HashMap;
 use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 use axum::extract::ws::Message;
-use base64::Engine;
-use tokio::sync::{mpsc, Mutex};
+use serde::Serialize;
+use tokio::sync::{mpsc, RwLock};
+use tokio_util::sync::CancellationToken;
+
+use crate::agents::Agent;
+use crate::config::DiarizationConfig;
+use crate::contacts::Contact;
+use crate::pipeline::actions::Action;
+use crate::pipeline::diarize::{Speakers, Voiceprint};
+use crate::pipeline::frames::{FrameEncoder, FRAME_BYTES};
+use crate::pipeline::language;
+use crate::tenants::Tenant;
 
 /// Audio transport type for a registered call.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
 pub enum Transport {
     /// Twilio media stream — audio wrapped in JSON event envelope.
     Twilio,
@@ -22,28 +34,155 @@ pub struct ActiveCall {
     pub speaking: Arc<AtomicBool>,
 }
 
+/// Per-call settings chosen when the call starts.
+#[derive(Clone, Default)]
+pub struct CallProfile {
+    /// Tenant the call belongs to, if any.
+    pub tenant: Option<Arc<Tenant>>,
+    /// Named agent handling the call, if any.
+    pub agent: Option<Arc<Agent>>,
+    /// Known contact on the other end, if any.
+    pub contact: Option<Arc<Contact>>,
+    /// The other party's number (E.164), when Twilio passed it on.
+    pub number: Option<String>,
+    /// The Discord user who started the session, on Discord.
+    pub discord_user: Option<String>,
+    /// The caller's language (ISO 639-1), once known.
+    pub language: Option<String>,
+    /// Added to the brain's system prompt for this call (`/api/call`).
+    pub system_prompt: Option<String>,
+    /// Tools the brain may use on this call, if `/api/call` granted any.
+    pub allowed_tools: Option<Vec<String>>,
+    /// Voice switched to mid-call by a `[VOICE:...]` directive.
+    pub voice_override: Option<String>,
+    /// Detected language not locked in yet, and how many detections in a
+    /// row agreed on it.
+    pub detection: Option<(String, u32)>,
+    /// Voices heard so far, with `[diarization]` on.
+    pub speakers: Speakers,
+}
+
+impl CallProfile {
+    /// A profile for a call handled by `agent`. The call's language starts
+    /// as the agent's locale, if it has one.
+    pub fn new(tenant: Option<Arc<Tenant>>, agent: Option<Arc<Agent>>) -> Self {
+        let language = agent
+            .as_ref()
+            .and_then(|a| a.locale.as_deref())
+            .map(language::of_locale);
+        Self {
+            tenant,
+            agent,
+            contact: None,
+            number: None,
+            discord_user: None,
+            language,
+            system_prompt: None,
+            allowed_tools: None,
+            voice_override: None,
+            detection: None,
+            speakers: Speakers::default(),
+        }
+    }
+
+    /// Count a detection of `code` towards locking in the call's language.
+    /// The language is locked once `lock_after` detections in a row agree;
+    /// returns true when that happens.
+    pub fn observe_language(&mut self, code: &str, lock_after: u32) -> bool {
+        let streak = match self.detection.take() {
+            Some((pending, streak)) if pending == code => streak + 1,
+            _ => 1,
+        };
+        if streak >= lock_after {
+            self.language = Some(code.to_string());
+            true
+        } else {
+            self.detection = Some((code.to_string(), streak));
+            false
+        }
+    }
+
+    /// Whether a request from `tenant` (`None` = operator) may touch this
+    /// call. The operator reaches every call; a tenant only its own.
+    pub fn belongs_to(&self, tenant: Option<&Tenant>) -> bool {
+        match tenant {
+            None => true,
+            Some(tenant) => self.tenant.as_ref().is_some_and(|t| t.name == tenant.name),
+        }
+    }
+
+    /// TTS voice for the call, if it differs from the default.
+    pub fn voice_id(&self) -> Option<&str> {
+        self.voice_override
+            .as_deref()
+            .or_else(|| self.agent.as_ref().and_then(|a| a.voice_id.as_deref()))
+    }
+}
+
 /// Thread-safe handle to an active call's resources.
 #[derive(Clone)]
 pub struct CallEntry {
     pub stream_sid: String,
     pub transport: Transport,
+    /// Mu-law bytes per outbound frame (`[audio] frame_ms`).
+    pub frame_bytes: usize,
+    /// Audio frames per outbound message (discord-voice batching).
+    pub batch_frames: usize,
+    pub profile: CallProfile,
     response_tx: mpsc::Sender<Message>,
     speaking: Arc<AtomicBool>,
+    started_at: Instant,
+    ended: CancellationToken,
+    /// Brain actions waiting for the reply playing now to finish.
+    after_playback: Vec<Action>,
+    /// A doubtful transcript the caller was asked to confirm.
+    unconfirmed: Option<String>,
+    /// The last turn's words and answer, priming STT for the next.
+    last_exchange: Option<String>,
+    /// When keys were pressed, until Twilio reconnects the media stream.
+    resuming: Option<Instant>,
 }
 
 impl CallEntry {
     pub fn set_speaking(&self, value: bool) {
         self.speaking.store(value, Ordering::Relaxed);
     }
+
+    /// True while a reply is playing to the caller.
+    pub fn is_speaking(&self) -> bool {
+        self.speaking.load(Ordering::Relaxed)
+    }
+
+    /// Time since the call was registered.
+    pub fn age(&self) -> Duration {
+        self.started_at.elapsed()
+    }
+
+    /// True when the stream handler that owns this call has gone away
+    /// without deregistering it. A call pressing keys has no handler until
+    /// its stream is back, for `RESUME_GRACE` at most.
+    pub fn is_orphaned(&self) -> bool {
+        self.response_tx.is_closed()
+            && self
+                .resuming
+                .map_or(true, |since| since.elapsed() > RESUME_GRACE)
+    }
 }
 
+/// How long a call pressing keys may go without a media stream.
+const RESUME_GRACE: Duration = Duration::from_secs(60);
+
 /// Registry of active calls, keyed by call_sid.
 ///
 /// Allows the inject endpoint to look up an active call and push
 /// TTS audio into it without going through the normal pipeline.
+///
+/// Every utterance of every call reads from here, so lookups share a read
+/// lock and writers never log while holding it.
 #[derive(Clone)]
 pub struct CallRegistry {
-    inner: Arc<Mutex<HashMap<String, CallEntry>>>,
+    inner: Arc<RwLock<HashMap<String, CallEntry>>>,
+    frame_bytes: usize,
 }
 
 impl Default for CallRegistry {
@@ -55,11 +194,22 @@ impl Default for CallRegistry {
 impl CallRegistry {
     pub fn new() -> Self {
         Self {
-            inner: Arc::new(Mutex::new(HashMap::new())),
+            inner: Arc::new(RwLock::new(HashMap::new())),
+            frame_bytes: FRAME_BYTES,
         }
     }
 
+    /// Frame audio sent into calls as `bytes`-sized frames.
+    pub fn with_frame_bytes(mut self, bytes: usize) -> Self {
+        self.frame_bytes = bytes;
+        self
+    }
+
     /// Register a new active call.
+    ///
+    /// Returns a token that is cancelled when the call is deregistered, so
+    /// the stream handler can close its socket if the call is ended from
+    /// elsewhere (e.g. the reaper).
     pub async fn register(
         &self,
         call_sid: String,
@@ -67,58 +217,267 @@ impl CallRegistry {
         transport: Transport,
         response_tx: mpsc::Sender<Message>,
         speaking: Arc<AtomicBool>,
-    ) {
+        profile: CallProfile,
+    ) -> CancellationToken {
         tracing::info!(
             call_sid = %call_sid,
             stream_sid = %stream_sid,
             transport = ?transport,
+            tenant = profile.tenant.as_ref().map(|t| t.name.as_str()),
+            agent = profile.agent.as_ref().map(|a| a.name.as_str()),
             "Call registered"
         );
-        self.inner.lock().await.insert(
+        let ended = CancellationToken::new();
+        self.inner.write().await.insert(
             call_sid,
             CallEntry {
                 stream_sid,
                 transport,
+                frame_bytes: self.frame_bytes,
+                batch_frames: 1,
+                profile,
                 response_tx,
                 speaking,
+                started_at: Instant::now(),
+                ended: ended.clone(),
+                after_playback: Vec::new(),
+                unconfirmed: None,
+                last_exchange: None,
+                resuming: None,
             },
         );
+        ended
+    }
+
+    /// Expect the call's media stream to end and come back, as it does
+    /// when keys are pressed. Returns false if it isn't registered.
+    pub async fn expect_resume(&self, call_sid: &str) -> bool {
+        match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => {
+                entry.resuming = Some(Instant::now());
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// The keys weren't pressed after all: the stream stays as it is.
+    pub async fn cancel_resume(&self, call_sid: &str) {
+        if let Some(entry) = self.inner.write().await.get_mut(call_sid) {
+            entry.resuming = None;
+        }
+    }
+
+    /// Move a call expecting its stream back onto the new stream. Returns
+    /// the call's `ended` token and profile, or `None` if the call isn't
+    /// waiting for a stream.
+    pub async fn resume(
+        &self,
+        call_sid: &str,
+        stream_sid: String,
+        response_tx: mpsc::Sender<Message>,
+        speaking: Arc<AtomicBool>,
+    ) -> Option<(CancellationToken, CallProfile)> {
+        let mut inner = self.inner.write().await;
+        let entry = inner.get_mut(call_sid).filter(|e| e.resuming.is_some())?;
+        entry.stream_sid = stream_sid;
+        entry.response_tx = response_tx;
+        entry.speaking = speaking;
+        entry.resuming = None;
+        let resumed = (entry.ended.clone(), entry.profile.clone());
+        drop(inner);
+        tracing::info!(call_sid, "Call resumed on a new stream");
+        Some(resumed)
+    }
+
+    /// True if the call outlives the stream `stream_sid` ending: it's
+    /// waiting for a new stream, or already on one.
+    pub async fn continues_elsewhere(&self, call_sid: &str, stream_sid: &str) -> bool {
+        self.inner
+            .read()
+            .await
+            .get(call_sid)
+            .is_some_and(|e| e.resuming.is_some() || e.stream_sid != stream_sid)
     }
 
-    /// Deregister a call when it ends.
-    pub async fn deregister(&self, call_sid: &str) {
-        if self.inner.lock().await.remove(call_sid).is_some() {
-            tracing::info!(call_sid = %call_sid, "Call deregistered");
+    /// Deregister a call when it ends. Returns its profile, or `None` if it
+    /// wasn't registered.
+    pub async fn deregister(&self, call_sid: &str) -> Option<CallProfile> {
+        let entry = self.inner.write().await.remove(call_sid)?;
+        entry.ended.cancel();
+        tracing::info!(call_sid = %call_sid, "Call deregistered");
+        Some(entry.profile)
+    }
+
+    /// The call's profile, or the default profile if it isn't registered.
+    pub async fn profile(&self, call_sid: &str) -> CallProfile {
+        self.inner
+            .read()
+            .await
+            .get(call_sid)
+            .map(|e| e.profile.clone())
+            .unwrap_or_default()
+    }
+
+    /// Switch the call's TTS voice. `None` returns to the call's own voice.
+    pub async fn set_voice(&self, call_sid: &str, voice_id: Option<String>) {
+        match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => entry.profile.voice_override = voice_id.clone(),
+            None => return,
         }
+        tracing::info!(call_sid, voice = ?voice_id, "Switching voice");
+    }
+
+    /// Send the call's audio `frames` frames per message, as agreed with
+    /// the discord-voice sidecar.
+    pub async fn set_batch_frames(&self, call_sid: &str, frames: usize) {
+        if let Some(entry) = self.inner.write().await.get_mut(call_sid) {
+            entry.batch_frames = frames;
+        }
+    }
+
+    /// Count a detected language towards the call's language lock.
+    pub async fn observe_language(&self, call_sid: &str, code: &str, lock_after: u32) {
+        let locked = match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => entry.profile.observe_language(code, lock_after),
+            None => false,
+        };
+        if locked {
+            tracing::info!(call_sid, language = %code, "Call language locked");
+        }
+    }
+
+    /// Label an utterance with its speaker. `learn` is false for utterances
+    /// too short to start a new speaker. `None` until a second speaker has
+    /// been heard: with one voice on the call there's no one to tell apart.
+    pub async fn identify_speaker(
+        &self,
+        call_sid: &str,
+        voice: &Voiceprint,
+        config: &DiarizationConfig,
+        learn: bool,
+    ) -> Option<u32> {
+        let (speaker, heard, now) = match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => {
+                let speakers = &mut entry.profile.speakers;
+                let heard = speakers.len();
+                let speaker =
+                    speakers.identify(voice, config.threshold, config.max_speakers, learn);
+                (speaker, heard, speakers.len())
+            }
+            None => return None,
+        };
+        if speaker.is_some_and(|n| n as usize > heard) {
+            tracing::info!(call_sid, speaker, "New speaker on the call");
+        }
+        speaker.filter(|_| now > 1)
+    }
+
+    /// Hold `actions` until the reply being sent has played (the stream's
+    /// next mark).
+    pub async fn after_playback(&self, call_sid: &str, actions: Vec<Action>) {
+        if let Some(entry) = self.inner.write().await.get_mut(call_sid) {
+            entry.after_playback.extend(actions);
+        }
+    }
+
+    /// Actions held for the end of playback, if any; they're handed over
+    /// once.
+    pub async fn take_after_playback(&self, call_sid: &str) -> Vec<Action> {
+        if !self
+            .inner
+            .read()
+            .await
+            .get(call_sid)
+            .is_some_and(|e| !e.after_playback.is_empty())
+        {
+            return Vec::new();
+        }
+        match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => std::mem::take(&mut entry.after_playback),
+            None => Vec::new(),
+        }
+    }
+
+    /// Remember that the caller was asked whether they said `transcript`.
+    pub async fn ask_to_confirm(&self, call_sid: &str, transcript: String) {
+        if let Some(entry) = self.inner.write().await.get_mut(call_sid) {
+            entry.unconfirmed = Some(transcript);
+        }
+    }
+
+    /// The transcript the caller was last asked to confirm, handed over
+    /// once.
+    pub async fn take_unconfirmed(&self, call_sid: &str) -> Option<String> {
+        if !self
+            .inner
+            .read()
+            .await
+            .get(call_sid)
+            .is_some_and(|e| e.unconfirmed.is_some())
+        {
+            return None;
+        }
+        self.inner
+            .write()
+            .await
+            .get_mut(call_sid)?
+            .unconfirmed
+            .take()
+    }
+
+    /// Remember the turn just answered, to prime STT with.
+    pub async fn set_last_exchange(&self, call_sid: &str, said: &str, answered: &str) {
+        if let Some(entry) = self.inner.write().await.get_mut(call_sid) {
+            entry.last_exchange = Some(format!("{said} {answered}"));
+        }
+    }
+
+    /// What was said and answered on the call's last turn, if anything.
+    pub async fn last_exchange(&self, call_sid: &str) -> Option<String> {
+        self.inner.read().await.get(call_sid)?.last_exchange.clone()
+    }
+
+    /// Record the call's language.
+    pub async fn set_language(&self, call_sid: &str, language: String) {
+        match self.inner.write().await.get_mut(call_sid) {
+            Some(entry) => entry.profile.language = Some(language.clone()),
+            None => return,
+        }
+        tracing::info!(call_sid, language = %language, "Call language set");
+    }
+
+    /// Snapshot of all registered calls.
+    pub async fn entries(&self) -> Vec<(String, CallEntry)> {
+        self.inner
+            .read()
+            .await
+            .iter()
+            .map(|(sid, entry)| (sid.clone(), entry.clone()))
+            .collect()
     }
 
     /// Look up an active call by call_sid.
     pub async fn get(&self, call_sid: &str) -> Option<CallEntry> {
-        self.inner.lock().await.get(call_sid).cloned()
+        self.inner.read().await.get(call_sid).cloned()
     }
 
     /// Send mu-law audio frames into an active call.
     ///
     /// Dispatches based on transport type:
     /// - Twilio: wraps in JSON event envelope with base64 payload + mark event
-    /// - Discord: sends plain mu-law chunks as binary + JSON mark event
+    /// - Discord: plain JSON audio messages, batched if the sidecar asked,
+    ///   + JSON mark event
     pub async fn send_audio(
         entry: &CallEntry,
         mulaw_bytes: &[u8],
     ) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
         match entry.transport {
             Transport::Twilio => {
-                for chunk in mulaw_bytes.chunks(160) {
-                    let b64 = base64::engine::general_purpose::STANDARD.encode(chunk);
-                    let msg = serde_json::json!({
-                        "event": "media",
-                        "streamSid": entry.stream_sid,
-                        "media": { "payload": b64 }
-                    });
-                    entry
-                        .response_tx
-                        .send(Message::Text(msg.to_string().into()))
-                        .await?;
+                let frames =
+                    FrameEncoder::twilio(&entry.stream_sid).with_frame_bytes(entry.frame_bytes);
+                for message in frames.messages(mulaw_bytes) {
+                    entry.response_tx.send(message).await?;
                 }
 
                 // Mark so Twilio knows when playback ends (resets VAD via Mark handler)
@@ -134,16 +493,10 @@ impl CallRegistry {
             }
             Transport::Discord => {
                 // Discord sidecar expects plain JSON audio messages (no Twilio envelope)
-                for chunk in mulaw_bytes.chunks(160) {
-                    let b64 = base64::engine::general_purpose::STANDARD.encode(chunk);
-                    let msg = serde_json::json!({
-                        "type": "audio",
-                        "audio": b64
-                    });
-                    entry
-                        .response_tx
-                        .send(Message::Text(msg.to_string().into()))
-                        .await?;
+                let frames = FrameEncoder::discord_batched(entry.batch_frames)
+                    .with_frame_bytes(entry.frame_bytes);
+                for message in frames.messages(mulaw_bytes) {
+                    entry.response_tx.send(message).await?;
                 }
 
                 // Mark so the discord stream handler resets VAD
@@ -158,3 +511,131 @@ impl CallRegistry {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn language_locks_after_agreeing_detections() {
+        let mut profile = CallProfile::default();
+        assert!(!profile.observe_language("es", 2));
+        assert!(!profile.observe_language("pt", 2));
+        assert_eq!(profile.language, None);
+        assert!(!profile.observe_language("es", 2));
+        assert!(profile.observe_language("es", 2));
+        assert_eq!(profile.language.as_deref(), Some("es"));
+        assert_eq!(profile.detection, None);
+    }
+
+    #[tokio::test]
+    async fn speakers_are_labelled_once_a_second_is_heard() {
+        let registry = CallRegistry::new();
+        let (tx, _rx) = mpsc::channel(1);
+        registry
+            .register(
+                "CA1".into(),
+                "MZ1".into(),
+                Transport::Twilio,
+                tx,
+                Arc::new(AtomicBool::new(false)),
+                CallProfile::default(),
+            )
+            .await;
+        let tone = |hz: f32| -> Vec<i16> {
+            (0..8000)
+                .map(|n| {
+                    (6000.0 * (2.0 * std::f32::consts::PI * hz * n as f32 / 8000.0).sin()) as i16
+                })
+                .collect()
+        };
+        let low = Voiceprint::of(&tone(300.0)).unwrap();
+        let high = Voiceprint::of(&tone(2500.0)).unwrap();
+        let config: DiarizationConfig = toml::from_str("").unwrap();
+
+        let identify = |voice| registry.identify_speaker("CA1", voice, &config, true);
+        assert_eq!(identify(&low).await, None);
+        assert_eq!(identify(&low).await, None);
+        assert_eq!(identify(&high).await, Some(2));
+        assert_eq!(identify(&low).await, Some(1));
+    }
+
+    #[tokio::test]
+    async fn deregister_cancels_and_is_idempotent() {
+        let registry = CallRegistry::new();
+        let (tx, _rx) = mpsc::channel(1);
+        let ended = registry
+            .register(
+                "CA1".into(),
+                "MZ1".into(),
+                Transport::Twilio,
+                tx,
+                Arc::new(AtomicBool::new(false)),
+                CallProfile::default(),
+            )
+            .await;
+        assert!(!ended.is_cancelled());
+        assert!(registry.deregister("CA1").await.is_some());
+        assert!(ended.is_cancelled());
+        assert!(registry.deregister("CA1").await.is_none());
+    }
+
+    #[tokio::test]
+    async fn entry_is_orphaned_once_handler_drops_receiver() {
+        let registry = CallRegistry::new();
+        let (tx, rx) = mpsc::channel(1);
+        registry
+            .register(
+                "CA1".into(),
+                "MZ1".into(),
+                Transport::Twilio,
+                tx,
+                Arc::new(AtomicBool::new(false)),
+                CallProfile::default(),
+            )
+            .await;
+        assert!(!registry.get("CA1").await.unwrap().is_orphaned());
+        drop(rx);
+        assert!(registry.get("CA1").await.unwrap().is_orphaned());
+    }
+
+    #[tokio::test]
+    async fn call_pressing_keys_moves_to_the_new_stream() {
+        let registry = CallRegistry::new();
+        let (tx, rx) = mpsc::channel(1);
+        let speaking = Arc::new(AtomicBool::new(false));
+        let ended = registry
+            .register(
+                "CA1".into(),
+                "MZ1".into(),
+                Transport::Twilio,
+                tx,
+                Arc::clone(&speaking),
+                CallProfile::default(),
+            )
+            .await;
+        assert!(!registry.continues_elsewhere("CA1", "MZ1").await);
+        let (tx, _rx) = mpsc::channel(1);
+        assert!(registry
+            .resume("CA1", "MZ2".into(), tx.clone(), Arc::clone(&speaking))
+            .await
+            .is_none());
+
+        // The old stream goes away without orphaning the call
+        assert!(registry.expect_resume("CA1").await);
+        drop(rx);
+        assert!(registry.continues_elsewhere("CA1", "MZ1").await);
+        assert!(!registry.get("CA1").await.unwrap().is_orphaned());
+
+        let (token, _) = registry
+            .resume("CA1", "MZ2".into(), tx, speaking)
+            .await
+            .unwrap();
+        assert_eq!(registry.get("CA1").await.unwrap().stream_sid, "MZ2");
+        // The old stream ending late leaves the call alone
+        assert!(registry.continues_elsewhere("CA1", "MZ1").await);
+        assert!(!registry.continues_elsewhere("CA1", "MZ2").await);
+        registry.deregister("CA1").await;
+        assert!(ended.is_cancelled() && token.is_cancelled());
+    }
+}
@@ -0,0 +1,422 @@
+//! Recurring calls, e.g. a daily medication reminder.
+//!
+//! Reminders are managed through `/api/reminders` and placed by the
+//! scheduler task: each has a cron schedule (see [`crate::cron`]) in the
+//! destination's timezone and the call to place. A `template` is rendered
+//! afresh on every run, with `{date}`, `{time}` and `{weekday}` filled in
+//! unless the reminder's `vars` set them. A run that can't be placed, or
+//! isn't answered within `[reminders] answer_timeout_secs`, is tried again
+//! up to `[reminders] retries` times; its outcome is published as a
+//! `reminder.finished` event, for `[[webhooks]]`, and POSTed to the
+//! reminder's own webhook if it has one. With `[reminders] file` set, reminders are kept
+//! across restarts. A run missed while the server was down fires once on
+//! start.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{broadcast, Mutex, Notify};
+
+use crate::api::outbound::{self, CallRequest};
+use crate::cron::Schedule;
+use crate::events::{CallEvent, EndReason};
+use crate::persist::{load_list, save_json};
+use crate::tenants::Tenant;
+use crate::webhooks;
+use crate::AppState;
+
+/// A recurring call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Reminder {
+    pub id: String,
+    pub schedule: Schedule,
+    /// Timezone the schedule runs in; unset means the server's.
+    pub timezone: Option<chrono_tz::Tz>,
+    /// Tenant that created the reminder; its Twilio account places it.
+    pub tenant: Option<String>,
+    /// Where this reminder's run outcomes go, as well as `[[webhooks]]`.
+    pub webhook_url: Option<String>,
+    /// Next scheduled run; `None` once the schedule never fires again.
+    pub next_at: Option<DateTime<Utc>>,
+    pub last_run: Option<RunOutcome>,
+    #[serde(flatten)]
+    pub request: CallRequest,
+}
+
+impl Reminder {
+    /// The first run strictly after `after`.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self.timezone {
+            Some(tz) => self
+                .schedule
+                .next_after(&after.with_timezone(&tz))
+                .map(|at| at.with_timezone(&Utc)),
+            None => self
+                .schedule
+                .next_after(&after.with_timezone(&chrono::Local))
+                .map(|at| at.with_timezone(&Utc)),
+        }
+    }
+
+    /// The call for a run at `at`, with the time vars added.
+    pub fn request_at(&self, at: DateTime<Utc>) -> CallRequest {
+        let (date, time, weekday) = match self.timezone {
+            Some(tz) => time_vars(at.with_timezone(&tz)),
+            None => time_vars(at.with_timezone(&chrono::Local)),
+        };
+        let mut request = self.request.clone();
+        for (key, value) in [("date", date), ("time", time), ("weekday", weekday)] {
+            request
+                .vars
+                .entry(key.to_string())
+                .or_insert(serde_json::Value::String(value));
+        }
+        request
+    }
+}
+
+fn time_vars<T: chrono::TimeZone>(at: DateTime<T>) -> (String, String, String)
+where
+    T::Offset: std::fmt::Display,
+{
+    (
+        at.format("%Y-%m-%d").to_string(),
+        at.format("%H:%M").to_string(),
+        at.format("%A").to_string(),
+    )
+}
+
+/// How a run ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunStatus {
+    /// Answered, and the call has since ended.
+    Completed,
+    /// Placed every time, never answered.
+    Unanswered,
+    /// Couldn't be placed.
+    Failed,
+}
+
+/// What happened on a run. Published as `reminder.finished`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RunOutcome {
+    pub reminder_id: String,
+    pub status: RunStatus,
+    pub scheduled_for: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub attempts: u32,
+    /// The last call placed.
+    pub call_sid: Option<String>,
+    /// Why the answered call ended.
+    pub end_reason: Option<EndReason>,
+    pub error: Option<String>,
+}
+
+pub struct Reminders {
+    reminders: Mutex<Vec<Reminder>>,
+    path: Option<PathBuf>,
+    pub(crate) changed: Notify,
+}
+
+impl Reminders {
+    /// The reminders saved at `path`, if any.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        Self {
+            reminders: Mutex::new(load_list(path.as_deref(), "reminders")),
+            path,
+            changed: Notify::new(),
+        }
+    }
+
+    /// Add a reminder, giving it an id and its first run. Returns it.
+    pub async fn add(&self, mut reminder: Reminder) -> Reminder {
+        reminder.id = format!("rem_{:016x}", rand::random::<u64>());
+        reminder.next_at = reminder.next_after(Utc::now());
+        let mut reminders = self.reminders.lock().await;
+        reminders.push(reminder.clone());
+        self.save(&reminders).await;
+        self.changed.notify_one();
+        reminder
+    }
+
+    /// All reminders, soonest first; finished ones last.
+    pub async fn list(&self) -> Vec<Reminder> {
+        let mut reminders = self.reminders.lock().await.clone();
+        reminders.sort_by_key(|r| (r.next_at.is_none(), r.next_at));
+        reminders
+    }
+
+    pub async fn get(&self, id: &str) -> Option<Reminder> {
+        self.reminders
+            .lock()
+            .await
+            .iter()
+            .find(|r| r.id == id)
+            .cloned()
+    }
+
+    /// Delete a reminder. Returns it, if it was there. A run in progress
+    /// carries on.
+    pub async fn remove(&self, id: &str) -> Option<Reminder> {
+        let mut reminders = self.reminders.lock().await;
+        let index = reminders.iter().position(|r| r.id == id)?;
+        let reminder = reminders.remove(index);
+        self.save(&reminders).await;
+        Some(reminder)
+    }
+
+    pub(crate) async fn next_at(&self) -> Option<DateTime<Utc>> {
+        self.reminders
+            .lock()
+            .await
+            .iter()
+            .filter_map(|r| r.next_at)
+            .min()
+    }
+
+    /// The reminders due by `now`, each with the time it was due. Their
+    /// next runs move past `now`.
+    pub(crate) async fn take_due(&self, now: DateTime<Utc>) -> Vec<(Reminder, DateTime<Utc>)> {
+        let mut reminders = self.reminders.lock().await;
+        let mut due = Vec::new();
+        for reminder in reminders.iter_mut() {
+            let Some(at) = reminder.next_at.filter(|at| *at <= now) else {
+                continue;
+            };
+            reminder.next_at = reminder.next_after(now);
+            due.push((reminder.clone(), at));
+        }
+        if !due.is_empty() {
+            self.save(&reminders).await;
+        }
+        due
+    }
+
+    /// Keep a run's outcome on its reminder, if that still exists.
+    async fn record(&self, outcome: &RunOutcome) {
+        let mut reminders = self.reminders.lock().await;
+        let Some(reminder) = reminders.iter_mut().find(|r| r.id == outcome.reminder_id) else {
+            return;
+        };
+        reminder.last_run = Some(outcome.clone());
+        self.save(&reminders).await;
+    }
+
+    async fn save(&self, reminders: &[Reminder]) {
+        if let Some(ref path) = self.path {
+            save_json(path, reminders, "reminders").await;
+        }
+    }
+}
+
+/// Why an attempt didn't get through.
+enum AttemptError {
+    /// The reminder itself is wrong (e.g. its agent is gone); not retried.
+    Invalid(String),
+    Failed(String),
+    Unanswered(String),
+}
+
+/// Run `reminder` as due at `at`: place it, retrying as configured, then
+/// record the outcome and publish it as `reminder.finished`.
+pub async fn run(state: AppState, reminder: Reminder, at: DateTime<Utc>) {
+    let config = &state.config.reminders;
+    tracing::info!(id = %reminder.id, to = %reminder.request.to, "Running reminder");
+
+    let mut attempts = 0;
+    let result = loop {
+        attempts += 1;
+        let result = attempt(&state, &reminder, at).await;
+        match result {
+            Err(AttemptError::Failed(ref e)) if attempts <= config.retries => {
+                tracing::warn!(id = %reminder.id, attempts, "Reminder call failed, retrying: {e}");
+            }
+            Err(AttemptError::Unanswered(ref call_sid)) if attempts <= config.retries => {
+                tracing::info!(id = %reminder.id, call_sid = %call_sid, attempts, "Reminder call unanswered, retrying");
+            }
+            result => break result,
+        }
+        tokio::time::sleep(Duration::from_secs(config.retry_delay_secs)).await;
+    };
+
+    let mut outcome = RunOutcome {
+        reminder_id: reminder.id.clone(),
+        status: RunStatus::Failed,
+        scheduled_for: at,
+        finished_at: Utc::now(),
+        attempts,
+        call_sid: None,
+        end_reason: None,
+        error: None,
+    };
+    match result {
+        Ok((call_sid, reason)) => {
+            outcome.status = RunStatus::Completed;
+            outcome.call_sid = Some(call_sid);
+            outcome.end_reason = Some(reason);
+        }
+        Err(AttemptError::Unanswered(call_sid)) => {
+            outcome.status = RunStatus::Unanswered;
+            outcome.call_sid = Some(call_sid);
+        }
+        Err(AttemptError::Invalid(e) | AttemptError::Failed(e)) => {
+            tracing::error!(id = %reminder.id, "Reminder failed: {e}");
+            outcome.error = Some(e);
+        }
+    }
+    tracing::info!(id = %reminder.id, status = ?outcome.status, attempts, "Reminder run finished");
+
+    state.reminders.record(&outcome).await;
+    state
+        .events
+        .publish(CallEvent::ReminderFinished(outcome.clone()));
+    if let Some(ref url) = reminder.webhook_url {
+        let hook = config.webhook(url);
+        webhooks::deliver(&state.http, &hook, &CallEvent::ReminderFinished(outcome)).await;
+    }
+}
+
+/// Place one call and follow it to its end.
+async fn attempt(
+    state: &AppState,
+    reminder: &Reminder,
+    at: DateTime<Utc>,
+) -> Result<(String, EndReason), AttemptError> {
+    if state.is_draining() {
+        return Err(AttemptError::Failed("Draining".to_string()));
+    }
+    let tenant: Option<Arc<Tenant>> = match reminder.tenant {
+        Some(ref name) => Some(
+            state
+                .tenants
+                .get(name)
+                .ok_or_else(|| AttemptError::Invalid(format!("Unknown tenant '{name}'")))?,
+        ),
+        None => None,
+    };
+    let mut request = reminder.request_at(at);
+    outbound::prepare(&mut request, tenant.as_deref(), state).map_err(AttemptError::Invalid)?;
+
+    // Subscribe first, so a quick answer isn't missed
+    let mut events = state.events.subscribe();
+    let twilio = tenant.as_ref().map_or(&state.twilio, |t| &t.twilio).clone();
+    let call_sid = outbound::place(state, tenant, request)
+        .await
+        .map_err(|e| AttemptError::Failed(e.to_string()))?;
+
+    let answer_timeout = Duration::from_secs(state.config.reminders.answer_timeout_secs);
+    // The stream may have started before `place` returned
+    let answered = state.call_registry.get(&call_sid).await.is_some()
+        || tokio::time::timeout(
+            answer_timeout,
+            next_event(&mut events, |event| {
+                matches!(event, CallEvent::CallStarted { call_sid: sid, .. } if *sid == call_sid)
+            }),
+        )
+        .await
+        .is_ok_and(|event| event.is_some());
+    if !answered {
+        if let Err(e) = twilio.hangup(&call_sid).await {
+            tracing::warn!(call_sid = %call_sid, "Failed to hang up unanswered reminder call: {e}");
+        }
+        state.call_metas.write().await.remove(&call_sid);
+        return Err(AttemptError::Unanswered(call_sid));
+    }
+
+    let ended = next_event(
+        &mut events,
+        |event| matches!(event, CallEvent::CallEnded { call_sid: sid, .. } if *sid == call_sid),
+    )
+    .await;
+    let reason = match ended {
+        Some(CallEvent::CallEnded { reason, .. }) => reason,
+        _ => EndReason::Disconnected,
+    };
+    Ok((call_sid, reason))
+}
+
+/// The next event matching `wanted`; `None` if the bus is gone.
+async fn next_event(
+    events: &mut broadcast::Receiver<CallEvent>,
+    wanted: impl Fn(&CallEvent) -> bool,
+) -> Option<CallEvent> {
+    loop {
+        match events.recv().await {
+            Ok(event) if wanted(&event) => return Some(event),
+            Ok(_) | Err(broadcast::error::RecvError::Lagged(_)) => {}
+            Err(broadcast::error::RecvError::Closed) => return None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn reminder(schedule: &str) -> Reminder {
+        serde_json::from_value(serde_json::json!({
+            "id": "",
+            "schedule": schedule,
+            "timezone": "Europe/Madrid",
+            "to": "+34600000001",
+            "template": "meds",
+            "vars": { "pill": "aspirin" }
+        }))
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn takes_due_reminders_and_moves_them_on() {
+        let reminders = Reminders::load(None);
+        let added = reminders.add(reminder("0 9 * * *")).await;
+        let next = added.next_at.unwrap();
+        assert!(next > Utc::now());
+        assert!(reminders.take_due(Utc::now()).await.is_empty());
+
+        let due = reminders.take_due(next).await;
+        assert_eq!(due.len(), 1);
+        assert_eq!(due[0].1, next);
+        let after = reminders.next_at().await.unwrap();
+        assert!(after > next);
+        assert_eq!(Some(after), added.next_after(next));
+    }
+
+    #[test]
+    fn adds_time_vars_without_overriding() {
+        let mut reminder = reminder("0 9 * * *");
+        let at: DateTime<Utc> = "2025-01-15T08:00:00Z".parse().unwrap();
+        let request = reminder.request_at(at);
+        assert_eq!(request.vars["date"], "2025-01-15");
+        assert_eq!(request.vars["time"], "09:00");
+        assert_eq!(request.vars["weekday"], "Wednesday");
+        assert_eq!(request.vars["pill"], "aspirin");
+
+        reminder
+            .request
+            .vars
+            .insert("time".to_string(), "morning".into());
+        assert_eq!(reminder.request_at(at).vars["time"], "morning");
+    }
+
+    #[tokio::test]
+    async fn survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("reminders.json");
+        let reminders = Reminders::load(Some(path.clone()));
+        let kept = reminders.add(reminder("30 8 * * mon-fri")).await;
+        let removed = reminders.add(reminder("@daily")).await;
+        assert!(reminders.remove(&removed.id).await.is_some());
+        assert!(reminders.remove(&removed.id).await.is_none());
+
+        let reloaded = Reminders::load(Some(path)).list().await;
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].id, kept.id);
+        assert_eq!(reloaded[0].next_at, kept.next_at);
+        assert_eq!(reloaded[0].schedule.to_string(), "30 8 * * mon-fri");
+        assert_eq!(reloaded[0].request.template.as_deref(), Some("meds"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Re-transcription of captured calls (`voice-echo --retranscribe`).
+//!
+//! A capture (`[capture] dir`) keeps everything the caller said. Run back
+//! through the VAD and `[stt] provider`, with another model than the call
+//! had if asked (`whisper-large-v3` after a call on turbo, say), it shows
+//! what was misheard on a call that mattered. The transcript is written
+//! next to the capture as `<call_sid>.transcript.json`, replacing the one
+//! from any earlier run, masked per `[privacy]` like kept conversations. An
+//! utterance STT fails on is kept with its error and the run goes on.
+
+use std::path::{Path, PathBuf};
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::capture;
+use crate::config::{Config, VadConfig};
+use crate::pipeline::frames::FRAME_BYTES;
+use crate::pipeline::privacy::PrivacyMask;
+use crate::pipeline::turn;
+use crate::pipeline::vad::VoiceActivityDetector;
+
+#[derive(Debug, Serialize)]
+pub struct Retranscript {
+    pub vendor: &'static str,
+    /// The model asked for; unset for the vendor block's.
+    pub model: Option<String>,
+    pub transcribed_at: DateTime<Utc>,
+    pub utterances: Vec<Utterance>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Utterance {
+    /// Where the utterance starts in the call.
+    pub at_ms: u64,
+    /// Empty when STT failed on it.
+    pub text: String,
+    /// Detected, with `[language] detect` on.
+    pub language: Option<String>,
+    pub confidence: Option<f32>,
+    /// Why STT failed on it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Transcribe the capture at `path` again, with `model` in place of the
+/// vendor block's if set, and write the transcript next to it. Returns
+/// where it went and what was heard.
+pub async fn run(
+    config: &Config,
+    path: &Path,
+    model: Option<&str>,
+) -> Result<(PathBuf, Retranscript), Box<dyn std::error::Error + Send + Sync>> {
+    let mulaw = capture::inbound_audio(path).await?;
+    let http = crate::http_client(&config.http)
+        .map_err(|e| format!("Failed to build the [http] client: {e}"))?;
+    let stt = crate::stt_vendor_client(&http, config.stt.provider, model, None, config);
+    let hint = match config.language.detect {
+        true => None,
+        false => Some(config.language.default.as_str()),
+    };
+    let mask = PrivacyMask::new(&config.privacy, &config.profanity);
+
+    let mut utterances = Vec::new();
+    for (at_ms, pcm) in split(&mulaw, &config.vad) {
+        let wav = turn::stt_wav(&config.stt, &pcm);
+        let heard = match stt.transcribe_in(wav, hint).await {
+            Ok(heard) => heard,
+            Err(e) => {
+                tracing::warn!(at_ms, "STT failed on utterance: {e}");
+                utterances.push(Utterance {
+                    at_ms,
+                    text: String::new(),
+                    language: None,
+                    confidence: None,
+                    error: Some(e.to_string()),
+                });
+                continue;
+            }
+        };
+        let text = heard.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        tracing::info!(at_ms, transcript = %text, "Transcribed");
+        utterances.push(Utterance {
+            at_ms,
+            text: mask.apply(text).into_owned(),
+            language: heard.language,
+            confidence: heard.confidence,
+            error: None,
+        });
+    }
+
+    let transcript = Retranscript {
+        vendor: stt.vendor(),
+        model: model.map(String::from),
+        transcribed_at: Utc::now(),
+        utterances,
+    };
+    let out = path.with_extension("transcript.json");
+    tokio::fs::write(&out, serde_json::to_vec_pretty(&transcript)?).await?;
+    Ok((out, transcript))
+}
+
+/// The utterances `[vad]` cuts from a call's audio, with where each starts.
+fn split(mulaw: &[u8], config: &VadConfig) -> Vec<(u64, Vec<i16>)> {
+    let mut vad = VoiceActivityDetector::from_config(config);
+    let mut utterances = Vec::new();
+    let mut fed = 0;
+    let start = |fed: usize, pcm: &[i16]| (fed.saturating_sub(pcm.len()) / 8) as u64;
+    for frame in mulaw.chunks(FRAME_BYTES) {
+        fed += frame.len();
+        if let Some(pcm) = vad.feed(frame) {
+            utterances.push((start(fed, &pcm), pcm));
+        }
+    }
+    // The call ended mid-sentence
+    if let Some(pcm) = vad.flush() {
+        utterances.push((start(fed, &pcm), pcm));
+    }
+    utterances
+}
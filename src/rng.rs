@@ -0,0 +1,36 @@
+//! Randomness that can be pinned with `[debug] seed`.
+//!
+//! Components that pick at random (greetings, hold music shuffle, `[chaos]`
+//! faults, `[local_llm]` sampling) draw from a `SharedRng` instead of
+//! `thread_rng`, so a seeded run makes the same choices every time: snapshot
+//! tests and demos come out the same.
+//! Sequences are stable for a given build, not across `rand` upgrades.
+
+use std::sync::{Mutex, PoisonError};
+
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+/// A `StdRng` behind a lock, seeded from `seed` or from the OS.
+pub struct SharedRng(Mutex<StdRng>);
+
+impl SharedRng {
+    pub fn new(seed: Option<u64>) -> Self {
+        let rng = match seed {
+            Some(seed) => StdRng::seed_from_u64(seed),
+            None => StdRng::from_entropy(),
+        };
+        Self(Mutex::new(rng))
+    }
+
+    /// Run `f` with the generator.
+    pub fn with<T>(&self, f: impl FnOnce(&mut StdRng) -> T) -> T {
+        f(&mut self.0.lock().unwrap_or_else(PoisonError::into_inner))
+    }
+}
+
+impl Default for SharedRng {
+    fn default() -> Self {
+        Self::new(None)
+    }
+}
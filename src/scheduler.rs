@@ -0,0 +1,207 @@
+//! Deferred outbound calls.
+//!
+//! `/api/call` hands calls that land in `[outbound] quiet_hours` to the
+//! scheduler, which places them once the window ends. With `[outbound]
+//! schedule_file` set, pending calls are written there on every change and
+//! reloaded on start, so a restart doesn't drop them.
+//!
+//! The same task starts recurring reminder runs (see [`crate::reminders`])
+//! as they fall due.
+
+use std::path::PathBuf;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::{Mutex, Notify};
+
+use crate::api::outbound::{self, CallRequest};
+use crate::persist::{load_list, save_json};
+use crate::reminders;
+use crate::AppState;
+
+/// Longest sleep between checks, so clock changes are noticed.
+const MAX_SLEEP: Duration = Duration::from_secs(60);
+
+/// A call waiting to be placed.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ScheduledCall {
+    pub id: String,
+    pub at: DateTime<Utc>,
+    /// Tenant that asked for the call; its Twilio account places it.
+    pub tenant: Option<String>,
+    pub request: CallRequest,
+}
+
+pub struct Scheduler {
+    calls: Mutex<Vec<ScheduledCall>>,
+    path: Option<PathBuf>,
+    changed: Notify,
+}
+
+impl Scheduler {
+    /// A scheduler holding the calls saved at `path`, if any.
+    pub fn load(path: Option<PathBuf>) -> Self {
+        Self {
+            calls: Mutex::new(load_list(path.as_deref(), "scheduled calls")),
+            path,
+            changed: Notify::new(),
+        }
+    }
+
+    /// Schedule `request` for `at`. Returns its id.
+    pub async fn defer(
+        &self,
+        at: DateTime<Utc>,
+        tenant: Option<String>,
+        request: CallRequest,
+    ) -> String {
+        let id = format!("sch_{:016x}", rand::random::<u64>());
+        let mut calls = self.calls.lock().await;
+        calls.push(ScheduledCall {
+            id: id.clone(),
+            at,
+            tenant,
+            request,
+        });
+        self.save(&calls).await;
+        self.changed.notify_one();
+        id
+    }
+
+    /// Pending calls, soonest first.
+    pub async fn pending(&self) -> Vec<ScheduledCall> {
+        let mut calls = self.calls.lock().await.clone();
+        calls.sort_by_key(|call| call.at);
+        calls
+    }
+
+    /// Drop a pending call. Returns it, if it was there.
+    pub async fn cancel(&self, id: &str) -> Option<ScheduledCall> {
+        let mut calls = self.calls.lock().await;
+        let index = calls.iter().position(|call| call.id == id)?;
+        let call = calls.remove(index);
+        self.save(&calls).await;
+        Some(call)
+    }
+
+    async fn next_at(&self) -> Option<DateTime<Utc>> {
+        self.calls.lock().await.iter().map(|call| call.at).min()
+    }
+
+    /// Remove and return the calls due by `now`.
+    async fn take_due(&self, now: DateTime<Utc>) -> Vec<ScheduledCall> {
+        let mut calls = self.calls.lock().await;
+        let (due, later): (Vec<_>, Vec<_>) = calls.drain(..).partition(|call| call.at <= now);
+        *calls = later;
+        if !due.is_empty() {
+            self.save(&calls).await;
+        }
+        due
+    }
+
+    async fn save(&self, calls: &[ScheduledCall]) {
+        if let Some(ref path) = self.path {
+            save_json(path, calls, "scheduled calls").await;
+        }
+    }
+}
+
+/// Place scheduled calls and start reminder runs as they fall due, until
+/// the task is aborted. Nothing is placed while draining; due calls wait
+/// for the next instance.
+pub async fn run(state: AppState) {
+    let scheduler = &state.scheduler;
+    let reminders = &state.reminders;
+    loop {
+        let next = [scheduler.next_at().await, reminders.next_at().await]
+            .into_iter()
+            .flatten()
+            .min();
+        let wait = match next {
+            Some(at) => (at - Utc::now())
+                .to_std()
+                .unwrap_or_default()
+                .min(MAX_SLEEP),
+            None => MAX_SLEEP,
+        };
+        tokio::select! {
+            _ = scheduler.changed.notified() => continue,
+            _ = reminders.changed.notified() => continue,
+            _ = tokio::time::sleep(wait) => {}
+        }
+        if state.is_draining() {
+            continue;
+        }
+        for call in scheduler.take_due(Utc::now()).await {
+            let tenant = match call.tenant {
+                Some(ref name) => match state.tenants.get(name) {
+                    Some(tenant) => Some(tenant),
+                    None => {
+                        tracing::warn!(id = %call.id, tenant = %name, "Dropping scheduled call for unknown tenant");
+                        continue;
+                    }
+                },
+                None => None,
+            };
+            tracing::info!(id = %call.id, to = %call.request.to, "Placing scheduled call");
+            if let Err(e) = outbound::place(&state, tenant, call.request).await {
+                tracing::error!(id = %call.id, "Scheduled call failed: {e}");
+            }
+        }
+        // Runs wait on the call to end, so each gets its own task
+        for (reminder, at) in reminders.take_due(Utc::now()).await {
+            tokio::spawn(reminders::run(state.clone(), reminder, at));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn request(to: &str) -> CallRequest {
+        serde_json::from_value(serde_json::json!({ "to": to })).unwrap()
+    }
+
+    #[tokio::test]
+    async fn takes_only_due_calls() {
+        let scheduler = Scheduler::load(None);
+        let now = Utc::now();
+        scheduler
+            .defer(now + chrono::Duration::hours(1), None, request("+1"))
+            .await;
+        let due = scheduler
+            .defer(now - chrono::Duration::seconds(1), None, request("+2"))
+            .await;
+
+        assert_eq!(
+            scheduler.next_at().await,
+            Some(now - chrono::Duration::seconds(1))
+        );
+        let taken = scheduler.take_due(now).await;
+        assert_eq!(taken.len(), 1);
+        assert_eq!(taken[0].id, due);
+        assert_eq!(scheduler.pending().await[0].request.to, "+1");
+    }
+
+    #[tokio::test]
+    async fn survives_a_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("schedule.json");
+        let scheduler = Scheduler::load(Some(path.clone()));
+        let at = Utc::now() + chrono::Duration::hours(8);
+        let kept = scheduler
+            .defer(at, Some("acme".to_string()), request("+1"))
+            .await;
+        let cancelled = scheduler.defer(at, None, request("+2")).await;
+        assert!(scheduler.cancel(&cancelled).await.is_some());
+        assert!(scheduler.cancel(&cancelled).await.is_none());
+
+        let reloaded = Scheduler::load(Some(path)).pending().await;
+        assert_eq!(reloaded.len(), 1);
+        assert_eq!(reloaded[0].id, kept);
+        assert_eq!(reloaded[0].at, at);
+        assert_eq!(reloaded[0].tenant.as_deref(), Some("acme"));
+    }
+}
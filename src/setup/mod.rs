@@ -6,6 +6,7 @@ mod writer;
 use std::io::IsTerminal;
 
 use rand::Rng;
+use voice_echo::config::TtsVendor;
 
 use writer::SetupValues;
 
@@ -44,17 +45,53 @@ pub fn run() {
     println!("\n  {} Groq (Whisper STT)", ansi::bold(">>"));
     let groq_api_key = prompts::ask_secret("API Key");
 
-    // Inworld TTS
-    println!("\n  {} Inworld TTS", ansi::bold(">>"));
-    let inworld_api_key = prompts::ask_secret("API Key (base64 credential)");
-    let inworld_voice_id = prompts::ask("Voice ID", Some("Olivia"));
+    // TTS
+    println!("\n  {} Text-to-speech", ansi::bold(">>"));
+    let tts_provider = loop {
+        let provider = prompts::ask(
+            "Provider (inworld, elevenlabs, openai, azure)",
+            Some("inworld"),
+        );
+        match provider.as_str() {
+            "inworld" => break TtsVendor::Inworld,
+            "elevenlabs" => break TtsVendor::ElevenLabs,
+            "openai" => break TtsVendor::OpenAi,
+            "azure" => break TtsVendor::Azure,
+            _ => println!(
+                "  {} Expected inworld, elevenlabs, openai or azure",
+                ansi::red("!")
+            ),
+        }
+    };
+    let (tts_api_key, tts_voice_id) = match tts_provider {
+        TtsVendor::Inworld => (
+            prompts::ask_secret("Inworld API Key (base64 credential)"),
+            prompts::ask("Voice ID", Some("Olivia")),
+        ),
+        TtsVendor::ElevenLabs => (
+            prompts::ask_secret("ElevenLabs API Key"),
+            prompts::ask("Voice ID", Some("21m00Tcm4TlvDq8ikWAM")),
+        ),
+        TtsVendor::OpenAi => (
+            prompts::ask_secret("OpenAI API Key"),
+            prompts::ask("Voice", Some("alloy")),
+        ),
+        TtsVendor::Azure => (
+            prompts::ask_secret("Azure Speech Key"),
+            prompts::ask("Voice", Some("en-US-JennyNeural")),
+        ),
+    };
+    let tts_region = match tts_provider {
+        TtsVendor::Azure => Some(prompts::ask("Region", Some("westeurope"))),
+        _ => None,
+    };
 
     // Server
     println!("\n  {} Server", ansi::bold(">>"));
     let external_url = prompts::ask("External URL", None);
 
     // Generate API token
-    let api_token = generate_hex_token(32);
+    let api_token = generate_hex_token(&mut rand::thread_rng(), 32);
     println!("\n  {} Generated ECHO_API_TOKEN", ansi::green("\u{2713}"));
 
     // Write config files
@@ -63,8 +100,10 @@ pub fn run() {
         twilio_auth_token,
         twilio_phone_number,
         groq_api_key,
-        inworld_api_key,
-        inworld_voice_id,
+        tts_provider,
+        tts_api_key,
+        tts_voice_id,
+        tts_region,
         external_url: external_url.clone(),
         api_token,
     };
@@ -108,8 +147,24 @@ fn validate_e164(s: &str) -> bool {
 }
 
 /// Generate a hex token of `byte_len` random bytes (output is 2x byte_len chars).
-fn generate_hex_token(byte_len: usize) -> String {
-    let mut rng = rand::thread_rng();
+fn generate_hex_token(rng: &mut impl Rng, byte_len: usize) -> String {
     let bytes: Vec<u8> = (0..byte_len).map(|_| rng.gen()).collect();
     bytes.iter().map(|b| format!("{b:02x}")).collect()
 }
+
+#[cfg(test)]
+mod tests {
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    use super::*;
+
+    #[test]
+    fn hex_token_from_seeded_rng() {
+        let token = generate_hex_token(&mut StdRng::seed_from_u64(1), 32);
+        assert_eq!(token.len(), 64);
+        assert!(token.chars().all(|c| c.is_ascii_hexdigit()));
+        assert_eq!(token, generate_hex_token(&mut StdRng::seed_from_u64(1), 32));
+        assert_ne!(token, generate_hex_token(&mut StdRng::seed_from_u64(2), 32));
+    }
+}
@@ -2,6 +2,8 @@ use std::fs;
 use std::os::unix::fs::PermissionsExt;
 use std::path::{Path, PathBuf};
 
+use voice_echo::config::TtsVendor;
+
 use super::ansi;
 use super::prompts::confirm;
 
@@ -11,8 +13,11 @@ pub struct SetupValues {
     pub twilio_auth_token: String,
     pub twilio_phone_number: String,
     pub groq_api_key: String,
-    pub inworld_api_key: String,
-    pub inworld_voice_id: String,
+    pub tts_provider: TtsVendor,
+    pub tts_api_key: String,
+    pub tts_voice_id: String,
+    /// Azure only.
+    pub tts_region: Option<String>,
     pub external_url: String,
     pub api_token: String,
 }
@@ -78,12 +83,7 @@ phone_number = "{phone}"
 api_key = ""
 model = "whisper-large-v3-turbo"
 
-[inworld]
-# Secret loaded from .env (INWORLD_API_KEY)
-api_key = ""
-voice_id = "{voice_id}"
-model = "inworld-tts-1.5-max"
-
+{tts}
 [claude]
 session_timeout_secs = 300
 greeting = "Hello, this is Echo"
@@ -102,13 +102,78 @@ energy_threshold = 50
 # volume = 0.3
 "#,
         phone = values.twilio_phone_number,
-        voice_id = values.inworld_voice_id,
+        tts = tts_toml(values),
     );
 
     fs::write(path, content).expect("Failed to write config.toml");
     println!("  {} {}", ansi::green("\u{2713}"), path.display());
 }
 
+/// `[tts]` and the chosen vendor's block.
+fn tts_toml(values: &SetupValues) -> String {
+    match values.tts_provider {
+        TtsVendor::Inworld => format!(
+            r#"[tts]
+provider = "inworld"
+
+[inworld]
+# Secret loaded from .env (INWORLD_API_KEY)
+api_key = ""
+voice_id = "{voice_id}"
+model = "inworld-tts-1.5-max"
+"#,
+            voice_id = values.tts_voice_id,
+        ),
+        TtsVendor::ElevenLabs => format!(
+            r#"[tts]
+provider = "elevenlabs"
+
+[elevenlabs]
+# Secret loaded from .env (ELEVENLABS_API_KEY)
+api_key = ""
+voice_id = "{voice_id}"
+model = "eleven_flash_v2_5"
+"#,
+            voice_id = values.tts_voice_id,
+        ),
+        TtsVendor::OpenAi => format!(
+            r#"[tts]
+provider = "openai"
+
+[openai]
+# Secret loaded from .env (OPENAI_API_KEY)
+api_key = ""
+tts_model = "tts-1"
+voice = "{voice}"
+"#,
+            voice = values.tts_voice_id,
+        ),
+        TtsVendor::Azure => format!(
+            r#"[tts]
+provider = "azure"
+
+[azure]
+# Secret loaded from .env (AZURE_SPEECH_KEY)
+api_key = ""
+region = "{region}"
+voice = "{voice}"
+"#,
+            region = values.tts_region.as_deref().unwrap_or("westeurope"),
+            voice = values.tts_voice_id,
+        ),
+    }
+}
+
+/// The chosen TTS vendor's `.env` comment and key variable.
+fn tts_env(vendor: TtsVendor) -> &'static str {
+    match vendor {
+        TtsVendor::Inworld => "# Inworld (TTS)\nINWORLD_API_KEY",
+        TtsVendor::ElevenLabs => "# ElevenLabs (TTS)\nELEVENLABS_API_KEY",
+        TtsVendor::OpenAi => "# OpenAI (TTS)\nOPENAI_API_KEY",
+        TtsVendor::Azure => "# Azure Speech (TTS)\nAZURE_SPEECH_KEY",
+    }
+}
+
 fn write_env_file(path: &Path, values: &SetupValues) {
     let content = format!(
         r#"# Twilio
@@ -118,8 +183,7 @@ TWILIO_AUTH_TOKEN={twilio_token}
 # Groq (Whisper STT)
 GROQ_API_KEY={groq_key}
 
-# Inworld (TTS)
-INWORLD_API_KEY={inworld_key}
+{tts_env}={tts_key}
 
 # API bearer token for /api/* endpoints
 ECHO_API_TOKEN={api_token}
@@ -130,7 +194,8 @@ SERVER_EXTERNAL_URL={external_url}
         twilio_sid = values.twilio_account_sid,
         twilio_token = values.twilio_auth_token,
         groq_key = values.groq_api_key,
-        inworld_key = values.inworld_api_key,
+        tts_env = tts_env(values.tts_provider),
+        tts_key = values.tts_api_key,
         api_token = values.api_token,
         external_url = values.external_url,
     );
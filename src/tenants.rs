@@ -0,0 +1,224 @@
+//! Tenants (`[[tenants]]`).
+//!
+//! A tenant is one customer on a shared instance: its own API token, Twilio
+//! numbers, provider keys, agents and data directory. Calls are attributed
+//! to a tenant by the number dialled (inbound) or the API token that placed
+//! them (outbound), and a tenant's token only reaches its own calls.
+//! Without `[[tenants]]` everything runs as the single top-level operator.
+
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use crate::config::TenantConfig;
+use crate::pipeline::stt::SttClient;
+use crate::pipeline::tts::TtsClient;
+use crate::twilio::outbound::TwilioClient;
+
+pub struct Tenant {
+    pub name: String,
+    api_token: String,
+    phone_numbers: Vec<String>,
+    agents: Vec<String>,
+    data_dir: Option<PathBuf>,
+    pub stt: Arc<SttClient>,
+    /// STT for the tenant's Discord sessions; `stt` unless set.
+    pub discord_stt: Arc<SttClient>,
+    pub tts: Arc<TtsClient>,
+    pub twilio: Arc<TwilioClient>,
+}
+
+impl Tenant {
+    /// Build a tenant around its providers (its own, or the shared ones
+    /// where it doesn't override a key).
+    pub fn new(
+        tenant: &TenantConfig,
+        stt: Arc<SttClient>,
+        tts: Arc<TtsClient>,
+        twilio: Arc<TwilioClient>,
+    ) -> Self {
+        let mut phone_numbers = tenant.phone_numbers.clone();
+        if let Some(ref account) = tenant.twilio {
+            phone_numbers.push(account.phone_number.clone());
+        }
+        Self {
+            name: tenant.name.clone(),
+            api_token: tenant.api_token.clone(),
+            phone_numbers,
+            agents: tenant.agents.clone(),
+            data_dir: tenant.data_dir.as_ref().map(PathBuf::from),
+            discord_stt: Arc::clone(&stt),
+            stt,
+            tts,
+            twilio,
+        }
+    }
+
+    /// Transcribe the tenant's Discord sessions with `stt` (`[stt.discord]`).
+    pub fn with_discord_stt(mut self, stt: Arc<SttClient>) -> Self {
+        self.discord_stt = stt;
+        self
+    }
+
+    /// Whether the tenant may use the named agent.
+    pub fn allows_agent(&self, agent: &str) -> bool {
+        self.agents.is_empty() || self.agents.iter().any(|a| a == agent)
+    }
+
+    /// The agent the tenant's calls use when none is picked.
+    pub fn default_agent(&self) -> Option<&str> {
+        self.agents.first().map(String::as_str)
+    }
+
+    /// Where the tenant's voicemails are saved, if it has a data directory.
+    pub fn voicemail_dir(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join("voicemail"))
+    }
+
+    /// Where the tenant's caller memories are kept, if it has a data
+    /// directory.
+    pub fn memory_dir(&self) -> Option<PathBuf> {
+        self.data_dir.as_ref().map(|dir| dir.join("memory"))
+    }
+}
+
+/// All configured tenants.
+#[derive(Default)]
+pub struct Tenants {
+    tenants: Vec<Arc<Tenant>>,
+}
+
+impl Tenants {
+    pub fn new(tenants: Vec<Tenant>) -> Self {
+        Self {
+            tenants: tenants.into_iter().map(Arc::new).collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.tenants.is_empty()
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<Tenant>> {
+        self.tenants.iter().find(|t| t.name == name).cloned()
+    }
+
+    /// The tenant owning an API token.
+    pub fn for_token(&self, token: &str) -> Option<Arc<Tenant>> {
+        self.tenants
+            .iter()
+            .find(|t| !t.api_token.is_empty() && t.api_token == token)
+            .cloned()
+    }
+
+    /// The tenant owning a dialled number.
+    pub fn for_number(&self, number: &str) -> Option<Arc<Tenant>> {
+        self.tenants
+            .iter()
+            .find(|t| t.phone_numbers.iter().any(|n| n == number))
+            .cloned()
+    }
+}
+
+/// Tenants for tests elsewhere, built around providers that go nowhere.
+#[cfg(test)]
+pub(crate) mod fixture {
+    use super::*;
+    use crate::breaker::CircuitBreaker;
+    use crate::config::BreakerConfig;
+    use crate::pipeline::stt::GroqStt;
+    use crate::pipeline::tts::InworldTts;
+
+    /// A tenant from its `[[tenants]]` entry.
+    pub fn tenant(toml: &str) -> Tenant {
+        let config: TenantConfig = toml::from_str(toml).unwrap();
+        let breaker = || CircuitBreaker::new("test", &BreakerConfig::default());
+        let twilio = config
+            .twilio
+            .clone()
+            .unwrap_or(crate::config::TwilioConfig {
+                account_sid: String::new(),
+                auth_token: String::new(),
+                phone_number: String::new(),
+            });
+        Tenant::new(
+            &config,
+            Arc::new(SttClient::new(
+                Box::new(GroqStt::new(
+                    reqwest::Client::new(),
+                    String::new(),
+                    String::new(),
+                )),
+                breaker(),
+            )),
+            Arc::new(TtsClient::new(
+                Box::new(InworldTts::new(
+                    reqwest::Client::new(),
+                    String::new(),
+                    String::new(),
+                )),
+                String::new(),
+                breaker(),
+            )),
+            Arc::new(TwilioClient::new(
+                reqwest::Client::new(),
+                &twilio,
+                "https://h",
+                breaker(),
+            )),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fixture::tenant;
+    use super::*;
+
+    #[test]
+    fn resolves_by_token_and_number() {
+        let tenants = Tenants::new(vec![
+            tenant(
+                r#"name = "acme"
+                   api_token = "t-acme"
+                   phone_numbers = ["+15550001"]"#,
+            ),
+            tenant(
+                r#"name = "globex"
+                   api_token = "t-globex"
+                   [twilio]
+                   account_sid = "AC1"
+                   auth_token = "x"
+                   phone_number = "+15550002""#,
+            ),
+        ]);
+        assert_eq!(tenants.for_token("t-globex").unwrap().name, "globex");
+        assert!(tenants.for_token("t-other").is_none());
+        assert_eq!(tenants.for_number("+15550001").unwrap().name, "acme");
+        assert_eq!(tenants.for_number("+15550002").unwrap().name, "globex");
+    }
+
+    #[test]
+    fn restricts_agents() {
+        let open = tenant(
+            r#"name = "a"
+                            api_token = "t""#,
+        );
+        assert!(open.allows_agent("anyone"));
+        assert!(open.default_agent().is_none());
+
+        let scoped = tenant(
+            r#"name = "b"
+               api_token = "t"
+               agents = ["support", "sales"]
+               data_dir = "/srv/b""#,
+        );
+        assert_eq!(scoped.default_agent(), Some("support"));
+        assert!(scoped.allows_agent("sales"));
+        assert!(!scoped.allows_agent("alerts"));
+        assert_eq!(
+            scoped.voicemail_dir(),
+            Some(PathBuf::from("/srv/b/voicemail"))
+        );
+        assert_eq!(scoped.memory_dir(), Some(PathBuf::from("/srv/b/memory")));
+    }
+}
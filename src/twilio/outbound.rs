@@ -1,29 +1,163 @@
+use crate::breaker::CircuitBreaker;
 use crate::config::TwilioConfig;
+use crate::twilio::webhook::{media_stream_url, stream_element, xml_escape};
 
-/// Twilio REST API client for initiating outbound calls.
+/// True if `digits` can be pressed with `<Play digits>`: keys `0-9 * #`,
+/// and `w` for a half-second pause.
+pub fn is_dtmf(digits: &str) -> bool {
+    !digits.is_empty()
+        && digits
+            .chars()
+            .all(|c| c.is_ascii_digit() || matches!(c, '*' | '#' | 'w'))
+}
+
+/// Twilio REST API client for initiating outbound calls and acting on
+/// live ones.
 pub struct TwilioClient {
     client: reqwest::Client,
     account_sid: String,
     auth_token: String,
     from_number: String,
     external_url: String,
+    breaker: CircuitBreaker,
+    #[cfg(feature = "mocks")]
+    mock: Option<crate::mocks::MockTwilio>,
 }
 
 impl TwilioClient {
-    pub fn new(twilio_config: &TwilioConfig, external_url: &str) -> Self {
+    pub fn new(
+        client: reqwest::Client,
+        twilio_config: &TwilioConfig,
+        external_url: &str,
+        breaker: CircuitBreaker,
+    ) -> Self {
         Self {
-            client: reqwest::Client::new(),
+            client,
             account_sid: twilio_config.account_sid.clone(),
             auth_token: twilio_config.auth_token.clone(),
             from_number: twilio_config.phone_number.clone(),
             external_url: external_url.to_string(),
+            breaker,
+            #[cfg(feature = "mocks")]
+            mock: None,
         }
     }
 
+    /// Place calls through `mock` instead of Twilio (`[mocks] twilio`).
+    #[cfg(feature = "mocks")]
+    pub fn with_mock(mut self, mock: crate::mocks::MockTwilio) -> Self {
+        self.mock = Some(mock);
+        self
+    }
+
+    /// Circuit breaker guarding the Twilio REST API.
+    pub fn breaker(&self) -> &CircuitBreaker {
+        &self.breaker
+    }
+
     /// Initiate an outbound call. Twilio will call `to`, and when answered,
     /// POST to our /twilio/voice/outbound webhook which provides TwiML
     /// to connect the media stream. The greeting is handled by the stream via TTS.
     pub async fn call(&self, to: &str) -> Result<String, OutboundError> {
+        #[cfg(feature = "mocks")]
+        if let Some(ref mock) = self.mock {
+            return Ok(mock.call(to).await);
+        }
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| OutboundError::Unavailable(e.to_string()))?;
+        let result = self.create_call(to).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// End a call, whether it is still ringing or already connected.
+    pub async fn hangup(&self, call_sid: &str) -> Result<(), OutboundError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| OutboundError::Unavailable(e.to_string()))?;
+        let result = self.complete_call(call_sid).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// Transfer a connected call to `to`, ending its media stream.
+    pub async fn transfer(&self, call_sid: &str, to: &str) -> Result<(), OutboundError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| OutboundError::Unavailable(e.to_string()))?;
+        let result = self.redirect_call(call_sid, to).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// Press `digits` on a connected call. Twilio plays them with
+    /// `<Play digits>`, which ends the media stream, then reconnects it
+    /// with a `resume` parameter so the call carries on.
+    pub async fn send_digits(&self, call_sid: &str, digits: &str) -> Result<(), OutboundError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| OutboundError::Unavailable(e.to_string()))?;
+        let result = self.play_digits(call_sid, digits).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// Text `to` from the configured number.
+    pub async fn send_sms(&self, to: &str, body: &str) -> Result<(), OutboundError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        let permit = self
+            .breaker
+            .check()
+            .map_err(|e| OutboundError::Unavailable(e.to_string()))?;
+        let result = self.create_message(to, body).await;
+        permit.finish(result.is_ok());
+        result
+    }
+
+    /// Cheap credential check: fetch the account. Bypasses the breaker.
+    pub async fn probe(&self) -> Result<(), OutboundError> {
+        #[cfg(feature = "mocks")]
+        if self.mock.is_some() {
+            return Ok(());
+        }
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}.json",
+            self.account_sid
+        );
+        let resp = self
+            .client
+            .get(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .send()
+            .await
+            .map_err(|e| OutboundError::Request(e.to_string()))?;
+        if !resp.status().is_success() {
+            return Err(OutboundError::Api(resp.status().to_string()));
+        }
+        Ok(())
+    }
+
+    async fn create_call(&self, to: &str) -> Result<String, OutboundError> {
         let url = format!(
             "https://api.twilio.com/2010-04-01/Accounts/{}/Calls.json",
             self.account_sid
@@ -62,6 +196,118 @@ impl TwilioClient {
         tracing::info!(to, call_sid = %call_sid, "Outbound call initiated");
         Ok(call_sid)
     }
+
+    async fn complete_call(&self, call_sid: &str) -> Result<(), OutboundError> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Calls/{call_sid}.json",
+            self.account_sid
+        );
+
+        let resp = self
+            .client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("Status", "completed")])
+            .send()
+            .await
+            .map_err(|e| OutboundError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(OutboundError::Api(format!("{status}: {body}")));
+        }
+
+        tracing::info!(call_sid, "Call hung up");
+        Ok(())
+    }
+
+    async fn redirect_call(&self, call_sid: &str, to: &str) -> Result<(), OutboundError> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Calls/{call_sid}.json",
+            self.account_sid
+        );
+        let twiml = format!("<Response><Dial>{}</Dial></Response>", xml_escape(to));
+
+        let resp = self
+            .client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("Twiml", twiml.as_str())])
+            .send()
+            .await
+            .map_err(|e| OutboundError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(OutboundError::Api(format!("{status}: {body}")));
+        }
+
+        tracing::info!(call_sid, to, "Call transferred");
+        Ok(())
+    }
+
+    async fn play_digits(&self, call_sid: &str, digits: &str) -> Result<(), OutboundError> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Calls/{call_sid}.json",
+            self.account_sid
+        );
+        let twiml = digits_twiml(&self.external_url, digits);
+
+        let resp = self
+            .client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&[("Twiml", twiml.as_str())])
+            .send()
+            .await
+            .map_err(|e| OutboundError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(OutboundError::Api(format!("{status}: {body}")));
+        }
+
+        tracing::info!(call_sid, digits, "DTMF sent");
+        Ok(())
+    }
+
+    async fn create_message(&self, to: &str, body: &str) -> Result<(), OutboundError> {
+        let url = format!(
+            "https://api.twilio.com/2010-04-01/Accounts/{}/Messages.json",
+            self.account_sid
+        );
+        let params = [("To", to), ("From", &self.from_number), ("Body", body)];
+
+        let resp = self
+            .client
+            .post(&url)
+            .basic_auth(&self.account_sid, Some(&self.auth_token))
+            .form(&params)
+            .send()
+            .await
+            .map_err(|e| OutboundError::Request(e.to_string()))?;
+
+        if !resp.status().is_success() {
+            let status = resp.status();
+            let body = resp.text().await.unwrap_or_default();
+            return Err(OutboundError::Api(format!("{status}: {body}")));
+        }
+
+        tracing::info!(to, "SMS sent");
+        Ok(())
+    }
+}
+
+/// TwiML pressing `digits`, then reconnecting the call's media stream.
+fn digits_twiml(external_url: &str, digits: &str) -> String {
+    let stream = stream_element(&media_stream_url(external_url), &[("resume", Some("true"))]);
+    format!(
+        "<Response><Play digits=\"{}\" /><Connect>{stream}</Connect></Response>",
+        xml_escape(digits)
+    )
 }
 
 #[derive(Debug, thiserror::Error)]
@@ -70,4 +316,27 @@ pub enum OutboundError {
     Request(String),
     #[error("Twilio API error: {0}")]
     Api(String),
+    #[error("Twilio unavailable: {0}")]
+    Unavailable(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn accepts_keypad_digits_only() {
+        assert!(is_dtmf("12#w*"));
+        assert!(!is_dtmf("12x"));
+        assert!(!is_dtmf("A"));
+        assert!(!is_dtmf(""));
+    }
+
+    #[test]
+    fn presses_digits_then_resumes_the_stream() {
+        let twiml = digits_twiml("https://echo.test", "2w#");
+        assert!(twiml.starts_with(r#"<Response><Play digits="2w#" /><Connect>"#));
+        assert!(twiml.contains(r#"<Stream url="wss://echo.test/twilio/media">"#));
+        assert!(twiml.contains(r#"<Parameter name="resume" value="true" />"#));
+    }
 }
@@ -1,21 +1,66 @@
+use axum::body::Bytes;
 use axum::extract::State;
 use axum::response::{IntoResponse, Response};
+use serde::Deserialize;
 
-use crate::AppState;
+use crate::agents::Agent;
+use crate::tenants::Tenant;
+use crate::{health, AppState};
+
+/// The subset of Twilio's voice webhook form we route on.
+#[derive(Debug, Default, Deserialize)]
+struct VoiceParams {
+    /// The dialled number (E.164).
+    #[serde(rename = "To", default)]
+    to: Option<String>,
+    /// The caller's number (E.164).
+    #[serde(rename = "From", default)]
+    from: Option<String>,
+}
 
 /// Handle POST /twilio/voice — Twilio webhook for incoming calls.
 ///
 /// Responds with TwiML that connects the call to a WebSocket media stream.
 /// Twilio will then open a WSS connection to /twilio/media where we handle
-/// the actual audio.
-pub async fn handle_voice(State(state): State<AppState>) -> Response {
+/// the actual audio. The tenant and agent owning the dialled number, if any,
+/// and the caller's number are passed to the stream as custom parameters.
+///
+/// While draining, callers hear the `[degraded]` message instead.
+pub async fn handle_voice(State(state): State<AppState>, body: Bytes) -> Response {
+    if state.is_draining() {
+        tracing::info!("Draining, turning call away");
+        return say_and_hang_up(&state.config.degraded.reject_message);
+    }
+    let params: VoiceParams = serde_urlencoded::from_bytes(&body).unwrap_or_else(|e| {
+        tracing::warn!("Failed to parse voice webhook form: {e}");
+        VoiceParams::default()
+    });
+    let to = params.to.as_deref().unwrap_or_default();
+    let tenant = state.tenants.for_number(to);
+    let agent = state.agents.for_number(to);
+    // The agent that will answer: the number's, or the tenant's default
+    let answering = agent.clone().or_else(|| {
+        let name = tenant.as_ref()?.default_agent()?;
+        state.agents.get(name)
+    });
+    if let Some(response) = reject_if_down(&state, tenant.as_deref(), answering.as_deref()) {
+        return response;
+    }
     let ws_url = media_stream_url(&state.config.server.external_url);
+    let stream = stream_element(
+        &ws_url,
+        &[
+            ("tenant", tenant.as_ref().map(|t| t.name.as_str())),
+            ("agent", agent.as_ref().map(|a| a.name.as_str())),
+            ("caller", params.from.as_deref()),
+        ],
+    );
 
     let twiml = format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
 <Response>
     <Connect>
-        <Stream url="{ws_url}" />
+        {stream}
     </Connect>
 </Response>"#
     );
@@ -27,15 +72,21 @@ pub async fn handle_voice(State(state): State<AppState>) -> Response {
 ///
 /// When Twilio calls someone and they pick up, this webhook provides TwiML.
 /// The greeting is handled by the media stream via TTS (better voice quality),
-/// so we just open the stream directly.
-pub async fn handle_voice_outbound(State(state): State<AppState>) -> Response {
+/// so we just open the stream directly. The number called is passed on as
+/// `caller`: either way, it's the other party.
+pub async fn handle_voice_outbound(State(state): State<AppState>, body: Bytes) -> Response {
+    if let Some(response) = reject_if_down(&state, None, None) {
+        return response;
+    }
+    let params: VoiceParams = serde_urlencoded::from_bytes(&body).unwrap_or_default();
     let ws_url = media_stream_url(&state.config.server.external_url);
+    let stream = stream_element(&ws_url, &[("caller", params.to.as_deref())]);
 
     let twiml = format!(
         r#"<?xml version="1.0" encoding="UTF-8"?>
 <Response>
     <Connect>
-        <Stream url="{ws_url}" />
+        {stream}
     </Connect>
 </Response>"#
     );
@@ -43,7 +94,63 @@ pub async fn handle_voice_outbound(State(state): State<AppState>) -> Response {
     ([("Content-Type", "text/xml")], twiml).into_response()
 }
 
-fn media_stream_url(external_url: &str) -> String {
+/// Apologize and hang up instead of connecting a media stream when a
+/// dependency the call needs (`tenant`'s providers, `agent`'s brain) is
+/// known to be down.
+fn reject_if_down(
+    state: &AppState,
+    tenant: Option<&Tenant>,
+    agent: Option<&Agent>,
+) -> Option<Response> {
+    let degraded = &state.config.degraded;
+    if !degraded.reject_calls {
+        return None;
+    }
+    let dead = health::known_dead(state, tenant, agent)?;
+    tracing::warn!(dependency = dead, "Dependency down, rejecting call");
+    Some(say_and_hang_up(&degraded.reject_message))
+}
+
+/// TwiML that speaks `message` and hangs up.
+fn say_and_hang_up(message: &str) -> Response {
+    let message = xml_escape(message);
+    let twiml = format!(
+        r#"<?xml version="1.0" encoding="UTF-8"?>
+<Response>
+    <Say>{message}</Say>
+    <Hangup/>
+</Response>"#
+    );
+    ([("Content-Type", "text/xml")], twiml).into_response()
+}
+
+/// The `<Stream>` element, carrying the set parameters as `<Parameter>`s.
+pub(crate) fn stream_element(ws_url: &str, params: &[(&str, Option<&str>)]) -> String {
+    let params: String = params
+        .iter()
+        .filter_map(|(name, value)| {
+            let value = xml_escape((*value)?);
+            Some(format!(
+                "\n            <Parameter name=\"{name}\" value=\"{value}\" />"
+            ))
+        })
+        .collect();
+    if params.is_empty() {
+        format!(r#"<Stream url="{ws_url}" />"#)
+    } else {
+        format!("<Stream url=\"{ws_url}\">{params}\n        </Stream>")
+    }
+}
+
+pub(crate) fn xml_escape(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+pub(crate) fn media_stream_url(external_url: &str) -> String {
     format!(
         "{}/twilio/media",
         external_url
@@ -51,3 +158,28 @@ fn media_stream_url(external_url: &str) -> String {
             .replace("http://", "ws://")
     )
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_say_text() {
+        assert_eq!(
+            xml_escape(r#"Tom & Jerry's <"show">"#),
+            "Tom &amp; Jerry&apos;s &lt;&quot;show&quot;&gt;"
+        );
+    }
+
+    #[test]
+    fn stream_carries_agent_parameter() {
+        let plain = stream_element("wss://h/twilio/media", &[("agent", None)]);
+        assert_eq!(plain, r#"<Stream url="wss://h/twilio/media" />"#);
+        let agent = stream_element(
+            "wss://h/twilio/media",
+            &[("tenant", None), ("agent", Some("alerts"))],
+        );
+        assert!(agent.contains(r#"<Parameter name="agent" value="alerts" />"#));
+        assert!(!agent.contains("tenant"));
+    }
+}
@@ -0,0 +1,123 @@
+//! Brain warmup on startup (`[warmup]`).
+//!
+//! The first turn of the day otherwise pays for everything cold at once:
+//! TLS handshakes to the API, a fresh bridge-echo connection, the Claude
+//! CLI or a local model paged in from disk. Warmup runs a throwaway prompt
+//! through the brain and each fallback as the server starts, in the
+//! background; `/health/deep` reports how it went.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use serde::Serialize;
+
+use crate::config::WarmupConfig;
+use crate::{AppState, Brain};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WarmupState {
+    /// `[warmup]` isn't enabled.
+    Disabled,
+    Running,
+    /// Every brain answered.
+    Done,
+    /// A brain failed or timed out; its first real turn starts cold.
+    Failed,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct WarmupStatus {
+    pub state: WarmupState,
+    /// How long the slowest brain took, once finished.
+    pub took_ms: Option<u64>,
+    pub error: Option<String>,
+}
+
+/// Shared warmup status. Cheap to clone.
+#[derive(Clone)]
+pub struct Warmup {
+    status: Arc<Mutex<WarmupStatus>>,
+}
+
+impl Warmup {
+    /// Running from the start when enabled, so nobody sees it idle before
+    /// [`run`] gets going.
+    pub fn new(config: &WarmupConfig) -> Self {
+        let state = match config.enabled {
+            true => WarmupState::Running,
+            false => WarmupState::Disabled,
+        };
+        Self {
+            status: Arc::new(Mutex::new(WarmupStatus {
+                state,
+                took_ms: None,
+                error: None,
+            })),
+        }
+    }
+
+    pub fn status(&self) -> WarmupStatus {
+        self.status.lock().unwrap().clone()
+    }
+
+    fn set(&self, status: WarmupStatus) {
+        *self.status.lock().unwrap() = status;
+    }
+}
+
+/// Warm up the brain and its fallbacks, if `[warmup]` is enabled.
+pub async fn run(state: AppState) {
+    let config = &state.config.warmup;
+    if !config.enabled {
+        return;
+    }
+
+    let timeout = Duration::from_secs(config.timeout_secs);
+    let brains = std::iter::once(&state.brain).chain(
+        state
+            .fallback
+            .as_ref()
+            .map_or(&[][..], |fallback| fallback.brains()),
+    );
+    let warmups = brains
+        .enumerate()
+        .map(|(i, brain)| warm(&state, brain, format!("warmup:{i}"), timeout));
+    let results = futures_util::future::join_all(warmups).await;
+
+    let took = results.iter().map(|(took, _)| *took).max();
+    let error = results.into_iter().find_map(|(_, result)| result.err());
+    let status = WarmupStatus {
+        state: match error {
+            Some(_) => WarmupState::Failed,
+            None => WarmupState::Done,
+        },
+        took_ms: took.map(|t| t.as_millis() as u64),
+        error,
+    };
+    match status.error {
+        Some(ref e) => tracing::warn!("Brain warmup failed: {e}"),
+        None => tracing::info!(took_ms = status.took_ms, "Brain warmed up"),
+    }
+    state.warmup.set(status);
+}
+
+async fn warm(
+    state: &AppState,
+    brain: &Brain,
+    key: String,
+    timeout: Duration,
+) -> (Duration, Result<(), String>) {
+    let started = Instant::now();
+    let answer = tokio::time::timeout(
+        timeout,
+        state.ask_once(brain, &key, &state.config.warmup.prompt),
+    )
+    .await;
+    let result = match answer {
+        Ok(Ok(_)) => Ok(()),
+        Ok(Err(e)) => Err(e.to_string()),
+        Err(_) => Err(format!("no answer within {}s", timeout.as_secs())),
+    };
+    (started.elapsed(), result)
+}
@@ -0,0 +1,177 @@
+//! Webhook subscriptions fed from the event bus (`[[webhooks]]`).
+//!
+//! Each hook gets its own subscription and delivers in order: one POST per
+//! matching event, retried with exponential backoff on network errors, 429
+//! and 5xx. A hook that falls far enough behind skips what it missed.
+//! Every delivery is logged with its outcome and attempt count. A
+//! reminder's own `webhook_url` gets its runs the same way.
+
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::broadcast::error::RecvError;
+
+use crate::config::WebhookConfig;
+use crate::events::{CallEvent, EventBus};
+
+/// What a hook receives.
+#[derive(Debug, Serialize)]
+struct Delivery<'a> {
+    /// e.g. `call.started`.
+    event: &'static str,
+    timestamp: chrono::DateTime<Utc>,
+    data: &'a CallEvent,
+}
+
+/// Deliver events from `bus` to `hook` until the task is aborted.
+pub async fn run(http: reqwest::Client, bus: EventBus, hook: WebhookConfig) {
+    for name in &hook.events {
+        if !CallEvent::NAMES.contains(&name.as_str()) {
+            tracing::warn!(url = %hook.url, event = %name, "Webhook subscribes to an unknown event");
+        }
+    }
+    let mut events = bus.subscribe();
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => event,
+            Err(RecvError::Lagged(missed)) => {
+                tracing::warn!(url = %hook.url, missed, "Webhook fell behind, skipping events");
+                continue;
+            }
+            Err(RecvError::Closed) => return,
+        };
+        if hook.wants(event.name()) {
+            deliver(&http, &hook, &event).await;
+        }
+    }
+}
+
+/// POST one event, retrying as configured. Returns whether it was accepted.
+pub(crate) async fn deliver(
+    http: &reqwest::Client,
+    hook: &WebhookConfig,
+    event: &CallEvent,
+) -> bool {
+    let body = Delivery {
+        event: event.name(),
+        timestamp: Utc::now(),
+        data: event,
+    };
+    let started = Instant::now();
+    let mut backoff = Duration::from_millis(hook.retry_backoff_ms);
+    let mut attempt = 0;
+    loop {
+        attempt += 1;
+        let mut request = http
+            .post(&hook.url)
+            .timeout(Duration::from_millis(hook.timeout_ms))
+            .json(&body);
+        for (name, value) in &hook.headers {
+            request = request.header(name, value);
+        }
+        let (retryable, error) = match request.send().await {
+            Ok(resp) if resp.status().is_success() => {
+                tracing::info!(
+                    url = %hook.url,
+                    event = body.event,
+                    call_sid = event.call_sid(),
+                    status = resp.status().as_u16(),
+                    attempt,
+                    elapsed_ms = started.elapsed().as_millis() as u64,
+                    "Webhook delivered"
+                );
+                return true;
+            }
+            Ok(resp) => {
+                let status = resp.status();
+                (
+                    status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS,
+                    format!("HTTP {status}"),
+                )
+            }
+            Err(e) => (true, e.to_string()),
+        };
+        if !retryable || attempt > hook.retries {
+            tracing::warn!(
+                url = %hook.url,
+                event = body.event,
+                call_sid = event.call_sid(),
+                attempt,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "Webhook delivery failed: {error}"
+            );
+            return false;
+        }
+        tracing::debug!(url = %hook.url, event = body.event, attempt, "Webhook delivery failed, retrying: {error}");
+        tokio::time::sleep(backoff).await;
+        backoff *= 2;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+
+    use axum::http::{HeaderMap, StatusCode};
+    use axum::routing::post;
+    use axum::{Json, Router};
+
+    fn hook(url: String, events: &[&str]) -> WebhookConfig {
+        let mut hook: WebhookConfig = toml::from_str(&format!(
+            "url = {url:?}\nretry_backoff_ms = 1\nheaders = {{ Authorization = \"Bearer s3cret\" }}"
+        ))
+        .unwrap();
+        hook.events = events.iter().map(|e| e.to_string()).collect();
+        hook
+    }
+
+    #[test]
+    fn filters_by_event_name() {
+        let all = hook("http://x".to_string(), &[]);
+        assert!(all.wants("call.started"));
+        let some = hook("http://x".to_string(), &["call.ended", "pipeline.error"]);
+        assert!(some.wants("pipeline.error"));
+        assert!(!some.wants("utterance.transcribed"));
+    }
+
+    #[tokio::test]
+    async fn retries_server_errors_with_the_hook_headers() {
+        let hits = Arc::new(AtomicUsize::new(0));
+        let seen = Arc::clone(&hits);
+        let app = Router::new().route(
+            "/hook",
+            post(
+                move |headers: HeaderMap, Json(body): Json<serde_json::Value>| async move {
+                    assert_eq!(headers["authorization"], "Bearer s3cret");
+                    assert_eq!(body["event"], "call.ended");
+                    assert_eq!(body["data"]["reason"], "hangup");
+                    // Fail the first delivery
+                    if seen.fetch_add(1, Ordering::Relaxed) == 0 {
+                        StatusCode::BAD_GATEWAY
+                    } else {
+                        StatusCode::NO_CONTENT
+                    }
+                },
+            ),
+        );
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/hook", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+
+        let event = CallEvent::CallEnded {
+            call_sid: "CA1".to_string(),
+            reason: crate::events::EndReason::Hangup,
+        };
+        assert!(deliver(&reqwest::Client::new(), &hook(url.clone(), &[]), &event).await);
+        assert_eq!(hits.load(Ordering::Relaxed), 2);
+
+        let mut once = hook(url, &[]);
+        once.retries = 0;
+        hits.store(0, Ordering::Relaxed);
+        assert!(!deliver(&reqwest::Client::new(), &once, &event).await);
+        assert_eq!(hits.load(Ordering::Relaxed), 1);
+    }
+}
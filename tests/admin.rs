@@ -0,0 +1,264 @@
+//! Tests for the `/admin` dashboard and the endpoints behind it.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use serde_json::{json, Value};
+
+use common::{
+    serve, summary, twilio_mark, twilio_speak, twilio_start, FakeTwilio, API_TOKEN, AWAIT_TIMEOUT,
+};
+
+fn get(addr: SocketAddr, path: &str) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .get(format!("http://{addr}{path}"))
+        .basic_auth("admin", Some(API_TOKEN))
+}
+
+fn post(addr: SocketAddr, path: &str, body: Value) -> reqwest::RequestBuilder {
+    reqwest::Client::new()
+        .post(format!("http://{addr}{path}"))
+        .basic_auth("admin", Some(API_TOKEN))
+        .json(&body)
+}
+
+async fn calls(addr: SocketAddr) -> Value {
+    get(addr, "/admin/calls")
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+/// A reader of `/admin/events`.
+struct Feed {
+    response: reqwest::Response,
+    buffer: String,
+}
+
+impl Feed {
+    async fn open(addr: SocketAddr) -> Self {
+        let response = get(addr, "/admin/events").send().await.unwrap();
+        assert_eq!(response.status(), 200);
+        Self {
+            response,
+            buffer: String::new(),
+        }
+    }
+
+    /// The next event's JSON, skipping keep-alives.
+    async fn next(&mut self) -> Value {
+        tokio::time::timeout(AWAIT_TIMEOUT, async {
+            loop {
+                if let Some(end) = self.buffer.find("\n\n") {
+                    let message: String = self.buffer.drain(..end + 2).collect();
+                    if let Some(data) = message.lines().find_map(|l| l.strip_prefix("data: ")) {
+                        return serde_json::from_str(data).unwrap();
+                    }
+                    continue;
+                }
+                let chunk = self.response.chunk().await.unwrap().expect("feed closed");
+                self.buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+            }
+        })
+        .await
+        .expect("no event")
+    }
+}
+
+#[tokio::test]
+async fn page_asks_for_the_token() {
+    let addr = serve("").await;
+    let url = format!("http://{addr}/admin");
+    let client = reqwest::Client::new();
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), 401);
+    let challenge = response.headers()["www-authenticate"].to_str().unwrap();
+    assert!(challenge.starts_with("Basic "), "{challenge}");
+
+    let response = client
+        .get(&url)
+        .basic_auth("admin", Some("wrong"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = get(addr, "/admin").send().await.unwrap();
+    assert_eq!(response.status(), 200);
+    assert!(response.headers()["content-type"]
+        .to_str()
+        .unwrap()
+        .starts_with("text/html"));
+    assert!(response.text().await.unwrap().contains("/admin/events"));
+
+    for path in ["/admin/calls", "/admin/events"] {
+        let status = client
+            .get(format!("http://{addr}{path}"))
+            .send()
+            .await
+            .unwrap()
+            .status();
+        assert_eq!(status, 401, "{path}");
+    }
+}
+
+#[tokio::test]
+async fn shows_a_live_call_and_hangs_it_up() {
+    let addr = serve("").await;
+    let mut feed = Feed::open(addr).await;
+    let mut twilio = FakeTwilio::connect(addr).await;
+    twilio.send_event(twilio_start()).await;
+    assert_eq!(
+        feed.next().await,
+        json!({ "type": "call_started", "call_sid": "CAtest", "transport": "twilio" })
+    );
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+
+    let listed = calls(addr).await;
+    assert_eq!(listed["draining"], false);
+    assert_eq!(listed["calls"][0]["call_sid"], "CAtest");
+    assert_eq!(listed["calls"][0]["transport"], "twilio");
+
+    twilio_speak(&mut twilio).await;
+    assert_eq!(
+        feed.next().await,
+        json!({ "type": "utterance_started", "call_sid": "CAtest" })
+    );
+    assert_eq!(
+        feed.next().await,
+        json!({ "type": "transcript", "call_sid": "CAtest", "text": "Hello" })
+    );
+    assert_eq!(
+        feed.next().await,
+        json!({ "type": "response", "call_sid": "CAtest", "text": "Sure." })
+    );
+
+    // Speaking into the call from the page
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+    twilio.received.clear();
+    let response = post(
+        addr,
+        "/admin/calls/CAtest/inject",
+        json!({ "text": "Hello" }),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 200);
+    twilio.wait_for("mark").await;
+    assert_eq!(summary(&twilio.received), ["audio 400", "mark inject_end"]);
+    // The API itself doesn't take the page's Basic auth
+    let response = post(
+        addr,
+        "/api/inject",
+        json!({ "call_sid": "CAtest", "text": "Hello" }),
+    )
+    .send()
+    .await
+    .unwrap();
+    assert_eq!(response.status(), 401);
+
+    let response = post(addr, "/admin/calls/CAtest/hangup", json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 200);
+    assert_eq!(
+        feed.next().await,
+        json!({ "type": "call_ended", "call_sid": "CAtest", "reason": "operator" })
+    );
+    assert_eq!(calls(addr).await["calls"], json!([]));
+
+    let response = post(addr, "/admin/calls/CAtest/hangup", json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 404);
+}
+
+#[tokio::test]
+async fn draining_turns_new_calls_away() {
+    let addr = serve("").await;
+    let client = reqwest::Client::new();
+    let voice = || async {
+        client
+            .post(format!("http://{addr}/twilio/voice"))
+            .send()
+            .await
+            .unwrap()
+            .text()
+            .await
+            .unwrap()
+    };
+
+    let response = post(addr, "/admin/drain", json!({ "draining": true }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.json::<Value>().await.unwrap()["draining"], true);
+    assert_eq!(calls(addr).await["draining"], true);
+
+    let twiml = voice().await;
+    assert!(
+        twiml.contains("<Hangup/>") && !twiml.contains("<Stream"),
+        "{twiml}"
+    );
+    let response = client
+        .post(format!("http://{addr}/api/call"))
+        .bearer_auth(API_TOKEN)
+        .json(&json!({ "to": "+15551234567" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), 503);
+    let health = client
+        .get(format!("http://{addr}/health/deep"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(health.status(), 503);
+    assert_eq!(health.json::<Value>().await.unwrap()["draining"], true);
+
+    post(addr, "/admin/drain", json!({ "draining": false }))
+        .send()
+        .await
+        .unwrap();
+    assert!(voice().await.contains("<Stream"));
+}
+
+#[tokio::test]
+async fn deep_health_reports_the_warmup() {
+    let addr = serve("\n[warmup]\nenabled = true\n").await;
+    let warmup = || async {
+        let health = reqwest::get(format!("http://{addr}/health/deep"))
+            .await
+            .unwrap()
+            .json::<Value>()
+            .await
+            .unwrap();
+        health["warmup"].clone()
+    };
+    let done = tokio::time::timeout(AWAIT_TIMEOUT, async {
+        loop {
+            let warmup = warmup().await;
+            if warmup["state"] != "running" {
+                return warmup;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+        }
+    })
+    .await
+    .unwrap();
+    assert_eq!(done["state"], "done");
+    assert!(done["took_ms"].is_u64());
+    assert_eq!(done["error"], Value::Null);
+}
@@ -0,0 +1,54 @@
+//! Calls handled by a named agent with its own brain (`[[agents]]`).
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use common::{serve, twilio_mark, twilio_start, FakeTwilio, AWAIT_TIMEOUT};
+
+/// A bridge-echo that only records the calls it's told have ended.
+async fn bridge() -> (String, mpsc::UnboundedReceiver<Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/call-ended",
+        post(move |Json(body): Json<Value>| async move {
+            let _ = tx.send(body);
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}"), rx)
+}
+
+#[tokio::test]
+async fn ending_a_call_drops_it_from_the_agents_brain() {
+    let (url, mut ended) = bridge().await;
+    let addr = serve(&format!(
+        "\n[[agents]]\nname = \"sales\"\nbridge_url = \"{url}\"\n"
+    ))
+    .await;
+
+    let mut twilio = FakeTwilio::connect(addr).await;
+    let mut start = twilio_start();
+    start["start"]["customParameters"] = json!({ "agent": "sales" });
+    twilio.send_event(start).await;
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+    twilio
+        .send_event(
+            json!({ "event": "stop", "streamSid": "MZtest", "stop": { "callSid": "CAtest" } }),
+        )
+        .await;
+
+    let body = tokio::time::timeout(AWAIT_TIMEOUT, ended.recv())
+        .await
+        .expect("agent's bridge-echo not told the call ended")
+        .unwrap();
+    assert_eq!(body["call_sid"], "CAtest");
+}
@@ -0,0 +1,113 @@
+//! `[chaos]` fault injection against mock providers, driven through
+//! `POST /debug/pipeline`: each fault should trip the mechanism built for it.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::json;
+
+use common::{debug_pipeline, serve, tone, API_TOKEN};
+use voice_echo::pipeline::audio;
+
+/// `[debug] pipeline` on and `[chaos]` enabled, followed by `faults`.
+fn chaos(faults: &str) -> String {
+    format!("\n[debug]\npipeline = true\n\n[chaos]\nenabled = true\n{faults}")
+}
+
+/// Half a second of tone as an 8kHz WAV.
+fn utterance() -> Vec<u8> {
+    audio::pcm_to_wav(&audio::decode_mulaw(&tone(500)))
+}
+
+#[tokio::test]
+async fn injected_errors_fail_the_stage() {
+    let addr = serve(&chaos("[chaos.stt]\nerror_rate = 1.0\n")).await;
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 502, "{body}");
+    assert_eq!(body["error"]["stage"], "stt");
+    assert!(body["error"]["message"]
+        .as_str()
+        .unwrap()
+        .contains("injected stt fault"));
+}
+
+#[tokio::test]
+async fn repeated_errors_open_the_breaker() {
+    let addr = serve(&chaos(
+        "[chaos.stt]\nerror_rate = 1.0\n\n[breaker]\nmin_requests = 2\n",
+    ))
+    .await;
+    for _ in 0..2 {
+        let (_, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+        assert!(body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("injected"));
+    }
+    // Now failing fast without reaching the provider
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 502);
+    assert!(
+        body["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("STT unavailable"),
+        "{body}"
+    );
+}
+
+#[tokio::test]
+async fn latency_trips_the_stage_budget() {
+    let addr = serve(&chaos(
+        "[chaos.brain]\nlatency_ms = 300\n\n[timeouts]\nbrain_ms = 100\n",
+    ))
+    .await;
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 502);
+    assert_eq!(body["error"]["stage"], "brain");
+    assert_eq!(
+        body["error"]["message"],
+        "brain stage timed out after 100ms"
+    );
+}
+
+#[tokio::test]
+async fn failing_brain_fails_over_unfaulted() {
+    // A fallback bridge-echo that always answers
+    let bridge = Router::new().route(
+        "/chat",
+        post(|| async { Json(json!({ "response": "Backup." })) }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let bridge_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move { axum::serve(listener, bridge).await.unwrap() });
+
+    let addr = serve(&chaos(&format!(
+        "[chaos.brain]\nerror_rate = 1.0\n\n[llm.fallback]\nbridge_url = {bridge_url:?}\nnotice = \"\"\n"
+    )))
+    .await;
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["response"], "Backup.");
+}
+
+#[tokio::test]
+async fn slow_drip_stretches_the_stage() {
+    // "Sure." is 400 bytes of audio: 200ms at 2000 bytes/s
+    let addr = serve(&chaos("[chaos.tts]\ndrip_bytes_per_sec = 2000\n")).await;
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 200, "{body}");
+    let tts_ms = body["timings_ms"]["tts"].as_u64().unwrap();
+    assert!(tts_ms >= 200, "{body}");
+}
+
+#[tokio::test]
+async fn nothing_is_injected_unless_enabled() {
+    let addr = serve("\n[debug]\npipeline = true\n\n[chaos.stt]\nerror_rate = 1.0\n").await;
+    let (status, body) = debug_pipeline(addr, Some(API_TOKEN), utterance()).await;
+    assert_eq!(status, 200, "{body}");
+    assert!(body.get("error").is_none());
+}
@@ -0,0 +1,284 @@
+//! Shared harness for the integration tests that run the server: config
+//! with mock providers, a server on a free port, and a fake Twilio client.
+//!
+//! Each test binary uses a different part of it.
+
+#![allow(dead_code)]
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use voice_echo::config::Config;
+use voice_echo::pipeline::audio;
+use voice_echo::VoiceEcho;
+
+pub const STREAM_SID: &str = "MZtest";
+pub const FRAME_BYTES: usize = 160;
+/// Bearer token for `/api/*` under `CONFIG`.
+pub const API_TOKEN: &str = "test-token";
+pub const AWAIT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Mock providers, no preflight, `API_TOKEN`, and a short silence gap.
+/// Greeting and reply are 160 and 400 bytes of audio at 10ms per character.
+pub const CONFIG: &str = r#"
+[server]
+host = "127.0.0.1"
+port = 0
+external_url = "https://echo.test"
+
+[twilio]
+account_sid = "ACtest"
+auth_token = "test"
+phone_number = "+15550000000"
+
+[groq]
+api_key = "test"
+
+[inworld]
+api_key = "test"
+
+[llm]
+greeting = "Hi"
+
+[vad]
+silence_threshold_ms = 200
+
+[preflight]
+enabled = false
+
+[api]
+token = "test-token"
+
+[mocks]
+stt = true
+tts = true
+brain = true
+responses = ["Sure."]
+ms_per_char = 10
+"#;
+
+/// Start a server with `CONFIG` plus `extra`, which continues the `[mocks]`
+/// table and may open others.
+pub async fn serve(extra: &str) -> SocketAddr {
+    let config: Config = toml::from_str(&format!("{CONFIG}{extra}")).unwrap();
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut voice = VoiceEcho::new(config);
+        voice.serve(listener).await.unwrap();
+    });
+    addr
+}
+
+/// POST `body` to `/debug/pipeline`, returning the status and JSON body.
+pub async fn debug_pipeline(addr: SocketAddr, token: Option<&str>, body: Vec<u8>) -> (u16, Value) {
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{addr}/debug/pipeline"))
+        .header("content-type", "audio/wav")
+        .body(body);
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap_or(Value::Null))
+}
+
+/// What the server sent back, with consecutive media frames merged.
+#[derive(Debug, PartialEq)]
+pub enum Received {
+    Audio(Vec<u8>),
+    Mark(String),
+    Clear,
+}
+
+impl Received {
+    fn event(&self) -> &str {
+        match self {
+            Received::Audio(_) => "media",
+            Received::Mark(_) => "mark",
+            Received::Clear => "clear",
+        }
+    }
+}
+
+pub struct FakeTwilio {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    pub received: Vec<Received>,
+}
+
+impl FakeTwilio {
+    pub async fn connect(addr: SocketAddr) -> Self {
+        let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/twilio/media"))
+            .await
+            .unwrap();
+        Self {
+            ws,
+            received: Vec::new(),
+        }
+    }
+
+    /// Replay `tests/fixtures/twilio/<name>.jsonl`, then hang up. Returns
+    /// everything the server sent.
+    pub async fn replay(mut self, name: &str) -> Vec<Received> {
+        let path = format!(
+            "{}/tests/fixtures/twilio/{name}.jsonl",
+            env!("CARGO_MANIFEST_DIR")
+        );
+        let fixture = std::fs::read_to_string(&path).unwrap();
+        for line in fixture.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let step: Value = serde_json::from_str(line).unwrap();
+            if let Some(event) = step["await"].as_str() {
+                self.wait_for(event).await;
+            } else if let Some(ms) = step["sleep_ms"].as_u64() {
+                self.drain_for(Duration::from_millis(ms)).await;
+            } else {
+                self.send_event(step).await;
+            }
+        }
+        // Whatever the server sends before it closes the stream
+        while let Ok(Some(message)) = tokio::time::timeout(AWAIT_TIMEOUT, self.ws.next()).await {
+            match message {
+                Ok(message) => self.record(message),
+                Err(_) => break,
+            }
+        }
+        self.received
+    }
+
+    pub async fn send_event(&mut self, event: Value) {
+        let media = &event["media"];
+        let generated = match (media["tone_ms"].as_u64(), media["silence_ms"].as_u64()) {
+            (Some(ms), _) => Some(tone(ms)),
+            (_, Some(ms)) => Some(vec![audio::pcm_to_mulaw(0); ms as usize * 8]),
+            _ => None,
+        };
+        let Some(mulaw) = generated else {
+            return self.send(event.to_string()).await;
+        };
+        for chunk in mulaw.chunks(FRAME_BYTES) {
+            let payload = base64::engine::general_purpose::STANDARD.encode(chunk);
+            let mut frame = event.clone();
+            frame["media"] = serde_json::json!({ "track": "inbound", "payload": payload });
+            self.send(frame.to_string()).await;
+        }
+    }
+
+    async fn send(&mut self, text: String) {
+        self.ws.send(Message::Text(text.into())).await.unwrap();
+    }
+
+    /// Read until the server sends `event`.
+    pub async fn wait_for(&mut self, event: &str) {
+        let seen = self.count(event);
+        tokio::time::timeout(AWAIT_TIMEOUT, async {
+            while self.count(event) == seen {
+                let message = self.ws.next().await.unwrap().unwrap();
+                self.record(message);
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("no {event} within {AWAIT_TIMEOUT:?}"));
+    }
+
+    /// Keep reading for `duration`.
+    pub async fn drain_for(&mut self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, async {
+            while let Some(Ok(message)) = self.ws.next().await {
+                self.record(message);
+            }
+        })
+        .await;
+    }
+
+    fn count(&self, event: &str) -> usize {
+        self.received.iter().filter(|r| r.event() == event).count()
+    }
+
+    fn record(&mut self, message: Message) {
+        let Message::Text(text) = message else {
+            return;
+        };
+        let json: Value = serde_json::from_str(text.as_str()).unwrap();
+        assert_eq!(json["streamSid"], STREAM_SID, "{json}");
+        match json["event"].as_str().unwrap() {
+            "media" => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(json["media"]["payload"].as_str().unwrap())
+                    .unwrap();
+                assert!(bytes.len() <= FRAME_BYTES, "oversized frame: {json}");
+                match self.received.last_mut() {
+                    Some(Received::Audio(audio)) => audio.extend(bytes),
+                    _ => self.received.push(Received::Audio(bytes)),
+                }
+            }
+            "mark" => self.received.push(Received::Mark(
+                json["mark"]["name"].as_str().unwrap().to_string(),
+            )),
+            "clear" => self.received.push(Received::Clear),
+            other => panic!("unexpected event {other}: {json}"),
+        }
+    }
+}
+
+/// A Twilio `start` event for call `CAtest`.
+pub fn twilio_start() -> Value {
+    json!({
+        "event": "start",
+        "streamSid": "MZtest",
+        "start": {
+            "streamSid": "MZtest",
+            "accountSid": "ACtest",
+            "callSid": "CAtest",
+            "tracks": ["inbound"],
+            "customParameters": {},
+            "mediaFormat": { "encoding": "audio/x-mulaw", "sampleRate": 8000, "channels": 1 }
+        }
+    })
+}
+
+/// Twilio reporting that mark `name` finished playing.
+pub fn twilio_mark(name: &str) -> Value {
+    json!({ "event": "mark", "streamSid": "MZtest", "mark": { "name": name } })
+}
+
+/// An utterance: 600ms of tone, then enough silence to end it.
+pub async fn twilio_speak(twilio: &mut FakeTwilio) {
+    for media in [json!({ "tone_ms": 600 }), json!({ "silence_ms": 300 })] {
+        twilio
+            .send_event(json!({ "event": "media", "streamSid": "MZtest", "media": media }))
+            .await;
+    }
+}
+
+/// `ms` of a 440Hz tone, loud enough to count as speech.
+pub fn tone(ms: u64) -> Vec<u8> {
+    (0..ms as usize * 8)
+        .map(|i| {
+            let t = i as f64 / 8000.0;
+            audio::pcm_to_mulaw(((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 8000.0) as i16)
+        })
+        .collect()
+}
+
+/// `Audio` lengths and the other events, for compact assertions.
+pub fn summary(received: &[Received]) -> Vec<String> {
+    received
+        .iter()
+        .map(|r| match r {
+            Received::Audio(audio) => format!("audio {}", audio.len()),
+            Received::Mark(name) => format!("mark {name}"),
+            Received::Clear => "clear".to_string(),
+        })
+        .collect()
+}
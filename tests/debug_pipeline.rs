@@ -0,0 +1,109 @@
+//! Tests for `POST /debug/pipeline` with mock providers.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::io::Cursor;
+
+use base64::Engine;
+
+use common::{debug_pipeline as run, serve, API_TOKEN};
+
+const ENABLED: &str = "\n[debug]\npipeline = true\n";
+
+/// One second of a 440Hz tone as a 16kHz stereo WAV, to exercise the
+/// conversion to 8kHz mono.
+fn wav() -> Vec<u8> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: 16000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut buffer = Cursor::new(Vec::new());
+    let mut writer = hound::WavWriter::new(&mut buffer, spec).unwrap();
+    for i in 0..16000 {
+        let t = i as f64 / 16000.0;
+        let sample = ((2.0 * std::f64::consts::PI * 440.0 * t).sin() * 8000.0) as i16;
+        writer.write_sample(sample).unwrap();
+        writer.write_sample(sample).unwrap();
+    }
+    writer.finalize().unwrap();
+    buffer.into_inner()
+}
+
+#[tokio::test]
+async fn reports_every_stage_and_the_reply() {
+    let addr = serve(ENABLED).await;
+    let (status, body) = run(addr, Some(API_TOKEN), wav()).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["transcript"], "Hello");
+    assert_eq!(body["response"], "Sure.");
+    assert!(body.get("error").is_none(), "{body}");
+    for stage in ["queue", "stt", "brain", "tts", "total"] {
+        assert!(body["timings_ms"][stage].is_u64(), "{stage}: {body}");
+    }
+
+    // "Sure." is 400 samples of mock audio
+    let wav = base64::engine::general_purpose::STANDARD
+        .decode(body["audio_wav"].as_str().unwrap())
+        .unwrap();
+    let reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+    assert_eq!(reader.spec().sample_rate, 8000);
+    assert_eq!(reader.len(), 400);
+}
+
+#[tokio::test]
+async fn asks_about_doubtful_transcripts() {
+    let addr = serve(&format!(
+        "confidence = 0.4\n{ENABLED}\n[stt]\nmin_confidence = 0.6\n\
+         confirm_prompt = \"Did you say {{transcript}}?\"\n"
+    ))
+    .await;
+    let (status, body) = run(addr, Some(API_TOKEN), wav()).await;
+    assert_eq!(status, 200, "{body}");
+    assert_eq!(body["transcript"], "Hello");
+    assert!((body["confidence"].as_f64().unwrap() - 0.4).abs() < 1e-6);
+    // The brain is never asked
+    assert!(body["response"].is_null(), "{body}");
+    assert!(body["timings_ms"]["brain"].is_null(), "{body}");
+
+    // "Did you say Hello?" is 18 characters of mock audio
+    let wav = base64::engine::general_purpose::STANDARD
+        .decode(body["audio_wav"].as_str().unwrap())
+        .unwrap();
+    let reader = hound::WavReader::new(Cursor::new(wav)).unwrap();
+    assert_eq!(reader.len(), 18 * 80);
+}
+
+#[tokio::test]
+async fn names_the_stage_that_failed() {
+    let addr = serve(&format!(
+        "latency_ms = 100\n{ENABLED}\n[timeouts]\nbrain_ms = 50\n"
+    ))
+    .await;
+    let (status, body) = run(addr, Some(API_TOKEN), wav()).await;
+    assert_eq!(status, 502, "{body}");
+    assert_eq!(body["error"]["stage"], "brain");
+    assert_eq!(body["transcript"], "Hello");
+    assert!(body["timings_ms"]["stt"].is_u64(), "{body}");
+    assert!(body["timings_ms"]["brain"].is_null(), "{body}");
+    assert!(body["response"].is_null() && body["audio_wav"].is_null());
+}
+
+#[tokio::test]
+async fn rejects_bad_requests() {
+    let addr = serve(ENABLED).await;
+    assert_eq!(run(addr, None, wav()).await.0, 401);
+    assert_eq!(run(addr, Some("wrong"), wav()).await.0, 401);
+    let (status, body) = run(addr, Some(API_TOKEN), b"not a wav".to_vec()).await;
+    assert_eq!(status, 400);
+    assert!(body["error"].as_str().unwrap().starts_with("Invalid WAV"));
+}
+
+#[tokio::test]
+async fn is_off_by_default() {
+    let addr = serve("").await;
+    assert_eq!(run(addr, Some(API_TOKEN), wav()).await.0, 404);
+}
@@ -0,0 +1,75 @@
+//! End-to-end tests for `POST /api/dtmf`.
+//!
+//! Twilio presses the keys with `<Play digits>`, which ends the call's media
+//! stream and opens a new one carrying a `resume` parameter. The call has
+//! to survive the switch: no second greeting, and the conversation goes on
+//! over the new stream.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use common::{
+    serve, summary, twilio_mark, twilio_speak, twilio_start, FakeTwilio, API_TOKEN, AWAIT_TIMEOUT,
+};
+
+/// Long enough for a greeting that shouldn't come to show up.
+const QUIET: Duration = Duration::from_millis(600);
+
+/// POST `/api/dtmf`, returning the status and JSON body.
+async fn dtmf(addr: SocketAddr, call_sid: &str, digits: &str) -> (u16, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("http://{addr}/api/dtmf"))
+        .bearer_auth(API_TOKEN)
+        .json(&json!({ "call_sid": call_sid, "digits": digits }))
+        .send()
+        .await
+        .unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn call_carries_on_over_the_resumed_stream() {
+    let addr = serve("twilio = true\n").await;
+    let mut twilio = FakeTwilio::connect(addr).await;
+    twilio.send_event(twilio_start()).await;
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+
+    let (status, body) = dtmf(addr, "CAtest", "2w#").await;
+    assert_eq!((status, body), (200, json!({ "status": "sent" })));
+
+    // Twilio stops the stream to play the digits, then opens another
+    twilio
+        .send_event(json!({ "event": "stop", "streamSid": "MZtest" }))
+        .await;
+    twilio.drain_for(AWAIT_TIMEOUT).await;
+    let mut resumed = FakeTwilio::connect(addr).await;
+    let mut start = twilio_start();
+    start["start"]["customParameters"] = json!({ "resume": "true" });
+    resumed.send_event(start).await;
+    resumed.drain_for(QUIET).await;
+    assert!(resumed.received.is_empty(), "greeted again");
+
+    twilio_speak(&mut resumed).await;
+    resumed.wait_for("mark").await;
+    assert_eq!(
+        summary(&resumed.received),
+        ["audio 400", "mark response_end"]
+    );
+}
+
+#[tokio::test]
+async fn rejects_digits_twilio_cannot_play() {
+    let addr = serve("").await;
+    let (status, _) = dtmf(addr, "CAtest", "2A").await;
+    assert_eq!(status, 400);
+    let (status, _) = dtmf(addr, "CAmissing", "2").await;
+    assert_eq!(status, 404);
+}
@@ -0,0 +1,154 @@
+#!/usr/bin/env python3
+"""Generate the golden-audio fixtures for tests/vad_golden.rs.
+
+Synthetic but speech-like: voiced syllables (a glottal pulse train shaped
+by two formants) with short consonant bursts, words separated by brief
+pauses and sentences by long ones. Seeded, so re-running reproduces the
+committed files byte for byte.
+
+Output: *.ulaw (8000 Hz, mono, raw G.711 mu-law, no header)
+
+  clean_speech.ulaw    two sentences in near silence
+  noisy_speech.ulaw    the same sentences over mains hum and hiss
+  music.ulaw           four seconds of chords and melody, then silence
+  silence_clicks.ulaw  near silence with a few line clicks
+"""
+
+import math
+import os
+import random
+
+SAMPLE_RATE = 8000
+MULAW_BIAS = 0x84
+MULAW_CLIP = 32635
+
+# Vowel formants (F1, F2) in Hz
+VOWELS = [(730, 1090), (530, 1840), (270, 2290), (570, 840), (440, 1020)]
+
+
+def pcm_to_mulaw(sample):
+    """G.711 mu-law, matching audio::pcm_to_mulaw."""
+    sign = 0
+    if sample < 0:
+        sign = 0x80
+        sample = -sample
+    sample = min(sample, MULAW_CLIP) + MULAW_BIAS
+    exponent = max(0, min(7, (sample >> 7).bit_length() - 1))
+    mantissa = (sample >> (exponent + 3)) & 0x0F
+    return ~(sign | (exponent << 4) | mantissa) & 0xFF
+
+
+def silence(seconds):
+    return [0.0] * int(SAMPLE_RATE * seconds)
+
+
+def syllable(rng, seconds, amplitude):
+    """One voiced syllable with a raised-cosine envelope."""
+    n = int(SAMPLE_RATE * seconds)
+    f0 = rng.uniform(105, 140)
+    f1, f2 = rng.choice(VOWELS)
+    out = []
+    for i in range(n):
+        t = i / SAMPLE_RATE
+        env = 0.5 - 0.5 * math.cos(2 * math.pi * i / n)
+        pitch = f0 * (1 + 0.03 * math.sin(2 * math.pi * 5 * t))
+        value = 0.0
+        harmonic = 1
+        while harmonic * pitch < 3400:
+            freq = harmonic * pitch
+            # Resonance around each formant, 1/f spectral tilt
+            gain = 1 / (1 + ((freq - f1) / 120) ** 2) + 0.6 / (1 + ((freq - f2) / 160) ** 2)
+            value += gain / harmonic * math.sin(2 * math.pi * freq * t)
+            harmonic += 1
+        out.append(value * env * amplitude)
+    return out
+
+
+def burst(rng, seconds, amplitude):
+    """A fricative-like noise burst."""
+    n = int(SAMPLE_RATE * seconds)
+    return [rng.gauss(0, 1) * amplitude * math.sin(math.pi * i / n) for i in range(n)]
+
+
+def sentence(rng, words):
+    """`words` words of one to three syllables, with short pauses."""
+    out = []
+    for w in range(words):
+        if w:
+            out += silence(rng.uniform(0.12, 0.3))
+        if rng.random() < 0.5:
+            out += burst(rng, rng.uniform(0.04, 0.08), 1500)
+        for s in range(rng.randint(1, 3)):
+            if s:
+                out += silence(rng.uniform(0.02, 0.06))
+            out += syllable(rng, rng.uniform(0.12, 0.24), 9000)
+    return out
+
+
+def speech(seed):
+    """Half a second of silence, two sentences two seconds apart, then two
+    seconds of trailing silence."""
+    rng = random.Random(seed)
+    return (
+        silence(0.5)
+        + sentence(rng, 5)
+        + silence(2.0)
+        + sentence(rng, 3)
+        + silence(2.0)
+    )
+
+
+def hum_and_hiss(rng, n):
+    """Mains hum (mostly below the speech band) plus broadband hiss."""
+    return [
+        120 * math.sin(2 * math.pi * 60 * i / SAMPLE_RATE) + rng.gauss(0, 45)
+        for i in range(n)
+    ]
+
+
+def music():
+    """Am - F - C - G, one chord a second, with an arpeggiated melody."""
+    chords = [(220.0, 261.63, 329.63), (174.61, 220.0, 261.63),
+              (261.63, 329.63, 392.0), (196.0, 246.94, 293.66)]
+    out = []
+    for chord in chords:
+        for i in range(SAMPLE_RATE):
+            t = i / SAMPLE_RATE
+            value = sum(math.sin(2 * math.pi * f * t) for f in chord) * 1500
+            note = chord[int(t * 4) % 3] * 2
+            value += math.sin(2 * math.pi * note * t) * 2500 * (1 - (t * 4) % 1)
+            out.append(value)
+    return out + silence(2.0)
+
+
+def clicks(rng):
+    """Six seconds of faint dither with 1ms clicks at 1.0s, 1.3s and 4.0s."""
+    out = [rng.gauss(0, 2) for _ in range(SAMPLE_RATE * 6)]
+    for at in (1.0, 1.3, 4.0):
+        start = int(SAMPLE_RATE * at)
+        for i in range(8):
+            out[start + i] += 12000 * (1 if i % 2 == 0 else -1) * (1 - i / 8)
+    return out
+
+
+def write(name, samples):
+    path = os.path.join(os.path.dirname(os.path.abspath(__file__)), name)
+    data = bytes(pcm_to_mulaw(int(max(-32768, min(32767, s)))) for s in samples)
+    with open(path, "wb") as f:
+        f.write(data)
+    print(f"{name}: {len(data) / SAMPLE_RATE:.2f}s")
+
+
+def generate():
+    clean = speech(7)
+    write("clean_speech.ulaw", clean)
+
+    noise = hum_and_hiss(random.Random(11), len(clean))
+    write("noisy_speech.ulaw", [s + n for s, n in zip(clean, noise)])
+
+    write("music.ulaw", music())
+    write("silence_clicks.ulaw", clicks(random.Random(13)))
+
+
+if __name__ == "__main__":
+    generate()
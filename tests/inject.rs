@@ -0,0 +1,254 @@
+//! End-to-end tests for `POST /api/inject`.
+//!
+//! A fake Twilio or Discord client opens a call, which registers it in the
+//! call registry; the test then injects text over HTTP and checks that the
+//! mock TTS audio arrives on the call's stream, framed for its transport and
+//! followed by a mark, and that the caller isn't heard until that mark is
+//! acknowledged.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use base64::Engine;
+use futures_util::{SinkExt, StreamExt};
+use serde_json::{json, Value};
+use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{MaybeTlsStream, WebSocketStream};
+
+use common::{
+    serve, summary, tone, twilio_mark, twilio_speak, twilio_start, FakeTwilio, API_TOKEN,
+    AWAIT_TIMEOUT, FRAME_BYTES,
+};
+use voice_echo::pipeline::audio;
+
+/// Long enough for a reply that shouldn't come to show up.
+const QUIET: Duration = Duration::from_millis(600);
+
+/// POST `/api/inject`, returning the status and JSON body.
+async fn inject(addr: SocketAddr, token: Option<&str>, call_sid: &str, text: &str) -> (u16, Value) {
+    let mut request = reqwest::Client::new()
+        .post(format!("http://{addr}/api/inject"))
+        .json(&json!({ "call_sid": call_sid, "text": text }));
+    if let Some(token) = token {
+        request = request.bearer_auth(token);
+    }
+    let response = request.send().await.unwrap();
+    let status = response.status().as_u16();
+    (status, response.json().await.unwrap())
+}
+
+#[tokio::test]
+async fn injects_into_a_twilio_call() {
+    let addr = serve("").await;
+    let mut twilio = FakeTwilio::connect(addr).await;
+    twilio.send_event(twilio_start()).await;
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+
+    // "Hello" is 400 bytes of mock audio, sent as Twilio media frames
+    let (status, body) = inject(addr, Some(API_TOKEN), "CAtest", "Hello").await;
+    assert_eq!((status, body), (200, json!({ "status": "injected" })));
+    twilio.wait_for("mark").await;
+
+    // Speaking until Twilio reports the injected audio played
+    twilio_speak(&mut twilio).await;
+    twilio.drain_for(QUIET).await;
+    assert_eq!(
+        summary(&twilio.received),
+        [
+            "audio 160",
+            "mark response_end",
+            "audio 400",
+            "mark inject_end"
+        ]
+    );
+
+    // Then the caller is heard again
+    twilio.send_event(twilio_mark("inject_end")).await;
+    twilio_speak(&mut twilio).await;
+    twilio.wait_for("mark").await;
+    assert_eq!(
+        summary(&twilio.received)[4..],
+        ["audio 400", "mark response_end"]
+    );
+}
+
+#[tokio::test]
+async fn rejects_unknown_calls_and_missing_tokens() {
+    let addr = serve("").await;
+    let mut twilio = FakeTwilio::connect(addr).await;
+    twilio.send_event(twilio_start()).await;
+    twilio.wait_for("mark").await;
+
+    let (status, _) = inject(addr, None, "CAtest", "Hello").await;
+    assert_eq!(status, 401);
+    let (status, _) = inject(addr, Some("wrong"), "CAtest", "Hello").await;
+    assert_eq!(status, 401);
+    let (status, body) = inject(addr, Some(API_TOKEN), "CAother", "Hello").await;
+    assert_eq!(status, 404);
+    assert_eq!(body["error"], "No active call with sid CAother");
+
+    // Nothing reached the call
+    twilio.drain_for(QUIET).await;
+    assert_eq!(
+        summary(&twilio.received),
+        ["audio 160", "mark response_end"]
+    );
+}
+
+/// A discord-voice sidecar: sends sidecar events and records the replies,
+/// with consecutive audio merged (`audio 880`).
+struct FakeDiscord {
+    ws: WebSocketStream<MaybeTlsStream<TcpStream>>,
+    batch_frames: usize,
+    received: Vec<String>,
+}
+
+impl FakeDiscord {
+    /// Connect and join channel 42, asking for `batch_frames` frames per
+    /// audio message.
+    async fn join(addr: SocketAddr, batch_frames: usize) -> Self {
+        let (ws, _) = tokio_tungstenite::connect_async(format!("ws://{addr}/discord-stream"))
+            .await
+            .unwrap();
+        let mut discord = Self {
+            ws,
+            batch_frames,
+            received: Vec::new(),
+        };
+        discord
+            .send(json!({
+                "type": "join",
+                "guild_id": "1",
+                "channel_id": "42",
+                "user_id": "7",
+                "batch_frames": batch_frames,
+            }))
+            .await;
+        discord
+    }
+
+    async fn send(&mut self, event: Value) {
+        self.ws
+            .send(Message::Text(event.to_string().into()))
+            .await
+            .unwrap();
+    }
+
+    /// An utterance, one frame per message: 600ms of tone, then enough
+    /// silence to end it.
+    async fn speak(&mut self) {
+        let mut mulaw = tone(600);
+        mulaw.resize(mulaw.len() + 300 * 8, audio::pcm_to_mulaw(0));
+        for frame in mulaw.chunks(FRAME_BYTES) {
+            let audio = base64::engine::general_purpose::STANDARD.encode(frame);
+            self.send(json!({ "type": "audio", "audio": audio })).await;
+        }
+    }
+
+    /// Read until the server sends a `mark`.
+    async fn wait_for_mark(&mut self) {
+        let seen = self.marks();
+        tokio::time::timeout(AWAIT_TIMEOUT, async {
+            while self.marks() == seen {
+                let message = self.ws.next().await.unwrap().unwrap();
+                self.record(message);
+            }
+        })
+        .await
+        .unwrap_or_else(|_| panic!("no mark within {AWAIT_TIMEOUT:?}"));
+    }
+
+    /// Keep reading for `duration`.
+    async fn drain_for(&mut self, duration: Duration) {
+        let _ = tokio::time::timeout(duration, async {
+            while let Some(Ok(message)) = self.ws.next().await {
+                self.record(message);
+            }
+        })
+        .await;
+    }
+
+    fn marks(&self) -> usize {
+        self.received.iter().filter(|r| *r == "mark").count()
+    }
+
+    fn record(&mut self, message: Message) {
+        let Message::Text(text) = message else {
+            return;
+        };
+        let json: Value = serde_json::from_str(text.as_str()).unwrap();
+        match json["type"].as_str().unwrap() {
+            "audio" => {
+                let bytes = base64::engine::general_purpose::STANDARD
+                    .decode(json["audio"].as_str().unwrap())
+                    .unwrap()
+                    .len();
+                // Batched as agreed, each frame at most a frame long
+                let lengths: Vec<usize> = match json["lengths"].as_array() {
+                    Some(lengths) => lengths
+                        .iter()
+                        .map(|l| l.as_u64().unwrap() as usize)
+                        .collect(),
+                    None => vec![bytes],
+                };
+                assert!(
+                    lengths.len() <= self.batch_frames,
+                    "oversized batch: {json}"
+                );
+                assert!(
+                    lengths.iter().all(|&l| l <= FRAME_BYTES),
+                    "oversized frame: {json}"
+                );
+                assert_eq!(lengths.iter().sum::<usize>(), bytes, "{json}");
+
+                let merged = match self.received.last() {
+                    Some(last) if last.starts_with("audio ") => {
+                        let so_far: usize = last[6..].parse().unwrap();
+                        self.received.pop();
+                        so_far + bytes
+                    }
+                    _ => bytes,
+                };
+                self.received.push(format!("audio {merged}"));
+            }
+            "batch" => self
+                .received
+                .push(format!("batch {}", json["frames"].as_u64().unwrap())),
+            "mark" => self.received.push("mark".to_string()),
+            other => panic!("unexpected message {other}: {json}"),
+        }
+    }
+}
+
+#[tokio::test]
+async fn injects_into_a_discord_call() {
+    let addr = serve("").await;
+    let mut discord = FakeDiscord::join(addr, 2).await;
+    discord.wait_for_mark().await;
+    discord.send(json!({ "type": "mark" })).await;
+
+    // 880 bytes: five full frames and a short one, two frames per message
+    let (status, body) = inject(addr, Some(API_TOKEN), "discord:42", "Hello there").await;
+    assert_eq!((status, body), (200, json!({ "status": "injected" })));
+    discord.wait_for_mark().await;
+
+    // Speaking until the sidecar reports the injected audio played
+    discord.speak().await;
+    discord.drain_for(QUIET).await;
+    assert_eq!(
+        discord.received,
+        ["batch 2", "audio 160", "mark", "audio 880", "mark"]
+    );
+
+    // Then the caller is heard again
+    discord.send(json!({ "type": "mark" })).await;
+    discord.speak().await;
+    discord.wait_for_mark().await;
+    assert_eq!(discord.received[5..], ["audio 400", "mark"]);
+}
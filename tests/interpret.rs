@@ -0,0 +1,49 @@
+//! End-to-end tests for `POST /api/interpret`.
+//!
+//! Mock Twilio takes `dial_ms` to place each leg, so the first party can
+//! answer while the second is still being called.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use common::{serve, twilio_start, FakeTwilio, API_TOKEN};
+
+#[tokio::test]
+async fn first_leg_answering_early_is_not_greeted() {
+    let addr = serve("twilio = true\ndial_ms = 1000\n").await;
+    let request = tokio::spawn(async move {
+        let response = reqwest::Client::new()
+            .post(format!("http://{addr}/api/interpret"))
+            .bearer_auth(API_TOKEN)
+            .json(&json!({
+                "legs": [
+                    { "to": "+34612345678", "language": "es" },
+                    { "to": "+14155550100", "language": "en" }
+                ]
+            }))
+            .send()
+            .await
+            .unwrap();
+        let status = response.status().as_u16();
+        (status, response.json::<Value>().await.unwrap())
+    });
+
+    // The first leg is placed at 1s and answered halfway through placing
+    // the second
+    tokio::time::sleep(Duration::from_millis(1500)).await;
+    let mut first = FakeTwilio::connect(addr).await;
+    let mut start = twilio_start();
+    start["start"]["callSid"] = json!("CAmock1");
+    first.send_event(start).await;
+
+    let (status, body) = request.await.unwrap();
+    assert_eq!(status, 200);
+    assert_eq!(body["call_sids"], json!(["CAmock1", "CAmock2"]));
+    first.drain_for(Duration::from_millis(600)).await;
+    assert!(first.received.is_empty(), "greeted as an Echo call");
+}
@@ -0,0 +1,121 @@
+//! `[outbound] quiet_hours`: `/api/call` defers to the scheduler, and
+//! `/api/scheduled` lists and cancels what it holds.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use chrono::{Duration, Timelike, Utc};
+use serde_json::{json, Value};
+
+use common::{serve, API_TOKEN};
+
+const TENANT_TOKEN: &str = "acme-token";
+
+/// Quiet from an hour ago to an hour from now, UTC, with a tenant.
+fn quiet_now() -> String {
+    let now = Utc::now();
+    let hhmm = |t: chrono::DateTime<Utc>| format!("{:02}:{:02}", t.hour(), t.minute());
+    format!(
+        "\n[outbound.quiet_hours]\nstart = {:?}\nend = {:?}\ntimezone = \"UTC\"\n\n\
+         [[tenants]]\nname = \"acme\"\napi_token = {TENANT_TOKEN:?}\n",
+        hhmm(now - Duration::hours(1)),
+        hhmm(now + Duration::hours(1)),
+    )
+}
+
+async fn call(addr: SocketAddr, token: &str, body: Value) -> (u16, Value) {
+    let response = reqwest::Client::new()
+        .post(format!("http://{addr}/api/call"))
+        .bearer_auth(token)
+        .json(&body)
+        .send()
+        .await
+        .unwrap();
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+async fn scheduled(addr: SocketAddr, token: &str) -> Value {
+    reqwest::Client::new()
+        .get(format!("http://{addr}/api/scheduled"))
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn defers_calls_until_the_window_ends() {
+    let addr = serve(&quiet_now()).await;
+
+    let (status, body) = call(
+        addr,
+        API_TOKEN,
+        json!({ "to": "+15551234567", "context": "Disk full", "language": "es-MX" }),
+    )
+    .await;
+    assert_eq!(status, 202, "{body}");
+    assert_eq!(body["status"], "deferred");
+    let at: chrono::DateTime<Utc> = body["scheduled_for"].as_str().unwrap().parse().unwrap();
+    assert!(
+        at > Utc::now() && at <= Utc::now() + Duration::hours(1),
+        "{at}"
+    );
+
+    let (status, _) = call(addr, TENANT_TOKEN, json!({ "to": "+15557654321" })).await;
+    assert_eq!(status, 202);
+
+    let all = scheduled(addr, API_TOKEN).await;
+    assert_eq!(all["calls"].as_array().unwrap().len(), 2);
+    let ours = &all["calls"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .find(|c| c["id"] == body["id"])
+        .unwrap();
+    assert_eq!(ours["request"]["context"], "Disk full");
+    assert_eq!(ours["request"]["language"], "es");
+
+    // Tenants only see, and cancel, their own
+    let theirs = scheduled(addr, TENANT_TOKEN).await;
+    assert_eq!(theirs["calls"].as_array().unwrap().len(), 1);
+    assert_eq!(theirs["calls"][0]["tenant"], "acme");
+    let cancel = |token: &'static str| {
+        reqwest::Client::new()
+            .delete(format!(
+                "http://{addr}/api/scheduled/{}",
+                body["id"].as_str().unwrap()
+            ))
+            .bearer_auth(token)
+            .send()
+    };
+    assert_eq!(cancel(TENANT_TOKEN).await.unwrap().status(), 404);
+    assert_eq!(cancel(API_TOKEN).await.unwrap().status(), 200);
+    assert_eq!(cancel(API_TOKEN).await.unwrap().status(), 404);
+    assert_eq!(
+        scheduled(addr, API_TOKEN).await["calls"]
+            .as_array()
+            .unwrap()
+            .len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn bad_requests_are_refused_not_deferred() {
+    let addr = serve(&quiet_now()).await;
+    let (status, body) = call(
+        addr,
+        API_TOKEN,
+        json!({ "to": "+15551234567", "agent": "nobody", "urgent": false }),
+    )
+    .await;
+    assert_eq!(status, 400);
+    assert_eq!(body["error"], "Unknown agent 'nobody'");
+    assert_eq!(scheduled(addr, API_TOKEN).await["calls"], json!([]));
+}
@@ -0,0 +1,194 @@
+//! `/api/reminders`: recurring calls placed through the mock Twilio, with
+//! retries and completion webhooks.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::net::SocketAddr;
+
+use axum::routing::post;
+use axum::{Json, Router};
+use serde_json::{json, Value};
+use tokio::sync::mpsc;
+
+use common::{serve, twilio_mark, twilio_start, FakeTwilio, API_TOKEN, AWAIT_TIMEOUT};
+
+/// A server placing calls nowhere, with quick retries.
+async fn serve_reminders() -> SocketAddr {
+    serve(
+        "twilio = true\n\n[reminders]\nretries = 1\nretry_delay_secs = 0\nanswer_timeout_secs = 1\n",
+    )
+    .await
+}
+
+/// A webhook receiver. Returns its URL and the bodies it's sent.
+async fn webhook() -> (String, mpsc::UnboundedReceiver<Value>) {
+    let (tx, rx) = mpsc::unbounded_channel();
+    let app = Router::new().route(
+        "/done",
+        post(move |Json(body): Json<Value>| async move {
+            let _ = tx.send(body);
+        }),
+    );
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+    (format!("http://{addr}/done"), rx)
+}
+
+async fn request(
+    addr: SocketAddr,
+    method: reqwest::Method,
+    path: &str,
+    body: Option<Value>,
+) -> (u16, Value) {
+    let mut request = reqwest::Client::new()
+        .request(method, format!("http://{addr}{path}"))
+        .bearer_auth(API_TOKEN);
+    if let Some(body) = body {
+        request = request.json(&body);
+    }
+    let response = request.send().await.unwrap();
+    (response.status().as_u16(), response.json().await.unwrap())
+}
+
+async fn create(addr: SocketAddr, body: Value) -> (u16, Value) {
+    request(addr, reqwest::Method::POST, "/api/reminders", Some(body)).await
+}
+
+/// The next run outcome POSTed to a reminder's webhook.
+async fn next_webhook(rx: &mut mpsc::UnboundedReceiver<Value>) -> Value {
+    let delivery = tokio::time::timeout(AWAIT_TIMEOUT, rx.recv())
+        .await
+        .expect("no webhook")
+        .unwrap();
+    assert_eq!(delivery["event"], "reminder.finished");
+    delivery["data"].clone()
+}
+
+/// Answer mock call `call_sid` by starting its media stream.
+async fn answer(addr: SocketAddr, call_sid: &str) -> FakeTwilio {
+    let mut start = twilio_start();
+    start["start"]["callSid"] = json!(call_sid);
+    let mut twilio = FakeTwilio::connect(addr).await;
+    twilio.send_event(start).await;
+    twilio
+}
+
+#[tokio::test]
+async fn runs_a_reminder_and_reports_the_outcome() {
+    let addr = serve_reminders().await;
+    let (url, mut done) = webhook().await;
+
+    let (status, reminder) = create(
+        addr,
+        json!({
+            "schedule": "0 9 * * *",
+            "timezone": "Europe/Madrid",
+            "to": "+34600000001",
+            "context": "Time for the {weekday} pills",
+            "webhook_url": url,
+        }),
+    )
+    .await;
+    assert_eq!(status, 201, "{reminder}");
+    assert!(reminder["next_at"].is_string(), "{reminder}");
+    let id = reminder["id"].as_str().unwrap();
+
+    let (_, listed) = request(addr, reqwest::Method::GET, "/api/reminders", None).await;
+    assert_eq!(listed["reminders"][0]["id"], id);
+    assert_eq!(listed["reminders"][0]["schedule"], "0 9 * * *");
+
+    let (status, _) = request(
+        addr,
+        reqwest::Method::POST,
+        &format!("/api/reminders/{id}/run"),
+        None,
+    )
+    .await;
+    assert_eq!(status, 202);
+
+    let mut twilio = answer(addr, "CAmock1").await;
+    twilio.wait_for("mark").await;
+    twilio.send_event(twilio_mark("response_end")).await;
+    twilio
+        .send_event(json!({
+            "event": "stop",
+            "streamSid": "MZtest",
+            "stop": { "accountSid": "ACtest", "callSid": "CAmock1" }
+        }))
+        .await;
+
+    let outcome = next_webhook(&mut done).await;
+    assert_eq!(outcome["reminder_id"], id);
+    assert_eq!(outcome["status"], "completed");
+    assert_eq!(outcome["attempts"], 1);
+    assert_eq!(outcome["call_sid"], "CAmock1");
+    assert_eq!(outcome["end_reason"], "hangup");
+
+    let (_, reminder) = request(
+        addr,
+        reqwest::Method::GET,
+        &format!("/api/reminders/{id}"),
+        None,
+    )
+    .await;
+    assert_eq!(reminder["last_run"]["status"], "completed");
+
+    let path = format!("/api/reminders/{id}");
+    let (status, _) = request(addr, reqwest::Method::DELETE, &path, None).await;
+    assert_eq!(status, 200);
+    let (status, _) = request(addr, reqwest::Method::DELETE, &path, None).await;
+    assert_eq!(status, 404);
+}
+
+#[tokio::test]
+async fn retries_unanswered_calls() {
+    let addr = serve_reminders().await;
+    let (url, mut done) = webhook().await;
+
+    let (_, reminder) = create(
+        addr,
+        json!({ "schedule": "@daily", "to": "+34600000001", "webhook_url": url }),
+    )
+    .await;
+    let id = reminder["id"].as_str().unwrap();
+    request(
+        addr,
+        reqwest::Method::POST,
+        &format!("/api/reminders/{id}/run"),
+        None,
+    )
+    .await;
+
+    let outcome = next_webhook(&mut done).await;
+    assert_eq!(outcome["status"], "unanswered");
+    assert_eq!(outcome["attempts"], 2);
+    assert_eq!(outcome["call_sid"], "CAmock2");
+}
+
+#[tokio::test]
+async fn refuses_bad_reminders() {
+    let addr = serve_reminders().await;
+    for (body, error) in [
+        (
+            json!({ "schedule": "0 9 * *", "to": "+1" }),
+            "Invalid schedule: expected 5 fields (minute hour day month weekday), got 4",
+        ),
+        (
+            json!({ "schedule": "0 0 30 2 *", "to": "+1" }),
+            "Schedule '0 0 30 2 *' never fires",
+        ),
+        (
+            json!({ "schedule": "@daily", "to": "+1", "agent": "nobody" }),
+            "Unknown agent 'nobody'",
+        ),
+    ] {
+        let (status, response) = create(addr, body).await;
+        assert_eq!(status, 400);
+        assert_eq!(response["error"], error);
+    }
+    let (_, listed) = request(addr, reqwest::Method::GET, "/api/reminders", None).await;
+    assert_eq!(listed["reminders"], json!([]));
+}
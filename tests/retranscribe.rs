@@ -0,0 +1,61 @@
+//! Tests for `voice-echo --retranscribe` with mock STT.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use base64::Engine;
+use serde_json::{json, Value};
+
+use common::{tone, CONFIG, FRAME_BYTES};
+use voice_echo::config::Config;
+use voice_echo::pipeline::audio;
+
+/// A Twilio capture of two utterances: 600ms of tone at 0 and at 900ms,
+/// each followed by 300ms of silence.
+fn capture() -> String {
+    let silence = vec![audio::pcm_to_mulaw(0); 300 * 8];
+    let mut mulaw = Vec::new();
+    for _ in 0..2 {
+        mulaw.extend(tone(600));
+        mulaw.extend(&silence);
+    }
+    let mut lines = vec![json!({ "transport": "twilio" })];
+    lines.push(json!({
+        "at_ms": 0,
+        "message": { "event": "start", "start": { "callSid": "CAtest" } },
+    }));
+    for (i, frame) in mulaw.chunks(FRAME_BYTES).enumerate() {
+        let payload = base64::engine::general_purpose::STANDARD.encode(frame);
+        lines.push(json!({
+            "at_ms": i * 20,
+            "message": { "event": "media", "media": { "payload": payload } },
+        }));
+    }
+    lines.iter().map(|line| format!("{line}\n")).collect()
+}
+
+#[tokio::test]
+async fn writes_the_transcript_next_to_the_capture() {
+    let config: Config =
+        toml::from_str(&format!("{CONFIG}transcripts = [\"Hello\", \"Goodbye\"]\n")).unwrap();
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let path = dir.join("CAtest.jsonl");
+    std::fs::write(&path, capture()).unwrap();
+
+    let (out, transcript) = voice_echo::retranscribe::run(&config, &path, Some("whisper-large-v3"))
+        .await
+        .unwrap();
+    assert_eq!(out, dir.join("CAtest.transcript.json"));
+    let texts: Vec<_> = transcript.utterances.iter().map(|u| &u.text).collect();
+    assert_eq!(texts, ["Hello", "Goodbye"]);
+    // The second starts at 900ms, less the pre-roll
+    let second = transcript.utterances[1].at_ms;
+    assert!((500..=900).contains(&second), "{second}");
+
+    let written: Value = serde_json::from_slice(&std::fs::read(&out).unwrap()).unwrap();
+    assert_eq!(written["vendor"], "groq");
+    assert_eq!(written["model"], "whisper-large-v3");
+    assert_eq!(written["utterances"][0]["text"], "Hello");
+}
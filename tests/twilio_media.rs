@@ -0,0 +1,188 @@
+//! Fake-Twilio tests for `/twilio/media`.
+//!
+//! Each test starts the server on a free port with mock providers, connects
+//! to the media stream as Twilio would, replays a fixture from
+//! `tests/fixtures/twilio/` and asserts on the media, mark and clear events
+//! that come back.
+//!
+//! Fixtures are one JSON message per line, sent as-is, except:
+//!
+//! - a `media` event with `"media": {"tone_ms": N}` or `{"silence_ms": N}`
+//!   is sent as 20ms frames of a 440Hz tone or of silence;
+//! - `{"await": "mark"}` (or `"clear"`) waits for the server to send that
+//!   event;
+//! - `{"sleep_ms": N}` pauses, e.g. for VAD's silence gap to elapse.
+//!
+//! Blank lines and lines starting with `#` are skipped.
+
+#![cfg(feature = "mocks")]
+
+mod common;
+
+use std::time::Duration;
+
+use common::{serve, summary, FakeTwilio, Received};
+use voice_echo::pipeline::audio;
+
+#[tokio::test]
+async fn greets_then_answers_an_utterance() {
+    let addr = serve("").await;
+    let received = FakeTwilio::connect(addr)
+        .await
+        .replay("greeting_and_reply")
+        .await;
+    assert_eq!(
+        summary(&received),
+        [
+            "audio 160",
+            "mark response_end",
+            "audio 400",
+            "mark response_end"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn speech_over_the_greeting_is_ignored() {
+    let addr = serve("").await;
+    let received = FakeTwilio::connect(addr)
+        .await
+        .replay("speech_during_greeting")
+        .await;
+    assert_eq!(summary(&received), ["audio 160", "mark response_end"]);
+}
+
+#[tokio::test]
+async fn silence_gets_no_reply() {
+    let addr = serve("").await;
+    let received = FakeTwilio::connect(addr).await.replay("silence").await;
+    assert_eq!(summary(&received), ["audio 160", "mark response_end"]);
+}
+
+#[tokio::test]
+async fn hold_music_is_cleared_before_the_reply() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav = dir.path().join("hold.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&wav, spec).unwrap();
+    for _ in 0..8000 {
+        writer.write_sample(1000i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    let addr = serve(&format!(
+        "latency_ms = 200\n\n[hold_music]\nfile = {:?}\n",
+        wav.display().to_string()
+    ))
+    .await;
+    let received = FakeTwilio::connect(addr)
+        .await
+        .replay("greeting_and_reply")
+        .await;
+
+    // Greeting, hold music while the turn runs, then clear and the reply
+    let summary = summary(&received);
+    assert_eq!(summary.len(), 6, "{summary:?}");
+    assert_eq!(summary[..2], ["audio 160", "mark response_end"]);
+    assert!(summary[2].starts_with("audio "), "{summary:?}");
+    assert_eq!(summary[3..], ["clear", "audio 400", "mark response_end"]);
+}
+
+#[tokio::test]
+async fn announcements_are_spoken_over_ducked_hold_music() {
+    let dir = tempfile::tempdir().unwrap();
+    let wav = dir.path().join("hold.wav");
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 8000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(&wav, spec).unwrap();
+    for _ in 0..8000 {
+        writer.write_sample(1000i16).unwrap();
+    }
+    writer.finalize().unwrap();
+
+    // A 3s turn; "Hold on." (640 bytes) is ready 2s in
+    let addr = serve(&format!(
+        "latency_ms = 1000\n\n[hold_music]\nfile = {:?}\nvolume = 1.0\n\n\
+         [hold_announcements]\nevery_secs = 1\nmessages = [\"Hold on.\"]\nduck = 0.25\n",
+        wav.display().to_string()
+    ))
+    .await;
+    let received = FakeTwilio::connect(addr)
+        .await
+        .replay("greeting_and_reply")
+        .await;
+
+    let summary = summary(&received);
+    assert_eq!(summary[3..], ["clear", "audio 400", "mark response_end"]);
+    let Received::Audio(ref hold) = received[2] else {
+        panic!("{summary:?}");
+    };
+    // Music frames are all the same byte; the announcement's aren't, and
+    // the music under it is turned down to a quarter
+    let music = hold[0];
+    let spoken: Vec<i32> = hold
+        .iter()
+        .filter(|&&b| b != music)
+        .map(|&b| audio::mulaw_to_pcm(b) as i32)
+        .collect();
+    assert!((600..=640).contains(&spoken.len()), "{}", spoken.len());
+    let under = spoken.iter().sum::<i32>() / spoken.len() as i32;
+    assert!((150..=350).contains(&under), "{under}");
+}
+
+#[tokio::test]
+async fn captured_call_replays_the_same_way() {
+    let tmp = tempfile::tempdir().unwrap();
+    let dir = tmp.path();
+    let addr = serve(&format!(
+        "\n[capture]\ndir = {:?}\n",
+        dir.display().to_string()
+    ))
+    .await;
+    FakeTwilio::connect(addr)
+        .await
+        .replay("greeting_and_reply")
+        .await;
+
+    // Complete once the server has wound the call down
+    let path = dir.join("CAtest.jsonl");
+    let mut captured = String::new();
+    for _ in 0..50 {
+        captured = std::fs::read_to_string(&path).unwrap_or_default();
+        if captured.contains(r#""event":"stop""#) {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    let header: serde_json::Value = serde_json::from_str(captured.lines().next().unwrap()).unwrap();
+    assert_eq!(header["transport"], "twilio");
+    assert!(!captured.contains("ACtest"), "account SID not redacted");
+
+    let replies = voice_echo::capture::replay(&format!("ws://{}", serve("").await), &path)
+        .await
+        .unwrap();
+
+    // Same replies as the original call, whatever the timing
+    let events: Vec<_> = voice_echo::capture::summarize(&replies)
+        .iter()
+        .map(|line| line.split_once("s  ").unwrap().1.to_string())
+        .collect();
+    assert_eq!(
+        events,
+        [
+            "audio 20ms",
+            "mark response_end",
+            "audio 50ms",
+            "mark response_end"
+        ]
+    );
+}
@@ -0,0 +1,104 @@
+//! Golden-audio regression tests for VAD.
+//!
+//! Feeds the recordings in `tests/fixtures/vad/` through the detector in
+//! 20ms frames, as a Twilio stream would, and pins where each utterance
+//! ends and how long it is, for the default and the adaptive
+//! configuration. A VAD tuning change that moves an end-of-utterance shows
+//! up here; update the expectations only when the move is intended.
+//!
+//! The fixtures are synthetic and reproducible: see
+//! `tests/fixtures/vad/generate_fixtures.py` for what each one holds.
+
+use voice_echo::pipeline::vad::VoiceActivityDetector;
+
+const FRAME_BYTES: usize = 160;
+/// How far (ms) a boundary may drift before it counts as a regression:
+/// two frames.
+const TOLERANCE_MS: u64 = 40;
+
+/// Defaults from `[vad]`.
+fn default_vad() -> VoiceActivityDetector {
+    VoiceActivityDetector::new(50, 1500)
+}
+
+/// `adaptive_threshold = true` with the default multiplier and decay.
+fn adaptive_vad() -> VoiceActivityDetector {
+    default_vad().with_adaptive(3.0, 0.995)
+}
+
+/// An utterance as it left the detector, in ms of fixture audio.
+#[derive(Debug)]
+struct Utterance {
+    /// When it was emitted
+    at_ms: u64,
+    /// How much audio it carried, pre-roll included
+    len_ms: u64,
+}
+
+fn run(mut vad: VoiceActivityDetector, fixture: &str) -> Vec<Utterance> {
+    let path = format!(
+        "{}/tests/fixtures/vad/{fixture}.ulaw",
+        env!("CARGO_MANIFEST_DIR")
+    );
+    let audio = std::fs::read(&path).unwrap();
+    let mut fed = 0;
+    let mut utterances = Vec::new();
+    for frame in audio.chunks(FRAME_BYTES) {
+        fed += frame.len();
+        if let Some(pcm) = vad.feed(frame) {
+            utterances.push(Utterance {
+                at_ms: fed as u64 / 8,
+                len_ms: pcm.len() as u64 / 8,
+            });
+        }
+    }
+    utterances
+}
+
+/// Assert utterances match `expected` `(at_ms, len_ms)` pairs, within
+/// `TOLERANCE_MS`.
+fn assert_utterances(actual: &[Utterance], expected: &[(u64, u64)]) {
+    assert_eq!(actual.len(), expected.len(), "{actual:?}");
+    for (utterance, &(at_ms, len_ms)) in actual.iter().zip(expected) {
+        assert!(
+            utterance.at_ms.abs_diff(at_ms) <= TOLERANCE_MS
+                && utterance.len_ms.abs_diff(len_ms) <= TOLERANCE_MS,
+            "expected ({at_ms}, {len_ms}), got {actual:?}"
+        );
+    }
+}
+
+#[test]
+fn clean_speech_splits_at_sentences() {
+    // Sentences end at 3.2s and 6.7s; each is sent 1.5s later, starting
+    // 300ms before the speech (pre-roll)
+    let expected = [(4700, 4500), (8200, 3300)];
+    assert_utterances(&run(default_vad(), "clean_speech"), &expected);
+    assert_utterances(&run(adaptive_vad(), "clean_speech"), &expected);
+}
+
+#[test]
+fn noise_below_threshold_does_not_hold_utterances_open() {
+    // Same sentences over hum and hiss: the same boundaries
+    let expected = [(4700, 4500), (8200, 3300)];
+    assert_utterances(&run(default_vad(), "noisy_speech"), &expected);
+    assert_utterances(&run(adaptive_vad(), "noisy_speech"), &expected);
+}
+
+#[test]
+fn music_is_one_utterance_until_it_stops() {
+    // Energy alone can't tell music from speech
+    let expected = [(5520, 5520)];
+    assert_utterances(&run(default_vad(), "music"), &expected);
+    assert_utterances(&run(adaptive_vad(), "music"), &expected);
+}
+
+#[test]
+fn clicks_open_short_utterances() {
+    // A click is loud enough to count as speech; the clicks at 1.0s and
+    // 1.3s share one utterance, the one at 4.0s gets its own. Their near
+    // silent audio is left to the transcript filters.
+    let expected = [(2820, 2120), (5520, 1820)];
+    assert_utterances(&run(default_vad(), "silence_clicks"), &expected);
+    assert_utterances(&run(adaptive_vad(), "silence_clicks"), &expected);
+}